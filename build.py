@@ -15,6 +15,15 @@ def parse_args():
     parser.add_argument("--install-prefix")
     parser.add_argument("--no-reducer", action="store_true")
     parser.add_argument("--no-harness", action="store_true")
+    parser.add_argument(
+        "--dawn-backends",
+        help="comma separated list of dawn backends to build (vulkan, d3d12, metal, null)",
+    )
+    parser.add_argument(
+        "--no-x11",
+        action="store_true",
+        help="build dawn without X11 support, for machines without a display server",
+    )
     return parser.parse_args()
 
 
@@ -33,6 +42,14 @@ host_target = get_cargo_host_target()
 build_target = args.target if args.target is not None else host_target
 is_cross = args.target is not None and host_target != args.target
 
+dawn_backends = ["vulkan", "d3d12", "metal", "null"]
+if args.dawn_backends is not None:
+    dawn_backends = [backend.strip() for backend in args.dawn_backends.split(",")]
+    for backend in dawn_backends:
+        if backend not in {"vulkan", "d3d12", "metal", "null"}:
+            print(f"invalid dawn backend: {backend}")
+            exit(1)
+
 
 def get_commit(git_dir):
     output = subprocess.check_output(["git", "--git-dir", git_dir, "rev-parse", "HEAD"])
@@ -78,10 +95,12 @@ def cmake_build(build_dir: Path, targets=[]):
     subprocess.run(cmd, cwd=build_dir).check_returncode()
 
 
-def cargo_build(package, target=None, cwd=None, features=[]):
+def cargo_build(package, target=None, cwd=None, features=[], default_features=True):
     cmd = ["./cargo", "build", "-p", package, "--release"]
     if target:
         cmd += ["--target", target]
+    if not default_features:
+        cmd += ["--no-default-features"]
     if len(features) > 0:
         cmd += ["--features", ",".join(features)]
     print(f">> {' '.join(cmd)}")
@@ -108,6 +127,26 @@ dawn_src_dir = Path("external/dawn")
 dawn_build_dir = Path(f"build/dawn/{build_target}")
 
 
+def dawn_cmake_args():
+    flags = {
+        "vulkan": "DAWN_ENABLE_VULKAN",
+        "d3d12": "DAWN_ENABLE_D3D12",
+        "metal": "DAWN_ENABLE_METAL",
+        "null": "DAWN_ENABLE_NULL",
+    }
+
+    cmake_args = []
+    for backend, flag in flags.items():
+        # Only disable backends explicitly, so that dawn can decide which are supported by default
+        if backend not in dawn_backends:
+            cmake_args.append(f"-D{flag}=OFF")
+
+    if args.no_x11:
+        cmake_args.append("-DDAWN_USE_X11=OFF")
+
+    return cmake_args
+
+
 def dawn_gen_cmake():
     if is_cross and build_target != "x86_64-pc-windows-msvc":
         print(f"cannot build dawn for target '{build_target}' (host={host_target})")
@@ -119,6 +158,7 @@ def dawn_gen_cmake():
                 f"-DLLVM_NATIVE_TOOLCHAIN={os.environ['LLVM_NATIVE_TOOLCHAIN']}",
                 f"-DXWIN_CACHE={os.environ['XWIN_CACHE']}",
                 f"-DCMAKE_TOOLCHAIN_FILE={Path('cmake/WinMsvc.cmake').absolute()}",
+                *dawn_cmake_args(),
             ]
 
             env = {"CXXFLAGS": "-Wno-float-equal"}
@@ -130,12 +170,12 @@ def dawn_gen_cmake():
                 env,
             )
         else:
-            gen_cmake_build(dawn_src_dir, dawn_build_dir)
+            gen_cmake_build(dawn_src_dir, dawn_build_dir, dawn_cmake_args())
 
 
 def build_tint():
     print(f"> building tint (target={build_target})")
-    cmake_build(dawn_build_dir, ["tint"])
+    cmake_build(dawn_build_dir, ["tint", "SPIRV-Tools-static"])
 
 
 def build_wgslsmith():
@@ -155,13 +195,28 @@ def build_dawn():
 
 def build_harness():
     print(f"> building harness (target={build_target})")
-    cargo_build("harness", target=args.target)
+    features = [f"dawn-{backend}" for backend in dawn_backends]
+    if not args.no_x11:
+        features.append("dawn-x11")
+    # SPIRV-Tools is only built along with dawn's vulkan backend
+    if "vulkan" in dawn_backends:
+        features.append("spirv-val")
+    cargo_build("harness", target=args.target, features=features, default_features=False)
 
 
 if args.task not in {"wgslsmith", "harness", "install"}:
     print(f"invalid task: {args.task}")
     exit(1)
 
+if args.dawn_backends is not None and args.task != "harness":
+    # wgslsmith always links all of the dawn backends
+    print("--dawn-backends can only be used with the harness task")
+    exit(1)
+
+if args.no_x11 and args.task != "harness":
+    print("--no-x11 can only be used with the harness task")
+    exit(1)
+
 print(f"> task: {args.task}")
 
 if args.task == "install":
@@ -62,12 +62,50 @@ pub enum BuiltinFn {
     Smoothstep,
     Sqrt,
     Step,
+    SubgroupAdd,
+    SubgroupAnd,
+    SubgroupBallot,
+    SubgroupBroadcast,
+    SubgroupBroadcastFirst,
+    SubgroupElect,
+    SubgroupMax,
+    SubgroupMin,
+    SubgroupOr,
+    SubgroupXor,
     Tan,
     Tanh,
     Trunc,
+    WorkgroupBarrier,
+    WorkgroupUniformLoad,
 }
 
 impl BuiltinFn {
+    /// Returns true if the builtin doesn't return a value, so it can only be called as a
+    /// statement.
+    pub fn is_void(&self) -> bool {
+        matches!(self, BuiltinFn::WorkgroupBarrier)
+    }
+
+    /// Returns the extension that must be enabled with an `enable` directive to call the builtin,
+    /// if it isn't part of core WGSL.
+    pub fn extension(&self) -> Option<&'static str> {
+        use BuiltinFn::*;
+
+        match self {
+            SubgroupAdd
+            | SubgroupAnd
+            | SubgroupBallot
+            | SubgroupBroadcast
+            | SubgroupBroadcastFirst
+            | SubgroupElect
+            | SubgroupMax
+            | SubgroupMin
+            | SubgroupOr
+            | SubgroupXor => Some("subgroups"),
+            _ => None,
+        }
+    }
+
     /// Determines the return type for a builtin function, given argument types.
     ///
     /// Note that this only does the bare minimum work for overload resolution and does not do any
@@ -140,9 +178,25 @@ impl BuiltinFn {
             Smoothstep => first_param()?,
             Sqrt => first_param()?,
             Step => first_param()?,
+            SubgroupAdd => first_param()?,
+            SubgroupAnd => first_param()?,
+            SubgroupBallot => DataType::Vector(4, U32),
+            SubgroupBroadcast => first_param()?,
+            SubgroupBroadcastFirst => first_param()?,
+            SubgroupElect => Bool.into(),
+            SubgroupMax => first_param()?,
+            SubgroupMin => first_param()?,
+            SubgroupOr => first_param()?,
+            SubgroupXor => first_param()?,
             Tan => first_param()?,
             Tanh => first_param()?,
             Trunc => first_param()?,
+            WorkgroupBarrier => return None,
+            // The argument is a pointer, which `dereference` doesn't look through
+            WorkgroupUniformLoad => match params.next()? {
+                DataType::Ptr(view) => view.inner.as_ref().clone(),
+                _ => return None,
+            },
         };
 
         Some(ret)
@@ -0,0 +1,67 @@
+//! Canonicalization and structural hashing of modules.
+//!
+//! Two programs which only differ in the names of their identifiers or in the order of the
+//! operands to commutative operators have the same canonical form, and hence the same structural
+//! hash. This is used by the fuzzer to skip programs (and findings) that it has already seen.
+//!
+//! The canonical form is only intended for comparing programs. Swapping operands changes the order
+//! in which they are evaluated, so it is not equivalent to the original program if both operands
+//! have side effects.
+
+use crate::hash::Fnv1a;
+use crate::visit::{self, VisitorMut};
+use crate::writer::Writer;
+use crate::{rename, BinOp, Expr, ExprNode, Module};
+
+/// Converts the module into its canonical form.
+pub fn canonicalize(module: &mut Module) {
+    rename::rename_identifiers(module);
+    Canonicalizer.visit_module_mut(module);
+}
+
+/// Computes a 64-bit hash of the structure of the module.
+///
+/// The module should be canonicalized first if programs that only differ in naming or operand
+/// order are to be considered equal. The hash is stable across runs and platforms.
+pub fn structural_hash(module: &Module) -> u64 {
+    let mut hasher = Fnv1a::new();
+    Writer::default().write_module(&mut hasher, module).unwrap();
+    hasher.finish()
+}
+
+/// Canonicalizes a copy of the module and returns its structural hash.
+pub fn canonical_hash(mut module: Module) -> u64 {
+    canonicalize(&mut module);
+    structural_hash(&module)
+}
+
+struct Canonicalizer;
+
+impl VisitorMut for Canonicalizer {
+    fn visit_expr_mut(&mut self, node: &mut ExprNode) {
+        // Operands are canonicalized first so that nested expressions are compared in their
+        // canonical form
+        visit::walk_expr_mut(self, node);
+
+        if let Expr::BinOp(expr) = &mut node.expr {
+            if is_commutative(expr.op) && expr.left.to_string() > expr.right.to_string() {
+                std::mem::swap(&mut expr.left, &mut expr.right);
+            }
+        }
+    }
+}
+
+fn is_commutative(op: BinOp) -> bool {
+    // Logical operators are excluded since swapping the operands would change which one is
+    // short circuited
+    matches!(
+        op,
+        BinOp::Plus
+            | BinOp::Times
+            | BinOp::BitAnd
+            | BinOp::BitOr
+            | BinOp::BitXOr
+            | BinOp::Equal
+            | BinOp::NotEqual
+    )
+}
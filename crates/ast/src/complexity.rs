@@ -0,0 +1,261 @@
+//! Complexity scores of modules, so that the fuzzer can skip programs that are too simple to be
+//! worth executing.
+//!
+//! The score combines a weighted count of the nodes in the module, where control flow and calls
+//! count for more than literals and variable references, with the maximum nesting depth of blocks
+//! and the length of the longest chain of local variables computed from each other.
+
+use std::collections::HashMap;
+
+use crate::visit::{self, Visitor};
+use crate::{
+    AssignmentLhs, AssignmentOp, Expr, ExprNode, FnDecl, LhsExpr, LhsExprNode, Module, Statement,
+};
+
+const BLOCK_DEPTH_WEIGHT: f64 = 5.0;
+const DATAFLOW_WEIGHT: f64 = 3.0;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Complexity {
+    /// Sum of the weights of the statements and expressions in the module.
+    pub weighted_nodes: f64,
+    /// Maximum nesting depth of blocks, where function bodies have a depth of 1.
+    pub max_block_depth: usize,
+    /// Length of the longest chain of local variables in a function, where each one is assigned a
+    /// value computed from the previous one.
+    pub max_dataflow: usize,
+}
+
+impl Complexity {
+    pub fn collect(module: &Module) -> Complexity {
+        let mut collector = Collector::default();
+        collector.visit_module(module);
+        collector.complexity
+    }
+
+    pub fn score(&self) -> f64 {
+        self.weighted_nodes
+            + BLOCK_DEPTH_WEIGHT * self.max_block_depth as f64
+            + DATAFLOW_WEIGHT * self.max_dataflow as f64
+    }
+}
+
+#[derive(Default)]
+struct Collector {
+    complexity: Complexity,
+    block_depth: usize,
+    /// Length of the longest chain leading to each local variable in the current function.
+    ///
+    /// Shadowed variables share an entry, so this is only an approximation.
+    dataflow: HashMap<String, usize>,
+}
+
+impl Collector {
+    /// Records that `name` is assigned a value computed from `expr`.
+    fn flow(&mut self, name: &str, expr: &ExprNode, compound: bool) {
+        let mut vars = VarRefs::default();
+        vars.visit_expr(expr);
+
+        let mut length = 1 + vars
+            .0
+            .iter()
+            .filter_map(|var| self.dataflow.get(var))
+            .max()
+            .copied()
+            .unwrap_or(0);
+
+        // A compound assignment also reads the variable being assigned
+        if compound {
+            length = length.max(1 + self.dataflow.get(name).copied().unwrap_or(0));
+        }
+
+        self.complexity.max_dataflow = self.complexity.max_dataflow.max(length);
+        self.dataflow.insert(name.to_owned(), length);
+    }
+}
+
+impl Visitor for Collector {
+    fn visit_fn_decl(&mut self, decl: &FnDecl) {
+        self.dataflow.clear();
+        visit::walk_fn_decl(self, decl);
+    }
+
+    fn visit_block(&mut self, block: &[Statement]) {
+        self.block_depth += 1;
+        self.complexity.max_block_depth = self.complexity.max_block_depth.max(self.block_depth);
+        visit::walk_block(self, block);
+        self.block_depth -= 1;
+    }
+
+    fn visit_stmt(&mut self, stmt: &Statement) {
+        let weight = match stmt {
+            Statement::If(_)
+            | Statement::Loop(_)
+            | Statement::Switch(_)
+            | Statement::ForLoop(_) => 4.0,
+            Statement::FnCall(_) => 3.0,
+            Statement::Compound(_) => 0.0,
+            _ => 1.0,
+        };
+
+        self.complexity.weighted_nodes += weight;
+
+        match stmt {
+            Statement::LetDecl(stmt) => self.flow(&stmt.ident, &stmt.initializer, false),
+            Statement::VarDecl(stmt) => {
+                if let Some(initializer) = &stmt.initializer {
+                    self.flow(&stmt.ident, initializer, false);
+                }
+            }
+            Statement::Assignment(stmt) => {
+                if let AssignmentLhs::Expr(lhs) = &stmt.lhs {
+                    if let Some(name) = lhs_root(lhs) {
+                        let compound = !matches!(stmt.op, AssignmentOp::Simple);
+                        self.flow(name, &stmt.rhs, compound);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, node: &ExprNode) {
+        self.complexity.weighted_nodes += match &node.expr {
+            Expr::Lit(_) | Expr::Var(_) => 0.5,
+            Expr::FnCall(_) => 2.0,
+            _ => 1.0,
+        };
+
+        visit::walk_expr(self, node);
+    }
+}
+
+/// Returns the variable which is assigned to through `lhs`.
+fn lhs_root(lhs: &LhsExprNode) -> Option<&str> {
+    match &lhs.expr {
+        LhsExpr::Ident(name) => Some(name),
+        LhsExpr::Postfix(inner, _) => lhs_root(inner),
+        // Writes through pointers could be to any variable
+        LhsExpr::Deref(_) | LhsExpr::AddressOf(_) => None,
+    }
+}
+
+/// Names of the variables referenced by an expression.
+#[derive(Default)]
+struct VarRefs(Vec<String>);
+
+impl Visitor for VarRefs {
+    fn visit_expr(&mut self, node: &ExprNode) {
+        if let Expr::Var(var) = &node.expr {
+            self.0.push(var.ident.clone());
+        }
+
+        visit::walk_expr(self, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AssignmentStatement, BinOp, BinOpExpr, DataType, IfStatement, LetDeclStatement, Lit,
+        LoopStatement, ScalarType, VarDeclStatement, VarExpr,
+    };
+
+    const I32: DataType = DataType::Scalar(ScalarType::I32);
+
+    fn var(name: &str) -> ExprNode {
+        VarExpr::new(name).into_node(I32)
+    }
+
+    fn func(name: &str, body: Vec<Statement>) -> FnDecl {
+        FnDecl {
+            attrs: vec![],
+            name: name.to_owned(),
+            inputs: vec![],
+            output: None,
+            body,
+        }
+    }
+
+    fn module(functions: Vec<FnDecl>) -> Module {
+        Module {
+            functions,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn empty_module() {
+        let complexity = Complexity::collect(&module(vec![]));
+
+        assert_eq!(complexity, Complexity::default());
+        assert_eq!(complexity.score(), 0.0);
+    }
+
+    #[test]
+    fn dataflow_chain() {
+        let body = vec![
+            LetDeclStatement::new("a", Lit::I32(1)).into(),
+            LetDeclStatement::new("b", BinOpExpr::new(BinOp::Plus, var("a"), Lit::I32(1))).into(),
+            VarDeclStatement::new("c", None, Some(var("b"))).into(),
+            // Compound assignments extend the chain of the variable being assigned
+            AssignmentStatement::new(AssignmentLhs::name("c", I32), AssignmentOp::Plus, var("a"))
+                .into(),
+        ];
+
+        let complexity = Complexity::collect(&module(vec![func("f", body)]));
+
+        assert_eq!(
+            complexity,
+            Complexity {
+                weighted_nodes: 7.5,
+                max_block_depth: 1,
+                max_dataflow: 4,
+            }
+        );
+
+        assert_eq!(complexity.score(), 7.5 + 5.0 + 3.0 * 4.0);
+    }
+
+    #[test]
+    fn nested_blocks() {
+        let body = vec![LoopStatement::new(vec![IfStatement::new(
+            Lit::Bool(true),
+            vec![Statement::Break],
+        )
+        .into()])
+        .into()];
+
+        let complexity = Complexity::collect(&module(vec![func("f", body)]));
+
+        assert_eq!(
+            complexity,
+            Complexity {
+                weighted_nodes: 4.0 + 4.0 + 0.5 + 1.0,
+                max_block_depth: 3,
+                max_dataflow: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn dataflow_is_per_function() {
+        let f = func(
+            "f",
+            vec![
+                LetDeclStatement::new("a", Lit::I32(1)).into(),
+                LetDeclStatement::new("b", var("a")).into(),
+            ],
+        );
+
+        // `b` here isn't the `b` in `f`, so this doesn't extend its chain
+        let g = func("g", vec![LetDeclStatement::new("c", var("b")).into()]);
+
+        let complexity = Complexity::collect(&module(vec![f, g]));
+
+        assert_eq!(complexity.max_dataflow, 2);
+    }
+}
@@ -0,0 +1,34 @@
+use derive_more::Display;
+
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Severity {
+    #[display(fmt = "error")]
+    Error,
+    #[display(fmt = "warning")]
+    Warning,
+    #[display(fmt = "info")]
+    Info,
+    #[display(fmt = "off")]
+    Off,
+}
+
+/// The arguments of a `diagnostic` directive or attribute, which set the severity of a diagnostic
+/// rule.
+#[derive(Clone, Debug, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[display(fmt = "diagnostic({severity}, {rule})")]
+pub struct DiagnosticControl {
+    pub severity: Severity,
+    /// Name of the triggering rule, e.g. `derivative_uniformity` or `chromium.unreachable_code`.
+    pub rule: String,
+}
+
+impl DiagnosticControl {
+    pub fn new(severity: Severity, rule: impl Into<String>) -> Self {
+        Self {
+            severity,
+            rule: rule.into(),
+        }
+    }
+}
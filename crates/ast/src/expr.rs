@@ -5,11 +5,17 @@ use derive_more::{Display, From};
 use crate::types::{DataType, ScalarType};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Lit {
     Bool(bool),
     I32(i32),
     U32(u32),
     F32(f32),
+    /// An integer literal without a suffix, which is implicitly converted to i32, u32 or f32
+    /// depending on where it is used.
+    AbstractInt(i64),
+    /// A float literal without a suffix, which is implicitly converted to f32.
+    AbstractFloat(f64),
 }
 
 impl Lit {
@@ -19,6 +25,22 @@ impl Lit {
             Lit::I32(_) => ScalarType::I32.into(),
             Lit::U32(_) => ScalarType::U32.into(),
             Lit::F32(_) => ScalarType::F32.into(),
+            // These are the types that abstract literals are converted to if nothing else
+            // constrains them
+            Lit::AbstractInt(_) => ScalarType::I32.into(),
+            Lit::AbstractFloat(_) => ScalarType::F32.into(),
+        }
+    }
+
+    /// Whether the literal can be used where a value of type `ty` is expected, which for abstract
+    /// literals requires the value to be representable in that type.
+    pub fn converts_to(&self, ty: ScalarType) -> bool {
+        match (self, ty) {
+            (Lit::AbstractInt(v), ScalarType::I32) => i32::try_from(*v).is_ok(),
+            (Lit::AbstractInt(v), ScalarType::U32) => u32::try_from(*v).is_ok(),
+            (Lit::AbstractInt(_), ScalarType::F32) => true,
+            (Lit::AbstractFloat(v), ScalarType::F32) => v.abs() <= f32::MAX as f64,
+            (lit, ty) => lit.data_type() == DataType::Scalar(ty),
         }
     }
 }
@@ -41,6 +63,16 @@ impl Display for Lit {
                     write!(f, ".0")?;
                 }
 
+                Ok(())
+            }
+            Lit::AbstractInt(v) => v.fmt(f),
+            Lit::AbstractFloat(v) => {
+                write!(f, "{v}")?;
+
+                if v.fract() == 0.0 {
+                    write!(f, ".0")?;
+                }
+
                 Ok(())
             }
         }
@@ -48,6 +80,7 @@ impl Display for Lit {
 }
 
 #[derive(Clone, Debug, Display, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[display(fmt = "{data_type}({})", "crate::FmtArgs(args)")]
 pub struct TypeConsExpr {
     pub data_type: DataType,
@@ -61,6 +94,7 @@ impl TypeConsExpr {
 }
 
 #[derive(Clone, Copy, Debug, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UnOp {
     #[display(fmt = "-")]
     Neg,
@@ -100,6 +134,7 @@ impl UnOp {
 }
 
 #[derive(Clone, Copy, Debug, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BinOp {
     #[display(fmt = "+")]
     Plus,
@@ -201,6 +236,7 @@ impl BinOp {
 }
 
 #[derive(Clone, Debug, Display, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Postfix {
     #[display(fmt = "[{_0}]")]
     Index(Box<ExprNode>),
@@ -244,6 +280,7 @@ impl Postfix {
 }
 
 #[derive(Clone, Debug, Display, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[display(fmt = "{ident}")]
 pub struct VarExpr {
     pub ident: String,
@@ -256,15 +293,16 @@ impl VarExpr {
         }
     }
 
-    pub fn into_node(self, data_type: DataType) -> ExprNode {
+    pub fn into_node(self, data_type: impl Into<DataType>) -> ExprNode {
         ExprNode {
-            data_type,
+            data_type: data_type.into(),
             expr: self.into(),
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PostfixExpr {
     pub inner: Box<ExprNode>,
     pub postfix: Postfix,
@@ -291,6 +329,7 @@ impl Display for PostfixExpr {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnOpExpr {
     pub op: UnOp,
     pub inner: Box<ExprNode>,
@@ -314,6 +353,8 @@ impl Display for UnOpExpr {
         if matches!(inner.expr, Expr::UnOp(_) | Expr::BinOp(_))
             || matches!(inner.expr, Expr::Lit(Lit::I32(v)) if v < 0)
             || matches!(inner.expr, Expr::Lit(Lit::F32(v)) if v < 0.0)
+            || matches!(inner.expr, Expr::Lit(Lit::AbstractInt(v)) if v < 0)
+            || matches!(inner.expr, Expr::Lit(Lit::AbstractFloat(v)) if v < 0.0)
         {
             write!(f, "{op}({inner})")
         } else {
@@ -323,6 +364,7 @@ impl Display for UnOpExpr {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BinOpExpr {
     pub op: BinOp,
     pub left: Box<ExprNode>,
@@ -360,6 +402,7 @@ impl Display for BinOpExpr {
 }
 
 #[derive(Clone, Debug, Display, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[display(fmt = "{ident}({})", "crate::FmtArgs(args)")]
 pub struct FnCallExpr {
     pub ident: String,
@@ -383,6 +426,7 @@ impl FnCallExpr {
 }
 
 #[derive(Clone, Debug, Display, PartialEq, From)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expr {
     Lit(Lit),
     TypeCons(TypeConsExpr),
@@ -394,6 +438,7 @@ pub enum Expr {
 }
 
 #[derive(Clone, Debug, Display, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[display(fmt = "{expr}")]
 pub struct ExprNode {
     pub data_type: DataType,
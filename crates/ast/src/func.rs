@@ -2,10 +2,12 @@ use std::fmt::Display;
 
 use derive_more::Display;
 
+use crate::diagnostic::DiagnosticControl;
 use crate::stmt::Statement;
 use crate::types::DataType;
 
-#[derive(Debug, Display, PartialEq, Eq)]
+#[derive(Clone, Debug, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ShaderStage {
     #[display(fmt = "compute")]
     Compute,
@@ -15,21 +17,96 @@ pub enum ShaderStage {
     Fragment,
 }
 
-#[derive(Debug, Display, PartialEq, Eq)]
+#[derive(Clone, Debug, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FnAttr {
     #[display(fmt = "stage({_0})")]
     Stage(ShaderStage),
     #[display(fmt = "workgroup_size({_0})")]
     WorkgroupSize(u32),
+    #[display(fmt = "{_0}")]
+    Diagnostic(DiagnosticControl),
 }
 
-#[derive(Debug, Display, PartialEq, Eq)]
-pub enum FnInputAttr {}
+/// A builtin value that can be passed to or returned from an entry point.
+#[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BuiltinValue {
+    #[display(fmt = "local_invocation_index")]
+    LocalInvocationIndex,
+    #[display(fmt = "vertex_index")]
+    VertexIndex,
+    #[display(fmt = "position")]
+    Position,
+    #[display(fmt = "frag_depth")]
+    FragDepth,
+}
+
+#[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InterpolationType {
+    #[display(fmt = "perspective")]
+    Perspective,
+    #[display(fmt = "linear")]
+    Linear,
+    #[display(fmt = "flat")]
+    Flat,
+}
+
+#[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InterpolationSampling {
+    #[display(fmt = "center")]
+    Center,
+    #[display(fmt = "centroid")]
+    Centroid,
+    #[display(fmt = "sample")]
+    Sample,
+}
+
+/// The arguments of an `@interpolate` attribute on an inter-stage variable.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Interpolation {
+    pub kind: InterpolationType,
+    /// This must be `None` for flat interpolation.
+    pub sampling: Option<InterpolationSampling>,
+}
+
+impl Display for Interpolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
 
-#[derive(Debug, Display, PartialEq, Eq)]
-pub enum FnOutputAttr {}
+        if let Some(sampling) = self.sampling {
+            write!(f, ", {sampling}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FnInputAttr {
+    #[display(fmt = "builtin({_0})")]
+    Builtin(BuiltinValue),
+    #[display(fmt = "location({_0})")]
+    Location(u32),
+    #[display(fmt = "interpolate({_0})")]
+    Interpolate(Interpolation),
+}
+
+#[derive(Clone, Debug, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FnOutputAttr {
+    #[display(fmt = "builtin({_0})")]
+    Builtin(BuiltinValue),
+    #[display(fmt = "location({_0})")]
+    Location(u32),
+}
 
-#[derive(Debug, Display, PartialEq, Eq)]
+#[derive(Clone, Debug, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[display(fmt = "{}{name}: {data_type}", "InlineAttrs(attrs)")]
 pub struct FnInput {
     pub attrs: Vec<FnInputAttr>,
@@ -47,7 +124,8 @@ impl FnInput {
     }
 }
 
-#[derive(Debug, Display, PartialEq, Eq)]
+#[derive(Clone, Debug, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[display(fmt = "{}{data_type}", "InlineAttrs(attrs)")]
 pub struct FnOutput {
     pub attrs: Vec<FnOutputAttr>,
@@ -63,7 +141,8 @@ impl FnOutput {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FnDecl {
     pub attrs: Vec<FnAttr>,
     pub name: String,
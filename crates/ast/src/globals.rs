@@ -3,7 +3,8 @@ use derive_more::Display;
 use crate::types::DataType;
 use crate::ExprNode;
 
-#[derive(Debug, Display, PartialEq, Eq)]
+#[derive(Clone, Debug, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GlobalVarAttr {
     #[display(fmt = "binding({_0})")]
     Binding(i32),
@@ -12,6 +13,7 @@ pub enum GlobalVarAttr {
 }
 
 #[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StorageClass {
     #[display(fmt = "function")]
     Function,
@@ -23,6 +25,8 @@ pub enum StorageClass {
     Uniform,
     #[display(fmt = "storage")]
     Storage,
+    #[display(fmt = "push_constant")]
+    PushConstant,
 }
 
 impl StorageClass {
@@ -33,11 +37,13 @@ impl StorageClass {
             StorageClass::WorkGroup => AccessMode::ReadWrite,
             StorageClass::Uniform => AccessMode::Read,
             StorageClass::Storage => AccessMode::Read,
+            StorageClass::PushConstant => AccessMode::Read,
         }
     }
 }
 
 #[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AccessMode {
     #[display(fmt = "read")]
     Read,
@@ -47,13 +53,15 @@ pub enum AccessMode {
     ReadWrite,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VarQualifier {
     pub storage_class: StorageClass,
     pub access_mode: Option<AccessMode>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GlobalVarDecl {
     pub attrs: Vec<GlobalVarAttr>,
     pub qualifier: Option<VarQualifier>,
@@ -84,7 +92,8 @@ impl GlobalVarDecl {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GlobalConstDecl {
     pub name: String,
     pub data_type: DataType,
@@ -1,7 +1,15 @@
+pub mod canonical;
+pub mod complexity;
+pub mod hash;
+pub mod rename;
+pub mod stats;
+pub mod typecheck;
 pub mod types;
+pub mod visit;
 pub mod writer;
 
 mod builtins;
+mod diagnostic;
 mod expr;
 mod func;
 mod globals;
@@ -9,6 +17,7 @@ mod stmt;
 mod structs;
 
 pub use builtins::*;
+pub use diagnostic::*;
 pub use expr::*;
 pub use func::*;
 pub use globals::*;
@@ -19,10 +28,19 @@ use std::rc::Rc;
 
 pub use types::{DataType, ScalarType};
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Module {
+    /// Extensions enabled by `enable` directives, e.g. `subgroups`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub enables: Vec<String>,
+    /// Module scope `diagnostic` directives, which are written before any declarations.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub diagnostics: Vec<DiagnosticControl>,
     pub structs: Vec<Rc<StructDecl>>,
     pub consts: Vec<GlobalConstDecl>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub const_asserts: Vec<ConstAssertStatement>,
     pub vars: Vec<GlobalVarDecl>,
     pub functions: Vec<FnDecl>,
 }
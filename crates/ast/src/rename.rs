@@ -0,0 +1,159 @@
+//! Renaming of identifiers to a canonical form.
+//!
+//! Identifiers are renamed in order of declaration, so that programs which only differ in the names
+//! they use are written out identically. Entry points and resource variables are referred to by the
+//! host so they keep their names, as do struct types and their members.
+
+use std::collections::HashMap;
+
+use crate::visit::{self, VisitorMut};
+use crate::{
+    Expr, ExprNode, FnAttr, FnDecl, ForLoopInit, ForLoopUpdate, LhsExpr, LhsExprNode, Module,
+    Statement,
+};
+
+pub fn rename_identifiers(module: &mut Module) {
+    let mut renamer = Renamer::default();
+    let mut next_global = 0;
+
+    for decl in &mut module.consts {
+        renamer.rename_global(&mut decl.name, format!("g{next_global}"));
+        next_global += 1;
+    }
+
+    for decl in &mut module.vars {
+        if decl.group_index().is_none() && decl.binding_index().is_none() {
+            renamer.rename_global(&mut decl.name, format!("g{next_global}"));
+            next_global += 1;
+        }
+    }
+
+    for (i, decl) in module
+        .functions
+        .iter()
+        .filter(|decl| !decl.attrs.iter().any(|it| matches!(it, FnAttr::Stage(_))))
+        .enumerate()
+    {
+        renamer.globals.insert(decl.name.clone(), format!("f{i}"));
+    }
+
+    renamer.visit_module_mut(module);
+}
+
+#[derive(Default)]
+struct Renamer {
+    globals: HashMap<String, String>,
+    scopes: Vec<HashMap<String, String>>,
+    next_local: usize,
+}
+
+impl Renamer {
+    fn rename_global(&mut self, name: &mut String, new_name: String) {
+        self.globals.insert(name.clone(), new_name.clone());
+        *name = new_name;
+    }
+
+    fn declare(&mut self, name: &mut String) {
+        let new_name = format!("v{}", self.next_local);
+        self.next_local += 1;
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.clone(), new_name.clone());
+        *name = new_name;
+    }
+
+    fn rename(&self, name: &mut String) {
+        let new_name = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name.as_str()))
+            .or_else(|| self.globals.get(name.as_str()));
+
+        if let Some(new_name) = new_name {
+            *name = new_name.clone();
+        }
+    }
+}
+
+impl VisitorMut for Renamer {
+    fn visit_fn_decl_mut(&mut self, decl: &mut FnDecl) {
+        self.rename(&mut decl.name);
+        self.next_local = 0;
+        self.scopes.push(HashMap::new());
+
+        for input in &mut decl.inputs {
+            self.declare(&mut input.name);
+        }
+
+        self.visit_block_mut(&mut decl.body);
+        self.scopes.pop();
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Vec<Statement>) {
+        self.scopes.push(HashMap::new());
+        visit::walk_block_mut(self, block);
+        self.scopes.pop();
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Statement) {
+        match stmt {
+            // Initializers are visited before declaring the new name, since they can refer to a
+            // variable with the same name from an outer scope
+            Statement::LetDecl(stmt) => {
+                self.visit_expr_mut(&mut stmt.initializer);
+                self.declare(&mut stmt.ident);
+            }
+            Statement::VarDecl(stmt) => {
+                visit::walk_var_decl_mut(self, stmt);
+                self.declare(&mut stmt.ident);
+            }
+            Statement::ForLoop(stmt) => {
+                self.scopes.push(HashMap::new());
+
+                if let Some(ForLoopInit::VarDecl(init)) = &mut stmt.header.init {
+                    visit::walk_var_decl_mut(self, init);
+                    self.declare(&mut init.ident);
+                }
+
+                if let Some(condition) = &mut stmt.header.condition {
+                    self.visit_expr_mut(condition);
+                }
+
+                if let Some(ForLoopUpdate::Assignment(update)) = &mut stmt.header.update {
+                    visit::walk_assignment_mut(self, update);
+                }
+
+                self.visit_block_mut(&mut stmt.body);
+                self.scopes.pop();
+            }
+            Statement::FnCall(stmt) => {
+                self.rename(&mut stmt.ident);
+
+                for arg in &mut stmt.args {
+                    self.visit_expr_mut(arg);
+                }
+            }
+            stmt => visit::walk_stmt_mut(self, stmt),
+        }
+    }
+
+    fn visit_lhs_expr_mut(&mut self, node: &mut LhsExprNode) {
+        if let LhsExpr::Ident(name) = &mut node.expr {
+            self.rename(name);
+        }
+
+        visit::walk_lhs_expr_mut(self, node);
+    }
+
+    fn visit_expr_mut(&mut self, node: &mut ExprNode) {
+        match &mut node.expr {
+            Expr::Var(expr) => self.rename(&mut expr.ident),
+            Expr::FnCall(expr) => self.rename(&mut expr.ident),
+            _ => {}
+        }
+
+        visit::walk_expr_mut(self, node);
+    }
+}
@@ -0,0 +1,179 @@
+//! Statistics about the contents of modules, for tuning the generator and comparing corpora.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::visit::{self, Visitor};
+use crate::{
+    BuiltinFn, Expr, ExprNode, FnDecl, GlobalConstDecl, GlobalVarDecl, LhsExprNode, Module,
+    Statement, StructDecl,
+};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stats {
+    /// Number of modules that the statistics were collected from.
+    pub modules: usize,
+    /// Total number of declarations, statements and expressions.
+    pub nodes: usize,
+    /// Maximum nesting depth of blocks, where function bodies have a depth of 1.
+    pub max_block_depth: usize,
+    /// Maximum nesting depth of expressions.
+    pub max_expr_depth: usize,
+    pub statements: BTreeMap<String, usize>,
+    pub expressions: BTreeMap<String, usize>,
+    /// Number of calls to each builtin function.
+    pub builtins: BTreeMap<String, usize>,
+}
+
+impl Stats {
+    pub fn collect(module: &Module) -> Stats {
+        let mut collector = Collector::default();
+        collector.visit_module(module);
+        collector.stats.modules = 1;
+        collector.stats
+    }
+
+    /// Combines the statistics from another set of modules into `self`.
+    pub fn merge(&mut self, other: &Stats) {
+        self.modules += other.modules;
+        self.nodes += other.nodes;
+        self.max_block_depth = self.max_block_depth.max(other.max_block_depth);
+        self.max_expr_depth = self.max_expr_depth.max(other.max_expr_depth);
+
+        for (map, from) in [
+            (&mut self.statements, &other.statements),
+            (&mut self.expressions, &other.expressions),
+            (&mut self.builtins, &other.builtins),
+        ] {
+            for (key, count) in from {
+                *map.entry(key.clone()).or_default() += count;
+            }
+        }
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "modules:         {}", self.modules)?;
+        writeln!(f, "nodes:           {}", self.nodes)?;
+        writeln!(f, "max block depth: {}", self.max_block_depth)?;
+        writeln!(f, "max expr depth:  {}", self.max_expr_depth)?;
+
+        for (title, map) in [
+            ("statements", &self.statements),
+            ("expressions", &self.expressions),
+            ("builtins", &self.builtins),
+        ] {
+            writeln!(f, "{title}:")?;
+            for (key, count) in map {
+                writeln!(f, "  {key}: {count}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Collector {
+    stats: Stats,
+    block_depth: usize,
+    expr_depth: usize,
+}
+
+impl Collector {
+    fn count_call(&mut self, ident: &str) {
+        if BuiltinFn::from_str(ident).is_ok() {
+            count(&mut self.stats.builtins, ident);
+        }
+    }
+}
+
+fn count(map: &mut BTreeMap<String, usize>, key: &str) {
+    *map.entry(key.to_owned()).or_default() += 1;
+}
+
+impl Visitor for Collector {
+    fn visit_struct_decl(&mut self, decl: &StructDecl) {
+        self.stats.nodes += 1;
+        visit::walk_struct_decl(self, decl);
+    }
+
+    fn visit_global_const(&mut self, decl: &GlobalConstDecl) {
+        self.stats.nodes += 1;
+        visit::walk_global_const(self, decl);
+    }
+
+    fn visit_global_var(&mut self, decl: &GlobalVarDecl) {
+        self.stats.nodes += 1;
+        visit::walk_global_var(self, decl);
+    }
+
+    fn visit_fn_decl(&mut self, decl: &FnDecl) {
+        self.stats.nodes += 1;
+        visit::walk_fn_decl(self, decl);
+    }
+
+    fn visit_block(&mut self, block: &[Statement]) {
+        self.block_depth += 1;
+        self.stats.max_block_depth = self.stats.max_block_depth.max(self.block_depth);
+        visit::walk_block(self, block);
+        self.block_depth -= 1;
+    }
+
+    fn visit_stmt(&mut self, stmt: &Statement) {
+        let kind = match stmt {
+            Statement::LetDecl(_) => "let",
+            Statement::VarDecl(_) => "var",
+            Statement::Assignment(_) => "assignment",
+            Statement::Compound(_) => "compound",
+            Statement::If(_) => "if",
+            Statement::Return(_) => "return",
+            Statement::Loop(_) => "loop",
+            Statement::Break => "break",
+            Statement::Continue => "continue",
+            Statement::Switch(_) => "switch",
+            Statement::Fallthrough => "fallthrough",
+            Statement::ForLoop(_) => "for",
+            Statement::FnCall(stmt) => {
+                self.count_call(&stmt.ident);
+                "call"
+            }
+            Statement::ConstAssert(_) => "const_assert",
+        };
+
+        self.stats.nodes += 1;
+        count(&mut self.stats.statements, kind);
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_lhs_expr(&mut self, node: &LhsExprNode) {
+        self.stats.nodes += 1;
+        visit::walk_lhs_expr(self, node);
+    }
+
+    fn visit_expr(&mut self, node: &ExprNode) {
+        let kind = match &node.expr {
+            Expr::Lit(_) => "literal",
+            Expr::TypeCons(_) => "constructor",
+            Expr::Var(_) => "var",
+            Expr::Postfix(_) => "postfix",
+            Expr::UnOp(_) => "unary",
+            Expr::BinOp(_) => "binary",
+            Expr::FnCall(expr) => {
+                self.count_call(&expr.ident);
+                "call"
+            }
+        };
+
+        self.stats.nodes += 1;
+        count(&mut self.stats.expressions, kind);
+
+        self.expr_depth += 1;
+        self.stats.max_expr_depth = self.stats.max_expr_depth.max(self.expr_depth);
+        visit::walk_expr(self, node);
+        self.expr_depth -= 1;
+    }
+}
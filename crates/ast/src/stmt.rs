@@ -6,7 +6,8 @@ use indenter::indented;
 use crate::types::DataType;
 use crate::{ExprNode, Postfix};
 
-#[derive(Debug, Display, PartialEq)]
+#[derive(Clone, Debug, Display, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[display(fmt = "let {ident} = {initializer}")]
 pub struct LetDeclStatement {
     pub ident: String,
@@ -32,7 +33,8 @@ impl LetDeclStatement {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VarDeclStatement {
     pub ident: String,
     pub data_type: Option<DataType>,
@@ -86,7 +88,8 @@ impl Display for VarDeclStatement {
     }
 }
 
-#[derive(Debug, Display, PartialEq)]
+#[derive(Clone, Debug, Display, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AssignmentLhs {
     #[display(fmt = "_")]
     Phony,
@@ -111,14 +114,15 @@ impl AssignmentLhs {
     }
 }
 
-#[derive(Debug, Display, PartialEq)]
+#[derive(Clone, Debug, Display, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LhsExpr {
     Ident(String),
     #[display(fmt = "({_0}){_1}")]
     Postfix(Box<LhsExprNode>, Postfix),
-    #[display(fmt = "*(_0)")]
+    #[display(fmt = "*{_0}")]
     Deref(Box<LhsExprNode>),
-    #[display(fmt = "&(_0)")]
+    #[display(fmt = "&{_0}")]
     AddressOf(Box<LhsExprNode>),
 }
 
@@ -128,7 +132,8 @@ impl From<LhsExprNode> for AssignmentLhs {
     }
 }
 
-#[derive(Debug, Display, PartialEq)]
+#[derive(Clone, Debug, Display, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[display(fmt = "{expr}")]
 pub struct LhsExprNode {
     pub data_type: DataType,
@@ -186,7 +191,8 @@ impl LhsExprNode {
     }
 }
 
-#[derive(Debug, Display, PartialEq, Eq)]
+#[derive(Clone, Debug, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AssignmentOp {
     #[display(fmt = "=")]
     Simple,
@@ -208,7 +214,8 @@ pub enum AssignmentOp {
     Xor,
 }
 
-#[derive(Debug, Display, PartialEq)]
+#[derive(Clone, Debug, Display, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[display(fmt = "{lhs} {op} {rhs}")]
 pub struct AssignmentStatement {
     pub lhs: AssignmentLhs,
@@ -226,7 +233,8 @@ impl AssignmentStatement {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Else {
     If(IfStatement),
     Else(Vec<Statement>),
@@ -253,7 +261,8 @@ impl Display for Else {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IfStatement {
     pub condition: ExprNode,
     pub body: Vec<Statement>,
@@ -299,7 +308,8 @@ impl Display for IfStatement {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ReturnStatement {
     pub value: Option<ExprNode>,
 }
@@ -334,7 +344,8 @@ impl Display for ReturnStatement {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LoopStatement {
     pub body: Vec<Statement>,
 }
@@ -357,7 +368,8 @@ impl Display for LoopStatement {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SwitchStatement {
     pub selector: ExprNode,
     pub cases: Vec<SwitchCase>,
@@ -406,30 +418,35 @@ impl Display for SwitchStatement {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SwitchCase {
     pub selector: ExprNode,
     pub body: Vec<Statement>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ForLoopInit {
     VarDecl(VarDeclStatement),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ForLoopUpdate {
     Assignment(AssignmentStatement),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ForLoopHeader {
     pub init: Option<ForLoopInit>,
     pub condition: Option<ExprNode>,
     pub update: Option<ForLoopUpdate>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ForLoopStatement {
     pub header: Box<ForLoopHeader>,
     pub body: Vec<Statement>,
@@ -480,7 +497,29 @@ impl Display for ForLoopStatement {
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// A `const_assert`, which can appear both in functions and at module scope.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConstAssertStatement {
+    pub condition: ExprNode,
+}
+
+impl ConstAssertStatement {
+    pub fn new(condition: impl Into<ExprNode>) -> Self {
+        Self {
+            condition: condition.into(),
+        }
+    }
+}
+
+impl Display for ConstAssertStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "const_assert {}", self.condition)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FnCallStatement {
     pub ident: String,
     pub args: Vec<ExprNode>,
@@ -507,7 +546,8 @@ impl Display for FnCallStatement {
     }
 }
 
-#[derive(Debug, PartialEq, From)]
+#[derive(Clone, Debug, PartialEq, From)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Statement {
     LetDecl(LetDeclStatement),
     VarDecl(VarDeclStatement),
@@ -522,6 +562,7 @@ pub enum Statement {
     Fallthrough,
     ForLoop(ForLoopStatement),
     FnCall(FnCallStatement),
+    ConstAssert(ConstAssertStatement),
 }
 
 impl Statement {
@@ -560,6 +601,7 @@ impl Display for Statement {
             Statement::Switch(stmt) => stmt.fmt(f),
             Statement::ForLoop(stmt) => stmt.fmt(f),
             Statement::FnCall(stmt) => write!(f, "{stmt};"),
+            Statement::ConstAssert(stmt) => write!(f, "{stmt};"),
         }
     }
 }
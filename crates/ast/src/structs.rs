@@ -1,18 +1,27 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::hash::Hash;
 use std::rc::Rc;
 
 use derive_more::Display;
 
 use crate::types::DataType;
+use crate::{BuiltinValue, Interpolation};
 
 #[derive(Clone, Debug, Display, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StructMemberAttr {
     #[display(fmt = "align({_0})")]
     Align(u8),
+    #[display(fmt = "builtin({_0})")]
+    Builtin(BuiltinValue),
+    #[display(fmt = "location({_0})")]
+    Location(u32),
+    #[display(fmt = "interpolate({_0})")]
+    Interpolate(Interpolation),
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StructMember {
     pub attrs: Vec<StructMemberAttr>,
     pub name: String,
@@ -54,6 +63,40 @@ impl PartialEq for StructDecl {
 
 impl Eq for StructDecl {}
 
+// The accessors are derived from the members, and can't be serialized anyway since they're keyed
+// by data type, so we only store the members and rebuild the accessors when deserializing.
+#[cfg(feature = "serde")]
+impl serde::Serialize for StructDecl {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut s = serializer.serialize_struct("StructDecl", 2)?;
+        s.serialize_field("name", &self.name)?;
+        s.serialize_field("members", &self.members)?;
+        s.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for StructDecl {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Fields {
+            name: String,
+            members: Vec<Rc<StructMember>>,
+        }
+
+        let Fields { name, members } = Fields::deserialize(deserializer)?;
+        let accessors = collect_struct_accessors(&members);
+
+        Ok(StructDecl {
+            name,
+            members,
+            accessors,
+        })
+    }
+}
+
 impl StructDecl {
     pub fn new(name: impl Into<String>, members: Vec<Rc<StructMember>>) -> Rc<StructDecl> {
         let name = name.into();
@@ -95,12 +138,17 @@ fn collect_struct_accessors(
 ) -> HashMap<DataType, Vec<Rc<StructMember>>> {
     let mut accessors = HashMap::new();
 
+    // Members are kept in declaration order rather than in a set, so that the order in which
+    // they're chosen from doesn't depend on a random hasher
     fn insert(
-        map: &mut HashMap<DataType, HashSet<Rc<StructMember>>>,
+        map: &mut HashMap<DataType, Vec<Rc<StructMember>>>,
         ty: &DataType,
         member: &Rc<StructMember>,
     ) {
-        map.entry(ty.clone()).or_default().insert(member.clone());
+        let members = map.entry(ty.clone()).or_default();
+        if !members.contains(member) {
+            members.push(member.clone());
+        }
     }
 
     for member in members {
@@ -130,10 +178,5 @@ fn collect_struct_accessors(
         }
     }
 
-    // Convert the sets into vectors
-    // We use vectors for more efficient random selection later on
     accessors
-        .into_iter()
-        .map(|(k, v)| (k, v.into_iter().collect()))
-        .collect()
 }
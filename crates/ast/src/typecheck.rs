@@ -0,0 +1,1950 @@
+//! Type checking for complete modules.
+//!
+//! This is used to catch bugs in the generator and in passes that transform the AST, by making sure
+//! that a program is well typed before it is handed to the compilers under test. Expressions are
+//! checked against the types they are annotated with, since passes rely on those annotations being
+//! accurate.
+//!
+//! References are treated loosely when comparing types, since expressions that read a variable
+//! may be annotated with either the reference type or the store type.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::types::{DataType, MemoryViewType, ScalarType};
+use crate::{
+    AccessMode, AssignmentLhs, AssignmentOp, AssignmentStatement, BuiltinFn, Else, Expr, ExprNode,
+    FnAttr, FnDecl, ForLoopInit, ForLoopUpdate, GlobalVarDecl, IfStatement, LhsExpr, LhsExprNode,
+    Module, Postfix, ShaderStage, Statement, StorageClass, UnOp, VarDeclStatement,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeError {
+    /// Name of the function containing the error, or `None` for module scope declarations.
+    pub func: Option<String>,
+    pub message: String,
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.func {
+            Some(func) => write!(f, "in function `{func}`: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+/// Checks that `module` is well typed, returning all errors that were found.
+pub fn check(module: &Module) -> Result<(), Vec<TypeError>> {
+    let mut checker = Checker::new();
+
+    checker.check_module(module);
+
+    if checker.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(checker.errors)
+    }
+}
+
+struct FnSig {
+    inputs: Vec<DataType>,
+    output: Option<DataType>,
+    is_entry_point: bool,
+}
+
+enum Breakable {
+    Loop,
+    Switch,
+}
+
+struct Checker {
+    fns: HashMap<String, FnSig>,
+    scopes: Vec<HashMap<String, DataType>>,
+    errors: Vec<TypeError>,
+    current_fn: Option<String>,
+    return_type: Option<DataType>,
+    breakables: Vec<Breakable>,
+    enables: Vec<String>,
+}
+
+/// Compares two types, ignoring any outer reference.
+fn same(a: &DataType, b: &DataType) -> bool {
+    a.dereference() == b.dereference()
+}
+
+fn is_numeric(ty: &DataType) -> bool {
+    (ty.is_scalar() || ty.is_vector())
+        && matches!(
+            ty.as_scalar(),
+            Some(ScalarType::I32 | ScalarType::U32 | ScalarType::F32)
+        )
+}
+
+fn is_storable(ty: &DataType) -> bool {
+    !matches!(ty, DataType::Ptr(_) | DataType::Ref(_))
+}
+
+impl Checker {
+    fn new() -> Checker {
+        Checker {
+            fns: HashMap::new(),
+            scopes: vec![HashMap::new()],
+            errors: vec![],
+            current_fn: None,
+            return_type: None,
+            breakables: vec![],
+            enables: vec![],
+        }
+    }
+
+    fn error(&mut self, message: impl Into<String>) {
+        self.errors.push(TypeError {
+            func: self.current_fn.clone(),
+            message: message.into(),
+        });
+    }
+
+    fn lookup(&self, name: &str) -> Option<&DataType> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn bind(&mut self, name: &str, ty: DataType) {
+        self.scopes.last_mut().unwrap().insert(name.to_owned(), ty);
+    }
+
+    fn with_scope(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        f(self);
+        self.scopes.pop();
+    }
+
+    fn check_module(&mut self, module: &Module) {
+        self.enables = module.enables.clone();
+
+        for decl in &module.structs {
+            for member in &decl.members {
+                if !is_storable(&member.data_type) {
+                    self.error(format!(
+                        "member `{}` of struct `{}` has non-storable type `{}`",
+                        member.name, decl.name, member.data_type
+                    ));
+                }
+            }
+        }
+
+        // Module scope declarations can be used before they are declared, so bind everything
+        // before checking initializers and function bodies
+        for decl in &module.consts {
+            self.bind(&decl.name, decl.data_type.clone());
+        }
+
+        for decl in &module.vars {
+            let storage_class = decl
+                .qualifier
+                .as_ref()
+                .map(|it| it.storage_class)
+                .unwrap_or(StorageClass::Private);
+
+            let mut view = MemoryViewType::new(decl.data_type.clone(), storage_class);
+            if let Some(access_mode) = decl.qualifier.as_ref().and_then(|it| it.access_mode) {
+                view.access_mode = access_mode;
+            }
+
+            self.bind(&decl.name, DataType::Ref(view));
+        }
+
+        for decl in &module.functions {
+            let sig = FnSig {
+                inputs: decl.inputs.iter().map(|it| it.data_type.clone()).collect(),
+                output: decl.output.as_ref().map(|it| it.data_type.clone()),
+                is_entry_point: decl.attrs.iter().any(|it| matches!(it, FnAttr::Stage(_))),
+            };
+
+            if self.fns.insert(decl.name.clone(), sig).is_some() {
+                self.error(format!(
+                    "function `{}` is declared more than once",
+                    decl.name
+                ));
+            }
+        }
+
+        for decl in &module.consts {
+            let ty = self.check_expr(&decl.initializer);
+            if !same(&ty, &decl.data_type) {
+                self.error(format!(
+                    "constant `{}` has type `{}` but its initializer has type `{ty}`",
+                    decl.name, decl.data_type
+                ));
+            }
+        }
+
+        for stmt in &module.const_asserts {
+            self.check_condition(&stmt.condition);
+        }
+
+        for decl in &module.vars {
+            self.check_global_var(decl);
+        }
+
+        for decl in &module.functions {
+            self.check_fn(decl);
+        }
+    }
+
+    fn check_global_var(&mut self, decl: &GlobalVarDecl) {
+        let name = &decl.name;
+
+        let qualifier = match &decl.qualifier {
+            Some(qualifier) => qualifier,
+            None => {
+                return self.error(format!(
+                    "module scope variable `{name}` must have an address space"
+                ))
+            }
+        };
+
+        let storage_class = qualifier.storage_class;
+        let has_bindings = decl.group_index().is_some() || decl.binding_index().is_some();
+
+        match storage_class {
+            StorageClass::Function => self.error(format!(
+                "module scope variable `{name}` cannot be in the `function` address space"
+            )),
+            StorageClass::Uniform | StorageClass::Storage => {
+                if decl.group_index().is_none() || decl.binding_index().is_none() {
+                    self.error(format!(
+                        "resource variable `{name}` must have `group` and `binding` attributes"
+                    ));
+                }
+            }
+            StorageClass::Private | StorageClass::WorkGroup | StorageClass::PushConstant => {
+                if has_bindings {
+                    self.error(format!(
+                        "variable `{name}` in the `{storage_class}` address space cannot have \
+                         `group` or `binding` attributes"
+                    ));
+                }
+            }
+        }
+
+        if qualifier.access_mode.is_some() && storage_class != StorageClass::Storage {
+            self.error(format!(
+                "variable `{name}` cannot have an access mode in the `{storage_class}` address space"
+            ));
+        }
+
+        if !is_storable(&decl.data_type) {
+            self.error(format!(
+                "variable `{name}` has non-storable type `{}`",
+                decl.data_type
+            ));
+        }
+
+        if let Some(initializer) = &decl.initializer {
+            if storage_class != StorageClass::Private {
+                self.error(format!(
+                    "variable `{name}` in the `{storage_class}` address space cannot have an \
+                     initializer"
+                ));
+            }
+
+            let ty = self.check_expr(initializer);
+            if !same(&ty, &decl.data_type) {
+                self.error(format!(
+                    "variable `{name}` has type `{}` but its initializer has type `{ty}`",
+                    decl.data_type
+                ));
+            }
+        }
+    }
+
+    fn check_fn(&mut self, decl: &FnDecl) {
+        self.current_fn = Some(decl.name.clone());
+        self.return_type = decl.output.as_ref().map(|it| it.data_type.clone());
+
+        let stages = decl
+            .attrs
+            .iter()
+            .filter_map(|it| match it {
+                FnAttr::Stage(stage) => Some(stage),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let has_workgroup_size = decl
+            .attrs
+            .iter()
+            .any(|it| matches!(it, FnAttr::WorkgroupSize(_)));
+
+        if stages.len() > 1 {
+            self.error("entry point has more than one `stage` attribute");
+        }
+
+        match stages.first() {
+            Some(stage) => {
+                if **stage == ShaderStage::Compute && !has_workgroup_size {
+                    self.error("compute entry point must have a `workgroup_size` attribute");
+                }
+
+                if **stage != ShaderStage::Compute && has_workgroup_size {
+                    self.error("`workgroup_size` can only be applied to compute entry points");
+                }
+
+                if **stage == ShaderStage::Vertex && decl.output.is_none() {
+                    self.error("vertex entry point must return a position");
+                }
+
+                if decl.inputs.iter().any(|it| it.attrs.is_empty()) {
+                    self.error("entry point parameters must have io attributes");
+                }
+
+                if let Some(output) = &decl.output {
+                    if output.attrs.is_empty() {
+                        self.error("entry point return type must have io attributes");
+                    }
+                }
+            }
+            None => {
+                if has_workgroup_size {
+                    self.error("`workgroup_size` can only be applied to compute entry points");
+                }
+            }
+        }
+
+        self.with_scope(|this| {
+            for input in &decl.inputs {
+                match &input.data_type {
+                    DataType::Ptr(view) => {
+                        if !matches!(
+                            view.storage_class,
+                            StorageClass::Function | StorageClass::Private
+                        ) {
+                            this.error(format!(
+                                "pointer parameter `{}` must be in the `function` or `private` \
+                                 address space",
+                                input.name
+                            ));
+                        }
+                    }
+                    DataType::Ref(_) => {
+                        this.error(format!(
+                            "parameter `{}` cannot have a reference type",
+                            input.name
+                        ));
+                    }
+                    _ => {}
+                }
+
+                this.bind(&input.name, input.data_type.clone());
+            }
+
+            this.check_block(&decl.body);
+        });
+
+        self.current_fn = None;
+        self.return_type = None;
+    }
+
+    fn check_block(&mut self, block: &[Statement]) {
+        self.with_scope(|this| {
+            for stmt in block {
+                this.check_stmt(stmt);
+            }
+        });
+    }
+
+    fn check_stmt(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::LetDecl(stmt) => {
+                self.check_expr(&stmt.initializer);
+                self.bind(&stmt.ident, stmt.inferred_type().clone());
+            }
+            Statement::VarDecl(stmt) => self.check_var_decl(stmt),
+            Statement::Assignment(stmt) => self.check_assignment(stmt),
+            Statement::Compound(block) => self.check_block(block),
+            Statement::If(stmt) => self.check_if(stmt),
+            Statement::Return(stmt) => {
+                let ty = stmt.value.as_ref().map(|it| self.check_expr(it));
+                match (ty, self.return_type.clone()) {
+                    (None, None) => {}
+                    (Some(_), None) => self.error("cannot return a value from a void function"),
+                    (None, Some(expected)) => {
+                        self.error(format!("missing return value of type `{expected}`"))
+                    }
+                    (Some(ty), Some(expected)) => {
+                        if !same(&ty, &expected) {
+                            self.error(format!(
+                                "returned value has type `{ty}` but function returns `{expected}`"
+                            ));
+                        }
+                    }
+                }
+            }
+            Statement::Loop(stmt) => {
+                self.breakables.push(Breakable::Loop);
+                self.check_block(&stmt.body);
+                self.breakables.pop();
+            }
+            Statement::Break => {
+                if self.breakables.is_empty() {
+                    self.error("`break` must be inside a loop or switch");
+                }
+            }
+            Statement::Continue => {
+                if !self
+                    .breakables
+                    .iter()
+                    .any(|it| matches!(it, Breakable::Loop))
+                {
+                    self.error("`continue` must be inside a loop");
+                }
+            }
+            Statement::Fallthrough => {
+                if !matches!(self.breakables.last(), Some(Breakable::Switch)) {
+                    self.error("`fallthrough` must be inside a switch case");
+                }
+            }
+            Statement::Switch(stmt) => {
+                let selector = self.check_expr(&stmt.selector);
+                if !matches!(
+                    selector.dereference(),
+                    DataType::Scalar(ScalarType::I32 | ScalarType::U32)
+                ) {
+                    self.error(format!(
+                        "switch selector must be an integer scalar, got `{selector}`"
+                    ));
+                }
+
+                self.breakables.push(Breakable::Switch);
+
+                for case in &stmt.cases {
+                    let ty = self.check_expr(&case.selector);
+                    if !same(&ty, &selector) {
+                        self.error(format!(
+                            "case selector has type `{ty}` but switch selector has type \
+                             `{selector}`"
+                        ));
+                    }
+
+                    self.check_block(&case.body);
+                }
+
+                self.check_block(&stmt.default);
+                self.breakables.pop();
+            }
+            Statement::ForLoop(stmt) => self.with_scope(|this| {
+                if let Some(ForLoopInit::VarDecl(init)) = &stmt.header.init {
+                    this.check_var_decl(init);
+                }
+
+                if let Some(condition) = &stmt.header.condition {
+                    this.check_condition(condition);
+                }
+
+                if let Some(ForLoopUpdate::Assignment(update)) = &stmt.header.update {
+                    this.check_assignment(update);
+                }
+
+                this.breakables.push(Breakable::Loop);
+                this.check_block(&stmt.body);
+                this.breakables.pop();
+            }),
+            Statement::FnCall(stmt) => {
+                for arg in &stmt.args {
+                    self.check_expr(arg);
+                }
+
+                let _ = self.check_call(&stmt.ident, &stmt.args);
+            }
+            Statement::ConstAssert(stmt) => self.check_condition(&stmt.condition),
+        }
+    }
+
+    fn check_var_decl(&mut self, stmt: &VarDeclStatement) {
+        let init_type = stmt.initializer.as_ref().map(|it| self.check_expr(it));
+
+        match (&stmt.data_type, &init_type) {
+            (None, None) => {
+                return self.error(format!(
+                    "variable `{}` must have a type or an initializer",
+                    stmt.ident
+                ))
+            }
+            (Some(ty), Some(init_type)) => {
+                if !same(ty, init_type) {
+                    self.error(format!(
+                        "variable `{}` has type `{ty}` but its initializer has type `{init_type}`",
+                        stmt.ident
+                    ));
+                }
+            }
+            _ => {}
+        }
+
+        let ty = stmt.inferred_type();
+        if !is_storable(ty) {
+            self.error(format!(
+                "variable `{}` has non-storable type `{ty}`",
+                stmt.ident
+            ));
+        }
+
+        let view = MemoryViewType::new(ty.clone(), StorageClass::Function);
+        self.bind(&stmt.ident, DataType::Ref(view));
+    }
+
+    fn check_condition(&mut self, condition: &ExprNode) {
+        let ty = self.check_expr(condition);
+        if *ty.dereference() != DataType::Scalar(ScalarType::Bool) {
+            self.error(format!("condition must be a `bool`, got `{ty}`"));
+        }
+    }
+
+    fn check_if(&mut self, stmt: &IfStatement) {
+        self.check_condition(&stmt.condition);
+        self.check_block(&stmt.body);
+
+        match stmt.else_.as_deref() {
+            Some(Else::If(stmt)) => self.check_if(stmt),
+            Some(Else::Else(block)) => self.check_block(block),
+            None => {}
+        }
+    }
+
+    fn check_assignment(&mut self, stmt: &AssignmentStatement) {
+        let rhs = self.check_expr(&stmt.rhs);
+
+        let lhs = match &stmt.lhs {
+            AssignmentLhs::Phony => return,
+            AssignmentLhs::Expr(lhs) => lhs,
+        };
+
+        let view = match self.check_lhs(lhs) {
+            Some(view) => view,
+            None => return,
+        };
+
+        if view.access_mode == AccessMode::Read {
+            self.error(format!("cannot assign to `{lhs}` since it is read-only"));
+        }
+
+        let target = view.inner.as_ref();
+        let rhs = rhs.dereference();
+
+        let valid = match stmt.op {
+            AssignmentOp::Simple => target == rhs,
+            AssignmentOp::Plus
+            | AssignmentOp::Minus
+            | AssignmentOp::Times
+            | AssignmentOp::Divide
+            | AssignmentOp::Mod => {
+                is_numeric(target)
+                    && (target == rhs || (rhs.is_scalar() && target.as_scalar() == rhs.as_scalar()))
+            }
+            AssignmentOp::And | AssignmentOp::Or | AssignmentOp::Xor => {
+                target == rhs
+                    && (target.is_scalar() || target.is_vector())
+                    && (target.is_integer() || target.as_scalar() == Some(ScalarType::Bool))
+            }
+        };
+
+        if !valid {
+            self.error(format!(
+                "cannot assign a value of type `{rhs}` to `{lhs}` of type `{target}` with `{}`",
+                stmt.op
+            ));
+        }
+    }
+
+    /// Checks the target of an assignment, returning the view of memory that it refers to.
+    fn check_lhs(&mut self, node: &LhsExprNode) -> Option<MemoryViewType> {
+        let view = match &node.expr {
+            LhsExpr::Ident(name) => match self.lookup(name) {
+                Some(DataType::Ref(view)) => view.clone(),
+                Some(_) => {
+                    self.error(format!(
+                        "cannot assign to `{name}` since it is not a variable"
+                    ));
+                    return None;
+                }
+                None => {
+                    self.error(format!("use of undeclared identifier `{name}`"));
+                    return None;
+                }
+            },
+            LhsExpr::Postfix(inner, postfix) => {
+                let view = self.check_lhs(inner)?;
+
+                if let Postfix::Index(index) = postfix {
+                    self.check_expr(index);
+                }
+
+                let ty = self.check_postfix(&view.inner, postfix)?;
+                view.clone_with_type(ty)
+            }
+            LhsExpr::Deref(inner) => match &inner.expr {
+                LhsExpr::Ident(name) => match self.lookup(name) {
+                    Some(DataType::Ptr(view)) => view.clone(),
+                    _ => {
+                        self.error(format!(
+                            "cannot dereference `{name}` since it is not a pointer"
+                        ));
+                        return None;
+                    }
+                },
+                _ => {
+                    self.error(format!("invalid pointer in assignment target `{node}`"));
+                    return None;
+                }
+            },
+            LhsExpr::AddressOf(_) => {
+                self.error(format!("cannot assign to address-of expression `{node}`"));
+                return None;
+            }
+        };
+
+        if !same(&node.data_type, &view.inner) {
+            self.error(format!(
+                "assignment target `{node}` is annotated with type `{}` but has type `{}`",
+                node.data_type, view.inner
+            ));
+        }
+
+        Some(view)
+    }
+
+    /// Checks an expression and its subexpressions, returning the type that it is annotated with.
+    fn check_expr(&mut self, node: &ExprNode) -> DataType {
+        if let Some(ty) = self.infer_expr(node) {
+            if !same(&ty, &node.data_type) {
+                self.error(format!(
+                    "expression `{node}` is annotated with type `{}` but has type `{ty}`",
+                    node.data_type
+                ));
+            }
+        }
+
+        node.data_type.clone()
+    }
+
+    /// Determines the type of an expression from its operands, or returns `None` if an error was
+    /// found.
+    fn infer_expr(&mut self, node: &ExprNode) -> Option<DataType> {
+        match &node.expr {
+            // Abstract literals take on the type that they're annotated with, as long as they can
+            // be converted to it
+            Expr::Lit(lit) => match &node.data_type {
+                DataType::Scalar(ty) if lit.converts_to(*ty) => Some(node.data_type.clone()),
+                _ => Some(lit.data_type()),
+            },
+            Expr::Var(expr) => match self.lookup(&expr.ident) {
+                Some(ty) => Some(ty.clone()),
+                None => {
+                    self.error(format!("use of undeclared identifier `{}`", expr.ident));
+                    None
+                }
+            },
+            Expr::TypeCons(expr) => {
+                let args = expr
+                    .args
+                    .iter()
+                    .map(|it| self.check_expr(it))
+                    .collect::<Vec<_>>();
+
+                self.check_type_cons(&expr.data_type, &args);
+
+                Some(expr.data_type.clone())
+            }
+            Expr::Postfix(expr) => {
+                let inner = self.check_expr(&expr.inner);
+
+                if let Postfix::Index(index) = &expr.postfix {
+                    self.check_expr(index);
+                }
+
+                let ty = self.check_postfix(inner.dereference(), &expr.postfix)?;
+
+                match inner {
+                    DataType::Ref(view) => Some(DataType::Ref(view.clone_with_type(ty))),
+                    _ => Some(ty),
+                }
+            }
+            Expr::UnOp(expr) => {
+                let inner = self.check_expr(&expr.inner);
+                let operand = inner.dereference();
+
+                let valid = match expr.op {
+                    UnOp::Neg => {
+                        is_numeric(operand)
+                            && matches!(
+                                operand.as_scalar(),
+                                Some(ScalarType::I32 | ScalarType::F32)
+                            )
+                    }
+                    UnOp::Not => {
+                        (operand.is_scalar() || operand.is_vector())
+                            && operand.as_scalar() == Some(ScalarType::Bool)
+                    }
+                    UnOp::BitNot => {
+                        (operand.is_scalar() || operand.is_vector()) && operand.is_integer()
+                    }
+                    UnOp::AddressOf => matches!(inner, DataType::Ref(_)),
+                    UnOp::Deref => matches!(inner, DataType::Ptr(_)),
+                };
+
+                if !valid {
+                    self.error(format!(
+                        "operator `{}` cannot be applied to `{}` of type `{inner}`",
+                        expr.op, expr.inner
+                    ));
+                    return None;
+                }
+
+                Some(expr.op.type_eval(&inner))
+            }
+            Expr::BinOp(expr) => {
+                let left = self.check_expr(&expr.left);
+                let right = self.check_expr(&expr.right);
+                let (l, r) = (left.dereference(), right.dereference());
+
+                let is_scalar_or_vector = |ty: &DataType| ty.is_scalar() || ty.is_vector();
+
+                use crate::BinOp::*;
+                let ty = match expr.op {
+                    Plus | Minus | Times | Divide | Mod
+                        if is_numeric(l)
+                            && is_numeric(r)
+                            && l.as_scalar() == r.as_scalar()
+                            && (l == r || l.is_scalar() || r.is_scalar()) =>
+                    {
+                        Some(if l.is_vector() { l.clone() } else { r.clone() })
+                    }
+                    BitAnd | BitOr
+                        if l == r
+                            && is_scalar_or_vector(l)
+                            && (l.is_integer() || l.as_scalar() == Some(ScalarType::Bool)) =>
+                    {
+                        Some(l.clone())
+                    }
+                    BitXOr if l == r && is_scalar_or_vector(l) && l.is_integer() => Some(l.clone()),
+                    LShift | RShift
+                        if is_scalar_or_vector(l)
+                            && l.is_integer()
+                            && *r == l.map(ScalarType::U32) =>
+                    {
+                        Some(l.clone())
+                    }
+                    LogAnd | LogOr if *l == DataType::Scalar(ScalarType::Bool) && l == r => {
+                        Some(l.clone())
+                    }
+                    Equal | NotEqual if l == r && is_scalar_or_vector(l) => {
+                        Some(l.map(ScalarType::Bool))
+                    }
+                    Less | LessEqual | Greater | GreaterEqual if l == r && is_numeric(l) => {
+                        Some(l.map(ScalarType::Bool))
+                    }
+                    _ => None,
+                };
+
+                if ty.is_none() {
+                    self.error(format!(
+                        "operator `{}` cannot be applied to operands of type `{left}` and \
+                         `{right}`",
+                        expr.op
+                    ));
+                }
+
+                ty
+            }
+            Expr::FnCall(expr) => {
+                for arg in &expr.args {
+                    self.check_expr(arg);
+                }
+
+                match self.check_call(&expr.ident, &expr.args) {
+                    Ok(Some(ty)) => Some(ty),
+                    Ok(None) => {
+                        self.error(format!(
+                            "function `{}` does not return a value, so cannot be used in an \
+                             expression",
+                            expr.ident
+                        ));
+                        None
+                    }
+                    Err(()) => None,
+                }
+            }
+        }
+    }
+
+    fn check_type_cons(&mut self, ty: &DataType, args: &[DataType]) {
+        // Every constructible type has a zero value constructor
+        if args.is_empty() {
+            if !is_storable(ty) {
+                self.error(format!("type `{ty}` cannot be constructed"));
+            }
+            return;
+        }
+
+        let args = args.iter().map(DataType::dereference).collect::<Vec<_>>();
+
+        let valid = match ty {
+            DataType::Scalar(_) => args.len() == 1 && args[0].is_scalar(),
+            DataType::Vector(n, t) => match args.as_slice() {
+                // Splat constructor
+                [arg] if arg.is_scalar() => arg.as_scalar() == Some(*t),
+                // Conversion constructor
+                [DataType::Vector(m, _)] => m == n,
+                args => {
+                    let components = args
+                        .iter()
+                        .map(|arg| match arg {
+                            DataType::Scalar(s) if s == t => Some(1),
+                            DataType::Vector(m, s) if s == t => Some(*m as u32),
+                            _ => None,
+                        })
+                        .sum::<Option<u32>>();
+
+                    components == Some(*n as u32)
+                }
+            },
+            DataType::Array(element, size) => {
+                matches!(size, Some(size) if args.len() == *size as usize)
+                    && args.iter().all(|arg| *arg == element.as_ref())
+            }
+            DataType::Struct(decl) => {
+                args.len() == decl.members.len()
+                    && args
+                        .iter()
+                        .zip(&decl.members)
+                        .all(|(arg, member)| *arg == &member.data_type)
+            }
+            DataType::Ptr(_) | DataType::Ref(_) => false,
+        };
+
+        if !valid {
+            let args = args
+                .iter()
+                .map(|it| it.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.error(format!(
+                "invalid arguments for `{ty}` constructor: ({args})"
+            ));
+        }
+    }
+
+    /// Checks that `postfix` can be applied to a value of type `ty`, returning the resulting type.
+    fn check_postfix(&mut self, ty: &DataType, postfix: &Postfix) -> Option<DataType> {
+        match postfix {
+            Postfix::Index(index) => {
+                if !matches!(
+                    index.data_type.dereference(),
+                    DataType::Scalar(ScalarType::I32 | ScalarType::U32)
+                ) {
+                    self.error(format!(
+                        "index `{index}` must be an integer scalar, got `{}`",
+                        index.data_type
+                    ));
+                }
+
+                match ty {
+                    DataType::Vector(_, t) => Some(DataType::Scalar(*t)),
+                    DataType::Array(element, _) => Some(element.as_ref().clone()),
+                    _ => {
+                        self.error(format!("cannot index into a value of type `{ty}`"));
+                        None
+                    }
+                }
+            }
+            Postfix::Member(member) => match ty {
+                DataType::Struct(decl) => match decl.member_type(member) {
+                    Some(ty) => Some(ty.clone()),
+                    None => {
+                        self.error(format!(
+                            "struct `{}` has no member named `{member}`",
+                            decl.name
+                        ));
+                        None
+                    }
+                },
+                DataType::Vector(n, t) => {
+                    let valid = (1..=4).contains(&member.len())
+                        && ["xyzw", "rgba"].iter().any(|set| {
+                            member
+                                .chars()
+                                .all(|c| matches!(set.find(c), Some(i) if i < *n as usize))
+                        });
+
+                    if !valid {
+                        self.error(format!("invalid swizzle `{member}` for type `{ty}`"));
+                        return None;
+                    }
+
+                    if member.len() == 1 {
+                        Some(DataType::Scalar(*t))
+                    } else {
+                        Some(DataType::Vector(member.len() as u8, *t))
+                    }
+                }
+                _ => {
+                    self.error(format!(
+                        "cannot access member `{member}` of a value of type `{ty}`"
+                    ));
+                    None
+                }
+            },
+        }
+    }
+
+    /// Checks a call to a function, returning its return type.
+    ///
+    /// The arguments must already have been checked. `Err` is returned if an error was found.
+    fn check_call(&mut self, ident: &str, args: &[ExprNode]) -> Result<Option<DataType>, ()> {
+        if let Some(sig) = self.fns.get(ident) {
+            let is_entry_point = sig.is_entry_point;
+            let output = sig.output.clone();
+            let mismatch = sig.inputs.len() != args.len()
+                || sig.inputs.iter().zip(args).any(|(param, arg)| match param {
+                    DataType::Ptr(_) => *param != arg.data_type,
+                    _ => !same(param, &arg.data_type),
+                });
+
+            if is_entry_point {
+                self.error(format!("entry point `{ident}` cannot be called"));
+                return Err(());
+            }
+
+            if mismatch {
+                let args = args
+                    .iter()
+                    .map(|it| it.data_type.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                self.error(format!("invalid arguments for `{ident}`: ({args})"));
+                return Err(());
+            }
+
+            return Ok(output);
+        }
+
+        let extension = BuiltinFn::from_str(ident)
+            .ok()
+            .and_then(|builtin| builtin.extension());
+
+        if let Some(extension) = extension {
+            if !self.enables.iter().any(|it| it == extension) {
+                self.error(format!("builtin `{ident}` requires `enable {extension};`"));
+                return Err(());
+            }
+        }
+
+        match BuiltinFn::from_str(ident) {
+            Ok(builtin) if builtin.is_void() => Ok(None),
+            Ok(builtin) => match builtin.return_type(args.iter().map(|it| &it.data_type)) {
+                Some(ty) => Ok(Some(ty)),
+                None => {
+                    self.error(format!("invalid arguments for builtin `{ident}`"));
+                    Err(())
+                }
+            },
+            Err(_) => {
+                self.error(format!("call to undeclared function `{ident}`"));
+                Err(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, TypeError};
+    use crate::types::MemoryViewType;
+    use crate::*;
+
+    const BOOL: DataType = DataType::Scalar(ScalarType::Bool);
+    const I32: DataType = DataType::Scalar(ScalarType::I32);
+    const U32: DataType = DataType::Scalar(ScalarType::U32);
+    const F32: DataType = DataType::Scalar(ScalarType::F32);
+
+    fn node(data_type: DataType, expr: impl Into<Expr>) -> ExprNode {
+        ExprNode {
+            data_type,
+            expr: expr.into(),
+        }
+    }
+
+    fn lit(lit: Lit) -> ExprNode {
+        lit.into()
+    }
+
+    fn var(name: &str, data_type: DataType) -> ExprNode {
+        VarExpr::new(name).into_node(data_type)
+    }
+
+    fn reference(inner: DataType) -> DataType {
+        DataType::Ref(MemoryViewType::new(inner, StorageClass::Function))
+    }
+
+    fn pointer(inner: DataType, storage_class: StorageClass) -> DataType {
+        DataType::Ptr(MemoryViewType::new(inner, storage_class))
+    }
+
+    fn let_(name: &str, initializer: ExprNode) -> Statement {
+        LetDeclStatement::new(name, initializer).into()
+    }
+
+    fn var_(name: &str, data_type: Option<DataType>, initializer: Option<ExprNode>) -> Statement {
+        VarDeclStatement::new(name, data_type, initializer).into()
+    }
+
+    fn assign(name: &str, data_type: DataType, op: AssignmentOp, rhs: ExprNode) -> Statement {
+        AssignmentStatement::new(AssignmentLhs::name(name, data_type), op, rhs).into()
+    }
+
+    fn func(
+        name: &str,
+        inputs: Vec<FnInput>,
+        output: Option<DataType>,
+        body: Vec<Statement>,
+    ) -> FnDecl {
+        FnDecl {
+            attrs: vec![],
+            name: name.to_owned(),
+            inputs,
+            output: output.map(FnOutput::new),
+            body,
+        }
+    }
+
+    fn entry_point(attrs: Vec<FnAttr>, output: Option<DataType>) -> FnDecl {
+        FnDecl {
+            attrs,
+            ..func("main", vec![], output, vec![])
+        }
+    }
+
+    fn module(functions: Vec<FnDecl>) -> Module {
+        Module {
+            functions,
+            ..Default::default()
+        }
+    }
+
+    /// Checks a function containing `body`, with no parameters or return value.
+    fn check_body(body: Vec<Statement>) -> Result<(), Vec<TypeError>> {
+        check(&module(vec![func("f", vec![], None, body)]))
+    }
+
+    #[track_caller]
+    fn assert_error(result: Result<(), Vec<TypeError>>, message: &str) {
+        let errors = result.expect_err("expected a type error");
+        assert!(
+            errors.iter().any(|e| e.message.contains(message)),
+            "expected an error containing `{message}`, got {errors:?}"
+        );
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(check_body(vec![let_("x", lit(Lit::I32(1)))]), Ok(()));
+        assert_eq!(
+            check_body(vec![let_("x", node(U32, Lit::AbstractInt(1)))]),
+            Ok(())
+        );
+
+        assert_error(
+            check_body(vec![let_("x", node(U32, Lit::AbstractInt(-1)))]),
+            "is annotated with type `u32` but has type `i32`",
+        );
+        assert_error(
+            check_body(vec![let_("x", node(F32, Lit::I32(1)))]),
+            "is annotated with type `f32` but has type `i32`",
+        );
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(
+            check_body(vec![
+                let_("x", lit(Lit::I32(1))),
+                let_("y", var("x", I32)),
+                var_("z", Some(I32), None),
+                let_("w", var("z", reference(I32))),
+            ]),
+            Ok(())
+        );
+
+        assert_error(
+            check_body(vec![let_("x", var("y", I32))]),
+            "use of undeclared identifier `y`",
+        );
+    }
+
+    #[test]
+    fn blocks_introduce_scopes() {
+        assert_error(
+            check_body(vec![
+                Statement::Compound(vec![let_("x", lit(Lit::I32(1)))]),
+                let_("y", var("x", I32)),
+            ]),
+            "use of undeclared identifier `x`",
+        );
+    }
+
+    #[test]
+    fn vector_constructors() {
+        let vec2f = DataType::Vector(2, ScalarType::F32);
+        let vec3f = DataType::Vector(3, ScalarType::F32);
+        let vec2i = DataType::Vector(2, ScalarType::I32);
+
+        let cons = |ty: &DataType, args: Vec<ExprNode>| {
+            check_body(vec![let_("x", TypeConsExpr::new(ty.clone(), args).into())])
+        };
+
+        assert_eq!(cons(&vec3f, vec![]), Ok(()));
+        assert_eq!(cons(&vec3f, vec![lit(Lit::F32(1.0))]), Ok(()));
+        assert_eq!(
+            cons(
+                &vec3f,
+                vec![lit(Lit::F32(1.0)), lit(Lit::F32(2.0)), lit(Lit::F32(3.0))]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            cons(
+                &vec3f,
+                vec![
+                    TypeConsExpr::new(vec2f.clone(), vec![]).into(),
+                    lit(Lit::F32(1.0))
+                ]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            cons(&vec2f, vec![TypeConsExpr::new(vec2i, vec![]).into()]),
+            Ok(())
+        );
+
+        assert_error(
+            cons(&vec3f, vec![lit(Lit::F32(1.0)), lit(Lit::F32(2.0))]),
+            "invalid arguments for `vec3<f32>` constructor",
+        );
+        assert_error(
+            cons(&vec2f, vec![lit(Lit::I32(1))]),
+            "invalid arguments for `vec2<f32>` constructor",
+        );
+    }
+
+    #[test]
+    fn array_and_struct_constructors() {
+        let array = DataType::array(I32, 2u32);
+        let decl = StructDecl::new(
+            "S",
+            vec![
+                StructMember::new(vec![], "a", I32),
+                StructMember::new(vec![], "b", F32),
+            ],
+        );
+        let s = DataType::Struct(decl);
+
+        let cons = |ty: &DataType, args: Vec<ExprNode>| {
+            check_body(vec![let_("x", TypeConsExpr::new(ty.clone(), args).into())])
+        };
+
+        assert_eq!(
+            cons(&array, vec![lit(Lit::I32(1)), lit(Lit::I32(2))]),
+            Ok(())
+        );
+        assert_eq!(cons(&s, vec![lit(Lit::I32(1)), lit(Lit::F32(2.0))]), Ok(()));
+
+        assert_error(
+            cons(&array, vec![lit(Lit::I32(1))]),
+            "invalid arguments for `array<i32, 2>` constructor",
+        );
+        assert_error(
+            cons(&s, vec![lit(Lit::F32(2.0)), lit(Lit::I32(1))]),
+            "invalid arguments for `S` constructor",
+        );
+    }
+
+    #[test]
+    fn index_expressions() {
+        let vec2i = DataType::Vector(2, ScalarType::I32);
+        let index = |index: ExprNode| {
+            check_body(vec![
+                let_("v", TypeConsExpr::new(vec2i.clone(), vec![]).into()),
+                let_(
+                    "x",
+                    node(
+                        I32,
+                        PostfixExpr::new(var("v", vec2i.clone()), Postfix::index(index)),
+                    ),
+                ),
+            ])
+        };
+
+        assert_eq!(index(lit(Lit::I32(0))), Ok(()));
+        assert_eq!(index(lit(Lit::U32(0))), Ok(()));
+        assert_error(index(lit(Lit::F32(0.0))), "must be an integer scalar");
+
+        assert_error(
+            check_body(vec![
+                let_("v", lit(Lit::I32(1))),
+                let_(
+                    "x",
+                    node(
+                        I32,
+                        PostfixExpr::new(var("v", I32), Postfix::index(lit(Lit::I32(0)))),
+                    ),
+                ),
+            ]),
+            "cannot index into a value of type `i32`",
+        );
+    }
+
+    #[test]
+    fn member_expressions() {
+        let decl = StructDecl::new(
+            "S",
+            vec![
+                StructMember::new(vec![], "a", I32),
+                StructMember::new(vec![], "b", F32),
+            ],
+        );
+        let s = DataType::Struct(decl);
+        let vec2i = DataType::Vector(2, ScalarType::I32);
+
+        let member = |ty: &DataType, member: &str, member_type: DataType| {
+            check_body(vec![
+                let_("v", TypeConsExpr::new(ty.clone(), vec![]).into()),
+                let_(
+                    "x",
+                    node(
+                        member_type,
+                        PostfixExpr::new(var("v", ty.clone()), Postfix::member(member)),
+                    ),
+                ),
+            ])
+        };
+
+        assert_eq!(member(&s, "b", F32), Ok(()));
+        assert_eq!(member(&vec2i, "y", I32), Ok(()));
+        assert_eq!(
+            member(&vec2i, "yxy", DataType::Vector(3, ScalarType::I32)),
+            Ok(())
+        );
+
+        assert_error(member(&s, "c", F32), "struct `S` has no member named `c`");
+        assert_error(member(&s, "a", F32), "is annotated with type `f32`");
+        assert_error(member(&vec2i, "z", I32), "invalid swizzle `z`");
+        assert_error(member(&vec2i, "xg", vec2i.clone()), "invalid swizzle `xg`");
+    }
+
+    #[test]
+    fn unary_operators() {
+        let unop = |op: UnOp, operand: ExprNode| {
+            let ty = operand.data_type.clone();
+            check_body(vec![let_("x", node(ty, UnOpExpr::new(op, operand)))])
+        };
+
+        assert_eq!(unop(UnOp::Neg, lit(Lit::I32(1))), Ok(()));
+        assert_eq!(unop(UnOp::Neg, lit(Lit::F32(1.0))), Ok(()));
+        assert_eq!(unop(UnOp::Not, lit(Lit::Bool(true))), Ok(()));
+        assert_eq!(unop(UnOp::BitNot, lit(Lit::U32(1))), Ok(()));
+
+        assert_error(
+            unop(UnOp::Neg, lit(Lit::U32(1))),
+            "operator `-` cannot be applied",
+        );
+        assert_error(
+            unop(UnOp::Not, lit(Lit::I32(1))),
+            "operator `!` cannot be applied",
+        );
+        assert_error(
+            unop(UnOp::BitNot, lit(Lit::F32(1.0))),
+            "operator `~` cannot be applied",
+        );
+        assert_error(
+            unop(UnOp::AddressOf, lit(Lit::I32(1))),
+            "operator `&` cannot be applied",
+        );
+        assert_error(
+            unop(UnOp::Deref, lit(Lit::I32(1))),
+            "operator `*` cannot be applied",
+        );
+    }
+
+    #[test]
+    fn pointers() {
+        let ptr = pointer(I32, StorageClass::Function);
+
+        assert_eq!(
+            check_body(vec![
+                var_("x", Some(I32), None),
+                let_(
+                    "p",
+                    node(
+                        ptr.clone(),
+                        UnOpExpr::new(UnOp::AddressOf, var("x", reference(I32)))
+                    ),
+                ),
+                let_(
+                    "y",
+                    node(
+                        reference(I32),
+                        UnOpExpr::new(UnOp::Deref, var("p", ptr.clone()))
+                    ),
+                ),
+            ]),
+            Ok(())
+        );
+
+        // Assigning through a pointer parameter
+        let lhs = LhsExprNode {
+            data_type: I32,
+            expr: LhsExpr::Deref(Box::new(LhsExprNode::name("p".to_owned(), ptr.clone()))),
+        };
+
+        let f = func(
+            "f",
+            vec![FnInput::new("p", ptr)],
+            None,
+            vec![
+                AssignmentStatement::new(lhs.into(), AssignmentOp::Simple, lit(Lit::I32(1))).into(),
+            ],
+        );
+
+        assert_eq!(check(&module(vec![f])), Ok(()));
+
+        let f = func(
+            "f",
+            vec![FnInput::new("p", pointer(I32, StorageClass::WorkGroup))],
+            None,
+            vec![],
+        );
+
+        assert_error(
+            check(&module(vec![f])),
+            "must be in the `function` or `private` address space",
+        );
+    }
+
+    #[test]
+    fn binary_operators() {
+        let vec2f = DataType::Vector(2, ScalarType::F32);
+        let vec2i = DataType::Vector(2, ScalarType::I32);
+
+        let binop = |op: BinOp, ty: DataType, left: ExprNode, right: ExprNode| {
+            check_body(vec![let_("x", node(ty, BinOpExpr::new(op, left, right)))])
+        };
+
+        assert_eq!(
+            binop(BinOp::Plus, I32, lit(Lit::I32(1)), lit(Lit::I32(2))),
+            Ok(())
+        );
+        assert_eq!(
+            binop(
+                BinOp::Times,
+                vec2f.clone(),
+                TypeConsExpr::new(vec2f.clone(), vec![]).into(),
+                lit(Lit::F32(2.0))
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            binop(BinOp::LShift, I32, lit(Lit::I32(1)), lit(Lit::U32(2))),
+            Ok(())
+        );
+        assert_eq!(
+            binop(BinOp::Less, BOOL, lit(Lit::F32(1.0)), lit(Lit::F32(2.0))),
+            Ok(())
+        );
+        assert_eq!(
+            binop(
+                BinOp::LogAnd,
+                BOOL,
+                lit(Lit::Bool(true)),
+                lit(Lit::Bool(false))
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            binop(
+                BinOp::BitAnd,
+                BOOL,
+                lit(Lit::Bool(true)),
+                lit(Lit::Bool(false))
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            binop(
+                BinOp::Equal,
+                DataType::Vector(2, ScalarType::Bool),
+                TypeConsExpr::new(vec2i.clone(), vec![]).into(),
+                TypeConsExpr::new(vec2i, vec![]).into()
+            ),
+            Ok(())
+        );
+
+        assert_error(
+            binop(BinOp::Plus, I32, lit(Lit::I32(1)), lit(Lit::U32(2))),
+            "operator `+` cannot be applied to operands of type `i32` and `u32`",
+        );
+        assert_error(
+            binop(BinOp::LShift, I32, lit(Lit::I32(1)), lit(Lit::I32(2))),
+            "operator `<<` cannot be applied",
+        );
+        assert_error(
+            binop(
+                BinOp::Less,
+                BOOL,
+                lit(Lit::Bool(true)),
+                lit(Lit::Bool(false)),
+            ),
+            "operator `<` cannot be applied",
+        );
+        assert_error(
+            binop(BinOp::LogAnd, BOOL, lit(Lit::I32(1)), lit(Lit::I32(1))),
+            "operator `&&` cannot be applied",
+        );
+        assert_error(
+            binop(BinOp::BitXOr, F32, lit(Lit::F32(1.0)), lit(Lit::F32(1.0))),
+            "operator `^` cannot be applied",
+        );
+        assert_error(
+            binop(BinOp::Plus, F32, lit(Lit::I32(1)), lit(Lit::I32(2))),
+            "is annotated with type `f32` but has type `i32`",
+        );
+    }
+
+    #[test]
+    fn function_calls() {
+        let g = func(
+            "g",
+            vec![FnInput::new("a", I32)],
+            Some(I32),
+            vec![ReturnStatement::new(var("a", I32)).into()],
+        );
+
+        let call = |args: Vec<ExprNode>| {
+            check(&module(vec![
+                func(
+                    "g",
+                    vec![FnInput::new("a", I32)],
+                    Some(I32),
+                    vec![ReturnStatement::new(var("a", I32)).into()],
+                ),
+                func("h", vec![], None, vec![]),
+                func(
+                    "f",
+                    vec![],
+                    None,
+                    vec![let_("x", FnCallExpr::new("g", args).into_node(I32))],
+                ),
+            ]))
+        };
+
+        assert_eq!(check(&module(vec![g])), Ok(()));
+        assert_eq!(call(vec![lit(Lit::I32(1))]), Ok(()));
+        assert_error(
+            call(vec![lit(Lit::F32(1.0))]),
+            "invalid arguments for `g`: (f32)",
+        );
+        assert_error(call(vec![]), "invalid arguments for `g`: ()");
+
+        assert_error(
+            check(&module(vec![
+                func("h", vec![], None, vec![]),
+                func(
+                    "f",
+                    vec![],
+                    None,
+                    vec![let_("x", FnCallExpr::new("h", vec![]).into_node(I32))],
+                ),
+            ])),
+            "function `h` does not return a value",
+        );
+
+        assert_error(
+            check_body(vec![let_("x", FnCallExpr::new("g", vec![]).into_node(I32))]),
+            "call to undeclared function `g`",
+        );
+
+        assert_error(
+            check(&module(vec![
+                func("g", vec![], None, vec![]),
+                func("g", vec![], None, vec![]),
+            ])),
+            "function `g` is declared more than once",
+        );
+    }
+
+    #[test]
+    fn builtin_calls() {
+        let call = |ident: &str, args: Vec<ExprNode>, ty: DataType| {
+            vec![let_("x", FnCallExpr::new(ident, args).into_node(ty))]
+        };
+
+        assert_eq!(
+            check_body(call("abs", vec![lit(Lit::F32(-1.0))], F32)),
+            Ok(())
+        );
+        assert_eq!(
+            check_body(vec![FnCallStatement::new(
+                "workgroupBarrier".to_owned(),
+                vec![]
+            )
+            .into()]),
+            Ok(())
+        );
+
+        assert_error(
+            check_body(call("dot", vec![], F32)),
+            "invalid arguments for builtin `dot`",
+        );
+
+        let subgroup_add = || call("subgroupAdd", vec![lit(Lit::F32(1.0))], F32);
+        assert_error(
+            check_body(subgroup_add()),
+            "builtin `subgroupAdd` requires `enable subgroups;`",
+        );
+
+        let mut module = module(vec![func("f", vec![], None, subgroup_add())]);
+        module.enables.push("subgroups".to_owned());
+        assert_eq!(check(&module), Ok(()));
+    }
+
+    #[test]
+    fn entry_points() {
+        let compute = vec![
+            FnAttr::Stage(ShaderStage::Compute),
+            FnAttr::WorkgroupSize(1),
+        ];
+
+        assert_eq!(check(&module(vec![entry_point(compute, None)])), Ok(()));
+
+        assert_error(
+            check(&module(vec![entry_point(
+                vec![FnAttr::Stage(ShaderStage::Compute)],
+                None,
+            )])),
+            "compute entry point must have a `workgroup_size` attribute",
+        );
+        assert_error(
+            check(&module(vec![entry_point(
+                vec![FnAttr::Stage(ShaderStage::Vertex)],
+                None,
+            )])),
+            "vertex entry point must return a position",
+        );
+        assert_error(
+            check(&module(vec![entry_point(
+                vec![
+                    FnAttr::Stage(ShaderStage::Fragment),
+                    FnAttr::WorkgroupSize(1),
+                ],
+                None,
+            )])),
+            "`workgroup_size` can only be applied to compute entry points",
+        );
+        assert_error(
+            check(&module(vec![entry_point(
+                vec![FnAttr::Stage(ShaderStage::Fragment)],
+                Some(F32),
+            )])),
+            "entry point return type must have io attributes",
+        );
+
+        let compute = vec![
+            FnAttr::Stage(ShaderStage::Compute),
+            FnAttr::WorkgroupSize(1),
+        ];
+
+        assert_error(
+            check(&module(vec![
+                entry_point(compute, None),
+                func(
+                    "f",
+                    vec![],
+                    None,
+                    vec![FnCallStatement::new("main".to_owned(), vec![]).into()],
+                ),
+            ])),
+            "entry point `main` cannot be called",
+        );
+    }
+
+    #[test]
+    fn variable_declarations() {
+        assert_eq!(
+            check_body(vec![var_("x", Some(I32), Some(lit(Lit::I32(1))))]),
+            Ok(())
+        );
+        assert_eq!(
+            check_body(vec![var_("x", None, Some(lit(Lit::F32(1.0))))]),
+            Ok(())
+        );
+
+        assert_error(
+            check_body(vec![var_("x", Some(I32), Some(lit(Lit::F32(1.0))))]),
+            "variable `x` has type `i32` but its initializer has type `f32`",
+        );
+        assert_error(
+            check_body(vec![var_("x", None, None)]),
+            "variable `x` must have a type or an initializer",
+        );
+    }
+
+    #[test]
+    fn assignments() {
+        let body = |stmt: Statement| {
+            check_body(vec![
+                var_("x", Some(I32), None),
+                let_("y", lit(Lit::I32(1))),
+                stmt,
+            ])
+        };
+
+        assert_eq!(
+            body(assign("x", I32, AssignmentOp::Simple, lit(Lit::I32(1)))),
+            Ok(())
+        );
+        assert_eq!(
+            body(assign("x", I32, AssignmentOp::Plus, lit(Lit::I32(1)))),
+            Ok(())
+        );
+        assert_eq!(
+            body(assign("x", I32, AssignmentOp::Xor, lit(Lit::I32(1)))),
+            Ok(())
+        );
+        assert_eq!(
+            body(
+                AssignmentStatement::new(
+                    AssignmentLhs::Phony,
+                    AssignmentOp::Simple,
+                    lit(Lit::F32(1.0))
+                )
+                .into()
+            ),
+            Ok(())
+        );
+
+        assert_error(
+            body(assign("x", I32, AssignmentOp::Simple, lit(Lit::F32(1.0)))),
+            "cannot assign a value of type `f32` to `x` of type `i32` with `=`",
+        );
+        assert_error(
+            body(assign("x", I32, AssignmentOp::Plus, lit(Lit::U32(1)))),
+            "cannot assign a value of type `u32` to `x` of type `i32` with `+=`",
+        );
+        assert_error(
+            body(assign("y", I32, AssignmentOp::Simple, lit(Lit::I32(1)))),
+            "cannot assign to `y` since it is not a variable",
+        );
+        assert_error(
+            body(assign("z", I32, AssignmentOp::Simple, lit(Lit::I32(1)))),
+            "use of undeclared identifier `z`",
+        );
+        assert_error(
+            body(assign("x", F32, AssignmentOp::Simple, lit(Lit::F32(1.0)))),
+            "assignment target `x` is annotated with type `f32` but has type `i32`",
+        );
+    }
+
+    #[test]
+    fn if_statements() {
+        let if_ = |condition: ExprNode, else_: Option<Else>| {
+            check_body(vec![IfStatement::new(lit(Lit::Bool(true)), vec![])
+                .with_else(Else::If(
+                    IfStatement::new(condition, vec![]).with_else(else_),
+                ))
+                .into()])
+        };
+
+        assert_eq!(if_(lit(Lit::Bool(false)), None), Ok(()));
+        assert_eq!(if_(lit(Lit::Bool(false)), Some(Else::Else(vec![]))), Ok(()));
+
+        assert_error(
+            if_(lit(Lit::I32(1)), None),
+            "condition must be a `bool`, got `i32`",
+        );
+        assert_error(
+            if_(
+                lit(Lit::Bool(false)),
+                Some(Else::Else(vec![let_("x", var("y", I32))])),
+            ),
+            "use of undeclared identifier `y`",
+        );
+    }
+
+    #[test]
+    fn return_statements() {
+        let g = |output: Option<DataType>, stmt: ReturnStatement| {
+            check(&module(vec![func("g", vec![], output, vec![stmt.into()])]))
+        };
+
+        assert_eq!(g(Some(I32), ReturnStatement::new(lit(Lit::I32(1)))), Ok(()));
+        assert_eq!(g(None, ReturnStatement::none()), Ok(()));
+
+        assert_error(
+            g(Some(I32), ReturnStatement::new(lit(Lit::F32(1.0)))),
+            "returned value has type `f32` but function returns `i32`",
+        );
+        assert_error(
+            g(Some(I32), ReturnStatement::none()),
+            "missing return value of type `i32`",
+        );
+        assert_error(
+            g(None, ReturnStatement::new(lit(Lit::I32(1)))),
+            "cannot return a value from a void function",
+        );
+    }
+
+    #[test]
+    fn loops() {
+        assert_eq!(
+            check_body(vec![LoopStatement::new(vec![
+                Statement::Continue,
+                Statement::Break
+            ])
+            .into()]),
+            Ok(())
+        );
+
+        assert_error(
+            check_body(vec![Statement::Break]),
+            "`break` must be inside a loop or switch",
+        );
+        assert_error(
+            check_body(vec![Statement::Continue]),
+            "`continue` must be inside a loop",
+        );
+    }
+
+    #[test]
+    fn for_loops() {
+        let for_ = |condition: ExprNode, body: Vec<Statement>| {
+            let header = ForLoopHeader {
+                init: Some(ForLoopInit::VarDecl(VarDeclStatement::new(
+                    "i",
+                    Some(I32),
+                    Some(lit(Lit::I32(0))),
+                ))),
+                condition: Some(condition),
+                update: Some(ForLoopUpdate::Assignment(AssignmentStatement::new(
+                    AssignmentLhs::name("i", I32),
+                    AssignmentOp::Plus,
+                    lit(Lit::I32(1)),
+                ))),
+            };
+
+            vec![ForLoopStatement::new(header, body).into()]
+        };
+
+        let less = node(
+            BOOL,
+            BinOpExpr::new(BinOp::Less, var("i", reference(I32)), lit(Lit::I32(4))),
+        );
+
+        assert_eq!(
+            check_body(for_(less.clone(), vec![Statement::Continue])),
+            Ok(())
+        );
+
+        assert_error(
+            check_body(for_(var("i", reference(I32)), vec![])),
+            "condition must be a `bool`",
+        );
+
+        // The loop variable is only in scope inside the loop
+        let mut body = for_(less, vec![]);
+        body.push(let_("x", var("i", I32)));
+        assert_error(check_body(body), "use of undeclared identifier `i`");
+    }
+
+    #[test]
+    fn switch_statements() {
+        let switch = |selector: ExprNode, case: ExprNode, body: Vec<Statement>| {
+            let case = SwitchCase {
+                selector: case,
+                body,
+            };
+            check_body(vec![
+                SwitchStatement::new(selector, vec![case], vec![]).into()
+            ])
+        };
+
+        assert_eq!(
+            switch(
+                lit(Lit::I32(1)),
+                lit(Lit::I32(1)),
+                vec![Statement::Fallthrough]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            switch(lit(Lit::U32(1)), lit(Lit::U32(1)), vec![Statement::Break]),
+            Ok(())
+        );
+
+        assert_error(
+            switch(lit(Lit::F32(1.0)), lit(Lit::F32(1.0)), vec![]),
+            "switch selector must be an integer scalar, got `f32`",
+        );
+        assert_error(
+            switch(lit(Lit::I32(1)), lit(Lit::U32(1)), vec![]),
+            "case selector has type `u32` but switch selector has type `i32`",
+        );
+        assert_error(
+            switch(
+                lit(Lit::I32(1)),
+                lit(Lit::I32(1)),
+                vec![Statement::Continue],
+            ),
+            "`continue` must be inside a loop",
+        );
+        assert_error(
+            check_body(vec![Statement::Fallthrough]),
+            "`fallthrough` must be inside a switch case",
+        );
+    }
+
+    #[test]
+    fn function_call_statements() {
+        let call = |args: Vec<ExprNode>| {
+            check(&module(vec![
+                func("g", vec![FnInput::new("a", I32)], None, vec![]),
+                func(
+                    "f",
+                    vec![],
+                    None,
+                    vec![FnCallStatement::new("g".to_owned(), args).into()],
+                ),
+            ]))
+        };
+
+        assert_eq!(call(vec![lit(Lit::I32(1))]), Ok(()));
+        assert_error(
+            call(vec![lit(Lit::Bool(true))]),
+            "invalid arguments for `g`: (bool)",
+        );
+    }
+
+    #[test]
+    fn const_asserts() {
+        assert_eq!(
+            check_body(vec![ConstAssertStatement::new(lit(Lit::Bool(true))).into()]),
+            Ok(())
+        );
+        assert_error(
+            check_body(vec![ConstAssertStatement::new(lit(Lit::I32(1))).into()]),
+            "condition must be a `bool`, got `i32`",
+        );
+    }
+
+    #[test]
+    fn module_scope_declarations() {
+        let global =
+            |storage_class: StorageClass, bindings: bool, initializer: Option<ExprNode>| {
+                let mut module = module(vec![]);
+                module.vars.push(GlobalVarDecl {
+                    attrs: if bindings {
+                        vec![GlobalVarAttr::Group(0), GlobalVarAttr::Binding(0)]
+                    } else {
+                        vec![]
+                    },
+                    qualifier: Some(VarQualifier {
+                        storage_class,
+                        access_mode: None,
+                    }),
+                    name: "g".to_owned(),
+                    data_type: I32,
+                    initializer,
+                });
+                check(&module)
+            };
+
+        assert_eq!(
+            global(StorageClass::Private, false, Some(lit(Lit::I32(1)))),
+            Ok(())
+        );
+        assert_eq!(global(StorageClass::Uniform, true, None), Ok(()));
+        assert_eq!(global(StorageClass::WorkGroup, false, None), Ok(()));
+
+        assert_error(
+            global(StorageClass::Uniform, false, None),
+            "resource variable `g` must have `group` and `binding` attributes",
+        );
+        assert_error(
+            global(StorageClass::Private, true, None),
+            "cannot have `group` or `binding` attributes",
+        );
+        assert_error(
+            global(StorageClass::WorkGroup, false, Some(lit(Lit::I32(1)))),
+            "variable `g` in the `workgroup` address space cannot have an initializer",
+        );
+        assert_error(
+            global(StorageClass::Private, false, Some(lit(Lit::F32(1.0)))),
+            "variable `g` has type `i32` but its initializer has type `f32`",
+        );
+
+        let constant = |initializer: ExprNode| {
+            let mut module = module(vec![]);
+            module.consts.push(GlobalConstDecl {
+                name: "c".to_owned(),
+                data_type: I32,
+                initializer,
+            });
+            check(&module)
+        };
+
+        assert_eq!(constant(lit(Lit::I32(1))), Ok(()));
+        assert_error(
+            constant(lit(Lit::Bool(true))),
+            "constant `c` has type `i32` but its initializer has type `bool`",
+        );
+
+        let mut module = module(vec![]);
+        module.structs.push(StructDecl::new(
+            "S",
+            vec![StructMember::new(
+                vec![],
+                "p",
+                pointer(I32, StorageClass::Private),
+            )],
+        ));
+        assert_error(
+            check(&module),
+            "member `p` of struct `S` has non-storable type",
+        );
+    }
+
+    #[test]
+    fn read_only_variables() {
+        let mut module = module(vec![func(
+            "f",
+            vec![],
+            None,
+            vec![assign("u", I32, AssignmentOp::Simple, lit(Lit::I32(1)))],
+        )]);
+
+        module.vars.push(GlobalVarDecl {
+            attrs: vec![GlobalVarAttr::Group(0), GlobalVarAttr::Binding(0)],
+            qualifier: Some(VarQualifier {
+                storage_class: StorageClass::Uniform,
+                access_mode: None,
+            }),
+            name: "u".to_owned(),
+            data_type: I32,
+            initializer: None,
+        });
+
+        assert_error(check(&module), "cannot assign to `u` since it is read-only");
+    }
+}
@@ -6,6 +6,7 @@ use derive_more::Display;
 use crate::{AccessMode, StorageClass, StructDecl};
 
 #[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ScalarType {
     #[display(fmt = "bool")]
     Bool,
@@ -18,6 +19,7 @@ pub enum ScalarType {
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemoryViewType {
     pub inner: Rc<DataType>,
     pub storage_class: StorageClass,
@@ -52,6 +54,7 @@ impl Display for MemoryViewType {
 }
 
 #[derive(Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DataType {
     Scalar(ScalarType),
     Vector(u8, ScalarType),
@@ -122,6 +125,20 @@ impl DataType {
     pub fn is_integer(&self) -> bool {
         matches!(self.as_scalar(), Some(ScalarType::I32 | ScalarType::U32))
     }
+
+    /// Returns `true` if the data type is a runtime sized array, or a struct whose last member is
+    /// runtime sized.
+    pub fn is_runtime_sized(&self) -> bool {
+        match self {
+            DataType::Array(_, None) => true,
+            DataType::Struct(decl) => decl
+                .members
+                .last()
+                .map(|it| it.data_type.is_runtime_sized())
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
 }
 
 impl fmt::Debug for DataType {
@@ -0,0 +1,475 @@
+//! Traversals over the AST.
+//!
+//! [`Visitor`] and [`VisitorMut`] have a method for each kind of node, which by default calls the
+//! matching `walk_*` function to recurse into the children of that node. Passes override the
+//! methods for the nodes they are interested in, and call the `walk_*` function from the override
+//! if they also want to continue into the children.
+//!
+//! Struct types are not walked into from [`DataType::Struct`], since the same declaration is
+//! referenced from every expression of that type. Struct declarations are instead visited once,
+//! from the module.
+
+use std::rc::Rc;
+
+use crate::types::DataType;
+use crate::{
+    AssignmentLhs, AssignmentStatement, Else, Expr, ExprNode, FnDecl, ForLoopInit, ForLoopUpdate,
+    GlobalConstDecl, GlobalVarDecl, IfStatement, LhsExpr, LhsExprNode, Module, Postfix, Statement,
+    StructDecl, VarDeclStatement,
+};
+
+pub trait Visitor {
+    fn visit_module(&mut self, module: &Module) {
+        walk_module(self, module);
+    }
+
+    fn visit_struct_decl(&mut self, decl: &StructDecl) {
+        walk_struct_decl(self, decl);
+    }
+
+    fn visit_global_const(&mut self, decl: &GlobalConstDecl) {
+        walk_global_const(self, decl);
+    }
+
+    fn visit_global_var(&mut self, decl: &GlobalVarDecl) {
+        walk_global_var(self, decl);
+    }
+
+    fn visit_fn_decl(&mut self, decl: &FnDecl) {
+        walk_fn_decl(self, decl);
+    }
+
+    fn visit_block(&mut self, block: &[Statement]) {
+        walk_block(self, block);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Statement) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_lhs_expr(&mut self, node: &LhsExprNode) {
+        walk_lhs_expr(self, node);
+    }
+
+    fn visit_expr(&mut self, node: &ExprNode) {
+        walk_expr(self, node);
+    }
+
+    fn visit_data_type(&mut self, data_type: &DataType) {
+        walk_data_type(self, data_type);
+    }
+}
+
+pub fn walk_module<V: Visitor + ?Sized>(v: &mut V, module: &Module) {
+    for decl in &module.structs {
+        v.visit_struct_decl(decl);
+    }
+
+    for decl in &module.consts {
+        v.visit_global_const(decl);
+    }
+
+    for stmt in &module.const_asserts {
+        v.visit_expr(&stmt.condition);
+    }
+
+    for decl in &module.vars {
+        v.visit_global_var(decl);
+    }
+
+    for decl in &module.functions {
+        v.visit_fn_decl(decl);
+    }
+}
+
+pub fn walk_struct_decl<V: Visitor + ?Sized>(v: &mut V, decl: &StructDecl) {
+    for member in &decl.members {
+        v.visit_data_type(&member.data_type);
+    }
+}
+
+pub fn walk_global_const<V: Visitor + ?Sized>(v: &mut V, decl: &GlobalConstDecl) {
+    v.visit_data_type(&decl.data_type);
+    v.visit_expr(&decl.initializer);
+}
+
+pub fn walk_global_var<V: Visitor + ?Sized>(v: &mut V, decl: &GlobalVarDecl) {
+    v.visit_data_type(&decl.data_type);
+    if let Some(initializer) = &decl.initializer {
+        v.visit_expr(initializer);
+    }
+}
+
+pub fn walk_fn_decl<V: Visitor + ?Sized>(v: &mut V, decl: &FnDecl) {
+    for input in &decl.inputs {
+        v.visit_data_type(&input.data_type);
+    }
+
+    if let Some(output) = &decl.output {
+        v.visit_data_type(&output.data_type);
+    }
+
+    v.visit_block(&decl.body);
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(v: &mut V, block: &[Statement]) {
+    for stmt in block {
+        v.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(v: &mut V, stmt: &Statement) {
+    match stmt {
+        Statement::LetDecl(stmt) => v.visit_expr(&stmt.initializer),
+        Statement::VarDecl(stmt) => walk_var_decl(v, stmt),
+        Statement::Assignment(stmt) => walk_assignment(v, stmt),
+        Statement::Compound(block) => v.visit_block(block),
+        Statement::If(stmt) => walk_if(v, stmt),
+        Statement::Return(stmt) => {
+            if let Some(value) = &stmt.value {
+                v.visit_expr(value);
+            }
+        }
+        Statement::Loop(stmt) => v.visit_block(&stmt.body),
+        Statement::Break | Statement::Continue | Statement::Fallthrough => {}
+        Statement::Switch(stmt) => {
+            v.visit_expr(&stmt.selector);
+
+            for case in &stmt.cases {
+                v.visit_expr(&case.selector);
+                v.visit_block(&case.body);
+            }
+
+            v.visit_block(&stmt.default);
+        }
+        Statement::ForLoop(stmt) => {
+            if let Some(ForLoopInit::VarDecl(init)) = &stmt.header.init {
+                walk_var_decl(v, init);
+            }
+
+            if let Some(condition) = &stmt.header.condition {
+                v.visit_expr(condition);
+            }
+
+            if let Some(ForLoopUpdate::Assignment(update)) = &stmt.header.update {
+                walk_assignment(v, update);
+            }
+
+            v.visit_block(&stmt.body);
+        }
+        Statement::FnCall(stmt) => {
+            for arg in &stmt.args {
+                v.visit_expr(arg);
+            }
+        }
+        Statement::ConstAssert(stmt) => v.visit_expr(&stmt.condition),
+    }
+}
+
+pub fn walk_var_decl<V: Visitor + ?Sized>(v: &mut V, stmt: &VarDeclStatement) {
+    if let Some(data_type) = &stmt.data_type {
+        v.visit_data_type(data_type);
+    }
+
+    if let Some(initializer) = &stmt.initializer {
+        v.visit_expr(initializer);
+    }
+}
+
+pub fn walk_assignment<V: Visitor + ?Sized>(v: &mut V, stmt: &AssignmentStatement) {
+    if let AssignmentLhs::Expr(lhs) = &stmt.lhs {
+        v.visit_lhs_expr(lhs);
+    }
+
+    v.visit_expr(&stmt.rhs);
+}
+
+pub fn walk_if<V: Visitor + ?Sized>(v: &mut V, stmt: &IfStatement) {
+    v.visit_expr(&stmt.condition);
+    v.visit_block(&stmt.body);
+
+    match stmt.else_.as_deref() {
+        Some(Else::If(stmt)) => walk_if(v, stmt),
+        Some(Else::Else(block)) => v.visit_block(block),
+        None => {}
+    }
+}
+
+pub fn walk_lhs_expr<V: Visitor + ?Sized>(v: &mut V, node: &LhsExprNode) {
+    v.visit_data_type(&node.data_type);
+
+    match &node.expr {
+        LhsExpr::Ident(_) => {}
+        LhsExpr::Postfix(inner, postfix) => {
+            v.visit_lhs_expr(inner);
+            if let Postfix::Index(index) = postfix {
+                v.visit_expr(index);
+            }
+        }
+        LhsExpr::Deref(inner) | LhsExpr::AddressOf(inner) => v.visit_lhs_expr(inner),
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(v: &mut V, node: &ExprNode) {
+    v.visit_data_type(&node.data_type);
+
+    match &node.expr {
+        Expr::Lit(_) | Expr::Var(_) => {}
+        Expr::TypeCons(expr) => {
+            v.visit_data_type(&expr.data_type);
+            for arg in &expr.args {
+                v.visit_expr(arg);
+            }
+        }
+        Expr::Postfix(expr) => {
+            v.visit_expr(&expr.inner);
+            if let Postfix::Index(index) = &expr.postfix {
+                v.visit_expr(index);
+            }
+        }
+        Expr::UnOp(expr) => v.visit_expr(&expr.inner),
+        Expr::BinOp(expr) => {
+            v.visit_expr(&expr.left);
+            v.visit_expr(&expr.right);
+        }
+        Expr::FnCall(expr) => {
+            for arg in &expr.args {
+                v.visit_expr(arg);
+            }
+        }
+    }
+}
+
+pub fn walk_data_type<V: Visitor + ?Sized>(v: &mut V, data_type: &DataType) {
+    match data_type {
+        DataType::Scalar(_) | DataType::Vector(_, _) | DataType::Struct(_) => {}
+        DataType::Array(inner, _) => v.visit_data_type(inner),
+        DataType::Ptr(view) | DataType::Ref(view) => v.visit_data_type(&view.inner),
+    }
+}
+
+/// Like [`Visitor`], but with mutable access to the nodes so that passes can rewrite the AST in
+/// place.
+///
+/// Struct declarations are shared through an [`Rc`] and so are not visited. Nested types held in
+/// an [`Rc`] are made unique with [`Rc::make_mut`] before being visited.
+pub trait VisitorMut {
+    fn visit_module_mut(&mut self, module: &mut Module) {
+        walk_module_mut(self, module);
+    }
+
+    fn visit_global_const_mut(&mut self, decl: &mut GlobalConstDecl) {
+        walk_global_const_mut(self, decl);
+    }
+
+    fn visit_global_var_mut(&mut self, decl: &mut GlobalVarDecl) {
+        walk_global_var_mut(self, decl);
+    }
+
+    fn visit_fn_decl_mut(&mut self, decl: &mut FnDecl) {
+        walk_fn_decl_mut(self, decl);
+    }
+
+    /// Visits a list of statements. Overriding this allows a pass to insert or remove statements.
+    #[allow(clippy::ptr_arg)]
+    fn visit_block_mut(&mut self, block: &mut Vec<Statement>) {
+        walk_block_mut(self, block);
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Statement) {
+        walk_stmt_mut(self, stmt);
+    }
+
+    fn visit_lhs_expr_mut(&mut self, node: &mut LhsExprNode) {
+        walk_lhs_expr_mut(self, node);
+    }
+
+    fn visit_expr_mut(&mut self, node: &mut ExprNode) {
+        walk_expr_mut(self, node);
+    }
+
+    fn visit_data_type_mut(&mut self, data_type: &mut DataType) {
+        walk_data_type_mut(self, data_type);
+    }
+}
+
+pub fn walk_module_mut<V: VisitorMut + ?Sized>(v: &mut V, module: &mut Module) {
+    for decl in &mut module.consts {
+        v.visit_global_const_mut(decl);
+    }
+
+    for stmt in &mut module.const_asserts {
+        v.visit_expr_mut(&mut stmt.condition);
+    }
+
+    for decl in &mut module.vars {
+        v.visit_global_var_mut(decl);
+    }
+
+    for decl in &mut module.functions {
+        v.visit_fn_decl_mut(decl);
+    }
+}
+
+pub fn walk_global_const_mut<V: VisitorMut + ?Sized>(v: &mut V, decl: &mut GlobalConstDecl) {
+    v.visit_data_type_mut(&mut decl.data_type);
+    v.visit_expr_mut(&mut decl.initializer);
+}
+
+pub fn walk_global_var_mut<V: VisitorMut + ?Sized>(v: &mut V, decl: &mut GlobalVarDecl) {
+    v.visit_data_type_mut(&mut decl.data_type);
+    if let Some(initializer) = &mut decl.initializer {
+        v.visit_expr_mut(initializer);
+    }
+}
+
+pub fn walk_fn_decl_mut<V: VisitorMut + ?Sized>(v: &mut V, decl: &mut FnDecl) {
+    for input in &mut decl.inputs {
+        v.visit_data_type_mut(&mut input.data_type);
+    }
+
+    if let Some(output) = &mut decl.output {
+        v.visit_data_type_mut(&mut output.data_type);
+    }
+
+    v.visit_block_mut(&mut decl.body);
+}
+
+#[allow(clippy::ptr_arg)]
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(v: &mut V, block: &mut Vec<Statement>) {
+    for stmt in block {
+        v.visit_stmt_mut(stmt);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Statement) {
+    match stmt {
+        Statement::LetDecl(stmt) => v.visit_expr_mut(&mut stmt.initializer),
+        Statement::VarDecl(stmt) => walk_var_decl_mut(v, stmt),
+        Statement::Assignment(stmt) => walk_assignment_mut(v, stmt),
+        Statement::Compound(block) => v.visit_block_mut(block),
+        Statement::If(stmt) => walk_if_mut(v, stmt),
+        Statement::Return(stmt) => {
+            if let Some(value) = &mut stmt.value {
+                v.visit_expr_mut(value);
+            }
+        }
+        Statement::Loop(stmt) => v.visit_block_mut(&mut stmt.body),
+        Statement::Break | Statement::Continue | Statement::Fallthrough => {}
+        Statement::Switch(stmt) => {
+            v.visit_expr_mut(&mut stmt.selector);
+
+            for case in &mut stmt.cases {
+                v.visit_expr_mut(&mut case.selector);
+                v.visit_block_mut(&mut case.body);
+            }
+
+            v.visit_block_mut(&mut stmt.default);
+        }
+        Statement::ForLoop(stmt) => {
+            if let Some(ForLoopInit::VarDecl(init)) = &mut stmt.header.init {
+                walk_var_decl_mut(v, init);
+            }
+
+            if let Some(condition) = &mut stmt.header.condition {
+                v.visit_expr_mut(condition);
+            }
+
+            if let Some(ForLoopUpdate::Assignment(update)) = &mut stmt.header.update {
+                walk_assignment_mut(v, update);
+            }
+
+            v.visit_block_mut(&mut stmt.body);
+        }
+        Statement::FnCall(stmt) => {
+            for arg in &mut stmt.args {
+                v.visit_expr_mut(arg);
+            }
+        }
+        Statement::ConstAssert(stmt) => v.visit_expr_mut(&mut stmt.condition),
+    }
+}
+
+pub fn walk_var_decl_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut VarDeclStatement) {
+    if let Some(data_type) = &mut stmt.data_type {
+        v.visit_data_type_mut(data_type);
+    }
+
+    if let Some(initializer) = &mut stmt.initializer {
+        v.visit_expr_mut(initializer);
+    }
+}
+
+pub fn walk_assignment_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut AssignmentStatement) {
+    if let AssignmentLhs::Expr(lhs) = &mut stmt.lhs {
+        v.visit_lhs_expr_mut(lhs);
+    }
+
+    v.visit_expr_mut(&mut stmt.rhs);
+}
+
+pub fn walk_if_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut IfStatement) {
+    v.visit_expr_mut(&mut stmt.condition);
+    v.visit_block_mut(&mut stmt.body);
+
+    match stmt.else_.as_deref_mut() {
+        Some(Else::If(stmt)) => walk_if_mut(v, stmt),
+        Some(Else::Else(block)) => v.visit_block_mut(block),
+        None => {}
+    }
+}
+
+pub fn walk_lhs_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut LhsExprNode) {
+    v.visit_data_type_mut(&mut node.data_type);
+
+    match &mut node.expr {
+        LhsExpr::Ident(_) => {}
+        LhsExpr::Postfix(inner, postfix) => {
+            v.visit_lhs_expr_mut(inner);
+            if let Postfix::Index(index) = postfix {
+                v.visit_expr_mut(index);
+            }
+        }
+        LhsExpr::Deref(inner) | LhsExpr::AddressOf(inner) => v.visit_lhs_expr_mut(inner),
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut ExprNode) {
+    v.visit_data_type_mut(&mut node.data_type);
+
+    match &mut node.expr {
+        Expr::Lit(_) | Expr::Var(_) => {}
+        Expr::TypeCons(expr) => {
+            v.visit_data_type_mut(&mut expr.data_type);
+            for arg in &mut expr.args {
+                v.visit_expr_mut(arg);
+            }
+        }
+        Expr::Postfix(expr) => {
+            v.visit_expr_mut(&mut expr.inner);
+            if let Postfix::Index(index) = &mut expr.postfix {
+                v.visit_expr_mut(index);
+            }
+        }
+        Expr::UnOp(expr) => v.visit_expr_mut(&mut expr.inner),
+        Expr::BinOp(expr) => {
+            v.visit_expr_mut(&mut expr.left);
+            v.visit_expr_mut(&mut expr.right);
+        }
+        Expr::FnCall(expr) => {
+            for arg in &mut expr.args {
+                v.visit_expr_mut(arg);
+            }
+        }
+    }
+}
+
+pub fn walk_data_type_mut<V: VisitorMut + ?Sized>(v: &mut V, data_type: &mut DataType) {
+    match data_type {
+        DataType::Scalar(_) | DataType::Vector(_, _) | DataType::Struct(_) => {}
+        DataType::Array(inner, _) => v.visit_data_type_mut(Rc::make_mut(inner)),
+        DataType::Ptr(view) | DataType::Ref(view) => {
+            v.visit_data_type_mut(Rc::make_mut(&mut view.inner))
+        }
+    }
+}
@@ -1,61 +1,135 @@
 use std::fmt::{Display, Result, Write};
+use std::io;
 
-use indenter::indented;
+use crate::{
+    DiagnosticControl, Else, FnAttr, FnDecl, ForLoopInit, ForLoopUpdate, GlobalConstDecl,
+    GlobalVarDecl, IfStatement, Module, Statement, StructDecl, SwitchCase,
+};
 
-use crate::{FnAttr, FnDecl, GlobalConstDecl, GlobalVarDecl, Module, StructDecl};
-
-#[derive(Default)]
 pub struct Writer {
-    #[allow(unused)]
     options: Options,
+    indent: String,
 }
 
-#[derive(Default)]
 pub struct Options {
     pub concise_stage_attrs: bool,
     pub module_scope_constants: bool,
+    /// Number of spaces to indent blocks by.
+    pub indent_width: usize,
+    /// Emit the whole module on a single line.
+    pub minify: bool,
+    /// Emit a trailing comma after the last function parameter and `diagnostic` argument.
+    pub trailing_commas: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            concise_stage_attrs: false,
+            module_scope_constants: false,
+            indent_width: 4,
+            minify: false,
+            trailing_commas: false,
+        }
+    }
+}
+
+impl Default for Writer {
+    fn default() -> Self {
+        Writer::new(Options::default())
+    }
 }
 
 impl Writer {
     pub fn new(options: Options) -> Writer {
-        Writer { options }
+        let indent = if options.minify {
+            String::new()
+        } else {
+            " ".repeat(options.indent_width)
+        };
+
+        Writer { options, indent }
+    }
+
+    fn indented<'a>(&'a self, f: &'a mut dyn Write) -> Indented<'a> {
+        Indented {
+            inner: f,
+            indent: &self.indent,
+            needs_indent: true,
+        }
     }
 
     pub fn write_module(&self, f: &mut dyn Write, module: &Module) -> Result {
+        if !module.enables.is_empty() {
+            write!(f, "enable {};", module.enables.join(", "))?;
+            self.write_newline(f)?;
+        }
+
+        for control in &module.diagnostics {
+            self.write_diagnostic(f, control)?;
+            write!(f, ";")?;
+            self.write_newline(f)?;
+        }
+
         for decl in &module.structs {
             self.write_struct(f, decl)?;
-            writeln!(f)?;
+            self.write_newline(f)?;
         }
 
         for decl in &module.consts {
             self.write_global_const(f, decl)?;
-            writeln!(f)?;
+            self.write_newline(f)?;
+        }
+
+        for stmt in &module.const_asserts {
+            write!(f, "{stmt};")?;
+            self.write_newline(f)?;
         }
 
         for decl in &module.vars {
             self.write_global_var(f, decl)?;
-            writeln!(f)?;
+            self.write_newline(f)?;
         }
 
         for decl in &module.functions {
             self.write_func(f, decl)?;
-            writeln!(f)?;
+            self.write_newline(f)?;
         }
 
         Ok(())
     }
 
+    /// Writes the module to an [`io::Write`], without building the whole output in memory first.
+    ///
+    /// Output is written in many small pieces, so `w` should usually be buffered.
+    pub fn write_module_io(&self, w: &mut dyn io::Write, module: &Module) -> io::Result<()> {
+        let mut adapter = IoAdapter {
+            inner: w,
+            error: None,
+        };
+
+        match self.write_module(&mut adapter, module) {
+            Ok(()) => Ok(()),
+            // fmt::Error doesn't carry any information, so the original io error is returned
+            Err(_) => Err(adapter
+                .error
+                .unwrap_or_else(|| io::Error::new(io::ErrorKind::Other, "formatter error"))),
+        }
+    }
+
     pub fn write_struct(&self, f: &mut dyn Write, decl: &StructDecl) -> Result {
-        writeln!(f, "struct {} {{", decl.name)?;
+        write!(f, "struct {} {{", decl.name)?;
+        self.write_newline(f)?;
 
         for member in &decl.members {
-            self.write_attrs(&mut indented(f), member.attrs.iter())?;
-            writeln!(indented(f), "{}: {},", member.name, member.data_type)?;
+            let mut f = self.indented(f);
+            self.write_attrs(&mut f, member.attrs.iter())?;
+            write!(f, "{}: {},", member.name, member.data_type)?;
+            self.write_newline(&mut f)?;
         }
 
-        writeln!(f, "}}")?;
-
-        Ok(())
+        write!(f, "}}")?;
+        self.write_newline(f)
     }
 
     pub fn write_global_const(&self, f: &mut dyn Write, decl: &GlobalConstDecl) -> Result {
@@ -65,11 +139,13 @@ impl Writer {
             write!(f, "let")?;
         }
 
-        writeln!(
+        write!(
             f,
             " {}: {} = {};",
             decl.name, decl.data_type, decl.initializer
-        )
+        )?;
+
+        self.write_newline(f)
     }
 
     pub fn write_global_var(&self, f: &mut dyn Write, decl: &GlobalVarDecl) -> Result {
@@ -91,7 +167,8 @@ impl Writer {
             write!(f, " = {initializer}")?;
         }
 
-        writeln!(f, ";")
+        write!(f, ";")?;
+        self.write_newline(f)
     }
 
     pub fn write_func(&self, f: &mut dyn Write, func: &FnDecl) -> Result {
@@ -100,11 +177,16 @@ impl Writer {
                 FnAttr::Stage(stage) => {
                     // TODO: Tint doesn't currently support the new stage attribute syntax - update when implemented
                     if self.options.concise_stage_attrs {
-                        writeln!(f, "@{stage}")?;
+                        self.write_attr(f, stage)?;
                     } else {
-                        writeln!(f, "@stage({stage})")?;
+                        self.write_attr(f, &format_args!("stage({stage})"))?;
                     }
                 }
+                FnAttr::Diagnostic(control) => {
+                    let mut attr = String::new();
+                    self.write_diagnostic(&mut attr, control)?;
+                    self.write_attr(f, &attr)?;
+                }
                 _ => self.write_attr(f, attr)?,
             }
         }
@@ -115,6 +197,8 @@ impl Writer {
             write!(f, "{param}")?;
             if i != func.inputs.len() - 1 {
                 f.write_str(", ")?;
+            } else if self.options.trailing_commas {
+                f.write_str(",")?;
             }
         }
 
@@ -124,15 +208,109 @@ impl Writer {
             write!(f, "-> {output} ")?;
         }
 
-        writeln!(f, "{{")?;
+        self.write_block(f, &func.body)?;
+        self.write_newline(f)
+    }
+
+    pub fn write_stmt(&self, f: &mut dyn Write, stmt: &Statement) -> Result {
+        match stmt {
+            Statement::Compound(block) => self.write_block(f, block),
+            Statement::If(stmt) => self.write_if(f, stmt),
+            Statement::Loop(stmt) => {
+                write!(f, "loop ")?;
+                self.write_block(f, &stmt.body)
+            }
+            Statement::Switch(stmt) => {
+                write!(f, "switch ({}) {{", stmt.selector)?;
+                self.write_newline(f)?;
+
+                for SwitchCase { selector, body } in &stmt.cases {
+                    let mut f = self.indented(f);
+                    write!(f, "case {selector}: ")?;
+                    self.write_block(&mut f, body)?;
+                    self.write_newline(&mut f)?;
+                }
+
+                {
+                    let mut f = self.indented(f);
+                    write!(f, "default: ")?;
+                    self.write_block(&mut f, &stmt.default)?;
+                    self.write_newline(&mut f)?;
+                }
+
+                write!(f, "}}")
+            }
+            Statement::ForLoop(stmt) => {
+                write!(f, "for (")?;
+
+                if let Some(ForLoopInit::VarDecl(init)) = &stmt.header.init {
+                    write!(f, "{init}")?;
+                }
 
-        for stmt in &func.body {
-            writeln!(indented(f), "{}", stmt)?;
+                write!(f, "; ")?;
+
+                if let Some(condition) = &stmt.header.condition {
+                    write!(f, "{condition}")?;
+                }
+
+                write!(f, "; ")?;
+
+                if let Some(ForLoopUpdate::Assignment(update)) = &stmt.header.update {
+                    write!(f, "{update}")?;
+                }
+
+                write!(f, ") ")?;
+                self.write_block(f, &stmt.body)
+            }
+            // The remaining statements don't contain any blocks, so they always fit on one line
+            stmt => write!(f, "{stmt}"),
+        }
+    }
+
+    fn write_diagnostic(&self, f: &mut dyn Write, control: &DiagnosticControl) -> Result {
+        write!(f, "diagnostic({}, {}", control.severity, control.rule)?;
+        if self.options.trailing_commas {
+            write!(f, ",")?;
         }
+        write!(f, ")")
+    }
 
-        writeln!(f, "}}")?;
+    fn write_if(&self, f: &mut dyn Write, stmt: &IfStatement) -> Result {
+        write!(f, "if ({}) ", stmt.condition)?;
+        self.write_block(f, &stmt.body)?;
 
-        Ok(())
+        match stmt.else_.as_deref() {
+            Some(Else::If(stmt)) => {
+                write!(f, " else ")?;
+                self.write_if(f, stmt)
+            }
+            Some(Else::Else(block)) => {
+                write!(f, " else ")?;
+                self.write_block(f, block)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn write_block(&self, f: &mut dyn Write, block: &[Statement]) -> Result {
+        write!(f, "{{")?;
+        self.write_newline(f)?;
+
+        for stmt in block {
+            let mut f = self.indented(f);
+            self.write_stmt(&mut f, stmt)?;
+            self.write_newline(&mut f)?;
+        }
+
+        write!(f, "}}")
+    }
+
+    fn write_newline(&self, f: &mut dyn Write) -> Result {
+        if self.options.minify {
+            Ok(())
+        } else {
+            writeln!(f)
+        }
     }
 
     fn write_attrs<'a, T: Display + 'a>(
@@ -148,6 +326,57 @@ impl Writer {
     }
 
     fn write_attr<T: Display>(&self, f: &mut dyn Write, attr: &T) -> Result {
-        writeln!(f, "@{attr}")
+        write!(f, "@{attr}")?;
+        if self.options.minify {
+            write!(f, " ")
+        } else {
+            writeln!(f)
+        }
+    }
+}
+
+/// Forwards formatted output to an [`io::Write`], keeping hold of the first error.
+struct IoAdapter<'a> {
+    inner: &'a mut dyn io::Write,
+    error: Option<io::Error>,
+}
+
+impl<'a> Write for IoAdapter<'a> {
+    fn write_str(&mut self, s: &str) -> Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error.get_or_insert(e);
+            std::fmt::Error
+        })
+    }
+}
+
+/// Indents each line written to the inner writer, without indenting empty lines.
+struct Indented<'a> {
+    inner: &'a mut dyn Write,
+    indent: &'a str,
+    needs_indent: bool,
+}
+
+impl<'a> Write for Indented<'a> {
+    fn write_str(&mut self, s: &str) -> Result {
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.inner.write_char('\n')?;
+                self.needs_indent = true;
+            }
+
+            if self.needs_indent {
+                if line.is_empty() {
+                    continue;
+                }
+
+                self.inner.write_str(self.indent)?;
+                self.needs_indent = false;
+            }
+
+            self.inner.write_str(line)?;
+        }
+
+        Ok(())
     }
 }
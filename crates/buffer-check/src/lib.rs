@@ -1,24 +1,302 @@
-use common::Type;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use ast::hash::Fnv1a;
+use common::{ScalarType, StructMember, Type};
 use reflection_types::{PipelineDescription, ResourceKind};
 
+/// Controls how values in an output buffer are compared between executions.
+///
+/// The default is an exact bitwise comparison. Integers are always compared exactly.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Tolerance {
+    /// Maximum distance between floats in units in the last place.
+    pub ulps: u32,
+    /// Maximum absolute difference between floats.
+    pub abs: f32,
+    /// Maximum difference between floats relative to the larger of the two.
+    pub rel: f32,
+    /// Whether NaNs compare equal to each other, regardless of their bit patterns.
+    pub nan_equal: bool,
+}
+
+impl Tolerance {
+    fn is_exact(&self) -> bool {
+        *self == Tolerance::default()
+    }
+
+    fn f32_eq(&self, a: f32, b: f32) -> bool {
+        if a.to_bits() == b.to_bits() {
+            return true;
+        }
+
+        if a.is_nan() || b.is_nan() {
+            return self.nan_equal && a.is_nan() && b.is_nan();
+        }
+
+        // The epsilons are only checked if set, so that e.g. 0.0 and -0.0 are still considered
+        // different by default
+        let diff = (a - b).abs();
+        (self.abs > 0.0 && diff <= self.abs)
+            || (self.rel > 0.0 && diff <= self.rel * a.abs().max(b.abs()))
+            || (self.ulps > 0 && ulp_distance(a, b) <= self.ulps as u64)
+    }
+}
+
+impl FromStr for Tolerance {
+    type Err = &'static str;
+
+    /// Parses a comma separated list of `ulps=<n>`, `abs=<x>`, `rel=<x>` and `nan`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ERROR: &str =
+            "invalid tolerance - expected comma separated list of ulps=<n>, abs=<x>, rel=<x> or nan";
+
+        let mut tolerance = Tolerance::default();
+
+        for part in s.split(',') {
+            match part.split_once('=') {
+                Some(("ulps", value)) => tolerance.ulps = value.parse().map_err(|_| ERROR)?,
+                Some(("abs", value)) => tolerance.abs = value.parse().map_err(|_| ERROR)?,
+                Some(("rel", value)) => tolerance.rel = value.parse().map_err(|_| ERROR)?,
+                None if part == "nan" => tolerance.nan_equal = true,
+                _ => return Err(ERROR),
+            }
+        }
+
+        Ok(tolerance)
+    }
+}
+
+/// A tolerance for either all buffers, or a specific buffer.
+///
+/// This is written as `<tolerance>` or `<group>:<binding>@<tolerance>`, e.g. `0:1@ulps=4,nan`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BufferTolerance {
+    pub binding: Option<(u32, u32)>,
+    pub tolerance: Tolerance,
+}
+
+impl FromStr for BufferTolerance {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (binding, tolerance) = match s.split_once('@') {
+            Some((binding, tolerance)) => {
+                let (group, binding) = binding
+                    .split_once(':')
+                    .ok_or("invalid buffer - expected <group>:<binding>")?;
+                let group = group.parse().map_err(|_| "invalid group index")?;
+                let binding = binding.parse().map_err(|_| "invalid binding index")?;
+                (Some((group, binding)), tolerance)
+            }
+            None => (None, s),
+        };
+
+        Ok(BufferTolerance {
+            binding,
+            tolerance: tolerance.parse()?,
+        })
+    }
+}
+
+/// Tolerances to use for each output buffer.
+#[derive(Clone, Debug, Default)]
+pub struct Tolerances {
+    pub default: Tolerance,
+    pub buffers: HashMap<(u32, u32), Tolerance>,
+}
+
+impl Tolerances {
+    pub fn get(&self, group: u32, binding: u32) -> Tolerance {
+        self.buffers
+            .get(&(group, binding))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+impl FromIterator<BufferTolerance> for Tolerances {
+    fn from_iter<T: IntoIterator<Item = BufferTolerance>>(iter: T) -> Self {
+        let mut tolerances = Tolerances::default();
+
+        for it in iter {
+            match it.binding {
+                Some(binding) => {
+                    tolerances.buffers.insert(binding, it.tolerance);
+                }
+                None => tolerances.default = it.tolerance,
+            }
+        }
+
+        tolerances
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    I32(i32),
+    U32(u32),
+    F32(f32),
+}
+
+impl Value {
+    fn read(bytes: &[u8], scalar_type: ScalarType) -> Value {
+        let bytes = bytes.try_into().unwrap();
+        match scalar_type {
+            ScalarType::I32 => Value::I32(i32::from_le_bytes(bytes)),
+            ScalarType::U32 => Value::U32(u32::from_le_bytes(bytes)),
+            ScalarType::F32 => Value::F32(f32::from_le_bytes(bytes)),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::I32(v) => write!(f, "{v}i"),
+            Value::U32(v) => write!(f, "{v}u"),
+            Value::F32(v) => write!(f, "{v:?} ({:#010x})", v.to_bits()),
+        }
+    }
+}
+
+/// An output of the pipeline that values are read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Output {
+    Buffer {
+        group: u32,
+        binding: u32,
+    },
+    /// The texture that the render pipeline draws into, if there is one.
+    RenderTarget,
+}
+
+impl Display for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Output::Buffer { group, binding } => write!(f, "{group}:{binding}"),
+            Output::RenderTarget => write!(f, "render target"),
+        }
+    }
+}
+
+/// A value in an output buffer that differed between two executions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mismatch {
+    pub output: Output,
+    /// Byte offset of the value in the buffer.
+    pub offset: usize,
+    /// Indices of the two executions that were compared.
+    pub executions: (usize, usize),
+    pub expected: Value,
+    pub actual: Value,
+    /// Size of the difference - ULPs for floats, or the absolute difference for integers.
+    pub error: f64,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Mismatch {
+            output,
+            offset,
+            executions: (a, b),
+            expected,
+            actual,
+            error,
+        } = self;
+
+        write!(
+            f,
+            "{output} at offset {offset}: {expected} (execution {a}) != {actual} (execution {b}), error: {error}"
+        )
+    }
+}
+
 pub fn compare<'a>(
-    mut buffers: impl Iterator<Item = &'a Vec<Vec<u8>>>,
+    buffers: impl Iterator<Item = &'a Vec<Vec<u8>>>,
     pipeline_desc: &PipelineDescription,
     type_descs: &[Type],
 ) -> bool {
+    compare_with_tolerance(buffers, pipeline_desc, type_descs, &Tolerances::default()).is_empty()
+}
+
+/// Compares the output buffers from each execution with the previous one, and returns all the
+/// values that differ with the worst offending values first.
+pub fn compare_with_tolerance<'a>(
+    mut buffers: impl Iterator<Item = &'a Vec<Vec<u8>>>,
+    pipeline_desc: &PipelineDescription,
+    type_descs: &[Type],
+    tolerances: &Tolerances,
+) -> Vec<Mismatch> {
+    let mut mismatches = vec![];
+
     if let Some(mut prev) = buffers.next() {
-        for execution in buffers {
-            for (i, (j, _)) in pipeline_desc
+        for (index, execution) in buffers.enumerate() {
+            for (i, (j, resource)) in pipeline_desc
                 .resources
                 .iter()
                 .enumerate()
                 .filter(|(_, it)| it.kind == ResourceKind::StorageBuffer)
                 .enumerate()
             {
-                for (offset, size) in type_descs[j].ranges() {
-                    let range = offset..(offset + size);
-                    if execution[i][range.clone()] != prev[i][range] {
-                        return false;
+                let tolerance = tolerances.get(resource.group, resource.binding);
+
+                for (offset, scalar_type) in type_descs[j].scalars() {
+                    let range = offset..(offset + 4);
+                    let expected = &prev[i][range.clone()];
+                    let actual = &execution[i][range];
+
+                    if tolerance.is_exact() && expected == actual {
+                        continue;
+                    }
+
+                    let expected = Value::read(expected, scalar_type);
+                    let actual = Value::read(actual, scalar_type);
+
+                    if let Some(error) = difference(expected, actual, &tolerance) {
+                        mismatches.push(Mismatch {
+                            output: Output::Buffer {
+                                group: resource.group,
+                                binding: resource.binding,
+                            },
+                            offset,
+                            executions: (index, index + 1),
+                            expected,
+                            actual,
+                            error,
+                        });
+                    }
+                }
+            }
+
+            // The render target is read back after the storage buffers. Expected outputs don't
+            // include it, so it's only compared between executions which both have it.
+            let storage_count = pipeline_desc
+                .resources
+                .iter()
+                .filter(|it| it.kind == ResourceKind::StorageBuffer)
+                .count();
+
+            if let (Some(_), Some(expected), Some(actual)) = (
+                &pipeline_desc.render,
+                prev.get(storage_count),
+                execution.get(storage_count),
+            ) {
+                for offset in (0..expected.len().min(actual.len())).step_by(4) {
+                    let range = offset..(offset + 4);
+                    let expected = Value::read(&expected[range.clone()], ScalarType::U32);
+                    let actual = Value::read(&actual[range], ScalarType::U32);
+
+                    if let Some(error) = difference(expected, actual, &Tolerance::default()) {
+                        mismatches.push(Mismatch {
+                            output: Output::RenderTarget,
+                            offset,
+                            executions: (index, index + 1),
+                            expected,
+                            actual,
+                            error,
+                        });
                     }
                 }
             }
@@ -27,5 +305,429 @@ pub fn compare<'a>(
         }
     }
 
-    true
+    // Errors are never NaN, so the comparison can't fail
+    mismatches.sort_by(|a, b| b.error.partial_cmp(&a.error).unwrap());
+    mismatches
+}
+
+/// Returns the size of the difference between two values, or `None` if they are considered equal.
+fn difference(expected: Value, actual: Value, tolerance: &Tolerance) -> Option<f64> {
+    match (expected, actual) {
+        (Value::I32(a), Value::I32(b)) if a != b => Some((a as f64 - b as f64).abs()),
+        (Value::U32(a), Value::U32(b)) if a != b => Some((a as f64 - b as f64).abs()),
+        (Value::F32(a), Value::F32(b)) if !tolerance.f32_eq(a, b) => {
+            if a.is_nan() || b.is_nan() {
+                Some(f64::INFINITY)
+            } else {
+                Some(ulp_distance(a, b) as f64)
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Number of representable floats between `a` and `b`.
+fn ulp_distance(a: f32, b: f32) -> u64 {
+    // Maps floats onto integers such that adjacent floats map to adjacent integers, with 0.0 and
+    // -0.0 both mapping to 0
+    fn ordered(v: f32) -> i64 {
+        let bits = v.to_bits() as i32 as i64;
+        if bits < 0 {
+            i32::MIN as i64 - bits
+        } else {
+            bits
+        }
+    }
+
+    (ordered(a) - ordered(b)).unsigned_abs()
+}
+
+/// Returns a hash of the contents of a set of output buffers, which is stable across runs and
+/// platforms so that it can be compared between logs.
+pub fn signature(buffers: &[Vec<u8>]) -> u64 {
+    let mut hasher = Fnv1a::new();
+
+    for buffer in buffers {
+        // Include the length so that moving bytes between buffers changes the hash
+        hasher.write(&(buffer.len() as u64).to_le_bytes());
+        hasher.write(buffer);
+    }
+
+    hasher.finish()
+}
+
+/// Returns the checksum in a buffer written by a program generated with `--checksum`, or `None`
+/// if the buffer doesn't have the type of a checksum output buffer.
+///
+/// With multiple invocations, the buffer contains a checksum for each invocation, which are
+/// merged into a single checksum for the whole dispatch.
+pub fn checksum(buffer: &[u8], type_desc: &Type) -> Option<u32> {
+    let is_checksum = |type_desc: &Type| match type_desc {
+        Type::Struct { members } => matches!(
+            members.as_slice(),
+            [StructMember {
+                name,
+                type_desc: Type::Scalar {
+                    scalar_type: ScalarType::U32
+                },
+            }] if name == common::checksum::MEMBER
+        ),
+        _ => false,
+    };
+
+    let is_checksum_buffer = match type_desc {
+        Type::Array { element_type, .. } => is_checksum(element_type),
+        type_desc => is_checksum(type_desc),
+    };
+
+    if !is_checksum_buffer {
+        return None;
+    }
+
+    let checksum = type_desc
+        .scalars()
+        .into_iter()
+        .map(|(offset, _)| u32::from_le_bytes(buffer[offset..offset + 4].try_into().unwrap()))
+        .fold(0, common::checksum::merge);
+
+    Some(checksum)
+}
+
+/// Groups executions by their outputs, returning an index for each execution such that two
+/// executions have the same index if and only if their signatures are the same.
+///
+/// Indices are assigned in order of first appearance, so the pattern only depends on which
+/// executions agreed with each other and not on the actual outputs. This makes it useful for
+/// bucketing mismatches that are likely to have the same cause.
+pub fn disagreement_pattern(signatures: &[u64]) -> Vec<usize> {
+    let mut classes: Vec<u64> = vec![];
+
+    signatures
+        .iter()
+        .map(
+            |signature| match classes.iter().position(|it| it == signature) {
+                Some(index) => index,
+                None => {
+                    classes.push(*signature);
+                    classes.len() - 1
+                }
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use common::VectorSize;
+    use reflection_types::PipelineResource;
+
+    use super::*;
+
+    fn resource(kind: ResourceKind, group: u32, binding: u32) -> PipelineResource {
+        PipelineResource {
+            name: format!("buffer_{group}_{binding}"),
+            kind,
+            group,
+            binding,
+            init: None,
+            size: 8,
+        }
+    }
+
+    fn pipeline(resources: Vec<PipelineResource>) -> PipelineDescription {
+        PipelineDescription {
+            resources,
+            entry_point: "main".to_owned(),
+            workgroup_size: [1, 1, 1],
+            dispatches: 1,
+            cache_check: false,
+            concurrent_compiles: 0,
+            dispatch_timing: None,
+            bounds_checks: true,
+            render: None,
+            push_constants: None,
+            subgroups: false,
+        }
+    }
+
+    fn vec2f() -> Type {
+        Type::Vector {
+            size: VectorSize::N2,
+            scalar_type: ScalarType::F32,
+        }
+    }
+
+    fn bytes(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    fn checksum_struct() -> Type {
+        Type::Struct {
+            members: vec![StructMember {
+                name: common::checksum::MEMBER.to_owned(),
+                type_desc: Type::Scalar {
+                    scalar_type: ScalarType::U32,
+                },
+            }],
+        }
+    }
+
+    fn next_up(v: f32) -> f32 {
+        f32::from_bits(v.to_bits() + 1)
+    }
+
+    #[test]
+    fn parse_tolerance() {
+        assert_eq!(
+            "ulps=4,nan".parse(),
+            Ok(Tolerance {
+                ulps: 4,
+                nan_equal: true,
+                ..Tolerance::default()
+            })
+        );
+
+        assert_eq!(
+            "abs=0.5,rel=0.01".parse(),
+            Ok(Tolerance {
+                abs: 0.5,
+                rel: 0.01,
+                ..Tolerance::default()
+            })
+        );
+
+        assert!("ulps".parse::<Tolerance>().is_err());
+        assert!("ulps=-1".parse::<Tolerance>().is_err());
+        assert!("eps=1".parse::<Tolerance>().is_err());
+    }
+
+    #[test]
+    fn parse_buffer_tolerance() {
+        assert_eq!(
+            "0:1@ulps=4".parse(),
+            Ok(BufferTolerance {
+                binding: Some((0, 1)),
+                tolerance: Tolerance {
+                    ulps: 4,
+                    ..Tolerance::default()
+                },
+            })
+        );
+
+        assert_eq!(
+            "nan".parse(),
+            Ok(BufferTolerance {
+                binding: None,
+                tolerance: Tolerance {
+                    nan_equal: true,
+                    ..Tolerance::default()
+                },
+            })
+        );
+
+        assert!("0@ulps=4".parse::<BufferTolerance>().is_err());
+        assert!("a:1@ulps=4".parse::<BufferTolerance>().is_err());
+    }
+
+    #[test]
+    fn buffer_tolerances_override_default() {
+        let tolerances = ["ulps=1", "0:1@ulps=8"]
+            .iter()
+            .map(|it| it.parse::<BufferTolerance>().unwrap())
+            .collect::<Tolerances>();
+
+        assert_eq!(tolerances.get(0, 0).ulps, 1);
+        assert_eq!(tolerances.get(0, 1).ulps, 8);
+    }
+
+    #[test]
+    fn exact_comparison() {
+        let exact = Tolerance::default();
+
+        assert!(exact.f32_eq(1.0, 1.0));
+        assert!(!exact.f32_eq(1.0, next_up(1.0)));
+        assert!(!exact.f32_eq(0.0, -0.0));
+        assert!(exact.f32_eq(f32::NAN, f32::NAN));
+        assert!(!exact.f32_eq(f32::NAN, f32::from_bits(f32::NAN.to_bits() + 1)));
+    }
+
+    #[test]
+    fn ulp_tolerance() {
+        let tolerance = Tolerance {
+            ulps: 2,
+            ..Tolerance::default()
+        };
+
+        assert_eq!(ulp_distance(1.0, next_up(1.0)), 1);
+        assert_eq!(ulp_distance(0.0, -0.0), 0);
+        // The smallest subnormals on either side of zero
+        assert_eq!(ulp_distance(-f32::from_bits(1), f32::from_bits(1)), 2);
+
+        assert!(tolerance.f32_eq(1.0, next_up(next_up(1.0))));
+        assert!(!tolerance.f32_eq(1.0, next_up(next_up(next_up(1.0)))));
+        assert!(tolerance.f32_eq(0.0, -0.0));
+    }
+
+    #[test]
+    fn abs_and_rel_tolerance() {
+        let abs = Tolerance {
+            abs: 0.5,
+            ..Tolerance::default()
+        };
+
+        assert!(abs.f32_eq(1.0, 1.5));
+        assert!(!abs.f32_eq(1.0, 1.75));
+        assert!(!abs.f32_eq(1.0, f32::INFINITY));
+
+        let rel = Tolerance {
+            rel: 0.1,
+            ..Tolerance::default()
+        };
+
+        assert!(rel.f32_eq(100.0, 91.0));
+        assert!(!rel.f32_eq(100.0, 89.0));
+        assert!(!rel.f32_eq(1.0, 1.25));
+    }
+
+    #[test]
+    fn nan_tolerance() {
+        let tolerance = Tolerance {
+            nan_equal: true,
+            abs: f32::MAX,
+            ..Tolerance::default()
+        };
+
+        assert!(tolerance.f32_eq(f32::NAN, f32::from_bits(f32::NAN.to_bits() + 1)));
+        assert!(!tolerance.f32_eq(f32::NAN, 1.0));
+        assert!(!Tolerance::default().f32_eq(f32::NAN, 1.0));
+    }
+
+    #[test]
+    fn difference_size() {
+        let exact = Tolerance::default();
+
+        assert_eq!(difference(Value::I32(1), Value::I32(-2), &exact), Some(3.0));
+        assert_eq!(difference(Value::U32(5), Value::U32(5), &exact), None);
+        assert_eq!(
+            difference(Value::F32(1.0), Value::F32(next_up(1.0)), &exact),
+            Some(1.0)
+        );
+        assert_eq!(
+            difference(Value::F32(f32::NAN), Value::F32(1.0), &exact),
+            Some(f64::INFINITY)
+        );
+    }
+
+    #[test]
+    fn compare_storage_buffers() {
+        // The uniform buffer has no output, so the storage buffer is the first one in each
+        // execution but its type is the second one
+        let pipeline_desc = pipeline(vec![
+            resource(ResourceKind::UniformBuffer, 0, 0),
+            resource(ResourceKind::StorageBuffer, 0, 1),
+        ]);
+        let type_descs = [vec2f(), vec2f()];
+
+        let executions = [
+            vec![bytes(&[1.0, 2.0])],
+            vec![bytes(&[next_up(1.0), 2.0])],
+            vec![bytes(&[next_up(1.0), 3.0])],
+        ];
+
+        let mismatches = compare_with_tolerance(
+            executions.iter(),
+            &pipeline_desc,
+            &type_descs,
+            &Tolerances::default(),
+        );
+
+        assert_eq!(
+            mismatches,
+            vec![
+                Mismatch {
+                    output: Output::Buffer {
+                        group: 0,
+                        binding: 1,
+                    },
+                    offset: 4,
+                    executions: (1, 2),
+                    expected: Value::F32(2.0),
+                    actual: Value::F32(3.0),
+                    error: (1 << 22) as f64,
+                },
+                Mismatch {
+                    output: Output::Buffer {
+                        group: 0,
+                        binding: 1,
+                    },
+                    offset: 0,
+                    executions: (0, 1),
+                    expected: Value::F32(1.0),
+                    actual: Value::F32(next_up(1.0)),
+                    error: 1.0,
+                },
+            ]
+        );
+
+        assert!(!compare(executions.iter(), &pipeline_desc, &type_descs));
+
+        // Only the larger difference exceeds the tolerance for the buffer
+        let tolerances = ["0:1@ulps=1".parse::<BufferTolerance>().unwrap()]
+            .into_iter()
+            .collect::<Tolerances>();
+
+        let mismatches =
+            compare_with_tolerance(executions.iter(), &pipeline_desc, &type_descs, &tolerances);
+
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].offset, 4);
+    }
+
+    #[test]
+    fn compare_identical_executions() {
+        let pipeline_desc = pipeline(vec![resource(ResourceKind::StorageBuffer, 0, 0)]);
+        let executions = [vec![bytes(&[1.0, f32::NAN])], vec![bytes(&[1.0, f32::NAN])]];
+
+        assert!(compare(executions.iter(), &pipeline_desc, &[vec2f()]));
+    }
+
+    #[test]
+    fn checksum_buffer() {
+        let buffer = 7u32.to_le_bytes();
+
+        assert_eq!(checksum(&buffer, &checksum_struct()), Some(7));
+    }
+
+    #[test]
+    fn checksums_from_each_invocation_are_merged() {
+        let type_desc = Type::Array {
+            size: 3,
+            element_type: Box::new(checksum_struct()),
+        };
+
+        let buffer = [1u32, 2, u32::MAX]
+            .iter()
+            .flat_map(|it| it.to_le_bytes())
+            .collect::<Vec<_>>();
+
+        assert_eq!(checksum(&buffer, &type_desc), Some(2));
+    }
+
+    #[test]
+    fn other_buffers_have_no_checksum() {
+        let buffer = bytes(&[1.0, 2.0]);
+
+        assert_eq!(checksum(&buffer, &vec2f()), None);
+
+        let type_desc = Type::Struct {
+            members: vec![StructMember {
+                name: "value".to_owned(),
+                type_desc: Type::Scalar {
+                    scalar_type: ScalarType::U32,
+                },
+            }],
+        };
+
+        assert_eq!(checksum(&buffer, &type_desc), None);
+    }
 }
@@ -0,0 +1,23 @@
+//! Authentication of clients by the servers that execute or compile shaders for wgslsmith.
+//!
+//! Servers can be started with a shared secret, which clients must present before any of their
+//! requests are handled, since those requests contain arbitrary shader code.
+
+/// Checks a token presented by a client against the one the server was started with.
+///
+/// If the server doesn't require a token, all clients are accepted.
+pub fn check_token(expected: Option<&str>, presented: Option<&str>) -> bool {
+    let (expected, presented) = match (expected, presented) {
+        (None, _) => return true,
+        (Some(_), None) => return false,
+        (Some(expected), Some(presented)) => (expected.as_bytes(), presented.as_bytes()),
+    };
+
+    // Compare every byte, to avoid leaking the position of the first mismatch through timing
+    expected.len() == presented.len()
+        && expected
+            .iter()
+            .zip(presented)
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
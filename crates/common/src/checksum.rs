@@ -0,0 +1,130 @@
+//! Checksum scheme used by generated programs to accumulate their results into the output buffer.
+//!
+//! Each value is mixed with an integer hash function, and the mixed values are combined with
+//! wrapping addition. Since addition is commutative and associative, the checksum doesn't depend
+//! on the order that values are combined in, so checksums computed by different invocations can be
+//! merged into a checksum for the whole dispatch by adding them together.
+//!
+//! Generated programs call [`combine_fn_decl`], which must compute exactly the same thing as
+//! [`combine`]. The reconditioner leaves it unchanged, since its overflow guards would break the
+//! wrapping arithmetic.
+
+use ast::{
+    BinOp, BinOpExpr, FnDecl, FnInput, FnOutput, LetDeclStatement, Lit, ReturnStatement,
+    ScalarType, VarExpr,
+};
+
+/// Name of the WGSL function that combines a value into a checksum.
+pub const COMBINE_FN: &str = "checksum_combine";
+
+/// Name of the output buffer member that a checksum is written to.
+pub const MEMBER: &str = "checksum";
+
+/// Multiplier used by [`mix`], from the `lowbias32` hash function.
+const MULTIPLIER: u32 = 0x7feb352d;
+
+/// Hashes a single value, so that similar values contribute very different amounts.
+pub fn mix(value: u32) -> u32 {
+    let a = value ^ (value >> 16);
+    let b = a.wrapping_mul(MULTIPLIER);
+    let c = b ^ (b >> 15);
+    c.rotate_left(7)
+}
+
+/// Combines a value into a checksum.
+pub fn combine(checksum: u32, value: u32) -> u32 {
+    checksum.wrapping_add(mix(value))
+}
+
+/// Merges two checksums, such as the checksums computed by two invocations.
+pub fn merge(a: u32, b: u32) -> u32 {
+    a.wrapping_add(b)
+}
+
+/// Returns the declaration of the WGSL function that implements [`combine`].
+pub fn combine_fn_decl() -> FnDecl {
+    let ty = || ScalarType::U32;
+    let var = |name: &str| VarExpr::new(name).into_node(ty());
+
+    let shr = |name: &str, n: u32| BinOpExpr::new(BinOp::RShift, var(name), Lit::U32(n));
+    let shl = |name: &str, n: u32| BinOpExpr::new(BinOp::LShift, var(name), Lit::U32(n));
+
+    let body = vec![
+        LetDeclStatement::new(
+            "a",
+            BinOpExpr::new(BinOp::BitXOr, var("value"), shr("value", 16)),
+        )
+        .into(),
+        LetDeclStatement::new(
+            "b",
+            BinOpExpr::new(BinOp::Times, var("a"), Lit::U32(MULTIPLIER)),
+        )
+        .into(),
+        LetDeclStatement::new("c", BinOpExpr::new(BinOp::BitXOr, var("b"), shr("b", 15))).into(),
+        // Rotate left by 7
+        ReturnStatement::new(BinOpExpr::new(
+            BinOp::Plus,
+            var("checksum"),
+            BinOpExpr::new(BinOp::BitOr, shl("c", 7), shr("c", 25)),
+        ))
+        .into(),
+    ];
+
+    FnDecl {
+        attrs: vec![],
+        name: COMBINE_FN.to_owned(),
+        inputs: vec![FnInput::new("checksum", ty()), FnInput::new("value", ty())],
+        output: Some(FnOutput::new(ty())),
+        body,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::Module;
+
+    use super::*;
+
+    #[test]
+    fn mix_values() {
+        // These must match what the WGSL implementation computes, so that checksums computed on
+        // the host can be compared with the ones written by shaders
+        assert_eq!(mix(0), 0);
+        assert_eq!(mix(1), 0xf5e57dbf);
+        assert_eq!(mix(u32::MAX), 0x694ad365);
+    }
+
+    #[test]
+    fn checksum_is_order_independent() {
+        let values = [1, 2, 3, 0xdeadbeef, u32::MAX];
+
+        let forwards = values.iter().copied().fold(0, combine);
+        let backwards = values.iter().rev().copied().fold(0, combine);
+
+        assert_eq!(forwards, backwards);
+        assert_ne!(forwards, values[..4].iter().copied().fold(0, combine));
+    }
+
+    #[test]
+    fn merged_checksums_match_single_checksum() {
+        let values = [5, 6, 7, 8, 9];
+
+        let whole = values.iter().copied().fold(0, combine);
+        let (a, b) = values.split_at(2);
+        let a = a.iter().copied().fold(0, combine);
+        let b = b.iter().copied().fold(0, combine);
+
+        assert_eq!(merge(a, b), whole);
+        assert_eq!(merge(b, a), whole);
+    }
+
+    #[test]
+    fn combine_fn_is_well_typed() {
+        let module = Module {
+            functions: vec![combine_fn_decl()],
+            ..Default::default()
+        };
+
+        assert_eq!(ast::typecheck::check(&module), Ok(()));
+    }
+}
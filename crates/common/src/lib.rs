@@ -1,4 +1,7 @@
-#[derive(Debug)]
+pub mod auth;
+pub mod checksum;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ScalarType {
     I32,
     U32,
@@ -120,6 +123,48 @@ impl Type {
 
         ranges
     }
+
+    /// Returns the offset and type of each scalar in the buffer layout of this type, including the
+    /// individual components of vectors.
+    pub fn scalars(&self) -> Vec<(usize, ScalarType)> {
+        let mut scalars = vec![];
+
+        fn collect_scalars(acc: &mut Vec<(usize, ScalarType)>, mut offset: u32, type_desc: &Type) {
+            match type_desc {
+                Type::Scalar { scalar_type } => acc.push((offset as _, *scalar_type)),
+                Type::Vector { size, scalar_type } => {
+                    let n = match size {
+                        VectorSize::N2 => 2,
+                        VectorSize::N3 => 3,
+                        VectorSize::N4 => 4,
+                    };
+                    for i in 0..n {
+                        acc.push(((offset + i * 4) as _, *scalar_type));
+                    }
+                }
+                Type::Array { size, element_type } => {
+                    let element_size = element_type.size();
+                    let alignment = element_type.alignment();
+                    for _ in 0..*size {
+                        collect_scalars(acc, offset, element_type);
+                        offset = aligned(offset + element_size, alignment);
+                    }
+                }
+                Type::Struct { members } => {
+                    for member in members {
+                        let alignment = member.type_desc.alignment();
+                        offset = aligned(offset, alignment);
+                        collect_scalars(acc, offset, &member.type_desc);
+                        offset += member.type_desc.size();
+                    }
+                }
+            }
+        }
+
+        collect_scalars(&mut scalars, 0, self);
+
+        scalars
+    }
 }
 
 impl TryFrom<&ast::ScalarType> for ScalarType {
@@ -139,6 +184,59 @@ impl TryFrom<&ast::DataType> for Type {
     type Error = &'static str;
 
     fn try_from(value: &ast::DataType) -> Result<Self, Self::Error> {
+        Type::from_data_type(value, None)
+    }
+}
+
+impl Type {
+    /// Converts a data type into its buffer layout, giving any runtime sized array `len` elements.
+    pub fn with_runtime_array_len(
+        data_type: &ast::DataType,
+        len: u32,
+    ) -> Result<Type, &'static str> {
+        Type::from_data_type(data_type, Some(len))
+    }
+
+    /// Returns the number of elements that a runtime sized array at the end of `data_type` can be
+    /// given such that the buffer is no larger than `size` bytes.
+    ///
+    /// This is always at least 1, so that there is at least one valid index into the array.
+    pub fn runtime_array_len(data_type: &ast::DataType, size: u32) -> Result<u32, &'static str> {
+        let element_type = match data_type {
+            ast::DataType::Array(element_type, None) => element_type,
+            ast::DataType::Struct(decl) => match decl.members.last().map(|it| &it.data_type) {
+                Some(ast::DataType::Array(element_type, None)) => element_type,
+                _ => return Err("struct does not end with a runtime sized array"),
+            },
+            _ => return Err("type does not contain a runtime sized array"),
+        };
+
+        let element_type = Type::try_from(element_type.as_ref())?;
+        let stride = aligned(element_type.size(), element_type.alignment());
+
+        let offset = match Type::with_runtime_array_len(data_type, 0)? {
+            Type::Struct { members } => {
+                let mut offset = 0;
+                let mut last_offset = 0;
+
+                for member in &members {
+                    offset = aligned(offset, member.type_desc.alignment());
+                    last_offset = offset;
+                    offset += member.type_desc.size();
+                }
+
+                last_offset
+            }
+            _ => 0,
+        };
+
+        Ok((size.saturating_sub(offset) / stride).max(1))
+    }
+
+    fn from_data_type(
+        value: &ast::DataType,
+        runtime_array_len: Option<u32>,
+    ) -> Result<Type, &'static str> {
         match value {
             ast::DataType::Scalar(scalar) => Ok(Type::Scalar {
                 scalar_type: scalar.try_into()?,
@@ -153,14 +251,16 @@ impl TryFrom<&ast::DataType> for Type {
                 scalar_type: scalar.try_into()?,
             }),
             ast::DataType::Array(inner, size) => Ok(Type::Array {
-                size: size.ok_or("runtime sized arrays are not supported")?,
+                size: size
+                    .or(runtime_array_len)
+                    .ok_or("runtime sized arrays are not supported")?,
                 element_type: Box::new(inner.as_ref().try_into()?),
             }),
             ast::DataType::Struct(decl) => {
                 let mut members = vec![];
 
                 for member in &decl.members {
-                    let type_desc = Type::try_from(&member.data_type)?;
+                    let type_desc = Type::from_data_type(&member.data_type, runtime_array_len)?;
 
                     members.push(StructMember {
                         name: member.name.clone(),
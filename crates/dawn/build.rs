@@ -12,20 +12,46 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let build_target = env::var("TARGET").unwrap();
 
-    let dawn_build_dir = env::var(format!("DAWN_BUILD_DIR_{}", build_target.replace('-', "_")))
-        .map(PathBuf::from)
-        .or_else(|_| env::var("DAWN_BUILD_DIR").map(PathBuf::from))
-        .unwrap_or_else(|_| root.join("build/dawn").join(&build_target));
+    let dawn_build_dir_override =
+        env::var(format!("DAWN_BUILD_DIR_{}", build_target.replace('-', "_")))
+            .or_else(|_| env::var("DAWN_BUILD_DIR"));
+
+    let dawn_build_dir = match dawn_build_dir_override {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => match env::var("DAWN_PREBUILT_URL") {
+            Ok(url) => download_prebuilt(&root, &build_target, &url)?,
+            Err(_) => {
+                let local_build_dir = root.join("build/dawn").join(&build_target);
+                if !local_build_dir.exists() {
+                    return Err(format!(
+                        "no dawn build found at {} (build dawn using build.py, or set DAWN_PREBUILT_URL to download prebuilt libraries)",
+                        local_build_dir.display()
+                    )
+                    .into());
+                }
+                local_build_dir
+            }
+        },
+    };
 
     println!("cargo:rerun-if-env-changed=DAWN_SRC_DIR");
     println!("cargo:rerun-if-env-changed=DAWN_BUILD_DIR");
+    println!("cargo:rerun-if-env-changed=DAWN_PREBUILT_URL");
 
     let dawn_lib_dir = dawn_build_dir.join("lib");
     let dawn_gen_dir = dawn_build_dir.join("gen");
 
     println!("cargo:rustc-link-search=native={}", dawn_lib_dir.display());
 
-    let common_libs = [
+    let target_os = env::var("CARGO_CFG_TARGET_OS")?;
+    let target_family = env::var("CARGO_CFG_TARGET_FAMILY")?;
+
+    let vulkan = env::var_os("CARGO_FEATURE_DAWN_VULKAN").is_some();
+    let d3d12 = env::var_os("CARGO_FEATURE_DAWN_D3D12").is_some() && target_os == "windows";
+    let metal = env::var_os("CARGO_FEATURE_DAWN_METAL").is_some() && target_os == "macos";
+    let x11 = env::var_os("CARGO_FEATURE_DAWN_X11").is_some() && target_os == "linux";
+
+    let mut dawn_libs = vec![
         "absl_base",
         "absl_int128",
         "absl_log_severity",
@@ -41,16 +67,16 @@ fn main() -> Result<(), Box<dyn Error>> {
         "dawn_platform",
         "dawn_proc",
         "dawncpp_headers",
-        "SPIRV-Tools-opt",
-        "SPIRV-Tools",
         "tint_diagnostic_utils",
         "tint",
     ];
 
-    let target_os = env::var("CARGO_CFG_TARGET_OS")?;
-    let target_family = env::var("CARGO_CFG_TARGET_FAMILY")?;
+    // SPIRV-Tools is only used by dawn for the vulkan backend
+    if vulkan {
+        dawn_libs.extend(["SPIRV-Tools-opt", "SPIRV-Tools"]);
+    }
 
-    for lib in common_libs {
+    for lib in dawn_libs {
         let lib_name = if target_family == "windows" {
             format!("{lib}.lib")
         } else if target_family == "unix" {
@@ -76,17 +102,24 @@ fn main() -> Result<(), Box<dyn Error>> {
         println!("cargo:rustc-link-lib=static={lib}");
     }
 
-    // Additional platform-specific libraries we need to link
-    let libs: &[_] = match target_os.as_str() {
-        "windows" => &["dxguid"],
-        "macos" => &[
+    // Additional platform-specific libraries needed by the enabled backends
+    let mut libs = vec![];
+
+    if d3d12 {
+        libs.push("dxguid");
+    }
+
+    if metal {
+        libs.extend([
             "framework=Foundation",
             "framework=IOKit",
             "framework=IOSurface",
-        ],
-        "linux" => &["X11"],
-        _ => &[],
-    };
+        ]);
+    }
+
+    if vulkan && x11 {
+        libs.push("X11");
+    }
 
     for lib in libs {
         println!("cargo:rustc-link-lib={lib}");
@@ -126,3 +159,68 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+/// Downloads and extracts a bundle of prebuilt dawn libraries from `url`, returning the path to
+/// the extracted build directory.
+///
+/// No bundles are published by wgslsmith, so this is only done when `DAWN_PREBUILT_URL` is set.
+/// The bundle must be a `.tar.gz` archive containing a `dawn-static-<target>` directory with the
+/// `lib` and `gen` directories of a dawn build, made from the same commit as the dawn submodule.
+/// The extracted bundle is cached in `build/dawn-prebuilt` until the URL changes.
+fn download_prebuilt(root: &Path, target: &str, url: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let bundle = format!("dawn-static-{target}");
+
+    let cache_dir = root.join("build/dawn-prebuilt");
+    let build_dir = cache_dir.join(&bundle);
+
+    // The URL that the cached bundle was downloaded from, so that changing it downloads it again
+    let source_file = cache_dir.join(format!("{bundle}.url"));
+
+    if build_dir.join("lib").exists() {
+        if std::fs::read_to_string(&source_file).ok().as_deref() == Some(url) {
+            return Ok(build_dir);
+        }
+
+        std::fs::remove_dir_all(&build_dir)?;
+    }
+
+    println!("cargo:warning=downloading prebuilt dawn libraries from {url}");
+
+    std::fs::create_dir_all(&cache_dir)?;
+
+    let archive = cache_dir.join(format!("{bundle}.tar.gz"));
+
+    let status = Command::new("curl")
+        .args(["-fsSL", "-o"])
+        .arg(&archive)
+        .arg(url)
+        .status()?;
+
+    if !status.success() {
+        return Err(format!(
+            "failed to download prebuilt dawn libraries from {url} (unset DAWN_PREBUILT_URL to use a local build)"
+        )
+        .into());
+    }
+
+    let status = Command::new("tar")
+        .arg("-xzf")
+        .arg(&archive)
+        .arg("-C")
+        .arg(&cache_dir)
+        .status()?;
+
+    if !status.success() {
+        return Err("failed to extract prebuilt dawn libraries".into());
+    }
+
+    std::fs::remove_file(&archive)?;
+
+    if !build_dir.join("lib").exists() {
+        return Err(format!("prebuilt dawn bundle from {url} did not contain {bundle}/lib").into());
+    }
+
+    std::fs::write(source_file, url)?;
+
+    Ok(build_dir)
+}
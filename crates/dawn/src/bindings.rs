@@ -1,17 +1,69 @@
+use std::cell::RefCell;
 use std::ffi::{c_void, CStr, CString};
 use std::mem::zeroed;
 use std::os::raw::c_char;
 use std::ptr::{null, null_mut};
 
 use crate::dawn;
+use crate::error::{Error, ErrorKind};
 use crate::webgpu::*;
 use futures::channel::oneshot;
 
 pub struct Instance(*mut c_void);
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackendType {
+    Null,
+    D3D11,
+    D3D12,
+    Metal,
+    Vulkan,
+    OpenGL,
+    OpenGLES,
+}
+
+impl BackendType {
+    #[allow(non_upper_case_globals)]
+    fn from_raw(backend: WGPUBackendType) -> Option<BackendType> {
+        Some(match backend {
+            WGPUBackendType_WGPUBackendType_Null => BackendType::Null,
+            WGPUBackendType_WGPUBackendType_D3D11 => BackendType::D3D11,
+            WGPUBackendType_WGPUBackendType_D3D12 => BackendType::D3D12,
+            WGPUBackendType_WGPUBackendType_Metal => BackendType::Metal,
+            WGPUBackendType_WGPUBackendType_Vulkan => BackendType::Vulkan,
+            WGPUBackendType_WGPUBackendType_OpenGL => BackendType::OpenGL,
+            WGPUBackendType_WGPUBackendType_OpenGLES => BackendType::OpenGLES,
+            _ => return None,
+        })
+    }
+
+    fn to_raw(self) -> WGPUBackendType {
+        match self {
+            BackendType::Null => WGPUBackendType_WGPUBackendType_Null,
+            BackendType::D3D11 => WGPUBackendType_WGPUBackendType_D3D11,
+            BackendType::D3D12 => WGPUBackendType_WGPUBackendType_D3D12,
+            BackendType::Metal => WGPUBackendType_WGPUBackendType_Metal,
+            BackendType::Vulkan => WGPUBackendType_WGPUBackendType_Vulkan,
+            BackendType::OpenGL => WGPUBackendType_WGPUBackendType_OpenGL,
+            BackendType::OpenGLES => WGPUBackendType_WGPUBackendType_OpenGLES,
+        }
+    }
+}
+
+/// Toggles to force on or off when creating a device.
+///
+/// See `src/dawn/native/Toggles.cpp` in the dawn repository for the available toggles.
+#[derive(Clone, Debug, Default)]
+pub struct DeviceToggles {
+    pub enabled: Vec<String>,
+    pub disabled: Vec<String>,
+}
+
 pub struct AdapterProperties {
     pub name: String,
-    pub backend: WGPUBackendType,
+    /// Description of the driver, which usually includes its version.
+    pub driver_description: String,
+    pub backend: BackendType,
     pub device_id: u32,
 }
 
@@ -23,12 +75,28 @@ impl Instance {
     pub fn enumerate_adapters(&self) -> Vec<AdapterProperties> {
         #[allow(non_upper_case_globals)]
         unsafe extern "C" fn cb(info: *const WGPUAdapterProperties, userdata: *mut c_void) {
+            // Skip adapters for backends that we don't know about
+            let backend = match BackendType::from_raw((*info).backendType) {
+                Some(backend) => backend,
+                None => return,
+            };
+
+            // Not every backend describes its driver
+            let driver_description = if (*info).driverDescription.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr((*info).driverDescription)
+                    .to_string_lossy()
+                    .into_owned()
+            };
+
             (userdata as *mut Vec<AdapterProperties>)
                 .as_mut()
                 .unwrap()
                 .push(AdapterProperties {
-                    name: CStr::from_ptr((*info).name).to_str().unwrap().to_owned(),
-                    backend: (*info).backendType,
+                    name: CStr::from_ptr((*info).name).to_string_lossy().into_owned(),
+                    driver_description,
+                    backend,
                     device_id: (*info).deviceID,
                 });
         }
@@ -42,20 +110,56 @@ impl Instance {
         adapters
     }
 
-    pub fn create_device(self, backend: WGPUBackendType, device_id: u32) -> Option<Device> {
-        let handle = unsafe { dawn::create_device(self.0, backend, device_id) };
+    /// Creates a device for the adapter with the given backend and device id.
+    ///
+    /// Returns `None` if no matching adapter was found.
+    pub fn create_device(
+        self,
+        backend: BackendType,
+        device_id: u32,
+        toggles: &DeviceToggles,
+    ) -> Option<Device> {
+        let to_c_strings = |toggles: &[String]| {
+            toggles
+                .iter()
+                .map(|toggle| CString::new(toggle.as_str()).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let enabled = to_c_strings(&toggles.enabled);
+        let disabled = to_c_strings(&toggles.disabled);
+
+        let enabled_ptrs = enabled.iter().map(|it| it.as_ptr()).collect::<Vec<_>>();
+        let disabled_ptrs = disabled.iter().map(|it| it.as_ptr()).collect::<Vec<_>>();
+
+        let handle = unsafe {
+            dawn::create_device(
+                self.0,
+                backend.to_raw(),
+                device_id,
+                enabled_ptrs.as_ptr(),
+                enabled_ptrs.len(),
+                disabled_ptrs.as_ptr(),
+                disabled_ptrs.len(),
+            )
+        };
 
         if handle.is_null() {
-            panic!("failed to create dawn device");
+            return None;
         }
 
+        let errors = Box::new(RefCell::new(vec![]));
+        let userdata = &*errors as *const RefCell<Vec<Error>> as *mut c_void;
+
         unsafe {
-            wgpuDeviceSetUncapturedErrorCallback(handle, Some(default_error_callback), null_mut());
+            wgpuDeviceSetUncapturedErrorCallback(handle, Some(uncaptured_error_callback), userdata);
+            wgpuDeviceSetDeviceLostCallback(handle, Some(device_lost_callback), userdata);
         }
 
         let device = Device {
             _instance: self,
             handle,
+            errors,
         };
 
         Some(device)
@@ -79,6 +183,10 @@ impl Drop for Instance {
 pub struct Device {
     _instance: Instance,
     handle: *mut crate::webgpu::WGPUDeviceImpl,
+    /// Errors reported through the uncaptured error and device lost callbacks.
+    ///
+    /// This is boxed so that it has a stable address to pass to the callbacks.
+    errors: Box<RefCell<Vec<Error>>>,
 }
 
 impl Device {
@@ -88,7 +196,7 @@ impl Device {
         }
     }
 
-    pub fn create_shader_module(&self, source: &str) -> ShaderModule {
+    pub fn create_shader_module(&self, source: &str) -> Result<ShaderModule, Error> {
         let source = CString::new(source).unwrap();
         ErrorScope::new(self, "shader module creation failed").execute(|| unsafe {
             let wgsl_descriptor = WGPUShaderModuleWGSLDescriptor {
@@ -114,7 +222,7 @@ impl Device {
         &self,
         shader_module: &ShaderModule,
         entrypoint: &str,
-    ) -> ComputePipeline {
+    ) -> Result<ComputePipeline, Error> {
         ErrorScope::new(self, "compute pipeline creation failed").execute(|| unsafe {
             let entrypoint = CString::new(entrypoint).unwrap();
             ComputePipeline {
@@ -132,7 +240,177 @@ impl Device {
                             nextInChain: null(),
                         },
                     },
-                ),
+                )
+                .assert_not_null(),
+            }
+        })
+    }
+
+    /// Creates a compute pipeline asynchronously, which lets dawn compile it on a worker thread.
+    ///
+    /// The returned receiver completes once the device has been ticked enough for creation to
+    /// finish. Pipelines created together may be compiled concurrently.
+    pub fn create_compute_pipeline_async(
+        &self,
+        shader_module: &ShaderModule,
+        entrypoint: &str,
+    ) -> oneshot::Receiver<Result<ComputePipeline, Error>> {
+        #[allow(non_upper_case_globals)]
+        unsafe extern "C" fn callback(
+            status: WGPUCreatePipelineAsyncStatus,
+            pipeline: WGPUComputePipeline,
+            message: *const c_char,
+            userdata: *mut c_void,
+        ) {
+            let result = match status {
+                // Panicking here would unwind across the ffi boundary and abort the process
+                WGPUCreatePipelineAsyncStatus_WGPUCreatePipelineAsyncStatus_Success
+                    if pipeline.is_null() =>
+                {
+                    Err(Error::new(
+                        ErrorKind::Unknown,
+                        "async compute pipeline creation succeeded without returning a pipeline",
+                    ))
+                }
+                WGPUCreatePipelineAsyncStatus_WGPUCreatePipelineAsyncStatus_Success => {
+                    Ok(ComputePipeline { handle: pipeline })
+                }
+                status => {
+                    let kind = match status {
+                        WGPUCreatePipelineAsyncStatus_WGPUCreatePipelineAsyncStatus_ValidationError => {
+                            ErrorKind::Validation
+                        }
+                        WGPUCreatePipelineAsyncStatus_WGPUCreatePipelineAsyncStatus_DeviceLost => {
+                            ErrorKind::DeviceLost
+                        }
+                        _ => ErrorKind::Unknown,
+                    };
+
+                    let message = if message.is_null() {
+                        String::new()
+                    } else {
+                        CStr::from_ptr(message).to_string_lossy().into_owned()
+                    };
+
+                    Err(Error::new(
+                        kind,
+                        format!("async compute pipeline creation failed\n{message}"),
+                    ))
+                }
+            };
+
+            let tx =
+                Box::from_raw(userdata as *mut oneshot::Sender<Result<ComputePipeline, Error>>);
+
+            // The receiver may have been dropped if the caller is no longer interested
+            let _ = tx.send(result);
+        }
+
+        let (tx, rx) = oneshot::channel();
+        let tx = Box::new(tx);
+        let entrypoint = CString::new(entrypoint).unwrap();
+
+        unsafe {
+            wgpuDeviceCreateComputePipelineAsync(
+                self.handle,
+                &WGPUComputePipelineDescriptor {
+                    label: null(),
+                    nextInChain: null(),
+                    layout: null_mut(),
+                    compute: WGPUProgrammableStageDescriptor {
+                        constantCount: 0,
+                        constants: null(),
+                        module: shader_module.handle,
+                        entryPoint: entrypoint.as_ptr(),
+                        nextInChain: null(),
+                    },
+                },
+                Some(callback),
+                Box::into_raw(tx) as _,
+            );
+        }
+
+        rx
+    }
+
+    /// Creates a render pipeline which draws triangles into a single `rgba32uint` colour target,
+    /// and optionally a `depth24plus-stencil8` attachment.
+    ///
+    /// `write_mask` is a mask of the colour channels which are written, with r = 1, g = 2, b = 4
+    /// and a = 8.
+    pub fn create_render_pipeline(
+        &self,
+        shader_module: &ShaderModule,
+        vertex_entrypoint: &str,
+        fragment_entrypoint: &str,
+        format: RenderTargetFormat,
+        write_mask: u32,
+        depth_stencil: Option<&DepthStencilState>,
+    ) -> Result<RenderPipeline, Error> {
+        ErrorScope::new(self, "render pipeline creation failed").execute(|| unsafe {
+            let vertex_entrypoint = CString::new(vertex_entrypoint).unwrap();
+            let fragment_entrypoint = CString::new(fragment_entrypoint).unwrap();
+
+            let target = WGPUColorTargetState {
+                format: format.to_raw(),
+                writeMask: write_mask & WGPUColorWriteMask_WGPUColorWriteMask_All,
+                ..zeroed()
+            };
+
+            let depth_stencil = depth_stencil.map(|state| {
+                let face = WGPUStencilFaceState {
+                    compare: state.stencil_compare.to_raw(),
+                    failOp: state.stencil_fail_op.to_raw(),
+                    depthFailOp: state.depth_fail_op.to_raw(),
+                    passOp: state.pass_op.to_raw(),
+                };
+
+                WGPUDepthStencilState {
+                    format: WGPUTextureFormat_WGPUTextureFormat_Depth24PlusStencil8,
+                    depthWriteEnabled: state.depth_write,
+                    depthCompare: state.depth_compare.to_raw(),
+                    stencilFront: face,
+                    stencilBack: face,
+                    stencilReadMask: !0,
+                    stencilWriteMask: !0,
+                    ..zeroed()
+                }
+            });
+
+            let fragment = WGPUFragmentState {
+                module: shader_module.handle,
+                entryPoint: fragment_entrypoint.as_ptr(),
+                targetCount: 1,
+                targets: &target,
+                ..zeroed()
+            };
+
+            RenderPipeline {
+                handle: wgpuDeviceCreateRenderPipeline(
+                    self.handle,
+                    &WGPURenderPipelineDescriptor {
+                        vertex: WGPUVertexState {
+                            module: shader_module.handle,
+                            entryPoint: vertex_entrypoint.as_ptr(),
+                            ..zeroed()
+                        },
+                        primitive: WGPUPrimitiveState {
+                            topology: WGPUPrimitiveTopology_WGPUPrimitiveTopology_TriangleList,
+                            ..zeroed()
+                        },
+                        multisample: WGPUMultisampleState {
+                            count: 1,
+                            mask: !0,
+                            ..zeroed()
+                        },
+                        fragment: &fragment,
+                        depthStencil: depth_stencil
+                            .as_ref()
+                            .map_or(null(), |state| state as *const _),
+                        ..zeroed()
+                    },
+                )
+                .assert_not_null(),
             }
         })
     }
@@ -142,7 +420,7 @@ impl Device {
         mapped: bool,
         size: usize,
         usage: DeviceBufferUsage,
-    ) -> DeviceBuffer {
+    ) -> Result<DeviceBuffer, Error> {
         ErrorScope::new(self, "buffer creation failed").execute(|| unsafe {
             DeviceBuffer {
                 handle: wgpuDeviceCreateBuffer(
@@ -160,11 +438,70 @@ impl Device {
         })
     }
 
+    /// Creates a square texture which can be rendered to and copied from.
+    pub fn create_render_target(
+        &self,
+        size: u32,
+        format: RenderTargetFormat,
+    ) -> Result<Texture, Error> {
+        ErrorScope::new(self, "texture creation failed").execute(|| unsafe {
+            Texture {
+                handle: wgpuDeviceCreateTexture(
+                    self.handle,
+                    &WGPUTextureDescriptor {
+                        usage: (WGPUTextureUsage_WGPUTextureUsage_RenderAttachment
+                            | WGPUTextureUsage_WGPUTextureUsage_CopySrc)
+                            as _,
+                        dimension: WGPUTextureDimension_WGPUTextureDimension_2D,
+                        size: WGPUExtent3D {
+                            width: size,
+                            height: size,
+                            depthOrArrayLayers: 1,
+                        },
+                        format: format.to_raw(),
+                        mipLevelCount: 1,
+                        sampleCount: 1,
+                        ..zeroed()
+                    },
+                )
+                .assert_not_null(),
+                size,
+            }
+        })
+    }
+
+    /// Creates a square `depth24plus-stencil8` texture which can be used as the depth/stencil
+    /// attachment of a render pass.
+    pub fn create_depth_stencil_target(&self, size: u32) -> Result<Texture, Error> {
+        ErrorScope::new(self, "texture creation failed").execute(|| unsafe {
+            Texture {
+                handle: wgpuDeviceCreateTexture(
+                    self.handle,
+                    &WGPUTextureDescriptor {
+                        usage: WGPUTextureUsage_WGPUTextureUsage_RenderAttachment as _,
+                        dimension: WGPUTextureDimension_WGPUTextureDimension_2D,
+                        size: WGPUExtent3D {
+                            width: size,
+                            height: size,
+                            depthOrArrayLayers: 1,
+                        },
+                        format: WGPUTextureFormat_WGPUTextureFormat_Depth24PlusStencil8,
+                        mipLevelCount: 1,
+                        sampleCount: 1,
+                        ..zeroed()
+                    },
+                )
+                .assert_not_null(),
+                size,
+            }
+        })
+    }
+
     pub fn create_bind_group(
         &self,
         layout: &BindGroupLayout,
         entries: &[BindGroupEntry],
-    ) -> BindGroup {
+    ) -> Result<BindGroup, Error> {
         ErrorScope::new(self, "bind group creation failed").execute(|| unsafe {
             let entries = entries.iter().map(|e| e.into()).collect::<Vec<_>>();
             BindGroup {
@@ -183,7 +520,7 @@ impl Device {
         })
     }
 
-    pub fn create_command_encoder(&self) -> CommandEncoder {
+    pub fn create_command_encoder(&self) -> Result<CommandEncoder, Error> {
         ErrorScope::new(self, "command encoder creation failed").execute(|| unsafe {
             CommandEncoder {
                 handle: wgpuDeviceCreateCommandEncoder(self.handle, &zeroed()).assert_not_null(),
@@ -196,11 +533,36 @@ impl Device {
             wgpuDeviceTick(self.handle);
         }
     }
+
+    /// Returns the errors that have been reported outside of an error scope since the last call,
+    /// including the device being lost.
+    pub fn take_errors(&self) -> Vec<Error> {
+        self.errors.borrow_mut().drain(..).collect()
+    }
+
+    /// Returns the first error that has been reported outside of an error scope since the last
+    /// call, if any.
+    pub fn check_errors(&self) -> Result<(), Error> {
+        let mut errors = self.take_errors().into_iter();
+        match errors.next() {
+            None => Ok(()),
+            Some(mut error) => {
+                for other in errors {
+                    error.message = format!("{}\n{other}", error.message);
+                }
+                Err(error)
+            }
+        }
+    }
 }
 
 impl Drop for Device {
     fn drop(&mut self) {
         unsafe {
+            // Other objects may keep the device alive after this, so we need to make sure that the
+            // callbacks don't try to access `errors` once it has been freed
+            wgpuDeviceSetUncapturedErrorCallback(self.handle, None, null_mut());
+            wgpuDeviceSetDeviceLostCallback(self.handle, None, null_mut());
             wgpuDeviceRelease(self.handle);
         }
     }
@@ -260,6 +622,162 @@ impl Drop for ComputePipeline {
     }
 }
 
+pub struct RenderPipeline {
+    handle: WGPURenderPipeline,
+}
+
+impl Drop for RenderPipeline {
+    fn drop(&mut self) {
+        unsafe {
+            wgpuRenderPipelineRelease(self.handle);
+        }
+    }
+}
+
+impl RenderPipeline {
+    pub fn get_bind_group_layout(&self, index: u32) -> BindGroupLayout {
+        unsafe {
+            BindGroupLayout {
+                handle: wgpuRenderPipelineGetBindGroupLayout(self.handle, index).assert_not_null(),
+            }
+        }
+    }
+}
+
+/// Texel formats that the render target can have.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderTargetFormat {
+    R32Uint,
+    RG32Uint,
+    RGBA32Uint,
+}
+
+impl RenderTargetFormat {
+    fn to_raw(self) -> WGPUTextureFormat {
+        match self {
+            RenderTargetFormat::R32Uint => WGPUTextureFormat_WGPUTextureFormat_R32Uint,
+            RenderTargetFormat::RG32Uint => WGPUTextureFormat_WGPUTextureFormat_RG32Uint,
+            RenderTargetFormat::RGBA32Uint => WGPUTextureFormat_WGPUTextureFormat_RGBA32Uint,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompareFunction {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl CompareFunction {
+    fn to_raw(self) -> WGPUCompareFunction {
+        match self {
+            CompareFunction::Never => WGPUCompareFunction_WGPUCompareFunction_Never,
+            CompareFunction::Less => WGPUCompareFunction_WGPUCompareFunction_Less,
+            CompareFunction::Equal => WGPUCompareFunction_WGPUCompareFunction_Equal,
+            CompareFunction::LessEqual => WGPUCompareFunction_WGPUCompareFunction_LessEqual,
+            CompareFunction::Greater => WGPUCompareFunction_WGPUCompareFunction_Greater,
+            CompareFunction::NotEqual => WGPUCompareFunction_WGPUCompareFunction_NotEqual,
+            CompareFunction::GreaterEqual => WGPUCompareFunction_WGPUCompareFunction_GreaterEqual,
+            CompareFunction::Always => WGPUCompareFunction_WGPUCompareFunction_Always,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StencilOperation {
+    Keep,
+    Zero,
+    Replace,
+    Invert,
+    IncrementClamp,
+    DecrementClamp,
+    IncrementWrap,
+    DecrementWrap,
+}
+
+impl StencilOperation {
+    fn to_raw(self) -> WGPUStencilOperation {
+        match self {
+            StencilOperation::Keep => WGPUStencilOperation_WGPUStencilOperation_Keep,
+            StencilOperation::Zero => WGPUStencilOperation_WGPUStencilOperation_Zero,
+            StencilOperation::Replace => WGPUStencilOperation_WGPUStencilOperation_Replace,
+            StencilOperation::Invert => WGPUStencilOperation_WGPUStencilOperation_Invert,
+            StencilOperation::IncrementClamp => {
+                WGPUStencilOperation_WGPUStencilOperation_IncrementClamp
+            }
+            StencilOperation::DecrementClamp => {
+                WGPUStencilOperation_WGPUStencilOperation_DecrementClamp
+            }
+            StencilOperation::IncrementWrap => {
+                WGPUStencilOperation_WGPUStencilOperation_IncrementWrap
+            }
+            StencilOperation::DecrementWrap => {
+                WGPUStencilOperation_WGPUStencilOperation_DecrementWrap
+            }
+        }
+    }
+}
+
+/// Depth and stencil state of a render pipeline, which uses the same stencil state for front and
+/// back faces.
+#[derive(Clone, Debug)]
+pub struct DepthStencilState {
+    pub depth_compare: CompareFunction,
+    pub depth_write: bool,
+    pub stencil_compare: CompareFunction,
+    pub stencil_fail_op: StencilOperation,
+    pub depth_fail_op: StencilOperation,
+    pub pass_op: StencilOperation,
+}
+
+/// Depth/stencil attachment of a render pass, which is cleared to the given values.
+pub struct DepthStencilAttachment<'a> {
+    pub view: &'a TextureView,
+    pub depth_clear: f32,
+    pub stencil_clear: u32,
+}
+
+pub struct Texture {
+    handle: WGPUTexture,
+    size: u32,
+}
+
+impl Texture {
+    pub fn create_view(&self) -> TextureView {
+        unsafe {
+            TextureView {
+                handle: wgpuTextureCreateView(self.handle, null()).assert_not_null(),
+            }
+        }
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            wgpuTextureRelease(self.handle);
+        }
+    }
+}
+
+pub struct TextureView {
+    handle: WGPUTextureView,
+}
+
+impl Drop for TextureView {
+    fn drop(&mut self) {
+        unsafe {
+            wgpuTextureViewRelease(self.handle);
+        }
+    }
+}
+
 pub struct DeviceBuffer {
     handle: WGPUBuffer,
 }
@@ -281,23 +799,39 @@ bitflags::bitflags! {
 }
 
 impl DeviceBuffer {
-    pub fn map_async(&self, mode: DeviceBufferMapMode, size: usize) -> oneshot::Receiver<()> {
-        unsafe {
-            unsafe extern "C" fn map_callback(
-                res: WGPUBufferMapAsyncStatus,
-                userdata: *mut c_void,
-            ) {
-                assert_eq!(
-                    res,
-                    WGPUBufferMapAsyncStatus_WGPUBufferMapAsyncStatus_Success
-                );
-                let mut tx = Box::from_raw(userdata as *mut Option<oneshot::Sender<()>>);
-                (*tx).take().unwrap().send(()).unwrap();
-            }
+    /// Maps the buffer for reading.
+    ///
+    /// The returned receiver completes once the device has been ticked enough for the mapping to
+    /// finish.
+    pub fn map_async(
+        &self,
+        mode: DeviceBufferMapMode,
+        size: usize,
+    ) -> oneshot::Receiver<Result<(), Error>> {
+        #[allow(non_upper_case_globals)]
+        unsafe extern "C" fn map_callback(res: WGPUBufferMapAsyncStatus, userdata: *mut c_void) {
+            let result = match res {
+                WGPUBufferMapAsyncStatus_WGPUBufferMapAsyncStatus_Success => Ok(()),
+                WGPUBufferMapAsyncStatus_WGPUBufferMapAsyncStatus_DeviceLost => Err(Error::new(
+                    ErrorKind::DeviceLost,
+                    "device was lost while mapping buffer",
+                )),
+                status => Err(Error::new(
+                    ErrorKind::Unknown,
+                    format!("buffer mapping failed with status {status}"),
+                )),
+            };
+
+            let tx = Box::from_raw(userdata as *mut oneshot::Sender<Result<(), Error>>);
+
+            // The receiver may have been dropped if the caller is no longer interested
+            let _ = tx.send(result);
+        }
 
-            let (tx, rx) = oneshot::channel::<()>();
-            let tx = Box::new(Some(tx));
+        let (tx, rx) = oneshot::channel();
+        let tx = Box::new(tx);
 
+        unsafe {
             wgpuBufferMapAsync(
                 self.handle,
                 mode.bits as _,
@@ -306,21 +840,27 @@ impl DeviceBuffer {
                 Some(map_callback),
                 Box::into_raw(tx) as _,
             );
-
-            rx
         }
+
+        rx
     }
 
+    /// Returns the mapped contents of the buffer.
+    ///
+    /// Panics if the buffer is not currently mapped.
     pub fn get_mapped_range(&mut self, size: usize) -> &mut [u8] {
         unsafe {
-            let ptr = wgpuBufferGetMappedRange(self.handle, 0, size as _);
+            let ptr = wgpuBufferGetMappedRange(self.handle, 0, size as _).assert_not_null();
             std::slice::from_raw_parts_mut(ptr as _, size)
         }
     }
 
+    /// Returns the mapped contents of the buffer.
+    ///
+    /// Panics if the buffer is not currently mapped.
     pub fn get_const_mapped_range(&self, size: usize) -> &[u8] {
         unsafe {
-            let ptr = wgpuBufferGetConstMappedRange(self.handle, 0, size as _);
+            let ptr = wgpuBufferGetConstMappedRange(self.handle, 0, size as _).assert_not_null();
             std::slice::from_raw_parts(ptr as _, size)
         }
     }
@@ -415,6 +955,76 @@ impl CommandEncoder {
         }
     }
 
+    /// Begins a render pass which clears `target` to zero and stores the results.
+    pub fn begin_render_pass(
+        &self,
+        target: &TextureView,
+        depth_stencil: Option<&DepthStencilAttachment>,
+    ) -> RenderPassEncoder {
+        unsafe {
+            let attachment = WGPURenderPassColorAttachment {
+                view: target.handle,
+                loadOp: WGPULoadOp_WGPULoadOp_Clear,
+                storeOp: WGPUStoreOp_WGPUStoreOp_Store,
+                ..zeroed()
+            };
+
+            let depth_stencil = depth_stencil.map(|it| WGPURenderPassDepthStencilAttachment {
+                view: it.view.handle,
+                depthLoadOp: WGPULoadOp_WGPULoadOp_Clear,
+                depthStoreOp: WGPUStoreOp_WGPUStoreOp_Store,
+                depthClearValue: it.depth_clear,
+                stencilLoadOp: WGPULoadOp_WGPULoadOp_Clear,
+                stencilStoreOp: WGPUStoreOp_WGPUStoreOp_Store,
+                stencilClearValue: it.stencil_clear,
+                ..zeroed()
+            });
+
+            RenderPassEncoder {
+                handle: wgpuCommandEncoderBeginRenderPass(
+                    self.handle,
+                    &WGPURenderPassDescriptor {
+                        colorAttachmentCount: 1,
+                        colorAttachments: &attachment,
+                        depthStencilAttachment: depth_stencil
+                            .as_ref()
+                            .map_or(null(), |it| it as *const _),
+                        ..zeroed()
+                    },
+                )
+                .assert_not_null(),
+            }
+        }
+    }
+
+    /// Copies the whole of a texture into a buffer, with rows which are `bytes_per_row` apart.
+    pub fn copy_texture_to_buffer(&self, src: &Texture, dst: &DeviceBuffer, bytes_per_row: u32) {
+        unsafe {
+            wgpuCommandEncoderCopyTextureToBuffer(
+                self.handle,
+                &WGPUImageCopyTexture {
+                    texture: src.handle,
+                    ..zeroed()
+                },
+                &WGPUImageCopyBuffer {
+                    layout: WGPUTextureDataLayout {
+                        offset: 0,
+                        bytesPerRow: bytes_per_row,
+                        rowsPerImage: src.size,
+                        ..zeroed()
+                    },
+                    buffer: dst.handle,
+                    ..zeroed()
+                },
+                &WGPUExtent3D {
+                    width: src.size,
+                    height: src.size,
+                    depthOrArrayLayers: 1,
+                },
+            );
+        }
+    }
+
     pub fn finish(self) -> CommandBuffer {
         unsafe {
             CommandBuffer {
@@ -465,6 +1075,45 @@ impl Drop for ComputePassEncoder {
     }
 }
 
+pub struct RenderPassEncoder {
+    handle: WGPURenderPassEncoder,
+}
+
+impl RenderPassEncoder {
+    pub fn set_pipeline(&self, pipeline: &RenderPipeline) {
+        unsafe {
+            wgpuRenderPassEncoderSetPipeline(self.handle, pipeline.handle);
+        }
+    }
+
+    pub fn set_bind_group(&self, index: u32, group: &BindGroup) {
+        unsafe {
+            wgpuRenderPassEncoderSetBindGroup(self.handle, index, group.handle, 0, [].as_ptr());
+        }
+    }
+
+    pub fn set_stencil_reference(&self, reference: u32) {
+        unsafe {
+            wgpuRenderPassEncoderSetStencilReference(self.handle, reference);
+        }
+    }
+
+    pub fn draw(&self, vertex_count: u32, instance_count: u32) {
+        unsafe {
+            wgpuRenderPassEncoderDraw(self.handle, vertex_count, instance_count, 0, 0);
+        }
+    }
+}
+
+impl Drop for RenderPassEncoder {
+    fn drop(&mut self) {
+        unsafe {
+            wgpuRenderPassEncoderEnd(self.handle);
+            wgpuRenderPassEncoderRelease(self.handle);
+        }
+    }
+}
+
 pub struct CommandBuffer {
     handle: WGPUCommandBuffer,
 }
@@ -503,6 +1152,7 @@ impl<T> PointerExt for *mut T {
     }
 }
 
+/// Captures validation errors produced while running a block of code.
 struct ErrorScope<'a> {
     device: &'a Device,
     message: &'a str,
@@ -513,7 +1163,7 @@ impl<'a> ErrorScope<'a> {
         ErrorScope { device, message }
     }
 
-    fn execute<T>(mut self, block: impl FnOnce() -> T) -> T {
+    fn execute<T>(self, block: impl FnOnce() -> T) -> Result<T, Error> {
         unsafe {
             wgpuDevicePushErrorScope(
                 self.device.handle,
@@ -526,58 +1176,63 @@ impl<'a> ErrorScope<'a> {
             message: *const c_char,
             userdata: *mut c_void,
         ) {
-            let scope = (userdata as *mut ErrorScope).as_mut().unwrap();
-
-            if error_type != WGPUErrorType_WGPUErrorType_Validation {
-                return;
-            }
-
-            if !message.is_null() {
-                let message = CStr::from_ptr(message).to_string_lossy();
-                eprintln!("{message}");
-            }
-
-            panic!("{}", scope.message);
+            *(userdata as *mut Option<Error>).as_mut().unwrap() =
+                Error::from_raw(error_type, message);
         }
 
         let result = block();
 
+        // Dawn native invokes the callback before returning from `PopErrorScope`, so it is safe to
+        // give it a pointer to the stack
+        let mut error = None;
+
         unsafe {
             wgpuDevicePopErrorScope(
                 self.device.handle,
                 Some(callback),
-                &mut self as *mut Self as *mut c_void,
+                &mut error as *mut Option<Error> as *mut c_void,
             );
         }
 
-        result
+        match error {
+            None => Ok(result),
+            Some(mut error) => {
+                error.message = format!("{}\n{}", self.message, error.message);
+                Err(error)
+            }
+        }
     }
 }
 
-unsafe extern "C" fn default_error_callback(
+unsafe extern "C" fn uncaptured_error_callback(
     error_type: WGPUErrorType,
     message: *const c_char,
-    _: *mut c_void,
+    userdata: *mut c_void,
 ) {
-    if !message.is_null() {
-        let message = CStr::from_ptr(message).to_string_lossy();
-        eprintln!("{message}");
+    if let Some(error) = Error::from_raw(error_type, message) {
+        (*(userdata as *const RefCell<Vec<Error>>))
+            .borrow_mut()
+            .push(error);
     }
+}
 
-    #[allow(non_upper_case_globals)]
-    match error_type {
-        WGPUErrorType_WGPUErrorType_Validation => {
-            panic!("validation error");
-        }
-        WGPUErrorType_WGPUErrorType_OutOfMemory => {
-            panic!("out of memory");
-        }
-        WGPUErrorType_WGPUErrorType_DeviceLost => {
-            panic!("the dawn device was lost");
-        }
-        WGPUErrorType_WGPUErrorType_Unknown => {
-            panic!("an unknown error occurred");
-        }
-        _ => {}
+unsafe extern "C" fn device_lost_callback(
+    reason: WGPUDeviceLostReason,
+    message: *const c_char,
+    userdata: *mut c_void,
+) {
+    // The device is always destroyed when it is dropped, which isn't an error
+    if reason == WGPUDeviceLostReason_WGPUDeviceLostReason_Destroyed {
+        return;
     }
+
+    let message = if message.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(message).to_string_lossy().into_owned()
+    };
+
+    (*(userdata as *const RefCell<Vec<Error>>))
+        .borrow_mut()
+        .push(Error::new(ErrorKind::DeviceLost, message));
 }
@@ -0,0 +1,75 @@
+use std::ffi::CStr;
+use std::fmt::Display;
+use std::os::raw::c_char;
+
+use crate::webgpu::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    Validation,
+    OutOfMemory,
+    DeviceLost,
+    Unknown,
+}
+
+/// An error reported by dawn.
+#[derive(Clone, Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl Error {
+    pub(crate) fn new(kind: ErrorKind, message: impl Into<String>) -> Error {
+        Error {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// Converts an error passed to a dawn callback, returning `None` if there was no error.
+    pub(crate) unsafe fn from_raw(
+        error_type: WGPUErrorType,
+        message: *const c_char,
+    ) -> Option<Error> {
+        #[allow(non_upper_case_globals)]
+        let kind = match error_type {
+            WGPUErrorType_WGPUErrorType_NoError => return None,
+            WGPUErrorType_WGPUErrorType_Validation => ErrorKind::Validation,
+            WGPUErrorType_WGPUErrorType_OutOfMemory => ErrorKind::OutOfMemory,
+            WGPUErrorType_WGPUErrorType_DeviceLost => ErrorKind::DeviceLost,
+            _ => ErrorKind::Unknown,
+        };
+
+        let message = if message.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(message).to_string_lossy().into_owned()
+        };
+
+        Some(Error { kind, message })
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Validation => write!(f, "validation error"),
+            ErrorKind::OutOfMemory => write!(f, "out of memory"),
+            ErrorKind::DeviceLost => write!(f, "device lost"),
+            ErrorKind::Unknown => write!(f, "unknown error"),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.message.is_empty() {
+            write!(f, "{}", self.kind)
+        } else {
+            write!(f, "{}: {}", self.kind, self.message.trim_end())
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -44,7 +44,11 @@ extern "C" void enumerate_adapters(
 extern "C" WGPUDevice create_device(
     const dawn_native::Instance* instance,
     WGPUBackendType backendType,
-    uint32_t deviceID
+    uint32_t deviceID,
+    const char* const* enabledToggles,
+    size_t enabledTogglesCount,
+    const char* const* disabledToggles,
+    size_t disabledTogglesCount
 ) {
     auto adapters = instance->GetAdapters();
 
@@ -60,6 +64,15 @@ extern "C" WGPUDevice create_device(
 
     if (!selectedAdapter) return nullptr;
 
+    WGPUDawnTogglesDeviceDescriptor toggles = {};
+    toggles.chain.sType = WGPUSType_DawnTogglesDeviceDescriptor;
+    toggles.forceEnabledTogglesCount = enabledTogglesCount;
+    toggles.forceEnabledToggles = enabledToggles;
+    toggles.forceDisabledTogglesCount = disabledTogglesCount;
+    toggles.forceDisabledToggles = disabledToggles;
+
     WGPUDeviceDescriptor descriptor = {};
+    descriptor.nextInChain = &toggles.chain;
+
     return selectedAdapter->CreateDevice(&descriptor);
 }
@@ -3,15 +3,19 @@
 #![allow(non_snake_case)]
 
 mod bindings;
+mod error;
 
 pub use bindings::*;
+pub use error::{Error, ErrorKind};
 
+/// Raw bindings to the webgpu C API.
 pub mod webgpu {
     include!(concat!(env!("OUT_DIR"), "/webgpu.rs"));
 }
 
 mod dawn {
     use std::ffi::c_void;
+    use std::os::raw::c_char;
 
     use crate::webgpu;
 
@@ -33,6 +37,10 @@ mod dawn {
             instance: *mut c_void,
             backend_type: webgpu::WGPUBackendType,
             device_id: u32,
+            enabled_toggles: *const *const c_char,
+            enabled_toggles_count: usize,
+            disabled_toggles: *const *const c_char,
+            disabled_toggles_count: usize,
         ) -> webgpu::WGPUDevice;
     }
 }
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+use ast::complexity::Complexity;
+use ast::stats::Stats;
+use ast::{StorageClass, VarQualifier};
+use eyre::{bail, eyre};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use reflection_types::PUSH_CONSTANTS_KEY;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::EnvFilter;
+
+use crate::identities;
+use crate::rng::{Pcg32, RecordingRng, RngExt, Tape, TapeRng, DECISION_VERSION};
+use crate::template::Template;
+use crate::{recondition, write_wgsl, Generator, LogFormat, Options};
+
+/// Maximum number of elements to allocate for a runtime sized array in the input data.
+const MAX_RUNTIME_ARRAY_LEN: u32 = 64;
+
+pub fn run(mut options: Options) -> eyre::Result<()> {
+    options.apply_preset();
+
+    let options = Rc::new(options);
+
+    init_logging(&options)?;
+
+    let seed = match options.seed {
+        Some(seed) => seed,
+        None => OsRng.next_u64(),
+    };
+
+    tracing::info!("generating shader from seed: {}", seed);
+
+    let mut rng = Pcg32::seeded(seed);
+
+    let mut replay = match &options.replay_tape {
+        Some(path) => {
+            let mut tape: Tape = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+            if tape.version != DECISION_VERSION {
+                tracing::warn!(
+                    "tape was recorded with decision version {} (current is {DECISION_VERSION}), so it may not reproduce the same program",
+                    tape.version
+                );
+            }
+            tape.mutate(&mut rng, options.tape_mutations);
+            Some(TapeRng::new(tape))
+        }
+        None => None,
+    };
+
+    let source: &mut dyn RngCore = match &mut replay {
+        Some(replay) => replay,
+        None => &mut rng,
+    };
+
+    let mut recorder = RecordingRng::new(source);
+
+    if let Some(path) = &options.template {
+        let template = Template::read(path).map_err(|e| eyre!(e))?;
+        return run_template(&options, &template, &mut recorder);
+    }
+
+    let (mut shader, expected_output, render_state) = {
+        let mut generator = Generator::new(&mut recorder, options.clone());
+        let shader = generator.gen_module();
+        (
+            shader,
+            generator.expected_output().map(<[u8]>::to_vec),
+            generator.render_state().cloned(),
+        )
+    };
+
+    // The rewrites use their own generator, so that the rest of the program is the same as without
+    // them
+    if let Some(rewrite_seed) = options.identity_rewrites {
+        identities::rewrite_identities(
+            &mut shader,
+            &mut Pcg32::seeded(rewrite_seed),
+            options.identity_rewrite_prob,
+        );
+    }
+
+    if let Some(path) = &options.record_tape {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        serde_json::to_writer(BufWriter::new(File::create(path)?), &recorder.into_tape())?;
+    }
+
+    if cfg!(debug_assertions) || options.typecheck {
+        if let Err(errors) = ast::typecheck::check(&shader) {
+            let errors = errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+            bail!("generated shader failed type checking (seed: {seed}):\n{errors}");
+        }
+    }
+
+    // Statistics are collected before reconditioning, since they're mostly useful for tuning the
+    // generator
+    if options.stats || options.stats_json.is_some() {
+        let stats = Stats::collect(&shader);
+
+        if options.stats {
+            eprint!("{stats}");
+            let complexity = Complexity::collect(&shader).score();
+            eprintln!("complexity:      {complexity:.1}");
+        }
+
+        if let Some(path) = &options.stats_json {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            serde_json::to_writer(BufWriter::new(File::create(path)?), &stats)?;
+        }
+    }
+
+    if options.recondition {
+        shader = recondition(shader, &options)?;
+    }
+
+    if let Some(path) = &options.ast_json {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        serde_json::to_writer(BufWriter::new(File::create(path)?), &shader)?;
+    }
+
+    let mut output: Box<dyn io::Write> = if options.output == "-" {
+        Box::new(BufWriter::new(io::stdout()))
+    } else {
+        if let Some(dir) = Path::new(&options.output).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        Box::new(BufWriter::new(File::create(&options.output)?))
+    };
+
+    if !options.debug {
+        let mut init_data = HashMap::new();
+
+        for var in &shader.vars {
+            if let Some(VarQualifier { storage_class, .. }) = &var.qualifier {
+                let data: Vec<u8> = match storage_class {
+                    // Push constants don't have a group or binding, so they get their own key
+                    StorageClass::PushConstant => {
+                        let type_desc =
+                            common::Type::try_from(&var.data_type).map_err(|e| eyre!(e))?;
+                        let size = type_desc.buffer_size();
+                        let data = (0..size).map(|_| rng.next_u32() as u8).collect();
+                        init_data.insert(PUSH_CONSTANTS_KEY.to_owned(), data);
+                        continue;
+                    }
+                    StorageClass::Uniform => {
+                        let type_desc =
+                            common::Type::try_from(&var.data_type).map_err(|e| eyre!(e))?;
+                        let size = type_desc.buffer_size();
+                        (0..size).map(|_| rng.next_u32() as u8).collect()
+                    }
+                    // The harness decides the length of a runtime sized array from the size of
+                    // the input data, so this picks a random length for each program
+                    StorageClass::Storage if var.data_type.is_runtime_sized() => {
+                        let len = rng.gen_range(1..=MAX_RUNTIME_ARRAY_LEN);
+                        let type_desc = common::Type::with_runtime_array_len(&var.data_type, len)
+                            .map_err(|e| eyre!(e))?;
+                        vec![0; type_desc.buffer_size() as usize]
+                    }
+                    _ => continue,
+                };
+
+                let group = var.group_index().unwrap();
+                let binding = var.binding_index().unwrap();
+
+                init_data.insert(format!("{group}:{binding}"), data);
+            }
+        }
+
+        let init_data = serde_json::to_string(&init_data)?;
+
+        writeln!(output, "// {init_data}")?;
+        writeln!(output, "// Seed: {seed}")?;
+
+        if let Some(depth) = options.nesting_ladder {
+            writeln!(output, "// Nesting depth: {depth}")?;
+        }
+
+        if let Some(render_state) = render_state {
+            let render_state = serde_json::to_string(&render_state)?;
+            writeln!(output, "// Render state: {render_state}")?;
+        }
+
+        if let Some(expected_output) = expected_output {
+            let var = shader.vars.iter().find(|it| it.name == "s_output").unwrap();
+
+            let key = format!(
+                "{}:{}",
+                var.group_index().unwrap(),
+                var.binding_index().unwrap()
+            );
+
+            let expected = serde_json::to_string(&HashMap::from([(key, expected_output)]))?;
+            writeln!(output, "// Expected: {expected}")?;
+        }
+
+        writeln!(output)?;
+    }
+
+    if options.debug {
+        writeln!(output, "{shader:#?}")?;
+    } else {
+        write_wgsl(&mut output, &shader)?;
+    }
+
+    output.flush()?;
+
+    Ok(())
+}
+
+/// Fills the holes in a template, and writes the result to the output.
+///
+/// There's no input data header, since the resources are declared by the skeleton.
+fn run_template(
+    options: &Rc<Options>,
+    template: &Template,
+    rng: &mut dyn RngCore,
+) -> eyre::Result<()> {
+    let mut module = Generator::new(rng, options.clone()).gen_template_module(template);
+
+    if options.recondition {
+        module = recondition(module, options)?;
+    }
+
+    let source = template.fill(module);
+
+    if options.output == "-" {
+        io::stdout().write_all(source.as_bytes())?;
+    } else {
+        if let Some(dir) = Path::new(&options.output).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&options.output, source)?;
+    }
+
+    Ok(())
+}
+
+fn init_logging(options: &Options) -> eyre::Result<()> {
+    let filter = if let Some(log) = &options.log {
+        EnvFilter::from(log)
+    } else if let Some(level) = options.log_level {
+        EnvFilter::new(level.as_str())
+    } else {
+        EnvFilter::from_default_env()
+    };
+
+    let writer = match &options.log_file {
+        Some(path) => BoxMakeWriter::new(Mutex::new(File::create(path)?)),
+        None => BoxMakeWriter::new(io::stderr),
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_span_events(FmtSpan::ACTIVE)
+        .with_target(true)
+        .with_writer(writer)
+        .with_ansi(false)
+        .with_env_filter(filter);
+
+    match options.log_format {
+        LogFormat::Compact => builder.compact().init(),
+        LogFormat::Json => builder.json().init(),
+    }
+
+    Ok(())
+}
@@ -1,12 +1,32 @@
+mod accumulators;
+mod address_spaces;
+mod checksum;
+mod const_assert;
+mod const_oracle;
+mod consts;
 mod cx;
+mod diagnostics;
+mod duplicates;
 mod expr;
 mod fns;
+mod invocations;
+mod methods;
+mod nesting;
+mod push_constants;
+mod render;
+mod runtime_array;
 mod scope;
 mod stmt;
 mod structs;
+mod subgroups;
+mod uniform_load;
+mod unused;
 mod utils;
 
 pub mod builtins;
+pub mod identities;
+pub mod limits;
+pub mod template;
 
 use std::rc::Rc;
 
@@ -14,58 +34,83 @@ use ast::types::{DataType, MemoryViewType};
 use ast::{
     AccessMode, AssignmentLhs, AssignmentOp, AssignmentStatement, FnAttr, FnDecl, GlobalVarAttr,
     GlobalVarDecl, LetDeclStatement, Module, Postfix, PostfixExpr, ShaderStage, Statement,
-    StorageClass, VarExpr, VarQualifier,
+    StorageClass, StructDecl, VarExpr, VarQualifier,
 };
-use rand::prelude::{SliceRandom, StdRng};
-use rand::Rng;
-use rand_distr::{Binomial, Distribution, StandardNormal};
+use rand::RngCore;
+use reflection_types::RenderState;
 
+use crate::gen::builtins::{Builtins, StandardBuiltins};
 use crate::gen::scope::Scope;
+use crate::rng::{RngExt, SliceExt};
 use crate::Options;
 
+use self::consts::GlobalConst;
 use self::cx::Context;
 use self::structs::StructKind;
 
 #[derive(Default)]
 struct FnState {
     is_loop: bool,
+    /// Whether return statements are disallowed, such as in an entry point that ends with a
+    /// barrier, which must be reached by every invocation.
+    no_return: bool,
     block_depth: u32,
     expression_depth: u32,
 }
 
 pub struct Generator<'a> {
-    rng: &'a mut StdRng,
+    rng: &'a mut dyn RngCore,
     options: Rc<Options>,
     cx: Context,
     return_type: Option<DataType>,
     fn_state: FnState,
     global_scope: Scope,
+    global_consts: Vec<GlobalConst>,
     scope: Scope,
     current_block: Vec<Statement>,
-    f32_dist: StandardNormal,
-    i32_dist: Binomial,
-    u32_dist: Binomial,
+    expected_output: Option<Vec<u8>>,
+    render_state: Option<RenderState>,
 }
 
 impl<'a> Generator<'a> {
-    pub fn new(rng: &'a mut StdRng, options: Rc<Options>) -> Self {
+    pub fn new(rng: &'a mut dyn RngCore, options: Rc<Options>) -> Self {
+        let builtins = Builtins::from_providers(&options, &[&StandardBuiltins]);
+        Generator::with_builtins(rng, options, builtins)
+    }
+
+    /// Creates a generator that calls the given builtins instead of the standard set.
+    pub fn with_builtins(
+        rng: &'a mut dyn RngCore,
+        options: Rc<Options>,
+        builtins: Builtins,
+    ) -> Self {
         Generator {
             rng,
-            options: options.clone(),
-            cx: Context::new(options),
+            options,
+            cx: Context::new(builtins),
             return_type: None,
             fn_state: FnState::default(),
             global_scope: Scope::empty(),
+            global_consts: vec![],
             scope: Scope::empty(),
             current_block: vec![],
-            f32_dist: StandardNormal,
-            i32_dist: Binomial::new(i32::MAX as u64 * 2, 0.5)
-                .expect("failed to create binomial distribution"),
-            u32_dist: Binomial::new(u32::MAX as u64 * 2, 0.5)
-                .expect("failed to create binomial distribution"),
+            expected_output: None,
+            render_state: None,
         }
     }
 
+    /// Returns the contents that the output buffer must have after executing the program, if it
+    /// was generated with `--const-oracle`.
+    pub fn expected_output(&self) -> Option<&[u8]> {
+        self.expected_output.as_deref()
+    }
+
+    /// Returns the fixed function state that the render pipeline must be created with, if the
+    /// program was generated with `--render-state`.
+    pub fn render_state(&self) -> Option<&RenderState> {
+        self.render_state.as_ref()
+    }
+
     #[tracing::instrument(skip(self))]
     pub fn gen_module(&mut self) -> Module {
         let struct_count = self
@@ -81,14 +126,24 @@ impl<'a> Generator<'a> {
         let ub_type_decl =
             self.gen_struct_with("UniformBuffer".to_owned(), StructKind::UniformBuffer);
         let sb_type_decl =
-            self.gen_struct_with("StorageBuffer".to_owned(), StructKind::HostShareable);
+            self.gen_struct_with("StorageBuffer".to_owned(), StructKind::StorageBuffer);
 
         self.global_scope
             .insert_readonly("u_input".to_owned(), DataType::Struct(ub_type_decl.clone()));
 
+        let enables = self.gen_enables();
+        let diagnostics = self.gen_diagnostic_directives();
+
+        let [(ub_group, ub_binding), (sb_group, sb_binding)] = self.gen_resource_bindings();
+
+        let out_buf_type = self.output_buffer_type(&sb_type_decl);
+
         let mut global_vars = vec![
             GlobalVarDecl {
-                attrs: vec![GlobalVarAttr::Group(0), GlobalVarAttr::Binding(0)],
+                attrs: vec![
+                    GlobalVarAttr::Group(ub_group as i32),
+                    GlobalVarAttr::Binding(ub_binding as i32),
+                ],
                 qualifier: Some(VarQualifier {
                     storage_class: StorageClass::Uniform,
                     access_mode: None,
@@ -98,63 +153,182 @@ impl<'a> Generator<'a> {
                 initializer: None,
             },
             GlobalVarDecl {
-                attrs: vec![GlobalVarAttr::Group(0), GlobalVarAttr::Binding(1)],
+                attrs: vec![
+                    GlobalVarAttr::Group(sb_group as i32),
+                    GlobalVarAttr::Binding(sb_binding as i32),
+                ],
                 qualifier: Some(VarQualifier {
                     storage_class: StorageClass::Storage,
                     access_mode: Some(AccessMode::ReadWrite),
                 }),
                 name: "s_output".to_owned(),
-                data_type: DataType::Struct(sb_type_decl.clone()),
+                data_type: out_buf_type.clone(),
                 initializer: None,
             },
         ];
 
+        global_vars.extend(self.gen_staging_var(&out_buf_type));
+        global_vars.extend(self.gen_checksum_var());
+        global_vars.extend(self.gen_subgroup_var());
+
+        let uniform_load_var = self.gen_uniform_load_var();
+        let uniform_load_type = uniform_load_var.as_ref().map(|var| var.data_type.clone());
+        global_vars.extend(uniform_load_var);
+
+        let push_constants_type = if self.options.push_constants {
+            let (decl, var) = self.gen_push_constants();
+            global_vars.push(var);
+            Some(decl)
+        } else {
+            None
+        };
+
+        for var in &mut global_vars {
+            var.attrs.shuffle(self.rng);
+        }
+
+        let consts = if self.options.disable_global_consts {
+            vec![]
+        } else {
+            (0..self.rng.gen_range(0..=4))
+                .map(|i| self.gen_global_const(format!("global_const{i}")))
+                .collect()
+        };
+
+        let mut bindings = vec![(ub_group, ub_binding), (sb_group, sb_binding)];
+
         for i in 0..self.rng.gen_range(0..=5) {
             let name = format!("global{i}");
-            global_vars.push(self.gen_global_var(name));
+            match self.choose_global_address_space() {
+                Some(StorageClass::Private) => global_vars.push(self.gen_global_var(name)),
+                Some(storage_class) => {
+                    global_vars.push(self.gen_shared_global_var(name, storage_class, &mut bindings))
+                }
+                None => {}
+            }
         }
 
+        let const_asserts = if self.options.disable_const_assert {
+            vec![]
+        } else {
+            (0..self.rng.gen_range(0..=2))
+                .map(|_| self.gen_const_assert())
+                .collect()
+        };
+
         let entrypoint = self.gen_entrypoint_function(
             DataType::Struct(ub_type_decl.clone()),
-            DataType::Struct(sb_type_decl.clone()),
+            &sb_type_decl,
+            &out_buf_type,
+            uniform_load_type.as_ref(),
         );
 
+        let (render_structs, render_fns) = if self.options.render {
+            self.gen_render_entry_points()
+        } else {
+            (vec![], vec![])
+        };
+
+        let unused_structs = if self.options.unused_decls {
+            global_vars.extend(self.gen_unused_global_vars());
+            self.gen_unused_fns();
+            self.gen_unused_structs()
+        } else {
+            vec![]
+        };
+
         let Context { types, fns } =
-            std::mem::replace(&mut self.cx, Context::new(self.options.clone()));
+            std::mem::replace(&mut self.cx, Context::new(Builtins::empty()));
 
         let mut functions = fns.into_fns();
 
+        functions.extend(self.gen_limit_fns());
+        functions.extend(self.gen_checksum_fn());
         functions.push(entrypoint);
+        functions.extend(render_fns);
 
         Module {
+            enables,
+            diagnostics,
             structs: {
                 let mut structs = types.into_structs();
+                structs.extend(unused_structs);
+                structs.extend(self.gen_limit_structs());
+                structs.extend(render_structs);
+                structs.extend(push_constants_type);
                 structs.push(ub_type_decl);
                 structs.push(sb_type_decl);
                 structs
             },
-            consts: vec![],
+            consts,
+            const_asserts,
             vars: global_vars,
             functions,
         }
     }
 
+    /// Returns the `(group, binding)` pairs for the input and output buffers.
+    ///
+    /// Groups and bindings can be sparse, so these are chosen from anywhere within the default
+    /// limits of 4 bind groups and 1000 bindings per group, with a bias towards small numbers.
+    fn gen_resource_bindings(&mut self) -> [(u32, u32); 2] {
+        if self.options.disable_random_bindings {
+            return [(0, 0), (0, 1)];
+        }
+
+        if let Some(binding) = self.limit_output_binding() {
+            return [(0, 0), binding];
+        }
+
+        let gen_binding = |rng: &mut dyn RngCore| {
+            if rng.gen_bool(0.75) {
+                rng.gen_range(0..=7)
+            } else {
+                rng.gen_range(8..1000)
+            }
+        };
+
+        let ub_group = self.rng.gen_range(0..4);
+        let ub_binding = gen_binding(self.rng);
+
+        let sb_group = if self.rng.gen_bool(0.5) {
+            ub_group
+        } else {
+            self.rng.gen_range(0..4)
+        };
+
+        let mut sb_binding = gen_binding(self.rng);
+        if sb_group == ub_group && sb_binding == ub_binding {
+            sb_binding = (ub_binding + 1) % 1000;
+        }
+
+        [(ub_group, ub_binding), (sb_group, sb_binding)]
+    }
+
     fn gen_global_var(&mut self, name: String) -> GlobalVarDecl {
-        let mut data_type = self.cx.types.select(self.rng);
+        let decl = self.gen_global_var_decl(name);
+
+        let mem_view = MemoryViewType::new(decl.data_type.clone(), StorageClass::Private);
+        self.global_scope
+            .insert_mutable(decl.name.clone(), DataType::Ref(mem_view));
+
+        decl
+    }
+
+    /// Generates a private module-scope variable, without adding it to the global scope.
+    fn gen_global_var_decl(&mut self, name: String) -> GlobalVarDecl {
+        let mut data_type = self.cx.types.select(&mut self.rng);
 
         if self.rng.gen_bool(0.5) {
             data_type = DataType::Array(Rc::new(data_type), Some(self.rng.gen_range(1..=32)));
         }
 
-        let mem_view = MemoryViewType::new(data_type.clone(), StorageClass::Private);
-        let ref_type = DataType::Ref(mem_view);
-
-        self.global_scope.insert_mutable(name.clone(), ref_type);
-
-        let initializer = if self.rng.gen_bool(0.5) {
+        let initializer = if !self.rng.gen_bool(0.5) {
+            None
+        } else if self.options.disable_global_consts {
             Some(self.gen_const_expr(&data_type))
         } else {
-            None
+            Some(self.gen_const_initializer(&data_type, 0).0)
         };
 
         GlobalVarDecl {
@@ -169,11 +343,27 @@ impl<'a> Generator<'a> {
         }
     }
 
-    #[tracing::instrument(skip(self))]
-    fn gen_entrypoint_function(&mut self, in_buf_type: DataType, out_buf_type: DataType) -> FnDecl {
+    #[tracing::instrument(skip(self, out_buf_decl, out_buf_type, uniform_load_type))]
+    fn gen_entrypoint_function(
+        &mut self,
+        in_buf_type: DataType,
+        out_buf_decl: &Rc<StructDecl>,
+        out_buf_type: &DataType,
+        uniform_load_type: Option<&DataType>,
+    ) -> FnDecl {
         let stmt_count = self.rng.gen_range(5..10);
-        let (_, block) = self.with_scope(self.global_scope.clone(), |this| {
+        let (_, (inputs, block)) = self.with_scope(self.global_scope.clone(), |this| {
+            let inputs = this.gen_entrypoint_inputs();
+
+            // Every invocation has to reach the barrier before the staged outputs are copied, the
+            // const output must always be written, and subgroup operations and uniform loads need
+            // uniform control flow
+            this.fn_state.no_return = this.is_staging_outputs()
+                || this.options.const_oracle
+                || this.options.subgroups
+                || uniform_load_type.is_some();
             let (scope, mut block) = this.gen_stmt_block(stmt_count);
+            this.fn_state.no_return = false;
 
             if let Some(Statement::Return(_)) = block.last() {
                 block.pop();
@@ -193,22 +383,78 @@ impl<'a> Generator<'a> {
                     .into(),
                 );
 
-                let out_lhs = AssignmentLhs::name("s_output", out_buf_type.clone());
-                let out_rhs = this.gen_expr(&out_buf_type);
-                this.current_block
-                    .push(AssignmentStatement::new(out_lhs, AssignmentOp::Simple, out_rhs).into());
+                if let Some(depth) = this.options.nesting_ladder {
+                    let stmts = this.gen_nesting_ladder(depth);
+                    this.current_block.extend(stmts);
+                }
+
+                if this.options.subgroups {
+                    let stmts = this.gen_subgroup_reductions();
+                    this.current_block.extend(stmts);
+                }
+
+                if let Some(data_type) = uniform_load_type {
+                    let stmts = this.gen_uniform_loads(data_type);
+                    this.current_block.extend(stmts);
+                }
+
+                if this.options.checksum {
+                    this.gen_checksum_result();
+                }
+
+                if let DataType::Array(element_type, _) = out_buf_type {
+                    let value = if this.options.checksum {
+                        this.checksum_output_value(element_type)
+                    } else {
+                        this.gen_expr(element_type)
+                    };
+                    let stmts = this.gen_invocation_output_writes(out_buf_type, value);
+                    this.current_block.extend(stmts);
+                } else if out_buf_type.is_runtime_sized() {
+                    let stmts = this.gen_runtime_array_writes("s_output", out_buf_decl);
+                    this.current_block.extend(stmts);
+                } else {
+                    let out_lhs = AssignmentLhs::name("s_output", out_buf_type.clone());
+                    let out_rhs = if this.options.const_oracle {
+                        let (expr, values) = this.gen_const_output(out_buf_decl);
+                        let bytes = const_oracle::encode_const_output(out_buf_type, &values)
+                            .expect("output buffer should only contain integers");
+                        this.expected_output = Some(bytes);
+                        expr
+                    } else if this.options.checksum {
+                        this.checksum_output_value(out_buf_type)
+                    } else {
+                        this.gen_expr(out_buf_type)
+                    };
+                    this.current_block.push(
+                        AssignmentStatement::new(out_lhs, AssignmentOp::Simple, out_rhs).into(),
+                    );
+                }
+
+                let stmt = this.gen_checksum_member_write(out_buf_type);
+                this.current_block.extend(stmt);
             });
 
-            std::mem::replace(&mut this.current_block, prev_block)
+            (
+                inputs,
+                std::mem::replace(&mut this.current_block, prev_block),
+            )
         });
 
+        let mut attrs = vec![
+            FnAttr::Stage(ShaderStage::Compute),
+            FnAttr::WorkgroupSize(self.options.invocations.max(1)),
+        ];
+
+        attrs.extend(self.gen_fn_diagnostic_attr());
+
+        // Attributes can be written in any order
+        attrs.shuffle(self.rng);
+
         FnDecl {
-            attrs: vec![
-                FnAttr::Stage(ShaderStage::Compute),
-                FnAttr::WorkgroupSize(1),
-            ],
+            attrs,
             name: "main".to_owned(),
-            inputs: vec![],
+            inputs,
             output: None,
             body: block,
         }
@@ -220,9 +466,17 @@ impl<'a> Generator<'a> {
         (std::mem::replace(&mut self.scope, old_scope), res)
     }
 
+    /// Returns the sum of 12 values sampled uniformly from `-a..a`, which approximates a normal
+    /// distribution with a standard deviation of `2a`.
+    ///
+    /// This is used instead of rand_distr so that the results are the same on every platform.
+    fn gen_approx_normal(&mut self, a: i64) -> i64 {
+        (0..12).map(|_| self.rng.gen_range(-a..a)).sum()
+    }
+
     fn gen_i32(&mut self) -> i32 {
         if self.rng.gen_bool(0.5) {
-            (self.i32_dist.sample(self.rng) as i64 - i32::MAX as i64) as i32
+            self.gen_approx_normal(1 << 14) as i32
         } else {
             *[0, 1, -1, i32::MAX, i32::MIN].choose(self.rng).unwrap()
         }
@@ -230,14 +484,17 @@ impl<'a> Generator<'a> {
 
     fn gen_u32(&mut self) -> u32 {
         if self.rng.gen_bool(0.5) {
-            (self.u32_dist.sample(self.rng) as i64 - u32::MAX as i64).unsigned_abs() as u32
+            // Standard deviation of roughly 2^15.5, to match a binomial distribution over 2^33 trials
+            self.gen_approx_normal(23170).unsigned_abs() as u32
         } else {
             *[0, 1, u32::MAX].choose(self.rng).unwrap()
         }
     }
 
     fn gen_f32(&mut self) -> f32 {
-        let k: f64 = self.f32_dist.sample(&mut self.rng);
+        // Irwin-Hall approximation of a standard normal distribution
+        let sum: u64 = (0..12).map(|_| self.rng.next_u32() as u64).sum();
+        let k = sum as f64 / (1u64 << 32) as f64 - 6.0;
         let k = if k.abs() < 0.1 {
             if self.rng.gen_bool(0.5) {
                 1.0
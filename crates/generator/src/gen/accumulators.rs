@@ -0,0 +1,195 @@
+//! Loops which carry a dependency between iterations through an accumulator.
+//!
+//! With `--loop-accumulators`, loops like the following are generated, where each iteration
+//! depends on the value of the accumulator from the previous one. Loop optimisations such as
+//! invariant code motion and unrolling have to preserve these dependencies, including when
+//! updates are conditional or the loop exits early.
+//!
+//! ```wgsl
+//! var var_0 = 1u;
+//! for (var var_1 = 0u; var_1 < 8u; var_1 += 1u) {
+//!     var_0 = var_0 * 3u;
+//!     if (x > 2u) {
+//!         var_0 = var_0 ^ var_1;
+//!     }
+//!     if (var_0 == 7u) {
+//!         break;
+//!     }
+//! }
+//! ```
+
+use std::mem;
+
+use ast::types::{DataType, MemoryViewType, ScalarType};
+use ast::{
+    AssignmentLhs, AssignmentOp, AssignmentStatement, BinOp, BinOpExpr, ExprNode, ForLoopHeader,
+    ForLoopInit, ForLoopStatement, ForLoopUpdate, IfStatement, Lit, Postfix, PostfixExpr,
+    Statement, StorageClass, TypeConsExpr, VarDeclStatement, VarExpr,
+};
+
+use crate::rng::{RngExt, SliceExt};
+
+/// Operators used to combine the accumulator with a new value.
+const UPDATE_OPS: &[BinOp] = &[
+    BinOp::Plus,
+    BinOp::Minus,
+    BinOp::Times,
+    BinOp::BitXOr,
+    BinOp::BitOr,
+    BinOp::BitAnd,
+];
+
+/// Operators used to compare the accumulator when deciding whether to exit the loop early.
+const EXIT_OPS: &[BinOp] = &[BinOp::Less, BinOp::Greater, BinOp::Equal, BinOp::NotEqual];
+
+struct Accumulator {
+    name: String,
+    data_type: DataType,
+    scalar_type: ScalarType,
+    counter: String,
+}
+
+impl Accumulator {
+    fn var(&self) -> ExprNode {
+        VarExpr::new(&self.name).into_node(self.data_type.clone())
+    }
+
+    /// Returns the loop counter converted to the type of the accumulator.
+    fn counter(&self) -> ExprNode {
+        let counter = VarExpr::new(&self.counter).into_node(ScalarType::U32);
+
+        let scalar: ExprNode = match self.scalar_type {
+            ScalarType::U32 => counter,
+            ty => TypeConsExpr::new(ty.into(), vec![counter]).into(),
+        };
+
+        match self.data_type {
+            DataType::Vector(_, _) => {
+                TypeConsExpr::new(self.data_type.clone(), vec![scalar]).into()
+            }
+            _ => scalar,
+        }
+    }
+}
+
+impl<'a> super::Generator<'a> {
+    /// Generates a loop which updates an accumulator on each iteration.
+    ///
+    /// The accumulator is declared before the loop (by pushing its declaration to the current
+    /// block), so that it can be used by the statements which follow.
+    pub fn gen_accumulator_loop(&mut self) -> Statement {
+        let scalar_type = *[ScalarType::I32, ScalarType::U32].choose(self.rng).unwrap();
+        let data_type = if self.rng.gen_bool(0.5) {
+            DataType::Scalar(scalar_type)
+        } else {
+            DataType::Vector(self.rng.gen_range(2..=4), scalar_type)
+        };
+
+        let name = self.scope.next_name();
+        let init = self.gen_expr(&data_type);
+        self.current_block
+            .push(VarDeclStatement::new(name.clone(), None, Some(init)).into());
+
+        let ref_type = DataType::Ref(MemoryViewType::new(
+            data_type.clone(),
+            StorageClass::Function,
+        ));
+
+        self.scope.insert_mutable(name.clone(), ref_type.clone());
+
+        let acc = Accumulator {
+            name,
+            data_type,
+            scalar_type,
+            counter: self.scope.next_name(),
+        };
+
+        // The counter can't be assigned to by generated code, so the trip count is fixed
+        let trip_count = self.rng.gen_range(1..=16);
+        let header = ForLoopHeader {
+            init: Some(ForLoopInit::VarDecl(VarDeclStatement::new(
+                acc.counter.clone(),
+                None,
+                Some(Lit::U32(0).into()),
+            ))),
+            condition: Some(
+                BinOpExpr::new(
+                    BinOp::Less,
+                    VarExpr::new(&acc.counter).into_node(ScalarType::U32),
+                    Lit::U32(trip_count),
+                )
+                .into(),
+            ),
+            update: Some(ForLoopUpdate::Assignment(AssignmentStatement::new(
+                AssignmentLhs::name(acc.counter.clone(), ScalarType::U32),
+                AssignmentOp::Plus,
+                Lit::U32(1),
+            ))),
+        };
+
+        let mut scope = self.scope.clone();
+        scope.insert_readonly(acc.counter.clone(), ScalarType::U32.into());
+
+        let (_, body) = self.with_scope(scope, |this| {
+            let is_loop = mem::replace(&mut this.fn_state.is_loop, true);
+            let prev_block = mem::take(&mut this.current_block);
+
+            for _ in 0..this.rng.gen_range(1..=3) {
+                let update = this.gen_accumulator_update(&acc);
+                if this.rng.gen_bool(0.5) {
+                    let condition = this.gen_expr(&ScalarType::Bool.into());
+                    this.current_block
+                        .push(IfStatement::new(condition, vec![update]).into());
+                } else {
+                    this.current_block.push(update);
+                }
+            }
+
+            // Combining the accumulator on every iteration makes the checksum depend on the
+            // number of iterations too
+            let checksum_update = this.gen_checksum_update(&acc.name, &ref_type);
+            this.current_block.extend(checksum_update);
+
+            if this.rng.gen_bool(0.5) {
+                let condition = this.gen_accumulator_exit_condition(&acc);
+                this.current_block
+                    .push(IfStatement::new(condition, vec![Statement::Break]).into());
+            }
+
+            this.fn_state.is_loop = is_loop;
+            mem::replace(&mut this.current_block, prev_block)
+        });
+
+        ForLoopStatement::new(header, body).into()
+    }
+
+    /// Generates an assignment which combines the accumulator with either the loop counter or a
+    /// random expression (which may itself read the accumulator).
+    fn gen_accumulator_update(&mut self, acc: &Accumulator) -> Statement {
+        let op = *UPDATE_OPS.choose(self.rng).unwrap();
+        let value = if self.rng.gen_bool(0.5) {
+            acc.counter()
+        } else {
+            self.gen_expr(&acc.data_type)
+        };
+
+        AssignmentStatement::new(
+            AssignmentLhs::name(acc.name.clone(), acc.data_type.clone()),
+            AssignmentOp::Simple,
+            BinOpExpr::new(op, acc.var(), value),
+        )
+        .into()
+    }
+
+    fn gen_accumulator_exit_condition(&mut self, acc: &Accumulator) -> ExprNode {
+        let value = match acc.data_type {
+            DataType::Vector(_, _) => PostfixExpr::new(acc.var(), Postfix::member("x")).into(),
+            _ => acc.var(),
+        };
+
+        let op = *EXIT_OPS.choose(self.rng).unwrap();
+        let threshold = self.gen_expr(&DataType::Scalar(acc.scalar_type));
+
+        BinOpExpr::new(op, value, threshold).into()
+    }
+}
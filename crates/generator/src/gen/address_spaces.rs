@@ -0,0 +1,153 @@
+//! Choosing the address spaces of variables with `--address-space-weights`.
+//!
+//! Without the option, module-scope variables are always private and assignments choose their
+//! target uniformly. With it, module-scope variables can also be declared in the workgroup,
+//! storage and uniform address spaces, and assignments are biased towards variables in the
+//! address spaces with higher weights.
+
+use std::rc::Rc;
+
+use ast::types::{DataType, MemoryViewType};
+use ast::{AccessMode, GlobalVarAttr, GlobalVarDecl, StorageClass, VarQualifier};
+
+use crate::rng::{RngExt, SliceExt};
+
+use super::cx::SelectionFilter;
+
+/// Address spaces that module-scope variables can be declared in.
+const MODULE_SPACES: [StorageClass; 4] = [
+    StorageClass::Private,
+    StorageClass::WorkGroup,
+    StorageClass::Storage,
+    StorageClass::Uniform,
+];
+
+impl<'a> super::Generator<'a> {
+    /// Chooses the address space of a module-scope variable, or returns `None` if all of the
+    /// module-scope address spaces have a weight of 0.
+    pub fn choose_global_address_space(&mut self) -> Option<StorageClass> {
+        let weights = match self.options.address_space_weights {
+            Some(weights) => weights,
+            None => return Some(StorageClass::Private),
+        };
+
+        // Every storage buffer would need an expected value with `--const-oracle`
+        let const_oracle = self.options.const_oracle;
+
+        MODULE_SPACES
+            .choose_weighted(self.rng, |space| match space {
+                StorageClass::Storage if const_oracle => 0,
+                space => weights.weight(*space),
+            })
+            .copied()
+    }
+
+    /// Returns true if local `var`s can be declared.
+    pub fn can_gen_local_var(&self) -> bool {
+        self.options
+            .address_space_weights
+            .map_or(true, |weights| weights.function > 0)
+    }
+
+    /// Chooses a mutable variable in scope, in proportion to the weight of its address space if
+    /// `--address-space-weights` is given.
+    pub fn choose_mutable(&mut self) -> (String, DataType) {
+        let (name, data_type) = match self.options.address_space_weights {
+            Some(weights) => self
+                .scope
+                .choose_mutable_weighted(&mut self.rng, |space| weights.weight(space)),
+            None => self.scope.choose_mutable(&mut self.rng),
+        };
+
+        (name.clone(), data_type.clone())
+    }
+
+    /// Generates a module-scope variable in the workgroup, storage or uniform address space, and
+    /// adds it to the global scope.
+    ///
+    /// Workgroup and storage variables are shared by every invocation, so they can only be read
+    /// with more than one invocation to avoid races. Storage and uniform variables are given a
+    /// binding which isn't in `bindings`, which is then added to it.
+    pub fn gen_shared_global_var(
+        &mut self,
+        name: String,
+        storage_class: StorageClass,
+        bindings: &mut Vec<(u32, u32)>,
+    ) -> GlobalVarDecl {
+        let mut data_type = match storage_class {
+            StorageClass::WorkGroup => self.cx.types.select(&mut self.rng),
+            StorageClass::Storage => self
+                .cx
+                .types
+                .select_with_filter(&mut self.rng, SelectionFilter::HostShareable),
+            StorageClass::Uniform => self
+                .cx
+                .types
+                .select_with_filter(&mut self.rng, SelectionFilter::Uniform),
+            space => unreachable!("`{space}` variables can't be shared"),
+        };
+
+        // Array elements in uniform buffers must be aligned to 16 bytes, so they are left out
+        if storage_class != StorageClass::Uniform && self.rng.gen_bool(0.5) {
+            data_type = DataType::Array(Rc::new(data_type), Some(self.rng.gen_range(1..=32)));
+        }
+
+        let access_mode = if storage_class == StorageClass::Storage {
+            AccessMode::ReadWrite
+        } else {
+            storage_class.default_access_mode()
+        };
+
+        let attrs = if storage_class == StorageClass::WorkGroup {
+            vec![]
+        } else {
+            let (group, binding) = self.gen_extra_binding(bindings);
+            vec![
+                GlobalVarAttr::Group(group as i32),
+                GlobalVarAttr::Binding(binding as i32),
+            ]
+        };
+
+        let mem_view = MemoryViewType {
+            inner: Rc::new(data_type.clone()),
+            storage_class,
+            access_mode,
+        };
+
+        if storage_class != StorageClass::Uniform && self.options.invocations <= 1 {
+            self.global_scope
+                .insert_mutable(name.clone(), DataType::Ref(mem_view));
+        } else {
+            self.global_scope
+                .insert_readonly(name.clone(), DataType::Ref(mem_view));
+        }
+
+        GlobalVarDecl {
+            attrs,
+            qualifier: Some(VarQualifier {
+                storage_class,
+                access_mode: (storage_class == StorageClass::Storage).then_some(access_mode),
+            }),
+            name,
+            data_type,
+            initializer: None,
+        }
+    }
+
+    /// Chooses a `(group, binding)` pair which isn't already in `bindings`, and adds it.
+    fn gen_extra_binding(&mut self, bindings: &mut Vec<(u32, u32)>) -> (u32, u32) {
+        // Only the first group is used if the bind group limit might be lower than the default
+        let group = if self.options.disable_random_bindings || self.options.limits.is_some() {
+            0
+        } else {
+            self.rng.gen_range(0..4)
+        };
+
+        let binding = (0..)
+            .find(|binding| !bindings.contains(&(group, *binding)))
+            .unwrap();
+
+        bindings.push((group, binding));
+        (group, binding)
+    }
+}
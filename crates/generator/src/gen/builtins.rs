@@ -1,12 +1,103 @@
+//! Builtin functions that the generator can call.
+//!
+//! The standard set of builtins is provided by [`StandardBuiltins`]. Other sets can be used by
+//! implementing [`BuiltinProvider`], for example to add builtins from vendor extensions or to
+//! restrict generation to a subset of the standard ones, and passing the providers to
+//! [`crate::generate_with_builtins`].
+
 use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 use ast::{BuiltinFn, DataType, ScalarType};
 
 use crate::gen::cx::Func;
+use crate::Options;
 
 use super::cx::Overload;
 
+/// The set of builtin function overloads available to the generator.
+#[derive(Clone, Debug, Default)]
+pub struct Builtins {
+    map: HashMap<DataType, Vec<Rc<Func>>>,
+}
+
+impl Builtins {
+    /// Returns an empty set of builtins.
+    pub fn empty() -> Builtins {
+        Builtins::default()
+    }
+
+    /// Builds a set of builtins by running each provider in order.
+    pub fn from_providers(options: &Options, providers: &[&dyn BuiltinProvider]) -> Builtins {
+        let mut builtins = Builtins::empty();
+        for provider in providers {
+            provider.register(options, &mut builtins);
+        }
+        builtins
+    }
+
+    /// Adds an overload for the function named `ident`, which can be a [`BuiltinFn`].
+    ///
+    /// The generator doesn't check that the function exists, so this can be used for builtins that
+    /// the `ast` crate doesn't know about. Programs using them will fail `ast::typecheck`, but can
+    /// still be reconditioned.
+    pub fn add(
+        &mut self,
+        ident: impl AsRef<str>,
+        params: impl Into<Vec<DataType>>,
+        return_type: impl Into<DataType>,
+    ) {
+        let return_type = return_type.into();
+        self.map
+            .entry(return_type.clone())
+            .or_default()
+            .push(Rc::new(Func::Builtin(
+                ident.as_ref().to_owned(),
+                Overload {
+                    params: params.into(),
+                    return_type,
+                },
+            )));
+    }
+
+    /// Removes all overloads for which `f` returns false.
+    ///
+    /// `f` is passed the name of the function, its parameter types, and its return type.
+    pub fn retain(&mut self, mut f: impl FnMut(&str, &[DataType], &DataType) -> bool) {
+        for overloads in self.map.values_mut() {
+            overloads.retain(|func| match func.as_ref() {
+                Func::Builtin(ident, overload) => f(ident, &overload.params, &overload.return_type),
+                Func::User(_) => true,
+            });
+        }
+
+        self.map.retain(|_, overloads| !overloads.is_empty());
+    }
+
+    pub(crate) fn into_map(self) -> HashMap<DataType, Vec<Rc<Func>>> {
+        self.map
+    }
+}
+
+/// A source of builtin functions for the generator.
+///
+/// Providers are run in order, and each one can both add overloads to and remove overloads from
+/// the builtins registered by the providers before it.
+pub trait BuiltinProvider {
+    fn register(&self, options: &Options, builtins: &mut Builtins);
+}
+
+/// The standard WGSL builtins supported by the generator, including any that have been enabled
+/// with `--enable-fn` and excluding any that have been disabled with `--disable-fn`.
+pub struct StandardBuiltins;
+
+impl BuiltinProvider for StandardBuiltins {
+    fn register(&self, options: &Options, builtins: &mut Builtins) {
+        gen_builtins(&options.enabled_fns, builtins);
+        builtins.retain(|ident, _, _| !options.disabled_fns.iter().any(|it| it.as_ref() == ident));
+    }
+}
+
 fn vectors_of(ty: ScalarType) -> impl Iterator<Item = DataType> {
     (2..=4).map(move |n| DataType::Vector(n, ty))
 }
@@ -20,13 +111,12 @@ pub const TINT_EXTRAS: &[BuiltinFn] = {
     &[CountLeadingZeros, CountTrailingZeros, Refract]
 };
 
-pub fn gen_builtins(enabled: &[BuiltinFn]) -> HashMap<DataType, Vec<Rc<Func>>> {
+fn gen_builtins(enabled: &[BuiltinFn], map: &mut Builtins) {
     use BuiltinFn::*;
     use DataType::*;
     use ScalarType::*;
 
     let enabled: HashSet<BuiltinFn> = HashSet::from_iter(enabled.iter().copied());
-    let mut map = HashMap::<DataType, Vec<Rc<Func>>>::new();
 
     for s_ty in [I32, U32, F32] {
         for ty in scalar_and_vectors_of(s_ty) {
@@ -163,35 +253,4 @@ pub fn gen_builtins(enabled: &[BuiltinFn]) -> HashMap<DataType, Vec<Rc<Func>>> {
     //         map.add(Refract, [ty.clone(), ty.clone(), F32.into()], ty.clone());
     //     }
     // }
-
-    map
-}
-
-trait HashMapExt {
-    fn add(
-        &mut self,
-        builtin: BuiltinFn,
-        params: impl Into<Vec<DataType>>,
-        return_type: impl Into<DataType>,
-    );
-}
-
-impl HashMapExt for HashMap<DataType, Vec<Rc<Func>>> {
-    fn add(
-        &mut self,
-        builtin: BuiltinFn,
-        params: impl Into<Vec<DataType>>,
-        return_type: impl Into<DataType>,
-    ) {
-        let return_type = return_type.into();
-        self.entry(return_type.clone())
-            .or_default()
-            .push(Rc::new(Func::Builtin(
-                builtin,
-                Overload {
-                    params: params.into(),
-                    return_type,
-                },
-            )));
-    }
 }
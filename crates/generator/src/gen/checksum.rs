@@ -0,0 +1,226 @@
+//! Accumulating the values computed by a program into a checksum in the output buffer.
+//!
+//! Without this, a variable that is never read again after it is declared (or assigned) is dead,
+//! so compilers are free to remove the computation of its value, along with any miscompilation of
+//! it. With `--live-hash` or `--checksum`, every such value is combined into a private `checksum`
+//! variable immediately afterwards, using the scheme from [`common::checksum`], and the checksum
+//! is written to a `checksum` member of the output buffer at the end of the entry point.
+//!
+//! Only integer and boolean components are combined, since floating point results can
+//! legitimately differ between implementations.
+
+use std::rc::Rc;
+
+use ast::types::{DataType, MemoryViewType, ScalarType};
+use ast::{
+    AccessMode, AssignmentLhs, AssignmentOp, AssignmentStatement, BinOp, BinOpExpr, Expr, ExprNode,
+    FnCallExpr, FnCallStatement, FnDecl, GlobalVarDecl, LetDeclStatement, LhsExpr, LhsExprNode,
+    Postfix, PostfixExpr, Statement, StorageClass, StructMember, TypeConsExpr, UnOp, UnOpExpr,
+    VarExpr, VarQualifier,
+};
+use common::checksum::{self, COMBINE_FN};
+
+use super::invocations::LOCAL_INDEX;
+
+/// Name of the private variable holding the checksum.
+const CHECKSUM_VAR: &str = "checksum";
+
+impl<'a> super::Generator<'a> {
+    /// Returns true if values are combined into a checksum as they are computed.
+    pub fn is_checksumming(&self) -> bool {
+        self.options.live_hash || self.options.checksum
+    }
+
+    /// Generates the private variable holding the checksum, if enabled.
+    ///
+    /// It isn't added to the global scope, so that generated code can't overwrite it.
+    pub fn gen_checksum_var(&self) -> Option<GlobalVarDecl> {
+        if !self.is_checksumming() {
+            return None;
+        }
+
+        Some(GlobalVarDecl {
+            attrs: vec![],
+            qualifier: Some(VarQualifier {
+                storage_class: StorageClass::Private,
+                access_mode: None,
+            }),
+            name: CHECKSUM_VAR.to_owned(),
+            data_type: DataType::Scalar(ScalarType::U32),
+            initializer: None,
+        })
+    }
+
+    /// Returns the function which combines a value into the checksum, if enabled.
+    pub fn gen_checksum_fn(&self) -> Option<FnDecl> {
+        self.is_checksumming().then(checksum::combine_fn_decl)
+    }
+
+    /// Returns the member of the storage buffer struct that the checksum is written to, if
+    /// enabled.
+    pub fn gen_checksum_member(&self) -> Option<Rc<StructMember>> {
+        self.is_checksumming()
+            .then(|| StructMember::new(vec![], checksum::MEMBER, DataType::Scalar(ScalarType::U32)))
+    }
+
+    /// Generates a statement that combines the current value of a variable into the checksum.
+    ///
+    /// Returns `None` if checksums are disabled, or the variable has no integer or boolean
+    /// components (e.g. a pointer or an `f32`).
+    pub fn gen_checksum_update(&self, name: &str, data_type: &DataType) -> Option<Statement> {
+        if !self.is_checksumming() {
+            return None;
+        }
+
+        let mut components = vec![];
+        hashable_components(
+            VarExpr::new(name).into_node(data_type.clone()),
+            &mut components,
+        );
+
+        // The components are xor-ed together first, so that each value only costs one call
+        let value = components
+            .into_iter()
+            .reduce(|acc, it| BinOpExpr::new(BinOp::BitXOr, acc, it).into())?;
+
+        let combined =
+            FnCallExpr::new(COMBINE_FN, vec![checksum_var(), value]).into_node(ScalarType::U32);
+
+        Some(
+            AssignmentStatement::new(
+                AssignmentLhs::name(CHECKSUM_VAR, ScalarType::U32),
+                AssignmentOp::Simple,
+                combined,
+            )
+            .into(),
+        )
+    }
+
+    /// Generates a statement that combines the variable assigned to by `stmt` into the checksum,
+    /// if it is assigned directly (rather than through a pointer).
+    pub fn gen_checksum_assignment_update(&self, stmt: &AssignmentStatement) -> Option<Statement> {
+        let mut lhs = match &stmt.lhs {
+            AssignmentLhs::Phony => return None,
+            AssignmentLhs::Expr(lhs) => lhs,
+        };
+
+        loop {
+            match &lhs.expr {
+                LhsExpr::Ident(name) => return self.gen_checksum_update(name, &lhs.data_type),
+                LhsExpr::Postfix(inner, _) => lhs = inner,
+                LhsExpr::Deref(_) | LhsExpr::AddressOf(_) => return None,
+            }
+        }
+    }
+
+    /// Generates a statement that combines the variable passed by pointer to a struct method call
+    /// into the checksum, since the call may have assigned to its members.
+    pub fn gen_checksum_call_update(&self, stmt: &FnCallStatement) -> Option<Statement> {
+        stmt.args.iter().find_map(|arg| match &arg.expr {
+            Expr::UnOp(UnOpExpr {
+                op: UnOp::AddressOf,
+                inner,
+            }) => match &inner.expr {
+                Expr::Var(var) => self.gen_checksum_update(&var.ident, &inner.data_type),
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
+    /// Generates a final expression in place of the output value with `--checksum`, and combines
+    /// it into the checksum.
+    pub fn gen_checksum_result(&mut self) {
+        let ty = self.cx.types.select(&mut self.rng);
+        let name = self.scope.next_name();
+        let value = self.gen_expr(&ty);
+
+        self.current_block
+            .push(LetDeclStatement::new(name.clone(), value).into());
+
+        let stmt = self.gen_checksum_update(&name, &ty);
+        self.current_block.extend(stmt);
+    }
+
+    /// Returns the value to write to an element of the output buffer with `--checksum`, which
+    /// only contains the checksum.
+    pub fn checksum_output_value(&self, element_type: &DataType) -> ExprNode {
+        TypeConsExpr::new(element_type.clone(), vec![checksum_var()]).into()
+    }
+
+    /// Generates a statement that writes the checksum to its member of the output buffer, if
+    /// enabled with `--live-hash`.
+    ///
+    /// With `--checksum`, the checksum is written as part of the output value instead.
+    pub fn gen_checksum_member_write(&self, out_buf_type: &DataType) -> Option<Statement> {
+        if !self.options.live_hash || self.options.checksum {
+            return None;
+        }
+
+        let lhs = match out_buf_type {
+            // Each invocation writes to the member of its own element
+            DataType::Array(_, _) => {
+                let out_view = MemoryViewType {
+                    inner: Rc::new(out_buf_type.clone()),
+                    storage_class: StorageClass::Storage,
+                    access_mode: AccessMode::ReadWrite,
+                };
+
+                let element = LhsExprNode::array_index(
+                    "s_output".to_owned(),
+                    DataType::Ref(out_view.clone()),
+                    VarExpr::new(LOCAL_INDEX).into_node(ScalarType::U32),
+                );
+
+                LhsExprNode {
+                    data_type: DataType::Ref(
+                        out_view.clone_with_type(DataType::Scalar(ScalarType::U32)),
+                    ),
+                    expr: LhsExpr::Postfix(Box::new(element), Postfix::member(checksum::MEMBER)),
+                }
+                .into()
+            }
+            _ => AssignmentLhs::member(
+                "s_output".to_owned(),
+                out_buf_type.clone(),
+                checksum::MEMBER.to_owned(),
+            ),
+        };
+
+        Some(AssignmentStatement::new(lhs, AssignmentOp::Simple, checksum_var()).into())
+    }
+}
+
+fn checksum_var() -> ExprNode {
+    VarExpr::new(CHECKSUM_VAR).into_node(ScalarType::U32)
+}
+
+/// Collects the integer and boolean components of `expr` as `u32` expressions.
+fn hashable_components(expr: ExprNode, components: &mut Vec<ExprNode>) {
+    match expr.data_type.dereference().clone() {
+        DataType::Scalar(ScalarType::F32) => {}
+        DataType::Scalar(ScalarType::U32) => components.push(expr),
+        // Converting an i32 to u32 keeps the same bits, and a bool becomes 0 or 1
+        DataType::Scalar(ScalarType::I32 | ScalarType::Bool) => {
+            components.push(TypeConsExpr::new(ScalarType::U32.into(), vec![expr]).into());
+        }
+        DataType::Vector(n, _) => {
+            for accessor in ["x", "y", "z", "w"].into_iter().take(n as usize) {
+                hashable_components(
+                    PostfixExpr::new(expr.clone(), Postfix::member(accessor)).into(),
+                    components,
+                );
+            }
+        }
+        DataType::Struct(decl) => {
+            for member in &decl.members {
+                hashable_components(
+                    PostfixExpr::new(expr.clone(), Postfix::member(&member.name)).into(),
+                    components,
+                );
+            }
+        }
+        // Locals are never arrays, and pointers point to variables that are combined themselves
+        DataType::Array(_, _) | DataType::Ptr(_) | DataType::Ref(_) => {}
+    }
+}
@@ -0,0 +1,154 @@
+use ast::types::{DataType, ScalarType};
+use ast::{BinOp, BinOpExpr, ConstAssertStatement, Expr, ExprNode, Lit, UnOp, UnOpExpr, VarExpr};
+
+use crate::rng::{RngExt, SliceExt};
+
+/// Maximum nesting depth of generated const-expressions.
+const MAX_DEPTH: u32 = 3;
+
+const COMPARISON_OPS: &[BinOp] = &[
+    BinOp::Equal,
+    BinOp::NotEqual,
+    BinOp::Less,
+    BinOp::LessEqual,
+    BinOp::Greater,
+    BinOp::GreaterEqual,
+];
+
+impl<'a> super::Generator<'a> {
+    /// Generates a `const_assert` whose condition always holds.
+    ///
+    /// Conditions are built only from literals and module-scope constants, so that they are valid
+    /// const-expressions. The value of each subexpression is tracked while generating it, which is
+    /// used to pick operators that make the whole condition true.
+    pub fn gen_const_assert(&mut self) -> ConstAssertStatement {
+        ConstAssertStatement::new(self.gen_const_bool(true, 0))
+    }
+
+    /// Generates a bool const-expression which evaluates to `value`.
+    pub fn gen_const_bool(&mut self, value: bool, depth: u32) -> ExprNode {
+        if depth >= MAX_DEPTH {
+            return Lit::Bool(value).into();
+        }
+
+        match self.rng.gen_range(0..6) {
+            0 => Lit::Bool(value).into(),
+            1 => UnOpExpr::new(UnOp::Not, self.gen_const_bool(!value, depth + 1)).into(),
+            2 | 3 => self.gen_const_comparison(value, depth + 1),
+            _ => {
+                let op = *[BinOp::LogAnd, BinOp::LogOr].choose(self.rng).unwrap();
+
+                let (l, r) = match (op, value) {
+                    (BinOp::LogAnd, true) => (true, true),
+                    (BinOp::LogOr, false) => (false, false),
+                    // One operand determines the result, so the other can be anything
+                    _ => {
+                        let other = self.rng.gen_bool(0.5);
+                        if self.rng.gen_bool(0.5) {
+                            (value, other)
+                        } else {
+                            (other, value)
+                        }
+                    }
+                };
+
+                let left = self.gen_const_bool(l, depth + 1);
+                let right = self.gen_const_bool(r, depth + 1);
+                BinOpExpr::new(op, left, right).into()
+            }
+        }
+    }
+
+    fn gen_const_comparison(&mut self, value: bool, depth: u32) -> ExprNode {
+        let ty = *[ScalarType::I32, ScalarType::U32].choose(self.rng).unwrap();
+        let (left, l) = self.gen_const_int(ty, depth);
+        let (right, r) = self.gen_const_int(ty, depth);
+
+        let ops = COMPARISON_OPS
+            .iter()
+            .copied()
+            .filter(|op| eval_comparison(*op, l, r) == value)
+            .collect::<Vec<_>>();
+
+        // Each operator has a negation in the list, so there's always one that works
+        let op = *ops.choose(self.rng).unwrap();
+        BinOpExpr::new(op, left, right).into()
+    }
+
+    /// Generates an integer const-expression along with its value.
+    pub fn gen_const_int(&mut self, ty: ScalarType, depth: u32) -> (ExprNode, i64) {
+        if depth < MAX_DEPTH && self.rng.gen_bool(0.5) {
+            let op = *[BinOp::Plus, BinOp::Minus, BinOp::Times]
+                .choose(self.rng)
+                .unwrap();
+
+            let (left, l) = self.gen_const_int(ty, depth + 1);
+            let (right, r) = self.gen_const_int(ty, depth + 1);
+
+            // The product of two u32s can overflow an i64, so this is checked before `fits`
+            let value = match op {
+                BinOp::Plus => l.checked_add(r),
+                BinOp::Minus => l.checked_sub(r),
+                _ => l.checked_mul(r),
+            };
+
+            // Overflow in a const-expression is a shader creation error, so fall back to a literal
+            // if the result doesn't fit in the type
+            if let Some(value) = value.filter(|value| fits(ty, *value)) {
+                return (BinOpExpr::new(op, left, right).into(), value);
+            }
+        }
+
+        if self.rng.gen_bool(0.25) {
+            let data_type = DataType::Scalar(ty);
+            if let Some((name, Some(value))) = self.choose_global_const(&data_type) {
+                return (VarExpr::new(name).into_node(data_type), value);
+            }
+        }
+
+        match ty {
+            ScalarType::I32 => {
+                let value = self.gen_i32();
+                (Lit::I32(value).into(), value as i64)
+            }
+            ScalarType::U32 => {
+                let data_type = DataType::Scalar(ty);
+                let value = self.gen_u32();
+
+                let lit = if self.can_gen_abstract_lit(&data_type) && self.rng.gen_bool(0.25) {
+                    Lit::AbstractInt(value as i64)
+                } else {
+                    Lit::U32(value)
+                };
+
+                let node = ExprNode {
+                    data_type,
+                    expr: Expr::Lit(lit),
+                };
+
+                (node, value as i64)
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn fits(ty: ScalarType, value: i64) -> bool {
+    match ty {
+        ScalarType::I32 => i32::try_from(value).is_ok(),
+        ScalarType::U32 => u32::try_from(value).is_ok(),
+        _ => unreachable!(),
+    }
+}
+
+fn eval_comparison(op: BinOp, l: i64, r: i64) -> bool {
+    match op {
+        BinOp::Equal => l == r,
+        BinOp::NotEqual => l != r,
+        BinOp::Less => l < r,
+        BinOp::LessEqual => l <= r,
+        BinOp::Greater => l > r,
+        BinOp::GreaterEqual => l >= r,
+        _ => unreachable!(),
+    }
+}
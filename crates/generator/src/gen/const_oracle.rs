@@ -0,0 +1,61 @@
+use std::rc::Rc;
+
+use ast::types::DataType;
+use ast::{ExprNode, StructDecl, TypeConsExpr};
+
+impl<'a> super::Generator<'a> {
+    /// Generates a const-expression for the value of the output buffer, along with the value of
+    /// each scalar in it (in the same order as [`common::Type::scalars`]).
+    ///
+    /// The members of the output buffer are always integer scalars or vectors in this mode, since
+    /// the generator can only track the values of integer const-expressions.
+    pub fn gen_const_output(&mut self, decl: &Rc<StructDecl>) -> (ExprNode, Vec<i64>) {
+        let mut values = vec![];
+
+        let args = decl
+            .members
+            .iter()
+            .map(|member| match &member.data_type {
+                DataType::Scalar(ty) => {
+                    let (expr, value) = self.gen_const_int(*ty, 0);
+                    values.push(value);
+                    expr
+                }
+                DataType::Vector(n, ty) => {
+                    let components = (0..*n)
+                        .map(|_| {
+                            let (expr, value) = self.gen_const_int(*ty, 0);
+                            values.push(value);
+                            expr
+                        })
+                        .collect();
+                    TypeConsExpr::new(member.data_type.clone(), components).into()
+                }
+                ty => unreachable!("no const output for member of type `{ty}`"),
+            })
+            .collect();
+
+        let expr = TypeConsExpr::new(DataType::Struct(decl.clone()), args).into();
+
+        (expr, values)
+    }
+}
+
+/// Encodes the values returned by [`gen_const_output`](super::Generator::gen_const_output) in the
+/// buffer layout of `data_type`.
+pub fn encode_const_output(data_type: &DataType, values: &[i64]) -> Result<Vec<u8>, &'static str> {
+    let type_desc = common::Type::try_from(data_type)?;
+    let mut bytes = vec![0; type_desc.buffer_size() as usize];
+
+    for ((offset, scalar_type), value) in type_desc.scalars().into_iter().zip(values) {
+        let value = match scalar_type {
+            common::ScalarType::I32 => *value as i32 as u32,
+            common::ScalarType::U32 => *value as u32,
+            common::ScalarType::F32 => return Err("float outputs are not supported"),
+        };
+
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    Ok(bytes)
+}
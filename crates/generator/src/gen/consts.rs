@@ -0,0 +1,108 @@
+use ast::types::{DataType, ScalarType};
+use ast::{ExprNode, GlobalConstDecl, TypeConsExpr, VarExpr};
+
+use crate::rng::{RngExt, SliceExt};
+
+/// Maximum nesting depth of generated initializers.
+const MAX_DEPTH: u32 = 3;
+
+/// A module-scope constant which can be referred to by later initializers.
+pub struct GlobalConst {
+    name: String,
+    data_type: DataType,
+    /// Value of the constant if it is an integer scalar, which is needed to avoid overflow when it
+    /// is used in arithmetic.
+    value: Option<i64>,
+}
+
+impl<'a> super::Generator<'a> {
+    /// Generates a module-scope constant, whose initializer may refer to the constants generated
+    /// before it.
+    pub fn gen_global_const(&mut self, name: String) -> GlobalConstDecl {
+        let data_type = self.cx.types.select(&mut self.rng);
+        let (initializer, value) = self.gen_const_initializer(&data_type, 0);
+
+        self.global_scope
+            .insert_readonly(name.clone(), data_type.clone());
+
+        self.global_consts.push(GlobalConst {
+            name: name.clone(),
+            data_type: data_type.clone(),
+            value,
+        });
+
+        GlobalConstDecl {
+            name,
+            data_type,
+            initializer,
+        }
+    }
+
+    /// Generates a const-expression for initializing a module-scope declaration, along with its
+    /// value if it is an integer scalar.
+    ///
+    /// Unlike [`gen_const_expr`](Self::gen_const_expr), this nests constructors, refers to other
+    /// module-scope constants and does arithmetic on integers, so that the compiler has to fold
+    /// the initializer.
+    pub fn gen_const_initializer(&mut self, ty: &DataType, depth: u32) -> (ExprNode, Option<i64>) {
+        if self.rng.gen_bool(0.25) {
+            if let Some((name, value)) = self.choose_global_const(ty) {
+                return (VarExpr::new(name).into_node(ty.clone()), value);
+            }
+        }
+
+        match ty {
+            DataType::Scalar(t @ (ScalarType::I32 | ScalarType::U32)) => {
+                let (expr, value) = self.gen_const_int(*t, depth);
+                (expr, Some(value))
+            }
+            DataType::Scalar(ScalarType::Bool) => {
+                let value = self.rng.gen_bool(0.5);
+                (self.gen_const_bool(value, depth), None)
+            }
+            DataType::Scalar(_) if self.can_gen_abstract_lit(ty) && self.rng.gen_bool(0.25) => {
+                (self.gen_abstract_lit_expr(ty), None)
+            }
+            DataType::Scalar(_) => (self.gen_const_expr(ty), None),
+            _ if depth >= MAX_DEPTH => (self.gen_const_expr(ty), None),
+            DataType::Vector(n, t) => {
+                let component_type = DataType::Scalar(*t);
+                let args = (0..*n)
+                    .map(|_| self.gen_const_initializer(&component_type, depth + 1).0)
+                    .collect();
+                (TypeConsExpr::new(ty.clone(), args).into(), None)
+            }
+            DataType::Array(element_type, Some(n)) => {
+                let args = (0..*n)
+                    .map(|_| self.gen_const_initializer(element_type, depth + 1).0)
+                    .collect();
+                (TypeConsExpr::new(ty.clone(), args).into(), None)
+            }
+            DataType::Struct(decl) => {
+                let args = decl
+                    .members
+                    .iter()
+                    .map(|it| self.gen_const_initializer(&it.data_type, depth + 1).0)
+                    .collect();
+                (TypeConsExpr::new(ty.clone(), args).into(), None)
+            }
+            DataType::Array(_, None) | DataType::Ptr(_) | DataType::Ref(_) => {
+                unreachable!("no const initializer for `{ty}`")
+            }
+        }
+    }
+
+    /// Randomly chooses a previously generated module-scope constant of type `ty`, returning its
+    /// name and value.
+    pub fn choose_global_const(&mut self, ty: &DataType) -> Option<(String, Option<i64>)> {
+        let candidates = self
+            .global_consts
+            .iter()
+            .filter(|it| it.data_type == *ty)
+            .collect::<Vec<_>>();
+
+        candidates
+            .choose(self.rng)
+            .map(|it| (it.name.clone(), it.value))
+    }
+}
@@ -3,13 +3,13 @@ use std::iter;
 use std::rc::Rc;
 
 use ast::types::{DataType, ScalarType};
-use ast::{BuiltinFn, FnDecl, StructDecl};
-use rand::prelude::SliceRandom;
-use rand::Rng;
+use ast::{FnDecl, StructDecl};
+use rand::RngCore;
 
-use crate::Options;
+use crate::rng::{RngExt, SliceExt};
 
-use super::{builtins, utils};
+use super::builtins::Builtins;
+use super::utils;
 
 pub struct Context {
     pub types: TypeContext,
@@ -17,10 +17,10 @@ pub struct Context {
 }
 
 impl Context {
-    pub fn new(options: Rc<Options>) -> Context {
+    pub fn new(builtins: Builtins) -> Context {
         Context {
             types: TypeContext::new(),
-            fns: FnContext::new(options),
+            fns: FnContext::new(builtins),
         }
     }
 }
@@ -52,11 +52,11 @@ impl TypeContext {
         self.types.push(decl);
     }
 
-    pub fn select(&self, rng: &mut impl Rng) -> DataType {
+    pub fn select(&self, rng: &mut impl RngCore) -> DataType {
         self.select_with_filter(rng, SelectionFilter::Any)
     }
 
-    pub fn select_with_filter(&self, rng: &mut impl Rng, filter: SelectionFilter) -> DataType {
+    pub fn select_with_filter(&self, rng: &mut impl RngCore, filter: SelectionFilter) -> DataType {
         let allowed_scalars: &[ScalarType] = match filter {
             SelectionFilter::Any => &[
                 ScalarType::I32,
@@ -111,14 +111,14 @@ pub struct Overload {
 
 #[derive(Debug)]
 pub enum Func {
-    Builtin(BuiltinFn, Overload),
+    Builtin(String, Overload),
     User(FnSignature),
 }
 
 impl Func {
     pub fn ident(&self) -> String {
         match self {
-            Func::Builtin(builtin, _) => builtin.as_ref().to_owned(),
+            Func::Builtin(ident, _) => ident.to_owned(),
             Func::User(signature) => signature.ident.to_owned(),
         }
     }
@@ -126,14 +126,26 @@ impl Func {
 
 pub struct FnContext {
     map: HashMap<DataType, Vec<Rc<Func>>>,
+    /// Generated functions, keyed by their exact return type.
+    user_fns: HashMap<DataType, Vec<Rc<Func>>>,
+    /// Generated functions, keyed by their parameter and return types.
+    ///
+    /// Functions with the same signature are never generated twice, so there is only one for each
+    /// key.
+    signatures: HashMap<(Vec<DataType>, DataType), Rc<Func>>,
+    /// Generated helper functions which take a pointer to a struct, keyed by the pointer type.
+    methods: HashMap<DataType, Vec<Rc<Func>>>,
     decls: Vec<FnDecl>,
     count: u32,
 }
 
 impl FnContext {
-    pub fn new(options: Rc<Options>) -> Self {
+    pub fn new(builtins: Builtins) -> Self {
         FnContext {
-            map: builtins::gen_builtins(&options.enabled_fns),
+            map: builtins.into_map(),
+            user_fns: HashMap::new(),
+            signatures: HashMap::new(),
+            methods: HashMap::new(),
             decls: vec![],
             count: 0,
         }
@@ -147,7 +159,7 @@ impl FnContext {
         self.map.contains_key(ty)
     }
 
-    pub fn select(&self, rng: &mut impl Rng, return_ty: &DataType) -> Option<Rc<Func>> {
+    pub fn select(&self, rng: &mut impl RngCore, return_ty: &DataType) -> Option<Rc<Func>> {
         self.map
             .get(return_ty)
             .map(Vec::as_slice)
@@ -156,6 +168,23 @@ impl FnContext {
             .cloned()
     }
 
+    /// Selects a previously generated function which returns exactly `return_ty`.
+    pub fn select_user(&self, rng: &mut impl RngCore, return_ty: &DataType) -> Option<Rc<Func>> {
+        self.user_fns
+            .get(return_ty)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+            .choose(rng)
+            .cloned()
+    }
+
+    /// Finds a previously generated function with the given parameter and return types.
+    pub fn find_user(&self, params: &[DataType], return_ty: &DataType) -> Option<Rc<Func>> {
+        self.signatures
+            .get(&(params.to_vec(), return_ty.clone()))
+            .cloned()
+    }
+
     pub fn insert(&mut self, decl: FnDecl) -> Rc<Func> {
         let sig = FnSignature {
             ident: decl.name.clone(),
@@ -167,10 +196,20 @@ impl FnContext {
             return_type: decl.output.as_ref().map(|ret| ret.data_type.clone()),
         };
 
+        let params = sig.params.clone();
         let return_type = sig.return_type.clone();
         let func = Rc::new(Func::User(sig));
 
         if let Some(ty) = return_type {
+            self.signatures
+                .entry((params, ty.clone()))
+                .or_insert_with(|| func.clone());
+
+            self.user_fns
+                .entry(ty.clone())
+                .or_default()
+                .push(func.clone());
+
             for key in iter::once(ty.clone()).chain(utils::accessible_types_of(&ty)) {
                 self.map.entry(key).or_default().push(func.clone());
             }
@@ -181,6 +220,35 @@ impl FnContext {
         func
     }
 
+    /// Adds a helper function whose only parameter is a pointer to a struct.
+    ///
+    /// It doesn't return anything, so it can only be called from statements.
+    pub fn insert_method(&mut self, decl: FnDecl) -> Rc<Func> {
+        let ptr_type = decl.inputs[0].data_type.clone();
+        let func = self.insert(decl);
+        self.methods.entry(ptr_type).or_default().push(func.clone());
+        func
+    }
+
+    pub fn has_method(&self, ptr_type: &DataType) -> bool {
+        self.methods.contains_key(ptr_type)
+    }
+
+    /// Selects a previously generated helper function which takes a pointer of type `ptr_type`.
+    pub fn select_method(&self, rng: &mut impl RngCore, ptr_type: &DataType) -> Option<Rc<Func>> {
+        self.methods
+            .get(ptr_type)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+            .choose(rng)
+            .cloned()
+    }
+
+    /// Adds a function to the module without making it available to call.
+    pub fn insert_unused(&mut self, decl: FnDecl) {
+        self.decls.push(decl);
+    }
+
     pub fn next_fn(&mut self) -> String {
         self.count += 1;
         format!("func_{}", self.count)
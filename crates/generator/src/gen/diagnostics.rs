@@ -0,0 +1,57 @@
+use ast::{DiagnosticControl, FnAttr, Severity};
+
+use crate::rng::{RngExt, SliceExt};
+
+/// Triggering rules used in diagnostic controls.
+///
+/// Unknown rules only produce a warning, so a vendor-specific rule is included to exercise that
+/// path in frontends which don't implement it.
+const RULES: &[&str] = &["derivative_uniformity", "chromium.unreachable_code"];
+
+impl<'a> super::Generator<'a> {
+    /// Generates the module scope `diagnostic` directives.
+    pub fn gen_diagnostic_directives(&mut self) -> Vec<DiagnosticControl> {
+        if self.options.disable_diagnostics {
+            return vec![];
+        }
+
+        // Directives for the same rule with different severities conflict, so each rule is used at
+        // most once
+        let mut rules = RULES.to_vec();
+        rules.shuffle(self.rng);
+
+        let count = self.rng.gen_range(0..=rules.len());
+        rules
+            .into_iter()
+            .take(count)
+            .map(|rule| self.gen_diagnostic_control(rule))
+            .collect()
+    }
+
+    /// Randomly generates a `@diagnostic` attribute for a function.
+    pub fn gen_fn_diagnostic_attr(&mut self) -> Option<FnAttr> {
+        if self.options.disable_diagnostics || !self.rng.gen_bool(0.2) {
+            return None;
+        }
+
+        let rule = *RULES.choose(self.rng).unwrap();
+        Some(FnAttr::Diagnostic(self.gen_diagnostic_control(rule)))
+    }
+
+    fn gen_diagnostic_control(&mut self, rule: &str) -> DiagnosticControl {
+        // Generated programs don't use derivatives, so any severity is fine for the uniformity
+        // analysis. They may contain unreachable code though, so that can't be made an error.
+        let severities: &[Severity] = if rule == "derivative_uniformity" {
+            &[
+                Severity::Error,
+                Severity::Warning,
+                Severity::Info,
+                Severity::Off,
+            ]
+        } else {
+            &[Severity::Warning, Severity::Info, Severity::Off]
+        };
+
+        DiagnosticControl::new(*severities.choose(self.rng).unwrap(), rule)
+    }
+}
@@ -0,0 +1,36 @@
+//! Repeated subexpressions for `--duplicate-expr-prob`.
+//!
+//! Large expressions are recorded in the scope that they were generated in, and can then be
+//! repeated verbatim wherever an expression of the same type is needed in that scope (or one
+//! nested inside it). This gives common subexpression elimination and value numbering passes in
+//! the compilers something to work on.
+
+use ast::types::DataType;
+use ast::ExprNode;
+
+use crate::rng::{RngExt, SliceExt};
+
+use super::utils;
+
+/// Minimum number of nodes in an expression for it to be recorded.
+const MIN_NODES: usize = 5;
+
+impl<'a> super::Generator<'a> {
+    /// Repeats an expression of type `ty` from the current scope with `duplicate_expr_prob`, or
+    /// otherwise generates a new one and records it if it is large enough.
+    pub fn gen_expr_or_duplicate(&mut self, ty: &DataType) -> ExprNode {
+        let exprs = self.scope.exprs_of_type(ty);
+        if !exprs.is_empty() && self.rng.gen_bool(self.options.duplicate_expr_prob) {
+            return (*exprs.choose(&mut self.rng).unwrap()).clone();
+        }
+
+        let expr = self.gen_new_expr(ty);
+
+        // Pointer expressions are small, and repeating them could alias function arguments
+        if !matches!(ty, DataType::Ptr(_)) && utils::node_count(&expr) >= MIN_NODES {
+            self.scope.insert_expr(expr.clone());
+        }
+
+        expr
+    }
+}
@@ -1,12 +1,11 @@
-use rand::prelude::SliceRandom;
-use rand::Rng;
-
 use ast::types::{DataType, MemoryViewType, ScalarType};
 use ast::{
     BinOp, BinOpExpr, Expr, ExprNode, FnCallExpr, FnInput, Lit, Postfix, PostfixExpr, StructDecl,
     TypeConsExpr, UnOp, UnOpExpr, VarDeclStatement, VarExpr,
 };
 
+use crate::rng::{RngExt, SliceExt};
+
 use super::cx::Func;
 
 #[derive(Clone, Copy, Debug)]
@@ -21,6 +20,15 @@ enum ExprType {
 
 impl<'a> super::Generator<'a> {
     pub fn gen_expr(&mut self, ty: &DataType) -> ExprNode {
+        if self.options.duplicate_expr_prob > 0.0 {
+            return self.gen_expr_or_duplicate(ty);
+        }
+
+        self.gen_new_expr(ty)
+    }
+
+    /// Generates a new expression of type `ty`, without repeating an existing one.
+    pub fn gen_new_expr(&mut self, ty: &DataType) -> ExprNode {
         let mut allowed = vec![];
 
         match ty {
@@ -65,7 +73,22 @@ impl<'a> super::Generator<'a> {
 
         tracing::info!("allowed constructions: {:?}", allowed);
 
-        match *allowed.choose(&mut self.rng).unwrap() {
+        let weights = self.options.expr_weights;
+        let weight = |t: &ExprType| match t {
+            ExprType::Lit => weights.lit,
+            ExprType::TypeCons => weights.type_cons,
+            ExprType::Var => weights.var,
+            ExprType::UnOp => weights.un_op,
+            ExprType::BinOp => weights.bin_op,
+            ExprType::FnCall => weights.fn_call,
+        };
+
+        let choice = allowed
+            .choose_weighted(self.rng, weight)
+            .or_else(|| allowed.choose(self.rng))
+            .unwrap();
+
+        match *choice {
             ExprType::Lit => self.gen_lit_expr(ty),
             ExprType::TypeCons => self.gen_type_cons_expr(ty),
             ExprType::UnOp => self.gen_un_op_expr(ty),
@@ -124,9 +147,7 @@ impl<'a> super::Generator<'a> {
 
         let args = match ty {
             DataType::Scalar(t) => vec![self.gen_expr(&DataType::Scalar(*t))],
-            DataType::Vector(n, t) => (0..*n)
-                .map(|_| self.gen_expr(&DataType::Scalar(*t)))
-                .collect(),
+            DataType::Vector(n, t) => self.gen_vector_cons_args(*n, *t),
             DataType::Array(_, _) => vec![],
             DataType::Struct(decl) => decl
                 .members
@@ -141,6 +162,77 @@ impl<'a> super::Generator<'a> {
         TypeConsExpr::new(ty.clone(), args).into()
     }
 
+    /// Generates the arguments for a `vecN<T>` constructor, in a form chosen according to the
+    /// weights in the options.
+    fn gen_vector_cons_args(&mut self, n: u8, t: ScalarType) -> Vec<ExprNode> {
+        #[derive(Clone, Copy)]
+        enum Form {
+            Components,
+            Splat,
+            Mixed,
+            Conversion,
+            Zero,
+        }
+
+        let weights = self.options.vector_cons_weights;
+        let forms = [
+            (Form::Components, weights.components),
+            (Form::Splat, weights.splat),
+            (Form::Mixed, weights.mixed),
+            (Form::Conversion, weights.conversion),
+            (Form::Zero, weights.zero),
+        ];
+
+        let form = forms
+            .choose_weighted(self.rng, |(_, weight)| *weight)
+            .map(|(form, _)| *form)
+            .unwrap_or(Form::Components);
+
+        match form {
+            Form::Components => (0..n)
+                .map(|_| self.gen_expr(&DataType::Scalar(t)))
+                .collect(),
+            Form::Splat => vec![self.gen_expr(&DataType::Scalar(t))],
+            Form::Mixed => {
+                let mut args = vec![];
+                let mut remaining = n;
+
+                // Each argument covers fewer than n components, so that there are always at least
+                // two arguments
+                while remaining > 0 {
+                    let size = self.rng.gen_range(1..=remaining.min(n - 1));
+                    let ty = match size {
+                        1 => DataType::Scalar(t),
+                        size => DataType::Vector(size, t),
+                    };
+
+                    args.push(self.gen_expr(&ty));
+                    remaining -= size;
+                }
+
+                args
+            }
+            Form::Conversion => {
+                // Converting an out of range float to an integer is an error in const-expressions,
+                // so floats are only converted to bools
+                let sources = [
+                    ScalarType::Bool,
+                    ScalarType::I32,
+                    ScalarType::U32,
+                    ScalarType::F32,
+                ]
+                .into_iter()
+                .filter(|it| *it != t)
+                .filter(|it| *it != ScalarType::F32 || t == ScalarType::Bool)
+                .collect::<Vec<_>>();
+
+                let source = *sources.choose(self.rng).unwrap();
+                vec![self.gen_expr(&DataType::Vector(n, source))]
+            }
+            Form::Zero => vec![],
+        }
+    }
+
     fn gen_const_type_cons_expr(&mut self, ty: &DataType) -> ExprNode {
         let args = match ty {
             DataType::Scalar(t) => vec![self.gen_expr(&DataType::Scalar(*t))],
@@ -220,7 +312,6 @@ impl<'a> super::Generator<'a> {
             ),
         };
 
-        let l = self.gen_expr(&l_ty);
         let r_ty = match op {
             // For shifts, right operand must be u32
             BinOp::LShift | BinOp::RShift => l_ty.map(ScalarType::U32),
@@ -228,13 +319,80 @@ impl<'a> super::Generator<'a> {
             _ => l_ty.clone(),
         };
 
-        let r = self.gen_expr(&r_ty);
+        // An abstract literal operand is converted to the type of the other operand, so shifts are
+        // excluded since their operands don't need to have the same type
+        let abstract_operand = if !matches!(op, BinOp::LShift | BinOp::RShift)
+            && self.can_gen_abstract_lit(&l_ty)
+            && self.rng.gen_bool(0.2)
+        {
+            Some(self.rng.gen_index(2))
+        } else {
+            None
+        };
+
+        let (l, r) = match abstract_operand {
+            Some(index) => {
+                let other = self.gen_expr(&l_ty);
+
+                // If the other operand is also abstract (typed i32 and f32 literals are written
+                // without a suffix too) then the operation would no longer be evaluated in the
+                // concrete type
+                let operand = if is_abstract(&other) {
+                    self.gen_expr(&l_ty)
+                } else {
+                    self.gen_abstract_lit_expr(&l_ty)
+                };
+
+                if index == 0 {
+                    (operand, other)
+                } else {
+                    (other, operand)
+                }
+            }
+            None => {
+                let l = self.gen_expr(&l_ty);
+                let r = self.gen_expr(&r_ty);
+                (l, r)
+            }
+        };
 
         self.fn_state.expression_depth -= 1;
 
         BinOpExpr::new(op, l, r).into()
     }
 
+    /// Whether an abstract literal can be generated where a value of type `ty` is expected.
+    ///
+    /// Unsuffixed integers are converted to i32 by default, so only conversions to u32 and f32
+    /// are interesting.
+    pub fn can_gen_abstract_lit(&self, ty: &DataType) -> bool {
+        !self.options.disable_abstract_literals
+            && matches!(ty, DataType::Scalar(ScalarType::U32 | ScalarType::F32))
+    }
+
+    /// Generates an abstract literal which is implicitly converted to `ty`.
+    ///
+    /// Values converted to f32 are always exactly representable, since implementations may round
+    /// inexact conversions in either direction.
+    pub fn gen_abstract_lit_expr(&mut self, ty: &DataType) -> ExprNode {
+        let lit = match ty {
+            DataType::Scalar(ScalarType::U32) => Lit::AbstractInt(self.gen_u32() as i64),
+            DataType::Scalar(ScalarType::F32) if self.rng.gen_bool(0.5) => {
+                Lit::AbstractInt(self.rng.gen_range(-(1 << 24)..=1 << 24))
+            }
+            // 15 integer bits and 8 fractional bits fit in the f32 mantissa
+            DataType::Scalar(ScalarType::F32) => {
+                Lit::AbstractFloat(self.rng.gen_range(-(1i64 << 23)..=1 << 23) as f64 / 256.0)
+            }
+            _ => unreachable!("no abstract literal for `{ty}`"),
+        };
+
+        ExprNode {
+            data_type: ty.clone(),
+            expr: Expr::Lit(lit),
+        }
+    }
+
     fn gen_var_expr(&mut self, ty: &DataType) -> ExprNode {
         tracing::info!("generating var with {:?}, scope={:?}", ty, self.scope);
 
@@ -263,27 +421,18 @@ impl<'a> super::Generator<'a> {
     }
 
     fn gen_raw_fn_call_expr(&mut self, ty: &DataType) -> ExprNode {
+        // Prefer calling a function that was already generated with the target return type, so
+        // that functions are reused from different call sites and call graphs get deeper
+        if self.rng.gen_bool(self.options.fn_reuse_prob) {
+            if let Some(func) = self.cx.fns.select_user(&mut self.rng, ty) {
+                return self.gen_call(&func);
+            }
+        }
+
         // Produce a function call with p=0.8 or p=1 if max functions reached
         if self.cx.fns.len() > self.options.max_fns || self.rng.gen_bool(0.8) {
-            if let Some(func) = self.cx.fns.select(self.rng, ty) {
-                let (name, params, return_type) = match func.as_ref() {
-                    Func::Builtin(builtin, overload) => (
-                        builtin.as_ref(),
-                        overload.params.as_slice(),
-                        Some(&overload.return_type),
-                    ),
-                    Func::User(sig) => (
-                        sig.ident.as_str(),
-                        sig.params.as_slice(),
-                        sig.return_type.as_ref(),
-                    ),
-                };
-
-                self.fn_state.expression_depth += 1;
-                let args = params.iter().map(|ty| self.gen_expr(ty)).collect();
-                self.fn_state.expression_depth -= 1;
-
-                return FnCallExpr::new(name, args).into_node(return_type.unwrap().clone());
+            if let Some(func) = self.cx.fns.select(&mut self.rng, ty) {
+                return self.gen_call(&func);
             }
         }
 
@@ -299,12 +448,12 @@ impl<'a> super::Generator<'a> {
                 && self.scope.has_references()
                 && self.rng.gen_bool(0.2)
             {
-                let (name, mem_view) = self.scope.choose_reference(self.rng);
+                let (name, mem_view) = self.scope.choose_reference(&mut self.rng);
                 let var_expr = VarExpr::new(name).into_node(DataType::Ref(mem_view.clone()));
                 UnOpExpr::new(UnOp::AddressOf, var_expr).into()
             } else {
                 self.fn_state.expression_depth += 1;
-                let data_type = self.cx.types.select(self.rng);
+                let data_type = self.cx.types.select(&mut self.rng);
                 let expr = self.gen_expr(&data_type);
                 self.fn_state.expression_depth -= 1;
                 expr
@@ -319,6 +468,17 @@ impl<'a> super::Generator<'a> {
             args.push(expr);
         }
 
+        // Functions with the same signature are interchangeable, so call the existing one rather
+        // than generating a duplicate
+        let param_types = params
+            .iter()
+            .map(|it| it.data_type.clone())
+            .collect::<Vec<_>>();
+
+        if let Some(func) = self.cx.fns.find_user(&param_types, ty) {
+            return FnCallExpr::new(func.ident(), args).into_node(ty.clone());
+        }
+
         let decl = self.gen_fn(params, ty);
 
         // Add the new function to the context
@@ -327,6 +487,41 @@ impl<'a> super::Generator<'a> {
         FnCallExpr::new(func.ident(), args).into_node(ty.clone())
     }
 
+    /// Generates a call to an existing function, with randomly generated arguments.
+    fn gen_call(&mut self, func: &Func) -> ExprNode {
+        let (name, params, return_type) = match func {
+            Func::Builtin(ident, overload) => (
+                ident.as_str(),
+                overload.params.as_slice(),
+                Some(&overload.return_type),
+            ),
+            Func::User(sig) => (
+                sig.ident.as_str(),
+                sig.params.as_slice(),
+                sig.return_type.as_ref(),
+            ),
+        };
+
+        // Builtins are excluded, since abstract arguments can change which overload is
+        // selected
+        let is_user = matches!(func, Func::User(_));
+
+        self.fn_state.expression_depth += 1;
+        let args = params
+            .iter()
+            .map(|ty| {
+                if is_user && self.can_gen_abstract_lit(ty) && self.rng.gen_bool(0.2) {
+                    self.gen_abstract_lit_expr(ty)
+                } else {
+                    self.gen_expr(ty)
+                }
+            })
+            .collect();
+        self.fn_state.expression_depth -= 1;
+
+        FnCallExpr::new(name, args).into_node(return_type.unwrap().clone())
+    }
+
     fn gen_accessor(&mut self, target: &DataType, expr: ExprNode) -> ExprNode {
         match expr.data_type.dereference() {
             DataType::Scalar(_) => unreachable!(),
@@ -339,7 +534,7 @@ impl<'a> super::Generator<'a> {
     }
 
     fn gen_vector_accessor(&mut self, size: u8, target: &DataType, expr: ExprNode) -> ExprNode {
-        let accessor = super::utils::gen_vector_accessor(self.rng, size, target);
+        let accessor = super::utils::gen_vector_accessor(&mut self.rng, size, target);
         PostfixExpr::new(expr, Postfix::member(accessor)).into()
     }
 
@@ -386,7 +581,7 @@ impl<'a> super::Generator<'a> {
 
         match ty {
             DataType::Scalar(t) => match t {
-                ScalarType::Bool => Lit::Bool(self.rng.gen()),
+                ScalarType::Bool => Lit::Bool(self.rng.gen_bool(0.5)),
                 ScalarType::I32 => Lit::I32(self.gen_i32()),
                 ScalarType::U32 => Lit::U32(self.gen_u32()),
                 ScalarType::F32 => Lit::F32(self.gen_f32()),
@@ -462,6 +657,22 @@ impl<'a> super::Generator<'a> {
             allowed.extend_from_slice(&[BinOp::LogAnd, BinOp::LogOr]);
         }
 
-        *allowed.choose(&mut self.rng).unwrap()
+        let weights = self.options.bin_op_weights;
+
+        *allowed
+            .choose_weighted(self.rng, |op| weights.weight(*op))
+            .or_else(|| allowed.choose(self.rng))
+            .unwrap()
+    }
+}
+
+/// Whether an expression is built only from unsuffixed literals, which makes it an abstract
+/// const-expression.
+fn is_abstract(node: &ExprNode) -> bool {
+    match &node.expr {
+        Expr::Lit(lit) => !matches!(lit, Lit::Bool(_) | Lit::U32(_)),
+        Expr::UnOp(expr) => is_abstract(&expr.inner),
+        Expr::BinOp(expr) => is_abstract(&expr.left) && is_abstract(&expr.right),
+        _ => false,
     }
 }
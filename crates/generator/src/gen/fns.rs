@@ -2,7 +2,8 @@ use std::mem;
 
 use ast::types::DataType;
 use ast::{FnDecl, FnInput, FnOutput};
-use rand::Rng;
+
+use crate::rng::RngExt;
 
 impl<'a> super::Generator<'a> {
     pub fn gen_fn(&mut self, params: Vec<FnInput>, return_type: &DataType) -> FnDecl {
@@ -27,7 +28,7 @@ impl<'a> super::Generator<'a> {
         self.fn_state = saved_state;
 
         FnDecl {
-            attrs: vec![],
+            attrs: self.gen_fn_diagnostic_attr().into_iter().collect(),
             name,
             inputs: params,
             output: Some(FnOutput {
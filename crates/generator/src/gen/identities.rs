@@ -0,0 +1,193 @@
+//! Semantics-preserving rewrites for `--identity-rewrites`.
+//!
+//! After a program has been generated, expressions are randomly rewritten into equivalent but
+//! more complicated forms, such as `x` into `(x ^ k) ^ k` or `a + b` into `b + a`. Programs
+//! generated from the same seed must then produce the same output whatever the rewrite seed is,
+//! which gives an oracle that doesn't depend on comparing different implementations.
+
+use std::mem;
+
+use ast::types::{DataType, ScalarType};
+use ast::visit::{self, VisitorMut};
+use ast::{
+    BinOp, BinOpExpr, Expr, ExprNode, FnCallExpr, Lit, Module, Postfix, Statement, TypeConsExpr,
+    UnOp, UnOpExpr,
+};
+use rand::RngCore;
+
+use crate::rng::{RngExt, SliceExt};
+
+use super::utils;
+
+/// Maximum number of nodes in an expression for it to be repeated by [`Rewrite::Select`].
+const MAX_SELECT_NODES: usize = 8;
+
+#[derive(Clone, Copy, Debug)]
+enum Rewrite {
+    /// `x` to `(x ^ k) ^ k`
+    XorTwice,
+    /// `x` to `~(~x)`, or `!(!x)` for bools
+    NotTwice,
+    /// `x` to `-(-x)`, for floats
+    NegTwice,
+    /// `x` to `select(x, x, c)`
+    Select,
+    /// `a op b` to `b op a`, for commutative operators
+    Swap,
+}
+
+/// Rewrites expressions in the functions of `module`, each with probability `prob`.
+///
+/// Module-scope initializers are left alone, since they can contain abstract literals whose
+/// types would change in the rewritten forms.
+pub fn rewrite_identities(module: &mut Module, rng: &mut dyn RngCore, prob: f64) {
+    let mut rewriter = Rewriter { rng, prob };
+
+    for decl in &mut module.functions {
+        rewriter.visit_fn_decl_mut(decl);
+    }
+}
+
+struct Rewriter<'a> {
+    rng: &'a mut dyn RngCore,
+    prob: f64,
+}
+
+impl<'a> Rewriter<'a> {
+    fn rewrite(&mut self, node: &mut ExprNode) {
+        // Other types are either references, which are used as places, or don't have any of the
+        // operators used by the rewrites
+        let scalar_type = match &node.data_type {
+            DataType::Scalar(ty) | DataType::Vector(_, ty) => *ty,
+            _ => return,
+        };
+
+        if let Expr::Lit(Lit::AbstractInt(_) | Lit::AbstractFloat(_)) = node.expr {
+            return;
+        }
+
+        let mut allowed = match scalar_type {
+            ScalarType::Bool => vec![Rewrite::NotTwice],
+            ScalarType::I32 | ScalarType::U32 => vec![Rewrite::XorTwice, Rewrite::NotTwice],
+            ScalarType::F32 => vec![Rewrite::NegTwice],
+        };
+
+        // Repeating or reordering an expression is only safe if evaluating it has no side effects
+        if is_pure(node) && utils::node_count(node) <= MAX_SELECT_NODES {
+            allowed.push(Rewrite::Select);
+        }
+
+        if let Expr::BinOp(expr) = &node.expr {
+            if is_commutative(expr.op) && is_pure(&expr.left) && is_pure(&expr.right) {
+                allowed.push(Rewrite::Swap);
+            }
+        }
+
+        let rewrite = *allowed.choose(&mut self.rng).unwrap();
+        let data_type = node.data_type.clone();
+        let inner = mem::replace(node, Lit::Bool(false).into());
+
+        *node = match rewrite {
+            Rewrite::XorTwice => {
+                // Negative i32 literals are avoided, since they're written with a unary minus
+                let lit = match scalar_type {
+                    ScalarType::I32 => Lit::I32((self.rng.next_u32() >> 1) as i32),
+                    _ => Lit::U32(self.rng.next_u32()),
+                };
+
+                let k = || -> ExprNode {
+                    match data_type {
+                        DataType::Vector(_, _) => {
+                            TypeConsExpr::new(data_type.clone(), vec![lit.into()]).into()
+                        }
+                        _ => lit.into(),
+                    }
+                };
+
+                BinOpExpr::new(
+                    BinOp::BitXOr,
+                    BinOpExpr::new(BinOp::BitXOr, inner, k()),
+                    k(),
+                )
+                .into()
+            }
+            Rewrite::NotTwice => {
+                let op = match scalar_type {
+                    ScalarType::Bool => UnOp::Not,
+                    _ => UnOp::BitNot,
+                };
+
+                UnOpExpr::new(op, UnOpExpr::new(op, inner)).into()
+            }
+            Rewrite::NegTwice => UnOpExpr::new(UnOp::Neg, UnOpExpr::new(UnOp::Neg, inner)).into(),
+            Rewrite::Select => {
+                let condition = Lit::Bool(self.rng.gen_bool(0.5));
+                FnCallExpr::new("select", vec![inner.clone(), inner, condition.into()])
+                    .into_node(data_type)
+            }
+            Rewrite::Swap => match inner.expr {
+                Expr::BinOp(expr) => BinOpExpr::new(expr.op, *expr.right, *expr.left).into(),
+                _ => unreachable!(),
+            },
+        };
+    }
+}
+
+impl<'a> VisitorMut for Rewriter<'a> {
+    fn visit_stmt_mut(&mut self, stmt: &mut Statement) {
+        match stmt {
+            // Case selectors are left as they are, so that they stay distinct literals
+            Statement::Switch(stmt) => {
+                self.visit_expr_mut(&mut stmt.selector);
+
+                for case in &mut stmt.cases {
+                    self.visit_block_mut(&mut case.body);
+                }
+
+                self.visit_block_mut(&mut stmt.default);
+            }
+            Statement::ConstAssert(_) => {}
+            stmt => visit::walk_stmt_mut(self, stmt),
+        }
+    }
+
+    fn visit_expr_mut(&mut self, node: &mut ExprNode) {
+        visit::walk_expr_mut(self, node);
+
+        if self.rng.gen_bool(self.prob) {
+            self.rewrite(node);
+        }
+    }
+}
+
+fn is_commutative(op: BinOp) -> bool {
+    matches!(
+        op,
+        BinOp::Plus
+            | BinOp::Times
+            | BinOp::BitAnd
+            | BinOp::BitOr
+            | BinOp::BitXOr
+            | BinOp::Equal
+            | BinOp::NotEqual
+    )
+}
+
+/// Returns true if an expression doesn't contain any function calls, which might have side
+/// effects.
+fn is_pure(node: &ExprNode) -> bool {
+    match &node.expr {
+        Expr::Lit(_) | Expr::Var(_) => true,
+        Expr::TypeCons(expr) => expr.args.iter().all(is_pure),
+        Expr::Postfix(expr) => {
+            is_pure(&expr.inner)
+                && match &expr.postfix {
+                    Postfix::Index(index) => is_pure(index),
+                    Postfix::Member(_) => true,
+                }
+        }
+        Expr::UnOp(expr) => is_pure(&expr.inner),
+        Expr::BinOp(expr) => is_pure(&expr.left) && is_pure(&expr.right),
+        Expr::FnCall(_) => false,
+    }
+}
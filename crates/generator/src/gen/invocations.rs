@@ -0,0 +1,156 @@
+use std::rc::Rc;
+
+use ast::types::{DataType, MemoryViewType};
+use ast::{
+    AccessMode, AssignmentLhs, AssignmentOp, AssignmentStatement, BinOp, BinOpExpr, BuiltinValue,
+    ExprNode, FnCallStatement, FnInput, FnInputAttr, GlobalVarDecl, Lit, Postfix, PostfixExpr,
+    ScalarType, Statement, StorageClass, StructDecl, VarExpr, VarQualifier,
+};
+
+use crate::rng::{RngExt, SliceExt};
+
+/// Name of the entry point parameter holding the `local_invocation_index` builtin.
+pub const LOCAL_INDEX: &str = "local_index";
+
+/// Name of the workgroup variable that outputs are staged in.
+const STAGING_VAR: &str = "w_staging";
+
+/// Permutation used to decide which invocation's staged output is copied to each element of the
+/// output buffer.
+#[derive(Clone, Copy)]
+enum StagingPermutation {
+    Identity,
+    Reverse,
+    /// XOR with a mask, which is only a permutation if the number of invocations is a power of
+    /// two.
+    Xor(u32),
+}
+
+impl<'a> super::Generator<'a> {
+    fn is_multi_invocation(&self) -> bool {
+        self.options.invocations > 1
+    }
+
+    /// Returns true if outputs are staged in workgroup memory before being copied to the output
+    /// buffer.
+    pub fn is_staging_outputs(&self) -> bool {
+        self.is_multi_invocation() && self.options.workgroup_staging
+    }
+
+    /// Returns the type of the output buffer variable.
+    ///
+    /// With multiple invocations, this is an array with an element for each invocation, so that
+    /// every invocation writes to its own disjoint slice of the buffer.
+    pub fn output_buffer_type(&self, decl: &Rc<StructDecl>) -> DataType {
+        let element_type = DataType::Struct(decl.clone());
+        if self.is_multi_invocation() {
+            DataType::Array(Rc::new(element_type), Some(self.options.invocations))
+        } else {
+            element_type
+        }
+    }
+
+    /// Generates the workgroup variable that outputs are staged in, if enabled.
+    ///
+    /// It isn't added to the global scope, so that only the final copy accesses it.
+    pub fn gen_staging_var(&self, out_buf_type: &DataType) -> Option<GlobalVarDecl> {
+        if !self.is_staging_outputs() {
+            return None;
+        }
+
+        Some(GlobalVarDecl {
+            attrs: vec![],
+            qualifier: Some(VarQualifier {
+                storage_class: StorageClass::WorkGroup,
+                access_mode: None,
+            }),
+            name: STAGING_VAR.to_owned(),
+            data_type: out_buf_type.clone(),
+            initializer: None,
+        })
+    }
+
+    /// Returns the parameters for the entry point, and adds them to the current scope.
+    ///
+    /// Subgroup reductions index workgroup memory by the local index, so it is also a parameter
+    /// when they are enabled, even with a single invocation.
+    pub fn gen_entrypoint_inputs(&mut self) -> Vec<FnInput> {
+        if !self.is_multi_invocation() && !self.options.subgroups {
+            return vec![];
+        }
+
+        let data_type = DataType::Scalar(ScalarType::U32);
+
+        self.scope
+            .insert_readonly(LOCAL_INDEX.to_owned(), data_type.clone());
+
+        vec![FnInput {
+            attrs: vec![FnInputAttr::Builtin(BuiltinValue::LocalInvocationIndex)],
+            name: LOCAL_INDEX.to_owned(),
+            data_type,
+        }]
+    }
+
+    /// Generates statements that write `value` for the current invocation to its element of the
+    /// output buffer.
+    ///
+    /// If staging is enabled, the value is written to workgroup memory first, and then each
+    /// invocation copies one of the staged values to its element after a barrier. The staged value
+    /// may have been written by a different invocation, but every invocation still only writes
+    /// to its own element, so there are no races.
+    pub fn gen_invocation_output_writes(
+        &mut self,
+        out_buf_type: &DataType,
+        value: ExprNode,
+    ) -> Vec<Statement> {
+        let local_index = || VarExpr::new(LOCAL_INDEX).into_node(ScalarType::U32);
+
+        let out_view = MemoryViewType {
+            inner: Rc::new(out_buf_type.clone()),
+            storage_class: StorageClass::Storage,
+            access_mode: AccessMode::ReadWrite,
+        };
+
+        let out_lhs =
+            AssignmentLhs::array_index("s_output", DataType::Ref(out_view), local_index());
+
+        if !self.is_staging_outputs() {
+            return vec![AssignmentStatement::new(out_lhs, AssignmentOp::Simple, value).into()];
+        }
+
+        let staging_type = DataType::Ref(MemoryViewType::new(
+            out_buf_type.clone(),
+            StorageClass::WorkGroup,
+        ));
+
+        let staging_lhs =
+            AssignmentLhs::array_index(STAGING_VAR, staging_type.clone(), local_index());
+
+        let n = self.options.invocations;
+        let mut permutations = vec![StagingPermutation::Identity, StagingPermutation::Reverse];
+        if n.is_power_of_two() {
+            permutations.push(StagingPermutation::Xor(self.rng.gen_range(1..n)));
+        }
+
+        let index: ExprNode = match *permutations.choose(self.rng).unwrap() {
+            StagingPermutation::Identity => local_index(),
+            StagingPermutation::Reverse => {
+                BinOpExpr::new(BinOp::Minus, Lit::U32(n - 1), local_index()).into()
+            }
+            StagingPermutation::Xor(mask) => {
+                BinOpExpr::new(BinOp::BitXOr, local_index(), Lit::U32(mask)).into()
+            }
+        };
+
+        let staged_value = PostfixExpr::new(
+            VarExpr::new(STAGING_VAR).into_node(staging_type),
+            Postfix::index(index),
+        );
+
+        vec![
+            AssignmentStatement::new(staging_lhs, AssignmentOp::Simple, value).into(),
+            FnCallStatement::new("workgroupBarrier".to_owned(), vec![]).into(),
+            AssignmentStatement::new(out_lhs, AssignmentOp::Simple, staged_value).into(),
+        ]
+    }
+}
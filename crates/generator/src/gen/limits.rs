@@ -0,0 +1,150 @@
+//! Declarations which sit at (or just over) implementation and device limits.
+//!
+//! These are enabled by passing a limits profile with `--limits`, and are used to test that
+//! compilers and drivers enforce their limits correctly, without rejecting programs that are
+//! within them.
+
+use std::rc::Rc;
+use std::str::FromStr;
+
+use ast::types::{DataType, ScalarType};
+use ast::{
+    FnDecl, FnInput, FnOutput, Lit, ReturnStatement, Statement, StructDecl, StructMember, VarExpr,
+};
+use serde::Deserialize;
+
+/// Limits to generate programs against.
+///
+/// This can be read from the JSON representation of a `GPUSupportedLimits` object, in which case
+/// the limits that aren't used by the generator are ignored. The WGSL implementation limits aren't
+/// part of WebGPU's limits, so they have to be added by hand (the minimums required by the WGSL
+/// spec are 255 function parameters, 1023 struct members and a brace nesting depth of 127).
+///
+/// Limits which aren't set are left alone.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Limits {
+    pub max_bind_groups: Option<u32>,
+    pub max_bindings_per_bind_group: Option<u32>,
+    pub max_function_parameters: Option<u32>,
+    pub max_struct_members: Option<u32>,
+    /// Maximum nesting depth of brace-enclosed statements in a function, including the function
+    /// body.
+    pub max_nesting_depth: Option<u32>,
+}
+
+impl FromStr for Limits {
+    type Err = String;
+
+    /// Reads limits from the JSON file at the path `s`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let json = std::fs::read_to_string(s).map_err(|e| format!("failed to read {s}: {e}"))?;
+        serde_json::from_str(&json).map_err(|e| format!("invalid limits in {s}: {e}"))
+    }
+}
+
+impl<'a> super::Generator<'a> {
+    /// Returns the value to use for a limit, which is the limit itself or one more than it if
+    /// the limits should be exceeded.
+    fn limit_value(&self, limit: u32) -> u32 {
+        if self.options.exceed_limits {
+            limit.saturating_add(1)
+        } else {
+            limit
+        }
+    }
+
+    /// Returns the `(group, binding)` for the output buffer, if it should be placed at the limits.
+    ///
+    /// The input buffer is always bound at `@group(0) @binding(0)`, so the position of the output
+    /// buffer is pushed away from it if necessary.
+    pub fn limit_output_binding(&self) -> Option<(u32, u32)> {
+        let limits = self.options.limits.as_ref()?;
+
+        if limits.max_bind_groups.is_none() && limits.max_bindings_per_bind_group.is_none() {
+            return None;
+        }
+
+        // Groups and bindings are indices, so the last valid one is one less than the limit
+        let index = |limit: Option<u32>| limit.map(|it| self.limit_value(it).saturating_sub(1));
+
+        let group = index(limits.max_bind_groups).unwrap_or(0);
+        let binding = index(limits.max_bindings_per_bind_group).unwrap_or(1);
+
+        if (group, binding) == (0, 0) {
+            Some((0, 1))
+        } else {
+            Some((group, binding))
+        }
+    }
+
+    /// Generates a struct with as many members as the limits allow.
+    pub fn gen_limit_structs(&self) -> Vec<Rc<StructDecl>> {
+        let limit = match &self.options.limits {
+            Some(Limits {
+                max_struct_members: Some(limit),
+                ..
+            }) => self.limit_value(*limit),
+            _ => return vec![],
+        };
+
+        let members = (0..limit)
+            .map(|i| StructMember::new(vec![], format!("m{i}"), ScalarType::I32.into()))
+            .collect();
+
+        vec![StructDecl::new("LimitStruct", members)]
+    }
+
+    /// Generates functions with as many parameters and as deeply nested blocks as the limits
+    /// allow.
+    ///
+    /// These are never called, since they only exist to check that the compiler accepts (or
+    /// rejects) the declarations.
+    pub fn gen_limit_fns(&self) -> Vec<FnDecl> {
+        let limits = match &self.options.limits {
+            Some(limits) => limits,
+            None => return vec![],
+        };
+
+        let mut fns = vec![];
+
+        if let Some(limit) = limits.max_function_parameters {
+            let inputs = (0..self.limit_value(limit))
+                .map(|i| FnInput::new(format!("arg_{i}"), ScalarType::I32))
+                .collect::<Vec<_>>();
+
+            let body = match inputs.first() {
+                Some(input) => ReturnStatement::new(
+                    VarExpr::new(input.name.clone()).into_node(ScalarType::I32),
+                ),
+                None => ReturnStatement::new(Lit::I32(0)),
+            };
+
+            fns.push(FnDecl {
+                attrs: vec![],
+                name: "limit_params".to_owned(),
+                inputs,
+                output: Some(FnOutput::new(DataType::Scalar(ScalarType::I32))),
+                body: vec![body.into()],
+            });
+        }
+
+        if let Some(limit) = limits.max_nesting_depth {
+            // The function body is the outermost block
+            let mut body = vec![];
+            for _ in 1..self.limit_value(limit) {
+                body = vec![Statement::Compound(body)];
+            }
+
+            fns.push(FnDecl {
+                attrs: vec![],
+                name: "limit_nesting".to_owned(),
+                inputs: vec![],
+                output: None,
+                body,
+            });
+        }
+
+        fns
+    }
+}
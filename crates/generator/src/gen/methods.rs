@@ -0,0 +1,141 @@
+//! Helper functions which take a pointer to a struct and mutate its members through it.
+//!
+//! With `--struct-methods`, a local struct variable can be passed by pointer to a function like
+//! `fn func_1(arg_0: ptr<function, Struct_1>)`, which reads and writes its members through the
+//! pointer. This combines aggregates, pointers and stores, which backends often get wrong.
+
+use std::mem;
+use std::rc::Rc;
+
+use ast::types::{DataType, MemoryViewType};
+use ast::{
+    AssignmentOp, AssignmentStatement, FnCallStatement, FnDecl, FnInput, LhsExpr, LhsExprNode,
+    Postfix, Statement, StructDecl, UnOp, UnOpExpr, VarExpr,
+};
+
+use crate::rng::{RngExt, SliceExt};
+
+/// Name of the pointer parameter of a helper function.
+const PTR_PARAM: &str = "arg_0";
+
+impl<'a> super::Generator<'a> {
+    /// Returns true if a struct method call statement can be generated in the current scope.
+    pub fn can_gen_method_call(&self) -> bool {
+        self.options.struct_methods
+            && self.scope.struct_vars().iter().any(|(_, view)| {
+                self.cx.fns.len() < self.options.max_fns || self.cx.fns.has_method(&ptr_type(view))
+            })
+    }
+
+    /// Generates a call to a helper function which takes a pointer to a local struct variable.
+    ///
+    /// An existing helper for the struct type is reused with `fn_reuse_prob`, or if no more
+    /// functions can be generated.
+    pub fn gen_method_call_stmt(&mut self) -> Statement {
+        let (name, view) = self
+            .scope
+            .struct_vars()
+            .choose(&mut self.rng)
+            .map(|(name, view)| ((*name).clone(), (*view).clone()))
+            .unwrap();
+
+        let ptr_type = ptr_type(&view);
+        let can_gen = self.cx.fns.len() < self.options.max_fns;
+
+        let existing = if !can_gen || self.rng.gen_bool(self.options.fn_reuse_prob) {
+            self.cx.fns.select_method(&mut self.rng, &ptr_type)
+        } else {
+            None
+        };
+
+        let func = match existing {
+            Some(func) => func,
+            None => {
+                let decl = match view.inner.as_ref() {
+                    DataType::Struct(decl) => self.gen_method(decl.clone(), view.clone()),
+                    ty => unreachable!("expected struct, got `{ty}`"),
+                };
+
+                self.cx.fns.insert_method(decl)
+            }
+        };
+
+        let arg = UnOpExpr::new(
+            UnOp::AddressOf,
+            VarExpr::new(name).into_node(DataType::Ref(view)),
+        );
+
+        FnCallStatement::new(func.ident(), vec![arg.into()]).into()
+    }
+
+    /// Generates a helper function which takes a pointer to a struct, and assigns to some of its
+    /// members through the pointer after a block of statements.
+    fn gen_method(&mut self, decl: Rc<StructDecl>, view: MemoryViewType) -> FnDecl {
+        let saved_state = mem::take(&mut self.fn_state);
+
+        let name = self.cx.fns.next_fn();
+        let ptr_type = ptr_type(&view);
+
+        let stmt_count = self
+            .rng
+            .gen_range(self.options.fn_min_stmts..=self.options.fn_max_stmts);
+
+        let mut function_scope = self.global_scope.clone();
+        function_scope.insert_readonly(PTR_PARAM.to_owned(), ptr_type.clone());
+
+        let (_, block) = self.with_scope(function_scope, |this| {
+            // The stores at the end must always be reached
+            this.fn_state.no_return = true;
+            let return_type = this.return_type.take();
+            let (scope, block) = this.gen_stmt_block(stmt_count);
+            this.return_type = return_type;
+
+            let prev_block = mem::replace(&mut this.current_block, block);
+
+            this.with_scope(scope, |this| {
+                // At least one member is always stored to
+                let first = this.rng.gen_index(decl.members.len());
+
+                for (i, member) in decl.members.iter().enumerate() {
+                    if i != first && !this.rng.gen_bool(0.5) {
+                        continue;
+                    }
+
+                    let deref = LhsExprNode {
+                        data_type: DataType::Ref(view.clone()),
+                        expr: LhsExpr::Deref(Box::new(LhsExprNode::name(
+                            PTR_PARAM.to_owned(),
+                            ptr_type.clone(),
+                        ))),
+                    };
+
+                    let lhs = LhsExprNode {
+                        data_type: DataType::Ref(view.clone_with_type(member.data_type.clone())),
+                        expr: LhsExpr::Postfix(Box::new(deref), Postfix::member(&member.name)),
+                    };
+
+                    let rhs = this.gen_expr(&member.data_type);
+                    this.current_block.push(
+                        AssignmentStatement::new(lhs.into(), AssignmentOp::Simple, rhs).into(),
+                    );
+                }
+            });
+
+            mem::replace(&mut this.current_block, prev_block)
+        });
+
+        self.fn_state = saved_state;
+
+        FnDecl {
+            attrs: self.gen_fn_diagnostic_attr().into_iter().collect(),
+            name,
+            inputs: vec![FnInput::new(PTR_PARAM, ptr_type)],
+            output: None,
+            body: block,
+        }
+    }
+}
+
+fn ptr_type(view: &MemoryViewType) -> DataType {
+    DataType::Ptr(view.clone())
+}
@@ -0,0 +1,119 @@
+//! Ladders of deeply nested ifs and blocks, for `--nesting-ladder`.
+//!
+//! These are meant to find stack overflows and recursion limits in parsers and code generators,
+//! so they are much deeper than anything that `--max-block-depth` would allow. Each level of the
+//! ladder updates a variable, so that taking the wrong branch at any level changes its value.
+
+use ast::types::{DataType, MemoryViewType, ScalarType};
+use ast::{
+    AssignmentLhs, AssignmentOp, AssignmentStatement, BinOp, BinOpExpr, Else, ExprNode,
+    IfStatement, Lit, Statement, StorageClass, VarDeclStatement, VarExpr,
+};
+
+use crate::rng::SliceExt;
+
+#[derive(Clone, Copy)]
+enum Level {
+    /// `{ ... }`
+    Block,
+    /// `if (c) { ... }`
+    If,
+    /// `if (c) { ... } else { mark }`
+    IfElse,
+    /// `if (c) { mark } else if ...`, or `else { ... }` if the next level isn't an if.
+    ElseIf,
+}
+
+impl Level {
+    const ALL: [Level; 4] = [Level::Block, Level::If, Level::IfElse, Level::ElseIf];
+}
+
+impl<'a> super::Generator<'a> {
+    /// Generates a ladder nested `depth` levels deep, preceded by the declaration of the variable
+    /// that it updates.
+    ///
+    /// The variable is added to the current scope, so that the statements which follow can read
+    /// it.
+    pub fn gen_nesting_ladder(&mut self, depth: u32) -> Vec<Statement> {
+        let name = self.scope.next_name();
+        let data_type = DataType::Scalar(ScalarType::U32);
+
+        // The levels have to be built from the inside out, but the conditions are generated from
+        // the outside in, so that the program reads in the same order as it was generated
+        let levels = (0..depth)
+            .map(|_| {
+                let level = *Level::ALL.choose(self.rng).unwrap();
+                let condition = match level {
+                    Level::Block => None,
+                    _ => Some(self.gen_expr(&DataType::Scalar(ScalarType::Bool))),
+                };
+                (level, condition)
+            })
+            .collect::<Vec<_>>();
+
+        let mark = |index: usize| -> Statement {
+            let value = BinOpExpr::new(
+                BinOp::Plus,
+                BinOpExpr::new(
+                    BinOp::Times,
+                    VarExpr::new(&name).into_node(data_type.clone()),
+                    Lit::U32(31),
+                ),
+                Lit::U32(index as u32 + 1),
+            );
+
+            AssignmentStatement::new(
+                AssignmentLhs::name(name.clone(), data_type.clone()),
+                AssignmentOp::Simple,
+                value,
+            )
+            .into()
+        };
+
+        let mut inner: Option<Statement> = None;
+
+        for (index, (level, condition)) in levels.into_iter().enumerate().rev() {
+            let mut body = vec![mark(index)];
+
+            let stmt: Statement = match (level, condition) {
+                (Level::Block, _) => {
+                    body.extend(inner);
+                    Statement::Compound(body)
+                }
+                (Level::If, Some(condition)) => {
+                    body.extend(inner);
+                    IfStatement::new(condition, body).into()
+                }
+                (Level::IfElse, Some(condition)) => {
+                    let else_ = Else::Else(vec![mark(depth as usize + index)]);
+                    body.extend(inner);
+                    IfStatement::new(condition, body).with_else(else_).into()
+                }
+                (Level::ElseIf, Some(condition)) => {
+                    let else_ = match inner {
+                        Some(Statement::If(stmt)) => Else::If(stmt),
+                        inner => Else::Else(inner.into_iter().collect()),
+                    };
+                    IfStatement::new(condition, body).with_else(else_).into()
+                }
+                (_, None) => unreachable!("if levels always have a condition"),
+            };
+
+            inner = Some(stmt);
+        }
+
+        let ref_type = DataType::Ref(MemoryViewType::new(
+            data_type.clone(),
+            StorageClass::Function,
+        ));
+
+        let init: ExprNode = Lit::U32(0).into();
+        let mut stmts = vec![VarDeclStatement::new(name.clone(), None, Some(init)).into()];
+        stmts.extend(inner);
+        stmts.extend(self.gen_checksum_update(&name, &ref_type));
+
+        self.scope.insert_mutable(name, ref_type);
+
+        stmts
+    }
+}
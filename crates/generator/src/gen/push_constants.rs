@@ -0,0 +1,61 @@
+//! Push constants for `--push-constants`.
+//!
+//! Push constants are a native-only extension in wgpu, and take a different path through the
+//! pipeline layout than uniform buffers, especially on Vulkan. The generated struct only has
+//! scalar and vector members, so that it always fits in the 128 bytes that every implementation
+//! supporting the extension must provide.
+
+use std::rc::Rc;
+
+use ast::types::{DataType, ScalarType};
+use ast::{GlobalVarDecl, StorageClass, StructDecl, StructMember, VarQualifier};
+
+use crate::rng::{RngExt, SliceExt};
+
+use super::structs::FIELD_NAMES;
+
+/// Maximum number of members of the push constants struct.
+///
+/// Each member takes at most 16 bytes, so this keeps the struct within 64 bytes.
+const MAX_MEMBERS: usize = 4;
+
+impl<'a> super::Generator<'a> {
+    /// Generates the `var<push_constant>` variable and its struct type, and adds the variable to
+    /// the global scope.
+    pub fn gen_push_constants(&mut self) -> (Rc<StructDecl>, GlobalVarDecl) {
+        let members = (0..self.rng.gen_range(1..=MAX_MEMBERS))
+            .map(|i| {
+                let scalar_type = *[ScalarType::I32, ScalarType::U32, ScalarType::F32]
+                    .choose(self.rng)
+                    .unwrap();
+
+                let data_type = if self.rng.gen_bool(0.5) {
+                    DataType::Scalar(scalar_type)
+                } else {
+                    DataType::Vector(self.rng.gen_range(2..=4), scalar_type)
+                };
+
+                StructMember::new(vec![], FIELD_NAMES[i], data_type)
+            })
+            .collect();
+
+        let decl = StructDecl::new("PushConstants", members);
+        let data_type = DataType::Struct(decl.clone());
+
+        self.global_scope
+            .insert_readonly("u_push".to_owned(), data_type.clone());
+
+        let var = GlobalVarDecl {
+            attrs: vec![],
+            qualifier: Some(VarQualifier {
+                storage_class: StorageClass::PushConstant,
+                access_mode: None,
+            }),
+            name: "u_push".to_owned(),
+            data_type,
+            initializer: None,
+        };
+
+        (decl, var)
+    }
+}
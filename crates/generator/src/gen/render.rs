@@ -0,0 +1,608 @@
+//! Vertex and fragment entry points for `--render`.
+//!
+//! The vertex entry point draws a triangle which covers the whole render target, and passes a
+//! random set of inter-stage variables to the fragment entry point. The variables have random
+//! locations and interpolation attributes, and the fragment entry point receives a subset of them
+//! through a struct, separate parameters or both. Linking the two stages into a render pipeline
+//! tests that the compilers match up their interfaces.
+//!
+//! The value written by the fragment entry point only depends on the integer variables (which are
+//! always flat) and the pixel position, so that it is the same on every implementation.
+//!
+//! With `--render-state`, the write mask and depth/stencil state of the pipeline are chosen here
+//! too. If there is a depth attachment, the fragment entry point writes a depth computed from the
+//! pixel position, so that the depth test passes for some pixels and fails for others.
+
+use std::mem;
+use std::rc::Rc;
+
+use ast::types::{DataType, ScalarType};
+use ast::{
+    BinOp, BinOpExpr, BuiltinValue, ExprNode, FnAttr, FnDecl, FnInput, FnInputAttr, FnOutput,
+    FnOutputAttr, Interpolation, InterpolationSampling, InterpolationType, LetDeclStatement, Lit,
+    Postfix, PostfixExpr, ReturnStatement, ShaderStage, Statement, StructDecl, StructMember,
+    StructMemberAttr, TypeConsExpr, VarExpr,
+};
+
+use reflection_types::{
+    CompareFunction, DepthStencilState, RenderState, StencilOperation, RENDER_TARGET_SIZE,
+};
+
+use crate::rng::{RngExt, SliceExt};
+
+use super::scope::Scope;
+
+/// Number of locations that inter-stage variables are chosen from.
+///
+/// This is the minimum number of inter-stage variables that every implementation has to support.
+const MAX_LOCATIONS: u32 = 16;
+
+/// Maximum number of inter-stage variables passed from the vertex to the fragment entry point.
+const MAX_VARYINGS: usize = 6;
+
+const VERTEX_ENTRY_POINT: &str = "vs_main";
+const FRAGMENT_ENTRY_POINT: &str = "fs_main";
+
+/// Values that the stencil reference and clear value are chosen from, including ones near the
+/// ends of the 8 bit range so that the wrapping operations can overflow.
+const STENCIL_VALUES: &[u32] = &[0, 1, 2, 254, 255];
+
+struct Varying {
+    location: u32,
+    data_type: DataType,
+    interpolation: Option<Interpolation>,
+}
+
+impl Varying {
+    fn is_integer(&self) -> bool {
+        is_integer(&self.data_type)
+    }
+}
+
+impl<'a> super::Generator<'a> {
+    /// Generates the vertex and fragment entry points, along with the structs used for their
+    /// inputs and outputs.
+    pub fn gen_render_entry_points(&mut self) -> (Vec<Rc<StructDecl>>, Vec<FnDecl>) {
+        if self.options.render_state {
+            self.render_state = Some(self.gen_render_state());
+        }
+
+        let writes_depth = matches!(
+            self.render_state,
+            Some(RenderState {
+                depth_stencil: Some(_),
+                ..
+            })
+        );
+
+        let mut locations = (0..MAX_LOCATIONS).collect::<Vec<_>>();
+        locations.shuffle(self.rng);
+        locations.truncate(self.rng.gen_range(1..=MAX_VARYINGS));
+
+        let varyings = locations
+            .into_iter()
+            .map(|location| self.gen_varying(location))
+            .collect::<Vec<_>>();
+
+        let (vertex_output, vertex) = self.gen_vertex_entry_point(&varyings);
+        let (mut structs, fragment) = self.gen_fragment_entry_point(&varyings, writes_depth);
+
+        structs.insert(0, vertex_output);
+
+        (structs, vec![vertex, fragment])
+    }
+
+    fn gen_render_state(&mut self) -> RenderState {
+        // Leaving every channel enabled is the common case, so it's chosen more often
+        let write_mask = if self.rng.gen_bool(0.5) {
+            0xf
+        } else {
+            self.rng.gen_range(0..0xf)
+        };
+
+        let depth_stencil = if self.rng.gen_bool(0.5) {
+            Some(self.gen_depth_stencil_state())
+        } else {
+            None
+        };
+
+        RenderState {
+            write_mask,
+            depth_stencil,
+        }
+    }
+
+    fn gen_depth_stencil_state(&mut self) -> DepthStencilState {
+        let pixels = RENDER_TARGET_SIZE * RENDER_TARGET_SIZE;
+
+        DepthStencilState {
+            depth_compare: *CompareFunction::ALL.choose(self.rng).unwrap(),
+            depth_write: self.rng.gen_bool(0.5),
+            // The fragment depths are multiples of 1 / pixels, so the clear value is too
+            depth_clear: self.rng.gen_range(0..=pixels) as f32 / pixels as f32,
+            stencil_compare: *CompareFunction::ALL.choose(self.rng).unwrap(),
+            stencil_fail_op: *StencilOperation::ALL.choose(self.rng).unwrap(),
+            depth_fail_op: *StencilOperation::ALL.choose(self.rng).unwrap(),
+            pass_op: *StencilOperation::ALL.choose(self.rng).unwrap(),
+            stencil_reference: *STENCIL_VALUES.choose(self.rng).unwrap(),
+            stencil_clear: *STENCIL_VALUES.choose(self.rng).unwrap(),
+        }
+    }
+
+    fn gen_varying(&mut self, location: u32) -> Varying {
+        let scalar_type = *[ScalarType::I32, ScalarType::U32, ScalarType::F32]
+            .choose(self.rng)
+            .unwrap();
+
+        let data_type = if self.rng.gen_bool(0.5) {
+            DataType::Scalar(scalar_type)
+        } else {
+            DataType::Vector(self.rng.gen_range(2..=4), scalar_type)
+        };
+
+        let interpolation = match scalar_type {
+            ScalarType::F32 => self.gen_float_interpolation(),
+            // Integers must always use flat interpolation
+            _ => Some(Interpolation {
+                kind: InterpolationType::Flat,
+                sampling: None,
+            }),
+        };
+
+        Varying {
+            location,
+            data_type,
+            interpolation,
+        }
+    }
+
+    fn gen_float_interpolation(&mut self) -> Option<Interpolation> {
+        let kind = match self.rng.gen_range(0..4) {
+            0 => return None,
+            1 => InterpolationType::Perspective,
+            2 => InterpolationType::Linear,
+            _ => InterpolationType::Flat,
+        };
+
+        let sampling = match kind {
+            InterpolationType::Flat => None,
+            _ => *[
+                None,
+                Some(InterpolationSampling::Center),
+                Some(InterpolationSampling::Centroid),
+                Some(InterpolationSampling::Sample),
+            ]
+            .choose(self.rng)
+            .unwrap(),
+        };
+
+        Some(Interpolation { kind, sampling })
+    }
+
+    /// Chooses a way of writing the same interpolation as `interpolation`, for the fragment input
+    /// which matches a vertex output.
+    ///
+    /// Leaving out the interpolation type or sampling is the same as giving the default, so the
+    /// two stages must still match when they are written differently.
+    fn gen_equivalent_interpolation(
+        &mut self,
+        interpolation: Option<Interpolation>,
+    ) -> Option<Interpolation> {
+        let (kind, sampling) = match interpolation {
+            None => (InterpolationType::Perspective, None),
+            Some(Interpolation {
+                kind: InterpolationType::Flat,
+                ..
+            }) => return interpolation,
+            Some(Interpolation { kind, sampling }) => (kind, sampling),
+        };
+
+        match sampling {
+            Some(InterpolationSampling::Centroid | InterpolationSampling::Sample) => {
+                Some(Interpolation { kind, sampling })
+            }
+            _ => {
+                let sampling = *[None, Some(InterpolationSampling::Center)]
+                    .choose(self.rng)
+                    .unwrap();
+
+                if kind == InterpolationType::Perspective
+                    && sampling.is_none()
+                    && self.rng.gen_bool(0.5)
+                {
+                    None
+                } else {
+                    Some(Interpolation { kind, sampling })
+                }
+            }
+        }
+    }
+
+    fn gen_member_attrs(
+        &mut self,
+        location: u32,
+        interpolation: Option<Interpolation>,
+    ) -> Vec<StructMemberAttr> {
+        let mut attrs = vec![StructMemberAttr::Location(location)];
+        attrs.extend(interpolation.map(StructMemberAttr::Interpolate));
+        // Attributes can be written in any order
+        attrs.shuffle(self.rng);
+        attrs
+    }
+
+    fn gen_param_attrs(
+        &mut self,
+        location: u32,
+        interpolation: Option<Interpolation>,
+    ) -> Vec<FnInputAttr> {
+        let mut attrs = vec![FnInputAttr::Location(location)];
+        attrs.extend(interpolation.map(FnInputAttr::Interpolate));
+        attrs.shuffle(self.rng);
+        attrs
+    }
+
+    fn gen_vertex_entry_point(&mut self, varyings: &[Varying]) -> (Rc<StructDecl>, FnDecl) {
+        let mut members = vec![StructMember::new(
+            vec![StructMemberAttr::Builtin(BuiltinValue::Position)],
+            "position",
+            DataType::Vector(4, ScalarType::F32),
+        )];
+
+        for varying in varyings {
+            let attrs = self.gen_member_attrs(varying.location, varying.interpolation);
+            members.push(StructMember::new(
+                attrs,
+                format!("v_{}", varying.location),
+                varying.data_type.clone(),
+            ));
+        }
+
+        members.shuffle(self.rng);
+
+        let decl = StructDecl::new("VertexOutput", members);
+        let output_type = DataType::Struct(decl.clone());
+
+        let mut scope = self.global_scope.clone();
+        scope.insert_readonly("vertex_index".to_owned(), ScalarType::U32.into());
+
+        let body = self.gen_stage_body(scope, vec![], |this| {
+            let args = decl
+                .members
+                .iter()
+                .map(|member| match member.name.as_str() {
+                    "position" => full_screen_position(),
+                    _ => this.gen_expr(&member.data_type),
+                })
+                .collect();
+
+            TypeConsExpr::new(output_type.clone(), args).into()
+        });
+
+        let func = FnDecl {
+            attrs: self.gen_stage_attrs(ShaderStage::Vertex),
+            name: VERTEX_ENTRY_POINT.to_owned(),
+            inputs: vec![FnInput {
+                attrs: vec![FnInputAttr::Builtin(BuiltinValue::VertexIndex)],
+                name: "vertex_index".to_owned(),
+                data_type: ScalarType::U32.into(),
+            }],
+            output: Some(FnOutput::new(output_type)),
+            body,
+        };
+
+        (decl, func)
+    }
+
+    fn gen_fragment_entry_point(
+        &mut self,
+        varyings: &[Varying],
+        writes_depth: bool,
+    ) -> (Vec<Rc<StructDecl>>, FnDecl) {
+        // The fragment entry point can leave out any of the vertex outputs
+        let mut received = varyings
+            .iter()
+            .filter(|_| self.rng.gen_bool(0.75))
+            .collect::<Vec<_>>();
+
+        received.shuffle(self.rng);
+
+        let mut params = vec![];
+        let mut members = vec![];
+        let mut scope = self.global_scope.clone();
+
+        // Expressions for the integer inputs, which are combined into the output
+        let mut integers = vec![];
+
+        for varying in received {
+            let interpolation = self.gen_equivalent_interpolation(varying.interpolation);
+            let name = format!("f_{}", varying.location);
+
+            if self.rng.gen_bool(0.5) {
+                let attrs = self.gen_param_attrs(varying.location, interpolation);
+                params.push(FnInput {
+                    attrs,
+                    name: name.clone(),
+                    data_type: varying.data_type.clone(),
+                });
+
+                // Only the integer inputs are added to the scope, since the values of the floats
+                // depend on how the implementation rasterizes the triangle
+                if varying.is_integer() {
+                    scope.insert_readonly(name.clone(), varying.data_type.clone());
+                    integers.push(VarExpr::new(name).into_node(varying.data_type.clone()));
+                }
+            } else {
+                let attrs = self.gen_member_attrs(varying.location, interpolation);
+                members.push(StructMember::new(attrs, name, varying.data_type.clone()));
+            }
+        }
+
+        // The pixel position is passed in as the centre of the pixel, so it can be converted to
+        // an integer exactly. The depth is computed from it, so it can't be left out then.
+        let position_type = DataType::Vector(4, ScalarType::F32);
+        let position_kind = if writes_depth {
+            self.rng.gen_range(1..3)
+        } else {
+            self.rng.gen_range(0..3)
+        };
+
+        let position = match position_kind {
+            0 => None,
+            1 => {
+                params.push(FnInput {
+                    attrs: vec![FnInputAttr::Builtin(BuiltinValue::Position)],
+                    name: "position".to_owned(),
+                    data_type: position_type.clone(),
+                });
+                Some(VarExpr::new("position").into_node(position_type))
+            }
+            _ => {
+                members.push(StructMember::new(
+                    vec![StructMemberAttr::Builtin(BuiltinValue::Position)],
+                    "position",
+                    position_type,
+                ));
+                None
+            }
+        };
+
+        let mut structs = vec![];
+        let mut prologue = vec![];
+        let mut position = position;
+
+        if !members.is_empty() {
+            members.shuffle(self.rng);
+
+            let decl = StructDecl::new("FragmentInput", members);
+            let input_type = DataType::Struct(decl.clone());
+            let input = || VarExpr::new("input").into_node(input_type.clone());
+
+            // The integer members are copied out of the struct, so that the rest of the body can't
+            // read the floats through it
+            for member in &decl.members {
+                let value = PostfixExpr::new(input(), Postfix::member(&member.name));
+                if member.name == "position" {
+                    position = Some(value.into());
+                } else if is_integer(&member.data_type) {
+                    let name = scope.next_name();
+                    prologue.push(LetDeclStatement::new(name.clone(), value).into());
+                    scope.insert_readonly(name.clone(), member.data_type.clone());
+                    integers.push(VarExpr::new(name).into_node(member.data_type.clone()));
+                }
+            }
+
+            params.push(FnInput::new("input", input_type));
+            params.shuffle(self.rng);
+            structs.push(decl);
+        }
+
+        let color_type = DataType::Vector(4, ScalarType::U32);
+
+        let output = if !writes_depth && self.rng.gen_bool(0.5) {
+            FnOutput {
+                attrs: vec![FnOutputAttr::Location(0)],
+                data_type: color_type.clone(),
+            }
+        } else {
+            let mut members = vec![StructMember::new(
+                vec![StructMemberAttr::Location(0)],
+                "color",
+                color_type.clone(),
+            )];
+
+            if writes_depth {
+                members.push(StructMember::new(
+                    vec![StructMemberAttr::Builtin(BuiltinValue::FragDepth)],
+                    "depth",
+                    ScalarType::F32.into(),
+                ));
+                members.shuffle(self.rng);
+            }
+
+            let decl = StructDecl::new("FragmentOutput", members);
+
+            structs.push(decl.clone());
+            FnOutput::new(DataType::Struct(decl))
+        };
+
+        let body = self.gen_stage_body(scope, prologue, |this| {
+            let mut components = integers.iter().flat_map(u32_components).collect::<Vec<_>>();
+            let depth = if writes_depth {
+                position.as_ref().map(pixel_depth)
+            } else {
+                None
+            };
+
+            if let Some(position) = position {
+                for axis in ["x", "y"] {
+                    let value = PostfixExpr::new(position.clone(), Postfix::member(axis));
+                    components
+                        .push(TypeConsExpr::new(ScalarType::U32.into(), vec![value.into()]).into());
+                }
+            }
+
+            let mut color = this.gen_expr(&color_type);
+
+            if let Some(hash) = components
+                .into_iter()
+                .reduce(|acc, it| BinOpExpr::new(BinOp::BitXOr, acc, it).into())
+            {
+                let hash = TypeConsExpr::new(color_type.clone(), vec![hash]);
+                color = BinOpExpr::new(BinOp::BitXOr, color, hash).into();
+            }
+
+            match &output.data_type {
+                DataType::Struct(decl) => {
+                    let args = decl
+                        .members
+                        .iter()
+                        .map(|member| match member.name.as_str() {
+                            "depth" => depth.clone().unwrap(),
+                            _ => color.clone(),
+                        })
+                        .collect();
+
+                    TypeConsExpr::new(output.data_type.clone(), args).into()
+                }
+                _ => color,
+            }
+        });
+
+        let func = FnDecl {
+            attrs: self.gen_stage_attrs(ShaderStage::Fragment),
+            name: FRAGMENT_ENTRY_POINT.to_owned(),
+            inputs: params,
+            output: Some(output),
+            body,
+        };
+
+        (structs, func)
+    }
+
+    fn gen_stage_attrs(&mut self, stage: ShaderStage) -> Vec<FnAttr> {
+        let mut attrs = vec![FnAttr::Stage(stage)];
+        attrs.extend(self.gen_fn_diagnostic_attr());
+        attrs.shuffle(self.rng);
+        attrs
+    }
+
+    /// Generates the body of an entry point from a block of random statements in `scope`,
+    /// followed by returning the value from `gen_output`.
+    fn gen_stage_body(
+        &mut self,
+        scope: Scope,
+        prologue: Vec<Statement>,
+        gen_output: impl FnOnce(&mut Self) -> ExprNode,
+    ) -> Vec<Statement> {
+        let saved_state = mem::take(&mut self.fn_state);
+
+        let stmt_count = self
+            .rng
+            .gen_range(self.options.fn_min_stmts..=self.options.fn_max_stmts);
+
+        let (_, block) = self.with_scope(scope, |this| {
+            // The output is always returned at the end
+            this.fn_state.no_return = true;
+            let return_type = this.return_type.take();
+            let (scope, block) = this.gen_stmt_block(stmt_count);
+            this.return_type = return_type;
+
+            let prev_block = mem::replace(&mut this.current_block, prologue);
+            this.current_block.extend(block);
+
+            this.with_scope(scope, |this| {
+                let output = gen_output(this);
+                this.current_block.push(ReturnStatement::new(output).into());
+            });
+
+            mem::replace(&mut this.current_block, prev_block)
+        });
+
+        self.fn_state = saved_state;
+
+        block
+    }
+}
+
+/// Returns the position of the vertex with index `vertex_index` in a triangle which covers the
+/// whole viewport, with vertices at (-1, -1), (3, -1) and (-1, 3).
+fn full_screen_position() -> ExprNode {
+    let vertex_index = || VarExpr::new("vertex_index").into_node(ScalarType::U32);
+
+    let coord = |bit: ExprNode| -> ExprNode {
+        let scaled = BinOpExpr::new(
+            BinOp::Minus,
+            BinOpExpr::new(
+                BinOp::Times,
+                TypeConsExpr::new(ScalarType::I32.into(), vec![bit]),
+                Lit::I32(4),
+            ),
+            Lit::I32(1),
+        );
+
+        TypeConsExpr::new(ScalarType::F32.into(), vec![scaled.into()]).into()
+    };
+
+    let x = coord(BinOpExpr::new(BinOp::BitAnd, vertex_index(), Lit::U32(1)).into());
+    let y = coord(BinOpExpr::new(BinOp::RShift, vertex_index(), Lit::U32(1)).into());
+
+    TypeConsExpr::new(
+        DataType::Vector(4, ScalarType::F32),
+        vec![x, y, Lit::F32(0.0).into(), Lit::F32(1.0).into()],
+    )
+    .into()
+}
+
+/// Returns a depth which is different for each pixel of the render target, computed from the
+/// pixel position as `f32(x + y * size) / f32(size * size)`.
+fn pixel_depth(position: &ExprNode) -> ExprNode {
+    let axis = |axis: &str| -> ExprNode {
+        let value = PostfixExpr::new(position.clone(), Postfix::member(axis));
+        TypeConsExpr::new(ScalarType::U32.into(), vec![value.into()]).into()
+    };
+
+    let index = BinOpExpr::new(
+        BinOp::Plus,
+        axis("x"),
+        BinOpExpr::new(BinOp::Times, axis("y"), Lit::U32(RENDER_TARGET_SIZE)),
+    );
+
+    BinOpExpr::new(
+        BinOp::Divide,
+        TypeConsExpr::new(ScalarType::F32.into(), vec![index.into()]),
+        Lit::F32((RENDER_TARGET_SIZE * RENDER_TARGET_SIZE) as f32),
+    )
+    .into()
+}
+
+fn is_integer(data_type: &DataType) -> bool {
+    !matches!(
+        data_type,
+        DataType::Scalar(ScalarType::F32) | DataType::Vector(_, ScalarType::F32)
+    )
+}
+
+/// Returns the components of an integer scalar or vector, converted to `u32`.
+fn u32_components(expr: &ExprNode) -> Vec<ExprNode> {
+    let to_u32 = |expr: ExprNode, ty: ScalarType| -> ExprNode {
+        match ty {
+            ScalarType::U32 => expr,
+            _ => TypeConsExpr::new(ScalarType::U32.into(), vec![expr]).into(),
+        }
+    };
+
+    match expr.data_type {
+        DataType::Scalar(ty) => vec![to_u32(expr.clone(), ty)],
+        DataType::Vector(n, ty) => ["x", "y", "z", "w"][..n as usize]
+            .iter()
+            .map(|component| {
+                let value = PostfixExpr::new(expr.clone(), Postfix::member(*component));
+                to_u32(value.into(), ty)
+            })
+            .collect(),
+        _ => unreachable!(
+            "expected an integer scalar or vector, got `{}`",
+            expr.data_type
+        ),
+    }
+}
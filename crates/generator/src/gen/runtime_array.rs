@@ -0,0 +1,163 @@
+use std::rc::Rc;
+
+use ast::types::{DataType, MemoryViewType};
+use ast::{
+    AccessMode, AssignmentOp, AssignmentStatement, BinOp, BinOpExpr, ExprNode, FnCallExpr,
+    ForLoopHeader, ForLoopInit, ForLoopStatement, ForLoopUpdate, LhsExpr, LhsExprNode, Lit,
+    Postfix, PostfixExpr, ScalarType, Statement, StorageClass, StructDecl, StructMember, UnOp,
+    UnOpExpr, VarDeclStatement, VarExpr,
+};
+
+use crate::rng::RngExt;
+
+use super::cx::SelectionFilter;
+
+/// Name of the runtime sized array member at the end of the storage buffer struct.
+const ARRAY_MEMBER: &str = "arr";
+
+impl<'a> super::Generator<'a> {
+    /// Randomly generates a runtime sized array member to add to the end of the storage buffer.
+    ///
+    /// The number of elements is decided by the harness, which allocates different buffer sizes
+    /// for different inputs.
+    pub fn gen_runtime_array_member(&mut self) -> Option<Rc<StructMember>> {
+        // Arrays of structs can't contain runtime sized arrays, so they are also disabled when the
+        // output buffer has an element for each invocation, or when it is written with a
+        // const-expression
+        if self.options.disable_runtime_arrays
+            || self.options.invocations > 1
+            || self.options.const_oracle
+            || !self.rng.gen_bool(0.5)
+        {
+            return None;
+        }
+
+        let element_type = self
+            .cx
+            .types
+            .select_with_filter(&mut self.rng, SelectionFilter::HostShareable);
+
+        Some(StructMember::new(
+            vec![],
+            ARRAY_MEMBER,
+            DataType::Array(Rc::new(element_type), None),
+        ))
+    }
+
+    /// Generates statements that write to each member of the storage buffer variable `name`.
+    ///
+    /// Structs containing runtime sized arrays can't be constructed or assigned as a whole, so
+    /// each fixed size member is assigned separately, and then every element of the array is
+    /// written in a loop bounded by `arrayLength`. A few more elements are written at random
+    /// indices, which may be out of bounds if the program isn't reconditioned.
+    pub fn gen_runtime_array_writes(
+        &mut self,
+        name: &str,
+        decl: &Rc<StructDecl>,
+    ) -> Vec<Statement> {
+        let mut stmts = vec![];
+
+        let buffer_view = MemoryViewType {
+            inner: Rc::new(DataType::Struct(decl.clone())),
+            storage_class: StorageClass::Storage,
+            access_mode: AccessMode::ReadWrite,
+        };
+
+        let member_lhs = |member: &StructMember| LhsExprNode {
+            data_type: DataType::Ref(buffer_view.clone_with_type(member.data_type.clone())),
+            expr: LhsExpr::Postfix(
+                Box::new(LhsExprNode::name(
+                    name.to_owned(),
+                    DataType::Ref(buffer_view.clone()),
+                )),
+                Postfix::member(&member.name),
+            ),
+        };
+
+        let (array_member, members) = decl.members.split_last().unwrap();
+
+        for member in members {
+            let rhs = self.gen_expr(&member.data_type);
+            stmts.push(
+                AssignmentStatement::new(member_lhs(member).into(), AssignmentOp::Simple, rhs)
+                    .into(),
+            );
+        }
+
+        let element_type = match &array_member.data_type {
+            DataType::Array(element_type, None) => element_type.as_ref().clone(),
+            ty => unreachable!("expected runtime sized array, got `{ty}`"),
+        };
+
+        let element_lhs = |index: ExprNode| LhsExprNode {
+            data_type: DataType::Ref(buffer_view.clone_with_type(element_type.clone())),
+            expr: LhsExpr::Postfix(Box::new(member_lhs(array_member)), Postfix::index(index)),
+        };
+
+        let array_len = FnCallExpr::new(
+            "arrayLength",
+            vec![UnOpExpr::new(
+                UnOp::AddressOf,
+                PostfixExpr::new(
+                    VarExpr::new(name).into_node(DataType::Ref(buffer_view.clone())),
+                    Postfix::member(ARRAY_MEMBER),
+                ),
+            )
+            .into()],
+        )
+        .into_node(ScalarType::U32);
+
+        let (_, stmt) = self.with_scope(self.scope.clone(), |this| {
+            let loop_var = this.scope.next_name();
+            let loop_var_type = DataType::Scalar(ScalarType::U32);
+            let loop_var_expr = || VarExpr::new(loop_var.clone()).into_node(loop_var_type.clone());
+
+            let header = ForLoopHeader {
+                init: Some(ForLoopInit::VarDecl(VarDeclStatement::new(
+                    loop_var.clone(),
+                    None,
+                    Some(Lit::U32(0).into()),
+                ))),
+                condition: Some(BinOpExpr::new(BinOp::Less, loop_var_expr(), array_len).into()),
+                update: Some(ForLoopUpdate::Assignment(AssignmentStatement::new(
+                    LhsExprNode::name(loop_var.clone(), loop_var_type.clone()).into(),
+                    AssignmentOp::Plus,
+                    Lit::U32(1),
+                ))),
+            };
+
+            // The loop variable is only read in the body, so that the loop always terminates
+            this.scope
+                .insert_readonly(loop_var.clone(), loop_var_type.clone());
+
+            let rhs = this.gen_expr(&element_type);
+            let body = vec![AssignmentStatement::new(
+                element_lhs(loop_var_expr()).into(),
+                AssignmentOp::Simple,
+                rhs,
+            )
+            .into()];
+
+            ForLoopStatement::new(header, body)
+        });
+
+        stmts.push(stmt.into());
+
+        for _ in 0..self.rng.gen_range(0..=2) {
+            let index_type = if self.rng.gen_bool(0.5) {
+                ScalarType::I32
+            } else {
+                ScalarType::U32
+            };
+
+            let index = self.gen_expr(&DataType::Scalar(index_type));
+            let rhs = self.gen_expr(&element_type);
+            stmts.push(
+                AssignmentStatement::new(element_lhs(index).into(), AssignmentOp::Simple, rhs)
+                    .into(),
+            );
+        }
+
+        stmts
+    }
+}
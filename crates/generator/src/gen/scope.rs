@@ -1,10 +1,12 @@
 use std::iter;
 
 use ast::types::{DataType, MemoryViewType};
-use rand::prelude::IteratorRandom;
-use rand::Rng;
+use ast::{ExprNode, StorageClass};
+use rand::RngCore;
 use rpds::{HashTrieMap, Vector};
 
+use crate::rng::{RngExt, SliceExt};
+
 use super::utils;
 
 #[derive(Clone, Debug)]
@@ -13,6 +15,7 @@ pub struct Scope {
     symbols: HashTrieMap<DataType, Vec<(String, DataType)>>,
     mutables: Vector<(String, DataType)>,
     references: Vector<(String, MemoryViewType)>,
+    exprs: HashTrieMap<DataType, Vector<ExprNode>>,
 }
 
 impl Scope {
@@ -22,6 +25,7 @@ impl Scope {
             symbols: HashTrieMap::new(),
             mutables: Vector::new(),
             references: Vector::new(),
+            exprs: HashTrieMap::new(),
         }
     }
 
@@ -37,22 +41,69 @@ impl Scope {
         self.symbols.get(ty).map(Vec::as_slice).unwrap_or(&[])
     }
 
-    pub fn choose_mutable(&self, rng: &mut impl Rng) -> (&String, &DataType) {
+    pub fn choose_mutable(&self, rng: &mut impl RngCore) -> (&String, &DataType) {
         self.mutables
-            .iter()
-            .choose(rng)
+            .get(rng.gen_index(self.mutables.len()))
             .map(|(n, t)| (n, t))
             .unwrap()
     }
 
-    pub fn choose_reference(&self, rng: &mut impl Rng) -> (&String, &MemoryViewType) {
+    /// Chooses a mutable variable with probability proportional to the weight of its address
+    /// space, or uniformly if they all have a weight of 0.
+    pub fn choose_mutable_weighted(
+        &self,
+        rng: &mut impl RngCore,
+        weight: impl Fn(StorageClass) -> u32,
+    ) -> (&String, &DataType) {
+        let mutables = self.mutables.iter().collect::<Vec<_>>();
+        let space = |data_type: &DataType| match data_type {
+            DataType::Ref(view) => view.storage_class,
+            _ => StorageClass::Function,
+        };
+
+        mutables
+            .choose_weighted(rng, |(_, data_type)| weight(space(data_type)))
+            .or_else(|| mutables.choose(rng))
+            .map(|(n, t)| (n, t))
+            .unwrap()
+    }
+
+    pub fn choose_reference(&self, rng: &mut impl RngCore) -> (&String, &MemoryViewType) {
         self.references
-            .iter()
-            .choose(rng)
+            .get(rng.gen_index(self.references.len()))
             .map(|(n, t)| (n, t))
             .unwrap()
     }
 
+    /// Returns the mutable variables in function scope which have a struct type.
+    pub fn struct_vars(&self) -> Vec<(&String, &MemoryViewType)> {
+        self.references
+            .iter()
+            .filter(|(_, view)| {
+                view.storage_class == StorageClass::Function
+                    && matches!(view.inner.as_ref(), DataType::Struct(_))
+            })
+            .map(|(n, t)| (n, t))
+            .collect()
+    }
+
+    /// Returns the expressions of the given type which were recorded in this scope, or in one of
+    /// its parents.
+    pub fn exprs_of_type(&self, ty: &DataType) -> Vec<&ExprNode> {
+        self.exprs
+            .get(ty)
+            .map(|exprs| exprs.iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Records an expression which can be duplicated anywhere in this scope.
+    pub fn insert_expr(&mut self, expr: ExprNode) {
+        let key = expr.data_type.clone();
+        let mut exprs = self.exprs.get(&key).cloned().unwrap_or_else(Vector::new);
+        exprs.push_back_mut(expr);
+        self.exprs.insert_mut(key, exprs);
+    }
+
     pub fn insert_readonly(&mut self, name: String, data_type: DataType) {
         self.insert_symbol(&name, &data_type);
     }
@@ -60,8 +111,11 @@ impl Scope {
     pub fn insert_mutable(&mut self, name: String, data_type: DataType) {
         self.insert_symbol(&name, &data_type);
         if let DataType::Ref(mem_view) = &data_type {
-            self.references
-                .push_back_mut((name.clone(), mem_view.clone()));
+            // References are passed to functions as pointers, which can't point to storage buffers
+            if mem_view.storage_class != StorageClass::Storage {
+                self.references
+                    .push_back_mut((name.clone(), mem_view.clone()));
+            }
         }
         self.mutables.push_back_mut((name, data_type));
     }
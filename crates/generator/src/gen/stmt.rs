@@ -8,8 +8,8 @@ use ast::{
     LhsExprNode, Lit, LoopStatement, ReturnStatement, Statement, StorageClass, SwitchCase,
     SwitchStatement, UnOp, UnOpExpr, VarDeclStatement, VarExpr,
 };
-use rand::prelude::SliceRandom;
-use rand::Rng;
+
+use crate::rng::{RngExt, SliceExt};
 
 use super::scope::Scope;
 use super::utils::is_terminal_stmt;
@@ -27,15 +27,22 @@ enum StatementType {
     ForLoop,
     Break,
     Continue,
+    ConstAssert,
+    MethodCall,
+    AccumulatorLoop,
 }
 
 impl<'a> super::Generator<'a> {
     pub fn gen_stmt(&mut self) -> Statement {
-        let mut allowed = vec![
-            StatementType::LetDecl,
-            StatementType::VarDecl,
-            StatementType::Return,
-        ];
+        let mut allowed = vec![StatementType::LetDecl];
+
+        if self.can_gen_local_var() {
+            allowed.push(StatementType::VarDecl);
+        }
+
+        if !self.fn_state.no_return {
+            allowed.push(StatementType::Return);
+        }
 
         if self.fn_state.is_loop {
             allowed.push(StatementType::Break);
@@ -46,6 +53,14 @@ impl<'a> super::Generator<'a> {
             allowed.push(StatementType::Assignment);
         }
 
+        if !self.options.disable_const_assert {
+            allowed.push(StatementType::ConstAssert);
+        }
+
+        if self.can_gen_method_call() {
+            allowed.push(StatementType::MethodCall);
+        }
+
         if self.fn_state.block_depth < self.options.max_block_depth {
             allowed.extend_from_slice(&[
                 // StatementType::Compound,
@@ -54,23 +69,38 @@ impl<'a> super::Generator<'a> {
                 StatementType::Switch,
                 StatementType::ForLoop,
             ]);
+
+            if self.options.loop_accumulators {
+                allowed.push(StatementType::AccumulatorLoop);
+            }
         }
 
-        let weights = |t: &StatementType| match t {
-            StatementType::LetDecl => 10,
-            StatementType::VarDecl => 10,
-            StatementType::Assignment => 10,
+        let weights = self.options.stmt_weights;
+        let weight = |t: &StatementType| match t {
+            StatementType::LetDecl => weights.let_decl,
+            StatementType::VarDecl => weights.var_decl,
+            StatementType::Assignment => weights.assignment,
             // StatementType::Compound => 1,
-            StatementType::If => 5,
-            StatementType::Return => 1,
-            StatementType::Loop => 5,
-            StatementType::Switch => 5,
-            StatementType::ForLoop => 5,
-            StatementType::Break => 5,
-            StatementType::Continue => 5,
+            StatementType::If => weights.if_,
+            StatementType::Return => weights.return_,
+            StatementType::Loop => weights.loop_,
+            StatementType::Switch => weights.switch,
+            StatementType::ForLoop => weights.for_loop,
+            StatementType::Break => weights.break_,
+            StatementType::Continue => weights.continue_,
+            StatementType::ConstAssert => weights.const_assert,
+            StatementType::MethodCall => weights.method,
+            StatementType::AccumulatorLoop => weights.accumulate,
         };
 
-        match allowed.choose_weighted(self.rng, weights).unwrap() {
+        // If all the allowed statements have been given a weight of 0, fall back to choosing one
+        // uniformly rather than failing
+        let choice = allowed
+            .choose_weighted(self.rng, weight)
+            .or_else(|| allowed.choose(self.rng))
+            .unwrap();
+
+        match choice {
             StatementType::LetDecl => self.gen_let_stmt(),
             StatementType::VarDecl => self.gen_var_stmt(),
             StatementType::Assignment => self.gen_assignment_stmt().into(),
@@ -82,42 +112,41 @@ impl<'a> super::Generator<'a> {
             StatementType::ForLoop => self.gen_for_stmt(),
             StatementType::Break => Statement::Break,
             StatementType::Continue => Statement::Continue,
+            StatementType::ConstAssert => self.gen_const_assert().into(),
+            StatementType::MethodCall => self.gen_method_call_stmt(),
+            StatementType::AccumulatorLoop => self.gen_accumulator_loop(),
         }
     }
 
     fn gen_let_stmt(&mut self) -> Statement {
         if self.options.enable_pointers && self.scope.has_mutables() && self.rng.gen_bool(0.2) {
-            let (ident, ty) = self.scope.choose_mutable(self.rng);
-            let initializer =
-                UnOpExpr::new(UnOp::AddressOf, VarExpr::new(ident).into_node(ty.clone()));
+            let (ident, ty) = self.choose_mutable();
+            let initializer = UnOpExpr::new(UnOp::AddressOf, VarExpr::new(ident).into_node(ty));
             LetDeclStatement::new(self.scope.next_name(), initializer).into()
         } else {
-            let ty = self.cx.types.select(self.rng);
+            let ty = self.cx.types.select(&mut self.rng);
             LetDeclStatement::new(self.scope.next_name(), self.gen_expr(&ty)).into()
         }
     }
 
     fn gen_var_stmt(&mut self) -> Statement {
-        let ty = self.cx.types.select(self.rng);
+        let ty = self.cx.types.select(&mut self.rng);
         VarDeclStatement::new(self.scope.next_name(), None, Some(self.gen_expr(&ty))).into()
     }
 
     fn gen_assignment_stmt(&mut self) -> AssignmentStatement {
-        let (name, data_type) = self.scope.choose_mutable(self.rng);
+        let (name, data_type) = self.choose_mutable();
 
-        let data_type = data_type.clone();
         let lhs = match &data_type {
             DataType::Vector(n, ty) if self.rng.gen_bool(0.7) => {
                 let accessor =
-                    super::utils::gen_vector_accessor(self.rng, *n, &DataType::Scalar(*ty));
-                LhsExprNode::member(name.clone(), data_type, accessor)
+                    super::utils::gen_vector_accessor(&mut self.rng, *n, &DataType::Scalar(*ty));
+                LhsExprNode::member(name, data_type, accessor)
+            }
+            DataType::Array(_, _) => {
+                LhsExprNode::array_index(name, data_type, self.gen_expr(&ScalarType::U32.into()))
             }
-            DataType::Array(_, _) => LhsExprNode::array_index(
-                name.clone(),
-                data_type,
-                self.gen_expr(&ScalarType::U32.into()),
-            ),
-            _ => LhsExprNode::name(name.clone(), data_type),
+            _ => LhsExprNode::name(name, data_type),
         };
 
         let rhs = self.gen_expr(lhs.data_type.dereference());
@@ -325,16 +354,24 @@ impl<'a> super::Generator<'a> {
 
             for _ in 0..max_count {
                 let stmt = this.gen_stmt();
+                let mut checksum_update = None;
 
                 // If we generated a variable declaration, track it in the environment
                 if let Statement::LetDecl(stmt) = &stmt {
                     this.scope
                         .insert_readonly(stmt.ident.clone(), stmt.initializer.data_type.clone());
+                    checksum_update =
+                        this.gen_checksum_update(&stmt.ident, &stmt.initializer.data_type);
                 } else if let Statement::VarDecl(stmt) = &stmt {
                     let mem_view =
                         MemoryViewType::new(stmt.inferred_type().clone(), StorageClass::Function);
                     let data_type = DataType::Ref(mem_view);
+                    checksum_update = this.gen_checksum_update(&stmt.ident, &data_type);
                     this.scope.insert_mutable(stmt.ident.clone(), data_type);
+                } else if let Statement::Assignment(stmt) = &stmt {
+                    checksum_update = this.gen_checksum_assignment_update(stmt);
+                } else if let Statement::FnCall(stmt) = &stmt {
+                    checksum_update = this.gen_checksum_call_update(stmt);
                 } else if is_terminal_stmt(&stmt) {
                     // Return/break/continue/fallthrough must be the last statement in the block
                     this.current_block.push(stmt);
@@ -342,6 +379,7 @@ impl<'a> super::Generator<'a> {
                 }
 
                 this.current_block.push(stmt);
+                this.current_block.extend(checksum_update);
             }
 
             this.fn_state.block_depth -= 1;
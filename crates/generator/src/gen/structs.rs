@@ -2,16 +2,17 @@ use std::rc::Rc;
 
 use ast::types::DataType;
 use ast::{StructDecl, StructMember, StructMemberAttr};
-use rand::Rng;
+
+use crate::rng::RngExt;
 
 use super::cx::SelectionFilter;
 
-const FIELD_NAMES: &[&str] = &["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"];
+pub const FIELD_NAMES: &[&str] = &["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"];
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum StructKind {
     Default,
-    HostShareable,
+    StorageBuffer,
     UniformBuffer,
 }
 
@@ -21,13 +22,20 @@ impl<'a> super::Generator<'a> {
     }
 
     pub fn gen_struct_with(&mut self, name: String, kind: StructKind) -> Rc<StructDecl> {
+        // Everything is combined into the checksum, so that's the only output
+        if kind == StructKind::StorageBuffer && self.options.checksum {
+            return StructDecl::new(name, self.gen_checksum_member().into_iter().collect());
+        }
+
         let member_count = self
             .rng
             .gen_range(self.options.min_struct_members..=self.options.max_struct_members);
 
         let filter = match kind {
             StructKind::Default => SelectionFilter::Any,
-            StructKind::HostShareable => SelectionFilter::HostShareable,
+            // Only integers can be used in const-expressions whose values are tracked
+            StructKind::StorageBuffer if self.options.const_oracle => SelectionFilter::Uniform,
+            StructKind::StorageBuffer => SelectionFilter::HostShareable,
             StructKind::UniformBuffer => SelectionFilter::Uniform,
         };
 
@@ -36,12 +44,12 @@ impl<'a> super::Generator<'a> {
                 StructMember::new(
                     vec![],
                     FIELD_NAMES[i as usize].to_owned(),
-                    self.cx.types.select_with_filter(self.rng, filter),
+                    self.cx.types.select_with_filter(&mut self.rng, filter),
                 )
             })
             .collect::<Vec<_>>();
 
-        if matches!(kind, StructKind::HostShareable | StructKind::UniformBuffer) {
+        if matches!(kind, StructKind::StorageBuffer | StructKind::UniformBuffer) {
             for member in &mut members {
                 if let DataType::Struct(_) = member.data_type {
                     Rc::get_mut(member)
@@ -52,6 +60,11 @@ impl<'a> super::Generator<'a> {
             }
         }
 
+        if kind == StructKind::StorageBuffer {
+            members.extend(self.gen_checksum_member());
+            members.extend(self.gen_runtime_array_member());
+        }
+
         StructDecl::new(name, members)
     }
 }
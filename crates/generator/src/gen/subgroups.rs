@@ -0,0 +1,287 @@
+//! Subgroup operations for `--subgroups`.
+//!
+//! The size of a subgroup and the way that invocations are assigned to them vary between devices,
+//! so the result of a subgroup operation is only used once it has been reduced over the whole
+//! workgroup. Each reduction has the following form, where the elected invocation of each
+//! subgroup contributes its subgroup's result and every other invocation contributes the identity
+//! of the operator, so that the final value is the same whatever the subgroup size is:
+//!
+//! ```wgsl
+//! w_subgroups[local_index] = select(0u, subgroupAdd(v), subgroupElect());
+//! workgroupBarrier();
+//! var var_0 = 0u;
+//! for (var var_1 = 0u; var_1 < 64u; var_1 += 1u) {
+//!     var_0 = var_0 + w_subgroups[var_1];
+//! }
+//! ```
+//!
+//! Subgroup operations must be called in uniform control flow, so the reductions are only
+//! generated at the top level of the entry point, which can't return early in this mode. Only
+//! integer operators are used, since floating point reductions depend on the order that values
+//! are combined in.
+
+use std::rc::Rc;
+
+use ast::types::{DataType, MemoryViewType, ScalarType};
+use ast::{
+    AssignmentLhs, AssignmentOp, AssignmentStatement, BinOp, BinOpExpr, ExprNode, FnCallExpr,
+    FnCallStatement, ForLoopHeader, ForLoopInit, ForLoopStatement, ForLoopUpdate, GlobalVarDecl,
+    Lit, Postfix, PostfixExpr, Statement, StorageClass, TypeConsExpr, VarDeclStatement, VarExpr,
+    VarQualifier,
+};
+
+use crate::rng::{RngExt, SliceExt};
+
+use super::invocations::LOCAL_INDEX;
+
+/// Name of the extension which provides the subgroup builtins.
+const EXTENSION: &str = "subgroups";
+
+/// Name of the workgroup variable that each invocation's contribution to a reduction is written
+/// to.
+const REDUCTION_VAR: &str = "w_subgroups";
+
+/// Largest subgroup size allowed by WebGPU.
+///
+/// If the number of invocations is a multiple of this, every subgroup is full, so the first few
+/// invocations of each one are known to be active.
+const MAX_SUBGROUP_SIZE: u32 = 128;
+
+#[derive(Clone, Copy)]
+enum Reduction {
+    Add,
+    And,
+    Max,
+    Min,
+    Or,
+    Xor,
+    /// Number of invocations for which a condition is true, counted with `subgroupBallot`.
+    Ballot,
+}
+
+impl Reduction {
+    const ALL: [Reduction; 7] = [
+        Reduction::Add,
+        Reduction::And,
+        Reduction::Max,
+        Reduction::Min,
+        Reduction::Or,
+        Reduction::Xor,
+        Reduction::Ballot,
+    ];
+
+    fn identity(self) -> u32 {
+        match self {
+            Reduction::And | Reduction::Min => u32::MAX,
+            _ => 0,
+        }
+    }
+
+    /// Returns an expression that combines two partial results.
+    fn combine(self, a: ExprNode, b: ExprNode) -> ExprNode {
+        let op = match self {
+            Reduction::Add | Reduction::Ballot => BinOp::Plus,
+            Reduction::And => BinOp::BitAnd,
+            Reduction::Or => BinOp::BitOr,
+            Reduction::Xor => BinOp::BitXOr,
+            Reduction::Max => {
+                return FnCallExpr::new("max", vec![a, b]).into_node(ScalarType::U32);
+            }
+            Reduction::Min => {
+                return FnCallExpr::new("min", vec![a, b]).into_node(ScalarType::U32);
+            }
+        };
+
+        BinOpExpr::new(op, a, b).into()
+    }
+}
+
+impl<'a> super::Generator<'a> {
+    /// Returns the extensions to enable at the top of the module.
+    pub fn gen_enables(&self) -> Vec<String> {
+        if self.options.subgroups {
+            vec![EXTENSION.to_owned()]
+        } else {
+            vec![]
+        }
+    }
+
+    /// Generates the workgroup variable that reductions are combined through, if enabled.
+    ///
+    /// It isn't added to the global scope, so that only the reductions access it.
+    pub fn gen_subgroup_var(&self) -> Option<GlobalVarDecl> {
+        if !self.options.subgroups {
+            return None;
+        }
+
+        Some(GlobalVarDecl {
+            attrs: vec![],
+            qualifier: Some(VarQualifier {
+                storage_class: StorageClass::WorkGroup,
+                access_mode: None,
+            }),
+            name: REDUCTION_VAR.to_owned(),
+            data_type: self.reduction_var_type(),
+            initializer: None,
+        })
+    }
+
+    fn reduction_var_type(&self) -> DataType {
+        DataType::Array(
+            Rc::new(ScalarType::U32.into()),
+            Some(self.options.invocations.max(1)),
+        )
+    }
+
+    /// Generates some reductions over the workgroup, and adds the variables holding their results
+    /// to the current scope.
+    pub fn gen_subgroup_reductions(&mut self) -> Vec<Statement> {
+        let mut stmts = vec![];
+
+        for i in 0..self.rng.gen_range(1..=3) {
+            // Every invocation has to have read the previous reduction's contributions before
+            // they are overwritten
+            if i > 0 {
+                stmts.push(FnCallStatement::new("workgroupBarrier".to_owned(), vec![]).into());
+            }
+
+            stmts.extend(self.gen_subgroup_reduction());
+        }
+
+        stmts
+    }
+
+    fn gen_subgroup_reduction(&mut self) -> Vec<Statement> {
+        let u32_type = DataType::Scalar(ScalarType::U32);
+        let reduction = *Reduction::ALL.choose(self.rng).unwrap();
+        let identity = || -> ExprNode { Lit::U32(reduction.identity()).into() };
+
+        let value = match reduction {
+            Reduction::Add => self.gen_subgroup_call("subgroupAdd"),
+            Reduction::And => self.gen_subgroup_call("subgroupAnd"),
+            Reduction::Max => self.gen_subgroup_call("subgroupMax"),
+            Reduction::Min => self.gen_subgroup_call("subgroupMin"),
+            Reduction::Or => self.gen_subgroup_call("subgroupOr"),
+            Reduction::Xor => self.gen_subgroup_call("subgroupXor"),
+            Reduction::Ballot => {
+                let condition = self.gen_expr(&ScalarType::Bool.into());
+                let ballot = FnCallExpr::new("subgroupBallot", vec![condition])
+                    .into_node(DataType::Vector(4, ScalarType::U32));
+                let counts = FnCallExpr::new("countOneBits", vec![ballot])
+                    .into_node(DataType::Vector(4, ScalarType::U32));
+                let ones = TypeConsExpr::new(
+                    DataType::Vector(4, ScalarType::U32),
+                    vec![Lit::U32(1).into()],
+                );
+                FnCallExpr::new("dot", vec![counts, ones.into()]).into_node(u32_type.clone())
+            }
+        };
+
+        let elected = FnCallExpr::new("subgroupElect", vec![]).into_node(ScalarType::Bool);
+        let contribution =
+            FnCallExpr::new("select", vec![identity(), value, elected]).into_node(u32_type.clone());
+
+        let reduction_type = DataType::Ref(MemoryViewType::new(
+            self.reduction_var_type(),
+            StorageClass::WorkGroup,
+        ));
+
+        let mut stmts: Vec<Statement> = vec![
+            AssignmentStatement::new(
+                AssignmentLhs::array_index(
+                    REDUCTION_VAR,
+                    reduction_type.clone(),
+                    VarExpr::new(LOCAL_INDEX).into_node(u32_type.clone()),
+                ),
+                AssignmentOp::Simple,
+                contribution,
+            )
+            .into(),
+            FnCallStatement::new("workgroupBarrier".to_owned(), vec![]).into(),
+        ];
+
+        let name = self.scope.next_name();
+        let counter = self.scope.next_name();
+        let var = || VarExpr::new(&name).into_node(u32_type.clone());
+
+        let header = ForLoopHeader {
+            init: Some(ForLoopInit::VarDecl(VarDeclStatement::new(
+                counter.clone(),
+                None,
+                Some(Lit::U32(0).into()),
+            ))),
+            condition: Some(
+                BinOpExpr::new(
+                    BinOp::Less,
+                    VarExpr::new(&counter).into_node(u32_type.clone()),
+                    Lit::U32(self.options.invocations.max(1)),
+                )
+                .into(),
+            ),
+            update: Some(ForLoopUpdate::Assignment(AssignmentStatement::new(
+                AssignmentLhs::name(counter.clone(), u32_type.clone()),
+                AssignmentOp::Plus,
+                Lit::U32(1),
+            ))),
+        };
+
+        let element = PostfixExpr::new(
+            VarExpr::new(REDUCTION_VAR).into_node(reduction_type),
+            Postfix::index(VarExpr::new(&counter).into_node(u32_type.clone())),
+        );
+
+        let body = vec![AssignmentStatement::new(
+            AssignmentLhs::name(name.clone(), u32_type.clone()),
+            AssignmentOp::Simple,
+            reduction.combine(var(), element.into()),
+        )
+        .into()];
+
+        stmts.push(VarDeclStatement::new(name.clone(), None, Some(identity())).into());
+        stmts.push(ForLoopStatement::new(header, body).into());
+
+        // The result is the same in every invocation, so broadcasting it leaves it unchanged
+        if self.rng.gen_bool(0.5) {
+            let broadcast = self.gen_subgroup_broadcast(var());
+            stmts.push(
+                AssignmentStatement::new(
+                    AssignmentLhs::name(name.clone(), u32_type.clone()),
+                    AssignmentOp::Simple,
+                    broadcast,
+                )
+                .into(),
+            );
+        }
+
+        let ref_type = DataType::Ref(MemoryViewType::new(u32_type, StorageClass::Function));
+
+        stmts.extend(self.gen_checksum_update(&name, &ref_type));
+
+        self.scope.insert_mutable(name, ref_type);
+
+        stmts
+    }
+
+    /// Generates a call to a subgroup reduction builtin, with an argument which may be different
+    /// in each invocation.
+    fn gen_subgroup_call(&mut self, ident: &str) -> ExprNode {
+        let arg = self.gen_expr(&ScalarType::U32.into());
+        FnCallExpr::new(ident, vec![arg]).into_node(ScalarType::U32)
+    }
+
+    /// Generates a broadcast of a value which is the same in every invocation.
+    ///
+    /// `subgroupBroadcast` is only used if the invocation it reads from must be active, since the
+    /// result is indeterminate otherwise.
+    fn gen_subgroup_broadcast(&mut self, value: ExprNode) -> ExprNode {
+        let full_subgroups = self.options.invocations.max(1) % MAX_SUBGROUP_SIZE == 0;
+
+        if full_subgroups && self.rng.gen_bool(0.5) {
+            // Every subgroup has at least 4 invocations
+            let id = Lit::U32(self.rng.gen_range(0..4));
+            FnCallExpr::new("subgroupBroadcast", vec![value, id.into()]).into_node(ScalarType::U32)
+        } else {
+            FnCallExpr::new("subgroupBroadcastFirst", vec![value]).into_node(ScalarType::U32)
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//! Filling holes in a WGSL skeleton with generated code.
+//!
+//! Holes are written as comments, e.g. `/* HOLE: expr<i32> */` for an expression of type `i32`,
+//! or `/* HOLE: stmt */` for a block of statements. Variables from the skeleton that the generated
+//! code may read are listed after `with`, e.g. `/* HOLE: expr<u32> with x: u32, v: vec2<u32> */`.
+//!
+//! Each hole is generated as the body of a function taking the listed variables as parameters, so
+//! that the holes can be reconditioned along with the rest of the generated code, and then
+//! extracted again when the template is filled in. Any functions and structs that the holes use
+//! are added to the end of the skeleton.
+
+use std::path::Path;
+
+use ast::types::{DataType, ScalarType};
+use ast::writer::Writer;
+use ast::{FnDecl, FnInput, FnOutput, Module, ReturnStatement, Statement};
+
+use crate::gen::builtins::Builtins;
+use crate::gen::cx::Context;
+use crate::gen::scope::Scope;
+use crate::rng::RngExt;
+
+const HOLE_START: &str = "/* HOLE:";
+const HOLE_END: &str = "*/";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HoleKind {
+    Expr(DataType),
+    Stmts,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hole {
+    pub kind: HoleKind,
+    /// Variables from the skeleton that are in scope at the hole.
+    pub vars: Vec<(String, DataType)>,
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Text(String),
+    Hole(Hole),
+}
+
+#[derive(Clone, Debug)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Template, String> {
+        let mut parts = vec![];
+        let mut rest = source;
+
+        while let Some(start) = rest.find(HOLE_START) {
+            let spec = &rest[start + HOLE_START.len()..];
+            let end = spec
+                .find(HOLE_END)
+                .ok_or_else(|| "unterminated hole".to_owned())?;
+
+            parts.push(Part::Text(rest[..start].to_owned()));
+            parts.push(Part::Hole(parse_hole(spec[..end].trim())?));
+
+            rest = &spec[end + HOLE_END.len()..];
+        }
+
+        parts.push(Part::Text(rest.to_owned()));
+
+        Ok(Template { parts })
+    }
+
+    /// Reads a template from the file at `path`.
+    pub fn read(path: &Path) -> Result<Template, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        Template::parse(&source).map_err(|e| format!("invalid template in {}: {e}", path.display()))
+    }
+
+    pub fn holes(&self) -> impl Iterator<Item = &Hole> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Hole(hole) => Some(hole),
+            Part::Text(_) => None,
+        })
+    }
+
+    /// Fills in the holes with their code from `module`, which must have been generated by
+    /// [`gen_template_module`](super::Generator::gen_template_module) (and may since have been
+    /// reconditioned).
+    pub fn fill(&self, module: Module) -> String {
+        let (mut holes, functions): (Vec<_>, Vec<_>) = module
+            .functions
+            .into_iter()
+            .partition(|it| it.name.starts_with(HOLE_FN_PREFIX));
+
+        let writer = Writer::default();
+        let mut output = String::new();
+        let mut index = 0;
+
+        for part in &self.parts {
+            let hole = match part {
+                Part::Text(text) => {
+                    output.push_str(text);
+                    continue;
+                }
+                Part::Hole(hole) => hole,
+            };
+
+            let name = hole_fn_name(index);
+            let decl = holes.swap_remove(holes.iter().position(|it| it.name == name).unwrap());
+            index += 1;
+
+            match &hole.kind {
+                HoleKind::Expr(_) => match decl.body.last() {
+                    Some(Statement::Return(ReturnStatement { value: Some(value) })) => {
+                        output.push_str(&format!("({value})"));
+                    }
+                    _ => unreachable!("expected hole function to return its expression"),
+                },
+                HoleKind::Stmts => writer
+                    .write_stmt(&mut output, &Statement::Compound(decl.body))
+                    .unwrap(),
+            }
+        }
+
+        output.push('\n');
+
+        writer
+            .write_module(
+                &mut output,
+                &Module {
+                    functions,
+                    ..module
+                },
+            )
+            .unwrap();
+
+        output
+    }
+}
+
+fn parse_hole(spec: &str) -> Result<Hole, String> {
+    let (kind, vars) = match spec.split_once(" with ") {
+        Some((kind, vars)) => (kind.trim(), Some(vars)),
+        None => (spec, None),
+    };
+
+    let kind = if kind == "stmt" {
+        HoleKind::Stmts
+    } else if let Some(ty) = kind
+        .strip_prefix("expr<")
+        .and_then(|it| it.strip_suffix('>'))
+    {
+        HoleKind::Expr(parse_type(ty)?)
+    } else {
+        return Err(format!(
+            "invalid hole `{kind}` (must be `expr<type>` or `stmt`)"
+        ));
+    };
+
+    let vars = vars
+        .into_iter()
+        .flat_map(split_vars)
+        .map(|var| {
+            let (name, ty) = var
+                .split_once(':')
+                .ok_or_else(|| format!("expected `name: type`, got `{var}`"))?;
+            Ok((name.trim().to_owned(), parse_type(ty)?))
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(Hole { kind, vars })
+}
+
+/// Splits a list of variables on the commas which aren't inside type arguments.
+fn split_vars(vars: &str) -> Vec<&str> {
+    let mut result = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in vars.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(vars[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    result.push(vars[start..].trim());
+    result.retain(|it| !it.is_empty());
+    result
+}
+
+/// Parses a scalar or vector type.
+fn parse_type(ty: &str) -> Result<DataType, String> {
+    let ty = ty.trim();
+
+    let scalar = |ty: &str| match ty.trim() {
+        "bool" => Ok(ScalarType::Bool),
+        "i32" => Ok(ScalarType::I32),
+        "u32" => Ok(ScalarType::U32),
+        "f32" => Ok(ScalarType::F32),
+        _ => Err(format!(
+            "unsupported type `{ty}` (must be a scalar or vector)"
+        )),
+    };
+
+    for n in 2..=4 {
+        let prefix = format!("vec{n}<");
+        if let Some(component) = ty.strip_prefix(&prefix).and_then(|it| it.strip_suffix('>')) {
+            return Ok(DataType::Vector(n, scalar(component)?));
+        }
+    }
+
+    scalar(ty).map(DataType::Scalar)
+}
+
+const HOLE_FN_PREFIX: &str = "template_hole_";
+
+fn hole_fn_name(index: usize) -> String {
+    format!("{HOLE_FN_PREFIX}{index}")
+}
+
+impl<'a> super::Generator<'a> {
+    /// Generates a module containing a function for each hole in the template, along with the
+    /// functions and structs that they use.
+    pub fn gen_template_module(&mut self, template: &Template) -> Module {
+        let struct_count = self
+            .rng
+            .gen_range(self.options.min_structs..=self.options.max_structs);
+
+        for i in 1..=struct_count {
+            let name = format!("Struct_{}", i);
+            let decl = self.gen_struct(name);
+            self.cx.types.insert(decl);
+        }
+
+        let holes = template
+            .holes()
+            .enumerate()
+            .map(|(i, hole)| self.gen_hole_fn(hole_fn_name(i), hole))
+            .collect::<Vec<_>>();
+
+        let cx = std::mem::replace(&mut self.cx, Context::new(Builtins::empty()));
+
+        let mut functions = cx.fns.into_fns();
+        functions.extend(holes);
+
+        Module {
+            structs: cx.types.into_structs(),
+            functions,
+            ..Default::default()
+        }
+    }
+
+    fn gen_hole_fn(&mut self, name: String, hole: &Hole) -> FnDecl {
+        let mut scope = Scope::empty();
+        for (name, ty) in &hole.vars {
+            scope.insert_readonly(name.clone(), ty.clone());
+        }
+
+        let inputs = hole
+            .vars
+            .iter()
+            .map(|(name, ty)| FnInput::new(name.clone(), ty.clone()))
+            .collect();
+
+        let (_, (output, body)) = self.with_scope(scope, |this| match &hole.kind {
+            HoleKind::Expr(ty) => {
+                let value = this.gen_expr(ty);
+                (
+                    Some(FnOutput::new(ty.clone())),
+                    vec![ReturnStatement::new(value).into()],
+                )
+            }
+            HoleKind::Stmts => {
+                let count = this
+                    .rng
+                    .gen_range(this.options.block_min_stmts..=this.options.block_max_stmts);
+
+                // The statements end up in the middle of the skeleton, so they mustn't return
+                this.fn_state.no_return = true;
+                let (_, block) = this.gen_stmt_block(count);
+                this.fn_state.no_return = false;
+
+                (None, block)
+            }
+        });
+
+        FnDecl {
+            attrs: vec![],
+            name,
+            inputs,
+            output,
+            body,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_holes() {
+        let template = Template::parse(
+            "let a = /* HOLE: expr<vec2<u32>> with x: u32, v: vec2<u32> */;\n/* HOLE: stmt */",
+        )
+        .unwrap();
+
+        let holes = template.holes().cloned().collect::<Vec<_>>();
+
+        assert_eq!(
+            holes,
+            vec![
+                Hole {
+                    kind: HoleKind::Expr(DataType::Vector(2, ScalarType::U32)),
+                    vars: vec![
+                        ("x".to_owned(), DataType::Scalar(ScalarType::U32)),
+                        ("v".to_owned(), DataType::Vector(2, ScalarType::U32)),
+                    ],
+                },
+                Hole {
+                    kind: HoleKind::Stmts,
+                    vars: vec![],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Calls to `workgroupUniformLoad` for `--workgroup-uniform-load`.
+//!
+//! Each load reads a workgroup variable that a single invocation has just written, so the value
+//! is the same in every invocation:
+//!
+//! ```wgsl
+//! if (local_index == 3u) {
+//!     w_uniform_load = ...;
+//! }
+//! let var_0 = workgroupUniformLoad(&w_uniform_load);
+//! if (var_0 != 0u) {
+//!     workgroupBarrier();
+//! }
+//! ```
+//!
+//! The builtin synchronizes the workgroup itself, so the write is only sometimes followed by an
+//! explicit barrier. Since the result is uniform, it can be used as the condition of a barrier,
+//! which uniformity analysis would reject for an ordinary load. The builtin must also be called
+//! in uniform control flow, so the loads are only generated at the top level of the entry point,
+//! which can't return early in this mode.
+
+use std::rc::Rc;
+
+use ast::types::{DataType, MemoryViewType, ScalarType};
+use ast::{
+    AssignmentLhs, AssignmentOp, AssignmentStatement, BinOp, BinOpExpr, ExprNode, FnCallExpr,
+    FnCallStatement, GlobalVarDecl, IfStatement, LetDeclStatement, Lit, Statement, StorageClass,
+    UnOp, UnOpExpr, VarExpr, VarQualifier,
+};
+
+use crate::rng::RngExt;
+
+use super::invocations::LOCAL_INDEX;
+
+/// Name of the workgroup variable that is written and then loaded.
+const UNIFORM_LOAD_VAR: &str = "w_uniform_load";
+
+impl<'a> super::Generator<'a> {
+    /// Generates the workgroup variable that is loaded from, if enabled.
+    ///
+    /// It isn't added to the global scope, so that only the loads and the writes preceding them
+    /// access it.
+    pub fn gen_uniform_load_var(&mut self) -> Option<GlobalVarDecl> {
+        if !self.options.workgroup_uniform_load {
+            return None;
+        }
+
+        let mut data_type = self.cx.types.select(&mut self.rng);
+        if self.rng.gen_bool(0.5) {
+            data_type = DataType::Array(Rc::new(data_type), Some(self.rng.gen_range(1..=8)));
+        }
+
+        Some(GlobalVarDecl {
+            attrs: vec![],
+            qualifier: Some(VarQualifier {
+                storage_class: StorageClass::WorkGroup,
+                access_mode: None,
+            }),
+            name: UNIFORM_LOAD_VAR.to_owned(),
+            data_type,
+            initializer: None,
+        })
+    }
+
+    /// Generates some writes to the workgroup variable of type `data_type`, each followed by a
+    /// `workgroupUniformLoad` of it, and adds the loaded values to the current scope.
+    pub fn gen_uniform_loads(&mut self, data_type: &DataType) -> Vec<Statement> {
+        let mut stmts = vec![];
+
+        for i in 0..self.rng.gen_range(1..=3) {
+            // Every invocation has to have read the previous value before it is overwritten
+            if i > 0 && self.options.invocations > 1 {
+                stmts.push(FnCallStatement::new("workgroupBarrier".to_owned(), vec![]).into());
+            }
+
+            stmts.extend(self.gen_uniform_load(data_type));
+        }
+
+        stmts
+    }
+
+    fn gen_uniform_load(&mut self, data_type: &DataType) -> Vec<Statement> {
+        let var_type = DataType::Ref(MemoryViewType::new(
+            data_type.clone(),
+            StorageClass::WorkGroup,
+        ));
+
+        let write: Statement = AssignmentStatement::new(
+            AssignmentLhs::name(UNIFORM_LOAD_VAR, var_type.clone()),
+            AssignmentOp::Simple,
+            self.gen_expr(data_type),
+        )
+        .into();
+
+        // The value can be different in each invocation, so it is only written by one of them
+        let mut stmts = if self.options.invocations > 1 {
+            let writer = Lit::U32(self.rng.gen_range(0..self.options.invocations));
+            let condition = BinOpExpr::new(
+                BinOp::Equal,
+                VarExpr::new(LOCAL_INDEX).into_node(ScalarType::U32),
+                writer,
+            );
+            vec![IfStatement::new(condition, vec![write]).into()]
+        } else {
+            vec![write]
+        };
+
+        // Not needed, since the load synchronizes the workgroup before reading
+        if self.rng.gen_bool(0.5) {
+            stmts.push(FnCallStatement::new("workgroupBarrier".to_owned(), vec![]).into());
+        }
+
+        let pointer = UnOpExpr::new(
+            UnOp::AddressOf,
+            VarExpr::new(UNIFORM_LOAD_VAR).into_node(var_type),
+        );
+
+        let name = self.scope.next_name();
+        let load = FnCallExpr::new("workgroupUniformLoad", vec![pointer.into()])
+            .into_node(data_type.clone());
+
+        stmts.push(LetDeclStatement::new(name.clone(), load).into());
+
+        if let Some(condition) = uniform_condition(&name, data_type) {
+            if self.rng.gen_bool(0.5) {
+                let barrier = FnCallStatement::new("workgroupBarrier".to_owned(), vec![]);
+                stmts.push(IfStatement::new(condition, vec![barrier.into()]).into());
+            }
+        }
+
+        stmts.extend(self.gen_checksum_update(&name, data_type));
+
+        self.scope.insert_readonly(name, data_type.clone());
+
+        stmts
+    }
+}
+
+/// Returns a condition on the scalar variable `name`, or `None` if it isn't a scalar.
+fn uniform_condition(name: &str, data_type: &DataType) -> Option<ExprNode> {
+    let var = VarExpr::new(name).into_node(data_type.clone());
+    let zero = match data_type {
+        DataType::Scalar(ScalarType::Bool) => return Some(var),
+        DataType::Scalar(ScalarType::I32) => Lit::I32(0),
+        DataType::Scalar(ScalarType::U32) => Lit::U32(0),
+        DataType::Scalar(ScalarType::F32) => Lit::F32(0.0),
+        _ => return None,
+    };
+
+    Some(BinOpExpr::new(BinOp::NotEqual, var, zero).into())
+}
@@ -0,0 +1,40 @@
+use std::rc::Rc;
+
+use ast::{FnInput, GlobalVarDecl, StructDecl};
+
+use crate::rng::RngExt;
+
+impl<'a> super::Generator<'a> {
+    /// Generates structs that aren't used by any other declaration.
+    pub fn gen_unused_structs(&mut self) -> Vec<Rc<StructDecl>> {
+        (0..self.rng.gen_range(1..=2))
+            .map(|i| self.gen_struct(format!("UnusedStruct_{i}")))
+            .collect()
+    }
+
+    /// Generates module-scope variables that are never referenced.
+    ///
+    /// Unlike the other module-scope variables, these aren't added to the global scope.
+    pub fn gen_unused_global_vars(&mut self) -> Vec<GlobalVarDecl> {
+        (0..self.rng.gen_range(0..=2))
+            .map(|i| self.gen_global_var_decl(format!("unused_global{i}")))
+            .collect()
+    }
+
+    /// Generates functions that are never called.
+    ///
+    /// Their bodies may still call other functions, so this must be done before the function
+    /// context is consumed.
+    pub fn gen_unused_fns(&mut self) {
+        for _ in 0..self.rng.gen_range(1..=2) {
+            let params = (0..self.rng.gen_range(0..=3))
+                .map(|i| FnInput::new(format!("arg_{i}"), self.cx.types.select(&mut self.rng)))
+                .collect::<Vec<_>>();
+
+            let return_type = self.cx.types.select(&mut self.rng);
+            let decl = self.gen_fn(params, &return_type);
+
+            self.cx.fns.insert_unused(decl);
+        }
+    }
+}
@@ -1,9 +1,11 @@
 use ast::types::DataType;
-use ast::Statement;
-use rand::prelude::SliceRandom;
-use rand::Rng;
+use ast::visit::{self, Visitor};
+use ast::{ExprNode, Statement};
+use rand::RngCore;
 
-pub fn gen_vector_accessor(rng: &mut impl Rng, size: u8, target_type: &DataType) -> String {
+use crate::rng::SliceExt;
+
+pub fn gen_vector_accessor(rng: &mut impl RngCore, size: u8, target_type: &DataType) -> String {
     // Find m (size of src vector) and n (size of target vector).
     let (m, n) = match target_type {
         DataType::Scalar(_) => return "x".to_owned(),
@@ -58,3 +60,19 @@ pub fn is_terminal_stmt<'a>(last_statement: impl Into<Option<&'a Statement>>) ->
         )
     )
 }
+
+/// Counts the nodes in an expression, including itself.
+pub fn node_count(expr: &ExprNode) -> usize {
+    struct Counter(usize);
+
+    impl Visitor for Counter {
+        fn visit_expr(&mut self, node: &ExprNode) {
+            self.0 += 1;
+            visit::walk_expr(self, node);
+        }
+    }
+
+    let mut counter = Counter(0);
+    counter.visit_expr(expr);
+    counter.0
+}
@@ -1,24 +1,31 @@
+#[cfg(feature = "cli")]
+mod cli;
 mod gen;
 
-use std::collections::HashMap;
-use std::fs::File;
+pub mod rng;
+pub mod weights;
+
+use std::fmt::Display;
 use std::hash::BuildHasher;
-use std::io::{self, BufWriter};
-use std::path::Path;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::str::FromStr;
 
-use ast::{BuiltinFn, StorageClass, VarQualifier};
+use ast::{BuiltinFn, Module};
 use clap::Parser;
-use eyre::{bail, eyre};
+use eyre::bail;
 use hashers::fx_hash::FxHasher;
 
-pub use gen::{builtins, Generator};
-use rand::prelude::StdRng;
-use rand::rngs::OsRng;
-use rand::{Rng, SeedableRng};
-use tracing_subscriber::fmt::format::FmtSpan;
-use tracing_subscriber::EnvFilter;
+#[cfg(feature = "cli")]
+pub use cli::run;
+pub use gen::{builtins, identities, limits, template, Generator};
+use rand::RngCore;
+use serde_json::Value;
+
+use crate::builtins::{BuiltinProvider, Builtins};
+use crate::limits::Limits;
+use crate::rng::Pcg32;
+use crate::weights::{AddressSpaceWeights, BinOpWeights, ExprWeights, StmtWeights};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Preset {
@@ -37,7 +44,192 @@ impl FromStr for Preset {
     }
 }
 
-#[derive(Parser)]
+/// Profiles which bias generation towards constructs that are known to break particular parts of
+/// compilers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// Lots of binary expressions, mostly bitwise operators and shifts, which stress constant
+    /// folding and instruction selection.
+    BitManipulation,
+    /// Deeply nested ifs, loops and switches with early exits, which stress control flow
+    /// structurisation.
+    ControlFlow,
+    /// Lots of large structs and vector constructors, which stress the lowering of composite
+    /// types.
+    Aggregates,
+}
+
+impl Profile {
+    /// Overrides the weights for the profile, and raises any limits that would stop its
+    /// constructs from being generated.
+    fn apply(&self, options: &mut Options) {
+        match self {
+            Profile::BitManipulation => {
+                options.expr_weights = ExprWeights {
+                    lit: 1,
+                    type_cons: 1,
+                    var: 2,
+                    un_op: 2,
+                    bin_op: 6,
+                    fn_call: 3,
+                };
+                options.bin_op_weights = BinOpWeights {
+                    arithmetic: 1,
+                    bitwise: 4,
+                    shift: 4,
+                    comparison: 1,
+                    logical: 1,
+                };
+            }
+            Profile::ControlFlow => {
+                options.stmt_weights = StmtWeights {
+                    let_decl: 4,
+                    var_decl: 4,
+                    assignment: 6,
+                    if_: 10,
+                    return_: 2,
+                    loop_: 10,
+                    switch: 10,
+                    for_loop: 10,
+                    break_: 8,
+                    continue_: 8,
+                    const_assert: 1,
+                    method: 5,
+                    accumulate: 10,
+                };
+                options.max_block_depth = options.max_block_depth.max(5);
+            }
+            Profile::Aggregates => {
+                options.expr_weights = ExprWeights {
+                    lit: 1,
+                    type_cons: 6,
+                    var: 2,
+                    un_op: 1,
+                    bin_op: 1,
+                    fn_call: 2,
+                };
+                options.min_structs = options.min_structs.max(3);
+                options.max_structs = options.max_structs.max(8);
+                options.max_struct_members = options.max_struct_members.max(10);
+            }
+        }
+    }
+}
+
+impl FromStr for Profile {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bit-manipulation" => Ok(Profile::BitManipulation),
+            "control-flow" => Ok(Profile::ControlFlow),
+            "aggregates" => Ok(Profile::Aggregates),
+            _ => {
+                Err("invalid profile - must be one of {bit-manipulation, control-flow, aggregates}")
+            }
+        }
+    }
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Profile::BitManipulation => write!(f, "bit-manipulation"),
+            Profile::ControlFlow => write!(f, "control-flow"),
+            Profile::Aggregates => write!(f, "aggregates"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human readable log lines.
+    Compact,
+    /// One JSON object per line.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compact" => Ok(LogFormat::Compact),
+            "json" => Ok(LogFormat::Json),
+            _ => Err("invalid log format - must be one of {compact, json}"),
+        }
+    }
+}
+
+impl Display for LogFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogFormat::Compact => write!(f, "compact"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Relative weights of the different forms of vector constructor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VectorConsWeights {
+    /// One scalar per component, e.g. `vec3<f32>(x, y, z)`.
+    pub components: u32,
+    /// A single scalar which is used for every component, e.g. `vec3<f32>(x)`.
+    pub splat: u32,
+    /// A mixture of scalars and smaller vectors, e.g. `vec4<f32>(v2, x, y)`.
+    pub mixed: u32,
+    /// A vector with a different component type, e.g. `vec3<u32>(vec3<i32>(...))`.
+    pub conversion: u32,
+    /// No arguments, which constructs the zero value.
+    pub zero: u32,
+}
+
+impl FromStr for VectorConsWeights {
+    type Err = &'static str;
+
+    /// Parses a comma separated list of `<form>=<weight>`, where forms that aren't listed have a
+    /// weight of 0.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ERROR: &str = "invalid weights - expected comma separated list of <form>=<weight>, \
+            where form is one of {components, splat, mixed, conversion, zero}";
+
+        let mut weights = VectorConsWeights {
+            components: 0,
+            splat: 0,
+            mixed: 0,
+            conversion: 0,
+            zero: 0,
+        };
+
+        for part in s.split(',') {
+            let (form, weight) = part.split_once('=').ok_or(ERROR)?;
+            let weight = weight.parse().map_err(|_| ERROR)?;
+            match form {
+                "components" => weights.components = weight,
+                "splat" => weights.splat = weight,
+                "mixed" => weights.mixed = weight,
+                "conversion" => weights.conversion = weight,
+                "zero" => weights.zero = weight,
+                _ => return Err(ERROR),
+            }
+        }
+
+        Ok(weights)
+    }
+}
+
+impl Display for VectorConsWeights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "components={},splat={},mixed={},conversion={},zero={}",
+            self.components, self.splat, self.mixed, self.conversion, self.zero
+        )
+    }
+}
+
+#[derive(Clone, Parser)]
 pub struct Options {
     /// Optional u64 to seed the random generator
     #[clap(action)]
@@ -51,20 +243,210 @@ pub struct Options {
     #[clap(long = "enable-fn", action)]
     pub enabled_fns: Vec<BuiltinFn>,
 
+    /// Never generate calls to a built-in function, e.g. one that a compiler is known to
+    /// miscompile
+    #[clap(long = "disable-fn", action)]
+    pub disabled_fns: Vec<BuiltinFn>,
+
     /// Whether to enable generating pointers.
     #[clap(long, action)]
     pub enable_pointers: bool,
 
+    /// Generate calls to helper functions which take a `ptr<function, S>` to a local struct
+    /// variable, and mutate its members through the pointer.
+    #[clap(long, action)]
+    pub struct_methods: bool,
+
+    /// Generate loops which update an integer accumulator on every iteration, based on its value
+    /// from the previous iteration, with conditional updates and early exits.
+    #[clap(long, action)]
+    pub loop_accumulators: bool,
+
+    /// Add a ladder of this many nested ifs, if-elses and blocks to the entry point, to find stack
+    /// overflows and recursion limits in parsers and code generators.
+    ///
+    /// Unlike `--max-block-depth`, the ladder always reaches the given depth, which can be in the
+    /// hundreds. The depth is recorded in a `// Nesting depth:` comment at the top of the program.
+    #[clap(long, action)]
+    pub nesting_ladder: Option<u32>,
+
+    /// Randomly rewrite expressions into equivalent forms after generating the program, using
+    /// this seed for the rewrites.
+    ///
+    /// The program still has to give the same output as when it's generated without this option,
+    /// or with a different rewrite seed. This allows an implementation to be checked against
+    /// itself. See `generator::identities` for the rewrites that are used.
+    #[clap(long, action)]
+    pub identity_rewrites: Option<u64>,
+
+    /// Probability (between 0 and 1) of rewriting each expression with `--identity-rewrites`
+    #[clap(long, action, default_value = "0.2", requires("identity-rewrites"))]
+    pub identity_rewrite_prob: f64,
+
+    /// Also generate a vertex and a fragment entry point, which pass a random set of inter-stage
+    /// variables between them, with random locations and interpolation attributes.
+    ///
+    /// The fragment entry point writes a `vec4<u32>` to location 0, which only depends on the
+    /// integer inter-stage variables and the pixel position. The harness draws a full-screen
+    /// triangle with the two entry points and compares the rendered pixels.
+    #[clap(
+        long,
+        action,
+        conflicts_with_all(&["address-space-weights", "template"])
+    )]
+    pub render: bool,
+
+    /// Randomize the colour write mask and the depth and stencil state of the render pipeline used
+    /// with `--render`.
+    ///
+    /// The state is written to a `// Render state:` comment at the top of the shader. When there
+    /// is a depth attachment, the fragment entry point also writes a different depth for each
+    /// pixel.
+    #[clap(long, action, requires("render"))]
+    pub render_state: bool,
+
+    /// Also generate a `var<push_constant>` struct, which is read like the uniform buffer.
+    ///
+    /// Push constants are a native-only extension, so the harness skips configurations which
+    /// don't support them (including all dawn configurations). The data for them is written to
+    /// the input data under the `push_constants` key.
+    #[clap(long, action, conflicts_with_all(&["render", "template"]))]
+    pub push_constants: bool,
+
     /// Skips the static pointer aliasing checks.
     ///
     /// This is only useful if reconditioning and pointer support is enabled.
     #[clap(long, action)]
     pub skip_pointer_checks: bool,
 
+    /// Don't generate `const_assert` statements, for compilers that don't support them yet.
+    #[clap(long, action)]
+    pub disable_const_assert: bool,
+
+    /// Don't generate `diagnostic` directives and attributes, for compilers that don't support them
+    /// yet.
+    #[clap(long, action)]
+    pub disable_diagnostics: bool,
+
+    /// Don't generate module-scope constants, and only initialise module-scope variables with
+    /// literals and constructors, for compilers that don't support const-expressions yet.
+    #[clap(long, action)]
+    pub disable_global_consts: bool,
+
+    /// Don't use unsuffixed literals where they have to be implicitly converted to u32 or f32, for
+    /// compilers that don't support abstract numeric types yet.
+    #[clap(long, action)]
+    pub disable_abstract_literals: bool,
+
+    /// Always bind the input and output buffers at `@group(0) @binding(0)` and
+    /// `@group(0) @binding(1)`, instead of choosing random groups and bindings.
+    #[clap(long, action)]
+    pub disable_random_bindings: bool,
+
+    /// Don't end the output buffer with a runtime sized array.
+    #[clap(long, action)]
+    pub disable_runtime_arrays: bool,
+
+    /// Also generate some functions, structs and module-scope variables that are never
+    /// referenced, to test dead code elimination.
+    #[clap(long, action)]
+    pub unused_decls: bool,
+
+    /// Number of invocations in the entry point's workgroup (at most 256).
+    ///
+    /// With more than one, the output buffer is an array with an element for each invocation,
+    /// which is only written by that invocation, while all invocations read the same input
+    /// buffer. Runtime sized arrays are disabled, since they can't be used in an array.
+    #[clap(long, action, default_value = "1")]
+    pub invocations: u32,
+
+    /// With multiple invocations, write each invocation's output to workgroup memory first and
+    /// then copy it to the output buffer after a barrier, possibly from another invocation's
+    /// element of the workgroup memory.
+    #[clap(long, action)]
+    pub workgroup_staging: bool,
+
+    /// Enable the `subgroups` extension, and reduce some values over the workgroup with subgroup
+    /// builtins such as `subgroupAdd` and `subgroupBallot`.
+    ///
+    /// Each subgroup's result is combined through workgroup memory, so that the output doesn't
+    /// depend on the subgroup size of the device. The harness skips configurations which don't
+    /// support subgroups.
+    #[clap(long, action, conflicts_with("template"))]
+    pub subgroups: bool,
+
+    /// Write a workgroup variable from a single invocation, then read it back in every invocation
+    /// with `workgroupUniformLoad`.
+    ///
+    /// The loaded values are uniform, so they are sometimes used in the condition of a barrier.
+    #[clap(long, action, conflicts_with("template"))]
+    pub workgroup_uniform_load: bool,
+
+    /// Write the output buffer with a const-expression, whose value is known by the generator.
+    ///
+    /// The expected contents of the output buffer are written in a `// Expected:` comment at the
+    /// top of the program, which the harness compares every execution against, so any mismatch
+    /// is a constant folding bug. The output buffer only contains integers in this mode.
+    #[clap(long, action, conflicts_with("invocations"))]
+    pub const_oracle: bool,
+
+    /// Fold the value of every local variable into a checksum after it is declared or assigned,
+    /// and write the checksum to a `checksum` member of the output buffer.
+    ///
+    /// This stops compilers from removing the computation of values that would otherwise be dead,
+    /// so that miscompilations of them show up in the output. Only integer and boolean components
+    /// are combined into the checksum.
+    #[clap(long, action, conflicts_with_all(&["const-oracle", "template"]))]
+    pub live_hash: bool,
+
+    /// Like `--live-hash`, but the output buffer only contains the checksum.
+    ///
+    /// Values are combined in a way that doesn't depend on their order, so the checksums written
+    /// by multiple invocations can be merged into a single checksum for the whole dispatch, which
+    /// the harness prints along with the output buffer.
+    #[clap(long, action, conflicts_with_all(&["const-oracle", "template"]))]
+    pub checksum: bool,
+
+    /// Path to a JSON file with limits to generate programs against, such as a WebGPU
+    /// `GPUSupportedLimits` object.
+    ///
+    /// The output buffer is bound at the largest group and binding allowed, and a struct with the
+    /// maximum number of members and functions with the maximum number of parameters and nesting
+    /// depth are added to the program. See `generator::limits::Limits` for the supported keys.
+    #[clap(long, action)]
+    pub limits: Option<Limits>,
+
+    /// Go one over each of the limits given by `--limits`, instead of sitting exactly on them.
+    #[clap(long, action, requires("limits"))]
+    pub exceed_limits: bool,
+
+    /// Path to a WGSL skeleton whose holes should be filled with generated code, instead of
+    /// generating a whole program.
+    ///
+    /// Holes are comments such as `/* HOLE: expr<i32> */` or `/* HOLE: stmt */`, optionally
+    /// followed by the variables in scope at the hole, e.g. `/* HOLE: expr<u32> with x: u32 */`.
+    /// See `generator::template` for details.
+    #[clap(long, action)]
+    pub template: Option<PathBuf>,
+
     /// Logging configuration string (see https://docs.rs/tracing-subscriber/0.3.7/tracing_subscriber/struct.EnvFilter.html#directives)
     #[clap(long, action)]
     pub log: Option<String>,
 
+    /// Minimum level of log messages to output (error, warn, info, debug or trace).
+    ///
+    /// This is ignored if `--log` is set.
+    #[clap(long, action)]
+    pub log_level: Option<tracing::Level>,
+
+    /// Format of log messages (compact or json).
+    #[clap(long, action, default_value = "compact")]
+    pub log_format: LogFormat,
+
+    /// Path to a file to write log messages to, instead of stderr.
+    #[clap(long, action)]
+    pub log_file: Option<PathBuf>,
+
     /// Minimum number of statements to generate in function bodies
     #[clap(long, action, default_value = "5")]
     pub fn_min_stmts: u32,
@@ -89,6 +471,17 @@ pub struct Options {
     #[clap(long, action, default_value = "5")]
     pub max_fns: u32,
 
+    /// Probability (between 0 and 1) of calling a previously generated function with the required
+    /// return type, where one exists, instead of a builtin or a new function
+    #[clap(long, action, default_value = "0.5")]
+    pub fn_reuse_prob: f64,
+
+    /// Probability (between 0 and 1) of repeating a large expression of the required type that was
+    /// generated earlier in the same scope, instead of generating a new one, to exercise common
+    /// subexpression elimination
+    #[clap(long, action, default_value = "0")]
+    pub duplicate_expr_prob: f64,
+
     /// Minimum number of structs to generate (excluding input and output)
     #[clap(long, action, default_value = "1")]
     pub min_structs: u32,
@@ -105,139 +498,267 @@ pub struct Options {
     #[clap(long, action, default_value = "5")]
     pub max_struct_members: u32,
 
+    /// Relative weights of the forms of vector constructor to generate, as a comma separated list
+    /// of `<form>=<weight>` (forms that aren't listed are never generated).
+    ///
+    /// The forms are `components` (one scalar per component), `splat` (a single scalar), `mixed`
+    /// (scalars and smaller vectors), `conversion` (a vector of another component type) and `zero`
+    /// (no arguments).
+    #[clap(
+        long,
+        action,
+        default_value = "components=4,splat=1,mixed=2,conversion=1,zero=1"
+    )]
+    pub vector_cons_weights: VectorConsWeights,
+
+    /// Relative weights of the kinds of statement to generate, as a comma separated list of
+    /// `<kind>=<weight>` (kinds that aren't listed are only generated if nothing else is allowed).
+    ///
+    /// The kinds are `let`, `var`, `assign`, `if`, `return`, `loop`, `switch`, `for`, `break`,
+    /// `continue`, `const-assert`, `method` (only generated with `--struct-methods`) and
+    /// `accumulate` (only generated with `--loop-accumulators`).
+    #[clap(
+        long,
+        action,
+        default_value = "let=10,var=10,assign=10,if=5,return=1,loop=5,switch=5,for=5,break=5,continue=5,const-assert=1,method=5,accumulate=5"
+    )]
+    pub stmt_weights: StmtWeights,
+
+    /// Relative weights of the kinds of expression to generate, as a comma separated list of
+    /// `<kind>=<weight>`.
+    ///
+    /// The kinds are `lit`, `type-cons`, `var`, `un-op`, `bin-op` and `fn-call`.
+    #[clap(
+        long,
+        action,
+        default_value = "lit=1,type-cons=1,var=1,un-op=1,bin-op=1,fn-call=1"
+    )]
+    pub expr_weights: ExprWeights,
+
+    /// Relative weights of the classes of binary operator to generate, as a comma separated list
+    /// of `<class>=<weight>`.
+    ///
+    /// The classes are `arithmetic`, `bitwise`, `shift`, `comparison` and `logical`.
+    #[clap(
+        long,
+        action,
+        default_value = "arithmetic=1,bitwise=1,shift=1,comparison=1,logical=1"
+    )]
+    pub bin_op_weights: BinOpWeights,
+
+    /// Relative weights of the address spaces to use for variables, as a comma separated list of
+    /// `<space>=<weight>`.
+    ///
+    /// The spaces are `function`, `private`, `workgroup`, `storage` and `uniform`. Module-scope
+    /// variables are declared in the module-scope spaces in proportion to their weights, and
+    /// assignments choose their target variable in proportion to the weight of its space. Local
+    /// `var`s are only declared if `function` has a non-zero weight. By default, only function
+    /// and private variables are generated, besides the input and output buffers.
+    #[clap(long, action)]
+    pub address_space_weights: Option<AddressSpaceWeights>,
+
     /// Preset options configuration. Individual options may still be overridden.
     #[clap(long, action)]
     pub preset: Option<Preset>,
 
+    /// Bias generation towards constructs known to break particular parts of compilers
+    /// (bit-manipulation, control-flow or aggregates).
+    ///
+    /// A profile replaces the relevant weights and may raise some size limits, so it takes
+    /// precedence over `--stmt-weights`, `--expr-weights` and `--bin-op-weights`.
+    #[clap(long, action)]
+    pub profile: Option<Profile>,
+
     /// Recondition the resulting program to remove UB
     #[clap(long, action)]
     pub recondition: bool,
 
+    /// Strategy used by the reconditioner for guarding integer division, remainder and shifts
+    /// (helpers, select or mask). If not set, one is chosen for each program.
+    #[clap(long, value_enum, action)]
+    pub safety_strategy: Option<reconditioner::SafetyStrategy>,
+
     /// Path to output file (use `-` for stdout)
     #[clap(short, long, action, default_value = "-")]
     pub output: String,
-}
 
-#[derive(Clone, Debug)]
-struct BuildFxHasher;
+    /// Type check the generated program before outputting it (always enabled in debug builds)
+    #[clap(long, action)]
+    pub typecheck: bool,
 
-impl BuildHasher for BuildFxHasher {
-    type Hasher = FxHasher;
+    /// Path at which to also write the ast of the program as JSON
+    #[clap(long, action)]
+    pub ast_json: Option<PathBuf>,
 
-    fn build_hasher(&self) -> Self::Hasher {
-        FxHasher::default()
+    /// Print statistics about the generated program to stderr
+    #[clap(long, action)]
+    pub stats: bool,
+
+    /// Path at which to write statistics about the generated program as JSON
+    #[clap(long, action)]
+    pub stats_json: Option<PathBuf>,
+
+    /// Path at which to write the tape of random decisions made while generating the program
+    #[clap(long, action)]
+    pub record_tape: Option<PathBuf>,
+
+    /// Generate the program by replaying the decisions from a tape, instead of from the seed
+    #[clap(long, action)]
+    pub replay_tape: Option<PathBuf>,
+
+    /// Number of random mutations to apply to the replayed tape (using the seed)
+    #[clap(long, action, default_value = "0", requires("replay-tape"))]
+    pub tape_mutations: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options::parse_from(["generator"])
     }
 }
 
-pub fn run(mut options: Options) -> eyre::Result<()> {
-    if let Some(preset) = &options.preset {
-        match preset {
-            Preset::Tint => {
-                for builtin in builtins::TINT_EXTRAS {
-                    if !options.enabled_fns.iter().any(|it| it == builtin) {
-                        options.enabled_fns.push(builtin.to_owned());
-                    }
+impl Options {
+    /// Parses options from a JSON object.
+    ///
+    /// The keys are the long option names accepted by `wgslsmith gen` (e.g. `max-fns`). The object
+    /// is converted into command line arguments and parsed in the same way, so that the two can't
+    /// get out of sync.
+    pub fn from_json(json: &str) -> eyre::Result<Options> {
+        let object = match serde_json::from_str(json)? {
+            Value::Object(object) => object,
+            _ => bail!("options must be a JSON object"),
+        };
+
+        let mut args = vec!["generator".to_owned()];
+
+        for (key, value) in object {
+            let values = match value {
+                Value::Array(values) => values,
+                value => vec![value],
+            };
+
+            for value in values {
+                match value {
+                    Value::Null | Value::Bool(false) => {}
+                    Value::Bool(true) => args.push(format!("--{key}")),
+                    Value::Number(value) => args.extend([format!("--{key}"), value.to_string()]),
+                    Value::String(value) => args.extend([format!("--{key}"), value]),
+                    _ => bail!("invalid value for option `{key}`"),
                 }
-
-                options.enable_pointers = true;
-                options.skip_pointer_checks = true;
-                options.recondition = true;
             }
         }
+
+        Ok(Options::try_parse_from(args)?)
     }
 
-    let options = Rc::new(options);
-
-    tracing_subscriber::fmt()
-        .compact()
-        .with_span_events(FmtSpan::ACTIVE)
-        .with_target(true)
-        .with_writer(io::stderr)
-        .with_ansi(false)
-        .with_env_filter(if let Some(log) = &options.log {
-            EnvFilter::from(log)
-        } else {
-            EnvFilter::from_default_env()
-        })
-        .init();
-
-    let seed = match options.seed {
-        Some(seed) => seed,
-        None => OsRng.gen(),
-    };
-
-    tracing::info!("generating shader from seed: {}", seed);
-
-    let mut rng = StdRng::seed_from_u64(seed);
-    let mut shader = Generator::new(&mut rng, options.clone()).gen_module();
-
-    if options.recondition {
-        if options.enable_pointers
-            && !options.skip_pointer_checks
-            && !reconditioner::analysis::analyse(&shader)
-        {
-            bail!("rejected shader due to possible invalid aliasing");
+    /// Applies the preset configuration and profile, if any.
+    pub fn apply_preset(&mut self) {
+        if let Some(profile) = self.profile {
+            profile.apply(self);
         }
 
-        shader = reconditioner::recondition_with(
-            shader,
-            reconditioner::Options {
-                only_loops: options.preset == Some(Preset::Tint),
-            },
-        );
-    }
+        if let Some(preset) = &self.preset {
+            match preset {
+                Preset::Tint => {
+                    for builtin in builtins::TINT_EXTRAS {
+                        if !self.enabled_fns.iter().any(|it| it == builtin) {
+                            self.enabled_fns.push(builtin.to_owned());
+                        }
+                    }
 
-    let mut output: Box<dyn io::Write> = if options.output == "-" {
-        Box::new(io::stdout())
-    } else {
-        if let Some(dir) = Path::new(&options.output).parent() {
-            std::fs::create_dir_all(dir)?;
+                    self.enable_pointers = true;
+                    self.skip_pointer_checks = true;
+                    self.recondition = true;
+                }
+            }
         }
-        Box::new(BufWriter::new(File::create(&options.output)?))
-    };
+    }
+}
 
-    if !options.debug {
-        let mut init_data = HashMap::new();
+/// Generates a random module from the given seed.
+///
+/// This produces the same program as `wgslsmith gen` given the same seed and options. Options
+/// which only affect the command line tool (such as `recondition` or `output`) are ignored - use
+/// the `reconditioner` crate to recondition the module if needed.
+///
+/// The output only depends on the seed and options, and is the same on every platform as long as
+/// [`rng::DECISION_VERSION`] hasn't changed.
+pub fn generate(seed: u64, options: &Options) -> Module {
+    generate_with_rng(&mut Pcg32::seeded(seed), options)
+}
 
-        for var in &shader.vars {
-            if let Some(VarQualifier { storage_class, .. }) = &var.qualifier {
-                if *storage_class != StorageClass::Uniform {
-                    continue;
-                }
+/// Generates a random module using decisions from the given random number generator.
+///
+/// See [`rng`] for generators that can be used to control the output, e.g. from a fuzzer input.
+pub fn generate_with_rng(rng: &mut dyn RngCore, options: &Options) -> Module {
+    let mut options = options.clone();
+    options.apply_preset();
 
-                let type_desc = common::Type::try_from(&var.data_type).map_err(|e| eyre!(e))?;
+    Generator::new(rng, Rc::new(options)).gen_module()
+}
 
-                let group = var.group_index().unwrap();
-                let binding = var.binding_index().unwrap();
+/// Generates a random module using only the builtin functions registered by `providers`.
+///
+/// Providers are run in order, so e.g. passing [`builtins::StandardBuiltins`] followed by a custom
+/// provider lets the custom provider add vendor-specific builtins to the standard set, or remove
+/// standard builtins that a particular compiler doesn't support.
+pub fn generate_with_builtins(
+    rng: &mut dyn RngCore,
+    options: &Options,
+    providers: &[&dyn BuiltinProvider],
+) -> Module {
+    let mut options = options.clone();
+    options.apply_preset();
+
+    let builtins = Builtins::from_providers(&options, providers);
+    Generator::with_builtins(rng, Rc::new(options), builtins).gen_module()
+}
 
-                let size = type_desc.buffer_size();
-                let data: Vec<u8> = (0..size).map(|_| rng.gen()).collect();
+/// Reconditions a generated module according to the options.
+///
+/// This fails if pointers are enabled and the module might contain invalid aliasing, unless
+/// `skip_pointer_checks` is set.
+pub fn recondition(module: Module, options: &Options) -> eyre::Result<Module> {
+    if options.enable_pointers
+        && !options.skip_pointer_checks
+        && !reconditioner::analysis::analyse(&module)
+    {
+        bail!("rejected shader due to possible invalid aliasing");
+    }
 
-                init_data.insert(format!("{group}:{binding}"), data);
-            }
-        }
+    Ok(reconditioner::recondition_with(
+        module,
+        reconditioner::Options {
+            only_loops: options.preset == Some(Preset::Tint),
+            safety: options.safety_strategy,
+        },
+    ))
+}
 
-        let init_data = serde_json::to_string(&init_data)?;
+/// Writes the module as WGSL source code.
+pub fn to_wgsl(module: &Module) -> String {
+    let mut output = String::new();
+    ast::writer::Writer::default()
+        .write_module(&mut output, module)
+        .unwrap();
+    output
+}
 
-        writeln!(output, "// {init_data}")?;
-        writeln!(output, "// Seed: {seed}")?;
-        writeln!(output)?;
-    }
+/// Writes the WGSL source code for a module to `w`, without building the whole output in memory.
+///
+/// This is the same as [`to_wgsl`], but can be used for very large programs or to pipe the output
+/// directly into another process.
+pub fn write_wgsl(w: &mut dyn std::io::Write, module: &Module) -> std::io::Result<()> {
+    ast::writer::Writer::default().write_module_io(w, module)
+}
 
-    if options.debug {
-        writeln!(output, "{shader:#?}")?;
-    } else {
-        struct Output<'a>(&'a mut dyn std::io::Write);
+#[derive(Clone, Debug)]
+struct BuildFxHasher;
 
-        impl<'a> std::fmt::Write for Output<'a> {
-            fn write_str(&mut self, s: &str) -> std::fmt::Result {
-                self.0.write_all(s.as_bytes()).unwrap();
-                Ok(())
-            }
-        }
+impl BuildHasher for BuildFxHasher {
+    type Hasher = FxHasher;
 
-        ast::writer::Writer::default().write_module(&mut Output(&mut output), &shader)?;
+    fn build_hasher(&self) -> Self::Hasher {
+        FxHasher::default()
     }
-
-    Ok(())
 }
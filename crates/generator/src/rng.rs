@@ -0,0 +1,557 @@
+//! Random number generators for driving the generator from sources other than a seed, and for
+//! recording and replaying the decisions that it makes.
+//!
+//! The same seed must always produce the same program, regardless of the platform or the versions
+//! of any dependencies, so that seeds can be shared in bug reports. rand doesn't guarantee this -
+//! `StdRng` may change algorithm between versions, and so may the algorithms used for sampling
+//! ranges and distributions. The generator therefore uses the [`Pcg32`] implementation and the
+//! sampling methods from [`RngExt`] and [`SliceExt`] defined here, which only use integer
+//! arithmetic and exactly representable floats.
+
+use std::ops::{Range, RangeInclusive};
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// Version of the algorithms used to turn random numbers into decisions.
+///
+/// This must be incremented whenever a change to the generator would cause the same seed or tape
+/// to produce a different program.
+pub const DECISION_VERSION: u32 = 9;
+
+/// The PCG32 random number generator (PCG-XSH-RR with 64 bits of state and 32 bits of output),
+/// matching the reference implementation from <https://www.pcg-random.org>.
+#[derive(Clone, Debug)]
+pub struct Pcg32 {
+    state: u64,
+    inc: u64,
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    /// Stream used by [`Pcg32::seeded`], which is the default stream from the reference
+    /// implementation.
+    const DEFAULT_STREAM: u64 = 721347520444481703;
+
+    /// Equivalent to `pcg32_srandom_r` from the reference implementation.
+    pub fn new(state: u64, stream: u64) -> Pcg32 {
+        let mut rng = Pcg32 {
+            state: 0,
+            inc: (stream << 1) | 1,
+        };
+        rng.step();
+        rng.state = rng.state.wrapping_add(state);
+        rng.step();
+        rng
+    }
+
+    pub fn seeded(seed: u64) -> Pcg32 {
+        Pcg32::new(seed, Pcg32::DEFAULT_STREAM)
+    }
+
+    fn step(&mut self) {
+        self.state = self
+            .state
+            .wrapping_mul(Pcg32::MULTIPLIER)
+            .wrapping_add(self.inc);
+    }
+}
+
+impl RngCore for Pcg32 {
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.step();
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let lo = self.next_u32() as u64;
+        let hi = self.next_u32() as u64;
+        (hi << 32) | lo
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Integer types that can be sampled uniformly from a range.
+///
+/// Values are always sampled as 64-bit integers, so that e.g. `usize` gives the same results on
+/// 32-bit and 64-bit platforms.
+pub trait UniformInt: Copy + PartialOrd {
+    fn to_i64(self) -> i64;
+    fn from_i64(value: i64) -> Self;
+}
+
+macro_rules! impl_uniform_int {
+    ($($t:ty),*) => {
+        $(
+            impl UniformInt for $t {
+                fn to_i64(self) -> i64 {
+                    self as i64
+                }
+
+                fn from_i64(value: i64) -> Self {
+                    value as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_uniform_int!(u8, u16, u32, i32, i64, usize);
+
+/// A range of integers that can be sampled from.
+pub trait SampleRange<T> {
+    /// Returns the inclusive bounds of the range.
+    fn bounds(self) -> (T, T);
+}
+
+impl<T: UniformInt> SampleRange<T> for Range<T> {
+    fn bounds(self) -> (T, T) {
+        assert!(self.start < self.end, "cannot sample empty range");
+        (self.start, T::from_i64(self.end.to_i64() - 1))
+    }
+}
+
+impl<T: UniformInt> SampleRange<T> for RangeInclusive<T> {
+    fn bounds(self) -> (T, T) {
+        assert!(self.start() <= self.end(), "cannot sample empty range");
+        self.into_inner()
+    }
+}
+
+/// Portable replacements for the sampling methods from [`rand::Rng`].
+pub trait RngExt: RngCore {
+    /// Returns a uniformly distributed integer in `0..n`.
+    fn gen_below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "cannot sample empty range");
+
+        // Rejecting values below `2^64 mod n` leaves a number of values that is a multiple of n,
+        // so that the result is exactly uniform
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let value = self.next_u64();
+            if value >= threshold {
+                return value % n;
+            }
+        }
+    }
+
+    fn gen_range<T: UniformInt, R: SampleRange<T>>(&mut self, range: R) -> T {
+        let (low, high) = range.bounds();
+        let (low, high) = (low.to_i64(), high.to_i64());
+        let span = high.wrapping_sub(low) as u64;
+
+        let offset = if span == u64::MAX {
+            self.next_u64()
+        } else {
+            self.gen_below(span + 1)
+        };
+
+        T::from_i64(low.wrapping_add(offset as i64))
+    }
+
+    /// Returns a uniformly distributed index into a collection of length `len`.
+    fn gen_index(&mut self, len: usize) -> usize {
+        self.gen_below(len as u64) as usize
+    }
+
+    /// Returns a float in `[0, 1)` with 53 bits of precision.
+    fn gen_unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns `true` with probability `p`.
+    fn gen_bool(&mut self, p: f64) -> bool {
+        assert!((0.0..=1.0).contains(&p), "probability must be in [0, 1]");
+        self.gen_unit() < p
+    }
+}
+
+impl<R: RngCore + ?Sized> RngExt for R {}
+
+/// Portable replacements for the methods from [`rand::seq::SliceRandom`].
+pub trait SliceExt {
+    type Item;
+
+    /// Returns a uniformly chosen element, or `None` if the slice is empty.
+    fn choose<R: RngCore + ?Sized>(&self, rng: &mut R) -> Option<&Self::Item>;
+
+    /// Returns an element chosen with probability proportional to its weight, or `None` if the
+    /// slice is empty or all the weights are zero.
+    fn choose_weighted<R: RngCore + ?Sized, F: Fn(&Self::Item) -> u32>(
+        &self,
+        rng: &mut R,
+        weight: F,
+    ) -> Option<&Self::Item>;
+
+    /// Shuffles the elements in place, using the Fisher-Yates algorithm.
+    fn shuffle<R: RngCore + ?Sized>(&mut self, rng: &mut R);
+}
+
+impl<T> SliceExt for [T] {
+    type Item = T;
+
+    fn choose<R: RngCore + ?Sized>(&self, rng: &mut R) -> Option<&T> {
+        if self.is_empty() {
+            None
+        } else {
+            self.get(rng.gen_index(self.len()))
+        }
+    }
+
+    fn choose_weighted<R: RngCore + ?Sized, F: Fn(&T) -> u32>(
+        &self,
+        rng: &mut R,
+        weight: F,
+    ) -> Option<&T> {
+        let total: u64 = self.iter().map(|it| weight(it) as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut target = rng.gen_below(total);
+        for item in self {
+            let weight = weight(item) as u64;
+            if target < weight {
+                return Some(item);
+            }
+            target -= weight;
+        }
+
+        unreachable!()
+    }
+
+    fn shuffle<R: RngCore + ?Sized>(&mut self, rng: &mut R) {
+        for i in (1..self.len()).rev() {
+            self.swap(i, rng.gen_index(i + 1));
+        }
+    }
+}
+
+/// A random number generator that reads its output from a byte slice.
+///
+/// This lets a coverage-guided fuzzer control the decisions made by the generator, so that small
+/// changes to its input produce small changes in the generated program. Once the input runs out,
+/// the rest of the output comes from a [`Pcg32`] seeded with the input length. Returning zeros
+/// forever instead could hang the generator, since some distributions use rejection sampling.
+pub struct ByteRng<'a> {
+    data: &'a [u8],
+    fallback: Pcg32,
+}
+
+impl<'a> ByteRng<'a> {
+    pub fn new(data: &'a [u8]) -> ByteRng<'a> {
+        ByteRng {
+            data,
+            fallback: Pcg32::seeded(data.len() as u64),
+        }
+    }
+}
+
+impl<'a> RngCore for ByteRng<'a> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let len = dest.len().min(self.data.len());
+        let (data, rest) = self.data.split_at(len);
+
+        dest[..len].copy_from_slice(data);
+        self.fallback.fill_bytes(&mut dest[len..]);
+        self.data = rest;
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// A record of every random decision made while generating a program.
+///
+/// Each decision is a single value drawn from the random number generator. Replaying a tape with
+/// [`TapeRng`] reproduces the same program, and modifying individual decisions makes small,
+/// targeted changes to it (though a change can also shift the meaning of all the decisions that
+/// come after it).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tape {
+    /// The [`DECISION_VERSION`] of the generator that recorded the tape.
+    ///
+    /// Tapes recorded by a different version won't reproduce the same program. Tapes from before
+    /// this was recorded have a version of 0.
+    #[serde(default)]
+    pub version: u32,
+    pub decisions: Vec<u64>,
+}
+
+impl Default for Tape {
+    fn default() -> Self {
+        Tape {
+            version: DECISION_VERSION,
+            decisions: vec![],
+        }
+    }
+}
+
+impl Tape {
+    /// Randomly modifies `count` decisions in the tape.
+    pub fn mutate(&mut self, rng: &mut impl RngCore, count: usize) {
+        if self.decisions.is_empty() {
+            return;
+        }
+
+        for _ in 0..count {
+            let i = rng.gen_range(0..self.decisions.len());
+            let decision = &mut self.decisions[i];
+            match rng.gen_range(0..3) {
+                0 => *decision = rng.next_u64(),
+                1 => *decision ^= 1 << rng.gen_range(0..64),
+                _ => *decision = 0,
+            }
+        }
+    }
+}
+
+/// Wraps a random number generator, recording its output into a [`Tape`].
+pub struct RecordingRng<R> {
+    inner: R,
+    tape: Tape,
+}
+
+impl<R: RngCore> RecordingRng<R> {
+    pub fn new(inner: R) -> RecordingRng<R> {
+        RecordingRng {
+            inner,
+            tape: Tape::default(),
+        }
+    }
+
+    pub fn into_tape(self) -> Tape {
+        self.tape
+    }
+}
+
+impl<R: RngCore> RngCore for RecordingRng<R> {
+    fn next_u32(&mut self) -> u32 {
+        let value = self.inner.next_u32();
+        self.tape.decisions.push(value as u64);
+        value
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let value = self.inner.next_u64();
+        self.tape.decisions.push(value);
+        value
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        // Recorded as one decision per 8 bytes, which is how `TapeRng` replays it
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// A random number generator that replays the decisions from a [`Tape`].
+///
+/// As with [`ByteRng`], once the tape runs out the rest of the output comes from a [`Pcg32`]
+/// seeded with the tape length.
+pub struct TapeRng {
+    tape: Tape,
+    position: usize,
+    fallback: Pcg32,
+}
+
+impl TapeRng {
+    pub fn new(tape: Tape) -> TapeRng {
+        TapeRng {
+            fallback: Pcg32::seeded(tape.decisions.len() as u64),
+            tape,
+            position: 0,
+        }
+    }
+}
+
+impl RngCore for TapeRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self.tape.decisions.get(self.position) {
+            Some(&value) => {
+                self.position += 1;
+                value
+            }
+            None => self.fallback.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+
+    use super::{Pcg32, RecordingRng, RngExt, SliceExt, Tape, TapeRng, DECISION_VERSION};
+    use crate::Options;
+
+    // The expected values in these tests must never change unless `DECISION_VERSION` is bumped,
+    // since they determine which program is generated from a seed
+
+    #[test]
+    fn pcg32_matches_reference() {
+        // From the pcg32-demo program in the reference implementation
+        let mut rng = Pcg32::new(42, 54);
+        let values: Vec<u32> = (0..6).map(|_| rng.next_u32()).collect();
+        assert_eq!(
+            values,
+            [0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e]
+        );
+    }
+
+    #[test]
+    fn pcg32_seeded() {
+        let mut rng = Pcg32::seeded(0);
+        let values: Vec<u32> = (0..3).map(|_| rng.next_u32()).collect();
+        assert_eq!(values, [0xe823a24e, 0x7a7ecbd9, 0x89fd6c06]);
+    }
+
+    #[test]
+    fn samplers_are_stable() {
+        let mut rng = Pcg32::seeded(42);
+
+        assert_eq!(rng.next_u64(), 0x6b07c4a9c2f57bd6);
+
+        let values: Vec<u32> = (0..8).map(|_| rng.gen_range(0..10)).collect();
+        assert_eq!(values, [5, 3, 6, 2, 5, 2, 3, 2]);
+
+        let values: Vec<i32> = (0..4).map(|_| rng.gen_range(-100..100)).collect();
+        assert_eq!(values, [-1, -44, 52, -65]);
+
+        let values: Vec<bool> = (0..8).map(|_| rng.gen_bool(0.5)).collect();
+        assert_eq!(values, [false, true, true, true, false, false, true, false]);
+
+        let weights = [1, 0, 3, 6];
+        let values: Vec<usize> = (0..8)
+            .map(|_| {
+                *[0, 1, 2, 3]
+                    .choose_weighted(&mut rng, |i| weights[*i])
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(values, [2, 3, 3, 3, 3, 3, 3, 3]);
+    }
+
+    #[test]
+    fn shuffle_is_stable() {
+        let mut rng = Pcg32::seeded(7);
+        let mut values = [0, 1, 2, 3, 4, 5];
+        values.shuffle(&mut rng);
+        assert_eq!(values, [2, 5, 1, 0, 4, 3]);
+    }
+
+    #[test]
+    fn samplers_handle_full_range() {
+        let mut rng = Pcg32::seeded(0);
+        rng.gen_range(i64::MIN..=i64::MAX);
+        rng.gen_range(0..=u8::MAX);
+        assert_eq!([0u32; 0].choose(&mut rng), None);
+        assert_eq!([7].choose_weighted(&mut rng, |_| 0), None);
+    }
+
+    fn generate_program(seed: u64) -> String {
+        let options = Options::default();
+        let module = crate::recondition(crate::generate(seed, &options), &options);
+        crate::to_wgsl(&module.unwrap())
+    }
+
+    fn generate_programs() -> Vec<String> {
+        (0..10).map(generate_program).collect()
+    }
+
+    #[test]
+    fn replaying_tape_reproduces_program() {
+        let options = Options::default();
+
+        let mut recorder = RecordingRng::new(Pcg32::seeded(3));
+        let recorded = crate::to_wgsl(&crate::generate_with_rng(&mut recorder, &options));
+        assert_eq!(recorded, crate::to_wgsl(&crate::generate(3, &options)));
+
+        // Tapes are saved as json by the cli
+        let json = serde_json::to_string(&recorder.into_tape()).unwrap();
+        let tape: Tape = serde_json::from_str(&json).unwrap();
+        assert_eq!(tape.version, DECISION_VERSION);
+        assert!(!tape.decisions.is_empty());
+
+        let replayed = crate::generate_with_rng(&mut TapeRng::new(tape), &options);
+        assert_eq!(crate::to_wgsl(&replayed), recorded);
+    }
+
+    #[test]
+    fn golden_programs() {
+        // The version is part of the snapshot names, so that a change to the generated programs
+        // shows up as new snapshots when `DECISION_VERSION` is bumped, and as a failure otherwise
+        assert_eq!(DECISION_VERSION, 9);
+
+        for seed in [0, 1, 42, 1234] {
+            insta::assert_snapshot!(
+                format!("v{DECISION_VERSION}_seed_{seed}"),
+                generate_program(seed)
+            );
+        }
+    }
+
+    #[test]
+    fn same_seed_generates_same_program() {
+        assert_eq!(generate_programs(), generate_programs());
+    }
+
+    #[test]
+    fn programs_do_not_depend_on_hasher_seeds() {
+        // Each thread gets its own random keys for std's hashers, so this catches output that
+        // depends on the iteration order of a hash map or set
+        let programs = std::thread::spawn(generate_programs).join().unwrap();
+        assert_eq!(programs, generate_programs());
+    }
+}
@@ -0,0 +1,285 @@
+//! Relative weights of the constructs that the generator chooses between.
+//!
+//! Each set of weights is parsed from a comma separated list of `<kind>=<weight>`, where kinds that
+//! aren't listed have a weight of 0 (so are never generated where there is an alternative). The
+//! defaults match the generator's behaviour before the weights were configurable, so that the
+//! same seed still produces the same program.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use ast::{BinOp, StorageClass};
+
+/// Parses a comma separated list of `<kind>=<weight>` into a weight for each of `kinds`.
+fn parse_weights<const N: usize>(
+    s: &str,
+    kinds: [&str; N],
+    error: &'static str,
+) -> Result<[u32; N], &'static str> {
+    let mut weights = [0; N];
+
+    for part in s.split(',') {
+        let (kind, weight) = part.split_once('=').ok_or(error)?;
+        let index = kinds.iter().position(|it| *it == kind).ok_or(error)?;
+        weights[index] = weight.parse().map_err(|_| error)?;
+    }
+
+    Ok(weights)
+}
+
+/// Relative weights of the different kinds of statement.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StmtWeights {
+    pub let_decl: u32,
+    pub var_decl: u32,
+    pub assignment: u32,
+    pub if_: u32,
+    pub return_: u32,
+    pub loop_: u32,
+    pub switch: u32,
+    pub for_loop: u32,
+    pub break_: u32,
+    pub continue_: u32,
+    pub const_assert: u32,
+    pub method: u32,
+    pub accumulate: u32,
+}
+
+impl FromStr for StmtWeights {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ERROR: &str = "invalid weights - expected comma separated list of <kind>=<weight>, \
+            where kind is one of {let, var, assign, if, return, loop, switch, for, break, \
+            continue, const-assert, method, accumulate}";
+
+        let weights = parse_weights(
+            s,
+            [
+                "let",
+                "var",
+                "assign",
+                "if",
+                "return",
+                "loop",
+                "switch",
+                "for",
+                "break",
+                "continue",
+                "const-assert",
+                "method",
+                "accumulate",
+            ],
+            ERROR,
+        )?;
+
+        Ok(StmtWeights {
+            let_decl: weights[0],
+            var_decl: weights[1],
+            assignment: weights[2],
+            if_: weights[3],
+            return_: weights[4],
+            loop_: weights[5],
+            switch: weights[6],
+            for_loop: weights[7],
+            break_: weights[8],
+            continue_: weights[9],
+            const_assert: weights[10],
+            method: weights[11],
+            accumulate: weights[12],
+        })
+    }
+}
+
+impl Display for StmtWeights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "let={},var={},assign={},if={},return={},loop={},switch={},for={},break={},continue={},const-assert={},method={},accumulate={}",
+            self.let_decl,
+            self.var_decl,
+            self.assignment,
+            self.if_,
+            self.return_,
+            self.loop_,
+            self.switch,
+            self.for_loop,
+            self.break_,
+            self.continue_,
+            self.const_assert,
+            self.method,
+            self.accumulate
+        )
+    }
+}
+
+/// Relative weights of the different kinds of expression.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExprWeights {
+    pub lit: u32,
+    pub type_cons: u32,
+    pub var: u32,
+    pub un_op: u32,
+    pub bin_op: u32,
+    pub fn_call: u32,
+}
+
+impl FromStr for ExprWeights {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ERROR: &str = "invalid weights - expected comma separated list of <kind>=<weight>, \
+            where kind is one of {lit, type-cons, var, un-op, bin-op, fn-call}";
+
+        let [lit, type_cons, var, un_op, bin_op, fn_call] = parse_weights(
+            s,
+            ["lit", "type-cons", "var", "un-op", "bin-op", "fn-call"],
+            ERROR,
+        )?;
+
+        Ok(ExprWeights {
+            lit,
+            type_cons,
+            var,
+            un_op,
+            bin_op,
+            fn_call,
+        })
+    }
+}
+
+impl Display for ExprWeights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "lit={},type-cons={},var={},un-op={},bin-op={},fn-call={}",
+            self.lit, self.type_cons, self.var, self.un_op, self.bin_op, self.fn_call
+        )
+    }
+}
+
+/// Relative weights of the different classes of binary operator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BinOpWeights {
+    /// `+`, `-`, `*`, `/` and `%`.
+    pub arithmetic: u32,
+    /// `&`, `|` and `^`.
+    pub bitwise: u32,
+    /// `<<` and `>>`.
+    pub shift: u32,
+    /// `==`, `!=`, `<`, `<=`, `>` and `>=`.
+    pub comparison: u32,
+    /// `&&` and `||`.
+    pub logical: u32,
+}
+
+impl BinOpWeights {
+    /// Returns the weight of a single operator.
+    pub fn weight(&self, op: BinOp) -> u32 {
+        match op {
+            BinOp::Plus | BinOp::Minus | BinOp::Times | BinOp::Divide | BinOp::Mod => {
+                self.arithmetic
+            }
+            BinOp::BitAnd | BinOp::BitOr | BinOp::BitXOr => self.bitwise,
+            BinOp::LShift | BinOp::RShift => self.shift,
+            BinOp::Equal
+            | BinOp::NotEqual
+            | BinOp::Less
+            | BinOp::LessEqual
+            | BinOp::Greater
+            | BinOp::GreaterEqual => self.comparison,
+            BinOp::LogAnd | BinOp::LogOr => self.logical,
+        }
+    }
+}
+
+impl FromStr for BinOpWeights {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ERROR: &str = "invalid weights - expected comma separated list of <class>=<weight>, \
+            where class is one of {arithmetic, bitwise, shift, comparison, logical}";
+
+        let [arithmetic, bitwise, shift, comparison, logical] = parse_weights(
+            s,
+            ["arithmetic", "bitwise", "shift", "comparison", "logical"],
+            ERROR,
+        )?;
+
+        Ok(BinOpWeights {
+            arithmetic,
+            bitwise,
+            shift,
+            comparison,
+            logical,
+        })
+    }
+}
+
+impl Display for BinOpWeights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "arithmetic={},bitwise={},shift={},comparison={},logical={}",
+            self.arithmetic, self.bitwise, self.shift, self.comparison, self.logical
+        )
+    }
+}
+
+/// Relative weights of the address spaces that variables are declared in and assigned to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AddressSpaceWeights {
+    pub function: u32,
+    pub private: u32,
+    pub workgroup: u32,
+    pub storage: u32,
+    pub uniform: u32,
+}
+
+impl AddressSpaceWeights {
+    /// Returns the weight of a single address space.
+    pub fn weight(&self, storage_class: StorageClass) -> u32 {
+        match storage_class {
+            StorageClass::Function => self.function,
+            StorageClass::Private => self.private,
+            StorageClass::WorkGroup => self.workgroup,
+            StorageClass::Storage => self.storage,
+            StorageClass::Uniform => self.uniform,
+            // Push constants are read-only, so they're never chosen
+            StorageClass::PushConstant => 0,
+        }
+    }
+}
+
+impl FromStr for AddressSpaceWeights {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ERROR: &str = "invalid weights - expected comma separated list of <space>=<weight>, \
+            where space is one of {function, private, workgroup, storage, uniform}";
+
+        let [function, private, workgroup, storage, uniform] = parse_weights(
+            s,
+            ["function", "private", "workgroup", "storage", "uniform"],
+            ERROR,
+        )?;
+
+        Ok(AddressSpaceWeights {
+            function,
+            private,
+            workgroup,
+            storage,
+            uniform,
+        })
+    }
+}
+
+impl Display for AddressSpaceWeights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "function={},private={},workgroup={},storage={},uniform={}",
+            self.function, self.private, self.workgroup, self.storage, self.uniform
+        )
+    }
+}
@@ -8,34 +8,20 @@ use std::time::Duration;
 use std::{fmt, io};
 
 use eyre::{eyre, Context};
-use reflection::PipelineDescription;
+use reflection::{PipelineDescription, RenderState, PUSH_CONSTANTS_KEY};
 
-pub use printer::{ExecutionEvent, ExecutionResult, Printer};
-use types::ConfigId;
+pub use printer::{
+    execution_label, ExecutionEvent, ExecutionResult, ExecutionStatus, Printer, ShaderOutcome,
+    ShaderSummary,
+};
+use types::{ConfigId, DawnOptions};
 
 pub fn read_input_data(
     shader: &str,
     input_data: Option<&str>,
 ) -> eyre::Result<HashMap<String, Vec<u8>>> {
     match input_data {
-        Some(input_data) => {
-            // Try parsing value as json string
-            match serde_json::from_str(input_data)
-                .wrap_err_with(|| eyre!("failed to parse input data"))
-            {
-                Ok(input_data) => Ok(input_data),
-                // On failure, try treating value as file path
-                Err(parse_err) => match File::open(input_data) {
-                    // File opened successfully, parse the contents as json
-                    Ok(file) => serde_json::from_reader(file)
-                        .wrap_err_with(|| eyre!("failed to parse input data")),
-                    // File not found, return original parsing error
-                    Err(e) if e.kind() == io::ErrorKind::NotFound => Err(parse_err),
-                    // Found file but failed to open it
-                    Err(e) => Err(e.into()),
-                },
-            }
-        }
+        Some(input_data) => read_buffer_data(input_data),
         None => {
             // Don't look for file if shader was passed over stdin
             if shader != "-" {
@@ -57,6 +43,159 @@ pub fn read_input_data(
     }
 }
 
+/// Reads the expected contents of the output buffers of a shader, in the same format as input
+/// data.
+///
+/// If `expected` isn't set, this looks for a `// Expected:` comment at the top of the shader
+/// source, which is written by the generator's `--const-oracle` mode.
+pub fn read_expected_output(
+    source: &str,
+    expected: Option<&str>,
+) -> eyre::Result<Option<HashMap<String, Vec<u8>>>> {
+    if let Some(expected) = expected {
+        return read_buffer_data(expected).map(Some);
+    }
+
+    let comment = source
+        .lines()
+        .take_while(|line| line.starts_with("//"))
+        .find_map(|line| line.strip_prefix("// Expected:"));
+
+    match comment {
+        Some(json) => Ok(Some(
+            serde_json::from_str(json.trim())
+                .wrap_err_with(|| eyre!("failed to parse expected output"))?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Reads the fixed function state of the render pipeline from the `// Render state:` comment at
+/// the top of the shader source, which is written by the generator's `--render-state` option.
+fn read_render_state(source: &str) -> eyre::Result<Option<RenderState>> {
+    let comment = source
+        .lines()
+        .take_while(|line| line.starts_with("//"))
+        .find_map(|line| line.strip_prefix("// Render state:"));
+
+    match comment {
+        Some(json) => Ok(Some(
+            serde_json::from_str(json.trim())
+                .wrap_err_with(|| eyre!("failed to parse render state"))?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Parses buffer data from a json string, or from a json file if `value` isn't valid json.
+fn read_buffer_data(value: &str) -> eyre::Result<HashMap<String, Vec<u8>>> {
+    // Try parsing value as json string
+    match serde_json::from_str(value).wrap_err_with(|| eyre!("failed to parse input data")) {
+        Ok(data) => Ok(data),
+        // On failure, try treating value as file path
+        Err(parse_err) => match File::open(value) {
+            // File opened successfully, parse the contents as json
+            Ok(file) => {
+                serde_json::from_reader(file).wrap_err_with(|| eyre!("failed to parse input data"))
+            }
+            // File not found, return original parsing error
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(parse_err),
+            // Found file but failed to open it
+            Err(e) => Err(e.into()),
+        },
+    }
+}
+
+/// Lays out the expected output buffers in the same way as the buffers from an execution, so
+/// that they can be compared.
+pub fn expected_execution(
+    expected: &HashMap<String, Vec<u8>>,
+    pipeline_desc: &PipelineDescription,
+) -> eyre::Result<Vec<Vec<u8>>> {
+    pipeline_desc
+        .resources
+        .iter()
+        .filter(|it| it.kind == reflection::ResourceKind::StorageBuffer)
+        .map(|resource| {
+            let key = format!("{}:{}", resource.group, resource.binding);
+            let mut data = expected
+                .get(&key)
+                .cloned()
+                .ok_or_else(|| eyre!("missing expected output for buffer {key}"))?;
+            data.resize(resource.size as usize, 0);
+            Ok(data)
+        })
+        .collect()
+}
+
+/// Reads input data from the binary format written by [`write_input_data_bin`].
+///
+/// The file is a sequence of records for each buffer, containing the group, binding and length of
+/// the data as little endian u32s, followed by the data itself.
+pub fn read_input_data_bin(path: &Path) -> eyre::Result<HashMap<String, Vec<u8>>> {
+    let bytes = std::fs::read(path)
+        .wrap_err_with(|| eyre!("failed to read input data from '{}'", path.display()))?;
+
+    let mut input_data = HashMap::new();
+    let mut rest = bytes.as_slice();
+
+    fn read_u32(bytes: &mut &[u8]) -> eyre::Result<u32> {
+        if bytes.len() < 4 {
+            return Err(eyre!("unexpected end of input data"));
+        }
+        let (value, rest) = bytes.split_at(4);
+        *bytes = rest;
+        Ok(u32::from_le_bytes(value.try_into().unwrap()))
+    }
+
+    while !rest.is_empty() {
+        let group = read_u32(&mut rest)?;
+        let binding = read_u32(&mut rest)?;
+        let len = read_u32(&mut rest)? as usize;
+
+        if rest.len() < len {
+            return Err(eyre!("unexpected end of input data"));
+        }
+
+        let (data, remaining) = rest.split_at(len);
+        input_data.insert(format!("{group}:{binding}"), data.to_vec());
+        rest = remaining;
+    }
+
+    Ok(input_data)
+}
+
+/// Writes input data in a binary format, so that it can be replayed exactly with `--inputs`.
+pub fn write_input_data_bin(
+    path: &Path,
+    input_data: &HashMap<String, Vec<u8>>,
+) -> eyre::Result<()> {
+    let mut buffers = input_data
+        .iter()
+        .map(|(key, data)| {
+            let (group, binding) = key
+                .split_once(':')
+                .and_then(|(group, binding)| Some((group.parse().ok()?, binding.parse().ok()?)))
+                .ok_or_else(|| eyre!("invalid buffer id '{key}', expected <group>:<binding>"))?;
+            Ok((group, binding, data))
+        })
+        .collect::<eyre::Result<Vec<(u32, u32, _)>>>()?;
+
+    // Sorted so that the same inputs always produce the same file
+    buffers.sort_by_key(|(group, binding, _)| (*group, *binding));
+
+    let mut bytes = vec![];
+    for (group, binding, data) in buffers {
+        bytes.extend(group.to_le_bytes());
+        bytes.extend(binding.to_le_bytes());
+        bytes.extend((data.len() as u32).to_le_bytes());
+        bytes.extend(data);
+    }
+
+    std::fs::write(path, bytes)
+        .wrap_err_with(|| eyre!("failed to write input data to '{}'", path.display()))
+}
+
 pub fn read_shader_from_path(path: &str) -> eyre::Result<String> {
     let mut input: Box<dyn io::Read> = match path {
         "-" => Box::new(std::io::stdin()),
@@ -73,11 +212,52 @@ pub fn read_shader_from_path(path: &str) -> eyre::Result<String> {
     Ok(shader)
 }
 
+/// Reflects the resources and entry point of a shader, giving each resource its input data.
+///
+/// Shaders that wgslsmith's parser doesn't support (such as hand-written ones) are reflected with
+/// naga instead, if the `naga` feature is enabled.
+///
+/// The render pipeline (if any) uses the fixed function state from the shader's
+/// `// Render state:` comment, or the default state if it doesn't have one. The push constants
+/// (if any) are given the input data under the `push_constants` key.
 pub fn reflect_shader(
     shader: &str,
     mut input_data: HashMap<String, Vec<u8>>,
-) -> (PipelineDescription, Vec<common::Type>) {
-    let module = parser::parse(shader);
+) -> eyre::Result<(PipelineDescription, Vec<common::Type>)> {
+    let (mut pipeline_desc, type_descs) = reflect_resources(shader, &mut input_data)?;
+
+    if let Some(push_constants) = &mut pipeline_desc.push_constants {
+        push_constants.init = input_data.remove(PUSH_CONSTANTS_KEY).map(|mut init| {
+            init.resize(push_constants.size as usize, 0);
+            init
+        });
+    }
+
+    if let Some(render) = &mut pipeline_desc.render {
+        if let Some(state) = read_render_state(shader)? {
+            render.state = state;
+        }
+    }
+
+    Ok((pipeline_desc, type_descs))
+}
+
+fn reflect_resources(
+    shader: &str,
+    input_data: &mut HashMap<String, Vec<u8>>,
+) -> eyre::Result<(PipelineDescription, Vec<common::Type>)> {
+    let module = match parser::try_parse(shader) {
+        Ok(module) => module,
+        #[cfg(feature = "naga")]
+        Err(_) => {
+            return reflection::naga::reflect(shader, |resource| {
+                input_data.remove(&format!("{}:{}", resource.group, resource.binding))
+            })
+            .map_err(|e| eyre!("failed to reflect shader: {e}"));
+        }
+        #[cfg(not(feature = "naga"))]
+        Err(e) => return Err(eyre!("failed to parse shader: {e}")),
+    };
 
     let (mut pipeline_desc, type_descs) = reflection::reflect(&module, |resource| {
         input_data.remove(&format!("{}:{}", resource.group, resource.binding))
@@ -95,7 +275,7 @@ pub fn reflect_shader(
         .resources
         .retain(|resource| !resource_vars.contains(&resource.name));
 
-    (pipeline_desc, type_descs)
+    Ok((pipeline_desc, type_descs))
 }
 
 #[derive(Debug)]
@@ -151,20 +331,36 @@ pub trait Executor {
         shader: &str,
         pipeline_desc: &PipelineDescription,
         configs: &[ConfigId],
+        dawn: &DawnOptions,
         timeout: Option<Duration>,
         on_event: &mut dyn FnMut(ExecutionEvent) -> Result<(), ExecutionError>,
     ) -> Result<(), ExecutionError>;
+
+    /// Returns an executor for a baseline build of the harness, whose executions of each
+    /// configuration are compared with the ones from this executor.
+    fn baseline(&self) -> Option<&dyn Executor> {
+        None
+    }
 }
 
 pub mod cli {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
     use std::time::Duration;
 
+    use buffer_check::{BufferTolerance, Tolerances};
     use clap::Parser;
     use color_eyre::Help;
-    use eyre::eyre;
-    use types::ConfigId;
+    use eyre::{eyre, Context};
+    use reflection::{DispatchTiming, PipelineDescription, RenderTargetFormat};
+    use types::{BackendType, BoundsChecks, ConfigId, DawnOptions, DawnToggle};
 
-    use crate::{ExecutionEvent, ExecutionResult, Executor};
+    use crate::{
+        ExecutionEvent, ExecutionResult, ExecutionStatus, Executor, ShaderOutcome, ShaderSummary,
+    };
+
+    /// Suffix of the labels of executions of the `--reference` configuration.
+    const REFERENCE_SUFFIX: &str = "@reference";
 
     #[derive(Parser)]
     pub struct RunOptions {
@@ -172,10 +368,27 @@ pub mod cli {
         #[clap(action, default_value = "-")]
         pub shader: String,
 
-        /// Input data for uniform buffers.
+        /// Input data for uniform and storage buffers.
         #[clap(action)]
         pub input_data: Option<String>,
 
+        /// Path to a binary input data file to replay, as written by `--save-inputs`.
+        #[clap(long, action, conflicts_with("input-data"))]
+        pub inputs: Option<PathBuf>,
+
+        /// Path at which to save the exact contents of the input buffers, in a binary format that
+        /// can be replayed with `--inputs`.
+        #[clap(long, action)]
+        pub save_inputs: Option<PathBuf>,
+
+        /// Expected contents of the storage buffers after execution, in the same format as the
+        /// input data.
+        ///
+        /// Every execution is compared against these, as well as against each other. If not set,
+        /// they are read from a `// Expected:` comment at the top of the shader, if it has one.
+        #[clap(long, action)]
+        pub expected: Option<String>,
+
         /// List of configurations to test.
         ///
         /// Configurations must be specified using their IDs. Use the `list` command to see available
@@ -185,31 +398,166 @@ pub mod cli {
         #[clap(short, long = "config", action)]
         pub configs: Vec<ConfigId>,
 
+        /// Path to a baseline build of the standalone harness, such as one built against an older
+        /// version of wgpu or dawn, which every configuration is also executed with.
+        ///
+        /// Its executions are labelled with `@baseline`, and are compared with the others like
+        /// any other configuration. The baseline must be built from a revision of wgslsmith with
+        /// the same execution protocol, and is only supported when executing locally.
+        #[clap(long, action)]
+        pub baseline_harness: Option<PathBuf>,
+
+        /// Configuration whose output is treated as the correct one, such as lavapipe (Mesa's
+        /// CPU implementation of Vulkan).
+        ///
+        /// It is executed in addition to the other configurations, and its executions are
+        /// labelled with `@reference`. If any execution disagrees with it, the executions which
+        /// differ from the reference are listed along with the usual mismatches.
+        #[clap(long, action)]
+        pub reference: Option<ConfigId>,
+
         /// Timeout in seconds.
         ///
         /// Use 0 to disable the timeout. Note that the timeout is per-execution rather than a global timeout.
         #[clap(long, action, default_value = "30")]
         pub timeout: u64,
+
+        /// Dawn backend to use when selecting default configurations (null, dx12, mtl or vk).
+        #[clap(long, action)]
+        pub dawn_backend: Option<BackendType>,
+
+        /// Dawn toggle to force on when creating devices, or off if written as `name=off`.
+        ///
+        /// This can be passed multiple times (e.g. `--dawn-toggle use_dxc`).
+        #[clap(long = "dawn-toggle", action)]
+        pub dawn_toggles: Vec<DawnToggle>,
+
+        /// Tolerance to use when comparing floats in output buffers.
+        ///
+        /// This is a comma separated list of `ulps=<n>`, `abs=<x>`, `rel=<x>` and `nan` (to treat
+        /// all NaNs as equal), optionally prefixed with `<group>:<binding>@` to only apply it to a
+        /// specific buffer (e.g. `--tolerance ulps=4 --tolerance 0:1@abs=1e-6,nan`). By default,
+        /// values must match exactly.
+        #[clap(long = "tolerance", action)]
+        pub tolerances: Vec<BufferTolerance>,
+
+        /// Number of times to dispatch the entry point.
+        ///
+        /// Between dispatches, the contents of each storage buffer are copied into the uniform
+        /// buffer at the same position, so that the output of each dispatch is used as the input
+        /// of the next. Only the outputs from the final dispatch are compared.
+        #[clap(long, action, default_value = "1")]
+        pub dispatches: u32,
+
+        /// Compile and execute the pipeline a second time on each configuration once any pipeline
+        /// caches are warm, and fail if the outputs differ from the first execution.
+        ///
+        /// The compile times for both executions are also printed.
+        #[clap(long, action)]
+        pub cache_check: bool,
+
+        /// After the first execution on each configuration, compile this many copies of the
+        /// pipeline concurrently on the same device and execute each of them, failing if any of
+        /// the outputs differ from the first execution.
+        ///
+        /// This stresses the thread safety of shader compilation in the implementation and
+        /// driver.
+        #[clap(long, action, default_value = "0")]
+        pub concurrent_compiles: u32,
+
+        /// After the outputs of each configuration have been read, time this many more
+        /// dispatches of the pipeline and print the median time of each configuration.
+        ///
+        /// Each timed dispatch is submitted and waited for on its own, so the times include the
+        /// overhead of a submission.
+        #[clap(long, action)]
+        pub time_dispatches: Option<u32>,
+
+        /// Number of untimed dispatches to run before the timed ones.
+        #[clap(long, action, default_value = "5")]
+        pub warmup_dispatches: u32,
+
+        /// Exit with code 2 if the median dispatch time of any execution is more than this many
+        /// times that of the fastest one, and the outputs matched.
+        ///
+        /// Every configuration executes the same program, so a much slower one points at poor
+        /// code generation in its compilers.
+        #[clap(long, action, requires = "time-dispatches")]
+        pub slow_dispatch_factor: Option<f64>,
+
+        /// Bounds checking policy to execute the shader with (on or off).
+        ///
+        /// This can be passed multiple times (e.g. `--bounds-checks on --bounds-checks off`), in
+        /// which case every configuration is executed under each policy and all of the outputs are
+        /// compared. Turning bounds checks off is only safe for shaders which never access memory
+        /// out of bounds, such as reconditioned programs. Defaults to on.
+        #[clap(long, action)]
+        pub bounds_checks: Vec<BoundsChecks>,
+
+        /// Texel format of the render target (r32uint, rg32uint or rgba32uint).
+        ///
+        /// This can be passed multiple times (e.g. `--render-target-format r32uint
+        /// --render-target-format rgba32uint`), in which case every configuration is executed with
+        /// each format. Executions are only compared with others using the same format, and
+        /// whether each format passed is printed at the end. Defaults to rgba32uint.
+        ///
+        /// Only the render target is affected, since the harness doesn't create storage or
+        /// sampled textures.
+        #[clap(long = "render-target-format", action)]
+        pub render_target_formats: Vec<RenderTargetFormat>,
+    }
+
+    impl RunOptions {
+        pub fn dawn_options(&self) -> DawnOptions {
+            DawnOptions {
+                backend: self.dawn_backend,
+                toggles: self.dawn_toggles.clone(),
+            }
+        }
     }
 
     pub fn run(options: RunOptions, executor: &dyn Executor) -> eyre::Result<()> {
         let shader = super::read_shader_from_path(&options.shader)?;
-        let input_data = super::read_input_data(&options.shader, options.input_data.as_deref())?;
-        let (pipeline_desc, type_descs) = super::reflect_shader(&shader, input_data);
+        let input_data = match &options.inputs {
+            Some(path) => super::read_input_data_bin(path)?,
+            None => super::read_input_data(&options.shader, options.input_data.as_deref())?,
+        };
+
+        let (mut pipeline_desc, type_descs) = super::reflect_shader(&shader, input_data)?;
+        let expected = super::read_expected_output(&shader, options.expected.as_deref())?;
+
+        pipeline_desc.dispatches = options.dispatches;
+        pipeline_desc.cache_check = options.cache_check;
+        pipeline_desc.concurrent_compiles = options.concurrent_compiles;
+        pipeline_desc.dispatch_timing = options.time_dispatches.map(|repetitions| DispatchTiming {
+            warmup: options.warmup_dispatches,
+            repetitions,
+        });
+
+        if let Some(path) = &options.save_inputs {
+            // Only buffers which were actually used are saved
+            let input_data: HashMap<_, _> = pipeline_desc
+                .resources
+                .iter()
+                .filter_map(|resource| {
+                    let init = resource.init.clone()?;
+                    Some((format!("{}:{}", resource.group, resource.binding), init))
+                })
+                .collect();
+            super::write_input_data_bin(path, &input_data)?;
+        }
 
         let printer = super::Printer::new();
 
-        let mut executions = vec![];
-        let mut is_fail = false;
-        let mut on_event = |event: ExecutionEvent| {
-            printer.print_execution_event(&event, &pipeline_desc)?;
-            if let ExecutionEvent::Success(buffers) = event {
-                executions.push(buffers);
-            } else if let ExecutionEvent::Failure(_) = event {
-                is_fail = true
-            }
-            Ok(())
-        };
+        if pipeline_desc.workgroup_size.iter().product::<u32>() > 1 {
+            printer.print_workgroup_size_warning(&pipeline_desc)?;
+        }
+
+        let dawn = options.dawn_options();
+        if !dawn.is_default() {
+            // Make sure the dawn configuration ends up in the output, so that it can be reproduced
+            printer.print_dawn_options(&dawn)?;
+        }
 
         let timeout = if options.timeout == 0 {
             None
@@ -217,34 +565,464 @@ pub mod cli {
             Some(Duration::from_secs(options.timeout))
         };
 
-        executor
-            .execute(
-                &shader,
-                &pipeline_desc,
-                &options.configs,
-                timeout,
-                &mut on_event,
+        let policies = if options.bounds_checks.is_empty() {
+            vec![BoundsChecks::On]
+        } else {
+            options.bounds_checks.clone()
+        };
+
+        let formats = if options.render_target_formats.is_empty() {
+            vec![None]
+        } else if pipeline_desc.render.is_none() {
+            return Err(eyre!(
+                "`--render-target-format` requires a shader with vertex and fragment entry points"
+            ));
+        } else {
+            options
+                .render_target_formats
+                .iter()
+                .copied()
+                .map(Some)
+                .collect()
+        };
+
+        if options.baseline_harness.is_some() && executor.baseline().is_none() {
+            return Err(eyre!(
+                "a baseline harness is only supported when executing locally"
+            ));
+        }
+
+        // Reference and baseline executions are suffixed so that they can be told apart from
+        // executions of the same configuration with the main harness. The reference goes first,
+        // so that the other executions are compared with it.
+        let executors = options
+            .reference
+            .as_ref()
+            .map(|it| (executor, std::slice::from_ref(it), REFERENCE_SUFFIX))
+            .into_iter()
+            .chain(std::iter::once((executor, options.configs.as_slice(), "")))
+            .chain(
+                executor
+                    .baseline()
+                    .map(|it| (it, options.configs.as_slice(), "@baseline")),
             )
-            .map_err(|e| match e {
-                crate::ExecutionError::NoDefaultConfigs => {
-                    eyre!("failed to find any suitable default configurations")
-                        .with_note(|| "use the `list` command to see all available configurations")
+            .collect::<Vec<_>>();
+
+        // Executions are grouped by render target format, since the render targets of different
+        // formats have different sizes
+        let mut groups = vec![];
+        let mut is_fail = false;
+
+        for format in formats {
+            if let (Some(render), Some(format)) = (&mut pipeline_desc.render, format) {
+                render.format = format;
+            }
+
+            let mut labels = vec![];
+            let mut executions = vec![];
+            let mut times = vec![];
+
+            for &policy in &policies {
+                pipeline_desc.bounds_checks = policy == BoundsChecks::On;
+
+                for &(executor, configs, suffix) in &executors {
+                    if suffix == "@baseline" {
+                        if let Some(path) = &options.baseline_harness {
+                            printer.print_baseline_harness(path)?;
+                        }
+                    }
+
+                    let mut current = None;
+                    let mut dispatch_time = None;
+                    let mut on_event = |event: ExecutionEvent| {
+                        printer.print_execution_event(&event, &pipeline_desc)?;
+                        if let ExecutionEvent::Start(config) = event {
+                            let label = super::execution_label(&config, &pipeline_desc);
+                            current = Some(format!("{label}{suffix}"));
+                        } else if let ExecutionEvent::DispatchTime(time) = event {
+                            dispatch_time = Some(time);
+                        } else if let ExecutionEvent::Success(buffers) = event {
+                            if let Some(label) = current.take() {
+                                times.extend(dispatch_time.take().map(|it| (label.clone(), it)));
+                                labels.push(label);
+                            }
+                            executions.push(buffers);
+                        } else if let ExecutionEvent::Failure(_) = event {
+                            is_fail = true
+                        }
+                        Ok(())
+                    };
+
+                    executor
+                        .execute(
+                            &shader,
+                            &pipeline_desc,
+                            configs,
+                            &dawn,
+                            timeout,
+                            &mut on_event,
+                        )
+                        .map_err(execution_error_report)?;
                 }
-                crate::ExecutionError::Other(e) => e,
-                e => eyre!(e),
-            })?;
+            }
+
+            groups.push((format, labels, executions, times));
+        }
 
         if is_fail {
             panic!("one or more executions failed");
         }
 
-        if buffer_check::compare(executions.iter(), &pipeline_desc, &type_descs) {
-            printer.print_execution_result(ExecutionResult::Ok)?;
-        } else {
+        let tolerances = options.tolerances.iter().copied().collect();
+        let mut format_results = vec![];
+        let mut is_mismatch = false;
+        let mut is_slow = false;
+
+        for (format, mut labels, mut executions, times) in groups {
+            // The expected output goes first, so that mismatches are reported against it
+            if let Some(expected) = &expected {
+                labels.insert(0, "expected".to_owned());
+                executions.insert(0, super::expected_execution(expected, &pipeline_desc)?);
+            }
+
+            let mismatches = buffer_check::compare_with_tolerance(
+                executions.iter(),
+                &pipeline_desc,
+                &type_descs,
+                &tolerances,
+            );
+
+            printer.print_checksums(&labels, &executions, &pipeline_desc, &type_descs)?;
+
+            let slow = match options.slow_dispatch_factor {
+                Some(factor) => slow_dispatches(&times, factor),
+                None => vec![],
+            };
+
+            if !times.is_empty() {
+                printer.print_dispatch_times(&times, &slow)?;
+            }
+
+            let result = if mismatches.is_empty() && !slow.is_empty() {
+                is_slow = true;
+                ExecutionResult::Slow
+            } else if mismatches.is_empty() {
+                ExecutionResult::Ok
+            } else {
+                printer.print_mismatches(&mismatches)?;
+
+                // The reference might have been skipped, in which case there's nothing to
+                // compare with
+                let reference = labels.iter().position(|it| it.ends_with(REFERENCE_SUFFIX));
+                if let Some(reference) = reference {
+                    let counts = reference_mismatches(
+                        reference,
+                        &executions,
+                        &pipeline_desc,
+                        &type_descs,
+                        &tolerances,
+                    );
+                    printer.print_reference_mismatches(reference, &labels, &counts)?;
+                }
+                printer.print_disagreement(&labels, &executions)?;
+                is_mismatch = true;
+                ExecutionResult::Mismatch
+            };
+
+            format_results.extend(format.map(|format| (format, result)));
+        }
+
+        if format_results.len() > 1 {
+            printer.print_format_matrix(&format_results)?;
+        }
+
+        if is_mismatch {
             printer.print_execution_result(ExecutionResult::Mismatch)?;
             std::process::exit(1);
         }
 
+        if is_slow {
+            printer.print_execution_result(ExecutionResult::Slow)?;
+            std::process::exit(2);
+        }
+
+        printer.print_execution_result(ExecutionResult::Ok)?;
+
+        Ok(())
+    }
+
+    /// Returns the number of values in each execution which differ from the reference
+    /// execution at index `reference`.
+    fn reference_mismatches(
+        reference: usize,
+        executions: &[Vec<Vec<u8>>],
+        pipeline_desc: &PipelineDescription,
+        type_descs: &[common::Type],
+        tolerances: &Tolerances,
+    ) -> Vec<usize> {
+        executions
+            .iter()
+            .map(|execution| {
+                buffer_check::compare_with_tolerance(
+                    [&executions[reference], execution].into_iter(),
+                    pipeline_desc,
+                    type_descs,
+                    tolerances,
+                )
+                .len()
+            })
+            .collect()
+    }
+
+    /// Returns the indices of the executions whose dispatch times are more than `factor` times
+    /// that of the fastest execution.
+    fn slow_dispatches(times: &[(String, Duration)], factor: f64) -> Vec<usize> {
+        let fastest = match times.iter().map(|(_, time)| *time).min() {
+            Some(fastest) => fastest,
+            None => return vec![],
+        };
+
+        times
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, time))| time.as_secs_f64() > factor * fastest.as_secs_f64())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn execution_error_report(e: crate::ExecutionError) -> eyre::Report {
+        match e {
+            crate::ExecutionError::NoDefaultConfigs => {
+                eyre!("failed to find any suitable default configurations")
+                    .with_note(|| "use the `list` command to see all available configurations")
+            }
+            crate::ExecutionError::Other(e) => e,
+            e => eyre!(e),
+        }
+    }
+
+    #[derive(Parser)]
+    pub struct RunDirOptions {
+        /// Path to a directory of wgsl shaders to be executed.
+        ///
+        /// The directory is searched recursively for `.wgsl` files. Input data for each shader is
+        /// read from an `inputs.json` file in the same directory, or a json file with the same name
+        /// as the shader.
+        #[clap(action)]
+        pub dir: PathBuf,
+
+        /// List of configurations to test.
+        ///
+        /// If no configurations are provided, defaults will be selected for the execution platform.
+        #[clap(short, long = "config", action)]
+        pub configs: Vec<ConfigId>,
+
+        /// Timeout in seconds.
+        ///
+        /// Use 0 to disable the timeout. Note that the timeout is per-execution rather than a global timeout.
+        #[clap(long, action, default_value = "30")]
+        pub timeout: u64,
+
+        /// Dawn backend to use when selecting default configurations (null, dx12, mtl or vk).
+        #[clap(long, action)]
+        pub dawn_backend: Option<BackendType>,
+
+        /// Dawn toggle to force on when creating devices, or off if written as `name=off`.
+        #[clap(long = "dawn-toggle", action)]
+        pub dawn_toggles: Vec<DawnToggle>,
+
+        /// Tolerance to use when comparing floats in output buffers, in the same format as for
+        /// the `run` command.
+        #[clap(long = "tolerance", action)]
+        pub tolerances: Vec<BufferTolerance>,
+    }
+
+    /// Executes every shader in a directory and prints a summary table of the results.
+    ///
+    /// Unlike [`run`], a shader which fails to reflect or execute doesn't stop the run. The process
+    /// exits with an error code if any shader didn't pass.
+    pub fn run_dir(options: RunDirOptions, executor: &dyn Executor) -> eyre::Result<()> {
+        let mut paths = vec![];
+        find_shaders(&options.dir, &mut paths)?;
+        paths.sort();
+
+        if paths.is_empty() {
+            return Err(eyre!("no shaders found in '{}'", options.dir.display()));
+        }
+
+        let printer = super::Printer::new();
+
+        let dawn = DawnOptions {
+            backend: options.dawn_backend,
+            toggles: options.dawn_toggles.clone(),
+        };
+
+        if !dawn.is_default() {
+            printer.print_dawn_options(&dawn)?;
+        }
+
+        let timeout = if options.timeout == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(options.timeout))
+        };
+
+        let tolerances = options.tolerances.iter().copied().collect();
+        let mut summaries = vec![];
+
+        for path in paths {
+            let name = path
+                .strip_prefix(&options.dir)
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+
+            let summary = match run_dir_shader(
+                &path,
+                &name,
+                &options,
+                executor,
+                &dawn,
+                timeout,
+                &tolerances,
+            ) {
+                Ok(summary) => summary,
+                // Without any configurations there's nothing to compare, so give up immediately
+                Err(crate::ExecutionError::NoDefaultConfigs) => {
+                    return Err(execution_error_report(
+                        crate::ExecutionError::NoDefaultConfigs,
+                    ))
+                }
+                Err(e) => ShaderSummary {
+                    name,
+                    executions: vec![],
+                    outcome: ShaderOutcome::Error(format!("{e:#}")),
+                },
+            };
+
+            printer.print_shader_outcome(&summary)?;
+            summaries.push(summary);
+        }
+
+        printer.print_summary_table(&summaries)?;
+
+        if summaries
+            .iter()
+            .any(|it| !matches!(it.outcome, ShaderOutcome::Ok))
+        {
+            std::process::exit(1);
+        }
+
         Ok(())
     }
+
+    fn find_shaders(dir: &Path, paths: &mut Vec<PathBuf>) -> eyre::Result<()> {
+        for entry in std::fs::read_dir(dir)
+            .wrap_err_with(|| eyre!("failed to read directory '{}'", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.is_dir() {
+                find_shaders(&path, paths)?;
+            } else if path.extension().map_or(false, |it| it == "wgsl") {
+                paths.push(path);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run_dir_shader(
+        path: &Path,
+        name: &str,
+        options: &RunDirOptions,
+        executor: &dyn Executor,
+        dawn: &DawnOptions,
+        timeout: Option<Duration>,
+        tolerances: &Tolerances,
+    ) -> Result<ShaderSummary, crate::ExecutionError> {
+        let path = path.to_string_lossy();
+        let shader = super::read_shader_from_path(&path)?;
+        let input_data = super::read_input_data(&path, None)?;
+        let (pipeline_desc, type_descs) = super::reflect_shader(&shader, input_data)?;
+
+        let mut statuses = vec![];
+        let mut executions = vec![];
+
+        let mut current = None;
+        let mut on_event = |event: ExecutionEvent| {
+            match event {
+                ExecutionEvent::Start(config) => {
+                    current = Some(super::execution_label(&config, &pipeline_desc));
+                }
+                ExecutionEvent::Success(buffers) => {
+                    if let Some(label) = current.take() {
+                        statuses.push((label, ExecutionStatus::Output(executions.len())));
+                    }
+                    executions.push(buffers);
+                }
+                ExecutionEvent::Failure(_) => {
+                    statuses.extend(current.take().map(|it| (it, ExecutionStatus::Failure)));
+                }
+                ExecutionEvent::Timeout => {
+                    statuses.extend(current.take().map(|it| (it, ExecutionStatus::Timeout)));
+                }
+                ExecutionEvent::Skipped(_) => {
+                    statuses.extend(current.take().map(|it| (it, ExecutionStatus::Skipped)));
+                }
+                ExecutionEvent::UsingDefaultConfigs(_)
+                | ExecutionEvent::CompileTimes(_)
+                | ExecutionEvent::DispatchTime(_)
+                | ExecutionEvent::InvalidSpirv(_) => {}
+            }
+            Ok(())
+        };
+
+        executor.execute(
+            &shader,
+            &pipeline_desc,
+            &options.configs,
+            dawn,
+            timeout,
+            &mut on_event,
+        )?;
+
+        // Replace the execution indices with the class of each output, so that executions which
+        // agree with each other share a class
+        let signatures = executions
+            .iter()
+            .map(|it| buffer_check::signature(it))
+            .collect::<Vec<_>>();
+        let classes = buffer_check::disagreement_pattern(&signatures);
+
+        for (_, status) in &mut statuses {
+            if let ExecutionStatus::Output(index) = status {
+                *index = classes[*index];
+            }
+        }
+
+        // Skipped executions don't count as failures, and have no outputs to compare
+        let outcome = if statuses
+            .iter()
+            .any(|(_, it)| !matches!(it, ExecutionStatus::Output(_) | ExecutionStatus::Skipped))
+        {
+            ShaderOutcome::Failure
+        } else if buffer_check::compare_with_tolerance(
+            executions.iter(),
+            &pipeline_desc,
+            &type_descs,
+            tolerances,
+        )
+        .is_empty()
+        {
+            ShaderOutcome::Ok
+        } else {
+            ShaderOutcome::Mismatch
+        };
+
+        Ok(ShaderSummary {
+            name: name.to_owned(),
+            executions: statuses,
+            outcome,
+        })
+    }
 }
@@ -1,22 +1,102 @@
 use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
 
 use bincode::{Decode, Encode};
-use reflection::{PipelineDescription, ResourceKind};
+use buffer_check::Mismatch;
+use reflection::{PipelineDescription, RenderTargetFormat, ResourceKind};
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
-use types::{Config, ConfigId};
+use types::{Config, ConfigId, DawnOptions};
 
 #[derive(Decode, Encode)]
 pub enum ExecutionEvent {
     UsingDefaultConfigs(Vec<ConfigId>),
     Start(ConfigId),
+    /// Time taken to compile the pipeline with a cold and then warm pipeline cache.
+    CompileTimes(Vec<Duration>),
+    /// Median time taken by a dispatch of the pipeline, if dispatches were timed.
+    DispatchTime(Duration),
+    /// The SPIR-V that the shader was translated to failed validation, with the diagnostics.
+    InvalidSpirv(String),
     Success(Vec<Vec<u8>>),
     Failure(Vec<u8>),
     Timeout,
+    /// The configuration doesn't support a feature used by the shader, for the given reason.
+    Skipped(String),
+}
+
+/// Identifies an execution of a configuration in the output.
+///
+/// Executions with bounds checks disabled are suffixed with `/unchecked`, and executions with a
+/// render target format other than `rgba32uint` are suffixed with the format, so that they can be
+/// told apart from other executions of the same configuration.
+pub fn execution_label(config: &ConfigId, pipeline_desc: &PipelineDescription) -> String {
+    let mut label = config.to_string();
+
+    if !pipeline_desc.bounds_checks {
+        label.push_str("/unchecked");
+    }
+
+    if let Some(render) = &pipeline_desc.render {
+        if render.format != RenderTargetFormat::default() {
+            label.push_str(&format!("/{}", render.format));
+        }
+    }
+
+    label
 }
 
 pub enum ExecutionResult {
     Ok,
     Mismatch,
+    /// The outputs matched, but some executions' dispatches were much slower than the fastest.
+    Slow,
+}
+
+/// Result of a single execution of a shader in a directory run.
+pub enum ExecutionStatus {
+    /// The execution succeeded, producing outputs in the given class. Executions with the same
+    /// class produced identical outputs.
+    Output(usize),
+    Failure,
+    Timeout,
+    Skipped,
+}
+
+/// Overall result of executing a shader in a directory run.
+pub enum ShaderOutcome {
+    Ok,
+    Mismatch,
+    Failure,
+    /// The shader couldn't be executed at all, such as when it fails to reflect.
+    Error(String),
+}
+
+impl ShaderOutcome {
+    fn label(&self) -> &'static str {
+        match self {
+            ShaderOutcome::Ok => "ok",
+            ShaderOutcome::Mismatch => "mismatch",
+            ShaderOutcome::Failure => "failure",
+            ShaderOutcome::Error(_) => "error",
+        }
+    }
+
+    fn color(&self) -> ColorSpec {
+        match self {
+            ShaderOutcome::Ok => green(),
+            ShaderOutcome::Mismatch | ShaderOutcome::Failure => red(),
+            ShaderOutcome::Error(_) => yellow(),
+        }
+    }
+}
+
+pub struct ShaderSummary {
+    /// Path to the shader, relative to the directory.
+    pub name: String,
+    /// Label and status of each execution.
+    pub executions: Vec<(String, ExecutionStatus)>,
+    pub outcome: ShaderOutcome,
 }
 
 #[derive(Default)]
@@ -42,11 +122,23 @@ impl Printer {
             .iter()
             .map(|it| it.adapter_name.len())
             .max()
-            .unwrap_or(0);
+            .unwrap_or(0)
+            .max("Adapter Name".len());
+
+        // The driver column is left out if no implementation knows its drivers
+        let show_drivers = configs.iter().any(|it| !it.driver.is_empty());
 
         stdout.set_color(&dimmed())?;
 
-        writeln!(&mut stdout, "{:<id_width$} | Adapter Name", "ID")?;
+        if show_drivers {
+            writeln!(
+                &mut stdout,
+                "{:<id_width$} | {:<name_width$} | Driver",
+                "ID", "Adapter Name"
+            )?;
+        } else {
+            writeln!(&mut stdout, "{:<id_width$} | Adapter Name", "ID")?;
+        }
 
         for _ in 0..id_width + 1 {
             write!(&mut stdout, "-")?;
@@ -58,6 +150,15 @@ impl Printer {
             write!(&mut stdout, "-")?;
         }
 
+        if show_drivers {
+            write!(&mut stdout, "-+")?;
+
+            let driver_width = configs.iter().map(|it| it.driver.len()).max().unwrap_or(0);
+            for _ in 0..driver_width + 1 {
+                write!(&mut stdout, "-")?;
+            }
+        }
+
         stdout.reset()?;
         writeln!(&mut stdout)?;
 
@@ -72,12 +173,67 @@ impl Printer {
             write!(&mut stdout, " | ")?;
 
             stdout.reset()?;
-            writeln!(&mut stdout, "{name}")?;
+
+            if show_drivers {
+                write!(&mut stdout, "{name:<name_width$}")?;
+
+                stdout.set_color(&dimmed())?;
+                write!(&mut stdout, " | ")?;
+
+                stdout.reset()?;
+                writeln!(&mut stdout, "{}", config.driver)?;
+            } else {
+                writeln!(&mut stdout, "{name}")?;
+            }
         }
 
         Ok(())
     }
 
+    pub fn print_dawn_options(&self, options: &DawnOptions) -> io::Result<()> {
+        let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+        write!(&mut stdout, "dawn options: ")?;
+        stdout.set_color(&cyan())?;
+        write!(&mut stdout, "{options}")?;
+        stdout.reset()?;
+        writeln!(&mut stdout)?;
+
+        Ok(())
+    }
+
+    pub fn print_baseline_harness(&self, path: &Path) -> io::Result<()> {
+        let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+        write!(&mut stdout, "executing with baseline harness: ")?;
+        stdout.set_color(&cyan())?;
+        write!(&mut stdout, "{}", path.display())?;
+        stdout.reset()?;
+        writeln!(&mut stdout)?;
+        writeln!(&mut stdout)?;
+
+        Ok(())
+    }
+
+    pub fn print_workgroup_size_warning(
+        &self,
+        pipeline_desc: &PipelineDescription,
+    ) -> io::Result<()> {
+        let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+        let [x, y, z] = pipeline_desc.workgroup_size;
+
+        stdout.set_color(&yellow())?;
+        write!(&mut stdout, "warning: ")?;
+        stdout.reset()?;
+        writeln!(
+            &mut stdout,
+            "entry point `{}` has a workgroup size of {x}x{y}x{z}, so outputs may depend on the order that invocations run in",
+            pipeline_desc.entry_point
+        )?;
+
+        Ok(())
+    }
+
     fn print_default_configs(&self, configs: &[ConfigId]) -> io::Result<()> {
         let mut stdout = StandardStream::stdout(ColorChoice::Auto);
 
@@ -109,7 +265,7 @@ impl Printer {
         write!(&mut stdout, "executing ")?;
 
         stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)))?;
-        writeln!(&mut stdout, "{config}")?;
+        writeln!(&mut stdout, "{}", execution_label(config, pipeline_desc))?;
         stdout.reset()?;
 
         writeln!(&mut stdout, "inputs:")?;
@@ -158,11 +314,157 @@ impl Printer {
             writeln!(&mut stdout, "  none")?;
         }
 
+        let signature = buffer_check::signature(buffers);
+        writeln!(&mut stdout, "signature: {signature:016x}")?;
+
         writeln!(&mut stdout)?;
 
         Ok(())
     }
 
+    fn print_compile_times(&self, times: &[Duration]) -> io::Result<()> {
+        let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+        write!(&mut stdout, "compile times:")?;
+
+        for (name, time) in ["cold", "warm"].iter().zip(times) {
+            write!(&mut stdout, " {name} ")?;
+            stdout.set_color(&cyan())?;
+            write!(&mut stdout, "{time:?}")?;
+            stdout.reset()?;
+        }
+
+        writeln!(&mut stdout)?;
+
+        Ok(())
+    }
+
+    fn print_dispatch_time(&self, time: &Duration) -> io::Result<()> {
+        let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+        write!(&mut stdout, "dispatch time: ")?;
+        stdout.set_color(&cyan())?;
+        writeln!(&mut stdout, "{time:?}")?;
+        stdout.reset()?;
+
+        Ok(())
+    }
+
+    fn print_invalid_spirv(&self, diagnostics: &str) -> io::Result<()> {
+        let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+        stdout.set_color(&yellow())?;
+        writeln!(&mut stdout, "invalid spirv:")?;
+        stdout.reset()?;
+
+        for line in diagnostics.lines() {
+            writeln!(&mut stdout, "  {line}")?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints the dispatch time of each execution relative to the fastest one, highlighting the
+    /// executions at the indices in `slow`.
+    pub fn print_dispatch_times(
+        &self,
+        times: &[(String, Duration)],
+        slow: &[usize],
+    ) -> io::Result<()> {
+        let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+        let fastest = times
+            .iter()
+            .map(|(_, time)| *time)
+            .min()
+            .unwrap_or_default();
+        let width = times
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0);
+
+        writeln!(stdout, "dispatch times:")?;
+
+        for (index, (label, time)) in times.iter().enumerate() {
+            write!(stdout, "  {label:<width$} : ")?;
+            if slow.contains(&index) {
+                stdout.set_color(&red())?;
+            }
+            write!(stdout, "{time:?}")?;
+            stdout.reset()?;
+            writeln!(
+                stdout,
+                " ({:.1}x)",
+                time.as_secs_f64() / fastest.as_secs_f64()
+            )?;
+        }
+
+        writeln!(stdout)?;
+
+        Ok(())
+    }
+
+    /// Prints which executions agreed with each other, as a greppable line of the form
+    /// `disagreement: <label>=<class> ...`.
+    pub fn print_disagreement(
+        &self,
+        labels: &[String],
+        executions: &[Vec<Vec<u8>>],
+    ) -> io::Result<()> {
+        let signatures = executions
+            .iter()
+            .map(|it| buffer_check::signature(it))
+            .collect::<Vec<_>>();
+
+        let pattern = buffer_check::disagreement_pattern(&signatures)
+            .into_iter()
+            .zip(labels)
+            .map(|(class, label)| format!("{label}={class}"))
+            .collect::<Vec<_>>();
+
+        // Not coloured, since this is intended to be parsed
+        println!("disagreement: {}", pattern.join(" "));
+
+        Ok(())
+    }
+
+    /// Prints the checksum computed by each execution of a program generated with `--checksum`,
+    /// as a greppable line of the form `checksum: <label>=<checksum> ...`.
+    ///
+    /// Nothing is printed if none of the output buffers contain a checksum.
+    pub fn print_checksums(
+        &self,
+        labels: &[String],
+        executions: &[Vec<Vec<u8>>],
+        pipeline_desc: &PipelineDescription,
+        type_descs: &[common::Type],
+    ) -> io::Result<()> {
+        let resources = pipeline_desc
+            .resources
+            .iter()
+            .enumerate()
+            .filter(|(_, it)| it.kind == ResourceKind::StorageBuffer)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        for (label, buffers) in labels.iter().zip(executions) {
+            let checksums = resources
+                .iter()
+                .zip(buffers)
+                .filter_map(|(&index, buffer)| buffer_check::checksum(buffer, &type_descs[index]))
+                .map(|it| format!("{it:08x}"))
+                .collect::<Vec<_>>();
+
+            if !checksums.is_empty() {
+                // Not coloured, since this is intended to be parsed
+                println!("checksum: {label}={}", checksums.join(","));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn print_execution_event(
         &self,
         event: &ExecutionEvent,
@@ -171,6 +473,9 @@ impl Printer {
         match event {
             ExecutionEvent::UsingDefaultConfigs(configs) => self.print_default_configs(configs),
             ExecutionEvent::Start(config) => self.print_pre_execution(config, pipeline_desc),
+            ExecutionEvent::CompileTimes(times) => self.print_compile_times(times),
+            ExecutionEvent::DispatchTime(time) => self.print_dispatch_time(time),
+            ExecutionEvent::InvalidSpirv(diagnostics) => self.print_invalid_spirv(diagnostics),
             ExecutionEvent::Success(buffers) => self.print_post_execution(buffers, pipeline_desc),
             ExecutionEvent::Failure(stderr) => {
                 std::io::stdout().write_all(stderr)?;
@@ -185,9 +490,78 @@ impl Printer {
                 writeln!(stdout)?;
                 Ok(())
             }
+            ExecutionEvent::Skipped(reason) => {
+                let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+                stdout.set_color(&yellow())?;
+                write!(stdout, "skipped: ")?;
+                stdout.reset()?;
+                writeln!(stdout, "{reason}")?;
+                writeln!(stdout)?;
+                Ok(())
+            }
         }
     }
 
+    /// Prints the worst offending values from a buffer mismatch.
+    pub fn print_mismatches(&self, mismatches: &[Mismatch]) -> io::Result<()> {
+        const MAX_SHOWN: usize = 10;
+
+        let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+        writeln!(stdout, "mismatched values ({} total):", mismatches.len())?;
+
+        for mismatch in mismatches.iter().take(MAX_SHOWN) {
+            writeln!(stdout, "  {mismatch}")?;
+        }
+
+        if mismatches.len() > MAX_SHOWN {
+            stdout.set_color(&dimmed())?;
+            writeln!(stdout, "  ... and {} more", mismatches.len() - MAX_SHOWN)?;
+            stdout.reset()?;
+        }
+
+        writeln!(stdout)?;
+
+        Ok(())
+    }
+
+    /// Prints how many values in each execution differ from the reference execution, which is
+    /// at index `reference` in `labels`.
+    pub fn print_reference_mismatches(
+        &self,
+        reference: usize,
+        labels: &[String],
+        counts: &[usize],
+    ) -> io::Result<()> {
+        let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+        write!(stdout, "compared with reference ")?;
+        stdout.set_color(&cyan())?;
+        write!(stdout, "{}", labels[reference])?;
+        stdout.reset()?;
+        writeln!(stdout, ":")?;
+
+        for (index, (label, count)) in labels.iter().zip(counts).enumerate() {
+            if index == reference {
+                continue;
+            }
+
+            write!(stdout, "  {label}: ")?;
+            if *count == 0 {
+                stdout.set_color(&green())?;
+                writeln!(stdout, "ok")?;
+            } else {
+                stdout.set_color(&red())?;
+                writeln!(stdout, "{count} mismatched values")?;
+            }
+            stdout.reset()?;
+        }
+
+        writeln!(stdout)?;
+
+        Ok(())
+    }
+
     pub fn print_execution_result(&self, result: ExecutionResult) -> io::Result<()> {
         let mut stdout = StandardStream::stdout(ColorChoice::Auto);
 
@@ -202,10 +576,176 @@ impl Printer {
                 writeln!(stdout, "mismatch")?;
                 stdout.reset()?;
             }
+            ExecutionResult::Slow => {
+                stdout.set_color(&yellow())?;
+                writeln!(stdout, "slow dispatch")?;
+                stdout.reset()?;
+            }
         }
 
         Ok(())
     }
+
+    /// Prints whether the executions with each render target format agreed with each other.
+    pub fn print_format_matrix(
+        &self,
+        results: &[(RenderTargetFormat, ExecutionResult)],
+    ) -> io::Result<()> {
+        let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+        writeln!(stdout, "render target formats:")?;
+
+        for (format, result) in results {
+            write!(stdout, "  {:<12}", format.to_string())?;
+            match result {
+                ExecutionResult::Ok => {
+                    stdout.set_color(&green())?;
+                    writeln!(stdout, "ok")?;
+                }
+                ExecutionResult::Mismatch => {
+                    stdout.set_color(&red())?;
+                    writeln!(stdout, "mismatch")?;
+                }
+                ExecutionResult::Slow => {
+                    stdout.set_color(&yellow())?;
+                    writeln!(stdout, "slow dispatch")?;
+                }
+            }
+            stdout.reset()?;
+        }
+
+        writeln!(stdout)?;
+
+        Ok(())
+    }
+
+    /// Prints the outcome of a shader in a directory run as soon as it has been executed.
+    pub fn print_shader_outcome(&self, summary: &ShaderSummary) -> io::Result<()> {
+        let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+        write!(stdout, "{}: ", summary.name)?;
+        stdout.set_color(&summary.outcome.color())?;
+        write!(stdout, "{}", summary.outcome.label())?;
+        stdout.reset()?;
+        writeln!(stdout)?;
+
+        if let ShaderOutcome::Error(e) = &summary.outcome {
+            stdout.set_color(&dimmed())?;
+            for line in e.lines() {
+                writeln!(stdout, "  {line}")?;
+            }
+            stdout.reset()?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints a table of the status of every execution of each shader in a directory run,
+    /// followed by the number of shaders with each outcome.
+    ///
+    /// Successful executions are shown by the class of their outputs, so that executions which
+    /// disagree with each other can be identified.
+    pub fn print_summary_table(&self, summaries: &[ShaderSummary]) -> io::Result<()> {
+        let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+        // Columns are ordered by when each configuration was first executed
+        let mut labels: Vec<&str> = vec![];
+        for (label, _) in summaries.iter().flat_map(|it| &it.executions) {
+            if !labels.contains(&label.as_str()) {
+                labels.push(label);
+            }
+        }
+
+        let name_width = summaries
+            .iter()
+            .map(|it| it.name.len())
+            .chain(["Shader".len()])
+            .max()
+            .unwrap_or(0);
+
+        let widths = labels
+            .iter()
+            .map(|it| it.len().max("timeout".len()))
+            .collect::<Vec<_>>();
+
+        writeln!(stdout)?;
+        stdout.set_color(&dimmed())?;
+
+        write!(stdout, "{:<name_width$} ", "Shader")?;
+        for (label, width) in labels.iter().zip(&widths) {
+            write!(stdout, "| {label:<width$} ")?;
+        }
+        writeln!(stdout, "| Result")?;
+
+        write!(stdout, "{}", "-".repeat(name_width + 1))?;
+        for width in &widths {
+            write!(stdout, "+{}", "-".repeat(width + 2))?;
+        }
+        write!(stdout, "+{}", "-".repeat("Result".len() + 1))?;
+
+        stdout.reset()?;
+        writeln!(stdout)?;
+
+        for summary in summaries {
+            write!(stdout, "{:<name_width$} ", summary.name)?;
+
+            for (label, width) in labels.iter().zip(&widths) {
+                let status = summary
+                    .executions
+                    .iter()
+                    .find(|(it, _)| it == label)
+                    .map(|(_, it)| it);
+
+                let (text, color) = match status {
+                    Some(ExecutionStatus::Output(class)) => (class.to_string(), cyan()),
+                    Some(ExecutionStatus::Failure) => ("failure".to_owned(), red()),
+                    Some(ExecutionStatus::Timeout) => ("timeout".to_owned(), yellow()),
+                    Some(ExecutionStatus::Skipped) => ("skipped".to_owned(), dimmed()),
+                    None => ("-".to_owned(), dimmed()),
+                };
+
+                stdout.set_color(&dimmed())?;
+                write!(stdout, "| ")?;
+                stdout.set_color(&color)?;
+                write!(stdout, "{text:<width$} ")?;
+            }
+
+            stdout.set_color(&dimmed())?;
+            write!(stdout, "| ")?;
+            stdout.set_color(&summary.outcome.color())?;
+            write!(stdout, "{}", summary.outcome.label())?;
+            stdout.reset()?;
+            writeln!(stdout)?;
+        }
+
+        writeln!(stdout)?;
+
+        let outcomes = [
+            ShaderOutcome::Ok,
+            ShaderOutcome::Mismatch,
+            ShaderOutcome::Failure,
+            ShaderOutcome::Error(String::new()),
+        ];
+
+        for (index, outcome) in outcomes.iter().enumerate() {
+            let count = summaries
+                .iter()
+                .filter(|it| it.outcome.label() == outcome.label())
+                .count();
+
+            stdout.set_color(&outcome.color())?;
+            write!(stdout, "{count} {}", outcome.label())?;
+            stdout.reset()?;
+
+            if index < outcomes.len() - 1 {
+                write!(stdout, ", ")?;
+            }
+        }
+
+        writeln!(stdout, " ({} total)", summaries.len())?;
+
+        Ok(())
+    }
 }
 
 fn dimmed() -> ColorSpec {
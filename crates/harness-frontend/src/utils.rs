@@ -126,6 +126,7 @@ fn visit_stmt(vars: &mut HashSet<String>, stmt: &Statement) {
         }
         Statement::Continue => {}
         Statement::Fallthrough => {}
+        Statement::ConstAssert(_) => {}
     }
 }
 
@@ -2,12 +2,20 @@ use std::time::Duration;
 
 use bincode::{Decode, Encode};
 use reflection_types::PipelineDescription;
-use types::{Config, ConfigId};
+use types::{Config, ConfigId, DawnOptions};
+
+/// Message sent by clients at the start of a connection, containing their request.
+#[derive(Debug, Decode, Encode)]
+pub struct Envelope {
+    /// Token which must match the one that the server was started with, if it has one.
+    pub token: Option<String>,
+    pub request: Request,
+}
 
 #[derive(Debug, Decode, Encode)]
 pub enum Request {
     List,
-    Run(RunRequest),
+    Run(Box<RunRequest>),
 }
 
 #[derive(Debug, Decode, Encode)]
@@ -20,6 +28,7 @@ pub struct RunRequest {
     pub shader: String,
     pub pipeline_desc: PipelineDescription,
     pub configs: Vec<ConfigId>,
+    pub dawn: DawnOptions,
     pub timeout: Option<Duration>,
 }
 
@@ -27,9 +36,13 @@ pub struct RunRequest {
 pub enum RunMessage {
     UsingDefaultConfigs(Vec<ConfigId>),
     ExecStart(ConfigId),
+    ExecCompileTimes(Vec<Duration>),
+    ExecDispatchTime(Duration),
+    ExecInvalidSpirv(String),
     ExecSuccess(Vec<Vec<u8>>),
     ExecFailure(Vec<u8>),
     ExecTimeout,
+    ExecSkipped(String),
     End(Result<(), RunError>),
 }
 
@@ -11,11 +11,40 @@ pub enum Implementation {
 
 #[derive(Clone, Copy, Debug, Decode, Encode, PartialEq, Eq)]
 pub enum BackendType {
+    /// Dawn's null backend, which doesn't execute anything on a GPU.
+    Null = 0,
     Dx12 = 3,
     Metal = 4,
     Vulkan = 5,
 }
 
+impl FromStr for BackendType {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<BackendType, Self::Err> {
+        match value {
+            "null" => Ok(BackendType::Null),
+            "dx12" => Ok(BackendType::Dx12),
+            "mtl" => Ok(BackendType::Metal),
+            "vk" => Ok(BackendType::Vulkan),
+            _ => Err("invalid backend"),
+        }
+    }
+}
+
+impl Display for BackendType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let id = match self {
+            BackendType::Null => "null",
+            BackendType::Dx12 => "dx12",
+            BackendType::Metal => "mtl",
+            BackendType::Vulkan => "vk",
+        };
+
+        f.write_str(id)
+    }
+}
+
 #[derive(Clone, Debug, Decode, Encode)]
 pub struct ConfigId {
     pub implementation: Implementation,
@@ -43,12 +72,7 @@ impl FromStr for ConfigId {
                 "wgpu" => Implementation::Wgpu,
                 _ => return Err("invalid implementation"),
             },
-            backend: match backend {
-                "dx12" => BackendType::Dx12,
-                "mtl" => BackendType::Metal,
-                "vk" => BackendType::Vulkan,
-                _ => return Err("invalid backend"),
-            },
+            backend: backend.parse()?,
             device_id: device.parse().map_err(|_| "invalid device id")?,
         })
     }
@@ -61,11 +85,7 @@ impl Display for ConfigId {
             Implementation::Wgpu => "wgpu",
         };
 
-        let backend_id = match self.backend {
-            BackendType::Dx12 => "dx12",
-            BackendType::Metal => "mtl",
-            BackendType::Vulkan => "vk",
-        };
+        let backend_id = self.backend.to_string();
 
         let device = self.device_id;
 
@@ -87,14 +107,27 @@ impl Display for ConfigId {
 #[derive(Debug)]
 pub struct Adapter {
     pub name: String,
+    /// Description of the driver (usually including its version), or empty if unknown.
+    pub driver: String,
     pub device_id: usize,
     pub backend: BackendType,
 }
 
+/// Substrings of the names of adapters that are implemented in software, in lowercase.
+const SOFTWARE_ADAPTERS: &[&str] = &[
+    "swiftshader",
+    "llvmpipe",
+    "lavapipe",
+    "softpipe",
+    "microsoft basic render driver",
+];
+
 #[derive(Debug, Decode, Encode)]
 pub struct Config {
     pub id: ConfigId,
     pub adapter_name: String,
+    /// Description of the driver (usually including its version), or empty if unknown.
+    pub driver: String,
 }
 
 impl Config {
@@ -106,6 +139,112 @@ impl Config {
                 device_id: adapter.device_id,
             },
             adapter_name: adapter.name,
+            driver: adapter.driver,
+        }
+    }
+
+    /// Returns true if the adapter runs on the CPU rather than a GPU, such as swiftshader or WARP.
+    ///
+    /// Dawn's null backend doesn't count, since it doesn't execute anything.
+    pub fn is_software(&self) -> bool {
+        let name = self.adapter_name.to_lowercase();
+        self.id.backend != BackendType::Null && SOFTWARE_ADAPTERS.iter().any(|it| name.contains(it))
+    }
+}
+
+/// A dawn toggle to force on or off when creating devices.
+#[derive(Clone, Debug, Decode, Encode, PartialEq, Eq)]
+pub struct DawnToggle {
+    pub name: String,
+    pub enabled: bool,
+}
+
+impl FromStr for DawnToggle {
+    type Err = &'static str;
+
+    /// Parses a toggle of the form `name`, `name=on` or `name=off`.
+    fn from_str(value: &str) -> Result<DawnToggle, Self::Err> {
+        let (name, enabled) = match value.split_once('=') {
+            None => (value, true),
+            Some((name, "on")) => (name, true),
+            Some((name, "off")) => (name, false),
+            Some(_) => return Err("toggle value must be `on` or `off`"),
+        };
+
+        if name.is_empty() {
+            return Err("missing toggle name");
+        }
+
+        Ok(DawnToggle {
+            name: name.to_owned(),
+            enabled,
+        })
+    }
+}
+
+impl Display for DawnToggle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = if self.enabled { "on" } else { "off" };
+        write!(f, "{}={state}", self.name)
+    }
+}
+
+/// Options that control how dawn devices are created.
+#[derive(Clone, Debug, Default, Decode, Encode)]
+pub struct DawnOptions {
+    /// Backend to use for dawn when selecting default configurations.
+    pub backend: Option<BackendType>,
+    pub toggles: Vec<DawnToggle>,
+}
+
+impl DawnOptions {
+    pub fn is_default(&self) -> bool {
+        self.backend.is_none() && self.toggles.is_empty()
+    }
+}
+
+impl Display for DawnOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.backend {
+            Some(backend) => write!(f, "backend={backend}")?,
+            None => write!(f, "backend=default")?,
+        }
+
+        for toggle in &self.toggles {
+            write!(f, " {toggle}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Policy for bounds checks on memory accesses, which implementations normally insert into
+/// shaders to make out of bounds accesses safe.
+#[derive(Clone, Copy, Debug, Decode, Encode, PartialEq, Eq)]
+pub enum BoundsChecks {
+    On,
+    /// Bounds checks are disabled where the implementation supports it (dawn's
+    /// `disable_robustness` toggle, and unchecked shader modules in wgpu).
+    Off,
+}
+
+impl FromStr for BoundsChecks {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<BoundsChecks, Self::Err> {
+        match value {
+            "on" => Ok(BoundsChecks::On),
+            "off" => Ok(BoundsChecks::Off),
+            _ => Err("bounds checks must be `on` or `off`"),
+        }
+    }
+}
+
+impl Display for BoundsChecks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoundsChecks::On => f.write_str("on"),
+            BoundsChecks::Off => f.write_str("off"),
         }
     }
 }
@@ -1,13 +1,15 @@
 use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::process::Command as ProcessCommand;
 use std::time::Duration;
 
 use clap::Parser;
-use frontend::cli::RunOptions;
+use frontend::cli::{RunDirOptions, RunOptions};
 use frontend::ExecutionError;
 use reflection::PipelineDescription;
-use types::ConfigId;
+use types::{ConfigId, DawnOptions};
 
-use crate::{ExecutionEvent, ExecutionInput, ExecutionOutput, HarnessHost};
+use crate::{ExecutionEvent, ExecutionInput, HarnessHost};
 
 #[derive(Parser)]
 pub enum Command {
@@ -17,6 +19,10 @@ pub enum Command {
     /// Runs a wgsl shader against one or more configurations.
     Run(RunOptions),
 
+    /// Runs every wgsl shader in a directory against one or more configurations, and prints a
+    /// summary of the results.
+    RunDir(RunDirOptions),
+
     #[clap(hide(true))]
     Exec {
         #[clap(action)]
@@ -25,14 +31,20 @@ pub enum Command {
 
     /// Runs the harness server for remote execution.
     Serve(crate::server::Options),
+
+    /// Checks that adapters, shader compilers and remote servers are available for differential
+    /// testing.
+    Doctor(crate::doctor::Options),
 }
 
 pub fn run<Host: HarnessHost>(command: Command) -> eyre::Result<()> {
     match command {
         Command::List => list(),
         Command::Run(options) => execute::<Host>(options),
+        Command::RunDir(options) => frontend::cli::run_dir(options, &Executor::<Host>::new()),
         Command::Exec { config } => internal_run(config),
         Command::Serve(options) => crate::server::run::<Host>(options),
+        Command::Doctor(options) => crate::doctor::run(options),
     }
 }
 
@@ -46,36 +58,99 @@ fn internal_run(config: ConfigId) -> eyre::Result<()> {
     let input: ExecutionInput =
         bincode::decode_from_std_read(&mut std::io::stdin(), bincode::config::standard())?;
 
-    let output = ExecutionOutput {
-        buffers: crate::execute_config(&input.shader, &input.pipeline_desc, &config)?,
-    };
+    #[cfg(feature = "spirv-val")]
+    let spirv_diagnostics = crate::spirv::validate(&input.shader, &config);
+    #[cfg(not(feature = "spirv-val"))]
+    let spirv_diagnostics = None;
+
+    // Printed before executing so that the diagnostics are included in the output of a crash
+    if let Some(diagnostics) = &spirv_diagnostics {
+        eprintln!("invalid spirv:\n{}", diagnostics.trim_end());
+    }
+
+    let mut output =
+        crate::execute_config(&input.shader, &input.pipeline_desc, &config, &input.dawn)?;
+
+    output.spirv_diagnostics = spirv_diagnostics;
 
     bincode::encode_into_std_write(output, &mut std::io::stdout(), bincode::config::standard())?;
 
     Ok(())
 }
 
-pub fn execute<Host: HarnessHost>(options: RunOptions) -> eyre::Result<()> {
-    struct Executor<Host>(PhantomData<Host>);
+struct Executor<Host> {
+    baseline: Option<BaselineExecutor>,
+    _host: PhantomData<Host>,
+}
 
-    impl<Host> Executor<Host> {
-        fn new() -> Executor<Host> {
-            Executor(PhantomData)
+impl<Host> Executor<Host> {
+    fn new() -> Executor<Host> {
+        Executor {
+            baseline: None,
+            _host: PhantomData,
         }
     }
 
-    impl<Host: HarnessHost> frontend::Executor for Executor<Host> {
-        fn execute(
-            &self,
-            shader: &str,
-            pipeline_desc: &PipelineDescription,
-            configs: &[ConfigId],
-            timeout: Option<Duration>,
-            on_event: &mut dyn FnMut(ExecutionEvent) -> Result<(), ExecutionError>,
-        ) -> Result<(), ExecutionError> {
-            crate::execute::<Host, _>(shader, pipeline_desc, configs, timeout, on_event)
+    fn with_baseline(path: Option<PathBuf>) -> Executor<Host> {
+        Executor {
+            baseline: path.map(BaselineExecutor),
+            _host: PhantomData,
         }
     }
+}
+
+impl<Host: HarnessHost> frontend::Executor for Executor<Host> {
+    fn execute(
+        &self,
+        shader: &str,
+        pipeline_desc: &PipelineDescription,
+        configs: &[ConfigId],
+        dawn: &DawnOptions,
+        timeout: Option<Duration>,
+        on_event: &mut dyn FnMut(ExecutionEvent) -> Result<(), ExecutionError>,
+    ) -> Result<(), ExecutionError> {
+        crate::execute::<Host, _>(shader, pipeline_desc, configs, dawn, timeout, on_event)
+    }
+
+    fn baseline(&self) -> Option<&dyn frontend::Executor> {
+        self.baseline
+            .as_ref()
+            .map(|it| it as &dyn frontend::Executor)
+    }
+}
 
-    frontend::cli::run(options, &Executor::<Host>::new())
+/// Executes configurations with another build of the standalone harness.
+struct BaselineExecutor(PathBuf);
+
+impl frontend::Executor for BaselineExecutor {
+    fn execute(
+        &self,
+        shader: &str,
+        pipeline_desc: &PipelineDescription,
+        configs: &[ConfigId],
+        dawn: &DawnOptions,
+        timeout: Option<Duration>,
+        on_event: &mut dyn FnMut(ExecutionEvent) -> Result<(), ExecutionError>,
+    ) -> Result<(), ExecutionError> {
+        let exec_command = || {
+            let mut cmd = ProcessCommand::new(&self.0);
+            cmd.arg("exec");
+            cmd
+        };
+
+        crate::execute_with_command(
+            exec_command,
+            shader,
+            pipeline_desc,
+            configs,
+            dawn,
+            timeout,
+            on_event,
+        )
+    }
+}
+
+pub fn execute<Host: HarnessHost>(options: RunOptions) -> eyre::Result<()> {
+    let executor = Executor::<Host>::with_baseline(options.baseline_harness.clone());
+    frontend::cli::run(options, &executor)
 }
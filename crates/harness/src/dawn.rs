@@ -1,40 +1,84 @@
+use std::time::{Duration, Instant};
+
 use color_eyre::eyre::eyre;
-use dawn::webgpu::{
-    WGPUBackendType_WGPUBackendType_D3D12, WGPUBackendType_WGPUBackendType_Metal,
-    WGPUBackendType_WGPUBackendType_Vulkan,
-};
 use dawn::*;
-use reflection::{PipelineDescription, ResourceKind};
+use reflection::{
+    DispatchTiming, PipelineDescription, RenderPipelineDescription, ResourceKind,
+    RENDER_TARGET_SIZE,
+};
 
-use crate::ConfigId;
+use crate::{ConfigId, DawnOptions, ExecutionOutput, Pass};
 
 enum BufferSet {
     Storage {
+        group: u32,
         binding: u32,
         size: usize,
         storage: DeviceBuffer,
         read: DeviceBuffer,
     },
     Uniform {
+        group: u32,
         binding: u32,
         size: usize,
         buffer: DeviceBuffer,
     },
 }
 
+impl BufferSet {
+    fn group(&self) -> u32 {
+        match self {
+            BufferSet::Storage { group, .. } | BufferSet::Uniform { group, .. } => *group,
+        }
+    }
+
+    fn binding(&self) -> u32 {
+        match self {
+            BufferSet::Storage { binding, .. } | BufferSet::Uniform { binding, .. } => *binding,
+        }
+    }
+
+    /// Returns the entry which binds the buffer that the shader accesses.
+    fn entry(&self) -> BindGroupEntry<'_> {
+        match self {
+            BufferSet::Storage {
+                binding,
+                size,
+                storage,
+                ..
+            } => BindGroupEntry {
+                binding: *binding,
+                buffer: storage,
+                size: *size,
+            },
+            BufferSet::Uniform {
+                binding,
+                size,
+                buffer,
+                ..
+            } => BindGroupEntry {
+                binding: *binding,
+                buffer,
+                size: *size,
+            },
+        }
+    }
+}
+
 pub fn get_adapters() -> Vec<types::Adapter> {
     Instance::new()
         .enumerate_adapters()
         .into_iter()
         .filter_map(|it| {
-            #[allow(non_upper_case_globals)]
             Some(types::Adapter {
                 name: it.name,
+                driver: it.driver_description,
                 device_id: it.device_id as usize,
                 backend: match it.backend {
-                    WGPUBackendType_WGPUBackendType_D3D12 => crate::BackendType::Dx12,
-                    WGPUBackendType_WGPUBackendType_Metal => crate::BackendType::Metal,
-                    WGPUBackendType_WGPUBackendType_Vulkan => crate::BackendType::Vulkan,
+                    BackendType::Null => crate::BackendType::Null,
+                    BackendType::D3D12 => crate::BackendType::Dx12,
+                    BackendType::Metal => crate::BackendType::Metal,
+                    BackendType::Vulkan => crate::BackendType::Vulkan,
                     _ => return None,
                 },
             })
@@ -42,44 +86,266 @@ pub fn get_adapters() -> Vec<types::Adapter> {
         .collect()
 }
 
+/// Returns true if the adapter for a configuration supports subgroup operations.
+///
+/// The version of dawn that the harness is built against predates the `subgroups` feature, so no
+/// adapter supports them.
+pub fn supports_subgroups(_config: &ConfigId) -> bool {
+    false
+}
+
 pub async fn run(
     shader: &str,
     meta: &PipelineDescription,
     config: &ConfigId,
-) -> color_eyre::Result<Vec<Vec<u8>>> {
+    options: &DawnOptions,
+) -> color_eyre::Result<ExecutionOutput> {
+    // Executions through the harness are skipped before getting here, but the `exec` command can
+    // still be given a dawn configuration directly
+    if meta.push_constants.is_some() {
+        return Err(eyre!("dawn doesn't support push constants"));
+    }
+
+    if meta.subgroups && !supports_subgroups(config) {
+        return Err(eyre!("adapter doesn't support subgroups"));
+    }
+
     let backend = match config.backend {
-        crate::BackendType::Dx12 => WGPUBackendType_WGPUBackendType_D3D12,
-        crate::BackendType::Metal => WGPUBackendType_WGPUBackendType_Metal,
-        crate::BackendType::Vulkan => WGPUBackendType_WGPUBackendType_Vulkan,
+        crate::BackendType::Null => BackendType::Null,
+        crate::BackendType::Dx12 => BackendType::D3D12,
+        crate::BackendType::Metal => BackendType::Metal,
+        crate::BackendType::Vulkan => BackendType::Vulkan,
     };
 
+    let mut toggles = DeviceToggles::default();
+    for toggle in &options.toggles {
+        if toggle.enabled {
+            toggles.enabled.push(toggle.name.clone());
+        } else {
+            toggles.disabled.push(toggle.name.clone());
+        }
+    }
+
+    if !meta.bounds_checks {
+        toggles.enabled.push("disable_robustness".to_owned());
+    }
+
     let device = Instance::new()
-        .create_device(backend, config.device_id as u32)
+        .create_device(backend, config.device_id as u32, &toggles)
         .ok_or_else(|| eyre!("no adapter found matching id: {config}"))?;
 
     let queue = device.create_queue();
-    let shader_module = device.create_shader_module(shader);
-    let pipeline = device.create_compute_pipeline(&shader_module, "main");
 
+    // With the cache check enabled, the same pipeline is compiled a second time after the first
+    // execution, by which point any pipeline caches in dawn or the driver should be warm
+    let passes = if meta.cache_check { 2 } else { 1 };
+
+    let mut outputs = vec![];
+    for pass in 0..passes {
+        // Only the last pass is timed, since the dispatches are the same in each pass
+        let timing = meta.dispatch_timing.filter(|_| pass == passes - 1);
+        outputs.push(execute(&device, &queue, shader, meta, timing)?);
+    }
+
+    if meta.concurrent_compiles > 1 {
+        let concurrent = execute_concurrently(&device, &queue, shader, meta)?;
+        crate::check_concurrent_outputs(&outputs[0].buffers, &concurrent)?;
+    }
+
+    crate::check_cache_passes(outputs)
+}
+
+/// Compiles several copies of the pipeline concurrently and executes each of them, returning
+/// their outputs.
+///
+/// Dawn's API isn't thread safe, so rather than creating pipelines from multiple threads they are
+/// all created asynchronously at once, which lets dawn compile them concurrently on its worker
+/// threads.
+fn execute_concurrently(
+    device: &Device,
+    queue: &DeviceQueue,
+    shader: &str,
+    meta: &PipelineDescription,
+) -> color_eyre::Result<Vec<Vec<Vec<u8>>>> {
+    let shader_module = device.create_shader_module(shader)?;
+
+    let receivers = (0..meta.concurrent_compiles)
+        .map(|_| device.create_compute_pipeline_async(&shader_module, &meta.entry_point))
+        .collect::<Vec<_>>();
+
+    let mut pipelines = vec![];
+    for mut rx in receivers {
+        loop {
+            match rx.try_recv()? {
+                Some(result) => {
+                    pipelines.push(result?);
+                    break;
+                }
+                None => {
+                    device.tick();
+                    device.check_errors()?;
+                    std::thread::sleep(std::time::Duration::from_millis(16));
+                }
+            }
+        }
+    }
+
+    // Only the compute pipelines are compiled concurrently, but they all draw with the same render
+    // pipeline so that their outputs have the same layout
+    let render_pipeline = create_render_pipeline(device, &shader_module, meta)?;
+
+    pipelines
+        .iter()
+        .map(|pipeline| {
+            let (buffers, _) = execute_pipeline(
+                device,
+                queue,
+                pipeline,
+                render_pipeline.as_ref(),
+                meta,
+                None,
+            )?;
+            Ok(buffers)
+        })
+        .collect()
+}
+
+fn create_render_pipeline(
+    device: &Device,
+    shader_module: &ShaderModule,
+    meta: &PipelineDescription,
+) -> color_eyre::Result<Option<RenderPipeline>> {
+    let render = match &meta.render {
+        Some(render) => render,
+        None => return Ok(None),
+    };
+
+    let depth_stencil = render
+        .state
+        .depth_stencil
+        .as_ref()
+        .map(|state| DepthStencilState {
+            depth_compare: compare_function(state.depth_compare),
+            depth_write: state.depth_write,
+            stencil_compare: compare_function(state.stencil_compare),
+            stencil_fail_op: stencil_operation(state.stencil_fail_op),
+            depth_fail_op: stencil_operation(state.depth_fail_op),
+            pass_op: stencil_operation(state.pass_op),
+        });
+
+    let pipeline = device.create_render_pipeline(
+        shader_module,
+        &render.vertex_entry_point,
+        &render.fragment_entry_point,
+        render_target_format(render.format),
+        render.state.write_mask,
+        depth_stencil.as_ref(),
+    )?;
+
+    Ok(Some(pipeline))
+}
+
+fn render_target_format(format: reflection::RenderTargetFormat) -> RenderTargetFormat {
+    match format {
+        reflection::RenderTargetFormat::R32Uint => RenderTargetFormat::R32Uint,
+        reflection::RenderTargetFormat::Rg32Uint => RenderTargetFormat::RG32Uint,
+        reflection::RenderTargetFormat::Rgba32Uint => RenderTargetFormat::RGBA32Uint,
+    }
+}
+
+fn compare_function(function: reflection::CompareFunction) -> CompareFunction {
+    match function {
+        reflection::CompareFunction::Never => CompareFunction::Never,
+        reflection::CompareFunction::Less => CompareFunction::Less,
+        reflection::CompareFunction::Equal => CompareFunction::Equal,
+        reflection::CompareFunction::LessEqual => CompareFunction::LessEqual,
+        reflection::CompareFunction::Greater => CompareFunction::Greater,
+        reflection::CompareFunction::NotEqual => CompareFunction::NotEqual,
+        reflection::CompareFunction::GreaterEqual => CompareFunction::GreaterEqual,
+        reflection::CompareFunction::Always => CompareFunction::Always,
+    }
+}
+
+fn stencil_operation(op: reflection::StencilOperation) -> StencilOperation {
+    match op {
+        reflection::StencilOperation::Keep => StencilOperation::Keep,
+        reflection::StencilOperation::Zero => StencilOperation::Zero,
+        reflection::StencilOperation::Replace => StencilOperation::Replace,
+        reflection::StencilOperation::Invert => StencilOperation::Invert,
+        reflection::StencilOperation::IncrementClamp => StencilOperation::IncrementClamp,
+        reflection::StencilOperation::DecrementClamp => StencilOperation::DecrementClamp,
+        reflection::StencilOperation::IncrementWrap => StencilOperation::IncrementWrap,
+        reflection::StencilOperation::DecrementWrap => StencilOperation::DecrementWrap,
+    }
+}
+
+fn execute(
+    device: &Device,
+    queue: &DeviceQueue,
+    shader: &str,
+    meta: &PipelineDescription,
+    timing: Option<DispatchTiming>,
+) -> color_eyre::Result<Pass> {
+    let start = Instant::now();
+    let shader_module = device.create_shader_module(shader)?;
+    let pipeline = device.create_compute_pipeline(&shader_module, &meta.entry_point)?;
+
+    let render_pipeline = create_render_pipeline(device, &shader_module, meta)?;
+    let compile_time = start.elapsed();
+
+    let (buffers, dispatch_time) = execute_pipeline(
+        device,
+        queue,
+        &pipeline,
+        render_pipeline.as_ref(),
+        meta,
+        timing,
+    )?;
+
+    Ok(Pass {
+        buffers,
+        compile_time,
+        dispatch_time,
+    })
+}
+
+fn execute_pipeline(
+    device: &Device,
+    queue: &DeviceQueue,
+    pipeline: &ComputePipeline,
+    render_pipeline: Option<&RenderPipeline>,
+    meta: &PipelineDescription,
+    timing: Option<DispatchTiming>,
+) -> color_eyre::Result<(Vec<Vec<u8>>, Option<Duration>)> {
     let mut buffer_sets = vec![];
 
+    // Buffers only need to be copied between if there are multiple dispatches
+    let feedback = meta.dispatches > 1;
+
     for resource in &meta.resources {
         let size = resource.size as usize;
         match resource.kind {
             ResourceKind::StorageBuffer => {
-                let storage = device.create_buffer(
-                    false,
+                let mut storage = device.create_buffer(
+                    resource.init.is_some(),
                     size,
                     DeviceBufferUsage::STORAGE | DeviceBufferUsage::COPY_SRC,
-                );
+                )?;
+
+                // Storage buffers are zero initialised unless there is input data for them
+                if let Some(init) = resource.init.as_deref() {
+                    storage.get_mapped_range(size).copy_from_slice(init);
+                    storage.unmap();
+                }
 
                 let read = device.create_buffer(
                     false,
                     size,
                     DeviceBufferUsage::COPY_DST | DeviceBufferUsage::MAP_READ,
-                );
+                )?;
 
                 buffer_sets.push(BufferSet::Storage {
+                    group: resource.group,
                     binding: resource.binding,
                     size,
                     storage,
@@ -87,7 +353,13 @@ pub async fn run(
                 });
             }
             ResourceKind::UniformBuffer => {
-                let mut buffer = device.create_buffer(true, size, DeviceBufferUsage::UNIFORM);
+                let usage = if feedback {
+                    DeviceBufferUsage::UNIFORM | DeviceBufferUsage::COPY_DST
+                } else {
+                    DeviceBufferUsage::UNIFORM
+                };
+
+                let mut buffer = device.create_buffer(true, size, usage)?;
 
                 if let Some(init) = resource.init.as_deref() {
                     buffer.get_mapped_range(size).copy_from_slice(init);
@@ -96,6 +368,7 @@ pub async fn run(
                 buffer.unmap();
 
                 buffer_sets.push(BufferSet::Uniform {
+                    group: resource.group,
                     binding: resource.binding,
                     size,
                     buffer,
@@ -104,74 +377,270 @@ pub async fn run(
         }
     }
 
-    let bind_group_entries = buffer_sets
+    // Any groups below the highest one that aren't used by the shader still need an empty bind
+    // group, since the pipeline layout includes them
+    let group_count = buffer_sets
         .iter()
-        .map(|buffers| match buffers {
-            BufferSet::Storage {
-                binding,
-                size,
-                storage,
-                ..
-            } => BindGroupEntry {
-                binding: *binding,
-                buffer: storage,
-                size: *size,
-            },
-            BufferSet::Uniform {
-                binding,
-                size,
-                buffer,
-            } => BindGroupEntry {
-                binding: *binding,
-                buffer,
-                size: *size,
-            },
+        .map(|it| it.group() + 1)
+        .max()
+        .unwrap_or(0);
+
+    let bind_groups = (0..group_count)
+        .map(|group| {
+            let entries = buffer_sets
+                .iter()
+                .filter(|buffers| buffers.group() == group)
+                .map(BufferSet::entry)
+                .collect::<Vec<_>>();
+
+            device.create_bind_group(&pipeline.get_bind_group_layout(group), &entries)
         })
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, _>>()?;
 
-    let bind_group =
-        device.create_bind_group(&pipeline.get_bind_group_layout(0), &bind_group_entries);
+    let storage_buffers = buffer_sets.iter().filter_map(|it| match it {
+        BufferSet::Storage { storage, size, .. } => Some((storage, *size)),
+        BufferSet::Uniform { .. } => None,
+    });
 
-    let encoder = device.create_command_encoder();
+    let uniform_buffers = buffer_sets.iter().filter_map(|it| match it {
+        BufferSet::Uniform { buffer, size, .. } => Some((buffer, *size)),
+        BufferSet::Storage { .. } => None,
+    });
 
-    {
-        let compute_pass = encoder.begin_compute_pass();
-        compute_pass.set_pipeline(&pipeline);
-        compute_pass.set_bind_group(0, &bind_group);
-        compute_pass.dispatch(1, 1, 1);
+    let feedback_pairs = storage_buffers.zip(uniform_buffers).collect::<Vec<_>>();
+    let dispatches = meta.dispatches.max(1);
+
+    // Each dispatch is submitted separately, so that the driver has to synchronise between them
+    for i in 0..dispatches {
+        let encoder = device.create_command_encoder()?;
+
+        if i > 0 {
+            for ((storage, storage_size), (uniform, uniform_size)) in &feedback_pairs {
+                encoder.copy_buffer_to_buffer(storage, uniform, *storage_size.min(uniform_size));
+            }
+        }
+
+        record_dispatch(&encoder, pipeline, &bind_groups);
+
+        if i == dispatches - 1 {
+            for buffers in &buffer_sets {
+                if let BufferSet::Storage {
+                    storage,
+                    read,
+                    size,
+                    ..
+                } = buffers
+                {
+                    encoder.copy_buffer_to_buffer(storage, read, *size);
+                }
+            }
+        }
+
+        let commands = encoder.finish();
+
+        queue.submit(&commands);
+
+        // Errors from command submission aren't captured by an error scope, so they are only
+        // reported to the device's uncaptured error callback
+        device.check_errors()?;
     }
 
+    // The render pipeline reads the uniform buffers as they were for the last dispatch
+    let render_target = match (&meta.render, render_pipeline) {
+        (Some(render), Some(pipeline)) => {
+            Some(draw(device, queue, pipeline, render, &buffer_sets)?)
+        }
+        _ => None,
+    };
+
+    let mut results = vec![];
     for buffers in &buffer_sets {
-        if let BufferSet::Storage {
-            storage,
-            read,
-            size,
-            ..
-        } = buffers
-        {
-            encoder.copy_buffer_to_buffer(storage, read, *size);
+        if let BufferSet::Storage { read, size, .. } = buffers {
+            results.push(read_buffer(device, read, *size)?);
         }
     }
 
+    if let (Some(render), Some((read, size))) = (&meta.render, &render_target) {
+        let (row_size, bytes_per_row) = crate::render_target_row_layout(render.format);
+        let bytes = read_buffer(device, read, *size)?;
+        results.push(crate::strip_row_padding(&bytes, row_size, bytes_per_row));
+    }
+
+    device.check_errors()?;
+
+    let dispatch_time = match timing {
+        Some(timing) => time_dispatches(device, queue, pipeline, &bind_groups, timing)?,
+        None => None,
+    };
+
+    Ok((results, dispatch_time))
+}
+
+fn record_dispatch(
+    encoder: &CommandEncoder,
+    pipeline: &ComputePipeline,
+    bind_groups: &[BindGroup],
+) {
+    let compute_pass = encoder.begin_compute_pass();
+    compute_pass.set_pipeline(pipeline);
+    for (group, bind_group) in bind_groups.iter().enumerate() {
+        compute_pass.set_bind_group(group as u32, bind_group);
+    }
+    compute_pass.dispatch(1, 1, 1);
+}
+
+/// Submits the warm-up and timed dispatches one at a time, and returns the median time taken by
+/// the timed ones.
+///
+/// The outputs have already been read by this point, so it doesn't matter what these dispatches
+/// write to the storage buffers.
+fn time_dispatches(
+    device: &Device,
+    queue: &DeviceQueue,
+    pipeline: &ComputePipeline,
+    bind_groups: &[BindGroup],
+    timing: DispatchTiming,
+) -> color_eyre::Result<Option<Duration>> {
+    // Dawn has no way to wait for the queue directly, but a mapping only completes once all of
+    // the work submitted before it has finished
+    let fence = device.create_buffer(
+        false,
+        4,
+        DeviceBufferUsage::COPY_DST | DeviceBufferUsage::MAP_READ,
+    )?;
+    let mut times = vec![];
+
+    for i in 0..timing.warmup + timing.repetitions {
+        let encoder = device.create_command_encoder()?;
+        record_dispatch(&encoder, pipeline, bind_groups);
+        let commands = encoder.finish();
+
+        let start = Instant::now();
+        queue.submit(&commands);
+
+        let mut rx = fence.map_async(DeviceBufferMapMode::READ, 4);
+        loop {
+            match rx.try_recv()? {
+                Some(result) => break result?,
+                // Polled without sleeping, since the dispatch may well finish in less time than
+                // a sleep would take
+                None => device.tick(),
+            }
+        }
+
+        if i >= timing.warmup {
+            times.push(start.elapsed());
+        }
+
+        fence.unmap();
+        device.check_errors()?;
+    }
+
+    Ok(crate::median_time(times))
+}
+
+/// Draws a triangle with the render pipeline, and copies the render target into a new buffer,
+/// which is returned along with its size.
+///
+/// The triangle is drawn twice, so that the second draw sees the depth and stencil values written
+/// by the first.
+fn draw(
+    device: &Device,
+    queue: &DeviceQueue,
+    pipeline: &RenderPipeline,
+    render: &RenderPipelineDescription,
+    buffer_sets: &[BufferSet],
+) -> color_eyre::Result<(DeviceBuffer, usize)> {
+    let target =
+        device.create_render_target(RENDER_TARGET_SIZE, render_target_format(render.format))?;
+
+    let (_, bytes_per_row) = crate::render_target_row_layout(render.format);
+    let size = (bytes_per_row * RENDER_TARGET_SIZE) as usize;
+    let read = device.create_buffer(
+        false,
+        size,
+        DeviceBufferUsage::COPY_DST | DeviceBufferUsage::MAP_READ,
+    )?;
+
+    let used = buffer_sets
+        .iter()
+        .filter(|buffers| {
+            render
+                .bindings
+                .contains(&(buffers.group(), buffers.binding()))
+        })
+        .collect::<Vec<_>>();
+
+    let group_count = used.iter().map(|it| it.group() + 1).max().unwrap_or(0);
+
+    let bind_groups = (0..group_count)
+        .map(|group| {
+            let entries = used
+                .iter()
+                .filter(|buffers| buffers.group() == group)
+                .map(|buffers| buffers.entry())
+                .collect::<Vec<_>>();
+
+            device.create_bind_group(&pipeline.get_bind_group_layout(group), &entries)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let depth_stencil = match &render.state.depth_stencil {
+        Some(state) => {
+            let texture = device.create_depth_stencil_target(RENDER_TARGET_SIZE)?;
+            let view = texture.create_view();
+            Some((texture, view, state))
+        }
+        None => None,
+    };
+
+    let view = target.create_view();
+    let encoder = device.create_command_encoder()?;
+
+    {
+        let attachment = depth_stencil
+            .as_ref()
+            .map(|(_, view, state)| DepthStencilAttachment {
+                view,
+                depth_clear: state.depth_clear,
+                stencil_clear: state.stencil_clear,
+            });
+
+        let render_pass = encoder.begin_render_pass(&view, attachment.as_ref());
+        render_pass.set_pipeline(pipeline);
+        for (group, bind_group) in bind_groups.iter().enumerate() {
+            render_pass.set_bind_group(group as u32, bind_group);
+        }
+        if let Some((_, _, state)) = &depth_stencil {
+            render_pass.set_stencil_reference(state.stencil_reference);
+        }
+        render_pass.draw(3, 2);
+    }
+
+    encoder.copy_texture_to_buffer(&target, &read, bytes_per_row);
+
     let commands = encoder.finish();
 
     queue.submit(&commands);
+    device.check_errors()?;
 
-    let mut results = vec![];
-    for buffers in &buffer_sets {
-        if let BufferSet::Storage { read, size, .. } = buffers {
-            let mut rx = read.map_async(DeviceBufferMapMode::READ, *size);
+    Ok((read, size))
+}
+
+/// Maps a buffer for reading, and returns a copy of its contents.
+fn read_buffer(device: &Device, read: &DeviceBuffer, size: usize) -> color_eyre::Result<Vec<u8>> {
+    let mut rx = read.map_async(DeviceBufferMapMode::READ, size);
 
-            while rx.try_recv().unwrap().is_none() {
+    loop {
+        match rx.try_recv()? {
+            Some(result) => break result?,
+            None => {
                 device.tick();
+                device.check_errors()?;
                 std::thread::sleep(std::time::Duration::from_millis(16));
             }
-
-            let bytes = read.get_const_mapped_range(*size);
-
-            results.push(bytes.to_vec());
         }
     }
 
-    Ok(results)
+    Ok(read.get_const_mapped_range(size).to_vec())
 }
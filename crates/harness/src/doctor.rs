@@ -0,0 +1,420 @@
+//! Checks that the environment is set up for differential testing, for `harness doctor`.
+//!
+//! Each check prints a line starting with `ok`, `warning` or `error`, followed by a suggestion for
+//! fixing anything that isn't ok. Warnings are for things that only some campaigns need.
+
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::Parser;
+use eyre::eyre;
+use server_types::{Envelope, ListResponse, Request};
+use types::{BackendType, Config, DawnOptions, Implementation};
+
+#[derive(Parser)]
+pub struct Options {
+    /// Address of a remote harness server to check, which can be passed multiple times.
+    #[clap(long = "remote", action)]
+    pub remotes: Vec<String>,
+
+    /// Token to present to remote harness servers that were started with `--token`.
+    #[clap(long, action)]
+    pub token: Option<String>,
+}
+
+enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+#[derive(Default)]
+struct Report {
+    errors: usize,
+    warnings: usize,
+}
+
+impl Report {
+    fn check(&mut self, status: Status, message: impl AsRef<str>, suggestion: Option<&str>) {
+        let label = match status {
+            Status::Ok => "ok",
+            Status::Warning => {
+                self.warnings += 1;
+                "warning"
+            }
+            Status::Error => {
+                self.errors += 1;
+                "error"
+            }
+        };
+
+        println!("{label:<8}{}", message.as_ref());
+
+        if let (false, Some(suggestion)) = (matches!(status, Status::Ok), suggestion) {
+            println!("        -> {suggestion}");
+        }
+    }
+}
+
+pub fn run(options: Options) -> eyre::Result<()> {
+    let mut report = Report::default();
+    let configs = crate::query_configs();
+
+    check_headless(&mut report, &configs);
+    check_adapters(&mut report, &configs);
+    check_dawn(&mut report, &configs);
+    check_validation_layers(&mut report, &configs);
+
+    if cfg!(target_os = "windows") {
+        check_shader_compilers(&mut report);
+    }
+
+    if cfg!(target_os = "macos") {
+        check_metal(&mut report, &configs);
+    }
+
+    for remote in &options.remotes {
+        check_remote(&mut report, remote, options.token.as_deref());
+    }
+
+    println!();
+    println!("{} error(s), {} warning(s)", report.errors, report.warnings);
+
+    if report.errors > 0 {
+        return Err(eyre!("{} check(s) failed", report.errors));
+    }
+
+    Ok(())
+}
+
+fn check_headless(report: &mut Report, configs: &[Config]) {
+    if !crate::is_headless() {
+        if crate::has_display() {
+            report.check(Status::Ok, "running with a display server", None);
+        } else {
+            report.check(
+                Status::Warning,
+                "no display server (neither DISPLAY nor WAYLAND_DISPLAY is set)",
+                Some("hardware adapters may not work without one, set WGSLSMITH_HEADLESS=1 to only use software adapters by default"),
+            );
+        }
+        return;
+    }
+
+    let hardware = configs
+        .iter()
+        .filter(|config| !config.is_software() && config.id.backend != BackendType::Null)
+        .map(|config| config.id.to_string())
+        .collect::<Vec<_>>();
+
+    if !configs.iter().any(|config| config.is_software()) {
+        report.check(
+            Status::Error,
+            "running headless, but no software adapters are available",
+            Some("install swiftshader or mesa's lavapipe, or set WGSLSMITH_HEADLESS=0 to use hardware adapters"),
+        );
+    } else if !hardware.is_empty() {
+        report.check(
+            Status::Warning,
+            format!(
+                "running headless, so hardware adapters are ignored by default: {}",
+                hardware.join(", ")
+            ),
+            Some("set WGSLSMITH_HEADLESS=0 if the container has access to a GPU"),
+        );
+    } else {
+        report.check(
+            Status::Ok,
+            "running headless, only software adapters will be used by default",
+            None,
+        );
+    }
+}
+
+fn check_adapters(report: &mut Report, configs: &[Config]) {
+    for imp in [Implementation::Wgpu, Implementation::Dawn] {
+        let adapters = configs
+            .iter()
+            .filter(|config| {
+                config.id.implementation == imp && config.id.backend != BackendType::Null
+            })
+            .map(|config| format!("{} ({})", config.id, config.adapter_name))
+            .collect::<Vec<_>>();
+
+        let name = match imp {
+            Implementation::Dawn => "dawn",
+            Implementation::Wgpu => "wgpu",
+        };
+
+        if adapters.is_empty() {
+            report.check(
+                Status::Error,
+                format!("no {name} adapters found"),
+                Some("install a vulkan driver (or swiftshader) and check that `vulkaninfo` lists a device"),
+            );
+        } else {
+            report.check(
+                Status::Ok,
+                format!("{name} adapters: {}", adapters.join(", ")),
+                None,
+            );
+        }
+    }
+
+    let defaults = crate::default_configs(&DawnOptions::default());
+    if defaults.is_empty() {
+        report.check(
+            Status::Error,
+            "no default configurations",
+            Some("pass configurations explicitly with `-c`, see `harness list`"),
+        );
+    } else if defaults.len() < 2 {
+        report.check(
+            Status::Warning,
+            format!("only one default configuration ({})", defaults[0]),
+            Some("differential testing needs at least two configurations to compare"),
+        );
+    } else {
+        let defaults = defaults.iter().map(|it| it.to_string()).collect::<Vec<_>>();
+        report.check(
+            Status::Ok,
+            format!("default configurations: {}", defaults.join(", ")),
+            None,
+        );
+    }
+}
+
+fn check_dawn(report: &mut Report, configs: &[Config]) {
+    let backends = [
+        (cfg!(feature = "dawn-vulkan"), "vulkan"),
+        (cfg!(feature = "dawn-d3d12"), "d3d12"),
+        (cfg!(feature = "dawn-metal"), "metal"),
+        (cfg!(feature = "dawn-null"), "null"),
+    ];
+
+    let enabled = backends
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>();
+
+    if enabled.is_empty() {
+        return report.check(
+            Status::Error,
+            "dawn was built without any backends",
+            Some("rebuild the harness with `./build.py harness --dawn-backends <backends>`"),
+        );
+    }
+
+    let x11 = if cfg!(feature = "dawn-x11") {
+        "with X11"
+    } else {
+        "without X11"
+    };
+
+    report.check(
+        Status::Ok,
+        format!("dawn built with backends: {} ({x11})", enabled.join(", ")),
+        None,
+    );
+
+    // The null backend is always available if it was built, so if it's missing then dawn failed
+    // to initialise at all
+    if cfg!(feature = "dawn-null")
+        && !configs.iter().any(|config| {
+            config.id.implementation == Implementation::Dawn
+                && config.id.backend == BackendType::Null
+        })
+    {
+        report.check(
+            Status::Error,
+            "dawn's null backend is enabled but no null adapter was found",
+            Some("check that the dawn libraries in build/dawn match the dawn submodule, and run with RUST_LOG=debug for details"),
+        );
+    }
+}
+
+fn check_validation_layers(report: &mut Report, configs: &[Config]) {
+    if !configs
+        .iter()
+        .any(|config| config.id.backend == BackendType::Vulkan)
+    {
+        return;
+    }
+
+    if find_vulkan_layer("VK_LAYER_KHRONOS_validation").is_some() {
+        report.check(Status::Ok, "vulkan validation layers found", None);
+    } else {
+        report.check(
+            Status::Warning,
+            "vulkan validation layers not found",
+            Some("install the vulkan SDK (or e.g. `vulkan-validationlayers` on debian) to debug driver crashes and invalid SPIR-V"),
+        );
+    }
+}
+
+/// Searches the directories that the vulkan loader reads explicit layer manifests from.
+///
+/// This doesn't cover the registry on Windows, where layers installed by the SDK are found through
+/// `VULKAN_SDK` instead.
+fn find_vulkan_layer(name: &str) -> Option<PathBuf> {
+    let mut dirs = vec![];
+
+    for var in ["VK_LAYER_PATH", "VK_ADD_LAYER_PATH"] {
+        if let Some(paths) = std::env::var_os(var) {
+            dirs.extend(std::env::split_paths(&paths));
+        }
+    }
+
+    if let Some(sdk) = std::env::var_os("VULKAN_SDK") {
+        let sdk = PathBuf::from(sdk);
+        dirs.push(sdk.join("Bin"));
+        dirs.push(sdk.join("share/vulkan/explicit_layer.d"));
+        dirs.push(sdk.join("etc/vulkan/explicit_layer.d"));
+    }
+
+    if let Some(home) = std::env::var_os("HOME") {
+        dirs.push(Path::new(&home).join(".local/share/vulkan/explicit_layer.d"));
+    }
+
+    for dir in [
+        "/usr/local/share/vulkan/explicit_layer.d",
+        "/usr/share/vulkan/explicit_layer.d",
+        "/usr/local/etc/vulkan/explicit_layer.d",
+        "/etc/vulkan/explicit_layer.d",
+    ] {
+        dirs.push(PathBuf::from(dir));
+    }
+
+    dirs.into_iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |it| it == "json"))
+        .find(|path| std::fs::read_to_string(path).map_or(false, |json| json.contains(name)))
+}
+
+/// Checks for the DLLs that dawn loads at runtime to compile HLSL.
+fn check_shader_compilers(report: &mut Report) {
+    if find_dll("d3dcompiler_47.dll").is_some() {
+        report.check(Status::Ok, "FXC (d3dcompiler_47.dll) found", None);
+    } else {
+        report.check(
+            Status::Error,
+            "FXC (d3dcompiler_47.dll) not found",
+            Some("d3dcompiler_47.dll is part of windows, copy it into System32 or next to the harness"),
+        );
+    }
+
+    match (find_dll("dxcompiler.dll"), find_dll("dxil.dll")) {
+        (Some(_), Some(_)) => {
+            report.check(Status::Ok, "DXC (dxcompiler.dll, dxil.dll) found", None)
+        }
+        (dxcompiler, _) => {
+            let missing = if dxcompiler.is_none() {
+                "dxcompiler.dll"
+            } else {
+                "dxil.dll"
+            };
+
+            report.check(
+                Status::Warning,
+                format!("DXC not found (missing {missing})"),
+                Some("copy dxcompiler.dll and dxil.dll from the windows SDK next to the harness to use `--dawn-toggle use_dxc`"),
+            );
+        }
+    }
+}
+
+/// Looks for a DLL in the places that windows searches when loading it: the directory of the
+/// executable, System32 and the PATH.
+fn find_dll(name: &str) -> Option<PathBuf> {
+    let mut dirs = vec![];
+
+    if let Ok(exe) = std::env::current_exe() {
+        dirs.extend(exe.parent().map(Path::to_owned));
+    }
+
+    if let Some(root) = std::env::var_os("SystemRoot") {
+        dirs.push(Path::new(&root).join("System32"));
+    }
+
+    if let Some(paths) = std::env::var_os("PATH") {
+        dirs.extend(std::env::split_paths(&paths));
+    }
+
+    dirs.into_iter()
+        .map(|dir| dir.join(name))
+        .find(|path| path.exists())
+}
+
+fn check_metal(report: &mut Report, configs: &[Config]) {
+    if configs
+        .iter()
+        .any(|config| config.id.backend == BackendType::Metal)
+    {
+        report.check(Status::Ok, "metal device found", None);
+    } else {
+        report.check(
+            Status::Error,
+            "no metal devices found",
+            Some("metal isn't available in most VMs, run the harness on the host instead"),
+        );
+    }
+
+    // The offline compiler is only needed for validating MSL, not for executing shaders
+    let compiler = Command::new("xcrun")
+        .args(["--find", "metal"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+
+    if compiler.map_or(false, |status| status.success()) {
+        report.check(Status::Ok, "metal compiler found", None);
+    } else {
+        report.check(
+            Status::Warning,
+            "metal compiler not found",
+            Some("install Xcode (not just the command line tools) to validate MSL with the metal compiler"),
+        );
+    }
+}
+
+fn check_remote(report: &mut Report, remote: &str, token: Option<&str>) {
+    match list_remote(remote, token) {
+        Ok(res) if res.configs.is_empty() => report.check(
+            Status::Error,
+            format!("remote {remote} has no configurations"),
+            Some("run `harness doctor` on the remote machine"),
+        ),
+        Ok(res) => report.check(
+            Status::Ok,
+            format!("remote {remote}: {} configuration(s)", res.configs.len()),
+            None,
+        ),
+        Err(e) => report.check(
+            Status::Error,
+            format!("remote {remote} is unreachable: {e:#}"),
+            Some("check that `harness serve` is running there with `-a 0.0.0.0:<port>`, since it only listens on localhost by default, and that `--token` matches if it requires one"),
+        ),
+    }
+}
+
+fn list_remote(remote: &str, token: Option<&str>) -> eyre::Result<ListResponse> {
+    let address = SocketAddr::from_str(remote)?;
+    let mut stream = TcpStream::connect_timeout(&address, Duration::from_secs(5))?;
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let envelope = Envelope {
+        token: token.map(str::to_owned),
+        request: Request::List,
+    };
+    bincode::encode_into_std_write(envelope, &mut stream, bincode::config::standard())?;
+    Ok(bincode::decode_from_std_read(
+        &mut stream,
+        bincode::config::standard(),
+    )?)
+}
@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::thread;
+use std::time::Duration;
+
+use buffer_check::{BufferTolerance, Tolerances};
+use color_eyre::eyre::{self, eyre};
+use frontend::{ExecutionError, ExecutionEvent};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use threadpool::ThreadPool;
+use tiny_http::{Header, Method, Request, Response, Server};
+use types::{BoundsChecks, ConfigId, DawnOptions, DawnToggle};
+
+use crate::HarnessHost;
+
+/// Maximum size of a request body, which matches the frame size limit of the validation server.
+const MAX_BODY_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Deserialize)]
+struct RunBody {
+    shader: String,
+    #[serde(default)]
+    input_data: HashMap<String, Vec<u8>>,
+    #[serde(default)]
+    configs: Vec<String>,
+    /// Dawn backend to use when selecting default configurations.
+    dawn_backend: Option<String>,
+    /// Dawn toggles, in the same format as `--dawn-toggle`.
+    #[serde(default)]
+    dawn_toggles: Vec<String>,
+    /// Timeout in seconds for each execution.
+    timeout: Option<u64>,
+    /// Float comparison tolerances, in the same format as `--tolerance`.
+    #[serde(default)]
+    tolerances: Vec<String>,
+    /// Number of times to dispatch the entry point, as with `--dispatches`.
+    dispatches: Option<u32>,
+    /// Whether to check the outputs with a warm pipeline cache, as with `--cache-check`.
+    #[serde(default)]
+    cache_check: bool,
+    /// Number of pipelines to compile concurrently, as with `--concurrent-compiles`.
+    #[serde(default)]
+    concurrent_compiles: u32,
+    /// Bounds checking policy (`on` or `off`), as with `--bounds-checks`.
+    bounds_checks: Option<String>,
+}
+
+/// Starts an HTTP server on a background thread, which accepts JSON equivalents of the `list`
+/// and `run` commands.
+///
+/// If `token` is set, requests must present it in an `Authorization: Bearer <token>` header.
+pub fn spawn<Host: HarnessHost>(
+    address: &str,
+    pool: ThreadPool,
+    token: Option<String>,
+) -> eyre::Result<()> {
+    let server = Server::http(address).map_err(|e| eyre!(e))?;
+
+    println!("Serving http on {}", server.server_addr());
+
+    thread::spawn(move || {
+        for req in server.incoming_requests() {
+            let token = token.clone();
+            pool.execute(move || {
+                if let Err(e) = handle::<Host>(req, token.as_deref()) {
+                    eprintln!("failed to handle http request: {e}");
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+fn handle<Host: HarnessHost>(mut req: Request, token: Option<&str>) -> io::Result<()> {
+    let presented = req
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "));
+
+    if !common::auth::check_token(token, presented) {
+        return req.respond(Response::empty(401));
+    }
+
+    let body = match req.method() {
+        Method::Post => match read_body(&mut req)? {
+            Some(body) => body,
+            None => return req.respond(Response::empty(413)),
+        },
+        _ => vec![],
+    };
+
+    let res = match (req.method(), req.url()) {
+        (Method::Get, "/configs") => Ok(list()),
+        (Method::Post, "/run") => match serde_json::from_slice(&body) {
+            Ok(body) => run::<Host>(body),
+            Err(e) => Err((400, e.to_string())),
+        },
+        _ => return req.respond(Response::empty(404)),
+    };
+
+    match res {
+        Ok(json) => {
+            let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+            req.respond(Response::from_string(json.to_string()).with_header(header))
+        }
+        Err((status, message)) => {
+            req.respond(Response::from_string(message).with_status_code(status))
+        }
+    }
+}
+
+/// Reads the body of a request, returning `None` if it is larger than [`MAX_BODY_SIZE`].
+fn read_body(req: &mut Request) -> io::Result<Option<Vec<u8>>> {
+    if req.body_length().map_or(false, |len| len > MAX_BODY_SIZE) {
+        return Ok(None);
+    }
+
+    // The length isn't known in advance for chunked requests
+    let mut body = vec![];
+    req.as_reader()
+        .take(MAX_BODY_SIZE as u64 + 1)
+        .read_to_end(&mut body)?;
+
+    if body.len() > MAX_BODY_SIZE {
+        return Ok(None);
+    }
+
+    Ok(Some(body))
+}
+
+fn list() -> Value {
+    let configs = crate::query_configs()
+        .into_iter()
+        .map(|config| {
+            json!({
+                "id": config.id.to_string(),
+                "adapter_name": config.adapter_name,
+                "driver": config.driver,
+            })
+        })
+        .collect();
+
+    Value::Array(configs)
+}
+
+fn run<Host: HarnessHost>(body: RunBody) -> Result<Value, (u16, String)> {
+    let configs = body
+        .configs
+        .iter()
+        .map(|config| config.parse::<ConfigId>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| (400, e.to_owned()))?;
+
+    let dawn = DawnOptions {
+        backend: body
+            .dawn_backend
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .map_err(|e: &str| (400, e.to_owned()))?,
+        toggles: body
+            .dawn_toggles
+            .iter()
+            .map(|toggle| toggle.parse::<DawnToggle>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| (400, e.to_owned()))?,
+    };
+
+    let tolerances: Tolerances = body
+        .tolerances
+        .iter()
+        .map(|tolerance| tolerance.parse::<BufferTolerance>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| (400, e.to_owned()))?;
+
+    let bounds_checks = body
+        .bounds_checks
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(|e: &str| (400, e.to_owned()))?
+        .unwrap_or(BoundsChecks::On);
+
+    let (mut pipeline_desc, type_descs) = frontend::reflect_shader(&body.shader, body.input_data)
+        .map_err(|e| (400, e.to_string()))?;
+
+    pipeline_desc.dispatches = body.dispatches.unwrap_or(1);
+    pipeline_desc.cache_check = body.cache_check;
+    pipeline_desc.concurrent_compiles = body.concurrent_compiles;
+    pipeline_desc.bounds_checks = bounds_checks == BoundsChecks::On;
+
+    let mut current = None;
+    let mut compile_times = None;
+    let mut spirv_diagnostics = None;
+    let mut executions = vec![];
+    let mut results = vec![];
+    let mut is_fail = false;
+
+    let on_event = |event: ExecutionEvent| {
+        let config = match &event {
+            ExecutionEvent::UsingDefaultConfigs(_) => return Ok(()),
+            ExecutionEvent::Start(config) => {
+                current = Some(config.to_string());
+                return Ok(());
+            }
+            // Dispatches are never timed through the HTTP API
+            ExecutionEvent::DispatchTime(_) => return Ok(()),
+            ExecutionEvent::CompileTimes(times) => {
+                compile_times = Some(times.iter().map(Duration::as_secs_f64).collect::<Vec<_>>());
+                return Ok(());
+            }
+            ExecutionEvent::InvalidSpirv(diagnostics) => {
+                spirv_diagnostics = Some(diagnostics.clone());
+                return Ok(());
+            }
+            _ => current.take(),
+        };
+
+        results.push(match event {
+            ExecutionEvent::Success(buffers) => {
+                // Formatted as a string so that it is the same as in the cli output, and because
+                // JSON numbers can't represent all u64s
+                let signature = format!("{:016x}", buffer_check::signature(&buffers));
+                let result = json!({
+                    "config": config,
+                    "status": "success",
+                    "buffers": buffers,
+                    "signature": signature,
+                    "compile_times": compile_times.take(),
+                    "spirv_diagnostics": spirv_diagnostics.take(),
+                });
+                executions.push(buffers);
+                result
+            }
+            ExecutionEvent::Failure(stderr) => {
+                is_fail = true;
+                let stderr = String::from_utf8_lossy(&stderr);
+                json!({ "config": config, "status": "failure", "stderr": stderr })
+            }
+            ExecutionEvent::Skipped(reason) => {
+                json!({ "config": config, "status": "skipped", "reason": reason })
+            }
+            _ => json!({ "config": config, "status": "timeout" }),
+        });
+
+        Ok(())
+    };
+
+    crate::execute::<Host, _>(
+        &body.shader,
+        &pipeline_desc,
+        &configs,
+        &dawn,
+        body.timeout.filter(|it| *it > 0).map(Duration::from_secs),
+        on_event,
+    )
+    .map_err(|e| match e {
+        ExecutionError::NoDefaultConfigs => (
+            400,
+            "failed to find any suitable default configurations".to_owned(),
+        ),
+        e => (500, format!("{:?}", eyre!(e))),
+    })?;
+
+    let mismatches = if is_fail {
+        vec![]
+    } else {
+        buffer_check::compare_with_tolerance(
+            executions.iter(),
+            &pipeline_desc,
+            &type_descs,
+            &tolerances,
+        )
+    };
+
+    let result = if is_fail {
+        "failure"
+    } else if mismatches.is_empty() {
+        "ok"
+    } else {
+        "mismatch"
+    };
+
+    let signatures = executions
+        .iter()
+        .map(|it| buffer_check::signature(it))
+        .collect::<Vec<_>>();
+
+    let disagreement = buffer_check::disagreement_pattern(&signatures);
+
+    // Only the worst offenders are included, since there could be a lot of them
+    let mismatches = mismatches
+        .iter()
+        .take(10)
+        .map(|mismatch| mismatch.to_string())
+        .collect::<Vec<_>>();
+
+    Ok(json!({
+        "result": result,
+        "executions": results,
+        "mismatches": mismatches,
+        "disagreement": disagreement,
+    }))
+}
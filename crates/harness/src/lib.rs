@@ -1,17 +1,26 @@
 mod dawn;
+mod doctor;
+#[cfg(feature = "http")]
+mod http;
 mod server;
+#[cfg(feature = "spirv-val")]
+mod spirv;
 mod wgpu;
 
 pub mod cli;
 
+use std::path::Path;
 use std::process::{Command, Stdio};
+use std::sync::Once;
 use std::time::Duration;
 
+use eyre::eyre;
+
 use frontend::{ExecutionError, ExecutionEvent};
 use futures::executor::block_on;
 use process_control::{ChildExt, Control};
-use reflection::PipelineDescription;
-use types::{BackendType, Config, ConfigId, Implementation};
+use reflection::{PipelineDescription, RenderTargetFormat, RENDER_TARGET_SIZE};
+use types::{BackendType, Config, ConfigId, DawnOptions, Implementation};
 
 pub trait HarnessHost {
     fn exec_command() -> Command;
@@ -35,18 +44,68 @@ pub fn query_configs() -> Vec<Config> {
     configurations
 }
 
-pub fn default_configs() -> Vec<ConfigId> {
+/// Returns true if the harness is running in a container, where GPU drivers usually aren't
+/// available.
+///
+/// Only software adapters are used by default in headless environments. Set `WGSLSMITH_HEADLESS`
+/// to `1` or `0` to override the detection.
+pub fn is_headless() -> bool {
+    match std::env::var("WGSLSMITH_HEADLESS").as_deref() {
+        Ok("1" | "true") => return true,
+        Ok("0" | "false") => return false,
+        _ => {}
+    }
+
+    // A missing display server isn't enough on its own, since GPUs can still be used without one
+    // (e.g. over ssh), so it's only reported by `harness doctor`
+    cfg!(target_os = "linux")
+        && (Path::new("/.dockerenv").exists() || Path::new("/run/.containerenv").exists())
+}
+
+/// Returns true if there is a display server to connect to, which is only checked on Linux.
+pub fn has_display() -> bool {
+    !cfg!(target_os = "linux")
+        || std::env::var_os("DISPLAY").is_some()
+        || std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+pub fn default_configs(dawn: &DawnOptions) -> Vec<ConfigId> {
     let mut configs = vec![];
-    let available = query_configs();
+    let mut available = query_configs();
+
+    if is_headless() {
+        let hardware = available
+            .iter()
+            .filter(|config| !config.is_software() && config.id.backend != BackendType::Null)
+            .count();
+
+        if hardware > 0 {
+            static WARNING: Once = Once::new();
+            WARNING.call_once(|| {
+                eprintln!(
+                    "warning: ignoring {hardware} hardware adapter(s) since the harness is running \
+                     headless (set WGSLSMITH_HEADLESS=0 to use them)"
+                );
+            });
+        }
+
+        available.retain(|config| config.is_software());
+    }
+
+    let dawn_targets = match dawn.backend {
+        Some(backend) => vec![(Implementation::Dawn, backend)],
+        None => vec![
+            (Implementation::Dawn, BackendType::Dx12),
+            (Implementation::Dawn, BackendType::Metal),
+            (Implementation::Dawn, BackendType::Vulkan),
+        ],
+    };
 
-    let targets = [
-        (Implementation::Dawn, BackendType::Dx12),
-        (Implementation::Dawn, BackendType::Metal),
-        (Implementation::Dawn, BackendType::Vulkan),
+    let targets = dawn_targets.into_iter().chain([
         (Implementation::Wgpu, BackendType::Dx12),
         (Implementation::Wgpu, BackendType::Metal),
         (Implementation::Wgpu, BackendType::Vulkan),
-    ];
+    ]);
 
     for target in targets {
         if let Some(config) = available
@@ -64,29 +123,70 @@ pub fn default_configs() -> Vec<ConfigId> {
 struct ExecutionArgs<'a> {
     pub shader: &'a str,
     pub pipeline_desc: &'a PipelineDescription,
+    pub dawn: &'a DawnOptions,
 }
 
 #[derive(bincode::Decode)]
 struct ExecutionInput {
     pub shader: String,
     pub pipeline_desc: PipelineDescription,
+    pub dawn: DawnOptions,
 }
 
 #[derive(bincode::Decode, bincode::Encode)]
-struct ExecutionOutput {
+pub struct ExecutionOutput {
     pub buffers: Vec<Vec<u8>>,
+    /// Time taken to compile the pipeline for each pass, if the pipeline cache was checked.
+    pub compile_times: Vec<Duration>,
+    /// Median time taken by a dispatch of the pipeline, if dispatches were timed.
+    pub dispatch_time: Option<Duration>,
+    /// Diagnostics from validating the SPIR-V that the shader was translated to, if it's invalid.
+    pub spirv_diagnostics: Option<String>,
+}
+
+/// Result of compiling and executing the pipeline once.
+struct Pass {
+    buffers: Vec<Vec<u8>>,
+    compile_time: Duration,
+    /// Median time taken by a dispatch, if this pass was timed.
+    dispatch_time: Option<Duration>,
 }
 
 pub fn execute<Host: HarnessHost, E: FnMut(ExecutionEvent) -> Result<(), ExecutionError>>(
     shader: &str,
     pipeline_desc: &PipelineDescription,
     configs: &[ConfigId],
+    dawn: &DawnOptions,
+    timeout: Option<Duration>,
+    on_event: E,
+) -> Result<(), ExecutionError> {
+    execute_with_command(
+        Host::exec_command,
+        shader,
+        pipeline_desc,
+        configs,
+        dawn,
+        timeout,
+        on_event,
+    )
+}
+
+/// Executes the shader on each configuration in a subprocess started by `exec_command`, which must
+/// accept the configuration as an argument and speak the same protocol as the `exec` command.
+///
+/// Default configurations are chosen by this process rather than by the subprocess.
+pub fn execute_with_command<E: FnMut(ExecutionEvent) -> Result<(), ExecutionError>>(
+    exec_command: impl Fn() -> Command,
+    shader: &str,
+    pipeline_desc: &PipelineDescription,
+    configs: &[ConfigId],
+    dawn: &DawnOptions,
     timeout: Option<Duration>,
     mut on_event: E,
 ) -> Result<(), ExecutionError> {
     let default_configs;
     let configs = if configs.is_empty() {
-        default_configs = crate::default_configs();
+        default_configs = crate::default_configs(dawn);
 
         if default_configs.is_empty() {
             return Err(ExecutionError::NoDefaultConfigs);
@@ -102,7 +202,11 @@ pub fn execute<Host: HarnessHost, E: FnMut(ExecutionEvent) -> Result<(), Executi
     configs.iter().try_for_each(|config| {
         on_event(ExecutionEvent::Start(config.clone()))?;
 
-        let mut child = Host::exec_command()
+        if let Some(reason) = unsupported_reason(config, pipeline_desc) {
+            return on_event(ExecutionEvent::Skipped(reason));
+        }
+
+        let mut child = exec_command()
             .arg(config.to_string())
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
@@ -115,6 +219,7 @@ pub fn execute<Host: HarnessHost, E: FnMut(ExecutionEvent) -> Result<(), Executi
             ExecutionArgs {
                 shader,
                 pipeline_desc,
+                dawn,
             },
             &mut stdin,
             bincode::config::standard(),
@@ -133,6 +238,15 @@ pub fn execute<Host: HarnessHost, E: FnMut(ExecutionEvent) -> Result<(), Executi
         if output.status.success() {
             let (output, _): (ExecutionOutput, _) =
                 bincode::decode_from_slice(&output.stdout, bincode::config::standard())?;
+            if !output.compile_times.is_empty() {
+                on_event(ExecutionEvent::CompileTimes(output.compile_times))?;
+            }
+            if let Some(time) = output.dispatch_time {
+                on_event(ExecutionEvent::DispatchTime(time))?;
+            }
+            if let Some(diagnostics) = output.spirv_diagnostics {
+                on_event(ExecutionEvent::InvalidSpirv(diagnostics))?;
+            }
             on_event(ExecutionEvent::Success(output.buffers))
         } else {
             on_event(ExecutionEvent::Failure(output.stderr))
@@ -140,13 +254,136 @@ pub fn execute<Host: HarnessHost, E: FnMut(ExecutionEvent) -> Result<(), Executi
     })
 }
 
+/// Returns the reason that a configuration can't execute a pipeline, if it uses a feature which
+/// the configuration doesn't support.
+fn unsupported_reason(config: &ConfigId, pipeline_desc: &PipelineDescription) -> Option<String> {
+    if pipeline_desc.subgroups && !supports_subgroups(config) {
+        return Some("adapter doesn't support subgroups".to_owned());
+    }
+
+    let push_constants = pipeline_desc.push_constants.as_ref()?;
+
+    match config.implementation {
+        Implementation::Dawn => Some("dawn doesn't support push constants".to_owned()),
+        Implementation::Wgpu if !wgpu::supports_push_constants(config, push_constants.size) => {
+            Some(format!(
+                "adapter doesn't support {} bytes of push constants",
+                push_constants.size
+            ))
+        }
+        Implementation::Wgpu => None,
+    }
+}
+
+/// Returns true if the adapter for a configuration advertises support for subgroup operations.
+fn supports_subgroups(config: &ConfigId) -> bool {
+    match config.implementation {
+        Implementation::Dawn => dawn::supports_subgroups(config),
+        Implementation::Wgpu => wgpu::supports_subgroups(config),
+    }
+}
+
+/// Required alignment of the stride between rows when copying a texture into a buffer.
+const COPY_BYTES_PER_ROW_ALIGNMENT: u32 = 256;
+
+/// Returns the size in bytes of a row of the render target, and the stride between rows when it
+/// is copied into a buffer.
+fn render_target_row_layout(format: RenderTargetFormat) -> (u32, u32) {
+    let row_size = RENDER_TARGET_SIZE * format.texel_size();
+    let bytes_per_row = (row_size + COPY_BYTES_PER_ROW_ALIGNMENT - 1)
+        / COPY_BYTES_PER_ROW_ALIGNMENT
+        * COPY_BYTES_PER_ROW_ALIGNMENT;
+    (row_size, bytes_per_row)
+}
+
+/// Removes the padding from the end of each row of a texture that was copied into a buffer.
+fn strip_row_padding(bytes: &[u8], row_size: u32, bytes_per_row: u32) -> Vec<u8> {
+    bytes
+        .chunks(bytes_per_row as usize)
+        .flat_map(|row| &row[..row_size as usize])
+        .copied()
+        .collect()
+}
+
 pub fn execute_config(
     shader: &str,
     pipeline_desc: &PipelineDescription,
     config: &ConfigId,
-) -> eyre::Result<Vec<Vec<u8>>> {
+    dawn: &DawnOptions,
+) -> eyre::Result<ExecutionOutput> {
     match config.implementation {
-        Implementation::Dawn => block_on(dawn::run(shader, pipeline_desc, config)),
+        Implementation::Dawn => block_on(dawn::run(shader, pipeline_desc, config, dawn)),
         Implementation::Wgpu => block_on(wgpu::run(shader, pipeline_desc, config)),
     }
 }
+
+/// Combines the results of executing the pipeline with a cold pipeline cache, and then optionally
+/// again with it warm, in which case both executions must have produced identical outputs.
+fn check_cache_passes(mut passes: Vec<Pass>) -> eyre::Result<ExecutionOutput> {
+    let compile_times = passes.iter().map(|it| it.compile_time).collect::<Vec<_>>();
+
+    if let [cold, warm] = passes.as_slice() {
+        let differing = cold
+            .buffers
+            .iter()
+            .zip(&warm.buffers)
+            .enumerate()
+            .filter(|(_, (cold, warm))| cold != warm)
+            .map(|(index, _)| index.to_string())
+            .collect::<Vec<_>>();
+
+        if !differing.is_empty() {
+            return Err(eyre!(
+                "outputs differ between cold and warm pipeline cache in storage buffers {} (compile times: cold {:?}, warm {:?})",
+                differing.join(", "),
+                compile_times[0],
+                compile_times[1],
+            ));
+        }
+    }
+
+    let pass = passes.pop().unwrap();
+
+    Ok(ExecutionOutput {
+        buffers: pass.buffers,
+        // Compile times are only interesting when there is something to compare them with
+        compile_times: if compile_times.len() > 1 {
+            compile_times
+        } else {
+            vec![]
+        },
+        dispatch_time: pass.dispatch_time,
+        spirv_diagnostics: None,
+    })
+}
+
+/// Returns the median of some dispatch times, which is less affected by the occasional slow
+/// dispatch than the mean.
+fn median_time(mut times: Vec<Duration>) -> Option<Duration> {
+    times.sort();
+    times.get(times.len() / 2).copied()
+}
+
+/// Checks that the outputs of each pipeline compiled concurrently match the outputs from the
+/// single-threaded baseline execution.
+fn check_concurrent_outputs(baseline: &[Vec<u8>], outputs: &[Vec<Vec<u8>>]) -> eyre::Result<()> {
+    for (index, output) in outputs.iter().enumerate() {
+        let differing = baseline
+            .iter()
+            .zip(output)
+            .enumerate()
+            .filter(|(_, (baseline, output))| baseline != output)
+            .map(|(index, _)| index.to_string())
+            .collect::<Vec<_>>();
+
+        if !differing.is_empty() {
+            return Err(eyre!(
+                "outputs of concurrently compiled pipeline {index} (of {}) differ from the single-threaded baseline in storage buffers {}",
+                outputs.len(),
+                differing.join(", "),
+            ));
+        }
+    }
+
+    Ok(())
+}
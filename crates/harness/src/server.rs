@@ -4,7 +4,7 @@ use std::net::TcpListener;
 use clap::Parser;
 use color_eyre::eyre::{self, eyre};
 use frontend::{ExecutionError, ExecutionEvent};
-use server_types::{ListResponse, Request, RunError, RunMessage, RunRequest};
+use server_types::{Envelope, ListResponse, Request, RunError, RunMessage, RunRequest};
 use threadpool::ThreadPool;
 
 use crate::HarnessHost;
@@ -20,6 +20,17 @@ pub struct Options {
     /// Defaults to the number of available CPUs.
     #[clap(long, action)]
     parallelism: Option<usize>,
+
+    /// Address at which to also serve requests using HTTP and JSON.
+    #[cfg(feature = "http")]
+    #[clap(long, action)]
+    http: Option<String>,
+
+    /// Shared secret that clients must present before making requests.
+    ///
+    /// This should be set if the server is reachable by untrusted machines.
+    #[clap(long, action)]
+    token: Option<String>,
 }
 
 pub fn run<Host: HarnessHost>(options: Options) -> eyre::Result<()> {
@@ -30,23 +41,36 @@ pub fn run<Host: HarnessHost>(options: Options) -> eyre::Result<()> {
     let pool = ThreadPool::new(parallelism);
     println!("Using thread pool with {parallelism} threads");
 
+    #[cfg(feature = "http")]
+    if let Some(address) = &options.http {
+        crate::http::spawn::<Host>(address, pool.clone(), options.token.clone())?;
+    }
+
     let listener = TcpListener::bind(options.address).unwrap();
     let address = listener.local_addr().unwrap();
     println!("Server listening at {address}");
 
     for stream in listener.incoming() {
+        let token = options.token.clone();
         pool.execute(move || {
             let stream = stream.unwrap();
 
             let mut reader = BufReader::new(&stream);
 
-            let req =
-                bincode::decode_from_std_read(&mut reader, bincode::config::standard()).unwrap();
+            let Envelope {
+                token: presented,
+                request,
+            } = bincode::decode_from_std_read(&mut reader, bincode::config::standard()).unwrap();
+
+            if !common::auth::check_token(token.as_deref(), presented.as_deref()) {
+                eprintln!("rejected request from a client without a valid token");
+                return;
+            }
 
             let writer = BufWriter::new(&stream);
-            match req {
+            match request {
                 Request::List => handle_list_request(writer).unwrap(),
-                Request::Run(req) => handle_run_request::<Host, _>(req, writer).unwrap(),
+                Request::Run(req) => handle_run_request::<Host, _>(*req, writer).unwrap(),
             }
         });
     }
@@ -71,9 +95,13 @@ fn handle_run_request<Host: HarnessHost, W: io::Write>(
                 RunMessage::UsingDefaultConfigs(configs)
             }
             ExecutionEvent::Start(config) => RunMessage::ExecStart(config),
+            ExecutionEvent::CompileTimes(times) => RunMessage::ExecCompileTimes(times),
+            ExecutionEvent::DispatchTime(time) => RunMessage::ExecDispatchTime(time),
+            ExecutionEvent::InvalidSpirv(diagnostics) => RunMessage::ExecInvalidSpirv(diagnostics),
             ExecutionEvent::Success(buffers) => RunMessage::ExecSuccess(buffers),
             ExecutionEvent::Failure(stderr) => RunMessage::ExecFailure(stderr),
             ExecutionEvent::Timeout => RunMessage::ExecTimeout,
+            ExecutionEvent::Skipped(reason) => RunMessage::ExecSkipped(reason),
         };
         send(&mut writer, message)?;
         writer.flush()?;
@@ -84,6 +112,7 @@ fn handle_run_request<Host: HarnessHost, W: io::Write>(
         &req.shader,
         &req.pipeline_desc,
         &req.configs,
+        &req.dawn,
         req.timeout,
         on_event,
     )
@@ -0,0 +1,48 @@
+//! Validation of the SPIR-V that shaders are translated to on vulkan configurations.
+//!
+//! Drivers are free to do anything with invalid SPIR-V, so a crash or mismatch on a vulkan
+//! configuration may be a translation bug rather than a driver bug. The shader is translated with
+//! the same compiler as the implementation (naga for wgpu and tint for dawn) and checked with
+//! SPIRV-Tools, which is the library behind `spirv-val`, so that the two can be told apart.
+//!
+//! Neither wgpu nor dawn expose the SPIR-V that they generate, so the shader has to be translated
+//! again here. For wgpu it goes through the same preprocessing first, so that naga sees exactly
+//! the source that wgpu compiles.
+
+use std::panic::AssertUnwindSafe;
+
+use types::{BackendType, ConfigId, Implementation};
+
+/// Returns the validator's diagnostics if the SPIR-V that the shader is translated to for the
+/// configuration is invalid.
+///
+/// Nothing is returned for configurations that don't use SPIR-V, or if the translation fails,
+/// since the same failure will be reported when the shader is executed.
+pub fn validate(shader: &str, config: &ConfigId) -> Option<String> {
+    if config.backend != BackendType::Vulkan {
+        return None;
+    }
+
+    let words = match config.implementation {
+        Implementation::Dawn => tint::compile_shader_to_spirv(shader).ok()?,
+        Implementation::Wgpu => {
+            let shader = crate::wgpu::preprocess(shader);
+            std::panic::catch_unwind(AssertUnwindSafe(|| compile_naga(&shader))).ok()??
+        }
+    };
+
+    tint::validate_spirv(&words).err().map(|e| e.diagnostics)
+}
+
+fn compile_naga(shader: &str) -> Option<Vec<u32>> {
+    use naga::back::spv;
+    use naga::front::wgsl;
+    use naga::valid::{Capabilities, ValidationFlags, Validator};
+
+    let module = wgsl::parse_str(shader).ok()?;
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .ok()?;
+
+    spv::write_vec(&module, &info, &spv::Options::default(), None).ok()
+}
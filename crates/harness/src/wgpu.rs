@@ -1,15 +1,32 @@
 use std::borrow::Cow;
+use std::num::NonZeroU32;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use color_eyre::eyre::eyre;
 use color_eyre::Result;
-use reflection::{PipelineDescription, ResourceKind};
+use futures::executor::block_on;
+use reflection::{
+    DispatchTiming, PipelineDescription, RenderPipelineDescription, RenderTargetFormat,
+    ResourceKind, RENDER_TARGET_SIZE,
+};
 use wgpu::{
-    Backends, BindGroupDescriptor, BindGroupEntry, Buffer, BufferDescriptor, BufferUsages,
-    CommandEncoderDescriptor, ComputePassDescriptor, ComputePipelineDescriptor, DeviceDescriptor,
-    Instance, Limits, Maintain, MapMode, ShaderModuleDescriptor, ShaderSource,
+    Adapter, Backends, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferDescriptor, BufferUsages,
+    Color, ColorTargetState, ColorWrites, CommandEncoder, CommandEncoderDescriptor,
+    CompareFunction, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor,
+    DepthBiasState, DepthStencilState, Device, DeviceDescriptor, Extent3d, Features, FragmentState,
+    ImageCopyBuffer, ImageDataLayout, Instance, Limits, LoadOp, Maintain, MapMode,
+    MultisampleState, Operations, PipelineLayout, PipelineLayoutDescriptor, PrimitiveState,
+    PushConstantRange, Queue, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, ShaderModule,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, StencilFaceState, StencilOperation,
+    StencilState, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+    TextureViewDescriptor, VertexState,
 };
 
-use crate::ConfigId;
+use crate::{ConfigId, ExecutionOutput, Pass};
 
 pub fn get_adapters() -> Vec<types::Adapter> {
     Instance::new(Backends::all())
@@ -18,6 +35,8 @@ pub fn get_adapters() -> Vec<types::Adapter> {
             let info = adapter.get_info();
             Some(types::Adapter {
                 name: info.name,
+                // wgpu 0.12 doesn't report anything about the driver
+                driver: String::new(),
                 device_id: info.device,
                 backend: match info.backend {
                     wgpu::Backend::Empty => return None,
@@ -33,62 +52,195 @@ pub fn get_adapters() -> Vec<types::Adapter> {
         .collect()
 }
 
-pub async fn run(
-    shader: &str,
-    meta: &PipelineDescription,
-    config: &ConfigId,
-) -> Result<Vec<Vec<u8>>> {
+fn find_adapter(config: &ConfigId) -> Result<Adapter> {
     let backend = match config.backend {
+        crate::BackendType::Null => return Err(eyre!("wgpu does not support the null backend")),
         crate::BackendType::Dx12 => wgpu::Backend::Dx12,
         crate::BackendType::Metal => wgpu::Backend::Metal,
         crate::BackendType::Vulkan => wgpu::Backend::Vulkan,
     };
 
-    let instance = Instance::new(Backends::all());
-    let adapter = instance
+    Instance::new(Backends::all())
         .enumerate_adapters(Backends::all())
         .find(|adapter| {
             let info = adapter.get_info();
             info.device == config.device_id && info.backend == backend
         })
-        .ok_or_else(|| eyre!("no adapter found matching id: {config}"))?;
+        .ok_or_else(|| eyre!("no adapter found matching id: {config}"))
+}
+
+/// Returns true if the adapter for a configuration supports push constants of the given size.
+pub fn supports_push_constants(config: &ConfigId, size: u32) -> bool {
+    match find_adapter(config) {
+        Ok(adapter) => {
+            adapter.features().contains(Features::PUSH_CONSTANTS)
+                && adapter.limits().max_push_constant_size >= size
+        }
+        Err(_) => false,
+    }
+}
+
+/// Returns true if the adapter for a configuration supports subgroup operations.
+///
+/// wgpu 0.12 doesn't have a feature for subgroups, so no adapter supports them.
+pub fn supports_subgroups(_config: &ConfigId) -> bool {
+    false
+}
+
+pub async fn run(
+    shader: &str,
+    meta: &PipelineDescription,
+    config: &ConfigId,
+) -> Result<ExecutionOutput> {
+    if meta.subgroups && !supports_subgroups(config) {
+        return Err(eyre!("adapter doesn't support subgroups"));
+    }
+
+    let adapter = find_adapter(config)?;
 
     let device_descriptor = DeviceDescriptor {
+        features: if meta.push_constants.is_some() {
+            Features::PUSH_CONSTANTS
+        } else {
+            Features::empty()
+        },
         limits: Limits {
             // This is needed to support swiftshader
             max_storage_textures_per_shader_stage: 4,
+            max_push_constant_size: meta.push_constants.as_ref().map_or(0, |it| it.size),
             ..Default::default()
         },
         ..Default::default()
     };
 
     let (device, queue) = adapter.request_device(&device_descriptor, None).await?;
+    let (device, queue) = (Arc::new(device), Arc::new(queue));
 
+    let preprocessed = preprocess(shader);
+
+    // With the cache check enabled, the same pipeline is compiled a second time after the first
+    // execution, by which point any pipeline caches in the driver should be warm
+    let passes = if meta.cache_check { 2 } else { 1 };
+
+    let mut outputs = vec![];
+    for pass in 0..passes {
+        // Only the last pass is timed, since the dispatches are the same in each pass
+        let timing = meta.dispatch_timing.filter(|_| pass == passes - 1);
+        outputs.push(execute(&device, &queue, &preprocessed, meta, timing).await?);
+    }
+
+    if meta.concurrent_compiles > 1 {
+        let concurrent = execute_concurrently(&device, &queue, &preprocessed, meta)?;
+        crate::check_concurrent_outputs(&outputs[0].buffers, &concurrent)?;
+    }
+
+    crate::check_cache_passes(outputs)
+}
+
+/// Rewrites the shader into the syntax supported by the version of naga used by wgpu.
+pub fn preprocess(shader: &str) -> String {
     let preprocessor_opts = preprocessor::Options {
         concise_stage_attrs: true,
         module_scope_constants: false,
+        ..Default::default()
     };
 
-    let preprocessed = preprocessor::preprocess(preprocessor_opts, shader.to_owned());
-    let shader = device.create_shader_module(&ShaderModuleDescriptor {
+    preprocessor::preprocess(preprocessor_opts, shader.to_owned())
+}
+
+/// Compiles and executes the pipeline from multiple threads at once on the same device, returning
+/// the outputs from each thread.
+fn execute_concurrently(
+    device: &Arc<Device>,
+    queue: &Arc<Queue>,
+    shader: &str,
+    meta: &PipelineDescription,
+) -> Result<Vec<Vec<Vec<u8>>>> {
+    let count = meta.concurrent_compiles as usize;
+
+    // Every thread waits until all of them are ready before compiling, so that the compilations
+    // overlap as much as possible
+    let barrier = Arc::new(Barrier::new(count));
+
+    let threads = (0..count)
+        .map(|_| {
+            let device = device.clone();
+            let queue = queue.clone();
+            let shader = shader.to_owned();
+            let meta = meta.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                barrier.wait();
+                block_on(execute(&device, &queue, &shader, &meta, None))
+            })
+        })
+        .collect::<Vec<_>>();
+
+    threads
+        .into_iter()
+        .map(|thread| {
+            let pass = thread
+                .join()
+                .map_err(|_| eyre!("concurrent compilation thread panicked"))??;
+            Ok(pass.buffers)
+        })
+        .collect()
+}
+
+struct IOBuffer {
+    group: u32,
+    binding: u32,
+    size: u64,
+    buffer: Buffer,
+    is_storage: bool,
+}
+
+async fn execute(
+    device: &Device,
+    queue: &Queue,
+    shader: &str,
+    meta: &PipelineDescription,
+    timing: Option<DispatchTiming>,
+) -> Result<Pass> {
+    let start = Instant::now();
+
+    let desc = ShaderModuleDescriptor {
         label: None,
-        source: ShaderSource::Wgsl(Cow::Owned(preprocessed)),
-    });
+        source: ShaderSource::Wgsl(Cow::Borrowed(shader)),
+    };
+
+    let shader = if meta.bounds_checks {
+        device.create_shader_module(&desc)
+    } else {
+        // Safety: the caller is responsible for only disabling bounds checks for shaders that
+        // never access memory out of bounds
+        unsafe { device.create_shader_module_unchecked(&desc) }
+    };
+
+    // Derived layouts can't have push constants, so the layout is created explicitly for them
+    let layout = meta
+        .push_constants
+        .as_ref()
+        .map(|push_constants| create_pipeline_layout(device, meta, push_constants.size));
 
     let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-        entry_point: "main",
+        entry_point: &meta.entry_point,
         label: None,
         module: &shader,
-        layout: None,
+        layout: layout.as_ref(),
     });
 
+    let render_pipeline = meta
+        .render
+        .as_ref()
+        .map(|render| (render, create_render_pipeline(device, &shader, render)));
+
+    let compile_time = start.elapsed();
+
     let mut buffers = vec![];
 
-    struct IOBuffer {
-        binding: u32,
-        buffer: Buffer,
-        is_storage: bool,
-    }
+    // Buffers only need to be copied between if there are multiple dispatches
+    let feedback = meta.dispatches > 1;
 
     for resource in &meta.resources {
         let size = resource.size as usize;
@@ -96,13 +248,28 @@ pub async fn run(
             ResourceKind::StorageBuffer => {
                 let buffer = device.create_buffer(&BufferDescriptor {
                     label: None,
-                    usage: BufferUsages::STORAGE | BufferUsages::MAP_READ,
+                    usage: if feedback {
+                        BufferUsages::STORAGE | BufferUsages::MAP_READ | BufferUsages::COPY_SRC
+                    } else {
+                        BufferUsages::STORAGE | BufferUsages::MAP_READ
+                    },
                     size: size as u64,
-                    mapped_at_creation: false,
+                    mapped_at_creation: resource.init.is_some(),
                 });
 
+                // Storage buffers are zero initialised unless there is input data for them
+                if let Some(init) = resource.init.as_deref() {
+                    buffer
+                        .slice(..)
+                        .get_mapped_range_mut()
+                        .copy_from_slice(init);
+                    buffer.unmap();
+                }
+
                 buffers.push(IOBuffer {
+                    group: resource.group,
                     binding: resource.binding,
+                    size: size as u64,
                     buffer,
                     is_storage: true,
                 });
@@ -110,7 +277,11 @@ pub async fn run(
             ResourceKind::UniformBuffer => {
                 let buffer = device.create_buffer(&BufferDescriptor {
                     label: None,
-                    usage: BufferUsages::UNIFORM,
+                    usage: if feedback {
+                        BufferUsages::UNIFORM | BufferUsages::COPY_DST
+                    } else {
+                        BufferUsages::UNIFORM
+                    },
                     size: size as u64,
                     mapped_at_creation: true,
                 });
@@ -125,7 +296,9 @@ pub async fn run(
                 buffer.unmap();
 
                 buffers.push(IOBuffer {
+                    group: resource.group,
                     binding: resource.binding,
+                    size: size as u64,
                     buffer,
                     is_storage: false,
                 });
@@ -133,32 +306,53 @@ pub async fn run(
         }
     }
 
-    let bind_group_entries = buffers
-        .iter()
-        .map(|buffer| BindGroupEntry {
-            binding: buffer.binding,
-            resource: buffer.buffer.as_entire_binding(),
+    // Any groups below the highest one that aren't used by the shader still need an empty bind
+    // group, since the pipeline layout includes them
+    let group_count = buffers.iter().map(|it| it.group + 1).max().unwrap_or(0);
+    let bind_groups = (0..group_count)
+        .map(|group| {
+            let entries = buffers
+                .iter()
+                .filter(|buffer| buffer.group == group)
+                .map(|buffer| BindGroupEntry {
+                    binding: buffer.binding,
+                    resource: buffer.buffer.as_entire_binding(),
+                })
+                .collect::<Vec<_>>();
+
+            device.create_bind_group(&BindGroupDescriptor {
+                layout: &pipeline.get_bind_group_layout(group),
+                label: None,
+                entries: &entries,
+            })
         })
         .collect::<Vec<_>>();
 
-    let bind_group = device.create_bind_group(&BindGroupDescriptor {
-        layout: &pipeline.get_bind_group_layout(0),
-        label: None,
-        entries: &bind_group_entries,
-    });
+    let storage_buffers = buffers.iter().filter(|it| it.is_storage);
+    let uniform_buffers = buffers.iter().filter(|it| !it.is_storage);
+    let feedback_pairs = storage_buffers.zip(uniform_buffers).collect::<Vec<_>>();
 
-    let commands = {
+    // Each dispatch is submitted separately, so that the driver has to synchronise between them
+    for i in 0..meta.dispatches.max(1) {
         let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
-        {
-            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor::default());
-            pass.set_pipeline(&pipeline);
-            pass.set_bind_group(0, &bind_group, &[]);
-            pass.dispatch_workgroups(1, 1, 1);
+
+        if i > 0 {
+            for (storage, uniform) in &feedback_pairs {
+                let size = storage.size.min(uniform.size);
+                encoder.copy_buffer_to_buffer(&storage.buffer, 0, &uniform.buffer, 0, size);
+            }
         }
-        encoder.finish()
-    };
 
-    queue.submit(std::iter::once(commands));
+        record_dispatch(&mut encoder, &pipeline, &bind_groups, meta);
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    // The render pipeline reads the uniform buffers as they were for the last dispatch, and has to
+    // finish before the storage buffers are mapped in case it uses them
+    let rendered = match &render_pipeline {
+        Some((render, pipeline)) => Some(draw(device, queue, pipeline, render, &buffers).await?),
+        None => None,
+    };
 
     let mut results = vec![];
     for buffer in &buffers {
@@ -176,8 +370,354 @@ pub async fn run(
             let bytes = slice.get_mapped_range();
 
             results.push(bytes.to_vec());
+
+            // The buffer can't be used by any timed dispatches while it is mapped
+            drop(bytes);
+            buffer.buffer.unmap();
+        }
+    }
+
+    results.extend(rendered);
+
+    let dispatch_time = timing
+        .and_then(|timing| time_dispatches(device, queue, &pipeline, &bind_groups, meta, timing));
+
+    Ok(Pass {
+        buffers: results,
+        compile_time,
+        dispatch_time,
+    })
+}
+
+fn record_dispatch(
+    encoder: &mut CommandEncoder,
+    pipeline: &ComputePipeline,
+    bind_groups: &[BindGroup],
+    meta: &PipelineDescription,
+) {
+    let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor::default());
+    pass.set_pipeline(pipeline);
+    for (group, bind_group) in bind_groups.iter().enumerate() {
+        pass.set_bind_group(group as u32, bind_group, &[]);
+    }
+    if let Some(push_constants) = &meta.push_constants {
+        let zeroed = vec![0; push_constants.size as usize];
+        let data = push_constants.init.as_deref().unwrap_or(&zeroed);
+        pass.set_push_constants(0, data);
+    }
+    pass.dispatch_workgroups(1, 1, 1);
+}
+
+/// Submits the warm-up and timed dispatches one at a time, and returns the median time taken by
+/// the timed ones.
+///
+/// The outputs have already been read by this point, so it doesn't matter what these dispatches
+/// write to the storage buffers.
+fn time_dispatches(
+    device: &Device,
+    queue: &Queue,
+    pipeline: &ComputePipeline,
+    bind_groups: &[BindGroup],
+    meta: &PipelineDescription,
+    timing: DispatchTiming,
+) -> Option<Duration> {
+    let mut times = vec![];
+
+    for i in 0..timing.warmup + timing.repetitions {
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+        record_dispatch(&mut encoder, pipeline, bind_groups, meta);
+
+        let start = Instant::now();
+        queue.submit(std::iter::once(encoder.finish()));
+        device.poll(Maintain::Wait);
+
+        if i >= timing.warmup {
+            times.push(start.elapsed());
         }
     }
 
-    Ok(results)
+    crate::median_time(times)
+}
+
+/// Creates a layout for the compute pipeline with a bind group layout entry for each resource,
+/// and a push constant range of `push_constant_size` bytes.
+///
+/// Storage buffers are always bound as read-write, like in the programs from the generator.
+fn create_pipeline_layout(
+    device: &Device,
+    meta: &PipelineDescription,
+    push_constant_size: u32,
+) -> PipelineLayout {
+    let group_count = meta
+        .resources
+        .iter()
+        .map(|it| it.group + 1)
+        .max()
+        .unwrap_or(0);
+
+    let bind_group_layouts = (0..group_count)
+        .map(|group| {
+            let entries = meta
+                .resources
+                .iter()
+                .filter(|resource| resource.group == group)
+                .map(|resource| BindGroupLayoutEntry {
+                    binding: resource.binding,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: match resource.kind {
+                            ResourceKind::StorageBuffer => {
+                                BufferBindingType::Storage { read_only: false }
+                            }
+                            ResourceKind::UniformBuffer => BufferBindingType::Uniform,
+                        },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                })
+                .collect::<Vec<_>>();
+
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: None,
+                entries: &entries,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &bind_group_layouts.iter().collect::<Vec<_>>(),
+        push_constant_ranges: &[PushConstantRange {
+            stages: ShaderStages::COMPUTE,
+            range: 0..push_constant_size,
+        }],
+    })
+}
+
+/// Draws a triangle with the render pipeline, and returns the contents of the render target.
+///
+/// The triangle is drawn twice, so that the second draw sees the depth and stencil values written
+/// by the first.
+async fn draw(
+    device: &Device,
+    queue: &Queue,
+    pipeline: &RenderPipeline,
+    render: &RenderPipelineDescription,
+    buffers: &[IOBuffer],
+) -> Result<Vec<u8>> {
+    let size = Extent3d {
+        width: RENDER_TARGET_SIZE,
+        height: RENDER_TARGET_SIZE,
+        depth_or_array_layers: 1,
+    };
+
+    let target = device.create_texture(&TextureDescriptor {
+        label: None,
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: texture_format(render.format),
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+    });
+
+    let (row_size, bytes_per_row) = crate::render_target_row_layout(render.format);
+    let readback = device.create_buffer(&BufferDescriptor {
+        label: None,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        size: (bytes_per_row * RENDER_TARGET_SIZE) as u64,
+        mapped_at_creation: false,
+    });
+
+    let used = buffers
+        .iter()
+        .filter(|buffer| render.bindings.contains(&(buffer.group, buffer.binding)))
+        .collect::<Vec<_>>();
+
+    let group_count = used.iter().map(|it| it.group + 1).max().unwrap_or(0);
+    let bind_groups = (0..group_count)
+        .map(|group| {
+            let entries = used
+                .iter()
+                .filter(|buffer| buffer.group == group)
+                .map(|buffer| BindGroupEntry {
+                    binding: buffer.binding,
+                    resource: buffer.buffer.as_entire_binding(),
+                })
+                .collect::<Vec<_>>();
+
+            device.create_bind_group(&BindGroupDescriptor {
+                layout: &pipeline.get_bind_group_layout(group),
+                label: None,
+                entries: &entries,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let depth_stencil = render.state.depth_stencil.as_ref().map(|state| {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Depth24PlusStencil8,
+            usage: TextureUsages::RENDER_ATTACHMENT,
+        });
+
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        (texture, view, state)
+    });
+
+    let view = target.create_view(&TextureViewDescriptor::default());
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+
+    {
+        let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: None,
+            color_attachments: &[RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::TRANSPARENT),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: depth_stencil.as_ref().map(|(_, view, state)| {
+                RenderPassDepthStencilAttachment {
+                    view,
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(state.depth_clear),
+                        store: true,
+                    }),
+                    stencil_ops: Some(Operations {
+                        load: LoadOp::Clear(state.stencil_clear),
+                        store: true,
+                    }),
+                }
+            }),
+        });
+
+        pass.set_pipeline(pipeline);
+        for (group, bind_group) in bind_groups.iter().enumerate() {
+            pass.set_bind_group(group as u32, bind_group, &[]);
+        }
+        if let Some((_, _, state)) = &depth_stencil {
+            pass.set_stencil_reference(state.stencil_reference);
+        }
+        pass.draw(0..3, 0..2);
+    }
+
+    encoder.copy_texture_to_buffer(
+        target.as_image_copy(),
+        ImageCopyBuffer {
+            buffer: &readback,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        size,
+    );
+
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = readback.slice(..);
+    let (tx, rx) = futures::channel::oneshot::channel();
+
+    slice.map_async(MapMode::Read, move |res| {
+        tx.send(res).unwrap();
+    });
+
+    device.poll(Maintain::Wait);
+    rx.await??;
+
+    let bytes = slice.get_mapped_range();
+
+    Ok(crate::strip_row_padding(&bytes, row_size, bytes_per_row))
+}
+
+fn create_render_pipeline(
+    device: &Device,
+    shader: &ShaderModule,
+    render: &RenderPipelineDescription,
+) -> RenderPipeline {
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: None,
+        layout: None,
+        vertex: VertexState {
+            module: shader,
+            entry_point: &render.vertex_entry_point,
+            buffers: &[],
+        },
+        primitive: PrimitiveState::default(),
+        depth_stencil: render.state.depth_stencil.as_ref().map(|state| {
+            let face = StencilFaceState {
+                compare: compare_function(state.stencil_compare),
+                fail_op: stencil_operation(state.stencil_fail_op),
+                depth_fail_op: stencil_operation(state.depth_fail_op),
+                pass_op: stencil_operation(state.pass_op),
+            };
+
+            DepthStencilState {
+                format: TextureFormat::Depth24PlusStencil8,
+                depth_write_enabled: state.depth_write,
+                depth_compare: compare_function(state.depth_compare),
+                stencil: StencilState {
+                    front: face.clone(),
+                    back: face,
+                    read_mask: !0,
+                    write_mask: !0,
+                },
+                bias: DepthBiasState::default(),
+            }
+        }),
+        multisample: MultisampleState::default(),
+        fragment: Some(FragmentState {
+            module: shader,
+            entry_point: &render.fragment_entry_point,
+            targets: &[ColorTargetState {
+                format: texture_format(render.format),
+                blend: None,
+                write_mask: ColorWrites::from_bits_truncate(render.state.write_mask),
+            }],
+        }),
+        multiview: None,
+    })
+}
+
+fn texture_format(format: RenderTargetFormat) -> TextureFormat {
+    match format {
+        RenderTargetFormat::R32Uint => TextureFormat::R32Uint,
+        RenderTargetFormat::Rg32Uint => TextureFormat::Rg32Uint,
+        RenderTargetFormat::Rgba32Uint => TextureFormat::Rgba32Uint,
+    }
+}
+
+fn compare_function(function: reflection::CompareFunction) -> CompareFunction {
+    match function {
+        reflection::CompareFunction::Never => CompareFunction::Never,
+        reflection::CompareFunction::Less => CompareFunction::Less,
+        reflection::CompareFunction::Equal => CompareFunction::Equal,
+        reflection::CompareFunction::LessEqual => CompareFunction::LessEqual,
+        reflection::CompareFunction::Greater => CompareFunction::Greater,
+        reflection::CompareFunction::NotEqual => CompareFunction::NotEqual,
+        reflection::CompareFunction::GreaterEqual => CompareFunction::GreaterEqual,
+        reflection::CompareFunction::Always => CompareFunction::Always,
+    }
+}
+
+fn stencil_operation(op: reflection::StencilOperation) -> StencilOperation {
+    match op {
+        reflection::StencilOperation::Keep => StencilOperation::Keep,
+        reflection::StencilOperation::Zero => StencilOperation::Zero,
+        reflection::StencilOperation::Replace => StencilOperation::Replace,
+        reflection::StencilOperation::Invert => StencilOperation::Invert,
+        reflection::StencilOperation::IncrementClamp => StencilOperation::IncrementClamp,
+        reflection::StencilOperation::DecrementClamp => StencilOperation::DecrementClamp,
+        reflection::StencilOperation::IncrementWrap => StencilOperation::IncrementWrap,
+        reflection::StencilOperation::DecrementWrap => StencilOperation::DecrementWrap,
+    }
 }
@@ -0,0 +1,72 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use clap::Parser;
+use color_eyre::eyre;
+use tempfile::NamedTempFile;
+use types::server::{self, ServerOptions};
+use types::{Backend, Capabilities, ValidateResponse};
+
+#[derive(Parser)]
+pub struct Options {
+    #[clap(flatten)]
+    server: ServerOptions,
+
+    /// Metal language standard to compile shaders with.
+    #[clap(long, default_value = "osx-metal2.0")]
+    metal_std: String,
+}
+
+pub fn run() -> eyre::Result<()> {
+    let options = Options::parse();
+
+    if options.server.worker {
+        let metal_std = options.metal_std;
+        return Ok(server::run_worker(|backend, source| match backend {
+            Backend::Msl => validate_metal(source, &metal_std)
+                .unwrap_or_else(|e| ValidateResponse::Failure(e.to_string())),
+            backend => ValidateResponse::Failure(format!("unsupported backend: {backend:?}")),
+        })?);
+    }
+
+    let metal = Command::new("xcrun")
+        .args(["-sdk", "macosx", "--find", "metal"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+
+    let capabilities = Capabilities {
+        fxc: false,
+        dxc: false,
+        metal,
+        max_batch_size: options.server.max_batch_size,
+    };
+
+    Ok(server::run(options.server, capabilities)?)
+}
+
+fn validate_metal(metal: &str, metal_std: &str) -> io::Result<ValidateResponse> {
+    let mut file = NamedTempFile::new()?;
+    write!(file, "{metal}")?;
+    file.flush()?;
+
+    let output = Command::new("xcrun")
+        .args(["-sdk", "macosx", "metal"])
+        .args(["-x", "metal"])
+        .args(["-o", "/dev/null"])
+        .arg(format!("-std={metal_std}"))
+        .arg("-c")
+        .arg(file.path())
+        .stderr(Stdio::piped())
+        .output()?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+
+    if !output.status.success() {
+        return Ok(ValidateResponse::Failure(stderr));
+    }
+
+    Ok(ValidateResponse::Success)
+}
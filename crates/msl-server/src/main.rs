@@ -0,0 +1,13 @@
+#[cfg(target_os = "macos")]
+mod imp;
+
+#[cfg(not(target_os = "macos"))]
+mod imp {
+    pub fn run() -> color_eyre::Result<()> {
+        unimplemented!("msl-server can only be used on macOS");
+    }
+}
+
+fn main() -> color_eyre::Result<()> {
+    imp::run()
+}
@@ -6,7 +6,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         .expect("missing argument: path to shader");
 
     let source = std::fs::read_to_string(path)?;
-    let ast = parser::parse(&source);
+    let ast = parser::try_parse(&source)?;
 
     print!("{:#?}", ast);
 
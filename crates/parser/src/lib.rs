@@ -1,9 +1,11 @@
+use std::fmt::Display;
 use std::hash::Hash;
 use std::rc::Rc;
 
 use ast::types::{DataType, MemoryViewType, ScalarType};
 use ast::*;
 use peeking_take_while::PeekableExt;
+use pest::error::ErrorVariant;
 use pest::iterators::Pair;
 use pest::prec_climber::{Assoc, Operator, PrecClimber};
 use pest::Parser;
@@ -79,8 +81,58 @@ impl Environment {
     }
 }
 
+/// Error returned when the input is not valid WGSL, or uses a construct that wgslsmith doesn't
+/// support.
+#[derive(Debug)]
+pub struct ParseError(Box<pest::error::Error<Rule>>);
+
+impl ParseError {
+    fn new(span: pest::Span, message: impl Into<String>) -> ParseError {
+        ParseError(Box::new(pest::error::Error::new_from_span(
+            ErrorVariant::CustomError {
+                message: message.into(),
+            },
+            span,
+        )))
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to parse shader\n{}", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+type Result<T, E = ParseError> = std::result::Result<T, E>;
+
+/// Parses a value from the text of a pair, such as the number in an attribute, ignoring any
+/// integer suffix.
+fn parse_value<T: std::str::FromStr>(pair: &Pair<Rule>, what: &str) -> Result<T>
+where
+    T::Err: Display,
+{
+    let text = pair.as_str().trim_end_matches(['u', 'i']);
+    text.parse()
+        .map_err(|e| ParseError::new(pair.as_span(), format!("invalid {what} `{text}`: {e}")))
+}
+
+/// Parses a WGSL module, panicking if the input is not valid.
+///
+/// Use [`try_parse`] when the source comes from outside of wgslsmith.
 pub fn parse(input: &str) -> Module {
-    let pairs = WGSLParser::parse(Rule::translation_unit, input).unwrap();
+    match try_parse(input) {
+        Ok(module) => module,
+        Err(e) => panic!("{e}"),
+    }
+}
+
+/// Parses a WGSL module, returning an error with the location of the problem if the input is not
+/// valid or uses an unsupported construct.
+pub fn try_parse(input: &str) -> Result<Module> {
+    let pairs =
+        WGSLParser::parse(Rule::translation_unit, input).map_err(|e| ParseError(Box::new(e)))?;
     let pair = pairs.into_iter().next().unwrap();
     parse_translation_unit(pair, &mut Environment::new())
 }
@@ -88,57 +140,163 @@ pub fn parse(input: &str) -> Module {
 pub fn parse_fn(input: &str, env: &mut Environment) -> FnDecl {
     let pairs = WGSLParser::parse(Rule::function_decl, input).unwrap();
     let pair = pairs.into_iter().next().unwrap();
-    parse_function_decl(pair, env)
+    match parse_function_decl(pair, env) {
+        Ok(decl) => decl,
+        Err(e) => panic!("{e}"),
+    }
 }
 
-fn parse_translation_unit(pair: Pair<Rule>, env: &mut Environment) -> Module {
-    let decls = pair
-        .into_inner()
+fn parse_translation_unit(pair: Pair<Rule>, env: &mut Environment) -> Result<Module> {
+    let mut pairs = pair.into_inner().peekable();
+
+    let mut enables = vec![];
+    let mut diagnostics = vec![];
+
+    // Directives can be written in any order, but must all come before the declarations
+    for pair in pairs.by_ref().peeking_take_while(|pair| {
+        matches!(
+            pair.as_rule(),
+            Rule::enable_directive | Rule::diagnostic_directive
+        )
+    }) {
+        match pair.as_rule() {
+            Rule::enable_directive => {
+                enables.extend(pair.into_inner().map(|pair| pair.as_str().to_owned()))
+            }
+            _ => diagnostics.push(parse_diagnostic_control(pair.into_inner().next().unwrap())),
+        }
+    }
+
+    let decls = pairs
         .take_while(|pair| pair.as_rule() != Rule::EOI)
         .map(|pair| parse_global_decl(pair, env))
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>>>()?;
 
     let mut functions = vec![];
     let mut structs = vec![];
     let mut consts = vec![];
+    let mut const_asserts = vec![];
     let mut vars = vec![];
 
     for decl in decls {
         match decl {
             GlobalDecl::Const(decl) => consts.push(decl),
+            GlobalDecl::ConstAssert(stmt) => const_asserts.push(stmt),
             GlobalDecl::Var(decl) => vars.push(decl),
             GlobalDecl::Struct(decl) => structs.push(decl),
             GlobalDecl::Fn(decl) => functions.push(decl),
         }
     }
 
-    Module {
+    Ok(Module {
+        enables,
+        diagnostics,
         functions,
         structs,
         consts,
+        const_asserts,
         vars,
-    }
+    })
 }
 
 enum GlobalDecl {
     Const(GlobalConstDecl),
+    ConstAssert(ConstAssertStatement),
     Var(GlobalVarDecl),
     Struct(Rc<StructDecl>),
     Fn(FnDecl),
 }
 
-fn parse_global_decl(pair: Pair<Rule>, env: &mut Environment) -> GlobalDecl {
+fn parse_global_decl(pair: Pair<Rule>, env: &mut Environment) -> Result<GlobalDecl> {
     let pair = pair.into_inner().next().unwrap();
-    match pair.as_rule() {
-        Rule::global_constant_decl => GlobalDecl::Const(parse_global_const_decl(pair, env)),
-        Rule::global_variable_decl => GlobalDecl::Var(parse_global_variable_decl(pair, env)),
-        Rule::struct_decl => GlobalDecl::Struct(parse_struct_decl(pair, env)),
-        Rule::function_decl => GlobalDecl::Fn(parse_function_decl(pair, env)),
+    Ok(match pair.as_rule() {
+        Rule::const_assert_statement => {
+            GlobalDecl::ConstAssert(parse_const_assert_statement(pair, env)?)
+        }
+        Rule::global_constant_decl => GlobalDecl::Const(parse_global_const_decl(pair, env)?),
+        Rule::global_variable_decl => GlobalDecl::Var(parse_global_variable_decl(pair, env)?),
+        Rule::struct_decl => GlobalDecl::Struct(parse_struct_decl(pair, env)?),
+        Rule::function_decl => GlobalDecl::Fn(parse_function_decl(pair, env)?),
         _ => unreachable!(),
-    }
+    })
+}
+
+fn parse_diagnostic_control(pair: Pair<Rule>) -> DiagnosticControl {
+    let mut pairs = pair.into_inner();
+
+    let severity = match pairs.next().unwrap().as_str() {
+        "error" => Severity::Error,
+        "warning" => Severity::Warning,
+        "info" => Severity::Info,
+        "off" => Severity::Off,
+        _ => unreachable!(),
+    };
+
+    DiagnosticControl::new(severity, pairs.next().unwrap().as_str())
 }
 
-fn parse_global_const_decl(pair: Pair<Rule>, env: &mut Environment) -> GlobalConstDecl {
+fn parse_builtin_value(pair: Pair<Rule>) -> Result<BuiltinValue> {
+    Ok(match pair.as_str() {
+        "local_invocation_index" => BuiltinValue::LocalInvocationIndex,
+        "vertex_index" => BuiltinValue::VertexIndex,
+        "position" => BuiltinValue::Position,
+        "frag_depth" => BuiltinValue::FragDepth,
+        value => {
+            return Err(ParseError::new(
+                pair.as_span(),
+                format!("unsupported builtin value: {value}"),
+            ))
+        }
+    })
+}
+
+fn parse_interpolation<'a>(
+    span: pest::Span<'a>,
+    mut args: impl Iterator<Item = Pair<'a, Rule>>,
+) -> Result<Interpolation> {
+    let arg = args
+        .next()
+        .ok_or_else(|| ParseError::new(span, "interpolate attribute must have a type"))?;
+
+    let kind = match arg.as_str() {
+        "perspective" => InterpolationType::Perspective,
+        "linear" => InterpolationType::Linear,
+        "flat" => InterpolationType::Flat,
+        kind => {
+            return Err(ParseError::new(
+                arg.as_span(),
+                format!("invalid interpolation type: {kind}"),
+            ))
+        }
+    };
+
+    let sampling = args
+        .next()
+        .map(|arg| match arg.as_str() {
+            "center" => Ok(InterpolationSampling::Center),
+            "centroid" => Ok(InterpolationSampling::Centroid),
+            "sample" => Ok(InterpolationSampling::Sample),
+            sampling => Err(ParseError::new(
+                arg.as_span(),
+                format!("invalid interpolation sampling: {sampling}"),
+            )),
+        })
+        .transpose()?;
+
+    Ok(Interpolation { kind, sampling })
+}
+
+/// Returns the argument of an attribute which requires exactly one.
+fn attribute_arg<'a>(
+    span: pest::Span<'a>,
+    name: &str,
+    mut args: impl Iterator<Item = Pair<'a, Rule>>,
+) -> Result<Pair<'a, Rule>> {
+    args.next()
+        .ok_or_else(|| ParseError::new(span, format!("{name} attribute requires an argument")))
+}
+
+fn parse_global_const_decl(pair: Pair<Rule>, env: &mut Environment) -> Result<GlobalConstDecl> {
     let mut pairs = pair.into_inner().peekable();
 
     let name = pairs.next().unwrap().as_str().to_owned();
@@ -147,23 +305,24 @@ fn parse_global_const_decl(pair: Pair<Rule>, env: &mut Environment) -> GlobalCon
     if let Some(pair) = pairs.peek() {
         if pair.as_rule() == Rule::type_decl {
             let pair = pairs.next().unwrap();
-            data_type = Some(parse_type_decl(pair, env));
+            data_type = Some(parse_type_decl(pair, env)?);
         }
     }
 
-    let expr = parse_expression(pairs.next().unwrap(), env);
+    let expr = parse_expression(pairs.next().unwrap(), env)?;
     let data_type = data_type.unwrap_or_else(|| expr.data_type.clone());
 
     env.insert_var(name.clone(), data_type.clone());
 
-    GlobalConstDecl {
+    Ok(GlobalConstDecl {
         name,
         data_type,
         initializer: expr,
-    }
+    })
 }
 
-fn parse_global_variable_decl(pair: Pair<Rule>, env: &mut Environment) -> GlobalVarDecl {
+fn parse_global_variable_decl(pair: Pair<Rule>, env: &mut Environment) -> Result<GlobalVarDecl> {
+    let span = pair.as_span();
     let mut pairs = pair.into_inner().peekable();
 
     let attrs = pairs
@@ -171,17 +330,28 @@ fn parse_global_variable_decl(pair: Pair<Rule>, env: &mut Environment) -> Global
         .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
         .flat_map(|pair| {
             pair.into_inner().map(|pair| {
+                let span = pair.as_span();
                 let mut pairs = pair.into_inner();
                 let name = pairs.next().unwrap().as_str();
-                let arg = pairs.next().unwrap().as_str();
-                match name {
-                    "binding" => GlobalVarAttr::Binding(arg.parse().unwrap()),
-                    "group" => GlobalVarAttr::Group(arg.parse().unwrap()),
-                    _ => panic!("invalid global variable attribute: {}", name),
-                }
+                Ok(match name {
+                    "binding" => GlobalVarAttr::Binding(parse_value(
+                        &attribute_arg(span, name, pairs)?,
+                        "binding",
+                    )?),
+                    "group" => GlobalVarAttr::Group(parse_value(
+                        &attribute_arg(span, name, pairs)?,
+                        "group",
+                    )?),
+                    _ => {
+                        return Err(ParseError::new(
+                            span,
+                            format!("invalid global variable attribute: {name}"),
+                        ))
+                    }
+                })
             })
         })
-        .collect();
+        .collect::<Result<_>>()?;
 
     let mut qualifier = None;
 
@@ -209,7 +379,13 @@ fn parse_global_variable_decl(pair: Pair<Rule>, env: &mut Environment) -> Global
         }
     }
 
-    let qualifier = qualifier.expect("module scope var declaration must specify storage class");
+    let qualifier = qualifier.ok_or_else(|| {
+        ParseError::new(
+            span,
+            "module scope var declaration must specify storage class",
+        )
+    })?;
+
     let name = pairs.next().unwrap().as_str().to_owned();
     let mut data_type = None;
     let mut expr = None;
@@ -217,21 +393,25 @@ fn parse_global_variable_decl(pair: Pair<Rule>, env: &mut Environment) -> Global
     if let Some(pair) = pairs.peek() {
         if pair.as_rule() == Rule::type_decl {
             let pair = pairs.next().unwrap();
-            data_type = Some(parse_type_decl(pair, env));
+            data_type = Some(parse_type_decl(pair, env)?);
         }
     }
 
     if pairs.peek().is_some() {
         let pair = pairs.next().unwrap();
-        expr = Some(parse_expression(pair, env))
+        expr = Some(parse_expression(pair, env)?)
     }
 
-    let data_type = data_type.unwrap_or_else(|| {
-        expr.as_ref()
-            .expect("var declaration must have type or initializer")
-            .data_type
-            .clone()
-    });
+    let data_type = match (data_type, &expr) {
+        (Some(data_type), _) => data_type,
+        (None, Some(expr)) => expr.data_type.clone(),
+        (None, None) => {
+            return Err(ParseError::new(
+                span,
+                "var declaration must have type or initializer",
+            ))
+        }
+    };
 
     let mut ref_view = MemoryViewType::new(data_type.clone(), qualifier.storage_class);
     if let Some(access_mode) = qualifier.access_mode {
@@ -240,16 +420,16 @@ fn parse_global_variable_decl(pair: Pair<Rule>, env: &mut Environment) -> Global
 
     env.insert_var(name.clone(), DataType::Ref(ref_view));
 
-    GlobalVarDecl {
+    Ok(GlobalVarDecl {
         attrs,
         qualifier: Some(qualifier),
         name,
         data_type,
         initializer: expr,
-    }
+    })
 }
 
-fn parse_struct_decl(pair: Pair<Rule>, env: &mut Environment) -> Rc<StructDecl> {
+fn parse_struct_decl(pair: Pair<Rule>, env: &mut Environment) -> Result<Rc<StructDecl>> {
     let mut pairs = pair.into_inner();
     let name = pairs.next().unwrap().as_str().to_owned();
     let members = pairs
@@ -261,32 +441,50 @@ fn parse_struct_decl(pair: Pair<Rule>, env: &mut Environment) -> Rc<StructDecl>
                 .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
                 .flat_map(|pair| {
                     pair.into_inner().map(|pair| {
+                        let span = pair.as_span();
                         let mut pairs = pair.into_inner();
                         let name = pairs.next().unwrap().as_str();
-                        let arg = pairs.next().unwrap().as_str();
-                        match name {
-                            "align" => StructMemberAttr::Align(arg.parse().unwrap()),
-                            _ => panic!("invalid struct member attribute: {}", name),
-                        }
+                        Ok(match name {
+                            "align" => StructMemberAttr::Align(parse_value(
+                                &attribute_arg(span, name, pairs)?,
+                                "alignment",
+                            )?),
+                            "builtin" => StructMemberAttr::Builtin(parse_builtin_value(
+                                attribute_arg(span, name, pairs)?,
+                            )?),
+                            "location" => StructMemberAttr::Location(parse_value(
+                                &attribute_arg(span, name, pairs)?,
+                                "location",
+                            )?),
+                            "interpolate" => {
+                                StructMemberAttr::Interpolate(parse_interpolation(span, pairs)?)
+                            }
+                            _ => {
+                                return Err(ParseError::new(
+                                    span,
+                                    format!("invalid struct member attribute: {name}"),
+                                ))
+                            }
+                        })
                     })
                 })
-                .collect();
+                .collect::<Result<_>>()?;
 
             let name = pairs.next().unwrap().as_str().to_owned();
-            let data_type = parse_type_decl(pairs.next().unwrap(), env);
-            StructMember::new(attrs, name, data_type)
+            let data_type = parse_type_decl(pairs.next().unwrap(), env)?;
+            Ok(StructMember::new(attrs, name, data_type))
         })
-        .collect();
+        .collect::<Result<_>>()?;
 
     let decl = StructDecl::new(name.clone(), members);
 
     env.insert_struct(name, decl.clone());
     env.insert_func(decl.name.clone(), DataType::Struct(decl.clone()));
 
-    decl
+    Ok(decl)
 }
 
-fn parse_function_decl(pair: Pair<Rule>, env: &mut Environment) -> FnDecl {
+fn parse_function_decl(pair: Pair<Rule>, env: &mut Environment) -> Result<FnDecl> {
     let mut pairs = pair.into_inner().peekable();
 
     let attrs = pairs
@@ -294,55 +492,134 @@ fn parse_function_decl(pair: Pair<Rule>, env: &mut Environment) -> FnDecl {
         .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
         .flat_map(|pair| {
             pair.into_inner().map(|pair| {
+                let span = pair.as_span();
                 let mut pairs = pair.into_inner();
                 let name = pairs.next().unwrap().as_str();
-                match name {
+                Ok(match name {
                     "compute" => FnAttr::Stage(ShaderStage::Compute),
                     "vertex" => FnAttr::Stage(ShaderStage::Vertex),
                     "fragment" => FnAttr::Stage(ShaderStage::Fragment),
-                    "stage" => FnAttr::Stage(match pairs.next().unwrap().as_str() {
-                        "compute" => ShaderStage::Compute,
-                        "vertex" => ShaderStage::Vertex,
-                        "fragment" => ShaderStage::Fragment,
-                        _ => panic!("invalid argument for stage attr"),
-                    }),
-                    "workgroup_size" => FnAttr::WorkgroupSize(
-                        match parse_literal_expression(pairs.next().unwrap()).expr {
-                            Expr::Lit(Lit::I32(v)) => v.try_into().unwrap(),
-                            Expr::Lit(Lit::U32(v)) => v,
-                            _ => panic!("invalid argument for workgroup_size attr"),
-                        },
-                    ),
-                    _ => panic!("invalid function attribute: {}", name),
-                }
+                    "stage" => {
+                        let arg = attribute_arg(span, name, pairs)?;
+                        FnAttr::Stage(match arg.as_str() {
+                            "compute" => ShaderStage::Compute,
+                            "vertex" => ShaderStage::Vertex,
+                            "fragment" => ShaderStage::Fragment,
+                            _ => {
+                                return Err(ParseError::new(
+                                    arg.as_span(),
+                                    "invalid argument for stage attr",
+                                ))
+                            }
+                        })
+                    }
+                    "diagnostic" => FnAttr::Diagnostic(parse_diagnostic_control(attribute_arg(
+                        span, name, pairs,
+                    )?)),
+                    "workgroup_size" => {
+                        let arg = attribute_arg(span, name, pairs)?;
+                        let arg_span = arg.as_span();
+                        let size = match parse_literal_expression(arg)?.expr {
+                            Expr::Lit(Lit::I32(v)) => v.try_into().ok(),
+                            Expr::Lit(Lit::U32(v)) => Some(v),
+                            _ => None,
+                        };
+                        FnAttr::WorkgroupSize(size.ok_or_else(|| {
+                            ParseError::new(arg_span, "invalid argument for workgroup_size attr")
+                        })?)
+                    }
+                    _ => {
+                        return Err(ParseError::new(
+                            span,
+                            format!("invalid function attribute: {name}"),
+                        ))
+                    }
+                })
             })
         })
-        .collect();
+        .collect::<Result<_>>()?;
 
     let name = pairs.next().unwrap().as_str().to_owned();
     let inputs = pairs
         .by_ref()
         .peeking_take_while(|pair| pair.as_rule() == Rule::param)
         .map(|pair| {
-            let mut pairs = pair.into_inner();
+            let mut pairs = pair.into_inner().peekable();
+
+            let attrs = pairs
+                .by_ref()
+                .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
+                .flat_map(|pair| {
+                    pair.into_inner().map(|pair| {
+                        let span = pair.as_span();
+                        let mut pairs = pair.into_inner();
+                        let name = pairs.next().unwrap().as_str();
+                        Ok(match name {
+                            "builtin" => FnInputAttr::Builtin(parse_builtin_value(attribute_arg(
+                                span, name, pairs,
+                            )?)?),
+                            "location" => FnInputAttr::Location(parse_value(
+                                &attribute_arg(span, name, pairs)?,
+                                "location",
+                            )?),
+                            "interpolate" => {
+                                FnInputAttr::Interpolate(parse_interpolation(span, pairs)?)
+                            }
+                            _ => {
+                                return Err(ParseError::new(
+                                    span,
+                                    format!("invalid function parameter attribute: {name}"),
+                                ))
+                            }
+                        })
+                    })
+                })
+                .collect::<Result<_>>()?;
+
             let name = pairs.next().unwrap().as_str().to_owned();
-            let data_type = parse_type_decl(pairs.next().unwrap(), env);
-            FnInput {
-                attrs: vec![],
+            let data_type = parse_type_decl(pairs.next().unwrap(), env)?;
+            Ok(FnInput {
+                attrs,
                 name,
                 data_type,
-            }
+            })
         })
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>>>()?;
+
+    let output_attrs = pairs
+        .by_ref()
+        .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
+        .flat_map(|pair| {
+            pair.into_inner().map(|pair| {
+                let span = pair.as_span();
+                let mut pairs = pair.into_inner();
+                let name = pairs.next().unwrap().as_str();
+                let arg = attribute_arg(span, name, pairs)?;
+                Ok(match name {
+                    "builtin" => FnOutputAttr::Builtin(parse_builtin_value(arg)?),
+                    "location" => FnOutputAttr::Location(parse_value(&arg, "location")?),
+                    _ => {
+                        return Err(ParseError::new(
+                            span,
+                            format!("invalid function output attribute: {name}"),
+                        ))
+                    }
+                })
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
 
     let output = pairs
         .by_ref()
         .peeking_take_while(|pair| pair.as_rule() == Rule::type_decl)
-        .map(|pair| FnOutput {
-            attrs: vec![],
-            data_type: parse_type_decl(pair, env),
+        .next()
+        .map(|pair| {
+            Ok(FnOutput {
+                attrs: output_attrs,
+                data_type: parse_type_decl(pair, env)?,
+            })
         })
-        .next();
+        .transpose()?;
 
     if let Some(output) = &output {
         env.insert_func(name.clone(), output.data_type.clone());
@@ -353,59 +630,61 @@ fn parse_function_decl(pair: Pair<Rule>, env: &mut Environment) -> FnDecl {
         env.insert_var(param.name.clone(), param.data_type.clone());
     }
 
-    let body = parse_compound_statement(pairs.next().unwrap(), &env).into_compount_statement();
+    let body = parse_compound_statement(pairs.next().unwrap(), &env)?.into_compount_statement();
 
-    FnDecl {
+    Ok(FnDecl {
         attrs,
         name,
         inputs,
         output,
         body,
-    }
+    })
 }
 
-fn parse_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
+fn parse_statement(pair: Pair<Rule>, env: &mut Environment) -> Result<Statement> {
     let pair = if pair.as_rule() == Rule::statement {
         pair.into_inner().next().unwrap()
     } else {
         pair
     };
 
-    match pair.as_rule() {
-        Rule::let_statement => parse_let_statement(pair, env),
-        Rule::var_statement => parse_var_statement(pair, env),
-        Rule::assignment_statement => parse_assignment_statement(pair, env),
-        Rule::compound_statement => parse_compound_statement(pair, env),
-        Rule::if_statement => parse_if_statement(pair, env),
-        Rule::return_statement => parse_return_statement(pair, env),
-        Rule::loop_statement => parse_loop_statement(pair, env),
+    Ok(match pair.as_rule() {
+        Rule::let_statement => parse_let_statement(pair, env)?,
+        Rule::var_statement => parse_var_statement(pair, env)?,
+        Rule::assignment_statement => parse_assignment_statement(pair, env)?,
+        Rule::compound_statement => parse_compound_statement(pair, env)?,
+        Rule::if_statement => parse_if_statement(pair, env)?,
+        Rule::return_statement => parse_return_statement(pair, env)?,
+        Rule::loop_statement => parse_loop_statement(pair, env)?,
         Rule::break_statement => Statement::Break,
         Rule::continue_statement => Statement::Continue,
         Rule::fallthrough_statement => Statement::Fallthrough,
-        Rule::switch_statement => parse_switch_statement(pair, env),
-        Rule::for_statement => parse_for_statement(pair, env),
-        Rule::call_statement => parse_call_statement(pair, env),
+        Rule::switch_statement => parse_switch_statement(pair, env)?,
+        Rule::for_statement => parse_for_statement(pair, env)?,
+        Rule::call_statement => parse_call_statement(pair, env)?,
+        Rule::const_assert_statement => parse_const_assert_statement(pair, env)?.into(),
         _ => unreachable!(),
-    }
+    })
 }
 
-fn parse_let_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
+fn parse_let_statement(pair: Pair<Rule>, env: &mut Environment) -> Result<Statement> {
     let mut pairs = pair.into_inner();
     let ident = pairs.next().unwrap().as_str().to_owned();
-    let initializer = parse_expression(pairs.next().unwrap(), env);
+    let initializer = parse_expression(pairs.next().unwrap(), env)?;
     let stmt = LetDeclStatement::new(ident.clone(), initializer);
     env.insert_var(ident, stmt.inferred_type().clone());
-    stmt.into()
+    Ok(stmt.into())
 }
 
-fn parse_var_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
+fn parse_var_statement(pair: Pair<Rule>, env: &mut Environment) -> Result<Statement> {
+    let span = pair.as_span();
     let mut pairs = pair.into_inner();
     let ident = pairs.next().unwrap().as_str().to_owned();
 
     let mut pair = pairs.next();
 
     let specified_type = if let Some(Rule::type_decl) = pair.as_ref().map(|it| it.as_rule()) {
-        let ty = parse_type_decl(pair.unwrap(), env);
+        let ty = parse_type_decl(pair.unwrap(), env)?;
         pair = pairs.next();
         Some(ty)
     } else {
@@ -413,25 +692,32 @@ fn parse_var_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
     };
 
     let initializer = if let Some(Rule::expression) = pair.as_ref().map(|it| it.as_rule()) {
-        Some(parse_expression(pair.unwrap(), env))
+        Some(parse_expression(pair.unwrap(), env)?)
     } else {
         None
     };
 
+    if specified_type.is_none() && initializer.is_none() {
+        return Err(ParseError::new(
+            span,
+            "var declaration must have type or initializer",
+        ));
+    }
+
     let stmt = VarDeclStatement::new(ident.clone(), specified_type, initializer);
 
     let ref_view = MemoryViewType::new(stmt.inferred_type().clone(), StorageClass::Function);
     env.insert_var(ident, DataType::Ref(ref_view));
 
-    stmt.into()
+    Ok(stmt.into())
 }
 
-fn parse_assignment_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
+fn parse_assignment_statement(pair: Pair<Rule>, env: &Environment) -> Result<Statement> {
     let mut pairs = pair.into_inner();
 
-    let lhs = parse_assignment_lhs(pairs.next().unwrap(), env);
+    let lhs = parse_assignment_lhs(pairs.next().unwrap(), env)?;
     let op = pairs.next().unwrap();
-    let rhs = parse_expression(pairs.next().unwrap(), env);
+    let rhs = parse_expression(pairs.next().unwrap(), env)?;
 
     let op = op.into_inner().next().unwrap();
     let op = match op.as_rule() {
@@ -450,30 +736,30 @@ fn parse_assignment_statement(pair: Pair<Rule>, env: &Environment) -> Statement
         _ => unreachable!(),
     };
 
-    AssignmentStatement::new(lhs, op, rhs).into()
+    Ok(AssignmentStatement::new(lhs, op, rhs).into())
 }
 
-fn parse_assignment_lhs(pair: Pair<Rule>, env: &Environment) -> AssignmentLhs {
-    match pair.as_rule() {
+fn parse_assignment_lhs(pair: Pair<Rule>, env: &Environment) -> Result<AssignmentLhs> {
+    Ok(match pair.as_rule() {
         Rule::lhs_phony => AssignmentLhs::Phony,
-        Rule::lhs_expression => AssignmentLhs::Expr(parse_lhs_expression(pair, env)),
+        Rule::lhs_expression => AssignmentLhs::Expr(parse_lhs_expression(pair, env)?),
         _ => unreachable!(),
-    }
+    })
 }
 
-fn parse_compound_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
+fn parse_compound_statement(pair: Pair<Rule>, env: &Environment) -> Result<Statement> {
     let mut inner_env = env.clone();
-    Statement::Compound(
+    Ok(Statement::Compound(
         pair.into_inner()
             .map(|pair| parse_statement(pair, &mut inner_env))
-            .collect(),
-    )
+            .collect::<Result<_>>()?,
+    ))
 }
 
-fn parse_if_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
+fn parse_if_statement(pair: Pair<Rule>, env: &Environment) -> Result<Statement> {
     let mut pairs = pair.into_inner();
-    let condition = parse_paren_expression(pairs.next().unwrap(), env);
-    let block = parse_compound_statement(pairs.next().unwrap(), env).into_compount_statement();
+    let condition = parse_paren_expression(pairs.next().unwrap(), env)?;
+    let block = parse_compound_statement(pairs.next().unwrap(), env)?.into_compount_statement();
 
     let els = pairs
         .next()
@@ -482,38 +768,41 @@ fn parse_if_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
             Rule::if_statement => parse_if_statement(pair, env),
             _ => unreachable!(),
         })
+        .transpose()?
         .map(|stmt| match stmt {
             Statement::Compound(stmts) => Else::Else(stmts),
             Statement::If(stmt) => Else::If(stmt),
             _ => unreachable!(),
         });
 
-    IfStatement::new(condition, block).with_else(els).into()
+    Ok(IfStatement::new(condition, block).with_else(els).into())
 }
 
-fn parse_return_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
+fn parse_return_statement(pair: Pair<Rule>, env: &Environment) -> Result<Statement> {
     let expression = pair
         .into_inner()
         .next()
-        .map(|pair| parse_expression(pair, env));
+        .map(|pair| parse_expression(pair, env))
+        .transpose()?;
 
     if let Some(value) = expression {
-        ReturnStatement::new(value).into()
+        Ok(ReturnStatement::new(value).into())
     } else {
-        ReturnStatement::none().into()
+        Ok(ReturnStatement::none().into())
     }
 }
 
-fn parse_loop_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
+fn parse_loop_statement(pair: Pair<Rule>, env: &Environment) -> Result<Statement> {
     let mut pairs = pair.into_inner();
-    let block = parse_compound_statement(pairs.next().unwrap(), env).into_compount_statement();
-    LoopStatement::new(block).into()
+    let block = parse_compound_statement(pairs.next().unwrap(), env)?.into_compount_statement();
+    Ok(LoopStatement::new(block).into())
 }
 
-fn parse_switch_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
+fn parse_switch_statement(pair: Pair<Rule>, env: &Environment) -> Result<Statement> {
+    let span = pair.as_span();
     let mut pairs = pair.into_inner();
 
-    let expr = parse_expression(pairs.next().unwrap(), env);
+    let expr = parse_expression(pairs.next().unwrap(), env)?;
 
     let mut cases = vec![];
     let mut default = None;
@@ -523,54 +812,67 @@ fn parse_switch_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
         let pair = pairs.next().unwrap();
 
         if pair.as_rule() == Rule::expression {
-            let selector = parse_expression(pair, env);
+            let selector = parse_expression(pair, env)?;
             let body =
-                parse_compound_statement(pairs.next().unwrap(), env).into_compount_statement();
+                parse_compound_statement(pairs.next().unwrap(), env)?.into_compount_statement();
             cases.push(SwitchCase { selector, body });
         } else {
-            default = Some(parse_compound_statement(pair, env).into_compount_statement());
+            default = Some(parse_compound_statement(pair, env)?.into_compount_statement());
         }
     }
 
-    let default = default.expect("switch statement must have default case");
+    let default =
+        default.ok_or_else(|| ParseError::new(span, "switch statement must have default case"))?;
 
-    SwitchStatement::new(expr, cases, default).into()
+    Ok(SwitchStatement::new(expr, cases, default).into())
 }
 
-fn parse_for_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
+fn parse_for_statement(pair: Pair<Rule>, env: &mut Environment) -> Result<Statement> {
     let mut pairs = pair.into_inner();
 
     let mut pair = pairs.next().unwrap();
 
     let mut init = None;
     if pair.as_rule() == Rule::for_init {
-        match parse_statement(pair.into_inner().next().unwrap(), env) {
+        let span = pair.as_span();
+        match parse_statement(pair.into_inner().next().unwrap(), env)? {
             Statement::VarDecl(stmt) => {
                 init = Some(ForLoopInit::VarDecl(stmt));
             }
-            _ => panic!("only assignment statement is currently supported in for loop init"),
+            _ => {
+                return Err(ParseError::new(
+                    span,
+                    "only var declaration is currently supported in for loop init",
+                ))
+            }
         };
         pair = pairs.next().unwrap();
     }
 
     let mut condition = None;
     if pair.as_rule() == Rule::expression {
-        condition = Some(parse_expression(pair, env));
+        condition = Some(parse_expression(pair, env)?);
         pair = pairs.next().unwrap();
     }
 
     let mut update = None;
     if pair.as_rule() == Rule::for_update {
-        match parse_statement(pair.into_inner().next().unwrap(), env) {
+        let span = pair.as_span();
+        match parse_statement(pair.into_inner().next().unwrap(), env)? {
             Statement::Assignment(stmt) => {
                 update = Some(ForLoopUpdate::Assignment(stmt));
             }
-            _ => panic!("only assignment statement is currently supported in for loop init"),
+            _ => {
+                return Err(ParseError::new(
+                    span,
+                    "only assignment statement is currently supported in for loop update",
+                ))
+            }
         };
         pair = pairs.next().unwrap();
     }
 
-    let body = parse_compound_statement(pair, env);
+    let body = parse_compound_statement(pair, env)?;
 
     let header = ForLoopHeader {
         init,
@@ -578,20 +880,68 @@ fn parse_for_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
         update,
     };
 
-    ForLoopStatement::new(header, body.into_compount_statement()).into()
+    Ok(ForLoopStatement::new(header, body.into_compount_statement()).into())
 }
 
-fn parse_call_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
+fn parse_call_statement(pair: Pair<Rule>, env: &Environment) -> Result<Statement> {
     let pair = pair.into_inner().next().unwrap();
     let mut pairs = pair.into_inner();
 
     let ident = pairs.next().unwrap().as_str().to_owned();
-    let args = pairs.map(|it| parse_expression(it, env)).collect();
+    let args = pairs
+        .map(|it| parse_expression(it, env))
+        .collect::<Result<_>>()?;
+
+    Ok(FnCallStatement::new(ident, args).into())
+}
+
+fn parse_const_assert_statement(
+    pair: Pair<Rule>,
+    env: &Environment,
+) -> Result<ConstAssertStatement> {
+    let pair = pair.into_inner().next().unwrap();
+    Ok(ConstAssertStatement::new(parse_expression(pair, env)?))
+}
+
+/// Determines the type of applying a postfix to an expression of type `ty`, returning an error
+/// rather than panicking if it can't be applied.
+fn postfix_type(span: pest::Span, postfix: &Postfix, ty: &DataType) -> Result<DataType> {
+    let valid = match (postfix, ty.dereference()) {
+        (Postfix::Index(_), DataType::Vector(..) | DataType::Array(..)) => true,
+        (Postfix::Member(member), DataType::Struct(decl)) => decl.member_type(member).is_some(),
+        (Postfix::Member(_), DataType::Vector(..)) => true,
+        _ => false,
+    };
+
+    if !valid {
+        return Err(ParseError::new(
+            span,
+            format!("`{postfix}` cannot be applied to type `{ty}`"),
+        ));
+    }
+
+    Ok(postfix.type_eval(ty))
+}
+
+/// Determines the type of applying a unary operator to an expression of type `ty`, returning an
+/// error rather than panicking if it can't be applied.
+fn unary_type(span: pest::Span, op: UnOp, ty: &DataType) -> Result<DataType> {
+    let valid = match op {
+        UnOp::AddressOf | UnOp::Deref => ty.as_memory_view().is_some(),
+        UnOp::Neg | UnOp::Not | UnOp::BitNot => true,
+    };
+
+    if !valid {
+        return Err(ParseError::new(
+            span,
+            format!("`{op}` cannot be applied to type `{ty}`"),
+        ));
+    }
 
-    FnCallStatement::new(ident, args).into()
+    Ok(op.type_eval(ty))
 }
 
-fn parse_lhs_expression(pair: Pair<Rule>, env: &Environment) -> LhsExprNode {
+fn parse_lhs_expression(pair: Pair<Rule>, env: &Environment) -> Result<LhsExprNode> {
     let mut pairs = pair.into_inner().peekable();
 
     let prefixes: Vec<_> = pairs
@@ -599,53 +949,51 @@ fn parse_lhs_expression(pair: Pair<Rule>, env: &Environment) -> LhsExprNode {
         .peeking_take_while(|pair| pair.as_rule() != Rule::core_lhs_expression)
         .collect();
 
-    fn parse_core(pair: Pair<Rule>, env: &Environment) -> LhsExprNode {
+    fn parse_core(pair: Pair<Rule>, env: &Environment) -> Result<LhsExprNode> {
         let pair = pair.into_inner().next().unwrap();
         match pair.as_rule() {
             Rule::lhs_expression => parse_lhs_expression(pair, env),
             Rule::ident => {
                 let ident = pair.as_str().to_owned();
-                LhsExprNode {
-                    data_type: env
-                        .var(&ident)
-                        .expect("variable must be defined before use")
-                        .clone(),
+                Ok(LhsExprNode {
+                    data_type: lookup_var(&pair, env)?,
                     expr: LhsExpr::Ident(ident),
-                }
+                })
             }
             _ => unreachable!(),
         }
     }
 
-    let node = parse_core(pairs.next().unwrap(), env);
-    let node = pairs.fold(node, |node, pair| {
+    let node = parse_core(pairs.next().unwrap(), env)?;
+    let node = pairs.try_fold(node, |node, pair| {
+        let span = pair.as_span();
         let pair = pair.into_inner().next().unwrap();
         let postfix = match pair.as_rule() {
-            Rule::expression => Postfix::Index(Box::new(parse_expression(pair, env))),
+            Rule::expression => Postfix::Index(Box::new(parse_expression(pair, env)?)),
             Rule::ident => Postfix::Member(pair.as_str().to_owned()),
             _ => unreachable!(),
         };
 
-        LhsExprNode {
-            data_type: postfix.type_eval(&node.data_type),
+        Ok(LhsExprNode {
+            data_type: postfix_type(span, &postfix, &node.data_type)?,
             expr: LhsExpr::Postfix(Box::new(node), postfix),
-        }
-    });
+        })
+    })?;
 
-    prefixes.iter().rev().fold(node, |node, pair| {
+    prefixes.iter().rev().try_fold(node, |node, pair| {
         let (data_type, expr) = match pair.as_rule() {
             Rule::op_address_of => (
-                UnOp::AddressOf.type_eval(&node.data_type),
+                unary_type(pair.as_span(), UnOp::AddressOf, &node.data_type)?,
                 LhsExpr::AddressOf(Box::new(node)),
             ),
             Rule::op_indirection => (
-                UnOp::Deref.type_eval(&node.data_type),
+                unary_type(pair.as_span(), UnOp::Deref, &node.data_type)?,
                 LhsExpr::Deref(Box::new(node)),
             ),
             _ => unreachable!(),
         };
 
-        LhsExprNode { data_type, expr }
+        Ok(LhsExprNode { data_type, expr })
     })
 }
 
@@ -677,7 +1025,7 @@ fn precedence_table() -> PrecClimber<Rule> {
     ])
 }
 
-fn parse_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+fn parse_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode> {
     let pair = pair.into_inner().next().unwrap();
     match pair.as_rule() {
         Rule::infix_expression => parse_infix_expression(pair, env),
@@ -686,18 +1034,47 @@ fn parse_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
     }
 }
 
-fn parse_infix_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+fn parse_infix_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode> {
     let pairs = pair.into_inner();
 
     let unary = |pair| parse_unary_expression(pair, env);
-    let infix = |l: ExprNode, op: Pair<Rule>, r: ExprNode| -> ExprNode {
-        BinOpExpr::new(op.as_rule().into(), l, r).into()
+    let infix = |l: Result<ExprNode>, op: Pair<Rule>, r: Result<ExprNode>| -> Result<ExprNode> {
+        let (l, r) = (l?, r?);
+        let op: BinOp = op.as_rule().into();
+        let (l, r) = if matches!(op, BinOp::LShift | BinOp::RShift) {
+            (l, r)
+        } else {
+            let l_ty = l.data_type.clone();
+            (
+                convert_abstract_int(l, &r.data_type),
+                convert_abstract_int(r, &l_ty),
+            )
+        };
+
+        Ok(BinOpExpr::new(op, l, r).into())
     };
 
     precedence_table().climb(pairs, unary, infix)
 }
 
-fn parse_unary_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+/// Converts an unsuffixed integer literal to an abstract literal of the same type as the other
+/// operand of a binary operator, if that is a u32 or f32.
+///
+/// Unsuffixed integers are otherwise parsed as i32 literals, which is their type when nothing
+/// else constrains them.
+fn convert_abstract_int(node: ExprNode, other: &DataType) -> ExprNode {
+    match (&node.expr, other.dereference()) {
+        (Expr::Lit(Lit::I32(v)), DataType::Scalar(ty @ (ScalarType::U32 | ScalarType::F32))) => {
+            ExprNode {
+                data_type: DataType::Scalar(*ty),
+                expr: Expr::Lit(Lit::AbstractInt(*v as i64)),
+            }
+        }
+        _ => node,
+    }
+}
+
+fn parse_unary_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode> {
     let mut pairs = pair.into_inner();
 
     let first_pair = pairs.next().unwrap();
@@ -706,6 +1083,7 @@ fn parse_unary_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
         _ => first_pair,
     };
 
+    let span = op.as_span();
     let op = match op.as_rule() {
         Rule::op_minus => UnOp::Neg,
         Rule::op_log_not => UnOp::Not,
@@ -715,30 +1093,33 @@ fn parse_unary_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
         _ => unreachable!(),
     };
 
-    let expr = parse_unary_expression(pairs.next().unwrap(), env);
+    let expr = parse_unary_expression(pairs.next().unwrap(), env)?;
+    unary_type(span, op, &expr.data_type)?;
 
-    UnOpExpr::new(op, expr).into()
+    Ok(UnOpExpr::new(op, expr).into())
 }
 
-fn parse_singular_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+fn parse_singular_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode> {
     let mut pairs = pair.into_inner();
-    let mut expr = parse_primary_expression(pairs.next().unwrap(), env);
+    let mut expr = parse_primary_expression(pairs.next().unwrap(), env)?;
 
     for pf in pairs {
+        let span = pf.as_span();
         let pair = pf.into_inner().next().unwrap();
         let pf = match pair.as_rule() {
-            Rule::expression => Postfix::Index(Box::new(parse_expression(pair, env))),
+            Rule::expression => Postfix::Index(Box::new(parse_expression(pair, env)?)),
             Rule::ident => Postfix::Member(pair.as_str().to_owned()),
             _ => unreachable!(),
         };
 
+        postfix_type(span, &pf, &expr.data_type)?;
         expr = PostfixExpr::new(expr, pf).into();
     }
 
-    expr
+    Ok(expr)
 }
 
-fn parse_primary_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+fn parse_primary_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode> {
     let pair = pair.into_inner().next().unwrap();
     match pair.as_rule() {
         Rule::literal_expression => parse_literal_expression(pair),
@@ -751,42 +1132,42 @@ fn parse_primary_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
     }
 }
 
-fn parse_literal_expression(pair: Pair<Rule>) -> ExprNode {
+fn parse_literal_expression(pair: Pair<Rule>) -> Result<ExprNode> {
     let pair = pair.into_inner().next().unwrap();
     let (t, lit) = match pair.as_rule() {
-        Rule::bool_literal => (ScalarType::Bool, Lit::Bool(pair.as_str().parse().unwrap())),
-        Rule::uint_literal => (
-            ScalarType::U32,
-            Lit::U32(pair.as_str().trim_end_matches('u').parse().unwrap()),
-        ),
-        Rule::int_literal => (ScalarType::I32, Lit::I32(pair.as_str().parse().unwrap())),
-        Rule::float_literal => (ScalarType::F32, Lit::F32(pair.as_str().parse().unwrap())),
+        Rule::bool_literal => (ScalarType::Bool, Lit::Bool(parse_value(&pair, "bool")?)),
+        Rule::uint_literal => (ScalarType::U32, Lit::U32(parse_value(&pair, "u32")?)),
+        Rule::int_literal => (ScalarType::I32, Lit::I32(parse_value(&pair, "i32")?)),
+        Rule::float_literal => (ScalarType::F32, Lit::F32(parse_value(&pair, "f32")?)),
         _ => unreachable!(),
     };
 
-    ExprNode {
+    Ok(ExprNode {
         data_type: DataType::Scalar(t),
         expr: Expr::Lit(lit),
-    }
+    })
 }
 
-fn parse_type_cons_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+fn parse_type_cons_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode> {
     let mut pairs = pair.into_inner();
     let t_decl = pairs.next().unwrap();
 
-    let t = parse_type_decl(t_decl, env);
-    let args = pairs.map(|pair| parse_expression(pair, env)).collect();
+    let t = parse_type_decl(t_decl, env)?;
+    let args = pairs
+        .map(|pair| parse_expression(pair, env))
+        .collect::<Result<_>>()?;
 
-    TypeConsExpr::new(t, args).into()
+    Ok(TypeConsExpr::new(t, args).into())
 }
 
-fn parse_call_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+fn parse_call_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode> {
+    let span = pair.as_span();
     let mut pairs = pair.into_inner();
 
     let ident = pairs.next().unwrap();
     let args = pairs
         .map(|pair| parse_expression(pair, env))
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>>>()?;
 
     struct FunSig<'a>(&'a str, &'a [ExprNode]);
 
@@ -807,19 +1188,24 @@ fn parse_call_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
 
     let return_type = env
         .func(ident.as_str(), args.iter().map(|arg| &arg.data_type))
-        .unwrap_or_else(|| panic!("`{}` not found", FunSig(ident.as_str(), &args)));
+        .ok_or_else(|| {
+            ParseError::new(
+                span,
+                format!("`{}` not found", FunSig(ident.as_str(), &args)),
+            )
+        })?;
 
-    FnCallExpr::new(ident.as_str().to_owned(), args).into_node(return_type)
+    Ok(FnCallExpr::new(ident.as_str().to_owned(), args).into_node(return_type))
 }
 
-fn parse_type_decl(pair: Pair<Rule>, env: &Environment) -> DataType {
+fn parse_type_decl(pair: Pair<Rule>, env: &Environment) -> Result<DataType> {
     let pair = pair.into_inner().next().unwrap();
 
     fn parse_t_scalar(pair: Pair<Rule>) -> ScalarType {
         pair.into_inner().next().unwrap().as_rule().into()
     }
 
-    match pair.as_rule() {
+    Ok(match pair.as_rule() {
         Rule::t_scalar => DataType::Scalar(parse_t_scalar(pair)),
         Rule::t_vector => {
             let t_vector = pair.into_inner().next().unwrap();
@@ -836,34 +1222,50 @@ fn parse_type_decl(pair: Pair<Rule>, env: &Environment) -> DataType {
             let mut pairs = pair.into_inner();
             let pair = pairs.next().unwrap();
             DataType::Array(
-                Rc::new(parse_type_decl(pair, env)),
-                pairs.next().map(|it| it.as_str().parse().unwrap()),
+                Rc::new(parse_type_decl(pair, env)?),
+                pairs
+                    .next()
+                    .map(|it| parse_value(&it, "array size"))
+                    .transpose()?,
             )
         }
         Rule::ptr_type_decl => {
             let mut pairs = pair.into_inner();
             let storage_class = parse_storage_class(pairs.next().unwrap());
-            let inner = parse_type_decl(pairs.next().unwrap(), env);
+            let inner = parse_type_decl(pairs.next().unwrap(), env)?;
             DataType::Ptr(MemoryViewType::new(inner, storage_class))
         }
         Rule::ident => DataType::Struct(
             env.ty(pair.as_str())
-                .unwrap_or_else(|| panic!("type not found: {}", pair.as_str()))
+                .ok_or_else(|| {
+                    ParseError::new(pair.as_span(), format!("type not found: {}", pair.as_str()))
+                })?
                 .clone(),
         ),
-        _ => panic!("{}", pair),
-    }
+        _ => {
+            return Err(ParseError::new(
+                pair.as_span(),
+                format!("unsupported type: {}", pair.as_str()),
+            ))
+        }
+    })
 }
 
-fn parse_var_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
-    VarExpr::new(pair.as_str()).into_node(
-        env.var(pair.as_str())
-            .unwrap_or_else(|| panic!("variable `{}` must be defined before use", pair.as_str()))
-            .clone(),
-    )
+/// Looks up the type of the variable named by an identifier.
+fn lookup_var(pair: &Pair<Rule>, env: &Environment) -> Result<DataType> {
+    env.var(pair.as_str()).cloned().ok_or_else(|| {
+        ParseError::new(
+            pair.as_span(),
+            format!("variable `{}` must be defined before use", pair.as_str()),
+        )
+    })
 }
 
-fn parse_paren_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+fn parse_var_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode> {
+    Ok(VarExpr::new(pair.as_str()).into_node(lookup_var(&pair, env)?))
+}
+
+fn parse_paren_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode> {
     let pair = pair.into_inner().next().unwrap();
     parse_expression(pair, env)
 }
@@ -875,6 +1277,7 @@ fn parse_storage_class(pair: Pair<Rule>) -> StorageClass {
         "workgroup" => StorageClass::WorkGroup,
         "uniform" => StorageClass::Uniform,
         "storage" => StorageClass::Storage,
+        "push_constant" => StorageClass::PushConstant,
         _ => unreachable!(),
     }
 }
@@ -931,12 +1334,222 @@ mod tests {
                 const SRC: &str = include_str!(concat!("tests/", stringify!($name), ".wgsl"));
                 let pairs = WGSLParser::parse(Rule::translation_unit, SRC).unwrap();
                 let pair = pairs.into_iter().next().unwrap();
-                let module = parse_translation_unit(pair, &mut Environment::new());
+                let module = parse_translation_unit(pair, &mut Environment::new()).unwrap();
                 insta::assert_debug_snapshot!(module);
             }
         };
     }
 
+    macro_rules! round_trip_test_case {
+        ($name:ident, $fn:ident) => {
+            #[test]
+            fn $fn() {
+                const SRC: &str = include_str!(concat!("tests/", stringify!($name), ".wgsl"));
+                let first = write(&parse(SRC));
+                let second = write(&parse(&first));
+                assert_eq!(first, second);
+            }
+        };
+    }
+
+    fn write(module: &Module) -> String {
+        let mut out = String::new();
+        ast::writer::Writer::default()
+            .write_module(&mut out, module)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn formatting_options() {
+        const SRC: &str = include_str!("tests/test_1.wgsl");
+        let module = parse(SRC);
+
+        let mut minified = String::new();
+        ast::writer::Writer::new(ast::writer::Options {
+            indent_width: 2,
+            minify: true,
+            trailing_commas: true,
+            ..Default::default()
+        })
+        .write_module(&mut minified, &module)
+        .unwrap();
+
+        assert!(!minified.contains('\n'));
+        assert_eq!(write(&parse(&minified)), write(&module));
+    }
+
+    #[test]
+    fn rename_identifiers() {
+        const SRC: &str = include_str!("tests/ptrs.wgsl");
+        let mut module = parse(SRC);
+
+        ast::rename::rename_identifiers(&mut module);
+
+        let renamed = write(&module);
+        assert_eq!(write(&parse(&renamed)), renamed);
+    }
+
+    #[test]
+    fn canonical_hash() {
+        use ast::canonical::canonical_hash;
+
+        let a = "fn f(x: i32) -> i32 { let y = x * 2; return y + x; }";
+        let b = "fn g(a: i32) -> i32 { let b = 2 * a; return a + b; }";
+        let c = "fn f(x: i32) -> i32 { let y = x - 2; return y + x; }";
+
+        assert_eq!(canonical_hash(parse(a)), canonical_hash(parse(b)));
+        assert_ne!(canonical_hash(parse(a)), canonical_hash(parse(c)));
+    }
+
+    #[test]
+    fn invalid_syntax() {
+        let e = try_parse("fn main( {}").unwrap_err();
+        assert!(e.to_string().contains("1:"));
+    }
+
+    #[test]
+    fn unsupported_constructs() {
+        let e = try_parse(
+            "@compute @workgroup_size(1)\nfn main(@builtin(global_invocation_id) id: vec3<u32>) {}",
+        )
+        .unwrap_err();
+        assert!(e
+            .to_string()
+            .contains("unsupported builtin value: global_invocation_id"));
+        assert!(e.to_string().contains("2:"));
+
+        let e = try_parse("fn main() { let x = y; }").unwrap_err();
+        assert!(e
+            .to_string()
+            .contains("variable `y` must be defined before use"));
+
+        let e = try_parse("fn main() { let x = 1; let y = x.z; }").unwrap_err();
+        assert!(e.to_string().contains("cannot be applied to type `i32`"));
+    }
+
+    #[test]
+    fn const_asserts() {
+        const SRC: &str = include_str!("tests/const_assert.wgsl");
+        let module = parse(SRC);
+
+        assert_eq!(module.const_asserts.len(), 2);
+        assert!(matches!(
+            module.functions[0].body[0],
+            Statement::ConstAssert(_)
+        ));
+    }
+
+    #[test]
+    fn diagnostics() {
+        const SRC: &str = include_str!("tests/diagnostics.wgsl");
+        let module = parse(SRC);
+
+        assert_eq!(
+            module.diagnostics,
+            [
+                DiagnosticControl::new(Severity::Off, "derivative_uniformity"),
+                DiagnosticControl::new(Severity::Warning, "chromium.unreachable_code"),
+            ]
+        );
+        assert_eq!(
+            module.functions[0].attrs[0],
+            FnAttr::Diagnostic(DiagnosticControl::new(
+                Severity::Info,
+                "derivative_uniformity"
+            ))
+        );
+    }
+
+    #[test]
+    fn runtime_array() {
+        const SRC: &str = include_str!("tests/runtime_array.wgsl");
+        let module = parse(SRC);
+
+        assert!(module.vars[0].data_type.is_runtime_sized());
+        assert!(matches!(
+            module.structs[0].members[1].data_type,
+            DataType::Array(_, None)
+        ));
+    }
+
+    #[test]
+    fn abstract_literals() {
+        const SRC: &str = include_str!("tests/abstract_literals.wgsl");
+        let module = parse(SRC);
+
+        let types = module.functions[0]
+            .body
+            .iter()
+            .map(|stmt| match stmt {
+                Statement::LetDecl(decl) => decl.initializer.data_type.clone(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            types,
+            [ScalarType::U32, ScalarType::F32, ScalarType::I32].map(DataType::Scalar)
+        );
+    }
+
+    #[test]
+    fn stage_io() {
+        const SRC: &str = include_str!("tests/stage_io.wgsl");
+        let module = parse(SRC);
+
+        assert_eq!(
+            module.structs[0].members[2].attrs,
+            [
+                StructMemberAttr::Location(0),
+                StructMemberAttr::Interpolate(Interpolation {
+                    kind: InterpolationType::Linear,
+                    sampling: Some(InterpolationSampling::Centroid),
+                }),
+            ]
+        );
+
+        let fragment = &module.functions[1];
+        assert_eq!(
+            fragment.inputs[0].attrs,
+            [
+                FnInputAttr::Location(3),
+                FnInputAttr::Interpolate(Interpolation {
+                    kind: InterpolationType::Flat,
+                    sampling: None,
+                }),
+            ]
+        );
+        assert_eq!(
+            fragment.inputs[1].attrs,
+            [FnInputAttr::Builtin(BuiltinValue::Position)]
+        );
+        assert_eq!(
+            fragment.output.as_ref().unwrap().attrs,
+            [FnOutputAttr::Location(0)]
+        );
+
+        assert_eq!(
+            module.structs[1].members[1].attrs,
+            [StructMemberAttr::Builtin(BuiltinValue::FragDepth)]
+        );
+    }
+
+    #[test]
+    fn subgroups() {
+        const SRC: &str = include_str!("tests/subgroups.wgsl");
+        let module = parse(SRC);
+
+        assert_eq!(module.enables, ["subgroups"]);
+        assert_eq!(
+            module.diagnostics,
+            [DiagnosticControl::new(
+                Severity::Off,
+                "derivative_uniformity"
+            )]
+        );
+    }
+
     test_case!(calls);
     test_case!(floats);
     test_case!(loops);
@@ -948,4 +1561,17 @@ mod tests {
     test_case!(test_3);
     test_case!(test_4);
     test_case!(test_5);
+
+    round_trip_test_case!(abstract_literals, round_trip_abstract_literals);
+    round_trip_test_case!(calls, round_trip_calls);
+    round_trip_test_case!(const_assert, round_trip_const_assert);
+    round_trip_test_case!(diagnostics, round_trip_diagnostics);
+    round_trip_test_case!(floats, round_trip_floats);
+    round_trip_test_case!(loops, round_trip_loops);
+    round_trip_test_case!(ptrs, round_trip_ptrs);
+    round_trip_test_case!(push_constants, round_trip_push_constants);
+    round_trip_test_case!(runtime_array, round_trip_runtime_array);
+    round_trip_test_case!(stage_io, round_trip_stage_io);
+    round_trip_test_case!(structs, round_trip_structs);
+    round_trip_test_case!(subgroups, round_trip_subgroups);
 }
@@ -0,0 +1,7 @@
+@compute
+@workgroup_size(1)
+fn main() {
+    let a = 1u + 2;
+    let b = 3 * 2.5;
+    let c = 1 << 2u;
+}
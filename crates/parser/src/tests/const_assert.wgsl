@@ -0,0 +1,7 @@
+const_assert 1i < 2i;
+const_assert (3u * 4u == 12u) && true;
+
+fn main() {
+    const_assert !(7i > 9i);
+    let x = 1i;
+}
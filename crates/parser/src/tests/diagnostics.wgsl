@@ -0,0 +1,8 @@
+diagnostic(off, derivative_uniformity);
+diagnostic(warning, chromium.unreachable_code,);
+
+@diagnostic(info, derivative_uniformity)
+@compute
+@workgroup_size(1,)
+fn main() {
+}
@@ -0,0 +1,15 @@
+struct PushConstants {
+    a: u32,
+    b: vec3<f32>,
+}
+
+var<push_constant> u_push: PushConstants;
+
+@group(0) @binding(0)
+var<storage, read_write> output: vec4<f32>;
+
+@compute
+@workgroup_size(1)
+fn main() {
+    output = vec4<f32>(u_push.b, f32(u_push.a));
+}
@@ -0,0 +1,16 @@
+struct Buffer {
+    a: u32,
+    arr: array<vec3<f32>>,
+}
+
+@group(0) @binding(0)
+var<storage, read_write> output: Buffer;
+
+@compute
+@workgroup_size(1)
+fn main() {
+    output.a = arrayLength(&output.arr);
+    for (var i = 0u; i < arrayLength(&output.arr); i += 1u) {
+        output.arr[i] = vec3<f32>(f32(i));
+    }
+}
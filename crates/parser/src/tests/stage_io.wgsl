@@ -0,0 +1,27 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(3) @interpolate(flat) a: vec2<u32>,
+    @location(0) @interpolate(linear, centroid) b: f32,
+}
+
+struct FragmentOutput {
+    @location(0) color: vec4<u32>,
+    @builtin(frag_depth) depth: f32,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let x = f32(i32(vertex_index & 1u) * 4 - 1);
+    let y = f32(i32(vertex_index >> 1u) * 4 - 1);
+    return VertexOutput(vec4<f32>(x, y, 0.0, 1.0), vec2<u32>(vertex_index), x);
+}
+
+@fragment
+fn fs_main(@location(3) @interpolate(flat) a: vec2<u32>, @builtin(position) position: vec4<f32>) -> @location(0) vec4<u32> {
+    return vec4<u32>(a, u32(position.x), 1u);
+}
+
+@fragment
+fn fs_depth(@builtin(position) position: vec4<f32>) -> FragmentOutput {
+    return FragmentOutput(vec4<u32>(u32(position.y)), position.x / 16.0);
+}
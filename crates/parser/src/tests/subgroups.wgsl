@@ -0,0 +1,14 @@
+enable subgroups;
+diagnostic(off, derivative_uniformity);
+
+@group(0) @binding(0)
+var<storage, read_write> output: array<u32, 4>;
+
+@compute
+@workgroup_size(4)
+fn main(@builtin(local_invocation_index) local_index: u32) {
+    let a = subgroupAdd(local_index);
+    let b = countOneBits(subgroupBallot(local_index > 1u));
+    let c = subgroupBroadcastFirst(a);
+    output[local_index] = select(0u, c ^ b.x, subgroupElect());
+}
@@ -269,6 +269,7 @@ fn visit_stmt<'a>(
         }
         Statement::Continue => {}
         Statement::Fallthrough => {}
+        Statement::ConstAssert(_) => {}
     }
 }
 
@@ -3,7 +3,7 @@ use std::io::Read;
 
 use clap::{Parser, ValueEnum};
 
-use crate::analysis;
+use crate::{analysis, SafetyStrategy};
 
 #[derive(Parser)]
 pub struct Options {
@@ -23,6 +23,12 @@ pub struct Options {
         require_value_delimiter(true)
     )]
     pub enable: Vec<Feature>,
+
+    /// Strategy for guarding integer division, remainder and shifts.
+    ///
+    /// If not set, a strategy is chosen based on a hash of the program.
+    #[clap(long, value_enum, action)]
+    pub safety: Option<SafetyStrategy>,
 }
 
 #[derive(ValueEnum, Clone, Debug)]
@@ -32,7 +38,7 @@ pub enum Feature {
 
 pub fn run(options: Options) -> eyre::Result<()> {
     let input = read_shader_from_path(&options.input)?;
-    let ast = parser::parse(&input);
+    let ast = parser::try_parse(&input)?;
 
     let result = analysis::analyse(&ast);
     if !result {
@@ -40,7 +46,10 @@ pub fn run(options: Options) -> eyre::Result<()> {
         std::process::exit(1);
     }
 
-    let mut rec_opts = crate::Options::default();
+    let mut rec_opts = crate::Options {
+        safety: options.safety,
+        ..Default::default()
+    };
 
     if !options.enable.is_empty() {
         assert!(matches!(options.enable.as_slice(), [Feature::LoopLimiters]));
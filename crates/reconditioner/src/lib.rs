@@ -3,18 +3,18 @@ mod safe_wrappers;
 pub mod analysis;
 pub mod cli;
 
-use std::collections::HashSet;
+use std::collections::BTreeMap;
 use std::fmt::Display;
 
 use ast::types::{DataType, MemoryViewType, ScalarType};
 use ast::*;
+use clap::ValueEnum;
 
 pub struct ReconditionResult {
     pub ast: Module,
     pub loop_count: u32,
 }
 
-#[derive(Hash, PartialEq, Eq)]
 enum Wrapper {
     Clamp(DataType),
     Dot(DataType),
@@ -25,6 +25,8 @@ enum Wrapper {
     Times(DataType),
     Divide(DataType),
     Mod(DataType),
+    LShift(DataType),
+    RShift(DataType),
     Index(DataType),
 }
 
@@ -41,6 +43,8 @@ impl Wrapper {
             Wrapper::Times(ty) => safe_wrappers::times(name, ty),
             Wrapper::Divide(ty) => safe_wrappers::divide(name, ty),
             Wrapper::Mod(ty) => safe_wrappers::modulo(name, ty),
+            Wrapper::LShift(ty) => safe_wrappers::shift(name, BinOp::LShift, ty),
+            Wrapper::RShift(ty) => safe_wrappers::shift(name, BinOp::RShift, ty),
             Wrapper::Index(ty) => safe_wrappers::index(name, ty),
         }
     }
@@ -58,6 +62,8 @@ impl Display for Wrapper {
             Wrapper::Times(ty) => ("mult", ty),
             Wrapper::Divide(ty) => ("div", ty),
             Wrapper::Mod(ty) => ("mod", ty),
+            Wrapper::LShift(ty) => ("shl", ty),
+            Wrapper::RShift(ty) => ("shr", ty),
             Wrapper::Index(ty) => ("index", ty),
         };
 
@@ -71,9 +77,44 @@ impl Display for Wrapper {
     }
 }
 
+/// Strategy for guarding integer division, remainder and shifts.
+///
+/// Using a different strategy for different programs varies the safety checks themselves, so that
+/// a backend bug in the handling of one particular pattern doesn't get masked in every program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SafetyStrategy {
+    /// Call generated helper functions, which check the operands before applying the operator.
+    Helpers,
+    /// Guard the operands inline with `select`.
+    ///
+    /// This duplicates the operands, so helper functions are still used when an operand is
+    /// anything other than a variable or literal.
+    Select,
+    /// Mask the operands inline with bitwise operations so that they are always in range.
+    Mask,
+}
+
+impl SafetyStrategy {
+    const ALL: [SafetyStrategy; 3] = [
+        SafetyStrategy::Helpers,
+        SafetyStrategy::Select,
+        SafetyStrategy::Mask,
+    ];
+
+    /// Chooses a strategy based on a hash of the module, so that the same program is always
+    /// reconditioned in the same way.
+    pub fn for_module(module: &Module) -> SafetyStrategy {
+        let hash = ast::canonical::structural_hash(module);
+        SafetyStrategy::ALL[(hash % SafetyStrategy::ALL.len() as u64) as usize]
+    }
+}
+
 #[derive(Default)]
 pub struct Options {
     pub only_loops: bool,
+    /// Strategy for guarding integer division, remainder and shifts, which is chosen with
+    /// [`SafetyStrategy::for_module`] if not set.
+    pub safety: Option<SafetyStrategy>,
 }
 
 pub fn recondition(ast: Module) -> Module {
@@ -81,17 +122,28 @@ pub fn recondition(ast: Module) -> Module {
 }
 
 pub fn recondition_with(mut ast: Module, options: Options) -> Module {
-    let mut reconditioner = Reconditioner::new(options);
+    let safety = options
+        .safety
+        .unwrap_or_else(|| SafetyStrategy::for_module(&ast));
+
+    let mut reconditioner = Reconditioner::new(options.only_loops, safety);
 
     let functions = ast
         .functions
         .into_iter()
-        .map(|f| reconditioner.recondition_fn(f))
+        .map(|f| {
+            // The checksum relies on wrapping arithmetic, which is already safe
+            if f.name == common::checksum::COMBINE_FN {
+                f
+            } else {
+                reconditioner.recondition_fn(f)
+            }
+        })
         .collect::<Vec<_>>();
 
     ast.functions = reconditioner
         .wrappers
-        .iter()
+        .values()
         .map(Wrapper::gen_fn_decl)
         .chain(functions)
         .collect();
@@ -114,16 +166,20 @@ pub fn recondition_with(mut ast: Module, options: Options) -> Module {
 
 struct Reconditioner {
     loop_var: u32,
-    wrappers: HashSet<Wrapper>,
+    /// Wrappers that have been used so far, keyed by name so that they're always emitted in the
+    /// same order.
+    wrappers: BTreeMap<String, Wrapper>,
     only_loops: bool,
+    safety: SafetyStrategy,
 }
 
 impl Reconditioner {
-    fn new(options: Options) -> Reconditioner {
+    fn new(only_loops: bool, safety: SafetyStrategy) -> Reconditioner {
         Reconditioner {
             loop_var: 0,
-            wrappers: HashSet::new(),
-            only_loops: options.only_loops,
+            wrappers: BTreeMap::new(),
+            only_loops,
+            safety,
         }
     }
 
@@ -243,6 +299,9 @@ impl Reconditioner {
             }
             Statement::Continue => Statement::Continue,
             Statement::Fallthrough => Statement::Fallthrough,
+            // Conditions must be const-expressions, which can't call the safe wrappers. They are
+            // evaluated at shader creation time anyway, so there's no runtime UB to avoid.
+            Statement::ConstAssert(stmt) => Statement::ConstAssert(stmt),
         }
     }
 
@@ -330,15 +389,17 @@ impl Reconditioner {
                 let postfix = match postfix {
                     Postfix::Index(index) => {
                         let index = self.recondition_expr(*index);
-                        Postfix::index(self.recondition_array_index(&expr.data_type, index))
+                        Postfix::index(self.recondition_array_index(&lhs_to_expr(&expr), index))
                     }
                     Postfix::Member(ident) => Postfix::Member(ident),
                 };
 
                 LhsExpr::Postfix(expr, postfix)
             }
-            LhsExpr::Deref(_) => todo!(),
-            LhsExpr::AddressOf(_) => todo!(),
+            LhsExpr::Deref(expr) => LhsExpr::Deref(Box::new(self.recondition_lhs_expr(*expr))),
+            LhsExpr::AddressOf(expr) => {
+                LhsExpr::AddressOf(Box::new(self.recondition_lhs_expr(*expr)))
+            }
         };
 
         LhsExprNode { expr, ..node }
@@ -415,7 +476,7 @@ impl Reconditioner {
                 let postfix = match expr.postfix {
                     Postfix::Index(index) => {
                         let index = self.recondition_expr(*index);
-                        Postfix::Index(Box::new(self.recondition_array_index(&e.data_type, index)))
+                        Postfix::Index(Box::new(self.recondition_array_index(&e, index)))
                     }
                     Postfix::Member(n) => Postfix::Member(n),
                 };
@@ -441,6 +502,8 @@ impl Reconditioner {
             matches!(expr, Expr::UnOp(UnOpExpr { op: UnOp::Neg, .. }))
                 || matches!(expr, Expr::Lit(Lit::I32(v)) if *v < 0)
                 || matches!(expr, Expr::Lit(Lit::F32(v)) if *v < 0.0)
+                || matches!(expr, Expr::Lit(Lit::AbstractInt(v)) if *v < 0)
+                || matches!(expr, Expr::Lit(Lit::AbstractFloat(v)) if *v < 0.0)
         }
 
         let data_type = inner.data_type.dereference();
@@ -461,25 +524,36 @@ impl Reconditioner {
         BinOpExpr::new(BinOp::Times, neg_multiplier, inner).into()
     }
 
-    fn recondition_array_index(&mut self, array_type: &DataType, index: ExprNode) -> ExprNode {
-        let size = match array_type.dereference() {
-            DataType::Array(_, Some(n)) => *n,
+    fn recondition_array_index(&mut self, array: &ExprNode, index: ExprNode) -> ExprNode {
+        let index_type = index.data_type.dereference().clone();
+        let index_scalar = index_type.as_scalar().unwrap();
+
+        let size_expr: ExprNode = match array.data_type.dereference() {
+            DataType::Array(_, Some(n)) => match index_scalar {
+                ScalarType::I32 => Lit::I32(*n as i32).into(),
+                ScalarType::U32 => Lit::U32(*n).into(),
+                _ => unreachable!("index expression must be an integer"),
+            },
             DataType::Array(_, None) => {
-                todo!("runtime-sized arrays are not currently supported")
-            }
-            _ => unreachable!("index operator cannot be applied to type `{array_type}`"),
-        };
+                // The harness always allocates at least one element, so the length is never zero
+                let len = FnCallExpr::new(
+                    "arrayLength",
+                    vec![UnOpExpr::new(UnOp::AddressOf, array.clone()).into()],
+                )
+                .into_node(ScalarType::U32);
 
-        let index_type = index.data_type.dereference().clone();
-        let size_expr = match index_type.as_scalar().unwrap() {
-            ScalarType::I32 => Lit::I32(size as i32),
-            ScalarType::U32 => Lit::U32(size),
-            _ => unreachable!("index expression must be an integer"),
+                match index_scalar {
+                    ScalarType::I32 => TypeConsExpr::new(ScalarType::I32.into(), vec![len]).into(),
+                    ScalarType::U32 => len,
+                    _ => unreachable!("index expression must be an integer"),
+                }
+            }
+            array_type => unreachable!("index operator cannot be applied to type `{array_type}`"),
         };
 
         FnCallExpr::new(
             self.safe_wrapper(Wrapper::Index(index_type.clone())),
-            vec![index, size_expr.into()],
+            vec![index, size_expr],
         )
         .into_node(index_type)
     }
@@ -491,20 +565,41 @@ impl Reconditioner {
         operand: ExprNode,
         shift_value: ExprNode,
     ) -> ExprNode {
-        let shift_type = shift_value.data_type.dereference();
-        let shift_bound: ExprNode = match ty {
-            DataType::Scalar(_) => Lit::U32(32).into(),
-            DataType::Vector(_, _) => {
-                TypeConsExpr::new(shift_type.clone(), vec![Lit::U32(32).into()]).into()
+        let shift_type = shift_value.data_type.dereference().clone();
+
+        match self.safety {
+            SafetyStrategy::Select if is_duplicable(&shift_value) => {
+                // Shifting by the bit width or more is replaced with a shift by zero
+                let out_of_range = BinOpExpr::new(
+                    BinOp::GreaterEqual,
+                    shift_value.clone(),
+                    splat(&shift_type, Lit::U32(32)),
+                );
+
+                BinOpExpr::new(
+                    shift_op,
+                    operand,
+                    select(shift_value, splat(&shift_type, Lit::U32(0)), out_of_range),
+                )
+                .into()
             }
-            _ => unreachable!(),
-        };
+            SafetyStrategy::Mask => BinOpExpr::new(
+                shift_op,
+                operand,
+                BinOpExpr::new(BinOp::BitAnd, shift_value, splat(&shift_type, Lit::U32(31))),
+            )
+            .into(),
+            SafetyStrategy::Helpers | SafetyStrategy::Select => {
+                let wrapper = match shift_op {
+                    BinOp::LShift => Wrapper::LShift(ty.clone()),
+                    BinOp::RShift => Wrapper::RShift(ty.clone()),
+                    _ => unreachable!(),
+                };
 
-        ExprNode::from(BinOpExpr::new(
-            shift_op,
-            operand,
-            BinOpExpr::new(BinOp::Mod, shift_value, shift_bound),
-        ))
+                FnCallExpr::new(self.safe_wrapper(wrapper), vec![operand, shift_value])
+                    .into_node(ty)
+            }
+        }
     }
 
     fn recondition_bin_op_expr(
@@ -541,14 +636,91 @@ impl Reconditioner {
             BinOp::Plus => self.safe_wrapper(Wrapper::Plus(data_type.clone())),
             BinOp::Minus => self.safe_wrapper(Wrapper::Minus(data_type.clone())),
             BinOp::Times => self.safe_wrapper(Wrapper::Times(data_type.clone())),
-            BinOp::Divide => self.safe_wrapper(Wrapper::Divide(data_type.clone())),
-            BinOp::Mod => self.safe_wrapper(Wrapper::Mod(data_type.clone())),
+            BinOp::Divide | BinOp::Mod => {
+                return self.recondition_integer_div_expr(data_type, op, l, r)
+            }
             op => return BinOpExpr::new(op, l, r).into(),
         };
 
         FnCallExpr::new(name, vec![l, r]).into_node(data_type)
     }
 
+    fn recondition_integer_div_expr(
+        &mut self,
+        data_type: DataType,
+        op: BinOp,
+        l: ExprNode,
+        r: ExprNode,
+    ) -> ExprNode {
+        let scalar_type = data_type.as_scalar().unwrap();
+        let lit = |v: i32| match scalar_type {
+            ScalarType::I32 => splat(&data_type, Lit::I32(v)),
+            _ => splat(&data_type, Lit::U32(v as u32)),
+        };
+
+        match self.safety {
+            SafetyStrategy::Select if is_duplicable(&l) && is_duplicable(&r) => {
+                // Divisors that would be unsafe are replaced with 1, and negative dividends are
+                // left unchanged by `%`, like in the helper functions
+                let mut unsafe_divisor: ExprNode = match (op, scalar_type) {
+                    (BinOp::Mod, ScalarType::I32) => {
+                        BinOpExpr::new(BinOp::LessEqual, r.clone(), lit(0)).into()
+                    }
+                    _ => BinOpExpr::new(BinOp::Equal, r.clone(), lit(0)).into(),
+                };
+
+                if let (BinOp::Divide, ScalarType::I32) = (op, scalar_type) {
+                    let overflow = BinOpExpr::new(
+                        BinOp::BitAnd,
+                        BinOpExpr::new(BinOp::Equal, l.clone(), lit(i32::MIN)),
+                        BinOpExpr::new(BinOp::Equal, r.clone(), lit(-1)),
+                    );
+                    unsafe_divisor = BinOpExpr::new(BinOp::BitOr, unsafe_divisor, overflow).into();
+                }
+
+                let result: ExprNode =
+                    BinOpExpr::new(op, l.clone(), select(r, lit(1), unsafe_divisor)).into();
+
+                if let (BinOp::Mod, ScalarType::I32) = (op, scalar_type) {
+                    let non_negative = BinOpExpr::new(BinOp::GreaterEqual, l.clone(), lit(0));
+                    select(l, result, non_negative)
+                } else {
+                    result
+                }
+            }
+            SafetyStrategy::Mask => {
+                // Clearing the sign bit and setting the lowest bit gives a positive divisor
+                let (l, r) = match scalar_type {
+                    ScalarType::I32 => {
+                        let r = BinOpExpr::new(
+                            BinOp::BitOr,
+                            BinOpExpr::new(BinOp::BitAnd, r, lit(i32::MAX)),
+                            lit(1),
+                        );
+
+                        let l = match op {
+                            BinOp::Mod => BinOpExpr::new(BinOp::BitAnd, l, lit(i32::MAX)).into(),
+                            _ => l,
+                        };
+
+                        (l, r)
+                    }
+                    _ => (l, BinOpExpr::new(BinOp::BitOr, r, lit(1))),
+                };
+
+                BinOpExpr::new(op, l, r).into()
+            }
+            SafetyStrategy::Helpers | SafetyStrategy::Select => {
+                let wrapper = match op {
+                    BinOp::Divide => Wrapper::Divide(data_type.clone()),
+                    _ => Wrapper::Mod(data_type.clone()),
+                };
+
+                FnCallExpr::new(self.safe_wrapper(wrapper), vec![l, r]).into_node(data_type)
+            }
+        }
+    }
+
     fn recondition_floating_point_bin_op_expr(
         &mut self,
         data_type: DataType,
@@ -585,7 +757,50 @@ impl Reconditioner {
 
     fn safe_wrapper(&mut self, wrapper: Wrapper) -> String {
         let ident = wrapper.to_string();
-        self.wrappers.insert(wrapper);
+        self.wrappers.entry(ident.clone()).or_insert(wrapper);
         ident
     }
 }
+
+/// Whether an expression can be evaluated more than once without changing the behaviour of the
+/// program or making it much larger.
+fn is_duplicable(node: &ExprNode) -> bool {
+    match &node.expr {
+        Expr::Lit(_) | Expr::Var(_) => true,
+        Expr::Postfix(expr) => {
+            matches!(expr.postfix, Postfix::Member(_)) && is_duplicable(&expr.inner)
+        }
+        _ => false,
+    }
+}
+
+/// Returns an expression of type `data_type` with every component set to `lit`.
+fn splat(data_type: &DataType, lit: Lit) -> ExprNode {
+    match data_type {
+        DataType::Vector(_, _) => TypeConsExpr::new(data_type.clone(), vec![lit.into()]).into(),
+        _ => lit.into(),
+    }
+}
+
+/// Builds a call to `select`, which evaluates to `t` where `condition` is true and `f` elsewhere.
+fn select(f: ExprNode, t: ExprNode, condition: impl Into<ExprNode>) -> ExprNode {
+    let data_type = f.data_type.dereference().clone();
+    FnCallExpr::new("select", vec![f, t, condition.into()]).into_node(data_type)
+}
+
+/// Converts the target of an assignment into the equivalent expression, e.g. for taking its address.
+fn lhs_to_expr(node: &LhsExprNode) -> ExprNode {
+    let expr = match &node.expr {
+        LhsExpr::Ident(ident) => VarExpr::new(ident).into(),
+        LhsExpr::Postfix(inner, postfix) => {
+            PostfixExpr::new(lhs_to_expr(inner), postfix.clone()).into()
+        }
+        LhsExpr::Deref(inner) => UnOpExpr::new(UnOp::Deref, lhs_to_expr(inner)).into(),
+        LhsExpr::AddressOf(inner) => UnOpExpr::new(UnOp::AddressOf, lhs_to_expr(inner)).into(),
+    };
+
+    ExprNode {
+        data_type: node.data_type.clone(),
+        expr,
+    }
+}
@@ -7,6 +7,7 @@ mod index;
 mod minus;
 mod modulo;
 mod plus;
+mod shift;
 mod times;
 
 use ast::{
@@ -22,6 +23,7 @@ pub use index::index;
 pub use minus::minus;
 pub use modulo::modulo;
 pub use plus::plus;
+pub use shift::shift;
 pub use times::times;
 
 /// Wraps the given expression in a call to `any()` if it is a vector.
@@ -0,0 +1,37 @@
+use ast::{
+    BinOp, BinOpExpr, DataType, ExprNode, FnDecl, FnInput, FnOutput, Lit, ReturnStatement,
+    ScalarType, TypeConsExpr, VarExpr,
+};
+
+pub fn shift(name: String, op: BinOp, data_type: &DataType) -> FnDecl {
+    let shift_type = data_type.map(ScalarType::U32);
+    let shift_bound: ExprNode = match &shift_type {
+        DataType::Scalar(_) => Lit::U32(32).into(),
+        DataType::Vector(_, _) => {
+            TypeConsExpr::new(shift_type.clone(), vec![Lit::U32(32).into()]).into()
+        }
+        ty => unreachable!("no shift wrapper for type {ty}"),
+    };
+
+    let shift_value = BinOpExpr::new(
+        BinOp::Mod,
+        VarExpr::new("b").into_node(shift_type.clone()),
+        shift_bound,
+    );
+
+    FnDecl {
+        attrs: vec![],
+        name,
+        inputs: vec![
+            FnInput::new("a", data_type.clone()),
+            FnInput::new("b", shift_type),
+        ],
+        output: Some(FnOutput::new(data_type.clone())),
+        body: vec![ReturnStatement::new(BinOpExpr::new(
+            op,
+            VarExpr::new("a").into_node(data_type.clone()),
+            shift_value,
+        ))
+        .into()],
+    }
+}
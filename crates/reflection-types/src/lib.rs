@@ -1,4 +1,8 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
 use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
 
 pub struct ResourceData<'a> {
     pub name: &'a str,
@@ -9,6 +13,224 @@ pub struct ResourceData<'a> {
 #[derive(Clone, Debug, Decode, Encode)]
 pub struct PipelineDescription {
     pub resources: Vec<PipelineResource>,
+    /// Name of the compute entry point to run.
+    pub entry_point: String,
+    /// Workgroup size of the entry point.
+    ///
+    /// Only a single workgroup is dispatched, so this is also the total number of invocations.
+    pub workgroup_size: [u32; 3],
+    /// Number of times to dispatch the entry point.
+    ///
+    /// After each dispatch except the last, the contents of each storage buffer are copied into
+    /// the uniform buffer at the same position (i.e. the first storage buffer into the first
+    /// uniform buffer, and so on), so that the output of one dispatch is the input of the next.
+    pub dispatches: u32,
+    /// Whether to compile and execute the pipeline a second time once any pipeline caches are
+    /// warm, and check that the outputs are identical to the first execution.
+    pub cache_check: bool,
+    /// Number of additional pipelines to compile concurrently on the same device after the first
+    /// execution, each of which is executed and checked against the first execution's outputs.
+    ///
+    /// Values below 2 disable the check.
+    pub concurrent_compiles: u32,
+    /// Whether to time some extra dispatches of the pipeline after its outputs have been read.
+    pub dispatch_timing: Option<DispatchTiming>,
+    /// Whether the implementation should insert bounds checks into the shader.
+    ///
+    /// Disabling them is only safe for shaders that never access memory out of bounds, such as
+    /// reconditioned programs.
+    pub bounds_checks: bool,
+    /// Vertex and fragment entry points to link into a render pipeline, if the shader has both.
+    pub render: Option<RenderPipelineDescription>,
+    /// The `var<push_constant>` variable, if the shader has one.
+    pub push_constants: Option<PushConstants>,
+    /// Whether the shader enables the `subgroups` extension, which the device must support.
+    pub subgroups: bool,
+}
+
+/// Number of extra dispatches to run when timing a pipeline.
+///
+/// Each timed dispatch is submitted and waited for on its own, and the median time is reported.
+/// The warm-up dispatches run first and aren't timed, so that caches and clock speeds have
+/// settled by the time the timed ones run.
+#[derive(Clone, Copy, Debug, Decode, Encode)]
+pub struct DispatchTiming {
+    pub warmup: u32,
+    pub repetitions: u32,
+}
+
+/// Key of the push constant data in a shader's input data, which is otherwise keyed by
+/// `group:binding`.
+pub const PUSH_CONSTANTS_KEY: &str = "push_constants";
+
+/// Push constants are only supported by wgpu (as a native-only feature), so configurations which
+/// don't support them skip shaders that use them.
+#[derive(Clone, Debug, Decode, Encode)]
+pub struct PushConstants {
+    pub name: String,
+    /// Size in bytes of the push constant data.
+    pub size: u32,
+    /// Data to set the push constants to, which is zeroed if not set.
+    pub init: Option<Vec<u8>>,
+}
+
+/// Width and height in pixels of the texture that the render pipeline draws into.
+///
+/// The contents of the texture are read back after drawing, and appended to the output buffers.
+pub const RENDER_TARGET_SIZE: u32 = 16;
+
+#[derive(Clone, Debug, Decode, Encode)]
+pub struct RenderPipelineDescription {
+    pub vertex_entry_point: String,
+    pub fragment_entry_point: String,
+    /// `(group, binding)` pairs of the resources used by either of the entry points.
+    ///
+    /// Only these are included in the render pipeline's layout, so the others must be left out of
+    /// its bind groups.
+    pub bindings: Vec<(u32, u32)>,
+    pub state: RenderState,
+    pub format: RenderTargetFormat,
+}
+
+/// Texel format of the render target.
+///
+/// Only formats with 32-bit unsigned integer channels are supported, so that the `vec4<u32>`
+/// colour output of generated programs is written without any conversion. Channels which the
+/// format doesn't have are dropped.
+#[derive(Clone, Copy, Debug, Default, Decode, Encode, PartialEq, Eq)]
+pub enum RenderTargetFormat {
+    R32Uint,
+    Rg32Uint,
+    #[default]
+    Rgba32Uint,
+}
+
+impl RenderTargetFormat {
+    /// Returns the number of channels in each texel.
+    pub fn channels(&self) -> u32 {
+        match self {
+            RenderTargetFormat::R32Uint => 1,
+            RenderTargetFormat::Rg32Uint => 2,
+            RenderTargetFormat::Rgba32Uint => 4,
+        }
+    }
+
+    /// Returns the size of each texel in bytes.
+    pub fn texel_size(&self) -> u32 {
+        self.channels() * 4
+    }
+}
+
+impl FromStr for RenderTargetFormat {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<RenderTargetFormat, Self::Err> {
+        match value {
+            "r32uint" => Ok(RenderTargetFormat::R32Uint),
+            "rg32uint" => Ok(RenderTargetFormat::Rg32Uint),
+            "rgba32uint" => Ok(RenderTargetFormat::Rgba32Uint),
+            _ => Err("render target format must be `r32uint`, `rg32uint` or `rgba32uint`"),
+        }
+    }
+}
+
+impl Display for RenderTargetFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderTargetFormat::R32Uint => f.write_str("r32uint"),
+            RenderTargetFormat::Rg32Uint => f.write_str("rg32uint"),
+            RenderTargetFormat::Rgba32Uint => f.write_str("rgba32uint"),
+        }
+    }
+}
+
+/// Fixed function state of the render pipeline.
+///
+/// The render target has an integer format, which can't be blended, so the colour output is only
+/// affected by the write mask and the depth and stencil tests.
+#[derive(Clone, Debug, Decode, Encode, PartialEq, Serialize, Deserialize)]
+pub struct RenderState {
+    /// Channels of the render target which are written, as a mask of r = 1, g = 2, b = 4 and
+    /// a = 8.
+    pub write_mask: u32,
+    /// State of the `depth24plus-stencil8` attachment, if there is one.
+    pub depth_stencil: Option<DepthStencilState>,
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        RenderState {
+            write_mask: 0xf,
+            depth_stencil: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq, Serialize, Deserialize)]
+pub struct DepthStencilState {
+    pub depth_compare: CompareFunction,
+    pub depth_write: bool,
+    /// Value that the depth attachment is cleared to before drawing.
+    pub depth_clear: f32,
+    pub stencil_compare: CompareFunction,
+    pub stencil_fail_op: StencilOperation,
+    pub depth_fail_op: StencilOperation,
+    pub pass_op: StencilOperation,
+    pub stencil_reference: u32,
+    /// Value that the stencil attachment is cleared to before drawing.
+    pub stencil_clear: u32,
+}
+
+#[derive(Clone, Copy, Debug, Decode, Encode, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareFunction {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl CompareFunction {
+    pub const ALL: [CompareFunction; 8] = [
+        CompareFunction::Never,
+        CompareFunction::Less,
+        CompareFunction::Equal,
+        CompareFunction::LessEqual,
+        CompareFunction::Greater,
+        CompareFunction::NotEqual,
+        CompareFunction::GreaterEqual,
+        CompareFunction::Always,
+    ];
+}
+
+#[derive(Clone, Copy, Debug, Decode, Encode, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StencilOperation {
+    Keep,
+    Zero,
+    Replace,
+    Invert,
+    IncrementClamp,
+    DecrementClamp,
+    IncrementWrap,
+    DecrementWrap,
+}
+
+impl StencilOperation {
+    pub const ALL: [StencilOperation; 8] = [
+        StencilOperation::Keep,
+        StencilOperation::Zero,
+        StencilOperation::Replace,
+        StencilOperation::Invert,
+        StencilOperation::IncrementClamp,
+        StencilOperation::DecrementClamp,
+        StencilOperation::IncrementWrap,
+        StencilOperation::DecrementWrap,
+    ];
 }
 
 #[derive(Clone, Debug, Decode, Encode, PartialEq, Eq)]
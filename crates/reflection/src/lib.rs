@@ -1,5 +1,21 @@
-use ast::{Module, StorageClass, VarQualifier};
-pub use types::{PipelineDescription, PipelineResource, ResourceData, ResourceKind};
+#[cfg(feature = "naga")]
+pub mod naga;
+
+use std::collections::HashSet;
+
+use ast::visit::{self, Visitor};
+use ast::{
+    Expr, ExprNode, FnAttr, FnDecl, LhsExpr, LhsExprNode, Module, ShaderStage, Statement,
+    StorageClass, VarQualifier,
+};
+pub use types::{
+    CompareFunction, DepthStencilState, DispatchTiming, PipelineDescription, PipelineResource,
+    PushConstants, RenderPipelineDescription, RenderState, RenderTargetFormat, ResourceData,
+    ResourceKind, StencilOperation, PUSH_CONSTANTS_KEY, RENDER_TARGET_SIZE,
+};
+
+/// Number of elements given to a runtime sized array when there is no input data for the buffer.
+pub const DEFAULT_RUNTIME_ARRAY_LEN: u32 = 16;
 
 pub fn reflect(
     module: &Module,
@@ -7,18 +23,28 @@ pub fn reflect(
 ) -> (PipelineDescription, Vec<common::Type>) {
     let mut resources = vec![];
     let mut types = vec![];
+    let mut push_constants = None;
 
     for var in &module.vars {
         if let Some(VarQualifier { storage_class, .. }) = &var.qualifier {
             let kind = match storage_class {
                 StorageClass::Uniform => ResourceKind::UniformBuffer,
                 StorageClass::Storage => ResourceKind::StorageBuffer,
+                StorageClass::PushConstant => {
+                    let type_desc = common::Type::try_from(&var.data_type)
+                        .expect("invalid type for push constants");
+
+                    push_constants = Some(PushConstants {
+                        name: var.name.clone(),
+                        size: type_desc.buffer_size(),
+                        init: None,
+                    });
+
+                    continue;
+                }
                 _ => continue,
             };
 
-            let type_desc =
-                common::Type::try_from(&var.data_type).expect("invalid type for pipeline resource");
-
             let group = var
                 .group_index()
                 .expect("resource variable must have group attribute");
@@ -31,8 +57,26 @@ pub fn reflect(
                 name: &var.name,
                 group,
                 binding,
-            })
-            .map(|mut init| {
+            });
+
+            // The length of a runtime sized array is determined by the size of the input data, so
+            // that the same inputs always produce the same buffer layout
+            let type_desc = if var.data_type.is_runtime_sized() {
+                let len = match &init {
+                    Some(init) => {
+                        common::Type::runtime_array_len(&var.data_type, init.len() as u32)
+                            .expect("invalid type for pipeline resource")
+                    }
+                    None => DEFAULT_RUNTIME_ARRAY_LEN,
+                };
+
+                common::Type::with_runtime_array_len(&var.data_type, len)
+            } else {
+                common::Type::try_from(&var.data_type)
+            }
+            .expect("invalid type for pipeline resource");
+
+            let init = init.map(|mut init| {
                 init.resize(type_desc.buffer_size() as usize, 0);
                 init
             });
@@ -50,5 +94,119 @@ pub fn reflect(
         }
     }
 
-    (PipelineDescription { resources }, types)
+    let find_entry_point = |stage: ShaderStage| {
+        module.functions.iter().find(|decl| {
+            decl.attrs
+                .iter()
+                .any(|attr| matches!(attr, FnAttr::Stage(it) if *it == stage))
+        })
+    };
+
+    let entry_point = find_entry_point(ShaderStage::Compute);
+
+    let render = find_entry_point(ShaderStage::Vertex)
+        .zip(find_entry_point(ShaderStage::Fragment))
+        .map(|(vertex, fragment)| {
+            let used = used_names(module, &[vertex, fragment]);
+            RenderPipelineDescription {
+                vertex_entry_point: vertex.name.clone(),
+                fragment_entry_point: fragment.name.clone(),
+                bindings: resources
+                    .iter()
+                    .filter(|it| used.contains(&it.name))
+                    .map(|it| (it.group, it.binding))
+                    .collect(),
+                state: RenderState::default(),
+                format: RenderTargetFormat::default(),
+            }
+        });
+
+    let workgroup_size = entry_point
+        .and_then(|decl| {
+            decl.attrs.iter().find_map(|attr| match attr {
+                FnAttr::WorkgroupSize(x) => Some([*x, 1, 1]),
+                _ => None,
+            })
+        })
+        .unwrap_or([1, 1, 1]);
+
+    (
+        PipelineDescription {
+            resources,
+            entry_point: entry_point
+                .map(|decl| decl.name.clone())
+                .unwrap_or_else(|| "main".to_owned()),
+            workgroup_size,
+            dispatches: 1,
+            cache_check: false,
+            concurrent_compiles: 0,
+            dispatch_timing: None,
+            bounds_checks: true,
+            render,
+            push_constants,
+            subgroups: module.enables.iter().any(|it| it == "subgroups"),
+        },
+        types,
+    )
+}
+
+/// Returns the names referenced by some functions, either directly or through the functions that
+/// they call.
+///
+/// Local variables aren't distinguished from module-scope ones, so a local which shadows a
+/// resource makes it look like it's used.
+fn used_names(module: &Module, fns: &[&FnDecl]) -> HashSet<String> {
+    #[derive(Default)]
+    struct Uses {
+        names: HashSet<String>,
+        calls: Vec<String>,
+    }
+
+    impl Visitor for Uses {
+        fn visit_stmt(&mut self, stmt: &Statement) {
+            if let Statement::FnCall(stmt) = stmt {
+                self.calls.push(stmt.ident.clone());
+            }
+
+            visit::walk_stmt(self, stmt);
+        }
+
+        fn visit_lhs_expr(&mut self, node: &LhsExprNode) {
+            if let LhsExpr::Ident(name) = &node.expr {
+                self.names.insert(name.clone());
+            }
+
+            visit::walk_lhs_expr(self, node);
+        }
+
+        fn visit_expr(&mut self, node: &ExprNode) {
+            match &node.expr {
+                Expr::Var(expr) => {
+                    self.names.insert(expr.ident.clone());
+                }
+                Expr::FnCall(expr) => self.calls.push(expr.ident.clone()),
+                _ => {}
+            }
+
+            visit::walk_expr(self, node);
+        }
+    }
+
+    let mut uses = Uses::default();
+    let mut visited = HashSet::new();
+    let mut pending = fns.to_vec();
+
+    while let Some(decl) = pending.pop() {
+        if !visited.insert(&decl.name) {
+            continue;
+        }
+
+        uses.visit_fn_decl(decl);
+
+        for ident in uses.calls.drain(..) {
+            pending.extend(module.functions.iter().find(|it| it.name == ident));
+        }
+    }
+
+    uses.names
 }
@@ -0,0 +1,253 @@
+//! Reflection using naga, for hand-written shaders that wgslsmith's parser doesn't support.
+
+use ::naga::front::wgsl;
+use ::naga::valid::{Capabilities, ValidationFlags, Validator};
+use ::naga::{
+    AddressSpace, ArraySize, ConstantInner, Handle, Module, ScalarKind, ScalarValue, ShaderStage,
+    Type, TypeInner, VectorSize,
+};
+
+use crate::{
+    PipelineDescription, PipelineResource, PushConstants, RenderPipelineDescription, RenderState,
+    RenderTargetFormat, ResourceData, ResourceKind, DEFAULT_RUNTIME_ARRAY_LEN,
+};
+
+/// Reflects the first compute entry point in a WGSL shader, and the first vertex and fragment
+/// entry points if it has both.
+///
+/// Only resources which are used by the entry point are included, since the others won't be in
+/// the pipeline's layout. Returns an error message if the shader is invalid or uses a resource
+/// type that the harness doesn't support.
+pub fn reflect(
+    source: &str,
+    mut init: impl FnMut(ResourceData<'_>) -> Option<Vec<u8>>,
+) -> Result<(PipelineDescription, Vec<common::Type>), String> {
+    let module = wgsl::parse_str(source).map_err(|e| e.emit_to_string(source))?;
+    let info = Validator::new(ValidationFlags::default(), Capabilities::all())
+        .validate(&module)
+        .map_err(|e| format!("shader validation failed: {e}"))?;
+
+    let (index, entry_point) = module
+        .entry_points
+        .iter()
+        .enumerate()
+        .find(|(_, it)| it.stage == ShaderStage::Compute)
+        .ok_or("shader has no compute entry point")?;
+
+    let entry_point_info = info.get_entry_point(index);
+
+    let find_entry_point = |stage: ShaderStage| {
+        module
+            .entry_points
+            .iter()
+            .enumerate()
+            .find(|(_, it)| it.stage == stage)
+    };
+
+    let render_entry_points =
+        find_entry_point(ShaderStage::Vertex).zip(find_entry_point(ShaderStage::Fragment));
+
+    let mut resources = vec![];
+    let mut types = vec![];
+    let mut render_bindings = vec![];
+    let mut push_constants = None;
+
+    for (handle, var) in module.global_variables.iter() {
+        let kind = match var.space {
+            AddressSpace::Uniform => ResourceKind::UniformBuffer,
+            AddressSpace::Storage { .. } => ResourceKind::StorageBuffer,
+            AddressSpace::PushConstant => {
+                let type_desc = convert_type(&module, var.ty, DEFAULT_RUNTIME_ARRAY_LEN)?;
+                push_constants = Some(PushConstants {
+                    name: var.name.clone().unwrap_or_default(),
+                    size: type_desc.buffer_size(),
+                    init: None,
+                });
+                continue;
+            }
+            _ => continue,
+        };
+
+        let used_by_render = match render_entry_points {
+            Some(((vertex, _), (fragment, _))) => {
+                !info.get_entry_point(vertex)[handle].is_empty()
+                    || !info.get_entry_point(fragment)[handle].is_empty()
+            }
+            None => false,
+        };
+
+        if entry_point_info[handle].is_empty() {
+            // The render pipeline uses the same buffers as the compute pipeline
+            if used_by_render {
+                return Err(format!(
+                    "resource `{}` is used by the render entry points but not the compute entry point",
+                    var.name.as_deref().unwrap_or_default()
+                ));
+            }
+
+            continue;
+        }
+
+        let binding = var
+            .binding
+            .as_ref()
+            .ok_or("resource variable must have a binding")?;
+
+        if used_by_render {
+            render_bindings.push((binding.group, binding.binding));
+        }
+
+        let name = var.name.clone().unwrap_or_default();
+
+        let init = init(ResourceData {
+            name: &name,
+            group: binding.group,
+            binding: binding.binding,
+        });
+
+        // Like in the main reflection, the length of a runtime sized array is determined by the
+        // size of the input data
+        let runtime_array_len = match (runtime_array_layout(&module, var.ty), &init) {
+            (Some((offset, stride)), Some(init)) => {
+                ((init.len() as u32).saturating_sub(offset) / stride).max(1)
+            }
+            _ => DEFAULT_RUNTIME_ARRAY_LEN,
+        };
+
+        let type_desc = convert_type(&module, var.ty, runtime_array_len)?;
+
+        // The harness computes buffer layouts itself, which doesn't account for explicit layout
+        // attributes
+        if let TypeInner::Struct { span, .. } = module.types[var.ty].inner {
+            if runtime_array_layout(&module, var.ty).is_none() && span != type_desc.size() {
+                return Err(format!("unsupported layout for resource `{name}`"));
+            }
+        }
+
+        let init = init.map(|mut init| {
+            init.resize(type_desc.buffer_size() as usize, 0);
+            init
+        });
+
+        resources.push(PipelineResource {
+            name,
+            kind,
+            group: binding.group,
+            binding: binding.binding,
+            init,
+            size: type_desc.size(),
+        });
+
+        types.push(type_desc);
+    }
+
+    let render =
+        render_entry_points.map(|((_, vertex), (_, fragment))| RenderPipelineDescription {
+            vertex_entry_point: vertex.name.clone(),
+            fragment_entry_point: fragment.name.clone(),
+            bindings: render_bindings,
+            state: RenderState::default(),
+            format: RenderTargetFormat::default(),
+        });
+
+    Ok((
+        PipelineDescription {
+            resources,
+            entry_point: entry_point.name.clone(),
+            workgroup_size: entry_point.workgroup_size,
+            dispatches: 1,
+            cache_check: false,
+            concurrent_compiles: 0,
+            dispatch_timing: None,
+            bounds_checks: true,
+            render,
+            push_constants,
+            // This version of naga can't parse `enable` directives, so any shader it accepts
+            // doesn't use subgroups
+            subgroups: false,
+        },
+        types,
+    ))
+}
+
+/// Returns the offset and stride of the runtime sized array at the end of a type, if it has one.
+fn runtime_array_layout(module: &Module, ty: Handle<Type>) -> Option<(u32, u32)> {
+    match &module.types[ty].inner {
+        TypeInner::Array {
+            size: ArraySize::Dynamic,
+            stride,
+            ..
+        } => Some((0, *stride)),
+        TypeInner::Struct { members, .. } => {
+            let last = members.last()?;
+            let (offset, stride) = runtime_array_layout(module, last.ty)?;
+            Some((last.offset + offset, stride))
+        }
+        _ => None,
+    }
+}
+
+fn convert_type(
+    module: &Module,
+    ty: Handle<Type>,
+    runtime_array_len: u32,
+) -> Result<common::Type, String> {
+    let ty = &module.types[ty];
+    let unsupported = || format!("unsupported type for pipeline resource: {ty:?}");
+
+    let scalar_type = |kind: ScalarKind, width: u8| match (kind, width) {
+        (ScalarKind::Sint, 4) => Ok(common::ScalarType::I32),
+        (ScalarKind::Uint, 4) => Ok(common::ScalarType::U32),
+        (ScalarKind::Float, 4) => Ok(common::ScalarType::F32),
+        _ => Err(unsupported()),
+    };
+
+    let type_desc = match &ty.inner {
+        TypeInner::Scalar { kind, width } => common::Type::Scalar {
+            scalar_type: scalar_type(*kind, *width)?,
+        },
+        TypeInner::Vector { size, kind, width } => common::Type::Vector {
+            size: match size {
+                VectorSize::Bi => common::VectorSize::N2,
+                VectorSize::Tri => common::VectorSize::N3,
+                VectorSize::Quad => common::VectorSize::N4,
+            },
+            scalar_type: scalar_type(*kind, *width)?,
+        },
+        TypeInner::Array { base, size, .. } => {
+            let size = match size {
+                ArraySize::Constant(constant) => match module.constants[*constant].inner {
+                    ConstantInner::Scalar {
+                        value: ScalarValue::Uint(size),
+                        ..
+                    } => size as u32,
+                    ConstantInner::Scalar {
+                        value: ScalarValue::Sint(size),
+                        ..
+                    } => size as u32,
+                    _ => return Err(unsupported()),
+                },
+                ArraySize::Dynamic => runtime_array_len,
+            };
+
+            common::Type::Array {
+                size,
+                element_type: Box::new(convert_type(module, *base, runtime_array_len)?),
+            }
+        }
+        TypeInner::Struct { members, .. } => common::Type::Struct {
+            members: members
+                .iter()
+                .map(|member| {
+                    Ok(common::StructMember {
+                        name: member.name.clone().unwrap_or_default(),
+                        type_desc: convert_type(module, member.ty, runtime_array_len)?,
+                    })
+                })
+                .collect::<Result<_, String>>()?,
+        },
+        _ => return Err(unsupported()),
+    };
+
+    Ok(type_desc)
+}
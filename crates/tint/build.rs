@@ -23,7 +23,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     println!("cargo:rustc-link-search=native={}", dawn_lib_dir.display());
 
-    let libs = ["tint_diagnostic_utils", "tint"];
+    let libs = ["tint_diagnostic_utils", "tint", "SPIRV-Tools"];
 
     let target_family = env::var("CARGO_CFG_TARGET_FAMILY")?;
 
@@ -48,9 +48,11 @@ fn main() -> Result<(), Box<dyn Error>> {
         .file("src/lib.cpp")
         .include(&dawn_src_dir)
         .include(dawn_src_dir.join("include"))
+        .include(dawn_src_dir.join("third_party/vulkan-deps/spirv-tools/src/include"))
         .define("TINT_BUILD_WGSL_READER", "1")
         .define("TINT_BUILD_HLSL_WRITER", "1")
-        .define("TINT_BUILD_MSL_WRITER", "1");
+        .define("TINT_BUILD_MSL_WRITER", "1")
+        .define("TINT_BUILD_SPV_WRITER", "1");
 
     if build_target.contains("msvc") {
         build.flag("/std:c++17").flag("/MD");
@@ -1,71 +1,130 @@
+#include <spirv-tools/libspirv.hpp>
 #include <tint/tint.h>
 
 #include "src/tint/writer/flatten_bindings.h"
 #include "lib.h"
+#include "tint/src/lib.rs.h"
 
-bool validate_shader(const char* source) {
-    auto source_file = std::make_unique<tint::Source::File>("[memory]", source);
-    auto program = std::make_unique<tint::Program>(tint::reader::wgsl::Parse(source_file.get()));
-    return program->IsValid();
+namespace {
+
+// Tint reports internal compiler errors through a global callback rather than failing the
+// compilation, so we stash them here and attach them to the result of the current call.
+thread_local bool ice_reported = false;
+thread_local std::string ice_diagnostics;
+
+std::string format_diagnostics(const tint::diag::List& diagnostics) {
+    return tint::diag::Formatter().format(diagnostics);
 }
 
-std::unique_ptr<std::string> compile_shader_to_hlsl(const char* source) {
-    auto source_file = std::make_unique<tint::Source::File>("[memory]", source);
-    auto program = std::make_unique<tint::Program>(tint::reader::wgsl::Parse(source_file.get()));
+void report_ice(const tint::diag::List& diagnostics) {
+    ice_reported = true;
+    ice_diagnostics += format_diagnostics(diagnostics);
+}
 
-    if (!program->IsValid()) {
-        return nullptr;
+void reset_ice_state() {
+    static bool reporter_installed = [] {
+        tint::SetInternalCompilerErrorReporter(&report_ice);
+        return true;
+    }();
+
+    (void)reporter_installed;
+
+    ice_reported = false;
+    ice_diagnostics.clear();
+}
+
+TextResult make_text_result(bool success, const std::string& output, const std::string& diagnostics) {
+    return TextResult{
+        success,
+        ice_reported,
+        rust::String(output),
+        rust::String(diagnostics + ice_diagnostics),
+    };
+}
+
+BinaryResult make_binary_result(bool success, const std::vector<uint32_t>& output, const std::string& diagnostics) {
+    rust::Vec<uint32_t> words;
+    words.reserve(output.size());
+    for (auto word : output) {
+        words.push_back(word);
     }
 
+    return BinaryResult{
+        success,
+        ice_reported,
+        std::move(words),
+        rust::String(diagnostics + ice_diagnostics),
+    };
+}
+
+bool rename(tint::Program* program, tint::transform::Renamer::Target target, std::string& diagnostics) {
     tint::transform::Manager transform_manager;
     tint::transform::DataMap transform_inputs;
 
-    transform_inputs.Add<tint::transform::Renamer::Config>(
-        tint::transform::Renamer::Target::kHlslKeywords,
-        /* preserve_unicode */ false
-    );
+    transform_inputs.Add<tint::transform::Renamer::Config>(target, /* preserve_unicode */ false);
     transform_manager.Add<tint::transform::Renamer>();
 
-    auto transformed = transform_manager.Run(program.get(), std::move(transform_inputs));
+    auto transformed = transform_manager.Run(program, std::move(transform_inputs));
     if (!transformed.program.IsValid()) {
-        return nullptr;
+        diagnostics = format_diagnostics(transformed.program.Diagnostics());
+        return false;
     }
 
     *program = std::move(transformed.program);
 
+    return true;
+}
+
+}
+
+TextResult validate_shader(const char* source) {
+    reset_ice_state();
+
+    auto source_file = std::make_unique<tint::Source::File>("[memory]", source);
+    auto program = std::make_unique<tint::Program>(tint::reader::wgsl::Parse(source_file.get()));
+
+    return make_text_result(program->IsValid(), "", format_diagnostics(program->Diagnostics()));
+}
+
+TextResult compile_shader_to_hlsl(const char* source) {
+    reset_ice_state();
+
+    auto source_file = std::make_unique<tint::Source::File>("[memory]", source);
+    auto program = std::make_unique<tint::Program>(tint::reader::wgsl::Parse(source_file.get()));
+
+    if (!program->IsValid()) {
+        return make_text_result(false, "", format_diagnostics(program->Diagnostics()));
+    }
+
+    std::string diagnostics;
+    if (!rename(program.get(), tint::transform::Renamer::Target::kHlslKeywords, diagnostics)) {
+        return make_text_result(false, "", diagnostics);
+    }
+
     tint::writer::hlsl::Options gen_options = {};
     auto result = tint::writer::hlsl::Generate(program.get(), gen_options);
     if (!result.success) {
-        return nullptr;
+        return make_text_result(false, "", result.error);
     }
 
-    return std::make_unique<std::string>(std::move(result.hlsl));
+    return make_text_result(true, result.hlsl, "");
 }
 
-std::unique_ptr<std::string> compile_shader_to_msl(const char* source) {
+TextResult compile_shader_to_msl(const char* source) {
+    reset_ice_state();
+
     auto source_file = std::make_unique<tint::Source::File>("[memory]", source);
     auto program = std::make_unique<tint::Program>(tint::reader::wgsl::Parse(source_file.get()));
 
     if (!program->IsValid()) {
-        return nullptr;
+        return make_text_result(false, "", format_diagnostics(program->Diagnostics()));
     }
 
-    tint::transform::Manager transform_manager;
-    tint::transform::DataMap transform_inputs;
-
-    transform_inputs.Add<tint::transform::Renamer::Config>(
-        tint::transform::Renamer::Target::kMslKeywords,
-        /* preserve_unicode */ false
-    );
-    transform_manager.Add<tint::transform::Renamer>();
-
-    auto transformed = transform_manager.Run(program.get(), std::move(transform_inputs));
-    if (!transformed.program.IsValid()) {
-        return nullptr;
+    std::string diagnostics;
+    if (!rename(program.get(), tint::transform::Renamer::Target::kMslKeywords, diagnostics)) {
+        return make_text_result(false, "", diagnostics);
     }
 
-    *program = std::move(transformed.program);
-
     const tint::Program* input_program = program.get();
     auto flattened = tint::writer::FlattenBindings(program.get());
     if (flattened) {
@@ -75,8 +134,54 @@ std::unique_ptr<std::string> compile_shader_to_msl(const char* source) {
     tint::writer::msl::Options gen_options;
     auto result = tint::writer::msl::Generate(input_program, gen_options);
     if (!result.success) {
-        return nullptr;
+        return make_text_result(false, "", result.error);
     }
 
-    return std::make_unique<std::string>(std::move(result.msl));
+    return make_text_result(true, result.msl, "");
+}
+
+BinaryResult compile_shader_to_spirv(const char* source) {
+    reset_ice_state();
+
+    auto source_file = std::make_unique<tint::Source::File>("[memory]", source);
+    auto program = std::make_unique<tint::Program>(tint::reader::wgsl::Parse(source_file.get()));
+
+    if (!program->IsValid()) {
+        return make_binary_result(false, {}, format_diagnostics(program->Diagnostics()));
+    }
+
+    tint::writer::spirv::Options gen_options;
+    auto result = tint::writer::spirv::Generate(program.get(), gen_options);
+    if (!result.success) {
+        return make_binary_result(false, {}, result.error);
+    }
+
+    return make_binary_result(true, result.spirv, "");
+}
+
+TextResult validate_spirv(rust::Slice<const uint32_t> words) {
+    std::string diagnostics;
+
+    spvtools::SpirvTools tools(SPV_ENV_VULKAN_1_1);
+    tools.SetMessageConsumer([&](spv_message_level_t, const char*, const spv_position_t& position, const char* message) {
+        diagnostics += "error: word " + std::to_string(position.index) + ": " + message + "\n";
+    });
+
+    bool valid = tools.Validate(words.data(), words.size());
+
+    return TextResult{valid, false, rust::String(), rust::String(diagnostics)};
+}
+
+TextResult disassemble_spirv(rust::Slice<const uint32_t> words) {
+    std::string diagnostics;
+
+    spvtools::SpirvTools tools(SPV_ENV_VULKAN_1_1);
+    tools.SetMessageConsumer([&](spv_message_level_t, const char*, const spv_position_t& position, const char* message) {
+        diagnostics += "error: word " + std::to_string(position.index) + ": " + message + "\n";
+    });
+
+    std::string text;
+    bool success = tools.Disassemble(words.data(), words.size(), &text, SPV_BINARY_TO_TEXT_OPTION_FRIENDLY_NAMES);
+
+    return TextResult{success, false, rust::String(text), rust::String(diagnostics)};
 }
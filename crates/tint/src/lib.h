@@ -1,10 +1,20 @@
 #pragma once
 
-#include <memory>
-#include <string>
+#include <cstdint>
 
-bool validate_shader(const char* source);
+#include "rust/cxx.h"
 
-std::unique_ptr<std::string> compile_shader_to_hlsl(const char* source);
+struct TextResult;
+struct BinaryResult;
 
-std::unique_ptr<std::string> compile_shader_to_msl(const char* source);
+TextResult validate_shader(const char* source);
+
+TextResult compile_shader_to_hlsl(const char* source);
+
+TextResult compile_shader_to_msl(const char* source);
+
+BinaryResult compile_shader_to_spirv(const char* source);
+
+TextResult validate_spirv(rust::Slice<const uint32_t> words);
+
+TextResult disassemble_spirv(rust::Slice<const uint32_t> words);
@@ -1,26 +1,114 @@
 use std::ffi::CString;
+use std::fmt::Display;
 
 #[cxx::bridge]
 mod ffi {
+    struct TextResult {
+        success: bool,
+        ice: bool,
+        output: String,
+        diagnostics: String,
+    }
+
+    struct BinaryResult {
+        success: bool,
+        ice: bool,
+        output: Vec<u32>,
+        diagnostics: String,
+    }
+
     unsafe extern "C++" {
         include!("tint/src/lib.h");
-        unsafe fn validate_shader(source: *const c_char) -> bool;
-        unsafe fn compile_shader_to_hlsl(source: *const c_char) -> UniquePtr<CxxString>;
-        unsafe fn compile_shader_to_msl(source: *const c_char) -> UniquePtr<CxxString>;
+        unsafe fn validate_shader(source: *const c_char) -> TextResult;
+        unsafe fn compile_shader_to_hlsl(source: *const c_char) -> TextResult;
+        unsafe fn compile_shader_to_msl(source: *const c_char) -> TextResult;
+        unsafe fn compile_shader_to_spirv(source: *const c_char) -> BinaryResult;
+        fn validate_spirv(words: &[u32]) -> TextResult;
+        fn disassemble_spirv(words: &[u32]) -> TextResult;
     }
 }
 
+/// Error produced when tint fails to validate or translate a shader.
+#[derive(Debug)]
+pub struct Error {
+    /// Whether tint reported an internal compiler error, as opposed to rejecting the shader.
+    pub ice: bool,
+    /// Formatted diagnostics reported by tint.
+    pub diagnostics: String,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ice {
+            write!(f, "tint internal compiler error")?;
+        } else {
+            write!(f, "tint error")?;
+        }
+
+        if !self.diagnostics.is_empty() {
+            write!(f, ":\n{}", self.diagnostics.trim_end())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
 pub fn validate_shader(source: &str) -> bool {
+    check_shader(source).is_ok()
+}
+
+/// Parses and resolves the shader, returning tint's diagnostics if it is not valid.
+pub fn check_shader(source: &str) -> Result<(), Error> {
     let source = CString::new(source).unwrap();
     unsafe { ffi::validate_shader(source.as_ptr()) }
+        .into_result()
+        .map(|_| ())
+}
+
+pub fn compile_shader_to_hlsl(source: &str) -> Result<String, Error> {
+    let source = CString::new(source).unwrap();
+    unsafe { ffi::compile_shader_to_hlsl(source.as_ptr()) }.into_result()
 }
 
-pub fn compile_shader_to_hlsl(source: &str) -> String {
+pub fn compile_shader_to_msl(source: &str) -> Result<String, Error> {
     let source = CString::new(source).unwrap();
-    unsafe { ffi::compile_shader_to_hlsl(source.as_ptr()) }.to_string()
+    unsafe { ffi::compile_shader_to_msl(source.as_ptr()) }.into_result()
 }
 
-pub fn compile_shader_to_msl(source: &str) -> String {
+/// Compiles the shader to a SPIR-V binary, returned as a list of words.
+pub fn compile_shader_to_spirv(source: &str) -> Result<Vec<u32>, Error> {
     let source = CString::new(source).unwrap();
-    unsafe { ffi::compile_shader_to_msl(source.as_ptr()) }.to_string()
+    unsafe { ffi::compile_shader_to_spirv(source.as_ptr()) }.into_result()
+}
+
+/// Validates a SPIR-V binary using SPIRV-Tools (i.e. the equivalent of running `spirv-val`).
+pub fn validate_spirv(words: &[u32]) -> Result<(), Error> {
+    ffi::validate_spirv(words).into_result().map(|_| ())
+}
+
+/// Disassembles a SPIR-V binary into its textual form (i.e. the equivalent of running `spirv-dis`).
+pub fn disassemble_spirv(words: &[u32]) -> Result<String, Error> {
+    ffi::disassemble_spirv(words).into_result()
+}
+
+impl ffi::TextResult {
+    fn into_result(self) -> Result<String, Error> {
+        into_result(self.success, self.ice, self.output, self.diagnostics)
+    }
+}
+
+impl ffi::BinaryResult {
+    fn into_result(self) -> Result<Vec<u32>, Error> {
+        into_result(self.success, self.ice, self.output, self.diagnostics)
+    }
+}
+
+fn into_result<T>(success: bool, ice: bool, output: T, diagnostics: String) -> Result<T, Error> {
+    if success && !ice {
+        Ok(output)
+    } else {
+        Err(Error { ice, diagnostics })
+    }
 }
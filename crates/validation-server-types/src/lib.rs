@@ -1,16 +1,99 @@
+#[cfg(feature = "server")]
+pub mod server;
+mod stats;
+
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
 use bincode::{Decode, Encode};
 
-#[derive(Debug, Encode, Decode)]
+pub use common::auth::check_token;
+pub use stats::StatsRecorder;
+
+/// Version of the validation server protocol.
+///
+/// This must be incremented whenever the request or response types change. `Request::Hello` and
+/// `HelloResponse` must always start with the `version` field, so that clients can always detect a
+/// mismatched server.
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// Maximum size of a single frame.
+///
+/// This protects against allocating huge buffers if we receive a garbage length prefix.
+pub const MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Backend {
-    Hlsl,
+    Hlsl(HlslCompiler),
     Msl,
 }
 
+/// Compiler to use for validating HLSL.
+#[derive(Clone, Copy, Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum HlslCompiler {
+    Fxc,
+    Dxc,
+    /// Validate with both compilers, failing if either of them rejects the shader.
+    Both,
+}
+
 #[derive(Debug, Encode, Decode)]
 pub enum Request {
+    /// Handshake sent by clients at the start of a connection.
+    ///
+    /// This must remain the first variant. If the server was started with a token, the client must
+    /// present it here before any other requests will be accepted.
+    Hello {
+        version: u32,
+        token: Option<String>,
+    },
     GetCount,
+    /// Resets the request counter and statistics.
     ResetCount,
-    Validate { backend: Backend, source: String },
+    Validate {
+        backend: Backend,
+        source: String,
+    },
+    ValidateBatch {
+        backend: Backend,
+        sources: Vec<String>,
+    },
+    GetStats,
+}
+
+#[derive(Debug, Encode, Decode)]
+pub struct HelloResponse {
+    pub version: u32,
+    pub capabilities: Capabilities,
+}
+
+/// Features supported by a server.
+///
+/// New fields should only be added at the end.
+#[derive(Clone, Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Capabilities {
+    pub fxc: bool,
+    pub dxc: bool,
+    pub metal: bool,
+    /// Maximum number of shaders that can be sent in a single batch request.
+    pub max_batch_size: u32,
+}
+
+impl Capabilities {
+    /// Returns whether the server has the compilers needed for validating the given backend.
+    pub fn supports(&self, backend: Backend) -> bool {
+        match backend {
+            Backend::Hlsl(HlslCompiler::Fxc) => self.fxc,
+            Backend::Hlsl(HlslCompiler::Dxc) => self.dxc,
+            Backend::Hlsl(HlslCompiler::Both) => self.fxc && self.dxc,
+            Backend::Msl => self.metal,
+        }
+    }
 }
 
 #[derive(Debug, Encode, Decode)]
@@ -18,8 +101,186 @@ pub struct GetCountResponse {
     pub count: u64,
 }
 
+/// Statistics about the shaders validated since the server started (or was last reset).
+#[derive(Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StatsResponse {
+    /// Total number of requests received, including non-validation requests.
+    pub requests: u64,
+    pub successes: u64,
+    pub failures: u64,
+    pub timeouts: u64,
+    pub crashes: u64,
+    pub average_time: Duration,
+    /// 95th percentile compile time, over recently validated shaders.
+    pub p95_time: Duration,
+    pub uptime: Duration,
+}
+
 #[derive(Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ValidateResponse {
     Success,
     Failure(String),
+    /// The compiler did not finish within the server's timeout.
+    Timeout,
+    /// The compiler crashed, with a description of how it exited.
+    Crashed(String),
+}
+
+impl ValidateResponse {
+    /// Truncates the compiler output in a failure or crash response to at most `max_len` bytes.
+    pub fn truncate_output(&mut self, max_len: usize) {
+        if let ValidateResponse::Failure(output) | ValidateResponse::Crashed(output) = self {
+            if output.len() > max_len {
+                let mut end = max_len;
+                while !output.is_char_boundary(end) {
+                    end -= 1;
+                }
+
+                output.truncate(end);
+                output.push_str("\n[output truncated]");
+            }
+        }
+    }
+
+    /// Shortens the compiler output so that the response fits in a single frame.
+    pub fn truncate_to_frame(&mut self) {
+        if encoded_len(&*self) > MAX_FRAME_SIZE {
+            self.truncate_output(MAX_FRAME_SIZE / 2);
+        }
+    }
+}
+
+#[derive(Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValidateBatchResponse {
+    /// Results for each shader in the batch, in the same order as the request.
+    pub results: Vec<BatchItemResult>,
+}
+
+impl ValidateBatchResponse {
+    /// Shortens the compiler output in the results so that the response fits in a single frame.
+    ///
+    /// Otherwise a large batch of shaders that produce a lot of errors could exceed
+    /// [`MAX_FRAME_SIZE`], and the client would get no results at all.
+    pub fn truncate_to_frame(&mut self) {
+        if encoded_len(&*self) > MAX_FRAME_SIZE {
+            // Split half of the frame between the results, leaving plenty of room for the rest
+            let max_len = MAX_FRAME_SIZE / 2 / self.results.len().max(1);
+            for item in &mut self.results {
+                item.result.truncate_output(max_len);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatchItemResult {
+    pub result: ValidateResponse,
+    /// Time taken to compile the shader.
+    pub time: Duration,
+}
+
+/// Writes a message to the stream, prefixed with its encoded length as a little-endian `u32`.
+///
+/// Connections to the validation server are persistent, so each request and response is sent as a
+/// single frame.
+pub fn write_frame<T: Encode>(writer: &mut impl Write, message: T) -> io::Result<()> {
+    let mut bytes = vec![0; 4];
+
+    bincode::encode_into_std_write(message, &mut bytes, bincode::config::standard())
+        .map_err(invalid_data)?;
+
+    let len = bytes.len() - 4;
+    if len > MAX_FRAME_SIZE {
+        return Err(invalid_data(format!("frame too large ({len} bytes)")));
+    }
+
+    bytes[..4].copy_from_slice(&(len as u32).to_le_bytes());
+
+    writer.write_all(&bytes)?;
+    writer.flush()
+}
+
+/// Reads a single frame from the stream.
+///
+/// Returns `None` if the stream was closed before the start of the next frame.
+pub fn read_frame<T: Decode>(reader: &mut impl Read) -> io::Result<Option<T>> {
+    let mut len = [0; 4];
+
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(invalid_data(format!("frame too large ({len} bytes)")));
+    }
+
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes)?;
+
+    let (message, _) =
+        bincode::decode_from_slice(&bytes, bincode::config::standard()).map_err(invalid_data)?;
+
+    Ok(Some(message))
+}
+
+fn encoded_len<T: Encode>(message: T) -> usize {
+    bincode::encode_to_vec(message, bincode::config::standard())
+        .map(|bytes| bytes.len())
+        .unwrap_or(0)
+}
+
+fn invalid_data(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn truncate_output() {
+        let mut response = ValidateResponse::Failure("error: bad".to_owned());
+        response.truncate_output(5);
+        assert!(
+            matches!(response, ValidateResponse::Failure(e) if e == "error\n[output truncated]")
+        );
+
+        let mut response = ValidateResponse::Crashed("short".to_owned());
+        response.truncate_output(5);
+        assert!(matches!(response, ValidateResponse::Crashed(e) if e == "short"));
+    }
+
+    #[test]
+    fn truncate_output_at_char_boundary() {
+        let mut response = ValidateResponse::Failure("aé".to_owned());
+        response.truncate_output(2);
+        assert!(matches!(response, ValidateResponse::Failure(e) if e == "a\n[output truncated]"));
+    }
+
+    #[test]
+    fn batch_response_fits_in_frame() {
+        let output = "x".repeat(MAX_FRAME_SIZE / 2 + 1);
+        let mut response = ValidateBatchResponse {
+            results: (0..2)
+                .map(|_| BatchItemResult {
+                    result: ValidateResponse::Failure(output.clone()),
+                    time: Duration::ZERO,
+                })
+                .collect(),
+        };
+
+        response.truncate_to_frame();
+
+        assert!(encoded_len(&response) <= MAX_FRAME_SIZE);
+        assert!(write_frame(&mut io::sink(), response).is_ok());
+    }
 }
@@ -0,0 +1,332 @@
+//! Connection handling shared by the validation server and the MSL server.
+//!
+//! Each server only needs to provide a function for validating a single shader. Shaders are
+//! validated in worker processes (see [`Worker`]), which run the same executable with `--worker`.
+
+#[cfg(feature = "http")]
+mod http;
+mod worker;
+
+use std::cell::RefCell;
+use std::io::{self, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bincode::Encode;
+use clap::Args;
+use threadpool::ThreadPool;
+
+use crate::{
+    Backend, BatchItemResult, Capabilities, GetCountResponse, HelloResponse, Request,
+    StatsRecorder, StatsResponse, ValidateBatchResponse, ValidateResponse, PROTOCOL_VERSION,
+};
+
+use self::worker::{Worker, WorkerRequest};
+
+#[derive(Args)]
+pub struct ServerOptions {
+    /// Server bind address.
+    #[clap(short, long, default_value = "localhost:0")]
+    pub address: String,
+
+    /// Number of worker threads to use.
+    ///
+    /// Defaults to the number of available CPUs.
+    #[clap(long)]
+    pub parallelism: Option<usize>,
+
+    /// Timeout in seconds for compiling a single shader.
+    #[clap(long, default_value = "10")]
+    pub timeout: u64,
+
+    /// Maximum number of shaders that clients may send in a single batch request.
+    #[clap(long, default_value = "256")]
+    pub max_batch_size: u32,
+
+    /// Shared secret that clients must present before making requests.
+    ///
+    /// This should be set if the server is reachable by untrusted machines.
+    #[clap(long)]
+    pub token: Option<String>,
+
+    #[clap(short, long)]
+    pub quiet: bool,
+
+    /// Address at which to also serve requests using HTTP and JSON.
+    #[cfg(feature = "http")]
+    #[clap(long)]
+    pub http: Option<String>,
+
+    /// Run as a compiler worker process, receiving requests on stdin.
+    #[clap(long, hide = true)]
+    pub worker: bool,
+}
+
+/// Runs the server until the process is killed.
+///
+/// `capabilities` should describe the compilers that are available to the worker processes.
+/// Requests for any other backends get a failure response.
+pub fn run(options: ServerOptions, capabilities: Capabilities) -> io::Result<()> {
+    let parallelism = options
+        .parallelism
+        .unwrap_or_else(|| std::thread::available_parallelism().unwrap().get());
+
+    let pool = ThreadPool::new(parallelism);
+    println!("Using thread pool with {parallelism} threads");
+
+    let listener = TcpListener::bind(options.address)?;
+    let address = listener.local_addr()?;
+    println!("Server listening at {address}");
+
+    println!("Capabilities: {capabilities:?}");
+
+    let state = Arc::new(State {
+        counter: AtomicU64::new(0),
+        stats: StatsRecorder::new(),
+        capabilities,
+        timeout: Duration::from_secs(options.timeout),
+        token: options.token,
+        quiet: options.quiet,
+    });
+
+    #[cfg(feature = "http")]
+    if let Some(address) = options.http {
+        http::spawn(&address, pool.clone(), state.clone())?;
+    }
+
+    for stream in listener.incoming() {
+        let state = state.clone();
+        pool.execute(move || {
+            let stream = stream.unwrap();
+            if let Err(e) = handle_connection(&stream, &state) {
+                eprintln!("connection error: {e}");
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Entry point for worker processes, which handle requests from the server until stdin is closed.
+///
+/// Any errors from `validate` (e.g. failing to load a compiler) should be returned as
+/// [`ValidateResponse::Failure`], since the server treats the worker exiting as a crash.
+pub fn run_worker(validate: impl Fn(Backend, &str) -> ValidateResponse) -> io::Result<()> {
+    let mut stdin = BufReader::new(io::stdin());
+    let mut stdout = io::stdout();
+
+    while let Some(req) = crate::read_frame::<WorkerRequest>(&mut stdin)? {
+        crate::write_frame(&mut stdout, validate(req.backend, &req.source))?;
+    }
+
+    Ok(())
+}
+
+struct State {
+    counter: AtomicU64,
+    stats: StatsRecorder,
+    capabilities: Capabilities,
+    timeout: Duration,
+    token: Option<String>,
+    quiet: bool,
+}
+
+impl State {
+    /// Returns whether a client presenting the given token may make requests.
+    fn authorize(&self, token: Option<&str>) -> bool {
+        crate::check_token(self.token.as_deref(), token)
+    }
+}
+
+enum Response {
+    Hello(HelloResponse),
+    GetCount(GetCountResponse),
+    Validate(ValidateResponse),
+    ValidateBatch(ValidateBatchResponse),
+    GetStats(StatsResponse),
+}
+
+impl Encode for Response {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        match self {
+            Response::Hello(inner) => inner.encode(encoder),
+            Response::GetCount(inner) => inner.encode(encoder),
+            Response::Validate(inner) => inner.encode(encoder),
+            Response::ValidateBatch(inner) => inner.encode(encoder),
+            Response::GetStats(inner) => inner.encode(encoder),
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+impl serde::Serialize for Response {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Response::Hello(inner) => inner.capabilities.serialize(serializer),
+            Response::GetCount(inner) => inner.count.serialize(serializer),
+            Response::Validate(inner) => inner.serialize(serializer),
+            Response::ValidateBatch(inner) => inner.serialize(serializer),
+            Response::GetStats(inner) => inner.serialize(serializer),
+        }
+    }
+}
+
+/// Handles requests from a client until it closes the connection.
+///
+/// Note that each connection occupies a worker thread for as long as it remains open.
+fn handle_connection(stream: &TcpStream, state: &State) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut writer = stream;
+
+    let mut authorized = state.token.is_none();
+
+    while let Some(req) = crate::read_frame::<Request>(&mut reader)? {
+        if !authorized {
+            match &req {
+                Request::Hello { token, .. } if state.authorize(token.as_deref()) => {
+                    authorized = true;
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        "client did not present a valid token",
+                    ))
+                }
+            }
+        }
+
+        if let Some(res) = handle_request(req, state)? {
+            crate::write_frame(&mut writer, res)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Handles a single request, returning the response to send back to the client (if any).
+fn handle_request(req: Request, state: &State) -> io::Result<Option<Response>> {
+    let State {
+        counter,
+        stats,
+        capabilities,
+        timeout,
+        quiet,
+        ..
+    } = state;
+
+    counter.fetch_add(1, Ordering::SeqCst);
+
+    let validate = |backend, source| {
+        if !capabilities.supports(backend) {
+            let result = ValidateResponse::Failure(format!("unsupported backend: {backend:?}"));
+            return (result, Duration::ZERO);
+        }
+
+        let start = Instant::now();
+        let result = validate_in_worker(backend, source, *timeout, *quiet).unwrap_or_else(|e| {
+            eprintln!("failed to run compiler worker: {e}");
+            ValidateResponse::Failure(format!("failed to run compiler worker: {e}"))
+        });
+        let time = start.elapsed();
+        stats.record(&result, time);
+        (result, time)
+    };
+
+    let res = match req {
+        Request::Hello { version, .. } => {
+            if version != PROTOCOL_VERSION {
+                eprintln!("client protocol version ({version}) does not match server");
+            }
+
+            Response::Hello(HelloResponse {
+                version: PROTOCOL_VERSION,
+                capabilities: capabilities.clone(),
+            })
+        }
+        Request::GetCount => Response::GetCount(GetCountResponse {
+            count: counter.load(Ordering::SeqCst),
+        }),
+        Request::ResetCount => {
+            counter.store(0, Ordering::SeqCst);
+            stats.reset();
+            return Ok(None);
+        }
+        Request::Validate { backend, source } => {
+            let (mut result, _) = validate(backend, source);
+            result.truncate_to_frame();
+            Response::Validate(result)
+        }
+        Request::ValidateBatch { backend, sources } => {
+            if sources.len() > capabilities.max_batch_size as usize {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("batch of {} shaders exceeds maximum size", sources.len()),
+                ));
+            }
+
+            let results = sources
+                .into_iter()
+                .map(|source| {
+                    let (result, time) = validate(backend, source);
+                    BatchItemResult { result, time }
+                })
+                .collect();
+
+            let mut response = ValidateBatchResponse { results };
+            response.truncate_to_frame();
+            Response::ValidateBatch(response)
+        }
+        Request::GetStats => Response::GetStats(stats.snapshot(counter.load(Ordering::SeqCst))),
+    };
+
+    Ok(Some(res))
+}
+
+thread_local! {
+    /// Each thread in the pool has its own worker process, which is replaced if it times out or
+    /// crashes.
+    static WORKER: RefCell<Option<Worker>> = RefCell::new(None);
+}
+
+fn validate_in_worker(
+    backend: Backend,
+    source: String,
+    timeout: Duration,
+    quiet: bool,
+) -> io::Result<ValidateResponse> {
+    let response = WORKER.with(|worker| {
+        let mut worker = worker.borrow_mut();
+
+        let response = match &mut *worker {
+            Some(worker) => worker.validate(backend, source, timeout),
+            None => worker
+                .insert(Worker::spawn()?)
+                .validate(backend, source, timeout),
+        };
+
+        if matches!(
+            response,
+            ValidateResponse::Timeout | ValidateResponse::Crashed(_)
+        ) {
+            *worker = None;
+        }
+
+        Ok::<_, io::Error>(response)
+    })?;
+
+    if !quiet {
+        match &response {
+            ValidateResponse::Success => {}
+            ValidateResponse::Failure(e) => println!("{e}"),
+            ValidateResponse::Timeout => println!("compiler timed out"),
+            ValidateResponse::Crashed(e) => println!("compiler crashed: {e}"),
+        }
+    }
+
+    Ok(response)
+}
@@ -0,0 +1,119 @@
+use std::io::{self, Read};
+use std::sync::Arc;
+use std::thread;
+
+use serde::Deserialize;
+use threadpool::ThreadPool;
+use tiny_http::{Header, Method, Request as HttpRequest, Response as HttpResponse, Server};
+
+use super::State;
+use crate::{Backend, Request, MAX_FRAME_SIZE};
+
+#[derive(Deserialize)]
+struct ValidateBody {
+    backend: Backend,
+    source: String,
+}
+
+#[derive(Deserialize)]
+struct ValidateBatchBody {
+    backend: Backend,
+    sources: Vec<String>,
+}
+
+/// Starts an HTTP server on a background thread, which accepts JSON equivalents of the
+/// requests supported by the TCP protocol.
+///
+/// Requests are dispatched to the same thread pool as TCP connections.
+pub(super) fn spawn(address: &str, pool: ThreadPool, state: Arc<State>) -> io::Result<()> {
+    let server = Server::http(address).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+    println!("Serving http on {}", server.server_addr());
+
+    thread::spawn(move || {
+        for req in server.incoming_requests() {
+            let state = state.clone();
+            pool.execute(move || {
+                if let Err(e) = handle(req, &state) {
+                    eprintln!("failed to handle http request: {e}");
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+fn handle(mut req: HttpRequest, state: &State) -> io::Result<()> {
+    let token = req
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "));
+
+    if !state.authorize(token) {
+        return req.respond(HttpResponse::empty(401));
+    }
+
+    let body = match req.method() {
+        Method::Post => match read_body(&mut req)? {
+            Some(body) => body,
+            None => return req.respond(HttpResponse::empty(413)),
+        },
+        _ => vec![],
+    };
+
+    let request = match (req.method(), req.url()) {
+        (Method::Get, "/capabilities") => Ok(Request::Hello {
+            version: crate::PROTOCOL_VERSION,
+            token: None,
+        }),
+        (Method::Get, "/count") => Ok(Request::GetCount),
+        (Method::Get, "/stats") => Ok(Request::GetStats),
+        (Method::Post, "/validate") => serde_json::from_slice(&body)
+            .map(|ValidateBody { backend, source }| Request::Validate { backend, source }),
+        (Method::Post, "/validate-batch") => {
+            serde_json::from_slice(&body).map(|ValidateBatchBody { backend, sources }| {
+                Request::ValidateBatch { backend, sources }
+            })
+        }
+        _ => return req.respond(HttpResponse::empty(404)),
+    };
+
+    let request = match request {
+        Ok(request) => request,
+        Err(e) => {
+            return req.respond(HttpResponse::from_string(e.to_string()).with_status_code(400))
+        }
+    };
+
+    match super::handle_request(request, state) {
+        Ok(Some(res)) => {
+            let json = serde_json::to_string(&res)?;
+            let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+            req.respond(HttpResponse::from_string(json).with_header(header))
+        }
+        Ok(None) => req.respond(HttpResponse::empty(204)),
+        Err(e) => req.respond(HttpResponse::from_string(e.to_string()).with_status_code(400)),
+    }
+}
+
+/// Reads the body of a request, returning `None` if it is larger than [`MAX_FRAME_SIZE`], which
+/// is the same limit as for requests over TCP.
+fn read_body(req: &mut HttpRequest) -> io::Result<Option<Vec<u8>>> {
+    if req.body_length().map_or(false, |len| len > MAX_FRAME_SIZE) {
+        return Ok(None);
+    }
+
+    // The length isn't known in advance for chunked requests
+    let mut body = vec![];
+    req.as_reader()
+        .take(MAX_FRAME_SIZE as u64 + 1)
+        .read_to_end(&mut body)?;
+
+    if body.len() > MAX_FRAME_SIZE {
+        return Ok(None);
+    }
+
+    Ok(Some(body))
+}
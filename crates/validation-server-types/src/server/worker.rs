@@ -0,0 +1,111 @@
+use std::io::{self, BufReader};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+use std::{env, thread};
+
+use bincode::{Decode, Encode};
+
+use crate::{Backend, ValidateResponse};
+
+#[derive(Encode, Decode)]
+pub struct WorkerRequest {
+    pub backend: Backend,
+    pub source: String,
+}
+
+/// A child process used to run the shader compilers.
+///
+/// Compilers occasionally hang or crash on pathological inputs. Running them out of process means
+/// that we can kill a stuck compile and replace the worker, without taking down the server.
+pub struct Worker {
+    child: Child,
+    stdin: ChildStdin,
+    responses: Receiver<io::Result<Option<ValidateResponse>>>,
+}
+
+impl Worker {
+    /// Spawns a worker by running the current executable with `--worker`.
+    ///
+    /// The worker is given the same arguments as the server, so that it sees the same options.
+    pub fn spawn() -> io::Result<Worker> {
+        let mut child = Command::new(env::current_exe()?)
+            .args(env::args_os().skip(1))
+            .arg("--worker")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        let (tx, responses) = mpsc::channel();
+
+        // Responses are read on a separate thread so that we can wait for them with a timeout
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            loop {
+                let response = crate::read_frame(&mut reader);
+                let done = !matches!(response, Ok(Some(_)));
+                if tx.send(response).is_err() || done {
+                    break;
+                }
+            }
+        });
+
+        Ok(Worker {
+            child,
+            stdin,
+            responses,
+        })
+    }
+
+    /// Validates the shader using the worker.
+    ///
+    /// If this returns [`ValidateResponse::Timeout`] or [`ValidateResponse::Crashed`], the worker
+    /// has been killed and must not be used again.
+    pub fn validate(
+        &mut self,
+        backend: Backend,
+        source: String,
+        timeout: Duration,
+    ) -> ValidateResponse {
+        if let Err(e) = crate::write_frame(&mut self.stdin, WorkerRequest { backend, source }) {
+            return self.crashed(format!("failed to send request to worker: {e}"));
+        }
+
+        match self.responses.recv_timeout(timeout) {
+            Ok(Ok(Some(response))) => response,
+            Ok(Ok(None)) | Err(RecvTimeoutError::Disconnected) => {
+                self.crashed("worker exited unexpectedly".to_owned())
+            }
+            Ok(Err(e)) => self.crashed(format!("failed to read response from worker: {e}")),
+            Err(RecvTimeoutError::Timeout) => {
+                self.kill();
+                ValidateResponse::Timeout
+            }
+        }
+    }
+
+    fn crashed(&mut self, message: String) -> ValidateResponse {
+        let status = self.kill();
+        ValidateResponse::Crashed(match status {
+            Some(status) => format!("{message} ({status})"),
+            None => message,
+        })
+    }
+
+    fn kill(&mut self) -> Option<std::process::ExitStatus> {
+        // The worker may have already exited, in which case this fails but `wait` will still
+        // give us the exit status
+        let _ = self.child.kill();
+        self.child.wait().ok()
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{StatsResponse, ValidateResponse};
+
+/// Number of recent compile times to keep for computing percentiles.
+const MAX_SAMPLES: usize = 10_000;
+
+/// Collects statistics about the shaders validated by a server.
+pub struct StatsRecorder {
+    start: Instant,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    successes: u64,
+    failures: u64,
+    timeouts: u64,
+    crashes: u64,
+    total_time: Duration,
+    samples: VecDeque<Duration>,
+}
+
+impl StatsRecorder {
+    pub fn new() -> StatsRecorder {
+        StatsRecorder {
+            start: Instant::now(),
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Records the result of validating a single shader.
+    pub fn record(&self, result: &ValidateResponse, time: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+
+        match result {
+            ValidateResponse::Success => inner.successes += 1,
+            ValidateResponse::Failure(_) => inner.failures += 1,
+            ValidateResponse::Timeout => inner.timeouts += 1,
+            ValidateResponse::Crashed(_) => inner.crashes += 1,
+        }
+
+        inner.total_time += time;
+
+        if inner.samples.len() == MAX_SAMPLES {
+            inner.samples.pop_front();
+        }
+
+        inner.samples.push_back(time);
+    }
+
+    /// Clears all recorded results. The server uptime is not affected.
+    pub fn reset(&self) {
+        *self.inner.lock().unwrap() = Inner::default();
+    }
+
+    pub fn snapshot(&self, requests: u64) -> StatsResponse {
+        let inner = self.inner.lock().unwrap();
+
+        let count = inner.successes + inner.failures + inner.timeouts + inner.crashes;
+        let average_time = if count == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(inner.total_time.as_secs_f64() / count as f64)
+        };
+
+        // The p95 is computed from recent samples only, so that it reflects the current state of
+        // the server during long campaigns
+        let mut samples = inner.samples.iter().copied().collect::<Vec<_>>();
+        samples.sort_unstable();
+        let p95_time = match samples.len() {
+            0 => Duration::ZERO,
+            n => samples[(n * 95 / 100).min(n - 1)],
+        };
+
+        StatsResponse {
+            requests,
+            successes: inner.successes,
+            failures: inner.failures,
+            timeouts: inner.timeouts,
+            crashes: inner.crashes,
+            average_time,
+            p95_time,
+            uptime: self.start.elapsed(),
+        }
+    }
+}
+
+impl Default for StatsRecorder {
+    fn default() -> Self {
+        StatsRecorder::new()
+    }
+}
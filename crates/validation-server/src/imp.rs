@@ -1,106 +1,74 @@
-use std::io::{BufReader, BufWriter, Write};
-use std::net::TcpListener;
+use std::ffi::c_void;
+use std::io::Write;
+use std::path::Path;
 use std::process::{Command, Stdio};
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
 use std::{env, ptr};
 
-use bincode::Encode;
 use clap::Parser;
-use color_eyre::eyre;
+use color_eyre::eyre::{self, eyre};
 use tempfile::NamedTempFile;
-use threadpool::ThreadPool;
-use types::{GetCountResponse, Request, ValidateResponse};
-use windows::core::PCSTR;
+use types::server::{self, ServerOptions};
+use types::{Backend, Capabilities, HlslCompiler, ValidateResponse};
+use windows::core::{Interface, GUID, HRESULT, PCSTR, PWSTR};
+use windows::Win32::Graphics::Direct3D::Dxc::{
+    CLSID_DxcCompiler, DxcBuffer, IDxcCompiler3, IDxcResult, DXC_CP_UTF8,
+};
 use windows::Win32::Graphics::Direct3D::Fxc::D3DCompile;
+use windows::Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryA};
 
 #[derive(Parser)]
 pub struct Options {
-    /// Server bind address.
-    #[clap(short, long, default_value = "localhost:0")]
-    address: String,
-
-    /// Number of worker threads to use.
-    ///
-    /// Defaults to the number of available CPUs.
-    #[clap(long)]
-    parallelism: Option<usize>,
-
-    #[clap(short, long)]
-    quiet: bool,
+    #[clap(flatten)]
+    server: ServerOptions,
 }
 
 pub fn run() -> eyre::Result<()> {
     let options = Options::parse();
-    let parallelism = options
-        .parallelism
-        .unwrap_or_else(|| std::thread::available_parallelism().unwrap().get());
-
-    let pool = ThreadPool::new(parallelism);
-    println!("Using thread pool with {parallelism} threads");
-
-    let listener = TcpListener::bind(options.address).unwrap();
-    let address = listener.local_addr().unwrap();
-    println!("Server listening at {address}");
-
-    let quiet = options.quiet;
-    let counter = Arc::new(AtomicU64::new(0));
-
-    for stream in listener.incoming() {
-        let counter = counter.clone();
-        counter.fetch_add(1, Ordering::SeqCst);
-        pool.execute(move || {
-            let stream = stream.unwrap();
-
-            let mut reader = BufReader::new(&stream);
-            let mut writer = BufWriter::new(&stream);
-
-            let req: Request =
-                bincode::decode_from_std_read(&mut reader, bincode::config::standard()).unwrap();
-
-            enum Response {
-                GetCount(GetCountResponse),
-                Validate(ValidateResponse),
-            }
-
-            impl Encode for Response {
-                fn encode<E: bincode::enc::Encoder>(
-                    &self,
-                    encoder: &mut E,
-                ) -> Result<(), bincode::error::EncodeError> {
-                    match self {
-                        Response::GetCount(inner) => inner.encode(encoder),
-                        Response::Validate(inner) => inner.encode(encoder),
-                    }
-                }
-            }
-
-            let res = match req {
-                Request::GetCount => Response::GetCount(GetCountResponse {
-                    count: counter.load(Ordering::SeqCst),
-                }),
-                Request::ResetCount => {
-                    counter.store(0, Ordering::SeqCst);
-                    return;
-                }
-                Request::Validate { backend, source } => match backend {
-                    types::Backend::Hlsl => {
-                        Response::Validate(validate_hlsl(&source, quiet).unwrap())
-                    }
-                    types::Backend::Msl => {
-                        Response::Validate(validate_metal(&source, quiet).unwrap())
-                    }
-                },
-            };
 
-            bincode::encode_into_std_write(res, &mut writer, bincode::config::standard()).unwrap();
-        });
+    if options.server.worker {
+        return Ok(server::run_worker(|backend, source| {
+            // Errors such as failing to load a compiler are reported like any other failure, so
+            // that `Crashed` is only used when the worker exits abnormally
+            validate(backend, source)
+                .unwrap_or_else(|e| ValidateResponse::Failure(format!("{e:#}")))
+        })?);
     }
 
-    Ok(())
+    let capabilities = Capabilities {
+        fxc: true,
+        dxc: unsafe { load_dxc() }.is_ok(),
+        metal: Path::new(METAL_PATH).exists(),
+        max_batch_size: options.server.max_batch_size,
+    };
+
+    Ok(server::run(options.server, capabilities)?)
 }
 
-fn validate_hlsl(hlsl: &str, quiet: bool) -> eyre::Result<ValidateResponse> {
+fn validate(backend: Backend, source: &str) -> eyre::Result<ValidateResponse> {
+    match backend {
+        Backend::Hlsl(HlslCompiler::Fxc) => validate_hlsl(source),
+        Backend::Hlsl(HlslCompiler::Dxc) => validate_dxc(source),
+        Backend::Hlsl(HlslCompiler::Both) => {
+            let fxc = validate_hlsl(source)?;
+            let dxc = validate_dxc(source)?;
+            Ok(match (fxc, dxc) {
+                (ValidateResponse::Success, ValidateResponse::Success) => ValidateResponse::Success,
+                (fxc, dxc) => {
+                    let mut messages = String::new();
+                    for (name, response) in [("fxc", fxc), ("dxc", dxc)] {
+                        if let ValidateResponse::Failure(e) = response {
+                            messages.push_str(&format!("[{name}]\n{e}\n"));
+                        }
+                    }
+                    ValidateResponse::Failure(messages)
+                }
+            })
+        }
+        Backend::Msl => validate_metal(source),
+    }
+}
+
+fn validate_hlsl(hlsl: &str) -> eyre::Result<ValidateResponse> {
     unsafe {
         let mut error_messages = None;
 
@@ -124,9 +92,6 @@ fn validate_hlsl(hlsl: &str, quiet: bool) -> eyre::Result<ValidateResponse> {
             let size = blob.GetBufferSize();
             let slice = std::slice::from_raw_parts_mut(ptr as *mut u8, size);
             let messages = String::from_utf8(slice.to_owned())?;
-            if !quiet {
-                println!("{messages}");
-            }
             return Ok(ValidateResponse::Failure(messages));
         }
     }
@@ -134,12 +99,73 @@ fn validate_hlsl(hlsl: &str, quiet: bool) -> eyre::Result<ValidateResponse> {
     Ok(ValidateResponse::Success)
 }
 
-fn validate_metal(metal: &str, quiet: bool) -> eyre::Result<ValidateResponse> {
+type DxcCreateInstanceProc = unsafe extern "system" fn(
+    rclsid: *const GUID,
+    riid: *const GUID,
+    ppv: *mut *mut c_void,
+) -> HRESULT;
+
+/// Loads `dxcompiler.dll` at runtime, so that the server can still be used for FXC and Metal
+/// validation if DXC is not available.
+unsafe fn load_dxc() -> eyre::Result<DxcCreateInstanceProc> {
+    let module = LoadLibraryA(PCSTR("dxcompiler.dll\0".as_ptr()))
+        .map_err(|e| eyre!("failed to load dxcompiler.dll: {e}"))?;
+
+    let proc = GetProcAddress(module, PCSTR("DxcCreateInstance\0".as_ptr()))
+        .ok_or_else(|| eyre!("failed to find DxcCreateInstance in dxcompiler.dll"))?;
+
+    Ok(std::mem::transmute(proc))
+}
+
+fn validate_dxc(hlsl: &str) -> eyre::Result<ValidateResponse> {
+    unsafe {
+        let create_instance = load_dxc()?;
+
+        let mut compiler: Option<IDxcCompiler3> = None;
+        create_instance(
+            &CLSID_DxcCompiler,
+            &IDxcCompiler3::IID,
+            &mut compiler as *mut _ as *mut _,
+        )
+        .ok()?;
+        let compiler = compiler.unwrap();
+
+        let buffer = DxcBuffer {
+            Ptr: hlsl.as_ptr() as _,
+            Size: hlsl.len(),
+            Encoding: DXC_CP_UTF8.0,
+        };
+
+        let mut args: Vec<Vec<u16>> = ["-E", "main", "-T", "cs_6_0"]
+            .iter()
+            .map(|arg| arg.encode_utf16().chain([0]).collect())
+            .collect();
+
+        let args: Vec<PWSTR> = args.iter_mut().map(|arg| PWSTR(arg.as_mut_ptr())).collect();
+
+        let result: IDxcResult = compiler.Compile(&buffer, &args, None)?;
+
+        if result.GetStatus()?.is_err() {
+            let blob = result.GetErrorBuffer()?;
+            let ptr = blob.GetBufferPointer();
+            let size = blob.GetBufferSize();
+            let slice = std::slice::from_raw_parts(ptr as *const u8, size);
+            let messages = String::from_utf8_lossy(slice).into_owned();
+            return Ok(ValidateResponse::Failure(messages));
+        }
+    }
+
+    Ok(ValidateResponse::Success)
+}
+
+const METAL_PATH: &str = "./Metal Developer Tools/macos/bin/metal.exe";
+
+fn validate_metal(metal: &str) -> eyre::Result<ValidateResponse> {
     let mut file = NamedTempFile::new_in(env::current_dir()?)?;
     write!(file, "{metal}")?;
     file.flush()?;
 
-    let output = Command::new("./Metal Developer Tools/macos/bin/metal.exe")
+    let output = Command::new(METAL_PATH)
         .args(["-x", "metal"])
         .args(["-o", "NUL"])
         .arg("-std=osx-metal2.0")
@@ -152,9 +178,6 @@ fn validate_metal(metal: &str, quiet: bool) -> eyre::Result<ValidateResponse> {
     let stderr = String::from_utf8(output.stderr)?;
 
     if output.status.code().unwrap() != 0 {
-        if !quiet {
-            println!("{stderr}");
-        }
         return Ok(ValidateResponse::Failure(stderr));
     }
 
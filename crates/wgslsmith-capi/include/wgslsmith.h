@@ -0,0 +1,31 @@
+#ifndef WGSLSMITH_H
+#define WGSLSMITH_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// Generates a random WGSL program from the given seed.
+//
+// `options_json` is either NULL or a JSON object whose keys are the long option names accepted by
+// `wgslsmith gen` (e.g. `{"max-fns": 3, "enable-pointers": true, "recondition": true}`).
+//
+// Returns a NUL-terminated string which must be freed with `wgslsmith_free_string`, or NULL on
+// failure, in which case `wgslsmith_last_error` describes what went wrong.
+char* wgslsmith_generate(uint64_t seed, const char* options_json);
+
+// Frees a string returned by `wgslsmith_generate`.
+void wgslsmith_free_string(char* str);
+
+// Returns the error message from the last failed call on this thread, or NULL if there was none.
+//
+// The returned string is owned by the library and is valid until the next call on this thread.
+const char* wgslsmith_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,83 @@
+//! C bindings for the generator, so that it can be used in-process by fuzzers written in C/C++.
+//!
+//! See `include/wgslsmith.h` for the API.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use eyre::eyre;
+use generator::Options;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+/// # Safety
+///
+/// `options_json` must either be null or point to a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn wgslsmith_generate(seed: u64, options_json: *const c_char) -> *mut c_char {
+    let options_json = if options_json.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(options_json))
+    };
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| generate(seed, options_json)))
+        .unwrap_or_else(|_| Err(eyre!("generator panicked")));
+
+    match result {
+        Ok(shader) => {
+            set_last_error(None);
+            CString::new(shader).unwrap().into_raw()
+        }
+        Err(e) => {
+            set_last_error(Some(format!("{e:#}")));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `str` must either be null or have been returned by [`wgslsmith_generate`], and must not be
+/// freed more than once.
+#[no_mangle]
+pub unsafe extern "C" fn wgslsmith_free_string(str: *mut c_char) {
+    if !str.is_null() {
+        drop(CString::from_raw(str));
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wgslsmith_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| match &*e.borrow() {
+        Some(e) => e.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+fn set_last_error(message: Option<String>) {
+    // Error messages come from eyre so shouldn't contain NULs, but just in case
+    let message = message.map(|it| CString::new(it.replace('\0', "")).unwrap());
+    LAST_ERROR.with(|e| *e.borrow_mut() = message);
+}
+
+fn generate(seed: u64, options_json: Option<&CStr>) -> eyre::Result<String> {
+    let mut options = match options_json {
+        Some(json) => Options::from_json(json.to_str()?)?,
+        None => Options::default(),
+    };
+
+    options.apply_preset();
+
+    let mut module = generator::generate(seed, &options);
+    if options.recondition {
+        module = generator::recondition(module, &options)?;
+    }
+
+    Ok(generator::to_wgsl(&module))
+}
@@ -0,0 +1,31 @@
+//! WebAssembly bindings for the generator, for use by the browser playground.
+//!
+//! Build with `wasm-pack build crates/wgslsmith-wasm --target web`.
+
+use generator::Options;
+use wasm_bindgen::prelude::*;
+
+/// Generates a random WGSL program from the given seed.
+///
+/// `seed` is a `BigInt` on the JS side. `options_json` is an optional JSON object using the long
+/// option names of `wgslsmith gen` as keys (e.g. `{"max-fns": 3, "recondition": true}`).
+#[wasm_bindgen]
+pub fn generate(seed: u64, options_json: Option<String>) -> Result<String, JsValue> {
+    let mut options = match options_json {
+        Some(json) => Options::from_json(&json).map_err(to_js_error)?,
+        None => Options::default(),
+    };
+
+    options.apply_preset();
+
+    let mut module = generator::generate(seed, &options);
+    if options.recondition {
+        module = generator::recondition(module, &options).map_err(to_js_error)?;
+    }
+
+    Ok(generator::to_wgsl(&module))
+}
+
+fn to_js_error(e: eyre::Report) -> JsValue {
+    JsValue::from_str(&format!("{e:#}"))
+}
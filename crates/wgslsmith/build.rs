@@ -0,0 +1,48 @@
+use std::process::Command;
+
+fn main() {
+    // Submodule commits are read from the gitlinks in the superproject tree, so they are still
+    // available when the submodules haven't been checked out.
+    let revs = [
+        ("WGSLSMITH", "HEAD"),
+        ("DAWN", "HEAD:external/dawn"),
+        ("NAGA", "HEAD:external/naga"),
+        ("WGPU", "HEAD:external/wgpu"),
+    ];
+
+    for (name, rev) in revs {
+        let commit = Command::new("git")
+            .args(["-C", "../..", "rev-parse", rev])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|commit| commit.trim().to_owned())
+            .unwrap_or_else(|| "unknown".to_owned());
+
+        println!("cargo:rustc-env=WGSLSMITH_{name}_COMMIT={commit}");
+    }
+
+    // wgpu and naga are patched to the submodules, so their versions come from the lockfile
+    let lockfile = std::fs::read_to_string("../../Cargo.lock").unwrap_or_default();
+
+    for (name, package) in [("NAGA", "naga"), ("WGPU", "wgpu")] {
+        let version = lockfile
+            .split("[[package]]")
+            .find_map(|entry| {
+                let mut lines = entry.trim().lines();
+                if lines.next()? != format!("name = \"{package}\"") {
+                    return None;
+                }
+                let version = lines.next()?.strip_prefix("version = ")?;
+                Some(version.trim_matches('"').to_owned())
+            })
+            .unwrap_or_else(|| "unknown".to_owned());
+
+        println!("cargo:rustc-env=WGSLSMITH_{name}_VERSION={version}");
+    }
+
+    println!("cargo:rerun-if-changed=../../Cargo.lock");
+    println!("cargo:rerun-if-changed=../../.git/HEAD");
+    println!("cargo:rerun-if-changed=../../.git/index");
+}
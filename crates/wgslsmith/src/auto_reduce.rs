@@ -0,0 +1,118 @@
+//! Background reduction of test cases saved by the fuzzer.
+
+use std::fs::File;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crossbeam_channel::Sender;
+use regex::Regex;
+
+use crate::harness_runner::strip_ansi;
+
+/// Lines containing any of these (ignoring case) are used to identify a crash.
+const CRASH_MARKERS: &[&str] = &["panicked at", "error:", "assertion", "fatal", "abort"];
+
+pub enum Job {
+    Crash {
+        dir: PathBuf,
+        config: String,
+        output: String,
+    },
+    Mismatch {
+        dir: PathBuf,
+    },
+}
+
+/// Pool of threads which run `wgslsmith reduce` on saved test cases.
+///
+/// Each reduction is run in the directory of the test case, so the reduced shader ends up in
+/// `<test case>/reduced/shader.wgsl` and the reducer's output in `<test case>/reduce.log`.
+pub struct Reductions {
+    tx: Sender<Job>,
+}
+
+impl Reductions {
+    pub fn spawn(jobs: usize, config_file: &Path, server: Option<&str>) -> Reductions {
+        let (tx, rx) = crossbeam_channel::unbounded::<Job>();
+
+        for _ in 0..jobs.max(1) {
+            let rx = rx.clone();
+            let config_file = config_file.to_owned();
+            let server = server.map(|it| it.to_owned());
+            thread::spawn(move || {
+                for job in rx {
+                    // Failures are recorded in the log, and shouldn't stop the fuzzer
+                    let _ = reduce(&job, &config_file, server.as_deref());
+                }
+            });
+        }
+
+        Reductions { tx }
+    }
+
+    pub fn submit(&self, job: Job) {
+        // The receivers live as long as the process, so this can't fail
+        self.tx.send(job).unwrap();
+    }
+}
+
+fn reduce(job: &Job, config_file: &Path, server: Option<&str>) -> eyre::Result<()> {
+    let dir = match job {
+        Job::Crash { dir, .. } | Job::Mismatch { dir } => dir,
+    };
+
+    let mut log = File::create(dir.join("reduce.log"))?;
+
+    let mut cmd = Command::new(std::env::current_exe()?);
+    cmd.arg("--config-file").arg(config_file).arg("reduce");
+
+    match job {
+        Job::Crash { config, output, .. } => {
+            let regex = match crash_regex(output) {
+                Some(regex) => regex,
+                None => {
+                    writeln!(log, "couldn't find an error message to reduce against")?;
+                    return Ok(());
+                }
+            };
+
+            cmd.arg("crash")
+                .args(["--config", config])
+                .args(["--regex", &regex]);
+        }
+        Job::Mismatch { .. } => {
+            cmd.arg("mismatch");
+        }
+    }
+
+    if let Some(server) = server {
+        cmd.args(["--server", server]);
+    }
+
+    cmd.arg(dir.join("shader.wgsl"))
+        .arg("--quiet")
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log)
+        .status()?;
+
+    Ok(())
+}
+
+/// Builds a regex matching the first error message in the output of a crash.
+///
+/// Numbers (such as line numbers and addresses) are replaced with wildcards, since they are likely
+/// to change as the shader is reduced.
+fn crash_regex(output: &str) -> Option<String> {
+    let line = output.lines().map(strip_ansi).find(|line| {
+        let line = line.to_lowercase();
+        CRASH_MARKERS.iter().any(|marker| line.contains(marker))
+    })?;
+
+    let escaped = regex::escape(line.trim());
+    let numbers = Regex::new("[0-9]+").unwrap();
+
+    Some(numbers.replace_all(&escaped, r"[0-9]+").into_owned())
+}
@@ -0,0 +1,285 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use ast::stats::Stats;
+use clap::Parser;
+use rand::rngs::OsRng;
+use rand::Rng;
+use serde::Serialize;
+
+#[cfg(all(target_family = "unix", feature = "reducer"))]
+use crate::compiler::{self, Backend, Compiler};
+
+#[derive(Parser)]
+pub struct Options {
+    /// Number of size budgets to benchmark.
+    ///
+    /// The budget doubles at each level, starting from `--base-stmts`.
+    #[clap(long, action, default_value = "6")]
+    levels: u32,
+
+    /// Number of statements in function bodies at the first level.
+    #[clap(long, action, default_value = "4")]
+    base_stmts: u32,
+
+    /// Number of programs to generate at each level.
+    #[clap(long, action, default_value = "5")]
+    samples: usize,
+
+    /// Seed for the first program at each level.
+    ///
+    /// Each level uses the same consecutive seeds starting at this one. If not set, a random seed
+    /// is used.
+    #[clap(long, action)]
+    seed: Option<u64>,
+
+    /// Generator options as a JSON object, using the long option names of `wgslsmith gen` as keys.
+    ///
+    /// The function statement counts are overridden by the size budget.
+    #[clap(long, action)]
+    gen_options: Option<String>,
+
+    /// Recondition the programs before compiling them, and include the reconditioning time.
+    #[clap(long, action)]
+    recondition: bool,
+
+    /// Compilers to measure compile times for, by translating the programs to every backend.
+    ///
+    /// If not set, both tint and naga are measured.
+    #[cfg(all(target_family = "unix", feature = "reducer"))]
+    #[clap(
+        long,
+        value_enum,
+        action,
+        use_value_delimiter(true),
+        require_value_delimiter(true)
+    )]
+    compilers: Vec<Compiler>,
+
+    /// Path to a file to write the measurements for each program to, as JSON.
+    #[clap(long, action)]
+    json: Option<PathBuf>,
+}
+
+/// Measurements for a single generated program.
+#[derive(Serialize)]
+struct Sample {
+    level: u32,
+    budget: u32,
+    seed: u64,
+    nodes: usize,
+    wgsl_bytes: usize,
+    gen_ms: f64,
+    recondition_ms: Option<f64>,
+    /// Time taken by each compiler to translate the program to every backend, or `None` if it
+    /// failed.
+    compile_ms: BTreeMap<String, Option<f64>>,
+}
+
+pub fn run(options: Options) -> eyre::Result<()> {
+    let mut base_options = match &options.gen_options {
+        Some(json) => generator::Options::from_json(json)?,
+        None => generator::Options::default(),
+    };
+
+    base_options.apply_preset();
+    base_options.recondition |= options.recondition;
+
+    let start = options.seed.unwrap_or_else(|| OsRng.gen());
+    let mut samples = vec![];
+    let mut rejected = 0;
+
+    println!("starting from seed {start}");
+
+    for level in 0..options.levels {
+        let budget = options.base_stmts << level;
+        let gen_options = generator::Options {
+            fn_min_stmts: budget,
+            fn_max_stmts: budget,
+            ..base_options.clone()
+        };
+
+        for seed in (0..options.samples as u64).map(|i| start.wrapping_add(i)) {
+            match measure(&options, &gen_options, level, budget, seed) {
+                Some(sample) => samples.push(sample),
+                None => rejected += 1,
+            }
+        }
+    }
+
+    print_table(&options, &samples);
+
+    if rejected > 0 {
+        println!("skipped {rejected} programs rejected by the reconditioner");
+    }
+
+    if let Some(path) = &options.json {
+        std::fs::write(path, serde_json::to_string_pretty(&samples)?)?;
+    }
+
+    Ok(())
+}
+
+fn measure(
+    options: &Options,
+    gen_options: &generator::Options,
+    level: u32,
+    budget: u32,
+    seed: u64,
+) -> Option<Sample> {
+    let (mut module, gen_time) = timed(|| generator::generate(seed, gen_options));
+    let nodes = Stats::collect(&module).nodes;
+
+    let mut recondition_ms = None;
+    if gen_options.recondition {
+        let (result, time) = timed(|| generator::recondition(module, gen_options));
+        module = result.ok()?;
+        recondition_ms = Some(millis(time));
+    }
+
+    let source = generator::to_wgsl(&module);
+
+    Some(Sample {
+        level,
+        budget,
+        seed,
+        nodes,
+        wgsl_bytes: source.len(),
+        gen_ms: millis(gen_time),
+        recondition_ms,
+        compile_ms: compile_times(options, &source),
+    })
+}
+
+#[cfg(all(target_family = "unix", feature = "reducer"))]
+fn compile_times(options: &Options, source: &str) -> BTreeMap<String, Option<f64>> {
+    let compilers = if options.compilers.is_empty() {
+        vec![Compiler::Tint, Compiler::Naga]
+    } else {
+        options.compilers.clone()
+    };
+
+    compilers
+        .iter()
+        .map(|compiler| {
+            let (result, time) = timed(|| {
+                compiler::catch_panic(|| {
+                    compiler.compile(source, Backend::Hlsl)?;
+                    compiler.compile(source, Backend::Msl)?;
+                    compiler.compile_spirv(source)?;
+                    Ok(())
+                })
+            });
+
+            (compiler.to_string(), result.ok().map(|_| millis(time)))
+        })
+        .collect()
+}
+
+#[cfg(not(all(target_family = "unix", feature = "reducer")))]
+fn compile_times(_: &Options, _: &str) -> BTreeMap<String, Option<f64>> {
+    BTreeMap::new()
+}
+
+fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let value = f();
+    (value, start.elapsed())
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), it| (sum + it, count + 1));
+    if count > 0 {
+        Some(sum / count as f64)
+    } else {
+        None
+    }
+}
+
+/// Prints the mean measurements at each level.
+///
+/// For each time, the exponent `k` such that the time grows like `size^k` between consecutive
+/// levels is also printed, where size is the number of AST nodes. Values much greater than 1
+/// indicate a superlinear blowup.
+fn print_table(options: &Options, samples: &[Sample]) {
+    let compilers = samples
+        .first()
+        .map(|it| it.compile_ms.keys().cloned().collect())
+        .unwrap_or_else(Vec::new);
+
+    let recondition = samples.iter().any(|it| it.recondition_ms.is_some());
+
+    print!(
+        "{:>6} {:>8} {:>10} {:>10} {:>16}",
+        "budget", "failed", "nodes", "bytes", "gen ms"
+    );
+
+    if recondition {
+        print!(" {:>16}", "recondition ms");
+    }
+
+    for compiler in &compilers {
+        print!(" {:>16}", format!("{compiler} ms"));
+    }
+
+    println!();
+
+    let mut prev: Option<(f64, Vec<Option<f64>>)> = None;
+
+    for level in 0..options.levels {
+        let samples = samples
+            .iter()
+            .filter(|it| it.level == level)
+            .collect::<Vec<_>>();
+
+        let budget = options.base_stmts << level;
+        let nodes = mean(samples.iter().map(|it| it.nodes as f64)).unwrap_or(0.0);
+        let bytes = mean(samples.iter().map(|it| it.wgsl_bytes as f64)).unwrap_or(0.0);
+
+        let mut times = vec![mean(samples.iter().map(|it| it.gen_ms))];
+
+        if recondition {
+            times.push(mean(samples.iter().filter_map(|it| it.recondition_ms)));
+        }
+
+        for compiler in &compilers {
+            times.push(mean(
+                samples.iter().filter_map(|it| it.compile_ms[compiler]),
+            ));
+        }
+
+        let failed = samples
+            .iter()
+            .filter(|it| it.compile_ms.values().any(|time| time.is_none()))
+            .count();
+
+        print!("{budget:>6} {failed:>8} {nodes:>10.0} {bytes:>10.0}");
+
+        for (i, time) in times.iter().enumerate() {
+            let exponent = match (&prev, time) {
+                (Some((prev_nodes, prev_times)), Some(time)) if nodes > *prev_nodes => {
+                    let growth = (nodes / prev_nodes).ln();
+                    prev_times[i].map(|prev_time| (time / prev_time).ln() / growth)
+                }
+                _ => None,
+            };
+
+            let cell = match (time, exponent) {
+                (Some(time), Some(exponent)) => format!("{time:.2} (^{exponent:.2})"),
+                (Some(time), None) => format!("{time:.2}"),
+                (None, _) => "-".to_owned(),
+            };
+
+            print!(" {cell:>16}");
+        }
+
+        println!();
+
+        prev = Some((nodes, times));
+    }
+}
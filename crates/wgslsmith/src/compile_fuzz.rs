@@ -0,0 +1,366 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::Parser;
+use eyre::{eyre, Context};
+use rand::rngs::OsRng;
+use rand::Rng;
+use regex::Regex;
+
+use crate::config::Config;
+use crate::driver::{CompilerDriver, Dxc, Format, NagaCli, Outcome, Pipeline, SpirvVal, TintCli};
+use crate::provenance::Provenance;
+
+/// A stage of a pipeline, as given on the command line.
+#[derive(Clone, Debug)]
+enum Stage {
+    Tint(Format),
+    Naga(Format),
+    Dxc,
+    SpirvVal,
+}
+
+/// Stages of a pipeline, separated by `+` (e.g. `tint:spirv+spirv-val`).
+#[derive(Clone, Debug)]
+struct PipelineSpec(Vec<Stage>);
+
+impl FromStr for PipelineSpec {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split('+')
+            .map(|stage| match stage.split_once(':') {
+                Some(("tint", format)) => Ok(Stage::Tint(format.parse()?)),
+                Some(("naga", format)) => Ok(Stage::Naga(format.parse()?)),
+                None if stage == "dxc" => Ok(Stage::Dxc),
+                None if stage == "spirv-val" => Ok(Stage::SpirvVal),
+                _ => Err(
+                    "invalid stage (must be one of tint:<format>, naga:<format>, dxc or spirv-val)",
+                ),
+            })
+            .collect::<Result<_, _>>()
+            .map(PipelineSpec)
+    }
+}
+
+#[derive(Parser)]
+pub struct Options {
+    /// Path to directory in which to save failing test cases.
+    #[clap(short, long, action, default_value = "out")]
+    output: PathBuf,
+
+    /// Compiler pipeline to run on each program.
+    ///
+    /// A pipeline is a list of stages separated by `+`, where each stage consumes the output of
+    /// the previous one. Stages are `tint:<format>` and `naga:<format>` (where format is one of
+    /// wgsl, hlsl, msl or spirv), `dxc` and `spirv-val`. For example, `naga:hlsl+dxc` or
+    /// `tint:spirv+spirv-val`. This can be given multiple times to test several pipelines.
+    ///
+    /// This can also be the name of a pipeline defined in the `pipelines` section of the config
+    /// file.
+    #[clap(long, action, required = true)]
+    pipeline: Vec<String>,
+
+    /// Path to the tint executable.
+    #[clap(long, action, default_value = "tint")]
+    tint: PathBuf,
+
+    /// Path to the naga CLI executable.
+    #[clap(long, action, default_value = "naga")]
+    naga: PathBuf,
+
+    /// Path to the dxc executable.
+    #[clap(long, action, default_value = "dxc")]
+    dxc: PathBuf,
+
+    /// Path to the spirv-val executable.
+    #[clap(long, action, default_value = "spirv-val")]
+    spirv_val: PathBuf,
+
+    /// Timeout in seconds for each compiler invocation.
+    #[clap(long, action, default_value = "10")]
+    timeout: u64,
+
+    /// Number of programs to test.
+    ///
+    /// If not set, the campaign runs until it is interrupted.
+    #[clap(short, long, action)]
+    count: Option<usize>,
+
+    /// Generator options as a JSON object, using the long option names of `wgslsmith gen` as keys.
+    ///
+    /// Programs are always reconditioned.
+    #[clap(long, action)]
+    gen_options: Option<String>,
+
+    /// Regex for ignoring certain types of crashes.
+    ///
+    /// This will be matched against the output from the crashing compiler.
+    #[clap(long, action)]
+    ignore: Vec<Regex>,
+
+    /// Also save programs that a compiler rejected.
+    ///
+    /// Reconditioned programs should always be valid, so these are either compiler bugs or
+    /// generator bugs.
+    #[clap(long, action)]
+    save_rejections: bool,
+
+    /// Save programs which take more than this many times longer to compile than usual.
+    ///
+    /// Each stage's compile time is compared with the median over the last 1000 programs that it
+    /// compiled, once there are at least 20 of them.
+    #[clap(long, action)]
+    slow_compile_factor: Option<f64>,
+
+    /// Minimum compile time in milliseconds for a program to be saved by `--slow-compile-factor`.
+    ///
+    /// This stops noise in the time taken to start a compiler from being reported for programs
+    /// that compile quickly.
+    #[clap(long, action, default_value = "1000")]
+    slow_compile_min: u64,
+}
+
+/// Number of recent compile times that the median is taken over.
+const COMPILE_TIMES_WINDOW: usize = 1000;
+
+/// Number of compile times needed before programs are compared with the median.
+const MIN_COMPILE_TIMES: usize = 20;
+
+#[derive(Default)]
+struct Counts {
+    programs: usize,
+    crashes: usize,
+    timeouts: usize,
+    rejections: usize,
+    slow: usize,
+    saved: usize,
+}
+
+/// Recent compile times of each driver.
+#[derive(Default)]
+struct CompileTimes(HashMap<String, VecDeque<Duration>>);
+
+impl CompileTimes {
+    /// Returns the median compile time of `driver`, if enough programs have been compiled by it.
+    fn median(&self, driver: &str) -> Option<Duration> {
+        let times = self.0.get(driver)?;
+        if times.len() < MIN_COMPILE_TIMES {
+            return None;
+        }
+
+        let mut times = times.iter().copied().collect::<Vec<_>>();
+        times.sort();
+        Some(times[times.len() / 2])
+    }
+
+    fn record(&mut self, driver: &str, time: Duration) {
+        let times = self.0.entry(driver.to_owned()).or_default();
+        if times.len() == COMPILE_TIMES_WINDOW {
+            times.pop_front();
+        }
+        times.push_back(time);
+    }
+}
+
+pub fn run(config: Config, options: Options) -> eyre::Result<()> {
+    let mut gen_options = match &options.gen_options {
+        Some(json) => generator::Options::from_json(json)?,
+        None => generator::Options::default(),
+    };
+
+    gen_options.apply_preset();
+    gen_options.recondition = true;
+
+    let pipelines = options
+        .pipeline
+        .iter()
+        .map(|spec| build_pipeline(&config, &options, spec))
+        .collect::<eyre::Result<Vec<_>>>()?;
+
+    let timeout = Duration::from_secs(options.timeout);
+    let tmp = std::env::temp_dir().join(format!("wgslsmith-compile-fuzz-{}", std::process::id()));
+    std::fs::create_dir_all(&tmp)?;
+    std::fs::create_dir_all(&options.output)?;
+
+    let provenance = Provenance::collect(None).to_json()?;
+    let mut counts = Counts::default();
+    let mut compile_times = CompileTimes::default();
+    let slow_compile_min = Duration::from_millis(options.slow_compile_min);
+
+    while options.count.map_or(true, |count| counts.programs < count) {
+        let seed = OsRng.gen();
+        let module = generator::generate(seed, &gen_options);
+        let module = match generator::recondition(module, &gen_options) {
+            Ok(module) => module,
+            Err(_) => continue,
+        };
+
+        let shader = generator::to_wgsl(&module);
+        let input = tmp.join("shader.wgsl");
+        std::fs::write(&input, &shader)?;
+
+        counts.programs += 1;
+
+        let mut findings = vec![];
+        let mut slow = vec![];
+        for pipeline in &pipelines {
+            let run = pipeline.run(&input, &tmp, timeout)?;
+
+            for (driver, time) in run.times {
+                if let Some(factor) = options.slow_compile_factor {
+                    if let Some(median) = compile_times.median(&driver) {
+                        if time >= slow_compile_min
+                            && time.as_secs_f64() > factor * median.as_secs_f64()
+                        {
+                            println!(
+                                "{seed}: {driver} took {}ms to compile (median {}ms)",
+                                time.as_millis(),
+                                median.as_millis()
+                            );
+                            slow.push((driver.clone(), time, median));
+                        }
+                    }
+                }
+
+                compile_times.record(&driver, time);
+            }
+
+            if let Some((driver, outcome)) = run.failure {
+                let save = match &outcome {
+                    Outcome::Success => false,
+                    Outcome::Rejected(_) => {
+                        counts.rejections += 1;
+                        options.save_rejections
+                    }
+                    Outcome::Crash(output) => {
+                        counts.crashes += 1;
+                        !options
+                            .ignore
+                            .iter()
+                            .chain(&config.fuzzer.ignore)
+                            .any(|it| it.is_match(output))
+                    }
+                    Outcome::Timeout => {
+                        counts.timeouts += 1;
+                        true
+                    }
+                };
+
+                println!(
+                    "{seed}: {} failed at {driver}: {}",
+                    pipeline.name(),
+                    kind(&outcome)
+                );
+
+                if save {
+                    findings.push((driver, outcome));
+                }
+            }
+        }
+
+        // A driver may appear in several pipelines, but it only needs to be reported once
+        slow.sort_by(|a, b| a.0.cmp(&b.0));
+        slow.dedup_by(|a, b| a.0 == b.0);
+
+        if !slow.is_empty() {
+            counts.slow += 1;
+        }
+
+        if !findings.is_empty() || !slow.is_empty() {
+            let dir = options.output.join(seed.to_string());
+            std::fs::create_dir_all(&dir)?;
+            std::fs::write(dir.join("shader.wgsl"), &shader)?;
+            std::fs::write(dir.join("provenance.json"), &provenance)?;
+
+            for (driver, outcome) in &findings {
+                let log = outcome.output().unwrap_or_default();
+                std::fs::write(dir.join(format!("{driver}.{}.txt", kind(outcome))), log)?;
+            }
+
+            for (driver, time, median) in &slow {
+                let report = format!(
+                    "compiled in {}ms, which is {:.1}x the median of {}ms\n",
+                    time.as_millis(),
+                    time.as_secs_f64() / median.as_secs_f64(),
+                    median.as_millis()
+                );
+                std::fs::write(dir.join(format!("{driver}.slow.txt")), report)?;
+            }
+
+            counts.saved += 1;
+        }
+
+        if counts.programs % 100 == 0 {
+            print_counts(&counts);
+        }
+    }
+
+    print_counts(&counts);
+    std::fs::remove_dir_all(&tmp)?;
+
+    Ok(())
+}
+
+fn build_pipeline(config: &Config, options: &Options, spec: &str) -> eyre::Result<Pipeline> {
+    if let Some(pipeline) = config.pipelines.get(spec) {
+        let drivers = pipeline
+            .stages
+            .iter()
+            .map(|stage| Box::new(stage.clone()) as Box<dyn CompilerDriver>)
+            .collect();
+        return Pipeline::new(drivers).wrap_err_with(|| format!("invalid pipeline `{spec}`"));
+    }
+
+    let spec = spec
+        .parse::<PipelineSpec>()
+        .map_err(|e| eyre!("invalid pipeline `{spec}`: {e}"))?;
+
+    let drivers = spec
+        .0
+        .iter()
+        .map(|stage| -> Box<dyn CompilerDriver> {
+            match stage {
+                Stage::Tint(format) => Box::new(TintCli {
+                    path: options.tint.clone(),
+                    format: *format,
+                }),
+                Stage::Naga(format) => Box::new(NagaCli {
+                    path: options.naga.clone(),
+                    format: *format,
+                }),
+                Stage::Dxc => Box::new(Dxc {
+                    path: options.dxc.clone(),
+                }),
+                Stage::SpirvVal => Box::new(SpirvVal {
+                    path: options.spirv_val.clone(),
+                }),
+            }
+        })
+        .collect();
+
+    Pipeline::new(drivers)
+}
+
+fn kind(outcome: &Outcome) -> &'static str {
+    match outcome {
+        Outcome::Success => "success",
+        Outcome::Rejected(_) => "rejected",
+        Outcome::Crash(_) => "crash",
+        Outcome::Timeout => "timeout",
+    }
+}
+
+fn print_counts(counts: &Counts) {
+    println!(
+        "tested {} programs: {} crashes, {} timeouts, {} rejections, {} slow ({} saved)",
+        counts.programs,
+        counts.crashes,
+        counts.timeouts,
+        counts.rejections,
+        counts.slow,
+        counts.saved
+    );
+}
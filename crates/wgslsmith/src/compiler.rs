@@ -1,4 +1,5 @@
 use std::fmt::Display;
+use std::panic::AssertUnwindSafe;
 
 use clap::ValueEnum;
 use eyre::{eyre, Context};
@@ -53,30 +54,64 @@ impl Compiler {
             Compiler::Naga => compile_naga(source, backend),
         }
     }
+
+    /// Compiles the shader to a SPIR-V binary, returned as a list of words.
+    pub fn compile_spirv(&self, source: &str) -> eyre::Result<Vec<u32>> {
+        match self {
+            Compiler::Tint => Ok(tint::compile_shader_to_spirv(source)?),
+            Compiler::Naga => compile_naga_spirv(source),
+        }
+    }
 }
 
-fn validate_naga(source: &str) -> eyre::Result<()> {
+/// Disassembles a SPIR-V binary produced by `compile_spirv`, for displaying it.
+pub fn disassemble_spirv(words: &[u32]) -> eyre::Result<String> {
+    Ok(tint::disassemble_spirv(words)?)
+}
+
+/// Runs `f`, converting any panic into an error.
+///
+/// This is used to catch compiler crashes (e.g. naga frontend panics) without bringing down the
+/// whole process.
+pub fn catch_panic<T>(f: impl FnOnce() -> eyre::Result<T>) -> eyre::Result<T> {
+    match std::panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_owned());
+            Err(eyre!("compiler panicked: {message}"))
+        }
+    }
+}
+
+fn parse_naga(source: &str) -> eyre::Result<(naga::Module, naga::valid::ModuleInfo)> {
     use naga::front::wgsl;
     use naga::valid::{Capabilities, ValidationFlags, Validator};
+
     let module = wgsl::parse_str(&source.replace("@stage(compute)", "@compute"))?;
-    Validator::new(ValidationFlags::default(), Capabilities::all()).validate(&module)?;
+    let validation =
+        Validator::new(ValidationFlags::default(), Capabilities::all()).validate(&module)?;
+
+    Ok((module, validation))
+}
+
+fn validate_naga(source: &str) -> eyre::Result<()> {
+    parse_naga(source)?;
     Ok(())
 }
 
 fn validate_tint(source: &str) -> eyre::Result<()> {
-    tint::validate_shader(source)
-        .then(|| ())
-        .ok_or_else(|| eyre!("invalid wgsl"))
+    tint::check_shader(source)?;
+    Ok(())
 }
 
 fn compile_naga(source: &str, backend: Backend) -> eyre::Result<String> {
     use naga::back::{hlsl, msl};
-    use naga::front::wgsl;
-    use naga::valid::{Capabilities, ValidationFlags, Validator};
 
-    let module = wgsl::parse_str(&source.replace("@stage(compute)", "@compute"))?;
-    let validation =
-        Validator::new(ValidationFlags::default(), Capabilities::all()).validate(&module)?;
+    let (module, validation) = parse_naga(source)?;
 
     let mut out = String::new();
 
@@ -92,17 +127,26 @@ fn compile_naga(source: &str, backend: Backend) -> eyre::Result<String> {
                 &msl::PipelineOptions::default(),
             )?;
         }
-        Backend::Spirv => todo!(),
+        Backend::Spirv => return Err(eyre!("spirv is a binary format, use `compile_spirv`")),
     }
 
     Ok(out)
 }
 
+fn compile_naga_spirv(source: &str) -> eyre::Result<Vec<u32>> {
+    use naga::back::spv;
+
+    let (module, validation) = parse_naga(source)?;
+    let words = spv::write_vec(&module, &validation, &spv::Options::default(), None)?;
+
+    Ok(words)
+}
+
 fn compile_tint(source: &str, backend: Backend) -> eyre::Result<String> {
     let out = match backend {
-        Backend::Hlsl => tint::compile_shader_to_hlsl(source),
-        Backend::Msl => tint::compile_shader_to_msl(source),
-        Backend::Spirv => todo!(),
+        Backend::Hlsl => tint::compile_shader_to_hlsl(source)?,
+        Backend::Msl => tint::compile_shader_to_msl(source)?,
+        Backend::Spirv => return Err(eyre!("spirv is a binary format, use `compile_spirv`")),
     };
     Ok(out)
 }
@@ -2,14 +2,15 @@ use std::collections::HashMap;
 use std::io;
 use std::path::{Path, PathBuf};
 
-#[cfg(all(target_family = "unix", feature = "reducer"))]
+use clap::{ArgAction, ArgMatches, CommandFactory, ValueSource};
 use color_eyre::Help;
 use directories::ProjectDirs;
-#[cfg(all(target_family = "unix", feature = "reducer"))]
 use eyre::eyre;
 use regex::Regex;
 use serde::Deserialize;
 
+use crate::driver::CommandDriver;
+
 #[derive(Default, Deserialize)]
 pub struct Config {
     #[serde(default)]
@@ -22,12 +23,21 @@ pub struct Config {
     pub reducer: Reducer,
     #[serde(default)]
     pub validator: Validator,
+    /// Named compiler pipelines, which can be used in place of pipeline specs.
+    #[serde(default)]
+    pub pipelines: HashMap<String, PipelineConfig>,
+    /// Default generator options, keyed by their long option names (e.g. `max-fns = 3`).
+    #[serde(default)]
+    pub gen: toml::value::Table,
 }
 
 #[derive(Default, Deserialize)]
 pub struct Harness {
     pub path: Option<PathBuf>,
     pub remote: Option<String>,
+    /// Token to present to remote harness servers that were started with `--token`.
+    #[serde(default)]
+    pub token: Option<String>,
 }
 
 #[derive(Default, Deserialize)]
@@ -39,6 +49,20 @@ pub struct Remote {
 pub struct Fuzzer {
     #[serde(with = "serde_regex")]
     pub ignore: Vec<Regex>,
+    /// Path to the list of known failures, if `wgslsmith fuzz --xfail` isn't given.
+    #[serde(default)]
+    pub xfail: Option<PathBuf>,
+    /// Repository to draft issues against, if `wgslsmith fuzz --issue-repo` isn't given.
+    #[serde(default)]
+    pub issue_repo: Option<String>,
+    /// Template for drafted issues, if `wgslsmith fuzz --issue-template` isn't given.
+    #[serde(default)]
+    pub issue_template: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+pub struct PipelineConfig {
+    pub stages: Vec<CommandDriver>,
 }
 
 #[derive(Default, Deserialize)]
@@ -98,6 +122,24 @@ impl Perses {
 pub struct Validator {
     #[serde(default)]
     pub server: Option<String>,
+    /// Address of a separate server to use for validating MSL (e.g. `msl-server` running on macOS).
+    #[serde(default)]
+    pub msl_server: Option<String>,
+    /// Compiler used by the validation server for HLSL shaders.
+    #[serde(default)]
+    pub hlsl_compiler: HlslCompiler,
+    /// Token to present to validation servers that were started with `--token`.
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HlslCompiler {
+    #[default]
+    Fxc,
+    Dxc,
+    Both,
 }
 
 impl Validator {
@@ -108,6 +150,25 @@ impl Validator {
                 .with_suggestion(|| "set `validator.server` in `wgslsmith.toml`")
         })
     }
+
+    /// Returns the address of the server to use for validating shaders for the given backend.
+    #[cfg(all(target_family = "unix", feature = "reducer"))]
+    pub fn server_for(&self, backend: crate::validator::Backend) -> eyre::Result<&str> {
+        match (backend, &self.msl_server) {
+            (crate::validator::Backend::Msl, Some(server)) => Ok(server),
+            _ => self.server(),
+        }
+    }
+
+    #[cfg(all(target_family = "unix", feature = "reducer"))]
+    pub fn hlsl_backend(&self) -> crate::validator::Backend {
+        use crate::validator::{Backend, HlslCompiler as Compiler};
+        Backend::Hlsl(match self.hlsl_compiler {
+            HlslCompiler::Fxc => Compiler::Fxc,
+            HlslCompiler::Dxc => Compiler::Dxc,
+            HlslCompiler::Both => Compiler::Both,
+        })
+    }
 }
 
 impl Config {
@@ -133,6 +194,78 @@ impl Config {
         }
     }
 
+    /// Returns the arguments for `wgslsmith gen` which set generator options from the
+    /// environment and the `[gen]` table.
+    ///
+    /// An option is read from a `WGSLSMITH_GEN_<NAME>` environment variable (e.g.
+    /// `WGSLSMITH_GEN_MAX_FNS` for `--max-fns`) if it's set, or from the config file otherwise.
+    /// Options that were passed on the command line according to `matches` are left out, so that
+    /// the command line always takes precedence.
+    pub fn gen_args(&self, matches: Option<&ArgMatches>) -> eyre::Result<Vec<String>> {
+        let command = generator::Options::command();
+        let mut args = vec![];
+
+        for key in self.gen.keys() {
+            if !command
+                .get_arguments()
+                .any(|arg| arg.get_long() == Some(key.as_str()))
+            {
+                return Err(eyre!("unknown generator option `{key}` in config file")
+                    .with_suggestion(|| "use the long option names from `wgslsmith gen --help`"));
+            }
+        }
+
+        for arg in command.get_arguments() {
+            let long = match arg.get_long() {
+                Some(long) => long,
+                None => continue,
+            };
+
+            if let Some(matches) = matches {
+                if matches.value_source(arg.get_id()) == Some(ValueSource::CommandLine) {
+                    continue;
+                }
+            }
+
+            let is_flag = matches!(arg.get_action(), ArgAction::SetTrue);
+            let var = format!("WGSLSMITH_GEN_{}", long.replace('-', "_").to_uppercase());
+
+            let values = if let Ok(value) = std::env::var(&var) {
+                match (is_flag, value.as_str()) {
+                    (true, "1" | "true") => vec![toml::Value::Boolean(true)],
+                    (true, "" | "0" | "false") => vec![],
+                    (true, _) => return Err(eyre!("`{var}` must be `true` or `false`")),
+                    (false, _) => vec![toml::Value::String(value)],
+                }
+            } else {
+                match self.gen.get(long) {
+                    Some(toml::Value::Array(values)) => values.clone(),
+                    Some(value) => vec![value.clone()],
+                    None => vec![],
+                }
+            };
+
+            for value in values {
+                let value = match value {
+                    toml::Value::Boolean(true) if is_flag => {
+                        args.push(format!("--{long}"));
+                        continue;
+                    }
+                    toml::Value::Boolean(false) if is_flag => continue,
+                    toml::Value::String(value) => value,
+                    toml::Value::Integer(value) => value.to_string(),
+                    toml::Value::Float(value) => value.to_string(),
+                    toml::Value::Boolean(value) => value.to_string(),
+                    _ => return Err(eyre!("invalid value for generator option `{long}`")),
+                };
+
+                args.extend([format!("--{long}"), value]);
+            }
+        }
+
+        Ok(args)
+    }
+
     pub fn default_remote(&self) -> Option<&str> {
         self.harness
             .remote
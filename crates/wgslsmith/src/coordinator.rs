@@ -0,0 +1,470 @@
+//! Coordinator for fuzzing campaigns that are spread across several machines.
+//!
+//! Workers are instances of `wgslsmith fuzz --coordinator <address>`, which register with the
+//! coordinator and then generate programs from ranges of seeds that it hands out, so that no two
+//! workers test the same program. Each finding is uploaded to the coordinator, which stores them
+//! all in one directory. Workers send heartbeats while they're running, and the range of seeds
+//! held by a worker that stops sending them is handed out again.
+//!
+//! Requests use the same bincode-over-TCP protocol as the harness server, with one request and
+//! response per connection. If the coordinator was started with `--token`, every request must
+//! present the same token.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bincode::{Decode, Encode};
+use clap::Parser;
+use eyre::{eyre, Context};
+use serde::{Deserialize, Serialize};
+
+/// Name of the file in the output directory that the coordinator's state is saved to.
+const STATE_FILE: &str = "coordinator.json";
+
+/// Interval at which workers send heartbeats.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Maximum size of an encoded request, which mostly limits the size of uploaded findings.
+const MAX_REQUEST_SIZE: usize = 64 * 1024 * 1024;
+
+/// Timeout for reading or writing a single request or response.
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Parser)]
+pub struct Options {
+    /// Address to listen for workers on.
+    #[clap(short, long, action, default_value = "localhost:9123")]
+    address: String,
+
+    /// Path to directory in which to store findings from all workers.
+    #[clap(short, long, action, default_value = "out")]
+    output: PathBuf,
+
+    /// Number of seeds to hand out to a worker at a time.
+    #[clap(long, action, default_value = "1000")]
+    range_size: u64,
+
+    /// Number of seconds after the last heartbeat from a worker at which its range of seeds is
+    /// handed out to another worker.
+    #[clap(long, action, default_value = "60")]
+    heartbeat_timeout: u64,
+
+    /// Shared secret that workers must present before making requests.
+    ///
+    /// This should be set if the coordinator is reachable by untrusted machines.
+    #[clap(long, action)]
+    token: Option<String>,
+}
+
+#[derive(Debug, Decode, Encode)]
+struct Envelope {
+    token: Option<String>,
+    request: Request,
+}
+
+#[derive(Debug, Decode, Encode)]
+enum Request {
+    Register {
+        name: String,
+        platform: String,
+    },
+    Heartbeat {
+        worker: u64,
+    },
+    /// Requests the next range of seeds, which also marks the previous range as done.
+    NextSeeds {
+        worker: u64,
+    },
+    Finding {
+        worker: u64,
+        /// Name of the finding's directory on the worker.
+        name: String,
+        files: Vec<(String, Vec<u8>)>,
+    },
+}
+
+#[derive(Debug, Decode, Encode)]
+enum Response {
+    Registered { worker: u64 },
+    Seeds { start: u64, count: u64 },
+    Ok,
+    UnknownWorker,
+    Unauthorized,
+    InvalidName,
+}
+
+/// State of the coordinator, which is saved so that a campaign can be resumed after restarting
+/// it.
+#[derive(Default, Deserialize, Serialize)]
+struct State {
+    /// Start of the next range of seeds that hasn't been handed out yet.
+    next_seed: u64,
+    /// Ranges of seeds from workers that timed out, which are handed out before any new ones.
+    requeued: VecDeque<(u64, u64)>,
+    workers: BTreeMap<u64, Worker>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Worker {
+    name: String,
+    platform: String,
+    /// Number of seeds handed out to the worker.
+    seeds: u64,
+    findings: usize,
+    /// Range of seeds that the worker is currently testing.
+    range: Option<(u64, u64)>,
+    #[serde(skip)]
+    last_seen: Option<Instant>,
+}
+
+struct Coordinator {
+    options: Options,
+    state: Mutex<State>,
+}
+
+pub fn run(options: Options) -> eyre::Result<()> {
+    std::fs::create_dir_all(&options.output)?;
+
+    let mut state: State = match std::fs::read_to_string(options.output.join(STATE_FILE)) {
+        Ok(json) => serde_json::from_str(&json)?,
+        Err(_) => State::default(),
+    };
+
+    // Workers from before a restart get the usual timeout to reconnect before their seeds are
+    // handed out again
+    for worker in state.workers.values_mut() {
+        if worker.range.is_some() {
+            worker.last_seen = Some(Instant::now());
+        }
+    }
+
+    let listener = TcpListener::bind(&options.address)
+        .wrap_err_with(|| format!("failed to bind to {}", options.address))?;
+
+    println!("coordinator listening at {}", listener.local_addr()?);
+    println!("next seed: {}", state.next_seed);
+
+    let coordinator = Arc::new(Coordinator {
+        options,
+        state: Mutex::new(state),
+    });
+
+    std::thread::spawn({
+        let coordinator = coordinator.clone();
+        move || loop {
+            std::thread::sleep(HEARTBEAT_INTERVAL);
+            coordinator.expire_workers();
+        }
+    });
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let coordinator = coordinator.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = coordinator.handle(&stream) {
+                eprintln!("failed to handle request: {e:#}");
+            }
+        });
+    }
+
+    Ok(())
+}
+
+impl Coordinator {
+    fn handle(&self, stream: &TcpStream) -> eyre::Result<()> {
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+        // The limit stops clients that haven't presented a token yet from making the coordinator
+        // allocate arbitrary amounts of memory
+        let mut reader = BufReader::new(stream);
+        let Envelope { token, request } = bincode::decode_from_std_read(
+            &mut reader,
+            bincode::config::standard().with_limit::<MAX_REQUEST_SIZE>(),
+        )?;
+
+        let res = if !common::auth::check_token(self.options.token.as_deref(), token.as_deref()) {
+            eprintln!("rejected request from a worker without a valid token");
+            Response::Unauthorized
+        } else {
+            self.dispatch(request)?
+        };
+
+        let mut writer = BufWriter::new(stream);
+        bincode::encode_into_std_write(res, &mut writer, bincode::config::standard())?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    fn dispatch(&self, req: Request) -> eyre::Result<Response> {
+        Ok(match req {
+            Request::Register { name, platform } => self.register(name, platform)?,
+            Request::Heartbeat { worker } => self.heartbeat(worker),
+            Request::NextSeeds { worker } => self.next_seeds(worker)?,
+            Request::Finding {
+                worker,
+                name,
+                files,
+            } => self.finding(worker, &name, &files)?,
+        })
+    }
+
+    fn register(&self, name: String, platform: String) -> eyre::Result<Response> {
+        // The name is used as the directory that the worker's findings are stored in
+        if file_name(&name).is_err() {
+            eprintln!("rejected worker with invalid name: {name:?}");
+            return Ok(Response::InvalidName);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let id = state.workers.keys().next_back().map_or(0, |id| id + 1);
+
+        println!("worker {id} registered: {name} ({platform})");
+
+        state.workers.insert(
+            id,
+            Worker {
+                name,
+                platform,
+                seeds: 0,
+                findings: 0,
+                range: None,
+                last_seen: Some(Instant::now()),
+            },
+        );
+
+        self.save(&state)?;
+
+        Ok(Response::Registered { worker: id })
+    }
+
+    fn heartbeat(&self, id: u64) -> Response {
+        let mut state = self.state.lock().unwrap();
+        match state.workers.get_mut(&id) {
+            Some(worker) => {
+                worker.last_seen = Some(Instant::now());
+                Response::Ok
+            }
+            None => Response::UnknownWorker,
+        }
+    }
+
+    fn next_seeds(&self, id: u64) -> eyre::Result<Response> {
+        let mut state = self.state.lock().unwrap();
+        if !state.workers.contains_key(&id) {
+            return Ok(Response::UnknownWorker);
+        }
+
+        let range = match state.requeued.pop_front() {
+            Some(range) => range,
+            None => {
+                let range = (state.next_seed, self.options.range_size);
+                state.next_seed += self.options.range_size;
+                range
+            }
+        };
+
+        let worker = state.workers.get_mut(&id).unwrap();
+        worker.seeds += range.1;
+        worker.range = Some(range);
+        worker.last_seen = Some(Instant::now());
+
+        self.save(&state)?;
+
+        Ok(Response::Seeds {
+            start: range.0,
+            count: range.1,
+        })
+    }
+
+    fn finding(&self, id: u64, name: &str, files: &[(String, Vec<u8>)]) -> eyre::Result<Response> {
+        let mut state = self.state.lock().unwrap();
+        let worker = match state.workers.get_mut(&id) {
+            Some(worker) => worker,
+            None => return Ok(Response::UnknownWorker),
+        };
+
+        worker.findings += 1;
+        worker.last_seen = Some(Instant::now());
+
+        // Findings are grouped by worker, since their names are only unique on each machine. The id
+        // is included since names aren't unique, e.g. if a worker is restarted.
+        let dir = self
+            .options
+            .output
+            .join(format!("{}-{id}", worker.name))
+            .join(file_name(name)?);
+        std::fs::create_dir_all(&dir)?;
+
+        for (file, data) in files {
+            std::fs::write(dir.join(file_name(file)?), data)?;
+        }
+
+        println!("finding from {}: {}", worker.name, dir.display());
+
+        self.save(&state)?;
+
+        Ok(Response::Ok)
+    }
+
+    /// Hands out the ranges of seeds from workers that have stopped sending heartbeats again.
+    fn expire_workers(&self) {
+        let timeout = Duration::from_secs(self.options.heartbeat_timeout);
+        let mut state = self.state.lock().unwrap();
+        let mut expired = vec![];
+
+        for (id, worker) in &mut state.workers {
+            if worker.last_seen.map_or(false, |it| it.elapsed() > timeout) {
+                println!("worker {id} ({}) timed out", worker.name);
+                worker.last_seen = None;
+                expired.extend(worker.range.take());
+            }
+        }
+
+        state.requeued.extend(expired);
+    }
+
+    fn save(&self, state: &State) -> eyre::Result<()> {
+        let path = self.options.output.join(STATE_FILE);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(state)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Checks that a name sent by a worker can't refer to a file outside of its directory.
+fn file_name(name: &str) -> eyre::Result<&str> {
+    match Path::new(name).file_name() {
+        Some(file_name) if file_name == name => Ok(name),
+        _ => Err(eyre!("invalid file name: {name}")),
+    }
+}
+
+/// Connection from a worker to the coordinator.
+#[derive(Clone)]
+pub struct Client {
+    address: String,
+    token: Option<String>,
+    worker: u64,
+}
+
+impl Client {
+    /// Registers with the coordinator at `address`, identifying the worker by `name`.
+    ///
+    /// If the coordinator requires authentication, `token` must match the one it was started with.
+    pub fn register(address: &str, token: Option<String>, name: String) -> eyre::Result<Client> {
+        let platform = format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH);
+        let req = Request::Register {
+            name: name.clone(),
+            platform,
+        };
+
+        match request(address, token.as_deref(), req)? {
+            Response::Registered { worker } => Ok(Client {
+                address: address.to_owned(),
+                token,
+                worker,
+            }),
+            Response::InvalidName => Err(eyre!("`{name}` is not a valid worker name")),
+            Response::Unauthorized => Err(unauthorized(address)),
+            res => Err(eyre!("unexpected response from coordinator: {res:?}")),
+        }
+    }
+
+    pub fn heartbeat(&self) -> eyre::Result<()> {
+        self.expect_ok(self.request(Request::Heartbeat {
+            worker: self.worker,
+        })?)
+    }
+
+    /// Returns the next range of seeds to test.
+    pub fn next_seeds(&self) -> eyre::Result<std::ops::Range<u64>> {
+        let req = Request::NextSeeds {
+            worker: self.worker,
+        };
+
+        match self.request(req)? {
+            Response::Seeds { start, count } => Ok(start..start + count),
+            res => Err(self.unexpected(res)),
+        }
+    }
+
+    /// Uploads the files in the directory of a finding.
+    pub fn submit_finding(&self, dir: &Path) -> eyre::Result<()> {
+        let mut files = vec![];
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                files.push((name, std::fs::read(entry.path())?));
+            }
+        }
+
+        let req = Request::Finding {
+            worker: self.worker,
+            name: dir.file_name().unwrap().to_string_lossy().into_owned(),
+            files,
+        };
+
+        self.expect_ok(self.request(req)?)
+    }
+
+    fn request(&self, req: Request) -> eyre::Result<Response> {
+        request(&self.address, self.token.as_deref(), req)
+    }
+
+    fn expect_ok(&self, res: Response) -> eyre::Result<()> {
+        match res {
+            Response::Ok => Ok(()),
+            res => Err(self.unexpected(res)),
+        }
+    }
+
+    fn unexpected(&self, res: Response) -> eyre::Report {
+        match res {
+            Response::UnknownWorker => eyre!(
+                "worker {} is not registered with the coordinator at {}",
+                self.worker,
+                self.address
+            ),
+            Response::Unauthorized => unauthorized(&self.address),
+            res => eyre!("unexpected response from coordinator: {res:?}"),
+        }
+    }
+}
+
+fn unauthorized(address: &str) -> eyre::Report {
+    eyre!("coordinator at {address} rejected the token (see `--coordinator-token`)")
+}
+
+fn request(address: &str, token: Option<&str>, request: Request) -> eyre::Result<Response> {
+    let socket = address
+        .to_socket_addrs()
+        .wrap_err_with(|| format!("failed to resolve coordinator address {address}"))?
+        .next()
+        .ok_or_else(|| eyre!("coordinator address {address} did not resolve to anything"))?;
+
+    let mut stream = TcpStream::connect_timeout(&socket, Duration::from_secs(10))
+        .wrap_err_with(|| format!("failed to connect to coordinator at {address}"))?;
+
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    let envelope = Envelope {
+        token: token.map(str::to_owned),
+        request,
+    };
+
+    bincode::encode_into_std_write(envelope, &mut stream, bincode::config::standard())?;
+
+    Ok(bincode::decode_from_std_read(
+        &mut stream,
+        bincode::config::standard(),
+    )?)
+}
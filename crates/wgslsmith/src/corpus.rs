@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use ast::stats::Stats;
+use clap::Parser;
+use eyre::bail;
+use rand::rngs::OsRng;
+use rand::Rng;
+
+#[derive(Parser)]
+pub struct Options {
+    /// Number of programs to generate.
+    #[clap(short, long, action, default_value = "100")]
+    count: usize,
+
+    /// Path to directory in which to save the programs.
+    #[clap(short, long, action, default_value = "corpus")]
+    out: PathBuf,
+
+    /// Seed for the first program.
+    ///
+    /// Programs are generated from consecutive seeds starting at this one, and are named after the
+    /// seed that they were generated from. If not set, a random seed is used.
+    #[clap(long, action)]
+    seed: Option<u64>,
+
+    /// Generator options as a JSON object, using the long option names of `wgslsmith gen` as keys.
+    #[clap(long, action)]
+    gen_options: Option<String>,
+
+    /// Recondition the programs before saving them.
+    #[clap(long, action)]
+    recondition: bool,
+
+    /// Keep programs that are structurally identical to one that has already been generated.
+    #[clap(long, action)]
+    no_dedup: bool,
+
+    /// Maximum number of seeds to try before giving up, if there are too many duplicates.
+    ///
+    /// Defaults to 10 times the count.
+    #[clap(long, action)]
+    max_attempts: Option<usize>,
+
+    /// Print statistics about the generated programs.
+    #[clap(long, action)]
+    stats: bool,
+}
+
+pub fn run(options: Options) -> eyre::Result<()> {
+    let mut gen_options = match &options.gen_options {
+        Some(json) => generator::Options::from_json(json)?,
+        None => generator::Options::default(),
+    };
+
+    gen_options.apply_preset();
+    gen_options.recondition |= options.recondition;
+
+    std::fs::create_dir_all(&options.out)?;
+
+    let start = options.seed.unwrap_or_else(|| OsRng.gen());
+    let max_attempts = options.max_attempts.unwrap_or(options.count * 10);
+
+    let mut seen = HashSet::new();
+    let mut stats = Stats::default();
+    let mut saved = 0;
+    let mut duplicates = 0;
+    let mut rejected = 0;
+
+    for seed in (0..max_attempts as u64).map(|i| start.wrapping_add(i)) {
+        if saved == options.count {
+            break;
+        }
+
+        let mut module = generator::generate(seed, &gen_options);
+
+        // Duplicates are detected before reconditioning, since that only adds noise
+        if !options.no_dedup {
+            if !seen.insert(ast::canonical::canonical_hash(module.clone())) {
+                duplicates += 1;
+                continue;
+            }
+        }
+
+        if options.stats {
+            stats.merge(&Stats::collect(&module));
+        }
+
+        if gen_options.recondition {
+            module = match generator::recondition(module, &gen_options) {
+                Ok(module) => module,
+                Err(_) => {
+                    rejected += 1;
+                    continue;
+                }
+            };
+        }
+
+        let path = options.out.join(format!("{seed}.wgsl"));
+        let mut file = BufWriter::new(File::create(path)?);
+        generator::write_wgsl(&mut file, &module)?;
+        file.flush()?;
+        saved += 1;
+    }
+
+    println!("saved {saved} programs to {}", options.out.display());
+    println!("skipped {duplicates} duplicates and {rejected} rejected programs");
+
+    if options.stats {
+        print!("{stats}");
+    }
+
+    if saved < options.count {
+        bail!(
+            "only generated {saved} of {} programs after {max_attempts} attempts",
+            options.count
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,354 @@
+//! Coverage feedback from an instrumented compiler, for `wgslsmith fuzz --coverage-cmd`.
+//!
+//! Each program is compiled with the instrumented build, and the parts of the compiler that it
+//! covered are hashed into a bitmap of edges, in the style of AFL. Programs that cover an edge
+//! which no earlier program covered are kept as seeds. Two kinds of instrumentation are supported:
+//!
+//! - `sancov`: a build with SanitizerCoverage and a sanitizer runtime, which writes the PCs that
+//!   were reached to a `.sancov` file when `ASAN_OPTIONS` contains `coverage=1`. For naga, this is
+//!   e.g. `RUSTFLAGS="-Zsanitizer=address -Cpasses=sancov-module
+//!   -Cllvm-args=-sanitizer-coverage-level=3 -Cllvm-args=-sanitizer-coverage-trace-pc-guard"`.
+//! - `llvm`: a build with LLVM source-based coverage (e.g. `RUSTFLAGS="-Cinstrument-coverage"`),
+//!   which writes a raw profile to `LLVM_PROFILE_FILE`. The profile is converted with
+//!   `llvm-profdata` and `llvm-cov` from the PATH, and each line that was executed counts as an
+//!   edge. This is much slower than sancov, since the whole report is exported for each program.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use clap::ValueEnum;
+use eyre::{eyre, Context};
+use process_control::{ChildExt, Control};
+
+/// Number of entries in the coverage bitmap.
+const MAP_SIZE: usize = 1 << 16;
+
+/// Time after which the instrumented compiler is killed, in which case its coverage is ignored.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+const SANCOV_MAGIC_64: u64 = 0xC0BF_FFFF_FFFF_FF64;
+const SANCOV_MAGIC_32: u64 = 0xC0BF_FFFF_FFFF_FF32;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum CoverageFormat {
+    Sancov,
+    Llvm,
+}
+
+pub struct Coverage {
+    command: Vec<String>,
+    format: CoverageFormat,
+    /// Directory that the shader and the coverage files are written to.
+    dir: PathBuf,
+    map: Vec<bool>,
+    edges: usize,
+}
+
+impl Coverage {
+    /// Creates an empty coverage map for the instrumented `command`, which is split on whitespace.
+    pub fn new(command: &str, format: CoverageFormat, dir: PathBuf) -> eyre::Result<Coverage> {
+        let command = command
+            .split_whitespace()
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        if command.is_empty() {
+            return Err(eyre!("coverage command must not be empty"));
+        }
+
+        Ok(Coverage {
+            command,
+            format,
+            dir,
+            map: vec![false; MAP_SIZE],
+            edges: 0,
+        })
+    }
+
+    /// Number of entries in the map that have been covered so far.
+    pub fn edges(&self) -> usize {
+        self.edges
+    }
+
+    /// Compiles a shader with the instrumented compiler, and returns the number of edges that it
+    /// covered for the first time.
+    pub fn run(&mut self, shader: &str) -> eyre::Result<usize> {
+        // Coverage files aren't overwritten by every compiler, so they're removed before each run
+        if self.dir.exists() {
+            std::fs::remove_dir_all(&self.dir)?;
+        }
+
+        std::fs::create_dir_all(&self.dir)?;
+
+        let shader_path = self.dir.join("shader.wgsl");
+        std::fs::write(&shader_path, shader)?;
+
+        let mut command = Command::new(&self.command[0]);
+        command
+            .args(&self.command[1..])
+            .arg(&shader_path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        match self.format {
+            CoverageFormat::Sancov => command.env(
+                "ASAN_OPTIONS",
+                format!("coverage=1:coverage_dir={}", self.dir.display()),
+            ),
+            CoverageFormat::Llvm => {
+                command.env("LLVM_PROFILE_FILE", self.dir.join("coverage.profraw"))
+            }
+        };
+
+        let mut child = command
+            .spawn()
+            .wrap_err_with(|| format!("failed to run {}", self.command[0]))?;
+
+        // Crashes are detected by the harness, so the exit status doesn't matter here
+        let status = child
+            .controlled()
+            .time_limit(TIMEOUT)
+            .terminate_for_timeout()
+            .wait()?;
+
+        if status.is_none() {
+            return Ok(0);
+        }
+
+        let edges = match self.format {
+            CoverageFormat::Sancov => read_sancov(&self.dir)?,
+            CoverageFormat::Llvm => read_llvm(&self.dir, &self.command[0])?,
+        };
+
+        let mut new = 0;
+        for edge in edges {
+            let entry = &mut self.map[edge as usize % MAP_SIZE];
+            if !*entry {
+                *entry = true;
+                new += 1;
+            }
+        }
+
+        self.edges += new;
+
+        Ok(new)
+    }
+}
+
+fn hash(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Reads the PCs from each `.sancov` file in `dir`, hashed together with the name of the module
+/// that they're in.
+///
+/// The PCs are offsets into their module, so they're the same across runs even with ASLR.
+fn read_sancov(dir: &Path) -> eyre::Result<Vec<u64>> {
+    let mut edges = vec![];
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|it| it.to_str()) != Some("sancov") {
+            continue;
+        }
+
+        // Files are named `<module>.<pid>.sancov`
+        let name = path.file_name().unwrap().to_string_lossy();
+        let module = name.split('.').next().unwrap_or_default().to_owned();
+
+        let data = std::fs::read(&path)?;
+        let pcs = parse_sancov(&module, &data)
+            .ok_or_else(|| eyre!("{} is not a sancov file", path.display()))?;
+
+        edges.extend(pcs);
+    }
+
+    Ok(edges)
+}
+
+/// Returns the hashed PCs in the contents of a `.sancov` file, or `None` if it doesn't start with
+/// a sancov magic number.
+fn parse_sancov(module: &str, data: &[u8]) -> Option<Vec<u64>> {
+    // Files which the runtime didn't get to write anything to are skipped
+    if data.len() < 8 {
+        return Some(vec![]);
+    }
+
+    let magic = u64::from_le_bytes(data[..8].try_into().unwrap());
+    let width = match magic {
+        SANCOV_MAGIC_64 => 8,
+        SANCOV_MAGIC_32 => 4,
+        _ => return None,
+    };
+
+    let pcs = data[8..]
+        .chunks_exact(width)
+        .map(|pc| {
+            let mut bytes = [0; 8];
+            bytes[..width].copy_from_slice(pc);
+            hash((module, u64::from_le_bytes(bytes)))
+        })
+        .collect();
+
+    Some(pcs)
+}
+
+/// Converts the raw profile in `dir` into an lcov report, and returns the lines that were
+/// executed, hashed together with their file names.
+fn read_llvm(dir: &Path, binary: &str) -> eyre::Result<Vec<u64>> {
+    let raw = dir.join("coverage.profraw");
+    if !raw.exists() {
+        return Ok(vec![]);
+    }
+
+    let profile = dir.join("coverage.profdata");
+    let status = Command::new("llvm-profdata")
+        .args(["merge", "-sparse"])
+        .arg(&raw)
+        .arg("-o")
+        .arg(&profile)
+        .status()
+        .wrap_err("failed to run llvm-profdata")?;
+
+    if !status.success() {
+        return Err(eyre!("llvm-profdata failed with {status}"));
+    }
+
+    let output = Command::new("llvm-cov")
+        .args(["export", "-format=lcov", binary])
+        .arg(format!("-instr-profile={}", profile.display()))
+        .stderr(Stdio::null())
+        .output()
+        .wrap_err("failed to run llvm-cov")?;
+
+    if !output.status.success() {
+        return Err(eyre!("llvm-cov failed with {}", output.status));
+    }
+
+    Ok(parse_lcov(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Returns the executed lines in an lcov report, hashed together with their file names.
+fn parse_lcov(report: &str) -> Vec<u64> {
+    let mut edges = vec![];
+    let mut file = "";
+
+    for line in report.lines() {
+        if let Some(name) = line.strip_prefix("SF:") {
+            file = name;
+        } else if let Some(record) = line.strip_prefix("DA:") {
+            // Records are `DA:<line>,<count>`
+            let mut parts = record.split(',');
+            let line = parts.next().unwrap_or_default();
+            let count = parts.next().unwrap_or_default();
+            if count.parse::<u64>().map_or(false, |count| count > 0) {
+                edges.push(hash((file, line)));
+            }
+        }
+    }
+
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sancov(magic: u64, pcs: &[u64], width: usize) -> Vec<u8> {
+        let mut data = magic.to_le_bytes().to_vec();
+        for pc in pcs {
+            data.extend_from_slice(&pc.to_le_bytes()[..width]);
+        }
+        data
+    }
+
+    #[test]
+    fn parse_sancov_64() {
+        let data = sancov(SANCOV_MAGIC_64, &[0x10, 0x1_0000_0020], 8);
+
+        assert_eq!(
+            parse_sancov("naga", &data),
+            Some(vec![
+                hash(("naga", 0x10u64)),
+                hash(("naga", 0x1_0000_0020u64))
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_sancov_32() {
+        let mut data = sancov(SANCOV_MAGIC_32, &[0x10, 0x20], 4);
+        // Incomplete PCs at the end are ignored
+        data.push(0xff);
+
+        // 32-bit PCs hash the same as the equivalent 64-bit ones
+        assert_eq!(
+            parse_sancov("naga", &data),
+            Some(vec![hash(("naga", 0x10u64)), hash(("naga", 0x20u64))])
+        );
+    }
+
+    #[test]
+    fn sancov_pcs_depend_on_module() {
+        let data = sancov(SANCOV_MAGIC_64, &[0x10], 8);
+
+        assert_ne!(parse_sancov("naga", &data), parse_sancov("tint", &data));
+    }
+
+    #[test]
+    fn invalid_sancov() {
+        assert_eq!(parse_sancov("naga", &[]), Some(vec![]));
+        assert_eq!(parse_sancov("naga", &sancov(0x1234, &[0x10], 8)), None);
+    }
+
+    #[test]
+    fn read_sancov_files() {
+        let dir = std::env::temp_dir().join(format!("wgslsmith-sancov-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let data = sancov(SANCOV_MAGIC_64, &[0x10], 8);
+        std::fs::write(dir.join("naga.1234.sancov"), &data).unwrap();
+        std::fs::write(dir.join("shader.wgsl"), "fn main() {}").unwrap();
+
+        let edges = read_sancov(&dir).unwrap();
+
+        std::fs::write(dir.join("bad.1234.sancov"), sancov(0, &[], 8)).unwrap();
+        let bad = read_sancov(&dir);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(edges, vec![hash(("naga", 0x10u64))]);
+        assert!(bad.is_err());
+    }
+
+    #[test]
+    fn parse_lcov_records() {
+        let report = "\
+TN:
+SF:src/front/wgsl.rs
+DA:10,3
+DA:11,0
+DA:12,1
+end_of_record
+SF:src/back/spv.rs
+DA:10,1
+DA:oops
+end_of_record
+";
+
+        assert_eq!(
+            parse_lcov(report),
+            vec![
+                hash(("src/front/wgsl.rs", "10")),
+                hash(("src/front/wgsl.rs", "12")),
+                hash(("src/back/spv.rs", "10")),
+            ]
+        );
+    }
+}
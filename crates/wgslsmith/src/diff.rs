@@ -0,0 +1,638 @@
+//! Structural diff of two shaders, for `wgslsmith diff`.
+//!
+//! Module-scope declarations are matched up by name, and the statements of each pair of
+//! functions are aligned block by block using their longest common subsequence. Statements with
+//! blocks of their own (such as ifs and loops) which weren't aligned are paired up with another
+//! statement of the same kind where possible, so that a change deep inside a loop shows up as a
+//! change to the loop's body rather than the whole loop being removed and added again.
+
+use std::fmt::Write;
+
+use ast::writer::Writer;
+use ast::{Else, FnDecl, ForLoopInit, ForLoopUpdate, IfStatement, Module, Statement};
+use clap::Parser;
+use eyre::Context;
+
+use crate::fmt::read_shader_from_path;
+
+#[derive(Parser)]
+pub struct Options {
+    /// Path to the original shader (use '-' for stdin).
+    #[clap(action)]
+    pub a: String,
+
+    /// Path to the shader to compare it with.
+    #[clap(action)]
+    pub b: String,
+
+    /// Rename identifiers in both shaders in order of declaration before comparing them, so that
+    /// differences in naming are ignored.
+    #[clap(long, action)]
+    pub rename: bool,
+}
+
+pub fn run(options: Options) -> eyre::Result<()> {
+    let mut a = parse(&options.a)?;
+    let mut b = parse(&options.b)?;
+
+    if options.rename {
+        ast::rename::rename_identifiers(&mut a);
+        ast::rename::rename_identifiers(&mut b);
+    }
+
+    let mut diff = Diff::default();
+    diff_decls(&mut diff, &decls(&a), &decls(&b));
+    diff_functions(&mut diff, &a, &b);
+
+    // Like diff(1), the exit code says whether there were any differences
+    if diff.changed {
+        println!("--- {}", options.a);
+        println!("+++ {}", options.b);
+        print!("{}", diff.out);
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn parse(path: &str) -> eyre::Result<Module> {
+    let source = read_shader_from_path(path)?;
+    parser::try_parse(&source).wrap_err_with(|| format!("failed to parse '{path}'"))
+}
+
+#[derive(Default)]
+struct Diff {
+    out: String,
+    changed: bool,
+}
+
+impl Diff {
+    /// Writes a line prefixed with `marker`, which is `-` for removed lines, `+` for added lines
+    /// and a space for context.
+    fn line(&mut self, marker: char, depth: usize, text: &str) {
+        if marker != ' ' {
+            self.changed = true;
+        }
+
+        writeln!(self.out, "{marker} {}{text}", "    ".repeat(depth)).unwrap();
+    }
+
+    fn lines(&mut self, marker: char, text: &str) {
+        for line in text.lines() {
+            self.line(marker, 0, line);
+        }
+    }
+}
+
+/// A module-scope declaration other than a function.
+struct Decl {
+    kind: &'static str,
+    name: String,
+    text: String,
+}
+
+fn decls(module: &Module) -> Vec<Decl> {
+    let writer = Writer::default();
+    let mut decls = vec![];
+
+    for name in &module.enables {
+        decls.push(Decl {
+            kind: "enable",
+            name: name.clone(),
+            text: format!("enable {name};"),
+        });
+    }
+
+    for decl in &module.structs {
+        let mut text = String::new();
+        writer.write_struct(&mut text, decl).unwrap();
+        decls.push(Decl {
+            kind: "struct",
+            name: decl.name.clone(),
+            text,
+        });
+    }
+
+    for decl in &module.consts {
+        let mut text = String::new();
+        writer.write_global_const(&mut text, decl).unwrap();
+        decls.push(Decl {
+            kind: "const",
+            name: decl.name.clone(),
+            text,
+        });
+    }
+
+    // Assertions don't have names, so they only match identical assertions
+    for stmt in &module.const_asserts {
+        let text = format!("{stmt};");
+        decls.push(Decl {
+            kind: "const_assert",
+            name: text.clone(),
+            text,
+        });
+    }
+
+    for decl in &module.vars {
+        let mut text = String::new();
+        writer.write_global_var(&mut text, decl).unwrap();
+        decls.push(Decl {
+            kind: "var",
+            name: decl.name.clone(),
+            text,
+        });
+    }
+
+    decls
+}
+
+fn diff_decls(diff: &mut Diff, a: &[Decl], b: &[Decl]) {
+    let find = |decls: &[Decl], decl: &Decl| {
+        decls
+            .iter()
+            .position(|it| it.kind == decl.kind && it.name == decl.name)
+    };
+
+    for decl in a {
+        match find(b, decl) {
+            Some(index) if b[index].text == decl.text => {}
+            Some(index) => {
+                diff.lines('-', &decl.text);
+                diff.lines('+', &b[index].text);
+            }
+            None => diff.lines('-', &decl.text),
+        }
+    }
+
+    for decl in b {
+        if find(a, decl).is_none() {
+            diff.lines('+', &decl.text);
+        }
+    }
+}
+
+fn diff_functions(diff: &mut Diff, a: &Module, b: &Module) {
+    let writer = Writer::default();
+    let write = |func: &FnDecl| {
+        let mut text = String::new();
+        writer.write_func(&mut text, func).unwrap();
+        text
+    };
+
+    for func in &a.functions {
+        let other = match b.functions.iter().find(|it| it.name == func.name) {
+            Some(other) => other,
+            None => {
+                diff.lines('-', &write(func));
+                continue;
+            }
+        };
+
+        let mut body = Diff::default();
+        let a_signature = signature(&write(func));
+        let b_signature = signature(&write(other));
+
+        if a_signature == b_signature {
+            body.lines(' ', &a_signature);
+        } else {
+            body.lines('-', &a_signature);
+            body.lines('+', &b_signature);
+        }
+
+        diff_block(&mut body, 1, &nodes(&func.body), &nodes(&other.body));
+        body.line(' ', 0, "}");
+
+        if body.changed {
+            diff.line(' ', 0, &format!("@@ fn {} @@", func.name));
+            diff.out.push_str(&body.out);
+            diff.changed = true;
+        }
+    }
+
+    for func in &b.functions {
+        if !a.functions.iter().any(|it| it.name == func.name) {
+            diff.lines('+', &write(func));
+        }
+    }
+}
+
+/// Returns the attributes and header of a written function, up to and including the line that
+/// opens its body.
+fn signature(text: &str) -> String {
+    let mut signature = String::new();
+
+    for line in text.lines() {
+        signature.push_str(line);
+        signature.push('\n');
+
+        if line.ends_with('{') {
+            break;
+        }
+    }
+
+    signature
+}
+
+/// A statement, with any blocks that it contains.
+#[derive(PartialEq)]
+struct Node {
+    kind: &'static str,
+    /// The whole statement if it doesn't contain any blocks, or the part before its first block.
+    header: String,
+    blocks: Vec<Block>,
+    /// Whether the blocks are nested inside braces after the header (as in a switch), rather than
+    /// following on from each other (as in an if-else chain).
+    nested: bool,
+}
+
+#[derive(PartialEq)]
+struct Block {
+    /// Text before the block, such as `else` or `case 1:`.
+    label: String,
+    body: Vec<Node>,
+}
+
+fn nodes(stmts: &[Statement]) -> Vec<Node> {
+    stmts.iter().map(node).collect()
+}
+
+fn node(stmt: &Statement) -> Node {
+    let chained = |kind, header, body| Node {
+        kind,
+        header,
+        blocks: vec![Block {
+            label: String::new(),
+            body: nodes(body),
+        }],
+        nested: false,
+    };
+
+    match stmt {
+        Statement::Compound(body) => chained("block", String::new(), body),
+        Statement::If(stmt) => if_node(stmt),
+        Statement::Loop(stmt) => chained("loop", "loop".to_owned(), &stmt.body),
+        Statement::ForLoop(stmt) => {
+            let mut header = "for (".to_owned();
+
+            if let Some(ForLoopInit::VarDecl(init)) = &stmt.header.init {
+                write!(header, "{init}").unwrap();
+            }
+
+            header.push_str("; ");
+
+            if let Some(condition) = &stmt.header.condition {
+                write!(header, "{condition}").unwrap();
+            }
+
+            header.push_str("; ");
+
+            if let Some(ForLoopUpdate::Assignment(update)) = &stmt.header.update {
+                write!(header, "{update}").unwrap();
+            }
+
+            header.push(')');
+            chained("for", header, &stmt.body)
+        }
+        Statement::Switch(stmt) => Node {
+            kind: "switch",
+            header: format!("switch ({})", stmt.selector),
+            blocks: stmt
+                .cases
+                .iter()
+                .map(|case| Block {
+                    label: format!("case {}:", case.selector),
+                    body: nodes(&case.body),
+                })
+                .chain(std::iter::once(Block {
+                    label: "default:".to_owned(),
+                    body: nodes(&stmt.default),
+                }))
+                .collect(),
+            nested: true,
+        },
+        stmt => Node {
+            kind: "",
+            header: stmt.to_string(),
+            blocks: vec![],
+            nested: false,
+        },
+    }
+}
+
+/// Flattens an if-else chain into a single node, with a block for each branch.
+fn if_node(stmt: &IfStatement) -> Node {
+    let mut blocks = vec![Block {
+        label: String::new(),
+        body: nodes(&stmt.body),
+    }];
+
+    let mut else_ = stmt.else_.as_deref();
+    while let Some(branch) = else_ {
+        match branch {
+            Else::If(stmt) => {
+                blocks.push(Block {
+                    label: format!("else if ({})", stmt.condition),
+                    body: nodes(&stmt.body),
+                });
+                else_ = stmt.else_.as_deref();
+            }
+            Else::Else(body) => {
+                blocks.push(Block {
+                    label: "else".to_owned(),
+                    body: nodes(body),
+                });
+                else_ = None;
+            }
+        }
+    }
+
+    Node {
+        kind: "if",
+        header: format!("if ({})", stmt.condition),
+        blocks,
+        nested: false,
+    }
+}
+
+/// Returns the line that opens a block after `header`.
+fn open(header: &str) -> String {
+    if header.is_empty() {
+        "{".to_owned()
+    } else {
+        format!("{header} {{")
+    }
+}
+
+fn write_node(diff: &mut Diff, marker: char, depth: usize, node: &Node) {
+    if node.blocks.is_empty() {
+        diff.line(marker, depth, &node.header);
+        return;
+    }
+
+    diff.line(marker, depth, &open(&node.header));
+
+    for (index, block) in node.blocks.iter().enumerate() {
+        write_block_open(diff, marker, depth, node, index, block);
+        for stmt in &block.body {
+            write_node(diff, marker, block_depth(depth, node), stmt);
+        }
+        write_block_close(diff, marker, depth, node);
+    }
+
+    diff.line(marker, depth, "}");
+}
+
+/// Writes the line that opens a block of `node`, if it isn't part of the header.
+fn write_block_open(
+    diff: &mut Diff,
+    marker: char,
+    depth: usize,
+    node: &Node,
+    index: usize,
+    block: &Block,
+) {
+    if node.nested {
+        diff.line(marker, depth + 1, &open(&block.label));
+    } else if index > 0 {
+        diff.line(marker, depth, &format!("}} {}", open(&block.label)));
+    }
+}
+
+fn write_block_close(diff: &mut Diff, marker: char, depth: usize, node: &Node) {
+    if node.nested {
+        diff.line(marker, depth + 1, "}");
+    }
+}
+
+fn block_depth(depth: usize, node: &Node) -> usize {
+    if node.nested {
+        depth + 2
+    } else {
+        depth + 1
+    }
+}
+
+/// Diffs two statements of the same kind which both contain blocks, matching up their blocks
+/// by label.
+fn diff_node(diff: &mut Diff, depth: usize, a: &Node, b: &Node) {
+    if a.header == b.header {
+        diff.line(' ', depth, &open(&a.header));
+    } else {
+        diff.line('-', depth, &open(&a.header));
+        diff.line('+', depth, &open(&b.header));
+    }
+
+    let mut used = vec![false; b.blocks.len()];
+
+    for (index, block) in a.blocks.iter().enumerate() {
+        let other = b
+            .blocks
+            .iter()
+            .enumerate()
+            .position(|(i, it)| !used[i] && it.label == block.label);
+
+        match other {
+            Some(other) => {
+                used[other] = true;
+                write_block_open(diff, ' ', depth, a, index, block);
+                diff_block(
+                    diff,
+                    block_depth(depth, a),
+                    &block.body,
+                    &b.blocks[other].body,
+                );
+                write_block_close(diff, ' ', depth, a);
+            }
+            None => {
+                write_block_open(diff, '-', depth, a, index, block);
+                for stmt in &block.body {
+                    write_node(diff, '-', block_depth(depth, a), stmt);
+                }
+                write_block_close(diff, '-', depth, a);
+            }
+        }
+    }
+
+    for (index, block) in b.blocks.iter().enumerate() {
+        if !used[index] {
+            write_block_open(diff, '+', depth, b, index, block);
+            for stmt in &block.body {
+                write_node(diff, '+', block_depth(depth, b), stmt);
+            }
+            write_block_close(diff, '+', depth, b);
+        }
+    }
+
+    diff.line(' ', depth, "}");
+}
+
+enum Op {
+    Same,
+    Removed(usize),
+    Added(usize),
+}
+
+/// Diffs two lists of statements, collapsing runs of unchanged statements.
+fn diff_block(diff: &mut Diff, depth: usize, a: &[Node], b: &[Node]) {
+    // Lengths of the longest common subsequences of the suffixes of `a` and `b`
+    let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push(Op::Same);
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(Op::Removed(i));
+            i += 1;
+        } else {
+            ops.push(Op::Added(j));
+            j += 1;
+        }
+    }
+
+    let mut unchanged = 0;
+    let mut removed = vec![];
+    let mut added = vec![];
+
+    for op in ops.into_iter().chain(std::iter::once(Op::Same)) {
+        match op {
+            Op::Removed(i) => removed.push(&a[i]),
+            Op::Added(j) => added.push(&b[j]),
+            Op::Same => {
+                if !removed.is_empty() || !added.is_empty() {
+                    write_unchanged(diff, depth, unchanged);
+                    diff_gap(diff, depth, &removed, &added);
+                    unchanged = 0;
+                    removed.clear();
+                    added.clear();
+                }
+
+                unchanged += 1;
+            }
+        }
+    }
+
+    // The sentinel at the end isn't a real statement
+    write_unchanged(diff, depth, unchanged - 1);
+}
+
+fn write_unchanged(diff: &mut Diff, depth: usize, count: usize) {
+    match count {
+        0 => {}
+        1 => diff.line(' ', depth, "... 1 unchanged statement"),
+        n => diff.line(' ', depth, &format!("... {n} unchanged statements")),
+    }
+}
+
+/// Diffs a run of statements that were removed from `a` and added in `b`, pairing up statements
+/// of the same kind that contain blocks.
+fn diff_gap(diff: &mut Diff, depth: usize, removed: &[&Node], added: &[&Node]) {
+    let mut next = 0;
+
+    for &node in removed {
+        let pair = added[next..]
+            .iter()
+            .position(|it| !node.blocks.is_empty() && it.kind == node.kind);
+
+        match pair {
+            Some(offset) => {
+                for &node in &added[next..next + offset] {
+                    write_node(diff, '+', depth, node);
+                }
+                diff_node(diff, depth, node, added[next + offset]);
+                next += offset + 1;
+            }
+            None => write_node(diff, '-', depth, node),
+        }
+    }
+
+    for &node in &added[next..] {
+        write_node(diff, '+', depth, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(a: &str, b: &str) -> Diff {
+        let a = parser::parse(a);
+        let b = parser::parse(b);
+
+        let mut diff = Diff::default();
+        diff_decls(&mut diff, &decls(&a), &decls(&b));
+        diff_functions(&mut diff, &a, &b);
+        diff
+    }
+
+    #[test]
+    fn identical_shaders() {
+        let shader = "fn f() {\n    var x: u32;\n    x = 1u;\n}\n";
+        let diff = diff(shader, shader);
+
+        assert!(!diff.changed);
+        assert_eq!(diff.out, "");
+    }
+
+    #[test]
+    fn changed_decls() {
+        let diff = diff(
+            "var<private> g: u32;\nvar<private> h: u32;\n",
+            "var<private> g: i32;\nvar<private> k: u32;\n",
+        );
+
+        assert!(diff.changed);
+        assert_eq!(
+            diff.out,
+            "- var<private> g: u32;\n\
+             + var<private> g: i32;\n\
+             - var<private> h: u32;\n\
+             + var<private> k: u32;\n"
+        );
+    }
+
+    #[test]
+    fn changed_statement_in_loop() {
+        let diff = diff(
+            "fn f() {\n    var x: u32;\n    loop {\n        x = 1u;\n        break;\n    }\n}\n",
+            "fn f() {\n    var x: u32;\n    loop {\n        x = 2u;\n        break;\n    }\n}\n",
+        );
+
+        // The loops are diffed block by block rather than the whole loop being replaced
+        assert!(diff.changed);
+        assert_eq!(
+            diff.out,
+            "  @@ fn f @@\n\
+             \x20 fn f() {\n\
+             \x20     ... 1 unchanged statement\n\
+             \x20     loop {\n\
+             -         x = 1u;\n\
+             +         x = 2u;\n\
+             \x20         ... 1 unchanged statement\n\
+             \x20     }\n\
+             \x20 }\n"
+        );
+    }
+
+    #[test]
+    fn added_and_removed_functions() {
+        let diff = diff("fn f() {}\nfn g() {}\n", "fn f() {}\nfn h() {}\n");
+
+        assert!(diff.changed);
+        assert!(diff.out.contains("- fn g() {\n"));
+        assert!(diff.out.contains("+ fn h() {\n"));
+        assert!(!diff.out.contains("fn f"));
+    }
+}
@@ -0,0 +1,396 @@
+//! Compiler drivers which shell out to external executables.
+//!
+//! Unlike the in-process compilers in `compiler`, these don't need tint or naga to be linked into
+//! wgslsmith, so they can be used from builds without the reducer feature. Running the compilers
+//! in a subprocess also means that crashes and hangs can be captured, rather than taking down
+//! wgslsmith with them.
+
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use eyre::Context;
+use process_control::{ChildExt, Control};
+use serde::Deserialize;
+
+/// Format of a file consumed or produced by a driver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Wgsl,
+    Hlsl,
+    Msl,
+    Spirv,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Wgsl => "wgsl",
+            Format::Hlsl => "hlsl",
+            Format::Msl => "metal",
+            Format::Spirv => "spv",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wgsl" => Ok(Format::Wgsl),
+            "hlsl" => Ok(Format::Hlsl),
+            "msl" => Ok(Format::Msl),
+            "spirv" => Ok(Format::Spirv),
+            _ => Err("invalid format (must be one of wgsl, hlsl, msl or spirv)"),
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Format::Wgsl => write!(f, "wgsl"),
+            Format::Hlsl => write!(f, "hlsl"),
+            Format::Msl => write!(f, "msl"),
+            Format::Spirv => write!(f, "spirv"),
+        }
+    }
+}
+
+/// Result of running a driver on a single input.
+#[derive(Debug)]
+pub enum Outcome {
+    Success,
+    /// The compiler exited normally with an error, with the given output.
+    Rejected(String),
+    /// The compiler was killed by a signal or panicked, with the given output.
+    Crash(String),
+    /// The compiler didn't finish within the time limit.
+    Timeout,
+}
+
+impl Outcome {
+    pub fn is_success(&self) -> bool {
+        matches!(self, Outcome::Success)
+    }
+
+    pub fn output(&self) -> Option<&str> {
+        match self {
+            Outcome::Rejected(output) | Outcome::Crash(output) => Some(output),
+            Outcome::Success | Outcome::Timeout => None,
+        }
+    }
+}
+
+/// An external compiler that can be run on a file.
+pub trait CompilerDriver {
+    /// Name used to identify the driver in output and file names.
+    fn name(&self) -> String;
+
+    /// Format of the files that the driver consumes.
+    fn input_format(&self) -> Format;
+
+    /// Format of the file that the driver produces, if any.
+    fn output_format(&self) -> Option<Format>;
+
+    /// Builds the command to run the driver on `input`.
+    ///
+    /// `output` is only given if the driver produces an output file.
+    fn command(&self, input: &Path, output: Option<&Path>) -> Command;
+
+    /// Runs the driver on `input`, killing it if it doesn't finish within `timeout`.
+    fn run(
+        &self,
+        input: &Path,
+        output_path: Option<&Path>,
+        timeout: Duration,
+    ) -> eyre::Result<Outcome> {
+        // Remove any output left over from a previous run, so that it can't be mistaken for this
+        // run's output
+        if let Some(path) = output_path {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        let child = self
+            .command(input, output_path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .wrap_err_with(|| format!("failed to run {}", self.name()))?;
+
+        let output = child
+            .controlled_with_output()
+            .time_limit(timeout)
+            .terminate_for_timeout()
+            .wait()?;
+
+        let output = match output {
+            Some(output) => output,
+            None => return Ok(Outcome::Timeout),
+        };
+
+        let mut log = String::from_utf8_lossy(&output.stdout).into_owned();
+        log.push_str(&String::from_utf8_lossy(&output.stderr));
+
+        if output.status.success() {
+            return match output_path {
+                // The output is checked since some compilers exit successfully without writing
+                // anything when they hit an internal error
+                Some(path) if !path.exists() => Ok(Outcome::Rejected(format!(
+                    "{log}\n{} didn't produce {}",
+                    self.name(),
+                    path.display()
+                ))),
+                _ => Ok(Outcome::Success),
+            };
+        }
+
+        // A missing exit code means the process was killed by a signal, and Rust programs (such as
+        // the naga CLI) exit with 101 when they panic
+        match output.status.code() {
+            None | Some(101) => Ok(Outcome::Crash(log)),
+            Some(_) => Ok(Outcome::Rejected(log)),
+        }
+    }
+}
+
+/// The tint executable, translating WGSL to another format.
+pub struct TintCli {
+    pub path: PathBuf,
+    pub format: Format,
+}
+
+impl CompilerDriver for TintCli {
+    fn name(&self) -> String {
+        format!("tint-{}", self.format)
+    }
+
+    fn input_format(&self) -> Format {
+        Format::Wgsl
+    }
+
+    fn output_format(&self) -> Option<Format> {
+        Some(self.format)
+    }
+
+    fn command(&self, input: &Path, output: Option<&Path>) -> Command {
+        let mut cmd = Command::new(&self.path);
+        cmd.args(["--format", &self.format.to_string()]);
+        if let Some(output) = output {
+            cmd.arg("-o").arg(output);
+        }
+        cmd.arg(input);
+        cmd
+    }
+}
+
+/// The naga CLI, translating WGSL to another format.
+///
+/// naga picks the output format from the extension of the output file.
+pub struct NagaCli {
+    pub path: PathBuf,
+    pub format: Format,
+}
+
+impl CompilerDriver for NagaCli {
+    fn name(&self) -> String {
+        format!("naga-{}", self.format)
+    }
+
+    fn input_format(&self) -> Format {
+        Format::Wgsl
+    }
+
+    fn output_format(&self) -> Option<Format> {
+        Some(self.format)
+    }
+
+    fn command(&self, input: &Path, output: Option<&Path>) -> Command {
+        let mut cmd = Command::new(&self.path);
+        cmd.arg(input);
+        if let Some(output) = output {
+            cmd.arg(output);
+        }
+        cmd
+    }
+}
+
+/// The DirectX shader compiler, compiling HLSL compute shaders.
+pub struct Dxc {
+    pub path: PathBuf,
+}
+
+impl CompilerDriver for Dxc {
+    fn name(&self) -> String {
+        "dxc".to_owned()
+    }
+
+    fn input_format(&self) -> Format {
+        Format::Hlsl
+    }
+
+    fn output_format(&self) -> Option<Format> {
+        None
+    }
+
+    fn command(&self, input: &Path, _: Option<&Path>) -> Command {
+        let mut cmd = Command::new(&self.path);
+        cmd.args(["-T", "cs_6_0", "-E", "main"]);
+        cmd.arg(input);
+        cmd
+    }
+}
+
+/// The SPIR-V validator from SPIRV-Tools.
+pub struct SpirvVal {
+    pub path: PathBuf,
+}
+
+impl CompilerDriver for SpirvVal {
+    fn name(&self) -> String {
+        "spirv-val".to_owned()
+    }
+
+    fn input_format(&self) -> Format {
+        Format::Spirv
+    }
+
+    fn output_format(&self) -> Option<Format> {
+        None
+    }
+
+    fn command(&self, input: &Path, _: Option<&Path>) -> Command {
+        let mut cmd = Command::new(&self.path);
+        cmd.arg(input);
+        cmd
+    }
+}
+
+/// A driver defined in the config file, which runs an arbitrary command.
+///
+/// The arguments can refer to the input and output files with `{input}` and `{output}`.
+#[derive(Clone, Deserialize)]
+pub struct CommandDriver {
+    pub name: String,
+    pub path: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub input: Format,
+    /// Format of the file that the command is expected to write to `{output}`, if any.
+    #[serde(default)]
+    pub output: Option<Format>,
+}
+
+impl CompilerDriver for CommandDriver {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn input_format(&self) -> Format {
+        self.input
+    }
+
+    fn output_format(&self) -> Option<Format> {
+        self.output
+    }
+
+    fn command(&self, input: &Path, output: Option<&Path>) -> Command {
+        let input = input.to_string_lossy();
+        let output = output.map(|it| it.to_string_lossy()).unwrap_or_default();
+
+        let mut cmd = Command::new(&self.path);
+        for arg in &self.args {
+            cmd.arg(arg.replace("{input}", &input).replace("{output}", &output));
+        }
+        cmd
+    }
+}
+
+/// A sequence of drivers, each consuming the output of the previous one.
+pub struct Pipeline {
+    drivers: Vec<Box<dyn CompilerDriver>>,
+}
+
+impl Pipeline {
+    pub fn new(drivers: Vec<Box<dyn CompilerDriver>>) -> eyre::Result<Pipeline> {
+        let mut format = Some(Format::Wgsl);
+
+        for driver in &drivers {
+            match format {
+                Some(format) if format == driver.input_format() => {}
+                Some(format) => eyre::bail!(
+                    "{} expects {} input, but got {format}",
+                    driver.name(),
+                    driver.input_format()
+                ),
+                None => eyre::bail!(
+                    "{} has no input, since the previous stage has no output",
+                    driver.name()
+                ),
+            }
+
+            format = driver.output_format();
+        }
+
+        Ok(Pipeline { drivers })
+    }
+
+    pub fn name(&self) -> String {
+        self.drivers
+            .iter()
+            .map(|driver| driver.name())
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+
+    /// Runs each driver in turn on the WGSL file at `input`, writing intermediate files to `dir`.
+    pub fn run(&self, input: &Path, dir: &Path, timeout: Duration) -> eyre::Result<PipelineRun> {
+        let mut input = input.to_owned();
+        let mut times = vec![];
+
+        for driver in &self.drivers {
+            let output = driver
+                .output_format()
+                .map(|format| dir.join(format!("{}.{}", driver.name(), format.extension())));
+
+            let start = Instant::now();
+            let outcome = driver.run(&input, output.as_deref(), timeout)?;
+
+            if !matches!(outcome, Outcome::Timeout) {
+                times.push((driver.name(), start.elapsed()));
+            }
+
+            if !outcome.is_success() {
+                return Ok(PipelineRun {
+                    times,
+                    failure: Some((driver.name(), outcome)),
+                });
+            }
+
+            if let Some(output) = output {
+                input = output;
+            }
+        }
+
+        Ok(PipelineRun {
+            times,
+            failure: None,
+        })
+    }
+}
+
+/// Result of running a [`Pipeline`] on a single input.
+pub struct PipelineRun {
+    /// Name of each driver that finished within the time limit, along with how long it took.
+    pub times: Vec<(String, Duration)>,
+    /// Name of the first driver that didn't succeed along with its outcome, or `None` if they all
+    /// succeeded.
+    pub failure: Option<(String, Outcome)>,
+}
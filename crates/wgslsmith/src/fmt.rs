@@ -1,6 +1,7 @@
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Write};
 
+use ast::Module;
 use clap::Parser;
 
 #[derive(Parser)]
@@ -12,35 +13,83 @@ pub struct Options {
     /// Path at which to write output (use '-' for stdout).
     #[clap(short, long, action, default_value = "-")]
     pub output: String,
+
+    /// Number of spaces to indent blocks by.
+    #[clap(long, action, default_value = "4")]
+    pub indent: usize,
+
+    /// Write the whole shader on a single line.
+    #[clap(long, action)]
+    pub minify: bool,
+
+    /// Add a trailing comma after the last function parameter.
+    #[clap(long, action)]
+    pub trailing_commas: bool,
+
+    /// Rename identifiers in order of declaration, so that shaders which only differ in naming
+    /// are formatted identically.
+    #[clap(long, action)]
+    pub rename: bool,
+
+    /// Don't write the formatted shader, but exit with status 1 if it differs from the input.
+    #[clap(long, action)]
+    pub check: bool,
 }
 
 pub fn run(options: Options) -> eyre::Result<()> {
     let source = read_shader_from_path(&options.input)?;
-    let ast = parser::parse(&source);
+    let mut ast = parser::try_parse(&source)?;
+
+    if options.rename {
+        ast::rename::rename_identifiers(&mut ast);
+    }
 
-    struct Output(Box<dyn std::io::Write>);
+    let formatted = format_module(
+        &ast,
+        ast::writer::Options {
+            indent_width: options.indent,
+            minify: options.minify,
+            trailing_commas: options.trailing_commas,
+            ..Default::default()
+        },
+    );
 
-    impl std::fmt::Write for Output {
-        fn write_str(&mut self, s: &str) -> std::fmt::Result {
-            use std::io::Write;
-            self.0.write_all(s.as_bytes()).unwrap();
-            Ok(())
+    if options.check {
+        if formatted != source {
+            eprintln!("{} is not formatted", options.input);
+            std::process::exit(1);
         }
+
+        return Ok(());
     }
 
-    let output: Box<dyn std::io::Write> = match options.output.as_str() {
-        "-" => Box::new(std::io::stdout()),
-        path => Box::new(File::create(path)?),
-    };
+    match options.output.as_str() {
+        "-" => std::io::stdout().write_all(formatted.as_bytes())?,
+        path => std::fs::write(path, formatted)?,
+    }
 
-    ast::writer::Writer::default()
-        .write_module(&mut Output(output), &ast)
+    Ok(())
+}
+
+/// Writes a module with the given writer options.
+///
+/// The output only depends on the structure of the module and not on how the source was laid out,
+/// so formatting a shader is idempotent.
+pub fn format_module(module: &Module, options: ast::writer::Options) -> String {
+    let mut formatted = String::new();
+    ast::writer::Writer::new(options)
+        .write_module(&mut formatted, module)
         .unwrap();
+    formatted
+}
 
-    Ok(())
+/// Parses and formats a shader with the default options.
+pub fn format_shader(source: &str) -> eyre::Result<String> {
+    let module = parser::try_parse(source)?;
+    Ok(format_module(&module, Default::default()))
 }
 
-fn read_shader_from_path(path: &str) -> eyre::Result<String> {
+pub fn read_shader_from_path(path: &str) -> eyre::Result<String> {
     let mut input: Box<dyn Read> = match path {
         "-" => Box::new(std::io::stdin()),
         path => Box::new(File::open(path)?),
@@ -1,9 +1,15 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
 use std::io::{self, BufWriter, Write as _};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Instant;
 
+use ast::complexity::Complexity;
 use clap::{Parser, ValueEnum};
 use crossbeam_channel::select;
 use crossterm::event::KeyCode;
@@ -13,8 +19,9 @@ use crossterm::terminal::{
 };
 use eyre::eyre;
 use harness_types::ConfigId;
+use rand::rngs::ThreadRng;
+use rand::Rng;
 use regex::Regex;
-use tap::Tap;
 use time::{format_description, OffsetDateTime, UtcOffset};
 use tui::backend::{Backend, CrosstermBackend};
 use tui::layout::Rect;
@@ -22,8 +29,17 @@ use tui::text::Spans;
 use tui::widgets::{Block, Borders, Paragraph};
 use tui::Terminal;
 
+#[cfg(all(target_family = "unix", feature = "reducer"))]
+use crate::auto_reduce::{Job, Reductions};
 use crate::config::Config;
-use crate::harness_runner::{self, ExecutionResult, Harness};
+use crate::coordinator::{self, Client};
+use crate::coverage::{Coverage, CoverageFormat};
+use crate::harness_runner::{self, ExecutionResult, Harness, OutputTracker};
+use crate::issue::{Finding, IssueDrafts};
+use crate::provenance::Provenance;
+use crate::schedule::{PowerSchedule, SeedPool};
+use crate::status::Status;
+use crate::xfail::XfailList;
 
 #[derive(Copy, Clone, ValueEnum)]
 enum SaveStrategy {
@@ -52,6 +68,14 @@ pub struct Options {
     #[clap(long, action)]
     ignore: Vec<Regex>,
 
+    /// Path to a TOML list of known failures on particular targets.
+    ///
+    /// Builtins from the list aren't generated for programs that run on an affected target, and
+    /// findings which match an entry aren't saved. Defaults to `fuzzer.xfail` from the config
+    /// file.
+    #[clap(long, action)]
+    xfail: Option<PathBuf>,
+
     /// Address of harness server.
     #[clap(short, long, action)]
     server: Option<String>,
@@ -72,19 +96,174 @@ pub struct Options {
     /// This is mostly for debugging.
     #[clap(long, action)]
     save_failures: bool,
+
+    /// Run programs even if a structurally identical one has already been tested.
+    ///
+    /// By default, programs that only differ in identifier names or the order of operands to
+    /// commutative operators are skipped.
+    #[clap(long, action)]
+    no_dedup: bool,
+
+    /// Skip programs with a complexity score below this, without executing them.
+    ///
+    /// The score is a weighted count of the statements and expressions in the program, plus a
+    /// bonus for deeply nested blocks and long chains of variables computed from each other.
+    /// `wgslsmith gen --stats` prints the score of a program, which helps with picking a value.
+    #[clap(long, action)]
+    min_complexity: Option<f64>,
+
+    /// Skip programs with a complexity score above this, without executing them.
+    #[clap(long, action)]
+    max_complexity: Option<f64>,
+
+    /// Minimum level of generator log messages to capture (error, warn, info, debug or trace).
+    ///
+    /// If set, the generator's logs are saved to `generator.log` alongside each test case. The
+    /// harness output is always saved to `harness.log`.
+    #[clap(long, action)]
+    gen_log_level: Option<tracing::Level>,
+
+    /// Format of captured generator logs (compact or json).
+    #[clap(long, action, default_value = "compact")]
+    gen_log_format: generator::LogFormat,
+
+    /// Generation profile to bias programs towards a known bug class (bit-manipulation,
+    /// control-flow or aggregates).
+    ///
+    /// This can be given multiple times, in which case the worker cycles through the profiles,
+    /// using the next one for each program. The profile used for a saved test case is recorded in
+    /// `profile.txt`.
+    #[clap(long = "profile", action)]
+    profiles: Vec<generator::Profile>,
+
+    /// Mutate the tapes of programs that showed new behaviours, instead of always generating
+    /// independent programs, picking which tape to mutate with the given power schedule (explore,
+    /// fast, lin or quad).
+    ///
+    /// The behaviour of a program is which configurations crashed or timed out, and the pattern of
+    /// any mismatch. The tapes of programs with new behaviours are saved to `tapes/` in the output
+    /// directory.
+    #[clap(long, action)]
+    power_schedule: Option<PowerSchedule>,
+
+    /// Address of a coordinator to get seeds from and send findings to, for campaigns that are
+    /// spread across several machines.
+    ///
+    /// Findings are still saved to the output directory as well.
+    #[clap(long, action)]
+    coordinator: Option<String>,
+
+    /// Name to identify this machine to the coordinator with (defaults to the hostname).
+    #[clap(long, action, requires("coordinator"))]
+    worker_name: Option<String>,
+
+    /// Token to present to a coordinator that was started with `--token`.
+    #[clap(long, action, requires("coordinator"))]
+    coordinator_token: Option<String>,
+
+    /// GitHub repository (as `<owner>/<repo>`) to draft issues against for findings in new bug
+    /// buckets.
+    ///
+    /// A crash's bucket is the configurations that crashed and the first error in the harness
+    /// output, and a mismatch's bucket is its disagreement pattern. For the first finding in each
+    /// bucket, the title, body and a link to GitHub's new issue form with them filled in are saved
+    /// to `issues/` in the output directory. Issues are never submitted automatically. Defaults to
+    /// `fuzzer.issue_repo` from the config file.
+    #[clap(long, action)]
+    issue_repo: Option<String>,
+
+    /// Path to a Markdown template for the body of drafted issues, instead of the default one.
+    ///
+    /// The placeholders `{{kind}}`, `{{signature}}`, `{{shader}}`, `{{reconditioned}}`,
+    /// `{{inputs}}`, `{{output}}`, `{{expected}}`, `{{provenance}}` and `{{finding}}` (the path to
+    /// the test case) are replaced with the details of the finding. Defaults to
+    /// `fuzzer.issue_template` from the config file.
+    #[clap(long, action)]
+    issue_template: Option<PathBuf>,
+
+    /// Number of decisions to change when mutating a tape.
+    #[clap(long, action, default_value = "8", requires("power-schedule"))]
+    tape_mutations: usize,
+
+    /// Command line of a compiler built with coverage instrumentation, which is used to keep
+    /// programs that cover new code in the compiler as seeds for `--power-schedule`.
+    ///
+    /// Each reconditioned program is compiled by running the command with the path to the shader
+    /// appended, e.g. `/path/to/naga` to just validate it.
+    #[clap(long, action, requires("power-schedule"))]
+    coverage_cmd: Option<String>,
+
+    /// Kind of instrumentation that the coverage command was built with (sancov or llvm).
+    #[clap(long, action, default_value = "sancov", requires("coverage-cmd"))]
+    coverage_format: CoverageFormat,
+
+    /// Reduce saved test cases in the background.
+    ///
+    /// The reduced shader is saved to `reduced/shader.wgsl` in the test case directory, and the
+    /// output of the reducer to `reduce.log`. Crashes are reduced against the first error message
+    /// in the harness output, with the first configuration that crashed.
+    #[cfg(all(target_family = "unix", feature = "reducer"))]
+    #[clap(long, action)]
+    auto_reduce: bool,
+
+    /// Maximum number of reductions to run at the same time with `--auto-reduce`.
+    #[cfg(all(target_family = "unix", feature = "reducer"))]
+    #[clap(long, action, default_value = "1")]
+    reduce_jobs: usize,
+}
+
+/// Returns the arguments to generate each shader with, other than the profile.
+///
+/// The fuzzer's own defaults come first, and are overridden by the options from the environment
+/// and the config file, which are in turn overridden by the fuzzer's command line.
+fn gen_args(
+    config: &Config,
+    options: &Options,
+    xfail: Option<&XfailList>,
+) -> eyre::Result<Vec<String>> {
+    let mut args = [
+        "--block-min-stmts",
+        "1",
+        "--block-max-stmts",
+        "1",
+        "--max-fns",
+        "3",
+    ]
+    .map(String::from)
+    .to_vec();
+
+    args.extend(config.gen_args(None)?);
+
+    if options.enable_pointers {
+        args.push("--enable-pointers".to_owned());
+    }
+
+    if let Some(xfail) = xfail {
+        args.extend(xfail.gen_args(options.config.as_ref()));
+    }
+
+    if let Some(level) = options.gen_log_level {
+        args.extend(["--log-level".to_owned(), level.as_str().to_owned()]);
+        args.extend([
+            "--log-format".to_owned(),
+            options.gen_log_format.to_string(),
+        ]);
+    }
+
+    Ok(args)
 }
 
-fn gen_shader(options: &Options) -> eyre::Result<String> {
+/// Generates a shader, returning it along with the generator's logs if they were captured.
+fn gen_shader(
+    options: &Options,
+    config_file: &Path,
+    gen_args: &[String],
+) -> eyre::Result<(String, Option<String>)> {
     let output = Command::new(std::env::current_exe().unwrap())
+        .arg("--config-file")
+        .arg(config_file)
         .arg("gen")
-        .args(["--block-min-stmts", "1"])
-        .args(["--block-max-stmts", "1"])
-        .args(["--max-fns", "3"])
-        .tap_mut(|cmd| {
-            if options.enable_pointers {
-                cmd.arg("--enable-pointers");
-            }
-        })
+        .args(gen_args)
         .stdout(Stdio::piped())
         .output()?;
 
@@ -92,7 +271,13 @@ fn gen_shader(options: &Options) -> eyre::Result<String> {
         return Err(eyre!("wgslsmith command failed"));
     }
 
-    Ok(String::from_utf8(output.stdout)?)
+    // Logs are written to stderr
+    let logs = match options.gen_log_level {
+        Some(_) => Some(String::from_utf8_lossy(&output.stderr).into_owned()),
+        None => None,
+    };
+
+    Ok((String::from_utf8(output.stdout)?, logs))
 }
 
 fn recondition_shader(shader: &str) -> eyre::Result<String> {
@@ -137,6 +322,16 @@ impl ExecutionResult {
     }
 }
 
+/// Summarizes the outcome of executing a program, for deciding whether it showed a new behaviour.
+fn behaviour(result: &ExecutionResult, tracker: &OutputTracker) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    std::mem::discriminant(result).hash(&mut hasher);
+    tracker.crashed().collect::<Vec<_>>().hash(&mut hasher);
+    tracker.timeouts().hash(&mut hasher);
+    tracker.disagreement().hash(&mut hasher);
+    hasher.finish()
+}
+
 static mut UTC_OFFSET: Option<UtcOffset> = None;
 
 fn save_shader(
@@ -145,7 +340,9 @@ fn save_shader(
     reconditioned: &str,
     metadata: &str,
     output: Option<&str>,
-) -> eyre::Result<()> {
+    disagreement: Option<&str>,
+    logs: &[(&str, &str)],
+) -> eyre::Result<PathBuf> {
     let now = OffsetDateTime::now_utc().to_offset(unsafe { UTC_OFFSET }.unwrap());
     let timestamp = now.format(&format_description::parse(
         "[year]-[month]-[day]-[hour]-[minute]-[second]",
@@ -159,14 +356,35 @@ fn save_shader(
     std::fs::write(out.join("reconditioned.wgsl"), reconditioned)?;
     std::fs::write(out.join("inputs.json"), metadata)?;
 
+    // Also saved in the binary format, for replaying with `--inputs`
+    let input_data = serde_json::from_str(metadata)?;
+    harness_frontend::write_input_data_bin(&out.join("inputs.bin"), &input_data)?;
+
     if let Some(output) = output {
         std::fs::write(out.join("stderr.txt"), output.replace('\0', ""))?;
     }
 
-    Ok(())
+    for (name, log) in logs {
+        std::fs::write(out.join(name), log)?;
+    }
+
+    if let Some(disagreement) = disagreement {
+        std::fs::write(out.join("disagreement.txt"), disagreement)?;
+
+        // Also recorded in a single index, so that findings with the same pattern can be bucketed
+        // with e.g. `sort -k2 | uniq -c -f1`
+        let mut index = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(out.parent().unwrap().join("disagreements.txt"))?;
+        // Written in one go since other workers may be appending at the same time
+        index.write_all(format!("{timestamp} {disagreement}\n").as_bytes())?;
+    }
+
+    Ok(out)
 }
 
-pub fn run(config: Config, options: Options) -> eyre::Result<()> {
+pub fn run(config: Config, config_file: &Path, options: Options) -> eyre::Result<()> {
     unsafe { UTC_OFFSET = Some(UtcOffset::current_local_offset()?) };
 
     let disable_tui = options.disable_tui;
@@ -186,20 +404,80 @@ pub fn run(config: Config, options: Options) -> eyre::Result<()> {
         ),
     };
 
+    #[cfg(all(target_family = "unix", feature = "reducer"))]
+    let reductions = if options.auto_reduce {
+        let server = options
+            .server
+            .as_deref()
+            .or_else(|| config.default_remote());
+        Some(Reductions::spawn(options.reduce_jobs, config_file, server))
+    } else {
+        None
+    };
+
+    let provenance = Provenance::collect(Some(&harness)).to_json()?;
+
+    let xfail = options
+        .xfail
+        .as_deref()
+        .or(config.fuzzer.xfail.as_deref())
+        .map(XfailList::load)
+        .transpose()?;
+
+    let gen = GenCommand {
+        config_file: config_file.to_owned(),
+        args: gen_args(&config, &options, xfail.as_ref())?,
+    };
+
+    let output = options.output.clone();
     let (worker_tx, worker_rx) = crossbeam_channel::bounded(1);
 
-    std::thread::spawn(move || {
-        worker(config, options, harness, &mut |result| {
-            worker_tx.send(result).unwrap()
-        })
-        .unwrap()
-    });
+    let coordinator = match &options.coordinator {
+        Some(address) => {
+            let name = options.worker_name.clone().unwrap_or_else(hostname);
+            let token = options.coordinator_token.clone();
+            let client = Client::register(address, token, name)?;
+
+            thread::spawn({
+                let client = client.clone();
+                move || loop {
+                    if let Err(e) = client.heartbeat() {
+                        eprintln!("failed to send heartbeat to coordinator: {e:#}");
+                    }
+                    thread::sleep(coordinator::HEARTBEAT_INTERVAL);
+                }
+            });
+
+            Some(client)
+        }
+        None => None,
+    };
+
+    let cx = WorkerContext {
+        config,
+        options,
+        gen,
+        provenance,
+        harness,
+        #[cfg(all(target_family = "unix", feature = "reducer"))]
+        reductions,
+        coordinator,
+        xfail,
+    };
+
+    std::thread::spawn(move || worker(cx, &mut |result| worker_tx.send(result).unwrap()).unwrap());
 
     if disable_tui {
+        let mut status = Status::new();
         while let Ok(msg) = worker_rx.recv() {
             match msg {
                 WorkerMessage::Log(line) => println!("{line}"),
-                WorkerMessage::Result(result) => println!("saved: {}", result.saved),
+                WorkerMessage::Result(result) => {
+                    println!("saved: {}", result.saved);
+                    record_result(&mut status, &result);
+                    status.save(&output)?;
+                }
+                WorkerMessage::Coverage(edges) => status.coverage = Some(edges),
             }
         }
     } else {
@@ -207,7 +485,7 @@ pub fn run(config: Config, options: Options) -> eyre::Result<()> {
         let mut stdout = io::stdout();
         execute!(stdout, EnterAlternateScreen)?;
         let terminal = Terminal::new(CrosstermBackend::new(stdout))?;
-        let ui = Arc::new(Mutex::new(Ui::new(terminal, UiState::default())));
+        let ui = Arc::new(Mutex::new(Ui::new(terminal, Status::new())));
 
         let (input_tx, input_rx) = crossbeam_channel::bounded(1);
 
@@ -217,26 +495,8 @@ pub fn run(config: Config, options: Options) -> eyre::Result<()> {
 
         let on_result = |result: WorkerResult| {
             let mut ui = ui.lock().unwrap();
-            ui.state.total += 1;
-            match result.kind {
-                WorkerResultKind::Success => ui.state.success += 1,
-                WorkerResultKind::Crash => {
-                    ui.state.crashes += 1;
-                    if result.saved {
-                        ui.state.saved_crashes += 1;
-                    }
-                }
-                WorkerResultKind::Mismatch => {
-                    ui.state.mismatches += 1;
-                    if result.saved {
-                        ui.state.saved_mismatches += 1;
-                    }
-                }
-                // WorkerResultKind::Timeout => ui.state.timeouts += 1,
-                WorkerResultKind::ReconditionFailure | WorkerResultKind::ExecutionFailure => {
-                    ui.state.failures += 1
-                }
-            }
+            record_result(&mut ui.state, &result);
+            ui.state.save(&output)
         };
 
         loop {
@@ -254,7 +514,8 @@ pub fn run(config: Config, options: Options) -> eyre::Result<()> {
                 recv(worker_rx) -> msg => {
                     match msg? {
                         WorkerMessage::Log(_line) => {},
-                        WorkerMessage::Result(result) => on_result(result),
+                        WorkerMessage::Result(result) => on_result(result)?,
+                        WorkerMessage::Coverage(edges) => ui.lock().unwrap().state.coverage = Some(edges),
                     }
                 }
             }
@@ -271,14 +532,78 @@ pub fn run(config: Config, options: Options) -> eyre::Result<()> {
     Ok(())
 }
 
+/// Returns the name of this machine, to identify it to a coordinator.
+fn hostname() -> String {
+    if let Ok(name) = std::env::var("HOSTNAME").or_else(|_| std::env::var("COMPUTERNAME")) {
+        return name;
+    }
+
+    Command::new("hostname")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "worker".to_owned())
+}
+
+/// Updates the campaign status with the result of a single iteration.
+fn record_result(status: &mut Status, result: &WorkerResult) {
+    status.total += 1;
+    match result.kind {
+        WorkerResultKind::Success => status.success += 1,
+        WorkerResultKind::Crash => {
+            status.crashes += 1;
+            if result.saved {
+                status.saved_crashes += 1;
+            }
+            for config in result.output.crashed() {
+                status.config(config).crashes += 1;
+            }
+        }
+        WorkerResultKind::Mismatch => {
+            status.mismatches += 1;
+            if result.saved {
+                status.saved_mismatches += 1;
+            }
+            for config in result.output.mismatched() {
+                status.config(config).mismatches += 1;
+            }
+        }
+        // WorkerResultKind::Timeout => status.timeouts += 1,
+        WorkerResultKind::Duplicate => status.duplicates += 1,
+        WorkerResultKind::Filtered => status.filtered += 1,
+        WorkerResultKind::ReconditionFailure | WorkerResultKind::ExecutionFailure => {
+            status.failures += 1
+        }
+    }
+
+    if result.known {
+        status.known += 1;
+    }
+
+    // Timeouts don't fail the run, so they can happen alongside any other result
+    if !result.output.timeouts().is_empty() {
+        status.timeouts += 1;
+        for config in result.output.timeouts() {
+            status.config(config).timeouts += 1;
+        }
+    }
+}
+
 enum WorkerMessage {
     Log(String),
     Result(WorkerResult),
+    /// Number of edges covered in the instrumented compiler so far.
+    Coverage(usize),
 }
 
 struct WorkerResult {
     kind: WorkerResultKind,
     saved: bool,
+    /// Whether the finding matched an entry in the list of known failures.
+    known: bool,
+    output: OutputTracker,
 }
 
 enum WorkerResultKind {
@@ -288,33 +613,215 @@ enum WorkerResultKind {
     // Timeout,
     ReconditionFailure,
     ExecutionFailure,
+    Duplicate,
+    /// Skipped for being outside of the complexity bounds.
+    Filtered,
+}
+
+/// Probability of generating an independent program when mutating tapes, so that the campaign
+/// doesn't get stuck mutating a few seeds.
+const FRESH_PROGRAM_PROB: f64 = 0.1;
+
+/// The `wgslsmith gen` command line that every shader is generated with.
+struct GenCommand {
+    config_file: PathBuf,
+    args: Vec<String>,
 }
 
-fn worker(
+/// Everything that a worker needs which stays the same between iterations.
+struct WorkerContext {
     config: Config,
     options: Options,
+    gen: GenCommand,
+    provenance: String,
     harness: Harness,
-    on_message: &mut dyn FnMut(WorkerMessage),
-) -> eyre::Result<()> {
+    #[cfg(all(target_family = "unix", feature = "reducer"))]
+    reductions: Option<Reductions>,
+    coordinator: Option<Client>,
+    xfail: Option<XfailList>,
+}
+
+fn worker(cx: WorkerContext, on_message: &mut dyn FnMut(WorkerMessage)) -> eyre::Result<()> {
+    let options = &cx.options;
+    let fuzzer_config = &cx.config.fuzzer;
+    let issue_template = options
+        .issue_template
+        .as_deref()
+        .or(fuzzer_config.issue_template.as_deref());
+
+    let mut state = WorkerState {
+        seen: HashSet::new(),
+        seeds: options
+            .power_schedule
+            .map(|schedule| SeedPool::load(schedule, options.output.join("tapes")))
+            .transpose()?,
+        rng: rand::thread_rng(),
+        coverage: options
+            .coverage_cmd
+            .as_deref()
+            .map(|cmd| {
+                let dir = options.output.join("coverage");
+                Coverage::new(cmd, options.coverage_format, dir)
+            })
+            .transpose()?,
+        coordinator_seeds: None,
+        coordinator_retry: None,
+        issues: options
+            .issue_repo
+            .clone()
+            .or_else(|| fuzzer_config.issue_repo.clone())
+            .map(|repo| IssueDrafts::new(repo, issue_template, options.output.join("issues")))
+            .transpose()?,
+    };
+
+    let mut profiles = options.profiles.iter().copied().cycle();
     loop {
         let mut logger = |line| on_message(WorkerMessage::Log(line));
-        let result = worker_iteration(&config, &options, &harness, &mut logger)?;
-        on_message(WorkerMessage::Result(result))
+        let result = worker_iteration(&cx, profiles.next(), &mut state, &mut logger)?;
+        on_message(WorkerMessage::Result(result));
+
+        if let Some(coverage) = &state.coverage {
+            on_message(WorkerMessage::Coverage(coverage.edges()));
+        }
     }
 }
 
+/// State that is carried between the iterations of a worker.
+struct WorkerState {
+    /// Structural hashes of the programs that have already been tested.
+    seen: HashSet<u64>,
+    seeds: Option<SeedPool>,
+    rng: ThreadRng,
+    coverage: Option<Coverage>,
+    /// Seeds handed out by the coordinator that haven't been tested yet.
+    coordinator_seeds: Option<Range<u64>>,
+    /// Time after which to ask the coordinator for seeds again, after it couldn't be reached.
+    coordinator_retry: Option<Instant>,
+    issues: Option<IssueDrafts>,
+}
+
+/// Returns whether a program with the given complexity score should be executed.
+fn in_complexity_bounds(score: f64, min: Option<f64>, max: Option<f64>) -> bool {
+    min.map_or(true, |min| score >= min) && max.map_or(true, |max| score <= max)
+}
+
 fn worker_iteration(
-    config: &Config,
-    options: &Options,
-    harness: &Harness,
+    cx: &WorkerContext,
+    profile: Option<generator::Profile>,
+    state: &mut WorkerState,
     logger: &mut dyn FnMut(String),
 ) -> eyre::Result<WorkerResult> {
-    let shader = gen_shader(options)?;
+    let WorkerContext {
+        config,
+        options,
+        gen,
+        provenance,
+        harness,
+        coordinator,
+        xfail,
+        ..
+    } = cx;
+
+    let mut gen_args = gen.args.clone();
+
+    // The seed is always passed explicitly so that the saved arguments reproduce the program. With
+    // a coordinator, each worker tests a different range of seeds so that no program is tested
+    // twice.
+    let seed = match coordinator {
+        Some(coordinator) => {
+            if state
+                .coordinator_seeds
+                .as_ref()
+                .map_or(true, |it| it.is_empty())
+                && state
+                    .coordinator_retry
+                    .map_or(true, |it| Instant::now() >= it)
+            {
+                match coordinator.next_seeds() {
+                    Ok(seeds) => {
+                        state.coordinator_seeds = Some(seeds);
+                        state.coordinator_retry = None;
+                    }
+                    Err(e) => {
+                        logger(format!(
+                            "failed to get seeds from coordinator, using random seeds until it's reachable: {e:#}"
+                        ));
+                        state.coordinator_retry =
+                            Some(Instant::now() + coordinator::HEARTBEAT_INTERVAL);
+                    }
+                }
+            }
+
+            // Random seeds may repeat programs tested by other workers, but keep the worker busy
+            // while the coordinator is down (e.g. being restarted)
+            match state.coordinator_seeds.as_mut().and_then(|it| it.next()) {
+                Some(seed) => seed,
+                None => state.rng.gen(),
+            }
+        }
+        None => state.rng.gen(),
+    };
+
+    gen_args.insert(0, seed.to_string());
+
+    // The tape is recorded to a temporary path, and only kept if the program is added as a seed
+    let tape = state
+        .seeds
+        .as_ref()
+        .map(|_| options.output.join("tapes").join("next.json"));
+
+    let parent = match &mut state.seeds {
+        Some(seeds) if !state.rng.gen_bool(FRESH_PROGRAM_PROB) => seeds.pick(&mut state.rng),
+        _ => None,
+    };
+
+    if let Some(tape) = &tape {
+        gen_args.extend(["--record-tape".to_owned(), tape.display().to_string()]);
+    }
+
+    if let Some((_, parent)) = &parent {
+        gen_args.extend([
+            "--replay-tape".to_owned(),
+            parent.display().to_string(),
+            "--tape-mutations".to_owned(),
+            options.tape_mutations.to_string(),
+        ]);
+    }
+
+    // Later occurrences of an option override earlier ones, so the profile always applies
+    if let Some(profile) = profile {
+        gen_args.extend(["--profile".to_owned(), profile.to_string()]);
+    }
+
+    let (shader, gen_log) = gen_shader(options, &gen.config_file, &gen_args)?;
     let (metadata, shader) = shader
         .split_once('\n')
         .ok_or_else(|| eyre!("expected first line of shader to be a JSON metadata comment"))?;
 
     let metadata = metadata.trim_start_matches("//").trim();
+
+    // Generated programs should always parse, but if not then just run them anyway
+    if let Ok(module) = parser::try_parse(shader) {
+        let complexity = Complexity::collect(&module).score();
+        if !in_complexity_bounds(complexity, options.min_complexity, options.max_complexity) {
+            return Ok(WorkerResult {
+                kind: WorkerResultKind::Filtered,
+                saved: false,
+                known: false,
+                output: OutputTracker::default(),
+            });
+        }
+
+        if !options.no_dedup && !state.seen.insert(ast::canonical::canonical_hash(module)) {
+            return Ok(WorkerResult {
+                kind: WorkerResultKind::Duplicate,
+                saved: false,
+                known: false,
+                output: OutputTracker::default(),
+            });
+        }
+    }
+
     let reconditioned = match recondition_shader(shader) {
         Ok(reconditioned) => reconditioned,
         Err(_) => {
@@ -322,18 +829,57 @@ fn worker_iteration(
             return Ok(WorkerResult {
                 kind: WorkerResultKind::ReconditionFailure,
                 saved: false,
+                known: false,
+                output: OutputTracker::default(),
             });
         }
     };
 
+    let new_coverage = match &mut state.coverage {
+        Some(coverage) => coverage.run(&reconditioned)? > 0,
+        None => false,
+    };
+
+    let mut tracker = OutputTracker::default();
+    let mut harness_log = String::new();
     let exec_result = harness_runner::exec_shader(
         harness,
         options.config.clone(),
         &reconditioned,
         metadata,
-        logger,
+        |line| {
+            tracker.line(&line);
+            harness_log.push_str(&line);
+            harness_log.push('\n');
+            logger(line)
+        },
     );
 
+    let mut logs = vec![("harness.log", harness_log.as_str())];
+    if let Some(gen_log) = &gen_log {
+        logs.push(("generator.log", gen_log.as_str()));
+    }
+
+    let profile = profile.map(|it| it.to_string());
+    if let Some(profile) = &profile {
+        logs.push(("profile.txt", profile.as_str()));
+    }
+
+    // Recorded so that the exact generator command for a finding is known even if the config file
+    // or environment change later. Tapes are recorded to a temporary path, so `tape.json` should
+    // be replayed instead of the `--record-tape` path.
+    let gen_command = gen_args.join(" ");
+    logs.push(("gen-args.txt", gen_command.as_str()));
+    logs.push(("provenance.json", provenance.as_str()));
+
+    // Templates aren't generated from a tape, so there may not be one
+    let tape_json = tape
+        .as_ref()
+        .and_then(|tape| std::fs::read_to_string(tape).ok());
+    if let Some(tape_json) = &tape_json {
+        logs.push(("tape.json", tape_json.as_str()));
+    }
+
     let result = match exec_result {
         Ok(result) => result,
         Err(e) => {
@@ -344,11 +890,15 @@ fn worker_iteration(
                     &reconditioned,
                     metadata,
                     Some(&format!("{e:#?}")),
+                    None,
+                    &logs,
                 )?;
             }
             return Ok(WorkerResult {
                 kind: WorkerResultKind::ExecutionFailure,
                 saved: false,
+                known: false,
+                output: OutputTracker::default(),
             });
         }
     };
@@ -365,40 +915,102 @@ fn worker_iteration(
         output = Some(out.as_str());
     }
 
-    let should_save = result.should_save(
+    let mut should_save = result.should_save(
         &options.strategy,
         options.ignore.iter().chain(&config.fuzzer.ignore),
     );
 
+    let mut known = false;
+    if let (true, Some(xfail)) = (should_save, xfail) {
+        let failed: Vec<&str> = match &result {
+            ExecutionResult::Crash(_) => tracker.crashed().map(String::as_str).collect(),
+            _ => tracker.mismatched(),
+        };
+
+        if let Some(entry) = xfail.find(shader, &failed, output) {
+            logger(format!("known failure: {}", entry.reason));
+            known = true;
+            should_save = false;
+        }
+    }
+
     if should_save {
-        save_shader(&options.output, shader, &reconditioned, metadata, output)?;
+        let dir = save_shader(
+            &options.output,
+            shader,
+            &reconditioned,
+            metadata,
+            output,
+            tracker.disagreement(),
+            &logs,
+        )?;
+
+        // The finding is still saved locally, so the campaign can carry on without the coordinator
+        if let Some(coordinator) = coordinator {
+            if let Err(e) = coordinator.submit_finding(&dir) {
+                eprintln!("failed to submit finding to coordinator: {e:#}");
+            }
+        }
+
+        if let Some(issues) = &mut state.issues {
+            let finding = match &result {
+                ExecutionResult::Success => None,
+                ExecutionResult::Crash(output) => Some(Finding::Crash {
+                    configs: tracker.crashed().map(String::as_str).collect(),
+                    output,
+                }),
+                ExecutionResult::Mismatch => tracker
+                    .disagreement()
+                    .map(|disagreement| Finding::Mismatch { disagreement }),
+            };
+
+            if let Some(finding) = finding {
+                if let Some(draft) = issues.record(&finding, &dir)? {
+                    logger(format!("drafted issue for new bucket: {}", draft.display()));
+                }
+            }
+        }
+
+        #[cfg(all(target_family = "unix", feature = "reducer"))]
+        if let Some(reductions) = &cx.reductions {
+            match &result {
+                ExecutionResult::Success => {}
+                ExecutionResult::Crash(output) => {
+                    if let Some(config) = tracker.crashed().next() {
+                        reductions.submit(Job::Crash {
+                            dir,
+                            config: config.clone(),
+                            output: output.clone(),
+                        });
+                    }
+                }
+                ExecutionResult::Mismatch => reductions.submit(Job::Mismatch { dir }),
+            }
+        }
+    }
+
+    if let (Some(seeds), Some(tape), Some(_)) = (&mut state.seeds, &tape, &tape_json) {
+        let behaviour = behaviour(&result, &tracker);
+        if seeds.record(parent.map(|(index, _)| index), behaviour, new_coverage) {
+            seeds.add(tape, behaviour)?;
+        }
     }
 
     Ok(WorkerResult {
         kind: result_kind,
         saved: should_save,
+        known,
+        output: tracker,
     })
 }
 
 struct Ui<B: Backend> {
     terminal: Terminal<B>,
-    state: UiState,
-}
-
-#[derive(Default)]
-struct UiState {
-    total: usize,
-    success: usize,
-    timeouts: usize,
-    crashes: usize,
-    saved_crashes: usize,
-    mismatches: usize,
-    saved_mismatches: usize,
-    failures: usize,
+    state: Status,
 }
 
 impl<B: Backend> Ui<B> {
-    fn new(terminal: Terminal<B>, state: UiState) -> Self {
+    fn new(terminal: Terminal<B>, state: Status) -> Self {
         Ui { terminal, state }
     }
 
@@ -420,19 +1032,29 @@ impl<B: Backend> Ui<B> {
             let saved_mismatches = self.state.saved_mismatches;
             let timeouts = self.state.timeouts;
             let failures = self.state.failures;
+            let duplicates = self.state.duplicates;
+            let filtered = self.state.filtered;
+            let known = self.state.known;
 
             #[rustfmt::skip]
-            let lines = vec![
+            let mut lines = vec![
                 Spans::from(format!("total:      {count}")),
                 Spans::from(format!("ok:         {success} ({:.2}%)", pc(success, count))),
                 Spans::from(format!("crashes:    {crashes} ({:.2}%)", pc(crashes, count))),
                 Spans::from(format!("  saved:    {saved_crashes} ({:.2}%)", pc(saved_crashes, crashes))),
                 Spans::from(format!("mismatches: {mismatches} ({:.2}%)", pc(mismatches, count))),
                 Spans::from(format!("  saved:    {saved_mismatches} ({:.2}%)", pc(saved_mismatches, mismatches))),
+                Spans::from(format!("known:      {known} ({:.2}%)", pc(known, crashes + mismatches))),
                 Spans::from(format!("timeouts:   {timeouts} ({:.2}%)", pc(timeouts, count))),
                 Spans::from(format!("failures:   {failures} ({:.2}%)", pc(failures, count))),
+                Spans::from(format!("duplicates: {duplicates} ({:.2}%)", pc(duplicates, count))),
+                Spans::from(format!("filtered:   {filtered} ({:.2}%)", pc(filtered, count))),
             ];
 
+            if let Some(edges) = self.state.coverage {
+                lines.push(Spans::from(format!("coverage:   {edges} edges")));
+            }
+
             let line_count = lines.len();
             let mut text_width = 0;
             for line in &lines {
@@ -459,3 +1081,23 @@ impl<B: Backend> Ui<B> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complexity_bounds() {
+        assert!(in_complexity_bounds(10.0, None, None));
+
+        assert!(in_complexity_bounds(10.0, Some(10.0), None));
+        assert!(!in_complexity_bounds(9.5, Some(10.0), None));
+
+        assert!(in_complexity_bounds(20.0, None, Some(20.0)));
+        assert!(!in_complexity_bounds(20.5, None, Some(20.0)));
+
+        assert!(in_complexity_bounds(15.0, Some(10.0), Some(20.0)));
+        assert!(!in_complexity_bounds(5.0, Some(10.0), Some(20.0)));
+        assert!(!in_complexity_bounds(25.0, Some(10.0), Some(20.0)));
+    }
+}
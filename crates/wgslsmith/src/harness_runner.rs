@@ -1,3 +1,4 @@
+use std::cmp::Reverse;
 use std::fmt::{Display, Write as _};
 use std::io::{self, BufRead, BufReader, BufWriter, Write as _};
 use std::path::PathBuf;
@@ -95,6 +96,32 @@ fn exec_shader_impl(
     Ok(result)
 }
 
+/// Returns the output of the harness `list` command, describing the available configurations.
+pub fn list_configs(harness: &Harness) -> eyre::Result<String> {
+    let mut cmd = match harness {
+        Harness::Local(harness_path) => {
+            let mut cmd = Command::new(harness_path);
+            // If the harness is embedded in wgslsmith then it lives under the `harness` subcommand
+            if *harness_path == std::env::current_exe()? {
+                cmd.arg("harness");
+            }
+            cmd.arg("list");
+            cmd
+        }
+        Harness::Remote(remote) => Command::new(std::env::current_exe()?).tap_mut(|cmd| {
+            cmd.args(["remote", remote, "list"]);
+        }),
+    };
+
+    let output = cmd.env("NO_COLOR", "1").stderr(Stdio::inherit()).output()?;
+
+    if !output.status.success() {
+        return Err(eyre!("failed to list harness configurations"));
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
+
 #[derive(PartialEq, Eq)]
 enum StdioKind {
     Stdout,
@@ -141,3 +168,100 @@ fn wait_for_child_with_line_logger(
 
     child.wait()
 }
+
+/// Keeps track of which configurations the harness executed by scanning its output, so that
+/// findings can be attributed to specific configurations.
+#[derive(Default)]
+pub struct OutputTracker {
+    executed: Vec<String>,
+    succeeded: Vec<String>,
+    timeouts: Vec<String>,
+    disagreement: Option<String>,
+}
+
+impl OutputTracker {
+    pub fn line(&mut self, line: &str) {
+        let line = strip_ansi(line);
+        if let Some(config) = line.strip_prefix("executing ") {
+            self.executed.push(config.trim().to_owned());
+        } else if let Some(pattern) = line.strip_prefix("disagreement: ") {
+            self.disagreement = Some(pattern.trim().to_owned());
+        } else if let Some(current) = self.executed.last() {
+            // These are printed at the end of each execution
+            if line == "outputs:" {
+                self.succeeded.push(current.clone());
+            } else if line == "timeout" {
+                self.timeouts.push(current.clone());
+            }
+        }
+    }
+
+    /// Configurations that timed out.
+    pub fn timeouts(&self) -> &[String] {
+        &self.timeouts
+    }
+
+    /// Configurations that neither succeeded nor timed out, and so must have crashed.
+    pub fn crashed(&self) -> impl Iterator<Item = &String> {
+        self.executed
+            .iter()
+            .filter(|it| !self.succeeded.contains(it) && !self.timeouts.contains(it))
+    }
+
+    /// The disagreement pattern printed by the harness if there was a mismatch.
+    pub fn disagreement(&self) -> Option<&str> {
+        self.disagreement.as_deref()
+    }
+
+    /// Configurations whose outputs differed from the most common output in a mismatch.
+    pub fn mismatched(&self) -> Vec<&str> {
+        let pattern = match &self.disagreement {
+            Some(pattern) => pattern
+                .split_whitespace()
+                .filter_map(|it| {
+                    let (config, class) = it.rsplit_once('=')?;
+                    Some((config, class.parse::<usize>().ok()?))
+                })
+                .collect::<Vec<_>>(),
+            None => return vec![],
+        };
+
+        let mut counts = vec![];
+        for (_, class) in &pattern {
+            if counts.len() <= *class {
+                counts.resize(class + 1, 0);
+            }
+            counts[*class] += 1;
+        }
+
+        // Ties go to the lower class, since classes are numbered in order of first appearance
+        let majority = (0..counts.len()).max_by_key(|class| (counts[*class], Reverse(*class)));
+
+        pattern
+            .into_iter()
+            .filter(|(_, class)| Some(*class) != majority)
+            .map(|(config, _)| config)
+            .collect()
+    }
+}
+
+/// Removes terminal colour escape sequences from a line of output.
+pub fn strip_ansi(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip until the end of the sequence, e.g. `\x1b[36m`
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
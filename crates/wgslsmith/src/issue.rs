@@ -0,0 +1,259 @@
+//! Drafts of GitHub issues for new bug buckets, for `wgslsmith fuzz --issue-repo`.
+//!
+//! Findings are bucketed by a signature: the disagreement pattern of a mismatch, or the
+//! configurations that crashed along with the first error in the harness output. The first
+//! finding in each bucket is rendered into an issue title and body, which are saved to
+//! `issues/<bucket>/` in the output directory along with a link that opens GitHub's new issue
+//! form with them filled in. Nothing is ever submitted, so drafts can be checked (and the bug
+//! reduced) before they're reported.
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use ast::hash::Fnv1a;
+use eyre::Context;
+
+/// Maximum length of a link to the new issue form, beyond which GitHub rejects the request.
+const MAX_URL_LEN: usize = 8000;
+
+/// Maximum length of an issue title, after which the signature is truncated.
+const MAX_TITLE_LEN: usize = 100;
+
+const DEFAULT_TEMPLATE: &str = r#"## Description
+
+<!-- Describe the bug here -->
+
+Found by [wgslsmith](https://github.com/hasali19/wgslsmith) ({{kind}}): `{{signature}}`
+
+## Shader
+
+```wgsl
+{{shader}}
+```
+
+<details>
+<summary>Reconditioned shader</summary>
+
+```wgsl
+{{reconditioned}}
+```
+</details>
+
+Input data:
+
+```json
+{{inputs}}
+```
+
+## Observed behaviour
+
+```
+{{output}}
+```
+
+## Expected behaviour
+
+{{expected}}
+
+## Environment
+
+<details>
+<summary>Provenance</summary>
+
+```json
+{{provenance}}
+```
+</details>
+"#;
+
+/// The kind of a finding, along with what identifies its bucket.
+pub enum Finding<'a> {
+    Crash {
+        configs: Vec<&'a str>,
+        output: &'a str,
+    },
+    Mismatch {
+        disagreement: &'a str,
+    },
+}
+
+impl Finding<'_> {
+    /// Returns a description of the finding that is the same for findings with the same cause.
+    pub fn signature(&self) -> String {
+        match self {
+            Finding::Crash { configs, output } => {
+                // The first error line is usually enough to recognise the crash, but may contain
+                // addresses and sizes that are different every time
+                let error = output
+                    .lines()
+                    .find(|it| it.contains("error") || it.contains("panicked"))
+                    .unwrap_or("")
+                    .trim();
+
+                let mut normalized = String::new();
+                for c in error.chars() {
+                    if !c.is_ascii_digit() {
+                        normalized.push(c);
+                    } else if !normalized.ends_with('N') {
+                        normalized.push('N');
+                    }
+                }
+
+                format!("crash on {}: {normalized}", configs.join(", "))
+            }
+            Finding::Mismatch { disagreement } => format!("mismatch: {disagreement}"),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Finding::Crash { .. } => "crash",
+            Finding::Mismatch { .. } => "mismatch",
+        }
+    }
+
+    fn expected(&self) -> &'static str {
+        match self {
+            Finding::Crash { .. } => "The shader should compile and execute successfully.",
+            Finding::Mismatch { .. } => {
+                "All configurations should produce identical output buffers."
+            }
+        }
+    }
+}
+
+pub struct IssueDrafts {
+    /// Repository to open issues against, as `<owner>/<repo>`.
+    repo: String,
+    template: String,
+    /// Directory that drafts are saved to, with a subdirectory for each bucket.
+    dir: PathBuf,
+    buckets: HashSet<String>,
+}
+
+impl IssueDrafts {
+    /// Creates drafts for `repo` from a template with `{{placeholders}}`, or the default
+    /// template, remembering the buckets that already have drafts in `dir`.
+    pub fn new(repo: String, template: Option<&Path>, dir: PathBuf) -> eyre::Result<IssueDrafts> {
+        let template = match template {
+            Some(path) => std::fs::read_to_string(path)
+                .wrap_err_with(|| format!("failed to read {}", path.display()))?,
+            None => DEFAULT_TEMPLATE.to_owned(),
+        };
+
+        let mut buckets = HashSet::new();
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for entry in entries {
+                buckets.insert(entry?.file_name().to_string_lossy().into_owned());
+            }
+        }
+
+        Ok(IssueDrafts {
+            repo,
+            template,
+            dir,
+            buckets,
+        })
+    }
+
+    /// Drafts an issue for the finding saved in `finding_dir` if it's the first in its bucket,
+    /// returning the directory that the draft was saved to.
+    pub fn record(
+        &mut self,
+        finding: &Finding,
+        finding_dir: &Path,
+    ) -> eyre::Result<Option<PathBuf>> {
+        let signature = finding.signature();
+        let bucket = format!("{:016x}", fnv1a(&signature));
+
+        if !self.buckets.insert(bucket.clone()) {
+            return Ok(None);
+        }
+
+        let read = |name: &str| std::fs::read_to_string(finding_dir.join(name)).unwrap_or_default();
+
+        let output = match finding {
+            Finding::Crash { output, .. } => output.to_string(),
+            Finding::Mismatch { .. } => read("harness.log"),
+        };
+
+        let values = [
+            ("kind", finding.kind().to_owned()),
+            ("signature", signature.clone()),
+            ("shader", read("shader.wgsl")),
+            ("reconditioned", read("reconditioned.wgsl")),
+            ("inputs", read("inputs.json")),
+            ("output", output),
+            ("expected", finding.expected().to_owned()),
+            ("provenance", read("provenance.json")),
+            ("finding", finding_dir.display().to_string()),
+        ];
+
+        let mut body = self.template.clone();
+        for (name, value) in &values {
+            body = body.replace(&format!("{{{{{name}}}}}"), value.trim_end());
+        }
+
+        let mut title = signature;
+        if title.len() > MAX_TITLE_LEN {
+            let mut end = MAX_TITLE_LEN;
+            while !title.is_char_boundary(end) {
+                end -= 1;
+            }
+            title.truncate(end);
+            title.push_str("...");
+        }
+
+        let dir = self.dir.join(&bucket);
+        std::fs::create_dir_all(&dir)?;
+
+        let mut url = self.new_issue_url(&title, &body);
+        if url.len() > MAX_URL_LEN {
+            let path = dir.join("body.md");
+            let placeholder = format!(
+                "<!-- The report is too long to prefill, paste the contents of {} here -->",
+                path.display()
+            );
+            url = self.new_issue_url(&title, &placeholder);
+        }
+
+        std::fs::write(dir.join("title.txt"), &title)?;
+        std::fs::write(dir.join("body.md"), &body)?;
+        std::fs::write(dir.join("url.txt"), &url)?;
+        std::fs::write(dir.join("finding.txt"), finding_dir.display().to_string())?;
+
+        Ok(Some(dir))
+    }
+
+    fn new_issue_url(&self, title: &str, body: &str) -> String {
+        format!(
+            "https://github.com/{}/issues/new?title={}&body={}",
+            self.repo,
+            encode(title),
+            encode(body)
+        )
+    }
+}
+
+/// Percent-encodes a string for use in a URL query.
+fn encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => write!(encoded, "%{byte:02X}").unwrap(),
+        }
+    }
+    encoded
+}
+
+/// Hashes a signature, in a way that is stable across builds so that buckets from earlier runs
+/// are recognised.
+fn fnv1a(value: &str) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write(value.as_bytes());
+    hasher.finish()
+}
@@ -1,28 +1,57 @@
 #[cfg(all(target_family = "unix", feature = "reducer"))]
+mod auto_reduce;
+mod bench_gen;
+mod compile_fuzz;
+#[cfg(all(target_family = "unix", feature = "reducer"))]
 mod compiler;
 mod config;
+mod coordinator;
+mod corpus;
+mod coverage;
+mod diff;
+mod driver;
 mod fmt;
 mod fuzzer;
 mod harness_runner;
+mod issue;
+mod provenance;
 #[cfg(all(target_family = "unix", feature = "reducer"))]
 mod reducer;
 mod remote;
 #[cfg(all(target_family = "unix", feature = "reducer"))]
+mod report;
+mod schedule;
+mod status;
+#[cfg(all(target_family = "unix", feature = "reducer"))]
 mod test;
 #[cfg(all(target_family = "unix", feature = "reducer"))]
+mod translate_diff;
+#[cfg(all(target_family = "unix", feature = "reducer"))]
+mod validate;
+#[cfg(all(target_family = "unix", feature = "reducer"))]
 mod validator;
+#[cfg(all(target_family = "unix", feature = "reducer"))]
+mod validator_stats;
+mod xfail;
 
+use std::ffi::OsString;
 use std::fs;
 use std::path::PathBuf;
 use std::time::Duration;
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser};
 use color_eyre::Help;
 use eyre::{eyre, Context};
 use harness_frontend::{ExecutionError, ExecutionEvent};
-use harness_types::ConfigId;
+use harness_types::{ConfigId, DawnOptions};
 use reflection_types::PipelineDescription;
 
+// Generating and printing large programs makes lots of small allocations for AST nodes, which
+// mimalloc may handle faster than the system allocator (compare with `bench-gen`)
+#[cfg(feature = "mimalloc")]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
 #[derive(Parser)]
 struct Options {
     #[clap(long, action)]
@@ -37,16 +66,40 @@ enum Cmd {
     Config,
     /// Generate a random shader.
     Gen(generator::Options),
+    /// Generate a corpus of unique shaders.
+    Corpus(corpus::Options),
+    /// Benchmark generation and compilation of programs at increasing sizes.
+    BenchGen(bench_gen::Options),
     /// Recondition a shader to add safety checks.
     Recondition(reconditioner::cli::Options),
     /// Format a shader.
     Fmt(fmt::Options),
+    /// Compare two shaders structurally.
+    Diff(diff::Options),
     Fuzz(fuzzer::Options),
+    /// Coordinate a fuzzing campaign across several machines running `wgslsmith fuzz`.
+    Coordinator(coordinator::Options),
+    /// Run a compile-only fuzzing campaign using external compiler executables.
+    CompileFuzz(compile_fuzz::Options),
+    /// Show the status of a fuzzing campaign from its output directory.
+    Status(status::Options),
     /// Reduce a shader.
     #[cfg(all(target_family = "unix", feature = "reducer"))]
     Reduce(reducer::Options),
     #[cfg(all(target_family = "unix", feature = "reducer"))]
     Test(test::Options),
+    /// Generate a Markdown bug report for a reduced shader.
+    #[cfg(all(target_family = "unix", feature = "reducer"))]
+    Report(report::Options),
+    /// Validate and translate a shader in-process.
+    #[cfg(all(target_family = "unix", feature = "reducer"))]
+    Validate(validate::Options),
+    /// Compare the output of tint and naga using downstream compilers.
+    #[cfg(all(target_family = "unix", feature = "reducer"))]
+    TranslateDiff(translate_diff::Options),
+    /// Print statistics from the validation server.
+    #[cfg(all(target_family = "unix", feature = "reducer"))]
+    ValidatorStats(validator_stats::Options),
     /// Execute a shader.
     #[cfg(feature = "harness")]
     Run(harness_frontend::cli::RunOptions),
@@ -79,7 +132,10 @@ fn main() -> eyre::Result<()> {
             .install()?;
     }
 
-    let options = Options::parse();
+    // The matches are kept around so that generator options can be layered under the ones that
+    // were given on the command line
+    let matches = Options::command().get_matches();
+    let options = Options::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
     let config_file = options
         .config_file
@@ -97,17 +153,61 @@ fn main() -> eyre::Result<()> {
             edit::edit_file(&config_file)?;
             Ok(())
         }
-        Cmd::Gen(options) => generator::run(options),
+        Cmd::Gen(options) => {
+            let args = config.gen_args(matches.subcommand_matches("gen"))?;
+            if args.is_empty() {
+                generator::run(options)
+            } else {
+                // The generator options are the last arguments, so the rest can be appended
+                let args = std::env::args_os().chain(args.into_iter().map(OsString::from));
+                match Options::parse_from(args).cmd {
+                    Cmd::Gen(options) => generator::run(options),
+                    _ => unreachable!(),
+                }
+            }
+        }
+        Cmd::Corpus(options) => corpus::run(options),
+        Cmd::BenchGen(options) => bench_gen::run(options),
         Cmd::Recondition(options) => reconditioner::cli::run(options),
         Cmd::Fmt(options) => fmt::run(options),
-        Cmd::Fuzz(options) => fuzzer::run(config, options),
+        Cmd::Diff(options) => diff::run(options),
+        Cmd::Fuzz(options) => fuzzer::run(config, &config_file, options),
+        Cmd::Coordinator(options) => coordinator::run(options),
+        Cmd::CompileFuzz(options) => compile_fuzz::run(config, options),
+        Cmd::Status(options) => status::run(options),
         #[cfg(all(target_family = "unix", feature = "reducer"))]
         Cmd::Reduce(options) => reducer::run(config, options),
         #[cfg(all(target_family = "unix", feature = "reducer"))]
         Cmd::Test(options) => test::run(&config, options),
+        #[cfg(all(target_family = "unix", feature = "reducer"))]
+        Cmd::Report(options) => report::run(&config, options),
+        #[cfg(all(target_family = "unix", feature = "reducer"))]
+        Cmd::Validate(options) => validate::run(options),
+        #[cfg(all(target_family = "unix", feature = "reducer"))]
+        Cmd::TranslateDiff(options) => translate_diff::run(&config, options),
+        #[cfg(all(target_family = "unix", feature = "reducer"))]
+        Cmd::ValidatorStats(options) => validator_stats::run(&config, options),
         #[cfg(feature = "harness")]
         Cmd::Run(options) => harness::cli::execute::<HarnessHost>(options),
         #[cfg(feature = "harness")]
+        Cmd::Harness {
+            cmd: harness::cli::Command::Doctor(mut options),
+        } => {
+            // Also check the remotes from the config file, since they're what the fuzzer uses
+            options
+                .remotes
+                .extend(config.default_remote().map(String::from));
+            options
+                .remotes
+                .extend(config.remotes.values().map(|it| it.address.clone()));
+            options.remotes.sort();
+            options.remotes.dedup();
+            if options.token.is_none() {
+                options.token = config.harness.token.clone();
+            }
+            harness::cli::run::<HarnessHost>(harness::cli::Command::Doctor(options))
+        }
+        #[cfg(feature = "harness")]
         Cmd::Harness { cmd } => harness::cli::run::<HarnessHost>(cmd),
         Cmd::Remote { cmd, server } => {
             let address = server
@@ -119,14 +219,18 @@ fn main() -> eyre::Result<()> {
                         .with_note(|| "specify a default remote using the `harness.remote` field in your config file")
                 })?;
 
+            let token = config.harness.token.as_deref();
+
             match cmd {
                 RemoteCmd::List => {
-                    let res = remote::list(address)?;
+                    let res = remote::list(address, token).with_suggestion(|| {
+                        "if the server requires a token, set `harness.token`"
+                    })?;
                     harness_frontend::Printer::new().print_all_configs(res.configs)?;
                     Ok(())
                 }
                 RemoteCmd::Run(options) => {
-                    struct Executor<'a>(&'a str);
+                    struct Executor<'a>(&'a str, Option<&'a str>);
 
                     impl harness_frontend::Executor for Executor<'_> {
                         fn execute(
@@ -134,21 +238,24 @@ fn main() -> eyre::Result<()> {
                             shader: &str,
                             pipeline_desc: &PipelineDescription,
                             configs: &[ConfigId],
+                            dawn: &DawnOptions,
                             timeout: Option<Duration>,
                             on_event: &mut dyn FnMut(ExecutionEvent) -> Result<(), ExecutionError>,
                         ) -> Result<(), ExecutionError> {
                             remote::execute(
                                 self.0,
+                                self.1,
                                 shader.to_owned(),
                                 pipeline_desc.clone(),
                                 configs.to_owned(),
+                                dawn.clone(),
                                 timeout,
                                 on_event,
                             )
                         }
                     }
 
-                    harness_frontend::cli::run(options, &Executor(address))
+                    harness_frontend::cli::run(options, &Executor(address, token))
                 }
             }
         }
@@ -0,0 +1,116 @@
+//! Provenance of findings, which is saved to `provenance.json` in each finding directory.
+//!
+//! This records exactly which versions of wgslsmith and the implementations under test produced
+//! a finding, along with the drivers and the OS, so that old findings can still be interpreted
+//! after everything has been updated.
+
+use std::process::Command;
+
+use serde::Serialize;
+
+use crate::harness_runner::{self, Harness};
+
+#[derive(Serialize)]
+pub struct Provenance {
+    wgslsmith: Source,
+    dawn: Source,
+    naga: Source,
+    wgpu: Source,
+    os: Os,
+    harness: Option<HarnessInfo>,
+}
+
+#[derive(Serialize)]
+struct Source {
+    commit: &'static str,
+    /// Crate version, for dependencies which are published on crates.io.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct Os {
+    family: &'static str,
+    arch: &'static str,
+    /// Kernel version, e.g. from `uname`.
+    version: Option<String>,
+    /// Name of the Linux distribution, from `/etc/os-release`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    distribution: Option<String>,
+}
+
+#[derive(Serialize)]
+struct HarnessInfo {
+    /// Path of a local harness, or address of a remote one.
+    location: String,
+    /// Output of the harness `list` command, with the adapter and driver of each configuration.
+    ///
+    /// A separate or remote harness may have been built from another revision, so this is the
+    /// only record of what it actually ran.
+    configs: Option<String>,
+}
+
+impl Provenance {
+    /// Collects the provenance of findings from this build of wgslsmith, executed with `harness`
+    /// if there is one.
+    pub fn collect(harness: Option<&Harness>) -> Provenance {
+        Provenance {
+            wgslsmith: Source {
+                commit: env!("WGSLSMITH_WGSLSMITH_COMMIT"),
+                version: None,
+            },
+            dawn: Source {
+                commit: env!("WGSLSMITH_DAWN_COMMIT"),
+                version: None,
+            },
+            naga: Source {
+                commit: env!("WGSLSMITH_NAGA_COMMIT"),
+                version: Some(env!("WGSLSMITH_NAGA_VERSION")),
+            },
+            wgpu: Source {
+                commit: env!("WGSLSMITH_WGPU_COMMIT"),
+                version: Some(env!("WGSLSMITH_WGPU_VERSION")),
+            },
+            os: Os {
+                family: std::env::consts::OS,
+                arch: std::env::consts::ARCH,
+                version: os_version(),
+                distribution: distribution(),
+            },
+            harness: harness.map(|harness| HarnessInfo {
+                location: match harness {
+                    Harness::Local(path) => path.display().to_string(),
+                    Harness::Remote(address) => address.clone(),
+                },
+                // A finding is still worth saving if the harness couldn't be queried
+                configs: harness_runner::list_configs(harness).ok(),
+            }),
+        }
+    }
+
+    pub fn to_json(&self) -> eyre::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+fn os_version() -> Option<String> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", "ver"]).output()
+    } else {
+        Command::new("uname").arg("-srvm").output()
+    };
+
+    output
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_owned())
+}
+
+fn distribution() -> Option<String> {
+    let release = std::fs::read_to_string("/etc/os-release").ok()?;
+    release.lines().find_map(|line| {
+        let name = line.strip_prefix("PRETTY_NAME=")?;
+        Some(name.trim_matches('"').to_owned())
+    })
+}
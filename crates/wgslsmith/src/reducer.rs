@@ -330,6 +330,10 @@ fn thread_main(config: &Config, options: Options) -> eyre::Result<()> {
     crate::fmt::run(crate::fmt::Options {
         input: result_path.clone(),
         output: result_path,
+        indent: 4,
+        minify: false,
+        trailing_commas: false,
+        rename: false,
     })?;
 
     // crate::reconditioner::run(crate::reconditioner::Options {
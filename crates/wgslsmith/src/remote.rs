@@ -5,30 +5,34 @@ use std::time::Duration;
 use bincode::Decode;
 use eyre::{eyre, Context};
 use harness_frontend::{ExecutionError, ExecutionEvent};
-use harness_server_types::{ListResponse, Request, RunError, RunMessage, RunRequest};
-use harness_types::ConfigId;
+use harness_server_types::{Envelope, ListResponse, Request, RunError, RunMessage, RunRequest};
+use harness_types::{ConfigId, DawnOptions};
 use reflection_types::PipelineDescription;
 
-pub fn list(server: &str) -> eyre::Result<ListResponse> {
-    decode_from_stream(&mut req(server, Request::List)?).map_err(Into::into)
+pub fn list(server: &str, token: Option<&str>) -> eyre::Result<ListResponse> {
+    decode_from_stream(&mut req(server, token, Request::List)?).map_err(Into::into)
 }
 
 pub fn execute(
     server: &str,
+    token: Option<&str>,
     shader: String,
     pipeline_desc: PipelineDescription,
     configs: Vec<ConfigId>,
+    dawn: DawnOptions,
     timeout: Option<Duration>,
     on_event: &mut dyn FnMut(ExecutionEvent) -> Result<(), ExecutionError>,
 ) -> Result<(), ExecutionError> {
     let mut stream = req(
         server,
-        Request::Run(RunRequest {
+        token,
+        Request::Run(Box::new(RunRequest {
             shader,
             pipeline_desc,
             configs,
+            dawn,
             timeout,
-        }),
+        })),
     )?;
 
     loop {
@@ -37,9 +41,15 @@ pub fn execute(
                 on_event(ExecutionEvent::UsingDefaultConfigs(configs))?
             }
             RunMessage::ExecStart(config) => on_event(ExecutionEvent::Start(config))?,
+            RunMessage::ExecCompileTimes(times) => on_event(ExecutionEvent::CompileTimes(times))?,
+            RunMessage::ExecDispatchTime(time) => on_event(ExecutionEvent::DispatchTime(time))?,
+            RunMessage::ExecInvalidSpirv(diagnostics) => {
+                on_event(ExecutionEvent::InvalidSpirv(diagnostics))?
+            }
             RunMessage::ExecSuccess(buffers) => on_event(ExecutionEvent::Success(buffers))?,
             RunMessage::ExecFailure(stderr) => on_event(ExecutionEvent::Failure(stderr))?,
             RunMessage::ExecTimeout => on_event(ExecutionEvent::Timeout)?,
+            RunMessage::ExecSkipped(reason) => on_event(ExecutionEvent::Skipped(reason))?,
             RunMessage::End(result) => {
                 return result.map_err(|e| match e {
                     RunError::NoDefaultConfigs => ExecutionError::NoDefaultConfigs,
@@ -52,11 +62,16 @@ pub fn execute(
     }
 }
 
-fn req(server: &str, req: Request) -> eyre::Result<TcpStream> {
+/// Sends a request to the server, presenting `token` if the server requires one.
+fn req(server: &str, token: Option<&str>, request: Request) -> eyre::Result<TcpStream> {
     let address = SocketAddr::from_str(server)?;
     let mut stream = TcpStream::connect_timeout(&address, Duration::from_secs(10))
         .wrap_err_with(|| format!("failed to connect to {server}"))?;
-    bincode::encode_into_std_write(req, &mut stream, bincode::config::standard())?;
+    let envelope = Envelope {
+        token: token.map(str::to_owned),
+        request,
+    };
+    bincode::encode_into_std_write(envelope, &mut stream, bincode::config::standard())?;
     Ok(stream)
 }
 
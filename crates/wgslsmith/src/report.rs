@@ -0,0 +1,236 @@
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::Write as _;
+use std::path::PathBuf;
+
+use clap::Parser;
+use eyre::{eyre, Context};
+use harness_types::ConfigId;
+
+use crate::compiler::{Backend, Compiler};
+use crate::config::Config;
+use crate::harness_runner::{self, ExecutionResult, Harness};
+use crate::reducer::ReductionKind;
+
+#[derive(Parser)]
+pub struct Options {
+    /// Type of bug that is being reported.
+    #[clap(action)]
+    kind: ReductionKind,
+
+    /// Path to the (reduced) WGSL shader file.
+    #[clap(action)]
+    shader: PathBuf,
+
+    /// Path to the input data file.
+    ///
+    /// If not set, the program will look for a JSON file with the same name as the shader.
+    #[clap(action)]
+    input_data: Option<PathBuf>,
+
+    /// Path at which to write the report (use '-' for stdout).
+    #[clap(short, long, action, default_value = "-")]
+    output: String,
+
+    /// Address of harness server.
+    #[clap(short, long, action)]
+    server: Option<String>,
+
+    /// Harness configuration to execute the shader with.
+    ///
+    /// If not set, the default configurations for the harness platform will be used.
+    #[clap(long, action)]
+    config: Option<ConfigId>,
+
+    /// Description of the expected behaviour, to include in the report.
+    #[clap(long, action)]
+    expected: Option<String>,
+
+    /// Don't recondition shader before executing.
+    #[clap(long, action)]
+    no_recondition: bool,
+
+    /// Normalize the layout of the shader with `wgslsmith fmt` before including it in the report.
+    #[clap(long, action)]
+    format: bool,
+}
+
+pub fn run(config: &Config, options: Options) -> eyre::Result<()> {
+    let mut source = std::fs::read_to_string(&options.shader)?;
+
+    if options.format {
+        source = crate::fmt::format_shader(&source)?;
+    }
+
+    let input_path = if let Some(input_path) = options.input_data {
+        input_path
+    } else {
+        let mut try_path = options
+            .shader
+            .parent()
+            .unwrap()
+            .join(options.shader.file_stem().unwrap())
+            .with_extension("json");
+
+        if !try_path.exists() {
+            try_path = options.shader.parent().unwrap().join("inputs.json");
+        }
+
+        if !try_path.exists() {
+            return Err(eyre!(
+                "couldn't determine path to inputs file, pass one explicitly"
+            ));
+        }
+
+        try_path
+    };
+
+    let metadata = std::fs::read_to_string(&input_path)?;
+
+    let harness = match options
+        .server
+        .as_deref()
+        .or_else(|| config.default_remote())
+    {
+        Some(server) => Harness::Remote(server.to_owned()),
+        None => Harness::Local(
+            config
+                .harness
+                .path
+                .clone()
+                .map(Ok)
+                .unwrap_or_else(std::env::current_exe)?,
+        ),
+    };
+
+    let executed = if options.no_recondition {
+        source.clone()
+    } else {
+        crate::test::recondition(parser::try_parse(&source)?)
+    };
+
+    let mut harness_output = String::new();
+    let result = harness_runner::exec_shader(
+        &harness,
+        options.config.clone(),
+        &executed,
+        &metadata,
+        |line| {
+            writeln!(harness_output, "{line}").unwrap();
+        },
+    )?;
+
+    let expected = options.expected.unwrap_or_else(|| {
+        match options.kind {
+            ReductionKind::Crash => "The shader should compile and execute successfully.",
+            ReductionKind::Mismatch => {
+                "All configurations should produce identical output buffers."
+            }
+        }
+        .to_owned()
+    });
+
+    let mut report = String::new();
+
+    writeln!(report, "## Description")?;
+    writeln!(report)?;
+    writeln!(report, "<!-- Describe the bug here -->")?;
+    writeln!(report)?;
+
+    writeln!(report, "## Shader")?;
+    writeln!(report)?;
+    write_code_block(&mut report, "wgsl", &source)?;
+
+    if !options.no_recondition {
+        writeln!(report, "<details>")?;
+        writeln!(report, "<summary>Reconditioned shader</summary>")?;
+        writeln!(report)?;
+        write_code_block(&mut report, "wgsl", &executed)?;
+        writeln!(report, "</details>")?;
+        writeln!(report)?;
+    }
+
+    writeln!(report, "Input data:")?;
+    writeln!(report)?;
+    write_code_block(&mut report, "json", &metadata)?;
+
+    writeln!(report, "## Observed behaviour")?;
+    writeln!(report)?;
+    writeln!(report, "Result: **{result}**")?;
+    writeln!(report)?;
+    write_code_block(&mut report, "", &harness_output)?;
+
+    writeln!(report, "## Expected behaviour")?;
+    writeln!(report)?;
+    writeln!(report, "{expected}")?;
+    writeln!(report)?;
+
+    writeln!(report, "## Generated code")?;
+    writeln!(report)?;
+
+    for compiler in [Compiler::Tint, Compiler::Naga] {
+        for backend in [Backend::Hlsl, Backend::Msl, Backend::Spirv] {
+            writeln!(report, "<details>")?;
+            writeln!(report, "<summary>{compiler} ({backend})</summary>")?;
+            writeln!(report)?;
+            let code = match backend {
+                Backend::Spirv => compiler
+                    .compile_spirv(&executed)
+                    .and_then(|words| crate::compiler::disassemble_spirv(&words)),
+                _ => compiler.compile(&executed, backend),
+            };
+            match code {
+                Ok(code) => write_code_block(&mut report, &backend.to_string(), &code)?,
+                Err(e) => write_code_block(&mut report, "", &format!("{e:#}"))?,
+            }
+            writeln!(report, "</details>")?;
+            writeln!(report)?;
+        }
+    }
+
+    writeln!(report, "## Environment")?;
+    writeln!(report)?;
+    writeln!(
+        report,
+        "- OS: {} ({})",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )?;
+    writeln!(
+        report,
+        "- wgslsmith: {}",
+        env!("WGSLSMITH_WGSLSMITH_COMMIT")
+    )?;
+    writeln!(report, "- dawn: {}", env!("WGSLSMITH_DAWN_COMMIT"))?;
+    writeln!(report, "- naga: {}", env!("WGSLSMITH_NAGA_COMMIT"))?;
+    writeln!(report, "- wgpu: {}", env!("WGSLSMITH_WGPU_COMMIT"))?;
+
+    if let Some(config) = &options.config {
+        writeln!(report, "- Configuration: `{config}`")?;
+    }
+
+    writeln!(report)?;
+
+    let configs = harness_runner::list_configs(&harness)
+        .wrap_err("failed to query harness configurations")?;
+
+    writeln!(report, "Available configurations:")?;
+    writeln!(report)?;
+    write_code_block(&mut report, "", &configs)?;
+
+    let mut output: Box<dyn std::io::Write> = match options.output.as_str() {
+        "-" => Box::new(std::io::stdout()),
+        path => Box::new(File::create(path)?),
+    };
+
+    write!(output, "{report}")?;
+
+    Ok(())
+}
+
+fn write_code_block(f: &mut String, lang: &str, code: &str) -> std::fmt::Result {
+    writeln!(f, "```{lang}")?;
+    writeln!(f, "{}", code.trim_end())?;
+    writeln!(f, "```")?;
+    writeln!(f)
+}
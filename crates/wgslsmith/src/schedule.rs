@@ -0,0 +1,348 @@
+//! Power schedules over saved tapes, for mutating programs with `wgslsmith fuzz --power-schedule`.
+//!
+//! Every program that shows a behaviour which hasn't been seen before (or covers new code in the
+//! compiler, with `--coverage-cmd`) is kept as a seed, along with the tape of decisions that
+//! generated it. Most iterations then pick a seed and mutate its tape,
+//! with each seed picked in proportion to its energy. As in AFLFast, the schedules give less
+//! energy to seeds whose behaviour is seen often, and seeds whose mutants went on to find new
+//! behaviours get more.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use rand::Rng;
+
+/// Upper bound on the energy of a single seed, so that one seed can't take over the campaign.
+const MAX_ENERGY: f64 = 64.0;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum PowerSchedule {
+    /// Every seed has the same base energy.
+    Explore,
+    /// Energy grows exponentially with the number of times a seed has been picked, and is divided
+    /// by the number of programs that showed the same behaviour.
+    Fast,
+    /// Like `fast`, but growing linearly.
+    Lin,
+    /// Like `fast`, but growing quadratically.
+    Quad,
+}
+
+struct Seed {
+    tape: PathBuf,
+    behaviour: u64,
+    /// Number of times that the seed has been picked for mutation.
+    picked: u32,
+    /// Number of new behaviours found by mutating this seed.
+    finds: u32,
+}
+
+pub struct SeedPool {
+    schedule: PowerSchedule,
+    /// Directory that the tapes of seeds are saved to.
+    dir: PathBuf,
+    seeds: Vec<Seed>,
+    /// Number of programs that have shown each behaviour.
+    behaviours: HashMap<u64, u32>,
+}
+
+impl SeedPool {
+    fn new(schedule: PowerSchedule, dir: PathBuf) -> SeedPool {
+        SeedPool {
+            schedule,
+            dir,
+            seeds: vec![],
+            behaviours: HashMap::new(),
+        }
+    }
+
+    fn energy(&self, seed: &Seed) -> f64 {
+        let picked = seed.picked as f64 + 1.0;
+        let frequency = self.behaviours.get(&seed.behaviour).copied().unwrap_or(1) as f64;
+
+        let base = match self.schedule {
+            PowerSchedule::Explore => 1.0,
+            PowerSchedule::Fast => 2f64.powf(picked.min(16.0)) / frequency,
+            PowerSchedule::Lin => picked / frequency,
+            PowerSchedule::Quad => picked * picked / frequency,
+        };
+
+        (base * (1.0 + seed.finds as f64)).min(MAX_ENERGY)
+    }
+
+    /// Picks a seed to mutate next, returning its index and the path to its tape.
+    pub fn pick(&mut self, rng: &mut impl Rng) -> Option<(usize, PathBuf)> {
+        let energies = self
+            .seeds
+            .iter()
+            .map(|seed| self.energy(seed))
+            .collect::<Vec<_>>();
+
+        let total = energies.iter().sum::<f64>();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut target = rng.gen_range(0.0..total);
+        let mut index = energies.len() - 1;
+        for (i, energy) in energies.iter().enumerate() {
+            if target < *energy {
+                index = i;
+                break;
+            }
+            target -= energy;
+        }
+
+        let seed = &mut self.seeds[index];
+        seed.picked += 1;
+        Some((index, seed.tape.clone()))
+    }
+
+    /// Records the behaviour of a program, which was a mutant of the seed at `parent` if there is
+    /// one, and whether it covered any new edges in the compiler.
+    ///
+    /// Returns true if the program found something new, in which case it should be added to the
+    /// pool with [`SeedPool::add`].
+    pub fn record(&mut self, parent: Option<usize>, behaviour: u64, new_coverage: bool) -> bool {
+        let count = self.behaviours.entry(behaviour).or_default();
+        *count += 1;
+
+        let new = *count == 1 || new_coverage;
+        if let (true, Some(parent)) = (new, parent) {
+            self.seeds[parent].finds += 1;
+        }
+
+        new
+    }
+
+    /// Creates a pool containing the seeds that were saved to `dir` by earlier runs, so that
+    /// resuming a campaign with the same output directory doesn't lose them.
+    pub fn load(schedule: PowerSchedule, dir: PathBuf) -> eyre::Result<SeedPool> {
+        let mut pool = SeedPool::new(schedule, dir);
+        if !pool.dir.exists() {
+            return Ok(pool);
+        }
+
+        let mut tapes = vec![];
+        for entry in std::fs::read_dir(&pool.dir)? {
+            let path = entry?.path();
+            if let Some((index, behaviour)) = parse_tape_name(&path) {
+                tapes.push((index, behaviour, path));
+            }
+        }
+
+        // Directory entries aren't returned in any particular order
+        tapes.sort();
+
+        for (_, behaviour, tape) in tapes {
+            *pool.behaviours.entry(behaviour).or_default() += 1;
+            pool.seeds.push(Seed {
+                tape,
+                behaviour,
+                picked: 0,
+                finds: 0,
+            });
+        }
+
+        Ok(pool)
+    }
+
+    /// Adds a program to the pool, moving its tape into the pool's directory.
+    pub fn add(&mut self, tape: &Path, behaviour: u64) -> eyre::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+
+        // The behaviour is saved in the name so that it can be restored by `load`, and the
+        // index is bumped past any tapes left by other runs sharing the directory
+        let mut index = self.seeds.len();
+        let path = loop {
+            let path = self.dir.join(format!("{index}-{behaviour:016x}.json"));
+            if !path.exists() {
+                break path;
+            }
+            index += 1;
+        };
+
+        std::fs::rename(tape, &path)?;
+
+        self.seeds.push(Seed {
+            tape: path,
+            behaviour,
+            picked: 0,
+            finds: 0,
+        });
+
+        Ok(())
+    }
+}
+
+/// Parses the index and behaviour from the name of a saved tape, which is
+/// `<index>-<behaviour>.json`.
+fn parse_tape_name(path: &Path) -> Option<(usize, u64)> {
+    if path.extension()? != "json" {
+        return None;
+    }
+
+    let (index, behaviour) = path.file_stem()?.to_str()?.split_once('-')?;
+    Some((
+        index.parse().ok()?,
+        u64::from_str_radix(behaviour, 16).ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn pool(schedule: PowerSchedule) -> SeedPool {
+        SeedPool::new(schedule, PathBuf::new())
+    }
+
+    /// Adds a seed without a tape, recording its behaviour.
+    fn add_seed(pool: &mut SeedPool, behaviour: u64) -> usize {
+        pool.record(None, behaviour, false);
+        pool.seeds.push(Seed {
+            tape: PathBuf::from(format!("{}.json", pool.seeds.len())),
+            behaviour,
+            picked: 0,
+            finds: 0,
+        });
+        pool.seeds.len() - 1
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wgslsmith-{name}-{}", std::process::id()));
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn explore_energy() {
+        let mut pool = pool(PowerSchedule::Explore);
+        let index = add_seed(&mut pool, 1);
+        add_seed(&mut pool, 1);
+
+        assert_eq!(pool.energy(&pool.seeds[index]), 1.0);
+
+        pool.seeds[index].picked = 10;
+        pool.seeds[index].finds = 2;
+        assert_eq!(pool.energy(&pool.seeds[index]), 3.0);
+
+        pool.seeds[index].finds = 1000;
+        assert_eq!(pool.energy(&pool.seeds[index]), MAX_ENERGY);
+    }
+
+    #[test]
+    fn scheduled_energy() {
+        let energy = |schedule, picked, frequency| {
+            let mut pool = pool(schedule);
+            for _ in 0..frequency {
+                add_seed(&mut pool, 1);
+            }
+            pool.seeds[0].picked = picked;
+            pool.energy(&pool.seeds[0])
+        };
+
+        // Energy is divided by the number of seeds with the same behaviour
+        assert_eq!(energy(PowerSchedule::Fast, 2, 2), 4.0);
+        assert_eq!(energy(PowerSchedule::Fast, 100, 1), MAX_ENERGY);
+        assert_eq!(energy(PowerSchedule::Lin, 3, 2), 2.0);
+        assert_eq!(energy(PowerSchedule::Quad, 3, 2), 8.0);
+    }
+
+    #[test]
+    fn record_behaviours() {
+        let mut pool = pool(PowerSchedule::Fast);
+        let parent = add_seed(&mut pool, 1);
+
+        assert!(!pool.record(Some(parent), 1, false));
+        assert_eq!(pool.seeds[parent].finds, 0);
+
+        assert!(pool.record(Some(parent), 2, false));
+        assert_eq!(pool.seeds[parent].finds, 1);
+
+        // New coverage counts as a find even if the behaviour has been seen before
+        assert!(pool.record(Some(parent), 2, true));
+        assert_eq!(pool.seeds[parent].finds, 2);
+
+        assert!(pool.record(None, 3, false));
+        assert_eq!(pool.behaviours[&1], 2);
+        assert_eq!(pool.behaviours[&2], 2);
+    }
+
+    #[test]
+    fn pick_in_proportion_to_energy() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut pool = pool(PowerSchedule::Explore);
+
+        assert_eq!(pool.pick(&mut rng), None);
+
+        let strong = add_seed(&mut pool, 1);
+        let weak = add_seed(&mut pool, 2);
+        pool.seeds[strong].finds = 63;
+
+        let mut picks = [0; 2];
+        for _ in 0..1000 {
+            let (index, tape) = pool.pick(&mut rng).unwrap();
+            assert_eq!(tape, pool.seeds[index].tape);
+            picks[index] += 1;
+        }
+
+        assert!(picks[strong] > 900, "{picks:?}");
+        assert!(picks[weak] > 0, "{picks:?}");
+        assert_eq!(pool.seeds[strong].picked, picks[strong]);
+        assert_eq!(pool.seeds[weak].picked, picks[weak]);
+    }
+
+    #[test]
+    fn tapes_are_reloaded() {
+        let dir = temp_dir("seed-pool");
+        let tape = std::env::temp_dir().join(format!("wgslsmith-tape-{}.json", std::process::id()));
+
+        let mut pool = SeedPool::load(PowerSchedule::Fast, dir.clone()).unwrap();
+        assert!(pool.seeds.is_empty());
+
+        for (behaviour, contents) in [(0xa, "first"), (0xb, "second")] {
+            std::fs::write(&tape, contents).unwrap();
+            pool.add(&tape, behaviour).unwrap();
+        }
+
+        // Other files in the directory are ignored
+        std::fs::write(dir.join("next.json"), "next").unwrap();
+
+        let mut pool = SeedPool::load(PowerSchedule::Fast, dir.clone()).unwrap();
+        assert_eq!(pool.seeds.len(), 2);
+        assert_eq!(pool.seeds[1].behaviour, 0xb);
+        assert_eq!(pool.behaviours[&0xa], 1);
+
+        // New seeds don't overwrite the ones from the earlier run
+        std::fs::write(&tape, "third").unwrap();
+        pool.add(&tape, 0xa).unwrap();
+
+        let contents = pool
+            .seeds
+            .iter()
+            .map(|seed| std::fs::read_to_string(&seed.tape).unwrap())
+            .collect::<Vec<_>>();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(contents, ["first", "second", "third"]);
+    }
+
+    #[test]
+    fn tape_names() {
+        assert_eq!(
+            parse_tape_name(Path::new("tapes/3-00000000000000ff.json")),
+            Some((3, 0xff))
+        );
+        assert_eq!(parse_tape_name(Path::new("tapes/next.json")), None);
+        assert_eq!(parse_tape_name(Path::new("tapes/3.json")), None);
+        assert_eq!(parse_tape_name(Path::new("tapes/3-ff.txt")), None);
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::Parser;
+use eyre::Context;
+use serde::{Deserialize, Serialize};
+use time::{format_description, OffsetDateTime};
+
+/// Name of the file in the fuzzer's output directory that the campaign status is saved to.
+const STATUS_FILE: &str = "status.json";
+
+/// Counts of findings attributed to a single configuration.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ConfigStatus {
+    pub crashes: usize,
+    pub mismatches: usize,
+    pub timeouts: usize,
+}
+
+/// Statistics for a fuzzing campaign, which the fuzzer periodically saves to its output directory.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Status {
+    /// Unix timestamp at which the campaign was started.
+    pub started: i64,
+    /// Unix timestamp at which the status was last saved.
+    pub updated: i64,
+    pub total: usize,
+    pub success: usize,
+    pub timeouts: usize,
+    pub crashes: usize,
+    pub saved_crashes: usize,
+    pub mismatches: usize,
+    pub saved_mismatches: usize,
+    pub failures: usize,
+    pub duplicates: usize,
+    /// Programs that were skipped for being outside of the complexity bounds.
+    #[serde(default)]
+    pub filtered: usize,
+    /// Crashes and mismatches that weren't saved because they matched a known failure.
+    #[serde(default)]
+    pub known: usize,
+    /// Number of edges covered in the instrumented compiler, if coverage is being collected.
+    #[serde(default)]
+    pub coverage: Option<usize>,
+    pub configs: BTreeMap<String, ConfigStatus>,
+}
+
+impl Status {
+    pub fn new() -> Status {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        Status {
+            started: now,
+            updated: now,
+            ..Default::default()
+        }
+    }
+
+    pub fn config(&mut self, config: &str) -> &mut ConfigStatus {
+        self.configs.entry(config.to_owned()).or_default()
+    }
+
+    pub fn load(dir: &Path) -> eyre::Result<Status> {
+        let path = dir.join(STATUS_FILE);
+        let json = std::fs::read_to_string(&path)
+            .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn save(&mut self, dir: &Path) -> eyre::Result<()> {
+        self.updated = OffsetDateTime::now_utc().unix_timestamp();
+
+        // Written to a temporary file first so that readers never see a partially written file
+        let tmp = dir.join(format!("{STATUS_FILE}.tmp"));
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(tmp, dir.join(STATUS_FILE))?;
+
+        Ok(())
+    }
+
+    /// Executions per second over the lifetime of the campaign.
+    pub fn throughput(&self) -> f64 {
+        let elapsed = self.updated - self.started;
+        if elapsed > 0 {
+            self.total as f64 / elapsed as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Parser)]
+pub struct Options {
+    /// Path to the fuzzer's output directory.
+    #[clap(short, long, action, default_value = "out")]
+    output: PathBuf,
+
+    /// Number of recent findings to show.
+    #[clap(short, long, action, default_value = "10")]
+    recent: usize,
+
+    /// Print the status every N seconds.
+    #[clap(short, long, action)]
+    watch: Option<u64>,
+}
+
+pub fn run(options: Options) -> eyre::Result<()> {
+    loop {
+        print_status(&options)?;
+
+        match options.watch {
+            Some(interval) => std::thread::sleep(Duration::from_secs(interval)),
+            None => return Ok(()),
+        }
+
+        println!();
+    }
+}
+
+fn print_status(options: &Options) -> eyre::Result<()> {
+    let status = Status::load(&options.output)?;
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+
+    let uptime = Duration::from_secs((status.updated - status.started).max(0) as u64);
+    let last_update = Duration::from_secs((now - status.updated).max(0) as u64);
+
+    println!("started:      {}", format_timestamp(status.started)?);
+    println!("running for:  {}", format_duration(uptime));
+    println!("last update:  {} ago", format_duration(last_update));
+    println!(
+        "executions:   {} ({:.2}/s)",
+        status.total,
+        status.throughput()
+    );
+    println!("  ok:         {}", status.success);
+    println!(
+        "  crashes:    {} ({} saved)",
+        status.crashes, status.saved_crashes
+    );
+    println!(
+        "  mismatches: {} ({} saved)",
+        status.mismatches, status.saved_mismatches
+    );
+    println!("  known:      {}", status.known);
+    println!("  timeouts:   {}", status.timeouts);
+    println!("  failures:   {}", status.failures);
+    println!("  duplicates: {}", status.duplicates);
+    println!("  filtered:   {}", status.filtered);
+
+    if let Some(edges) = status.coverage {
+        println!("  coverage:   {edges} edges");
+    }
+
+    if !status.configs.is_empty() {
+        let width = status.configs.keys().map(|it| it.len()).max().unwrap_or(0);
+
+        println!();
+        println!("{:<width$}   crashes  mismatches  timeouts", "config");
+
+        for (config, counts) in &status.configs {
+            println!(
+                "{config:<width$}   {:>7}  {:>10}  {:>8}",
+                counts.crashes, counts.mismatches, counts.timeouts
+            );
+        }
+    }
+
+    let findings = recent_findings(&options.output, options.recent)?;
+    if !findings.is_empty() {
+        println!();
+        println!("recent findings:");
+        for (name, kind) in findings {
+            println!("  {name}  {kind}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the names of the most recently saved test cases, along with a description of each.
+fn recent_findings(dir: &Path, count: usize) -> eyre::Result<Vec<(String, String)>> {
+    let mut findings = vec![];
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.join("shader.wgsl").exists() {
+            if let Some(name) = path.file_name().and_then(|it| it.to_str()) {
+                findings.push((name.to_owned(), path));
+            }
+        }
+    }
+
+    // Test cases are named by timestamp, so this sorts them by time with the most recent first
+    findings.sort_by(|a, b| b.0.cmp(&a.0));
+    findings.truncate(count);
+
+    let findings = findings
+        .into_iter()
+        .map(|(name, path)| {
+            let kind = if let Ok(pattern) = std::fs::read_to_string(path.join("disagreement.txt")) {
+                format!("mismatch ({})", pattern.trim())
+            } else if let Ok(output) = std::fs::read_to_string(path.join("stderr.txt")) {
+                // The first error line is usually enough to recognise the crash
+                let error = output
+                    .lines()
+                    .find(|it| it.contains("error") || it.contains("panicked"))
+                    .unwrap_or("")
+                    .trim();
+                format!("crash ({error})")
+            } else {
+                "mismatch".to_owned()
+            };
+
+            (name, kind)
+        })
+        .collect();
+
+    Ok(findings)
+}
+
+fn format_timestamp(timestamp: i64) -> eyre::Result<String> {
+    let time = OffsetDateTime::from_unix_timestamp(timestamp)?;
+    Ok(time.format(&format_description::parse(
+        "[year]-[month]-[day] [hour]:[minute]:[second] UTC",
+    )?)?)
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes}m {secs}s")
+    } else if minutes > 0 {
+        format!("{minutes}m {secs}s")
+    } else {
+        format!("{secs}s")
+    }
+}
@@ -131,7 +131,7 @@ fn reduce_crash(
     let should_recondition = !options.no_recondition;
 
     let source = if should_recondition {
-        recondition(parser::parse(&source))
+        recondition(parser::try_parse(&source)?)
     } else {
         source
     };
@@ -150,16 +150,22 @@ fn reduce_crash(
     } else {
         let compiler = options.compiler.unwrap();
         let backend = options.backend.unwrap();
-        let compiled = compiler.compile(&source, backend)?;
 
         match backend {
             Backend::Hlsl => {
-                remote_validate(config, &compiled, validator::Backend::Hlsl, &regex, quiet)?
+                let compiled = compiler.compile(&source, backend)?;
+                let backend = config.validator.hlsl_backend();
+                remote_validate(config, &compiled, backend, &regex, quiet)?
             }
             Backend::Msl => {
+                let compiled = compiler.compile(&source, backend)?;
                 remote_validate(config, &compiled, validator::Backend::Msl, &regex, quiet)?
             }
-            Backend::Spirv => todo!(),
+            Backend::Spirv => {
+                let compiled = compiler.compile_spirv(&source)?;
+                let result = validator::validate_spirv(&compiled);
+                is_interesting(result, &regex, quiet)
+            }
         }
     };
 
@@ -176,7 +182,7 @@ fn reduce_mismatch(
     harness: &Harness,
     quiet: bool,
 ) -> eyre::Result<()> {
-    let module = parser::parse(&source);
+    let module = parser::try_parse(&source)?;
     let reconditioned = recondition(module);
 
     Compiler::Naga.validate(&reconditioned)?;
@@ -195,7 +201,7 @@ fn reduce_mismatch(
     Ok(())
 }
 
-fn recondition(module: Module) -> String {
+pub fn recondition(module: Module) -> String {
     let reconditioned = reconditioner::recondition(module);
     let mut formatted = String::new();
 
@@ -218,19 +224,22 @@ fn remote_validate(
         println!("{source}");
     }
 
-    let server = config.validator.server()?;
-    let result = validator::validate(server, backend, source.to_owned())?;
+    let server = config.validator.server_for(backend)?;
+    let token = config.validator.token.as_deref();
+    let result = validator::validate(server, token, backend, source.to_owned())?;
+
+    Ok(is_interesting(result, regex, quiet))
+}
 
-    let is_interesting = match result {
-        validator::ValidateResponse::Success => false,
-        validator::ValidateResponse::Failure(err) => {
+fn is_interesting(result: validator::ValidateResponse, regex: &Regex, quiet: bool) -> bool {
+    match result {
+        validator::ValidateResponse::Success | validator::ValidateResponse::Timeout => false,
+        validator::ValidateResponse::Failure(err) | validator::ValidateResponse::Crashed(err) => {
             if !quiet {
                 println!("-----");
                 println!("{err}");
             }
             regex.is_match(&err)
         }
-    };
-
-    Ok(is_interesting)
+    }
 }
@@ -0,0 +1,178 @@
+use std::fmt::Display;
+
+use clap::Parser;
+use eyre::eyre;
+
+use crate::compiler::{self, Backend, Compiler};
+use crate::config::Config;
+use crate::fmt::read_shader_from_path;
+use crate::validator::{self, ValidateResponse};
+
+#[derive(Parser)]
+pub struct Options {
+    /// Path to a wgsl shader program (use '-' for stdin).
+    #[clap(action, default_value = "-")]
+    input: String,
+
+    /// Backends to compare.
+    ///
+    /// If not set, all supported backends will be compared.
+    #[clap(
+        short,
+        long,
+        value_enum,
+        action,
+        use_value_delimiter(true),
+        require_value_delimiter(true)
+    )]
+    backends: Vec<Backend>,
+
+    /// Print the translated shaders.
+    #[clap(long, action)]
+    show_output: bool,
+}
+
+enum Outcome {
+    TranslationFailed(String),
+    Rejected(String),
+    Accepted,
+}
+
+impl Outcome {
+    fn is_accepted(&self) -> bool {
+        matches!(self, Outcome::Accepted)
+    }
+}
+
+impl From<ValidateResponse> for Outcome {
+    fn from(response: ValidateResponse) -> Self {
+        match response {
+            ValidateResponse::Success => Outcome::Accepted,
+            ValidateResponse::Failure(e) => Outcome::Rejected(e),
+            ValidateResponse::Timeout => Outcome::Rejected("validator timed out".to_owned()),
+            ValidateResponse::Crashed(e) => Outcome::Rejected(format!("validator crashed: {e}")),
+        }
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::TranslationFailed(_) => write!(f, "translation failed"),
+            Outcome::Rejected(_) => write!(f, "rejected"),
+            Outcome::Accepted => write!(f, "accepted"),
+        }
+    }
+}
+
+pub fn run(config: &Config, options: Options) -> eyre::Result<()> {
+    let source = read_shader_from_path(&options.input)?;
+
+    let backends = if options.backends.is_empty() {
+        vec![Backend::Hlsl, Backend::Msl, Backend::Spirv]
+    } else {
+        options.backends
+    };
+
+    let mut divergences = vec![];
+
+    for backend in backends {
+        let outcomes = translate_and_validate(config, backend, &source, &options)?;
+        let [(_, tint), (_, naga)] = &outcomes;
+
+        println!("{backend}: tint {tint}, naga {naga}");
+
+        for (compiler, outcome) in &outcomes {
+            if let Outcome::TranslationFailed(e) | Outcome::Rejected(e) = outcome {
+                println!("[{compiler} ({backend})]");
+                println!("{}", e.trim_end());
+            }
+        }
+
+        if tint.is_accepted() != naga.is_accepted() {
+            divergences.push(backend);
+        }
+    }
+
+    if !divergences.is_empty() {
+        let backends = divergences
+            .iter()
+            .map(|backend| backend.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(eyre!("tint and naga disagree for backends: {backends}"));
+    }
+
+    Ok(())
+}
+
+/// Translates the shader using both tint and naga, and runs the outputs through the downstream
+/// validator for the backend.
+fn translate_and_validate(
+    config: &Config,
+    backend: Backend,
+    source: &str,
+    options: &Options,
+) -> eyre::Result<[(Compiler, Outcome); 2]> {
+    let mut outcomes = [
+        (Compiler::Tint, Outcome::Accepted),
+        (Compiler::Naga, Outcome::Accepted),
+    ];
+
+    match backend {
+        Backend::Hlsl | Backend::Msl => {
+            let mut pending = vec![];
+
+            for (i, (compiler, outcome)) in outcomes.iter_mut().enumerate() {
+                match compiler::catch_panic(|| compiler.compile(source, backend)) {
+                    Ok(code) => {
+                        if options.show_output {
+                            println!("[{compiler} ({backend}) output]");
+                            println!("{code}");
+                        }
+                        pending.push((i, code));
+                    }
+                    Err(e) => *outcome = Outcome::TranslationFailed(format!("{e:#}")),
+                }
+            }
+
+            if pending.is_empty() {
+                return Ok(outcomes);
+            }
+
+            let validator_backend = match backend {
+                Backend::Hlsl => config.validator.hlsl_backend(),
+                _ => validator::Backend::Msl,
+            };
+
+            let (indices, sources): (Vec<_>, Vec<_>) = pending.into_iter().unzip();
+            let server = config.validator.server_for(validator_backend)?;
+            let token = config.validator.token.as_deref();
+            let response = validator::Client::connect(server, token)?
+                .validate_batch(validator_backend, sources)?;
+
+            for (i, item) in indices.into_iter().zip(response.results) {
+                outcomes[i].1 = item.result.into();
+            }
+        }
+        Backend::Spirv => {
+            for (compiler, outcome) in &mut outcomes {
+                *outcome = match compiler::catch_panic(|| compiler.compile_spirv(source)) {
+                    Ok(words) => {
+                        if options.show_output {
+                            println!("[{compiler} ({backend}) output]");
+                            match compiler::disassemble_spirv(&words) {
+                                Ok(text) => println!("{text}"),
+                                Err(e) => println!("failed to disassemble spirv: {e:#}"),
+                            }
+                        }
+                        validator::validate_spirv(&words).into()
+                    }
+                    Err(e) => Outcome::TranslationFailed(format!("{e:#}")),
+                };
+            }
+        }
+    }
+
+    Ok(outcomes)
+}
@@ -0,0 +1,125 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use color_eyre::Help;
+use eyre::eyre;
+
+use crate::compiler::{self, Backend, Compiler};
+use crate::fmt::read_shader_from_path;
+use crate::validator::{self, ValidateResponse};
+
+#[derive(Parser)]
+pub struct Options {
+    /// Path to a wgsl shader program (use '-' for stdin).
+    #[clap(action, default_value = "-")]
+    input: String,
+
+    /// Validate and translate the shader in-process using tint.
+    #[clap(long, action)]
+    tint: bool,
+
+    /// Validate and translate the shader in-process using naga.
+    #[clap(long, action)]
+    naga: bool,
+
+    /// Backends to translate the shader to.
+    ///
+    /// If not set, the shader will be translated to all supported backends.
+    #[clap(
+        short,
+        long,
+        value_enum,
+        action,
+        use_value_delimiter(true),
+        require_value_delimiter(true)
+    )]
+    backends: Vec<Backend>,
+
+    /// Directory in which to write the translated shaders.
+    #[clap(short, long, action)]
+    out_dir: Option<PathBuf>,
+}
+
+pub fn run(options: Options) -> eyre::Result<()> {
+    let mut compilers = vec![];
+
+    if options.tint {
+        compilers.push(Compiler::Tint);
+    }
+
+    if options.naga {
+        compilers.push(Compiler::Naga);
+    }
+
+    if compilers.is_empty() {
+        return Err(eyre!("no compilers selected"))
+            .with_suggestion(|| "pass `--tint` and/or `--naga`");
+    }
+
+    let source = read_shader_from_path(&options.input)?;
+
+    let backends = if options.backends.is_empty() {
+        vec![Backend::Hlsl, Backend::Msl, Backend::Spirv]
+    } else {
+        options.backends
+    };
+
+    if let Some(out_dir) = &options.out_dir {
+        std::fs::create_dir_all(out_dir)?;
+    }
+
+    let mut failed = false;
+
+    for compiler in compilers {
+        if let Err(e) = compiler::catch_panic(|| compiler.validate(&source)) {
+            println!("{compiler}: {e:#}");
+            failed = true;
+            continue;
+        }
+
+        println!("{compiler}: ok");
+
+        for &backend in &backends {
+            let result = compiler::catch_panic(|| match backend {
+                Backend::Spirv => {
+                    let words = compiler.compile_spirv(&source)?;
+
+                    // Unlike the text formats, we can check SPIR-V output in-process
+                    if let ValidateResponse::Failure(e) = validator::validate_spirv(&words) {
+                        return Err(eyre!("invalid spirv:\n{}", e.trim_end()));
+                    }
+
+                    Ok(words.iter().flat_map(|word| word.to_le_bytes()).collect())
+                }
+                _ => compiler.compile(&source, backend).map(String::into_bytes),
+            });
+
+            let out = match result {
+                Ok(out) => out,
+                Err(e) => {
+                    println!("{compiler} ({backend}): {e:#}");
+                    failed = true;
+                    continue;
+                }
+            };
+
+            println!("{compiler} ({backend}): ok");
+
+            if let Some(out_dir) = &options.out_dir {
+                let extension = match backend {
+                    Backend::Hlsl => "hlsl",
+                    Backend::Msl => "metal",
+                    Backend::Spirv => "spv",
+                };
+
+                std::fs::write(out_dir.join(format!("{compiler}.{extension}")), out)?;
+            }
+        }
+    }
+
+    if failed {
+        return Err(eyre!("shader failed validation"));
+    }
+
+    Ok(())
+}
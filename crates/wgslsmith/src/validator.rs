@@ -1,15 +1,110 @@
 use std::net::TcpStream;
 
 use bincode::Decode;
+use color_eyre::Help;
+use eyre::{eyre, Context};
 
 pub use validation_server_types::*;
 
-pub fn validate(server: &str, backend: Backend, source: String) -> eyre::Result<ValidateResponse> {
-    let mut stream = TcpStream::connect(server)?;
-    req(&mut stream, Request::Validate { backend, source })
+pub fn validate(
+    server: &str,
+    token: Option<&str>,
+    backend: Backend,
+    source: String,
+) -> eyre::Result<ValidateResponse> {
+    Client::connect(server, token)?.validate(backend, source)
+}
+
+/// A persistent connection to the validation server, which can be used for multiple requests.
+pub struct Client {
+    stream: TcpStream,
+    capabilities: Capabilities,
+}
+
+impl Client {
+    /// Connects to the server, checking that it is using the same protocol version.
+    ///
+    /// If the server requires authentication, `token` must match the one it was started with.
+    pub fn connect(server: &str, token: Option<&str>) -> eyre::Result<Client> {
+        let mut stream = TcpStream::connect(server)
+            .wrap_err_with(|| format!("failed to connect to {server}"))?;
+
+        let hello: HelloResponse = req(
+            &mut stream,
+            Request::Hello {
+                version: PROTOCOL_VERSION,
+                token: token.map(str::to_owned),
+            },
+        )
+        .wrap_err("handshake with validation server failed")
+        .with_suggestion(|| "make sure the server was built from the same version of wgslsmith")
+        .with_suggestion(|| "if the server requires a token, set `validator.token`")?;
+
+        if hello.version != PROTOCOL_VERSION {
+            return Err(eyre!(
+                "validation server is using protocol version {}, but {PROTOCOL_VERSION} is required",
+                hello.version
+            ))
+            .with_suggestion(|| "rebuild the validation server from the same version of wgslsmith");
+        }
+
+        Ok(Client {
+            stream,
+            capabilities: hello.capabilities,
+        })
+    }
+
+    pub fn validate(&mut self, backend: Backend, source: String) -> eyre::Result<ValidateResponse> {
+        self.check_backend(backend)?;
+        req(&mut self.stream, Request::Validate { backend, source })
+    }
+
+    /// Validates multiple shaders, splitting them into batches according to the server's maximum
+    /// batch size.
+    pub fn validate_batch(
+        &mut self,
+        backend: Backend,
+        sources: Vec<String>,
+    ) -> eyre::Result<ValidateBatchResponse> {
+        self.check_backend(backend)?;
+
+        let batch_size = (self.capabilities.max_batch_size as usize).max(1);
+        let mut results = Vec::with_capacity(sources.len());
+
+        for batch in sources.chunks(batch_size) {
+            let sources = batch.to_vec();
+            let res: ValidateBatchResponse = req(
+                &mut self.stream,
+                Request::ValidateBatch { backend, sources },
+            )?;
+            results.extend(res.results);
+        }
+
+        Ok(ValidateBatchResponse { results })
+    }
+
+    pub fn stats(&mut self) -> eyre::Result<StatsResponse> {
+        req(&mut self.stream, Request::GetStats)
+    }
+
+    fn check_backend(&self, backend: Backend) -> eyre::Result<()> {
+        if !self.capabilities.supports(backend) {
+            return Err(eyre!("validation server does not support {backend:?}"));
+        }
+
+        Ok(())
+    }
 }
 
 fn req<T: Decode>(stream: &mut TcpStream, req: Request) -> eyre::Result<T> {
-    bincode::encode_into_std_write(req, stream, bincode::config::standard())?;
-    bincode::decode_from_std_read(stream, bincode::config::standard()).map_err(Into::into)
+    write_frame(stream, req)?;
+    read_frame(stream)?.ok_or_else(|| eyre!("connection closed by server"))
+}
+
+/// Validates a SPIR-V binary in-process using SPIRV-Tools.
+pub fn validate_spirv(words: &[u32]) -> ValidateResponse {
+    match tint::validate_spirv(words) {
+        Ok(()) => ValidateResponse::Success,
+        Err(e) => ValidateResponse::Failure(e.diagnostics),
+    }
 }
@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use clap::Parser;
+
+use crate::config::Config;
+use crate::validator::{self, StatsResponse};
+
+#[derive(Parser)]
+pub struct Options {
+    /// Address of the validation server.
+    ///
+    /// If not set, `validator.server` from the config file will be used.
+    #[clap(short, long, action)]
+    server: Option<String>,
+
+    /// Poll the server and print its statistics every N seconds.
+    #[clap(short, long, action)]
+    watch: Option<u64>,
+}
+
+pub fn run(config: &Config, options: Options) -> eyre::Result<()> {
+    let server = match options.server.as_deref() {
+        Some(server) => server,
+        None => config.validator.server()?,
+    };
+
+    let mut client = validator::Client::connect(server, config.validator.token.as_deref())?;
+
+    loop {
+        print_stats(&client.stats()?);
+
+        match options.watch {
+            Some(interval) => std::thread::sleep(Duration::from_secs(interval)),
+            None => return Ok(()),
+        }
+
+        println!();
+    }
+}
+
+fn print_stats(stats: &StatsResponse) {
+    let total = stats.successes + stats.failures + stats.timeouts + stats.crashes;
+    let uptime = stats.uptime.as_secs_f64();
+    let throughput = if uptime > 0.0 {
+        total as f64 / uptime
+    } else {
+        0.0
+    };
+
+    println!("uptime:       {}s", stats.uptime.as_secs());
+    println!("requests:     {}", stats.requests);
+    println!("validated:    {total} ({throughput:.2}/s)");
+    println!("  successes:  {}", stats.successes);
+    println!("  failures:   {}", stats.failures);
+    println!("  timeouts:   {}", stats.timeouts);
+    println!("  crashes:    {}", stats.crashes);
+    println!("average time: {:?}", stats.average_time);
+    println!("p95 time:     {:?}", stats.p95_time);
+}
@@ -0,0 +1,261 @@
+//! Lists of known failures, for keeping fuzzing campaigns focused on new bugs.
+//!
+//! The list is a TOML file with an `[[xfail]]` table for each known bug:
+//!
+//! ```toml
+//! [[xfail]]
+//! reason = "firstLeadingBit is miscompiled by naga's HLSL backend"
+//! builtin = "firstLeadingBit"
+//! targets = ["naga-hlsl"]
+//! ```
+//!
+//! Targets are configuration ids, prefixes of them such as `wgpu:dx12`, or the name of a shader
+//! compiler and its output language such as `naga-hlsl` or `tint-spv`. The fuzzer doesn't
+//! generate calls to the builtin for programs that are run on an affected target, and doesn't
+//! save findings which only fail on affected targets and match the rest of the entry.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use ast::BuiltinFn;
+use eyre::{eyre, Context};
+use harness_types::{BackendType, ConfigId};
+use regex::Regex;
+use serde::Deserialize;
+
+/// Names of the shader compilers used by each implementation and backend, as targets.
+const COMPILER_TARGETS: &[(&str, &str)] = &[
+    ("naga-spv", "wgpu:vk"),
+    ("naga-hlsl", "wgpu:dx12"),
+    ("naga-msl", "wgpu:mtl"),
+    ("tint-spv", "dawn:vk"),
+    ("tint-hlsl", "dawn:dx12"),
+    ("tint-msl", "dawn:mtl"),
+];
+
+#[derive(Deserialize)]
+struct XfailFile {
+    #[serde(default)]
+    xfail: Vec<Xfail>,
+}
+
+#[derive(Deserialize)]
+pub struct Xfail {
+    /// Description of the bug, which is logged when a finding is suppressed.
+    pub reason: String,
+    /// Builtin function that triggers the bug.
+    #[serde(default)]
+    builtin: Option<String>,
+    /// Regex which must match the harness output, so that the entry only applies to crashes.
+    #[serde(default, with = "serde_regex")]
+    pattern: Option<Regex>,
+    /// Configuration id prefixes that the bug affects, after resolving compiler names.
+    targets: Vec<String>,
+}
+
+impl Xfail {
+    fn affects(&self, config: &str) -> bool {
+        self.targets
+            .iter()
+            .any(|target| config == target || config.starts_with(&format!("{target}:")))
+    }
+}
+
+pub struct XfailList {
+    entries: Vec<Xfail>,
+}
+
+impl XfailList {
+    pub fn load(path: &Path) -> eyre::Result<XfailList> {
+        let toml = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+
+        XfailList::parse(&toml, path)
+    }
+
+    /// Parses the contents of an xfail list, where `path` is only used in error messages.
+    fn parse(toml: &str, path: &Path) -> eyre::Result<XfailList> {
+        let file: XfailFile =
+            toml::from_str(toml).wrap_err_with(|| format!("failed to parse {}", path.display()))?;
+
+        let mut entries = file.xfail;
+        for entry in &mut entries {
+            if let Some(builtin) = &entry.builtin {
+                BuiltinFn::from_str(builtin)
+                    .map_err(|_| eyre!("unknown builtin `{builtin}` in {}", path.display()))?;
+            }
+
+            if entry.targets.is_empty() {
+                return Err(eyre!("`{}` has no targets", entry.reason));
+            }
+
+            for target in &mut entry.targets {
+                *target = resolve_target(target)
+                    .ok_or_else(|| eyre!("invalid target `{target}` in {}", path.display()))?;
+            }
+        }
+
+        Ok(XfailList { entries })
+    }
+
+    /// Returns the generator arguments that stop the builtins of entries from being generated.
+    ///
+    /// If the configuration that programs will be run on isn't known, then the builtins of every
+    /// entry are disabled, since the harness may pick any of the targets by default.
+    pub fn gen_args(&self, config: Option<&ConfigId>) -> Vec<String> {
+        let config = config.map(|it| it.to_string());
+        let mut args = vec![];
+
+        for entry in &self.entries {
+            if let Some(builtin) = &entry.builtin {
+                if config.as_deref().map_or(true, |it| entry.affects(it)) {
+                    args.extend(["--disable-fn".to_owned(), builtin.clone()]);
+                }
+            }
+        }
+
+        args
+    }
+
+    /// Returns the entry that explains a finding, if there is one.
+    ///
+    /// `failed` is the set of configurations that crashed or disagreed with the rest, all of
+    /// which must be affected by the entry. `output` is the harness output for crashes.
+    pub fn find(&self, shader: &str, failed: &[&str], output: Option<&str>) -> Option<&Xfail> {
+        if failed.is_empty() {
+            return None;
+        }
+
+        self.entries.iter().find(|entry| {
+            failed.iter().all(|config| entry.affects(config))
+                && entry
+                    .builtin
+                    .as_ref()
+                    .map_or(true, |builtin| shader.contains(&format!("{builtin}(")))
+                && entry.pattern.as_ref().map_or(true, |pattern| {
+                    output.map_or(false, |output| pattern.is_match(output))
+                })
+        })
+    }
+}
+
+/// Converts a target into a prefix of configuration ids, checking that it's valid.
+fn resolve_target(target: &str) -> Option<String> {
+    if let Some((_, prefix)) = COMPILER_TARGETS.iter().find(|(name, _)| *name == target) {
+        return Some((*prefix).to_owned());
+    }
+
+    let mut segments = target.split(':');
+
+    if !matches!(segments.next(), Some("dawn" | "wgpu")) {
+        return None;
+    }
+
+    if let Some(backend) = segments.next() {
+        BackendType::from_str(backend).ok()?;
+    }
+
+    if let Some(device) = segments.next() {
+        device.parse::<usize>().ok()?;
+    }
+
+    if segments.next().is_some() {
+        return None;
+    }
+
+    Some(target.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XFAIL: &str = r#"
+[[xfail]]
+reason = "firstLeadingBit is miscompiled"
+builtin = "firstLeadingBit"
+targets = ["naga-hlsl"]
+
+[[xfail]]
+reason = "dawn crashes on vulkan"
+pattern = "device lost"
+targets = ["dawn:vk", "wgpu:vk:1"]
+"#;
+
+    fn parse(toml: &str) -> eyre::Result<XfailList> {
+        XfailList::parse(toml, Path::new("xfail.toml"))
+    }
+
+    #[test]
+    fn resolve_targets() {
+        assert_eq!(resolve_target("naga-hlsl").as_deref(), Some("wgpu:dx12"));
+        assert_eq!(resolve_target("tint-spv").as_deref(), Some("dawn:vk"));
+        assert_eq!(resolve_target("dawn").as_deref(), Some("dawn"));
+        assert_eq!(resolve_target("wgpu:mtl").as_deref(), Some("wgpu:mtl"));
+        assert_eq!(resolve_target("wgpu:vk:0").as_deref(), Some("wgpu:vk:0"));
+
+        assert_eq!(resolve_target("naga"), None);
+        assert_eq!(resolve_target("wgpu:gl"), None);
+        assert_eq!(resolve_target("wgpu:vk:x"), None);
+        assert_eq!(resolve_target("wgpu:vk:0:0"), None);
+    }
+
+    #[test]
+    fn invalid_entries() {
+        assert!(parse("[[xfail]]\nreason = \"a\"\ntargets = [\"naga\"]").is_err());
+        assert!(parse("[[xfail]]\nreason = \"a\"\ntargets = []").is_err());
+        assert!(parse(
+            "[[xfail]]\nreason = \"a\"\nbuiltin = \"notABuiltin\"\ntargets = [\"dawn\"]"
+        )
+        .is_err());
+        assert!(parse("").unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn find_builtin_entry() {
+        let list = parse(XFAIL).unwrap();
+        let shader = "let x = firstLeadingBit(1u);";
+
+        let entry = list.find(shader, &["wgpu:dx12:0"], None).unwrap();
+        assert_eq!(entry.reason, "firstLeadingBit is miscompiled");
+
+        // Every failing configuration must be affected
+        assert!(list
+            .find(shader, &["wgpu:dx12:0", "dawn:dx12:0"], None)
+            .is_none());
+        // The shader must call the builtin
+        assert!(list.find("let x = 1u;", &["wgpu:dx12:0"], None).is_none());
+        assert!(list.find(shader, &[], None).is_none());
+    }
+
+    #[test]
+    fn find_pattern_entry() {
+        let list = parse(XFAIL).unwrap();
+        let failed = ["dawn:vk:0", "wgpu:vk:1"];
+
+        let entry = list.find("", &failed, Some("error: device lost")).unwrap();
+        assert_eq!(entry.reason, "dawn crashes on vulkan");
+
+        assert!(list
+            .find("", &failed, Some("error: out of memory"))
+            .is_none());
+        assert!(list.find("", &failed, None).is_none());
+        // Targets with a device id only match that device
+        assert!(list.find("", &["wgpu:vk:0"], Some("device lost")).is_none());
+    }
+
+    #[test]
+    fn gen_args_for_config() {
+        let list = parse(XFAIL).unwrap();
+        let disable = vec!["--disable-fn".to_owned(), "firstLeadingBit".to_owned()];
+
+        assert_eq!(list.gen_args(None), disable);
+        assert_eq!(
+            list.gen_args(Some(&"wgpu:dx12:0".parse().unwrap())),
+            disable
+        );
+        assert!(list
+            .gen_args(Some(&"dawn:dx12:0".parse().unwrap()))
+            .is_empty());
+    }
+}
@@ -0,0 +1,63 @@
+//! Generates a program using the fuzzer input as the source of random decisions, and runs it
+//! through the compilers in-process.
+//!
+//! Compilation errors are ignored since the generator doesn't guarantee that every program will be
+//! accepted - this is only looking for crashes.
+
+#![no_main]
+
+use generator::rng::ByteRng;
+use generator::Options;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut options = Options::default();
+    options.recondition = true;
+
+    let module = generator::generate_with_rng(&mut ByteRng::new(data), &options);
+    let module = match generator::recondition(module, &options) {
+        Ok(module) => module,
+        Err(_) => return,
+    };
+
+    let source = generator::to_wgsl(&module);
+
+    run_naga(&source);
+
+    #[cfg(feature = "tint")]
+    {
+        let _ = tint::compile_shader_to_hlsl(&source);
+        let _ = tint::compile_shader_to_msl(&source);
+        let _ = tint::compile_shader_to_spirv(&source);
+    }
+});
+
+fn run_naga(source: &str) {
+    use naga::back::{hlsl, msl, spv};
+    use naga::front::wgsl;
+    use naga::valid::{Capabilities, ValidationFlags, Validator};
+
+    let module = match wgsl::parse_str(&source.replace("@stage(compute)", "@compute")) {
+        Ok(module) => module,
+        Err(_) => return,
+    };
+
+    let info =
+        match Validator::new(ValidationFlags::default(), Capabilities::all()).validate(&module) {
+            Ok(info) => info,
+            Err(_) => return,
+        };
+
+    let mut out = String::new();
+    let _ = hlsl::Writer::new(&mut out, &hlsl::Options::default()).write(&module, &info);
+
+    let mut out = String::new();
+    let _ = msl::Writer::new(&mut out).write(
+        &module,
+        &info,
+        &msl::Options::default(),
+        &msl::PipelineOptions::default(),
+    );
+
+    let _ = spv::write_vec(&module, &info, &spv::Options::default(), None);
+}
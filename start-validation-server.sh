@@ -27,4 +27,5 @@ docker container ls -q --filter name=wgslsmith-validation-server | grep -q . &&
 docker container ls -qa --filter name=wgslsmith-validation-server | grep -q . && docker rm wgslsmith-validation-server
 
 # Start container
-docker run --name wgslsmith-validation-server -p 9123:9123 -d wgslsmith-validation-server -a 0.0.0.0:9123 -q
+docker run --name wgslsmith-validation-server -p 9123:9123 -d wgslsmith-validation-server -a 0.0.0.0:9123 -q \
+    ${VALIDATION_SERVER_TOKEN:+--token "$VALIDATION_SERVER_TOKEN"}